@@ -0,0 +1 @@
+pub use hashing_with_chaining::wasm::*;
@@ -0,0 +1,34 @@
+/*
+ * Counts a stream in two shards, merges them and ships the result as bytes.
+ *   cargo build --release --manifest-path ffi/Cargo.toml
+ *   cc ffi/example.c -Iffi -Lffi/target/release -l:libhashing_with_chaining.a -lpthread -ldl -lm -o example
+ */
+#include <stdio.h>
+#include "hashing_with_chaining.h"
+
+int main(void) {
+    HwcCountMin *left = hwc_count_min_new(4, 12);
+    HwcCountMin *right = hwc_count_min_clone(left);
+    HwcHyperLogLog *distinct = hwc_hyperloglog_new(12);
+    for (uint64_t i = 0; i < 100000; i++) {
+        hwc_count_min_insert(i % 2 ? left : right, i % 1000, 1);
+        hwc_hyperloglog_insert(distinct, i % 1000);
+    }
+    if (hwc_count_min_merge(left, right) != HWC_STATUS_OK) {
+        fprintf(stderr, "cannot merge the shards\n");
+        return 1;
+    }
+
+    uintptr_t len = 0;
+    uint8_t *bytes = hwc_count_min_to_bytes(left, &len);
+    HwcCountMin *received = hwc_count_min_from_bytes(bytes, len);
+    printf("count of key 7: %llu (exactly 100), distinct keys: %.0f (exactly 1000), %zu bytes\n",
+           (unsigned long long)hwc_count_min_estimate(received, 7), hwc_hyperloglog_estimate(distinct), (size_t)len);
+
+    hwc_bytes_free(bytes, len);
+    hwc_count_min_free(received);
+    hwc_count_min_free(right);
+    hwc_count_min_free(left);
+    hwc_hyperloglog_free(distinct);
+    return 0;
+}
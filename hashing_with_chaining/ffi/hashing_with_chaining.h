@@ -0,0 +1,170 @@
+/*
+ * Sketches and hash tables of hashing_with_chaining for C and C++.
+ *
+ * Every object is an opaque handle made by a *_new, *_clone or *_from_bytes function and
+ * freed once by the matching *_free function. A handle must not be used by two threads at
+ * a time. Functions accept null handles: they do nothing, return zero or null, or return
+ * HWC_STATUS_NULL_POINTER. Creation returns null for arguments out of range.
+ *
+ * Sketches only merge with clones of the same sketch, or sketches decoded from its bytes,
+ * since they must share their hash functions. The bytes of a *_to_bytes function are
+ * the format of the sketch files of the crate and are freed with hwc_bytes_free.
+ *
+ * Link with -lhashing_with_chaining, built by cargo build --release in ffi/.
+ */
+
+
+#ifndef HASHING_WITH_CHAINING_H
+#define HASHING_WITH_CHAINING_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Outcome of the functions that can fail
+typedef enum HwcStatus {
+  HWC_STATUS_OK = 0,
+  HWC_STATUS_NULL_POINTER = 1,
+  // The sketches have different dimensions
+  HWC_STATUS_DIMENSION_MISMATCH = 2,
+  // The sketches hash keys differently, so only clones of one sketch merge
+  HWC_STATUS_SEED_MISMATCH = 3,
+} HwcStatus;
+
+// Bloom filter
+typedef struct HwcBloomFilter HwcBloomFilter;
+
+// Count-Min sketch with u64 counters and standard updates
+typedef struct HwcCountMin HwcCountMin;
+
+// HyperLogLog distinct counter
+typedef struct HwcHyperLogLog HwcHyperLogLog;
+
+// NormSketch of a turnstile stream with i64 counters
+typedef struct HwcNormSketch HwcNormSketch;
+
+// Hashing with chaining table of exact i64 counts
+typedef struct HwcTable HwcTable;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Frees the bytes of a *_to_bytes function, given with the length it returned
+void hwc_bytes_free(uint8_t *bytes, uintptr_t len);
+
+// depth rows of 2^width_len counters, or null unless depth >= 1 and width_len is in 1..=32
+struct HwcCountMin *hwc_count_min_new(uintptr_t depth, uint32_t width_len);
+
+// A copy with the same hash functions, so the two can be merged later
+struct HwcCountMin *hwc_count_min_clone(const struct HwcCountMin *sketch);
+
+// Adds count to key
+void hwc_count_min_insert(struct HwcCountMin *sketch, uint64_t key, uint64_t count);
+
+// Takes count of key back out again
+void hwc_count_min_remove(struct HwcCountMin *sketch, uint64_t key, uint64_t count);
+
+// Never below the count of key
+uint64_t hwc_count_min_estimate(const struct HwcCountMin *sketch, uint64_t key);
+
+// Adds the counts of other into sketch
+enum HwcStatus hwc_count_min_merge(struct HwcCountMin *sketch, const struct HwcCountMin *other);
+
+// The sketch in the format of the sketch files, with its length stored in len
+uint8_t *hwc_count_min_to_bytes(const struct HwcCountMin *sketch, uintptr_t *len);
+
+// The sketch of hwc_count_min_to_bytes, or null for bytes of anything else, including
+// sketches with conservative updates, which cannot take counts back out
+struct HwcCountMin *hwc_count_min_from_bytes(const uint8_t *bytes, uintptr_t len);
+
+void hwc_count_min_free(struct HwcCountMin *sketch);
+
+// 2^precision registers, or null unless precision is in 4..=18
+struct HwcHyperLogLog *hwc_hyperloglog_new(uint32_t precision);
+
+// A copy with the same hash function, so the two can be merged later
+struct HwcHyperLogLog *hwc_hyperloglog_clone(const struct HwcHyperLogLog *sketch);
+
+void hwc_hyperloglog_insert(struct HwcHyperLogLog *sketch, uint64_t key);
+
+// Estimated number of distinct keys inserted
+double hwc_hyperloglog_estimate(const struct HwcHyperLogLog *sketch);
+
+// Counts the keys of other in sketch as well
+enum HwcStatus hwc_hyperloglog_merge(struct HwcHyperLogLog *sketch, const struct HwcHyperLogLog *other);
+
+uint8_t *hwc_hyperloglog_to_bytes(const struct HwcHyperLogLog *sketch, uintptr_t *len);
+
+struct HwcHyperLogLog *hwc_hyperloglog_from_bytes(const uint8_t *bytes, uintptr_t len);
+
+void hwc_hyperloglog_free(struct HwcHyperLogLog *sketch);
+
+// 2^width_len counters, or null unless width_len is in 1..=32
+struct HwcNormSketch *hwc_norm_sketch_new(uint32_t width_len);
+
+// A copy with the same hash functions, so the two can be merged later
+struct HwcNormSketch *hwc_norm_sketch_clone(const struct HwcNormSketch *sketch);
+
+// Adds delta, which may be negative, to the count of key
+void hwc_norm_sketch_update(struct HwcNormSketch *sketch, uint64_t key, int64_t delta);
+
+// Unbiased estimate of the count of key
+int64_t hwc_norm_sketch_estimate(const struct HwcNormSketch *sketch, uint64_t key);
+
+// Estimated sum of the squared counts
+double hwc_norm_sketch_norm(const struct HwcNormSketch *sketch);
+
+// Adds the counts of other into sketch
+enum HwcStatus hwc_norm_sketch_merge(struct HwcNormSketch *sketch, const struct HwcNormSketch *other);
+
+uint8_t *hwc_norm_sketch_to_bytes(const struct HwcNormSketch *sketch, uintptr_t *len);
+
+struct HwcNormSketch *hwc_norm_sketch_from_bytes(const uint8_t *bytes, uintptr_t len);
+
+void hwc_norm_sketch_free(struct HwcNormSketch *sketch);
+
+// Table with the given number of buckets, or null for none
+struct HwcTable *hwc_table_new(uintptr_t buckets);
+
+// Adds delta, which may be negative, to the count of key. Keys whose count returns to zero
+// are removed.
+void hwc_table_update(struct HwcTable *table, uint64_t key, int64_t delta);
+
+// The exact count of key, zero for keys not in the table
+int64_t hwc_table_get(const struct HwcTable *table, uint64_t key);
+
+// Exact sum of the squared counts
+uint64_t hwc_table_norm(const struct HwcTable *table);
+
+uint8_t *hwc_table_to_bytes(const struct HwcTable *table, uintptr_t *len);
+
+struct HwcTable *hwc_table_from_bytes(const uint8_t *bytes, uintptr_t len);
+
+void hwc_table_free(struct HwcTable *table);
+
+// Sized for n keys at a false positive rate of at most fpr, or null unless fpr is in (0, 1)
+// and the filter needs at most 2^61 bits
+struct HwcBloomFilter *hwc_bloom_filter_new(uintptr_t n, double fpr);
+
+// A copy with the same hash functions, so the two can be merged later
+struct HwcBloomFilter *hwc_bloom_filter_clone(const struct HwcBloomFilter *filter);
+
+void hwc_bloom_filter_insert(struct HwcBloomFilter *filter, uint64_t key);
+
+// Always true for an inserted key, and true for others at about the false positive rate
+bool hwc_bloom_filter_contains(const struct HwcBloomFilter *filter, uint64_t key);
+
+// Adds the keys of other to filter
+enum HwcStatus hwc_bloom_filter_merge(struct HwcBloomFilter *filter, const struct HwcBloomFilter *other);
+
+void hwc_bloom_filter_free(struct HwcBloomFilter *filter);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* HASHING_WITH_CHAINING_H */
@@ -0,0 +1,68 @@
+use crate::stats::mean;
+
+// Errors of sketch estimates against the exact answers, as the accuracy experiments report
+// them. Errors are pooled over every query and every seeded stream before they are
+// summarized, so the tail shows how bad an unlucky hash function or key gets.
+
+// Distance of the estimate from the exact value relative to scale, which is the exact value
+// itself for a norm or a count of distinct keys and the stream length for point queries,
+// whose guarantees are additive
+pub fn relative_error(estimate: f64, exact: f64, scale: f64) -> f64 {
+    return (estimate - exact).abs() / scale.max(1.0);
+}
+
+// Value at or below which a fraction q of the samples lie, by the nearest rank
+pub fn quantile(samples: &[f64], q: f64) -> f64 {
+    assert!(!samples.is_empty(), "Needs samples");
+    let mut sorted: Vec<f64> = samples.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let rank: usize = (q * sorted.len() as f64).ceil() as usize;
+    return sorted[rank.clamp(1, sorted.len()) - 1];
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ErrorSummary {
+    pub samples: usize,
+    pub mean: f64,
+    pub p95: f64,
+    pub max: f64,
+}
+
+impl ErrorSummary {
+    pub fn of(errors: &[f64]) -> ErrorSummary {
+        return ErrorSummary {
+            samples: errors.len(),
+            mean: mean(errors),
+            p95: quantile(errors, 0.95),
+            max: errors.iter().copied().fold(0.0, f64::max),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hwc::HwC;
+    use crate::norm_sketch::NormSketch;
+
+    #[test]
+    fn summaries_of_sketch_errors() {
+        let errors: Vec<f64> = (1..=100).map(|x| x as f64 / 100.0).collect();
+        let summary: ErrorSummary = ErrorSummary::of(&errors);
+        assert_eq!((summary.samples, summary.p95, summary.max), (100, 0.95, 1.0));
+        assert!((summary.mean - 0.505).abs() < 1e-12);
+        assert_eq!(quantile(&[3.0], 0.0), 3.0);
+        assert_eq!(relative_error(90.0, 100.0, 100.0), 0.1);
+        assert_eq!(relative_error(2.0, 0.0, 0.0), 2.0);
+
+        // A norm sketch of 2^14 counters is within a few percent of the exact norm
+        let mut exact: HwC = HwC::new(1 << 10);
+        let mut sketch: NormSketch = NormSketch::new(14);
+        for key in 0..10_000u64 {
+            exact.insert(key % 1000, 1);
+            sketch.insert(key % 1000, 1);
+        }
+        let error: f64 = relative_error(sketch.norm() as f64, exact.get_norm() as f64, exact.get_norm() as f64);
+        assert!(error < 0.1, "{}", error);
+    }
+}
@@ -0,0 +1,73 @@
+use rand::prelude::*;
+
+// Walker's alias method: n weights become n columns of height 1/n, each holding at most two
+// outcomes, its own with probability probability[i] and alias[i] otherwise. A sample is a
+// uniform column and one biased coin, O(1) time whatever the weights, against the
+// O(log n) binary search of the cumulative weights. Construction is Vose's O(n) version.
+#[derive(Clone, Debug)]
+pub struct AliasTable {
+    probability: Vec<f64>,
+    alias: Vec<usize>,
+}
+
+impl AliasTable {
+    pub fn new(weights: &[f64]) -> AliasTable {
+        assert!(weights.iter().all(|w| w.is_finite() && *w >= 0.0), "Weights must be nonnegative");
+        let total: f64 = weights.iter().sum();
+        assert!(total > 0.0, "Needs a positive weight");
+        let n: usize = weights.len();
+        // Weights scaled so that a full column is 1
+        let mut scaled: Vec<f64> = weights.iter().map(|w| w * n as f64 / total).collect();
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) = (0..n).partition(|i| scaled[*i] < 1.0);
+        let mut probability: Vec<f64> = vec![1.0; n];
+        let mut alias: Vec<usize> = (0..n).collect();
+        // Every step fills the column of a small outcome with a piece of a large one, which
+        // keeps whatever it has left over
+        while let (Some(s), Some(l)) = (small.pop(), large.last().copied()) {
+            probability[s] = scaled[s];
+            alias[s] = l;
+            scaled[l] -= 1.0 - scaled[s];
+            if scaled[l] < 1.0 {
+                large.pop();
+                small.push(l);
+            }
+        }
+        // Whatever is left is 1 up to rounding errors, so those columns keep probability 1
+        return AliasTable { probability, alias }
+    }
+    pub fn sample(&self, rng: &mut impl Rng) -> usize {
+        let column: usize = rng.gen_range(0..self.probability.len());
+        return if rng.gen::<f64>() < self.probability[column] { column } else { self.alias[column] };
+    }
+    pub fn len(&self) -> usize {
+        return self.probability.len();
+    }
+    pub fn is_empty(&self) -> bool {
+        return self.probability.is_empty();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_follow_the_weights() {
+        let weights: Vec<f64> = vec![1.0, 0.0, 5.0, 2.0, 0.5, 1.5, 0.0, 10.0];
+        let total: f64 = weights.iter().sum();
+        let table: AliasTable = AliasTable::new(&weights);
+        // The probability of outcome i is its own share of column i plus the rest of every
+        // column that has i as its alias
+        let mut exact: Vec<f64> = table.probability.iter().map(|p| p / 8.0).collect();
+        (0..8).for_each(|column| exact[table.alias[column]] += (1.0 - table.probability[column]) / 8.0);
+        assert!(exact.iter().zip(&weights).all(|(p, w)| (p - w / total).abs() < 1e-12), "{:?}", exact);
+
+        let mut rng = thread_rng();
+        let mut counts: Vec<usize> = vec![0; 8];
+        (0..1_000_000).for_each(|_| counts[table.sample(&mut rng)] += 1);
+        // A count has standard deviation at most 500, and zero weights never come up
+        assert!(counts.iter().zip(&weights).all(|(c, w)| (*c as f64 - 1_000_000.0 * w / total).abs() < 3000.0), "{:?}", counts);
+        assert_eq!(counts[1] + counts[6], 0);
+        assert_eq!(AliasTable::new(&[3.0]).sample(&mut rng), 0);
+    }
+}
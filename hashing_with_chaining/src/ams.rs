@@ -0,0 +1,336 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::mem::size_of;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::memory::{vec_allocations, MemoryFootprint};
+use crate::mergeable::{MergeError, MergeableSketch};
+use crate::norm_sketch::NormSketch;
+use crate::sketch_format::{ByteReader, ByteWriter, DecodeError, SketchKind};
+
+// The AMS estimator of F2 as median of means: s2 groups of s1 independent NormSketch
+// instances. Averaging a group brings the variance down so that a group mean is within
+// a factor 1 +- epsilon with probability 3/4, and the median over the groups boosts
+// that to probability 1 - delta.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AmsF2Estimator {
+    groups: Vec<Vec<NormSketch>>,
+}
+
+impl AmsF2Estimator {
+    pub fn new(s1: usize, s2: usize, width_len: u32) -> AmsF2Estimator {
+        assert!(s1 > 0 && s2 > 0, "Needs at least one group of one sketch");
+        return AmsF2Estimator {
+            groups: (0..s2).map(|_| (0..s1).map(|_| NormSketch::new(width_len)).collect()).collect(),
+        }
+    }
+    // A single AMS counter has variance at most 2 F2^2, so by Chebyshev s1 = 8 / epsilon^2
+    // gives a group mean within epsilon F2 with probability 3/4. By a Chernoff bound the
+    // median of s2 = 8 ln(1 / delta) groups fails with probability at most delta.
+    pub fn with_guarantees(epsilon: f64, delta: f64) -> AmsF2Estimator {
+        let s1: usize = (8.0 / (epsilon * epsilon)).ceil() as usize;
+        let s2: usize = (8.0 * (1.0 / delta).ln()).ceil().max(1.0) as usize;
+        return AmsF2Estimator::new(s1, s2, 0);
+    }
+    pub fn insert(&mut self, key: u64, delta: i64) {
+        for sketch in self.groups.iter_mut().flatten() {
+            sketch.insert(key, delta);
+        }
+    }
+    pub fn norm(&self) -> u64 {
+        let mut means: Vec<f64> = self.groups.iter()
+            .map(|group| group.iter().map(|sketch| sketch.norm_wide() as f64).sum::<f64>() / group.len() as f64)
+            .collect();
+        means.sort_unstable_by(|a, b| a.total_cmp(b));
+        return means[means.len() / 2].round() as u64;
+    }
+    // (s1, s2)
+    pub fn dimensions(&self) -> (usize, usize) {
+        return (self.groups[0].len(), self.groups.len());
+    }
+    // s1 (u32) | s2 (u32) followed by every sketch, group after group, as a NormSketch without header
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (s1, s2) = self.dimensions();
+        let mut writer: ByteWriter = ByteWriter::new(SketchKind::AmsF2Estimator);
+        writer.u32(s1 as u32);
+        writer.u32(s2 as u32);
+        self.groups.iter().flatten().for_each(|sketch| sketch.write_body(&mut writer));
+        return writer.finish();
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<AmsF2Estimator, DecodeError> {
+        let mut reader: ByteReader = ByteReader::new(bytes, SketchKind::AmsF2Estimator)?;
+        let s1: usize = reader.u32()? as usize;
+        let s2: usize = reader.u32()? as usize;
+        if s1 == 0 || s2 == 0 {
+            return Err(DecodeError::Invalid("needs at least one group of one sketch"));
+        }
+        let groups: Vec<Vec<NormSketch>> = (0..s2)
+            .map(|_| (0..s1).map(|_| NormSketch::read_body(&mut reader)).collect::<Result<_, _>>())
+            .collect::<Result<_, _>>()?;
+        reader.finish()?;
+        return Ok(AmsF2Estimator { groups });
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.groups.iter().flatten().map(|sketch| sketch.space_usage()).sum();
+    }
+}
+
+impl MemoryFootprint for AmsF2Estimator {
+    fn heap_bytes(&self) -> usize {
+        return self.space_usage();
+    }
+    fn allocations(&self) -> usize {
+        return vec_allocations(&self.groups) + self.groups.iter()
+            .map(|group| vec_allocations(group) + group.iter().map(|sketch| sketch.allocations()).sum::<usize>())
+            .sum::<usize>();
+    }
+}
+
+impl MergeableSketch for AmsF2Estimator {
+    fn merge(&mut self, other: &AmsF2Estimator) -> Result<(), MergeError> {
+        if self.dimensions() != other.dimensions() {
+            return Err(MergeError::DimensionMismatch);
+        }
+        // Check every sketch before touching any, so a failed merge leaves self unchanged
+        for (sketch, other_sketch) in self.groups.iter().flatten().zip(other.groups.iter().flatten()) {
+            sketch.check_mergeable(other_sketch)?;
+        }
+        for (sketch, other_sketch) in self.groups.iter_mut().flatten().zip(other.groups.iter().flatten()) {
+            sketch.merge(other_sketch)?;
+        }
+        return Ok(());
+    }
+}
+
+// The AMS estimator of the k-th frequency moment F_k, the sum of count^k over all keys, for
+// insert-only streams. Every basic estimator samples a uniform position of the stream and
+// counts the occurrences r of its key from there on, and m (r^k - (r - 1)^k) is unbiased for
+// F_k on a stream of length m. Groups of s1 basic estimators are averaged and the median of
+// the s2 group means is returned, as for F2.
+pub struct AmsFkEstimator {
+    // Sampled key of every basic estimator, with the count of that key when it was sampled
+    samples: Vec<(u64, u64)>,
+    // Occurrences of every sampled key since it was first sampled, and how many estimators hold it
+    counts: HashMap<u64, (u64, usize)>,
+    // Stream position at which each basic estimator samples again, earliest on top
+    replacements: BinaryHeap<Reverse<(u64, usize)>>,
+    k: u32,
+    s1: usize,
+    len: u64,
+}
+
+impl AmsFkEstimator {
+    pub fn new(k: u32, s1: usize, s2: usize) -> AmsFkEstimator {
+        assert!(k >= 1, "Moments start at k = 1");
+        assert!(s1 > 0 && s2 > 0, "Needs at least one group of one estimator");
+        return AmsFkEstimator {
+            samples: vec![(0, 0); s1 * s2],
+            counts: HashMap::new(),
+            // The first item is sampled by every estimator
+            replacements: (0..s1 * s2).map(|i| Reverse((1, i))).collect(),
+            k,
+            s1,
+            len: 0,
+        }
+    }
+    // A basic estimator has variance at most k n^(1 - 1/k) F_k^2 over a universe of n keys, so
+    // by Chebyshev s1 = 8 k n^(1 - 1/k) / epsilon^2 gives a group mean within epsilon F_k with
+    // probability 7/8, and s2 = 8 ln(1 / delta) groups fail with probability at most delta.
+    pub fn with_guarantees(k: u32, epsilon: f64, delta: f64, universe: u64) -> AmsFkEstimator {
+        let spread: f64 = (universe.max(1) as f64).powf(1.0 - 1.0 / k as f64);
+        let s1: usize = (8.0 * k as f64 * spread / (epsilon * epsilon)).ceil() as usize;
+        let s2: usize = (8.0 * (1.0 / delta).ln()).ceil().max(1.0) as usize;
+        return AmsFkEstimator::new(k, s1, s2);
+    }
+    // Every basic estimator replaces its sample at position t with probability 1/t, so only
+    // the positions of replacements are drawn: the next one after t is past t' with
+    // probability t/t'. The other items only bump the count of their key, if it is sampled.
+    pub fn insert(&mut self, key: u64) {
+        self.len += 1;
+        while let Some(Reverse((position, i))) = self.replacements.peek().copied() {
+            if position != self.len {
+                break;
+            }
+            self.replacements.pop();
+            if self.len > 1 {
+                self.release(self.samples[i].0);
+            }
+            let (count, holders) = self.counts.entry(key).or_insert((0, 0));
+            *holders += 1;
+            self.samples[i] = (key, *count);
+            let u: f64 = 1.0 - thread_rng().gen::<f64>();
+            let next: u64 = (self.len as f64 / u).floor().min(u64::MAX as f64) as u64;
+            self.replacements.push(Reverse((next.saturating_add(1), i)));
+        }
+        if let Some((count, _)) = self.counts.get_mut(&key) {
+            *count += 1;
+        }
+    }
+    // Drops the count of a key once no estimator holds it anymore
+    fn release(&mut self, key: u64) {
+        let (_, holders) = self.counts.get_mut(&key).unwrap();
+        *holders -= 1;
+        if *holders == 0 {
+            self.counts.remove(&key);
+        }
+    }
+    pub fn moment(&self) -> f64 {
+        if self.len == 0 {
+            return 0.0;
+        }
+        let basic = |(key, offset): &(u64, u64)| {
+            let r: f64 = (self.counts[key].0 - offset) as f64;
+            self.len as f64 * (r.powi(self.k as i32) - (r - 1.0).powi(self.k as i32))
+        };
+        let mut means: Vec<f64> = self.samples.chunks(self.s1)
+            .map(|group| group.iter().map(basic).sum::<f64>() / group.len() as f64)
+            .collect();
+        means.sort_unstable_by(|a, b| a.total_cmp(b));
+        return means[means.len() / 2];
+    }
+    pub fn k(&self) -> u32 {
+        return self.k;
+    }
+    // (s1, s2)
+    pub fn dimensions(&self) -> (usize, usize) {
+        return (self.s1, self.samples.len() / self.s1);
+    }
+    // Number of items inserted so far
+    pub fn len(&self) -> u64 {
+        return self.len;
+    }
+    pub fn is_empty(&self) -> bool {
+        return self.len == 0;
+    }
+    // Heap footprint of the samples, the sampled key counts and the replacement schedule in bytes
+    pub fn space_usage(&self) -> usize {
+        return self.samples.len() * size_of::<(u64, u64)>()
+            + self.counts.len() * size_of::<(u64, (u64, usize))>()
+            + self.replacements.len() * size_of::<(u64, usize)>();
+    }
+    // k (u32) | s1 (u64) | len (u64) | number of basic estimators (u64), followed by the
+    // sampled key (u64), count offset (u64) and next replacement (u64) of every estimator,
+    // then the number of sampled keys (u64) and the key (u64) and count (u64) of each in
+    // order of their keys
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new(SketchKind::AmsFkEstimator);
+        writer.u32(self.k);
+        writer.u64(self.s1 as u64);
+        writer.u64(self.len);
+        let mut next: Vec<u64> = vec![0; self.samples.len()];
+        self.replacements.iter().for_each(|Reverse((position, i))| next[*i] = *position);
+        writer.u64(self.samples.len() as u64);
+        for ((key, offset), position) in self.samples.iter().zip(next) {
+            writer.u64(*key);
+            writer.u64(*offset);
+            writer.u64(position);
+        }
+        let mut counts: Vec<(u64, u64)> = self.counts.iter().map(|(key, (count, _))| (*key, *count)).collect();
+        counts.sort_unstable();
+        writer.u64(counts.len() as u64);
+        for (key, count) in counts {
+            writer.u64(key);
+            writer.u64(count);
+        }
+        return writer.finish();
+    }
+    // The holders of every count and the replacement schedule are rebuilt from the samples.
+    // Before the first item no estimator holds a key, afterwards the counts must cover
+    // exactly the sampled keys, each past the offset it was sampled at.
+    pub fn from_bytes(bytes: &[u8]) -> Result<AmsFkEstimator, DecodeError> {
+        let mut reader: ByteReader = ByteReader::new(bytes, SketchKind::AmsFkEstimator)?;
+        let k: u32 = reader.u32()?;
+        let s1: usize = reader.u64()? as usize;
+        let len: u64 = reader.u64()?;
+        let estimators: usize = reader.len(3 * size_of::<u64>())?;
+        if k == 0 || s1 == 0 || estimators == 0 || !estimators.is_multiple_of(s1) {
+            return Err(DecodeError::Invalid("needs at least one group of one estimator"));
+        }
+        let mut samples: Vec<(u64, u64)> = Vec::with_capacity(estimators);
+        let mut replacements: BinaryHeap<Reverse<(u64, usize)>> = BinaryHeap::with_capacity(estimators);
+        for i in 0..estimators {
+            let (key, offset, position) = (reader.u64()?, reader.u64()?, reader.u64()?);
+            if position <= len {
+                return Err(DecodeError::Invalid("replacement scheduled in the past"));
+            }
+            samples.push((key, offset));
+            replacements.push(Reverse((position, i)));
+        }
+        let keys: usize = reader.len(2 * size_of::<u64>())?;
+        let mut counts: HashMap<u64, (u64, usize)> = HashMap::with_capacity(keys);
+        for _ in 0..keys {
+            let (key, count) = (reader.u64()?, reader.u64()?);
+            if counts.insert(key, (count, 0)).is_some() {
+                return Err(DecodeError::Invalid("key counted twice"));
+            }
+        }
+        reader.finish()?;
+        if len > 0 {
+            for (key, offset) in &samples {
+                match counts.get_mut(key) {
+                    Some((count, holders)) if *offset < *count => *holders += 1,
+                    _ => return Err(DecodeError::Invalid("sample without a count past its offset")),
+                }
+            }
+        }
+        if counts.values().any(|(_, holders)| *holders == 0) {
+            return Err(DecodeError::Invalid("count of a key no estimator holds"));
+        }
+        return Ok(AmsFkEstimator { samples, counts, replacements, k, s1, len });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_of_means_stays_within_epsilon() {
+        let mut ams: AmsF2Estimator = AmsF2Estimator::with_guarantees(0.5, 0.01);
+        assert_eq!(ams.dimensions(), (32, 37));
+        for key in 0..1000u64 {
+            ams.insert(key, (key % 7) as i64 - 3);
+        }
+        let norm: f64 = (0..1000u64).map(|key| ((key % 7) as f64 - 3.0).powi(2)).sum();
+        assert!((ams.norm() as f64 - norm).abs() < 0.5 * norm);
+    }
+
+    #[test]
+    fn third_moment_stays_within_epsilon() {
+        let mut ams: AmsFkEstimator = AmsFkEstimator::with_guarantees(3, 0.5, 0.05, 64);
+        assert_eq!(ams.dimensions().1, 24);
+        let mut counts: Vec<u64> = vec![0; 64];
+        for i in 0..5000u64 {
+            // Skewed keys, so a few heavy ones dominate F_3
+            let key: u64 = (i * 7919 % 4096).trailing_zeros().min(11) as u64 * 5 + i % 5;
+            ams.insert(key);
+            counts[key as usize] += 1;
+        }
+        let moment: f64 = counts.iter().map(|count| (*count as f64).powi(3)).sum();
+        assert!((ams.moment() - moment).abs() < 0.5 * moment, "{} vs {}", ams.moment(), moment);
+
+        // F_1 is the stream length, which every basic estimator hits exactly
+        let mut f1: AmsFkEstimator = AmsFkEstimator::new(1, 4, 3);
+        (0..1000u64).for_each(|key| f1.insert(key % 10));
+        assert_eq!(f1.moment(), 1000.0);
+    }
+
+    #[test]
+    fn fk_bytes_round_trip() {
+        let mut ams: AmsFkEstimator = AmsFkEstimator::new(2, 8, 3);
+        let empty: AmsFkEstimator = AmsFkEstimator::from_bytes(&ams.to_bytes()).unwrap();
+        assert_eq!(empty.moment(), 0.0);
+        (0..5000u64).for_each(|i| ams.insert(i * i % 97));
+        let bytes: Vec<u8> = ams.to_bytes();
+        let restored: AmsFkEstimator = AmsFkEstimator::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.moment(), ams.moment());
+        assert_eq!(restored.dimensions(), (8, 3));
+        assert_eq!(restored.to_bytes(), bytes);
+        assert_eq!(restored.replacements.peek(), ams.replacements.peek());
+
+        // The first estimator's next replacement moved before the items seen so far
+        let mut bytes: Vec<u8> = bytes;
+        bytes[52..60].copy_from_slice(&100u64.to_le_bytes());
+        assert_eq!(AmsFkEstimator::from_bytes(&bytes).err(), Some(DecodeError::Invalid("replacement scheduled in the past")));
+    }
+}
@@ -0,0 +1,117 @@
+use rand::prelude::*;
+
+// A search state for simulated annealing. Moves are proposed and scored before they are
+// applied, so a rejected move costs only its delta, not a copy of the state.
+pub trait Annealable: Clone {
+    type Move;
+
+    // Lower is better
+    fn energy(&self) -> f64;
+    fn propose(&self, rng: &mut impl Rng) -> Self::Move;
+    // Energy after the move minus energy before it
+    fn delta(&self, proposal: &Self::Move) -> f64;
+    fn apply(&mut self, proposal: Self::Move);
+}
+
+// How the temperature falls over the steps of a run
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Schedule {
+    // From start down to end by a constant factor per step, the usual choice
+    Geometric { start: f64, end: f64 },
+    // From start down to 0 in equal decrements
+    Linear { start: f64 },
+    // c / ln(step + 2), slow enough that the chain converges to the global minima for c at
+    // least the deepest local minimum's barrier (Hajek), but too slow to be practical
+    Logarithmic { c: f64 },
+}
+
+impl Schedule {
+    pub fn temperature(&self, step: usize, steps: usize) -> f64 {
+        let progress: f64 = step as f64 / steps.max(1) as f64;
+        return match self {
+            Schedule::Geometric { start, end } => start * (end / start).powf(progress),
+            Schedule::Linear { start } => start * (1.0 - progress),
+            Schedule::Logarithmic { c } => c / (step as f64 + 2.0).ln(),
+        };
+    }
+}
+
+// Best state seen during a run, which may be better than the one it ended in
+#[derive(Clone, Debug)]
+pub struct Annealed<S> {
+    pub best: S,
+    pub best_energy: f64,
+    pub final_energy: f64,
+    pub accepted: usize,
+}
+
+// Simulated annealing with the Metropolis rule: a proposed move that lowers the energy is
+// always taken, and one that raises it by delta is taken with probability
+// e^(-delta / temperature), so the walk can climb out of local minima while it is hot and
+// settles into one as it cools. trace sees the step, the temperature and the current energy
+// after every step. Passing a seeded generator repeats a run exactly.
+pub fn anneal<S: Annealable>(mut state: S, schedule: Schedule, steps: usize, rng: &mut impl Rng, mut trace: impl FnMut(usize, f64, f64)) -> Annealed<S> {
+    let mut energy: f64 = state.energy();
+    let mut best: S = state.clone();
+    let mut best_energy: f64 = energy;
+    let mut accepted: usize = 0;
+    for step in 0..steps {
+        let temperature: f64 = schedule.temperature(step, steps);
+        let proposal: S::Move = state.propose(rng);
+        let delta: f64 = state.delta(&proposal);
+        if delta <= 0.0 || (temperature > 0.0 && rng.gen::<f64>() < (-delta / temperature).exp()) {
+            state.apply(proposal);
+            energy += delta;
+            accepted += 1;
+            if energy < best_energy {
+                best_energy = energy;
+                best = state.clone();
+            }
+        }
+        trace(step, temperature, energy);
+    }
+    return Annealed { best, best_energy, final_energy: energy, accepted }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+
+    // An integer walking by +-1 on a bumpy parabola with its minimum at 40 and local minima
+    // at every multiple of 5, which plain descent from 0 gets stuck in at once
+    #[derive(Clone)]
+    struct Bumpy(i64);
+
+    impl Annealable for Bumpy {
+        type Move = i64;
+
+        fn energy(&self) -> f64 {
+            let x: f64 = self.0 as f64;
+            return (x - 40.0).powi(2) / 100.0 + 3.0 * (self.0.rem_euclid(5) != 0) as u8 as f64;
+        }
+        fn propose(&self, rng: &mut impl Rng) -> i64 {
+            return if rng.gen::<bool>() { 1 } else { -1 };
+        }
+        fn delta(&self, proposal: &i64) -> f64 {
+            return Bumpy(self.0 + proposal).energy() - self.energy();
+        }
+        fn apply(&mut self, proposal: i64) {
+            self.0 += proposal;
+        }
+    }
+
+    #[test]
+    fn escapes_local_minima_while_hot() {
+        let mut rng: StdRng = StdRng::seed_from_u64(5);
+        let mut traced: usize = 0;
+        let annealed: Annealed<Bumpy> = anneal(Bumpy(0), Schedule::Geometric { start: 5.0, end: 0.01 }, 100_000, &mut rng, |_, _, _| traced += 1);
+        assert_eq!((annealed.best.0, traced), (40, 100_000));
+        assert!(annealed.best_energy.abs() < 1e-9, "{}", annealed.best_energy);
+        // At temperature 0 only improving moves are taken, and both neighbors of 0 are worse
+        let frozen: Annealed<Bumpy> = anneal(Bumpy(0), Schedule::Linear { start: 0.0 }, 1000, &mut rng, |_, _, _| {});
+        assert_eq!((frozen.best.0, frozen.accepted), (0, 0));
+        assert!((Schedule::Logarithmic { c: 2.0 }.temperature(0, 10) - 2.0 / 2f64.ln()).abs() < 1e-12);
+        assert!((Schedule::Geometric { start: 4.0, end: 1.0 }.temperature(5, 10) - 2.0).abs() < 1e-12);
+    }
+}
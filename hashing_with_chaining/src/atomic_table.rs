@@ -0,0 +1,90 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::hash::{log2u, SeededHash};
+
+// Marks an unclaimed slot, so this key cannot be counted
+const EMPTY: u64 = u64::MAX;
+
+// Fixed capacity linear probing table of counters that any number of threads can update
+// without locks. A key claims its slot once with a compare-and-swap and is never moved,
+// so counting afterwards is a single fetch_add.
+pub struct AtomicCounterTable {
+    keys: Vec<AtomicU64>,
+    counts: Vec<AtomicU64>,
+    hash_function: SeededHash,
+}
+
+impl AtomicCounterTable {
+    // Room for `capacity` distinct keys at a load factor of at most 1/2
+    pub fn new(capacity: usize) -> AtomicCounterTable {
+        let table_len: usize = (2 * capacity).max(2).next_power_of_two();
+        return AtomicCounterTable {
+            keys: (0..table_len).map(|_| AtomicU64::new(EMPTY)).collect(),
+            counts: (0..table_len).map(|_| AtomicU64::new(0)).collect(),
+            hash_function: SeededHash::new(log2u(table_len)),
+        }
+    }
+    pub fn insert(&self, key: u64, count: u64) {
+        assert_ne!(key, EMPTY, "Key {} is reserved", EMPTY);
+        let mask: usize = self.keys.len() - 1;
+        let mut pos: usize = self.hash_function.hash(key);
+        for _ in 0..self.keys.len() {
+            let mut current: u64 = self.keys[pos].load(Ordering::Acquire);
+            if current == EMPTY {
+                current = match self.keys[pos].compare_exchange(EMPTY, key, Ordering::AcqRel, Ordering::Acquire) {
+                    Ok(_) => key,
+                    Err(other) => other,
+                };
+            }
+            if current == key {
+                self.counts[pos].fetch_add(count, Ordering::Relaxed);
+                return;
+            }
+            pos = (pos + 1) & mask;
+        }
+        panic!("Atomic counter table is full");
+    }
+    pub fn get(&self, key: u64) -> u64 {
+        let mask: usize = self.keys.len() - 1;
+        let mut pos: usize = self.hash_function.hash(key);
+        for _ in 0..self.keys.len() {
+            let current: u64 = self.keys[pos].load(Ordering::Acquire);
+            if current == key {
+                return self.counts[pos].load(Ordering::Relaxed);
+            }
+            if current == EMPTY {
+                return 0;
+            }
+            pos = (pos + 1) & mask;
+        }
+        return 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hwc::HwC;
+    use std::thread;
+
+    #[test]
+    fn concurrent_totals_match_single_threaded_run() {
+        let keys: Vec<u64> = (0..100_000u64).map(|x| x * x % 4099).collect();
+        let table: AtomicCounterTable = AtomicCounterTable::new(4099);
+        thread::scope(|s| {
+            for chunk in keys.chunks(12_500) {
+                let table: &AtomicCounterTable = &table;
+                s.spawn(move || {
+                    for key in chunk {
+                        table.insert(*key, 3);
+                    }
+                });
+            }
+        });
+
+        let mut hwc: HwC = HwC::new(4096);
+        for key in &keys {
+            hwc.insert(*key, 3);
+        }
+        assert!((0..4099).all(|key| table.get(key) as i64 == hwc.get(key)));
+    }
+}
@@ -0,0 +1,99 @@
+use crate::hash::IndependentHash;
+
+// Where a ball goes: its one hashed bin, or the least loaded of d hashed bins
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Policy {
+    OneChoice,
+    TwoChoice,
+    DChoice(usize),
+}
+
+impl Policy {
+    pub fn choices(&self) -> usize {
+        return match self {
+            Policy::OneChoice => 1,
+            Policy::TwoChoice => 2,
+            Policy::DChoice(d) => *d,
+        };
+    }
+}
+
+// Balls are keys and the bins of a key come from d independent 4-wise independent hash
+// functions. With n balls in n bins one choice leaves a maximum load of about
+// ln n / ln ln n, while the least loaded of d >= 2 choices brings it down to
+// ln ln n / ln d + O(1) (Azar, Broder, Karlin and Upfal), exponentially less. With m > n
+// balls the gap between the maximum and the average m / n stays at that for d >= 2, but
+// grows like sqrt(m ln n / n) for one choice.
+#[derive(Clone, Debug)]
+pub struct BallsIntoBins {
+    hash_functions: Vec<IndependentHash>,
+    loads: Vec<u32>,
+}
+
+impl BallsIntoBins {
+    pub fn new(bins: usize, policy: Policy) -> BallsIntoBins {
+        assert!(bins > 0 && policy.choices() > 0, "Needs a bin and a choice");
+        return BallsIntoBins {
+            hash_functions: (0..policy.choices()).map(|_| IndependentHash::new(4)).collect(),
+            loads: vec![0; bins],
+        }
+    }
+    // Puts the ball in the least loaded of its bins, the first of them on ties, and returns it
+    pub fn throw(&mut self, key: u64) -> usize {
+        let bins: u128 = self.loads.len() as u128;
+        let bin: usize = self.hash_functions.iter()
+            .map(|hash_function| ((hash_function.hash(key) as u128 * bins) >> 61) as usize)
+            .min_by_key(|bin| self.loads[*bin])
+            .unwrap();
+        self.loads[bin] += 1;
+        return bin;
+    }
+    pub fn loads(&self) -> &[u32] {
+        return &self.loads;
+    }
+    pub fn max_load(&self) -> u32 {
+        return self.loads.iter().copied().max().unwrap_or(0);
+    }
+}
+
+// Maximum loads of trials throws of the keys 0 to balls - 1, each with fresh hash functions,
+// as a histogram: entry l counts the trials whose maximum load was l
+pub fn max_load_distribution(balls: u64, bins: usize, policy: Policy, trials: usize) -> Vec<usize> {
+    let mut histogram: Vec<usize> = Vec::new();
+    for _ in 0..trials {
+        let mut simulation: BallsIntoBins = BallsIntoBins::new(bins, policy);
+        (0..balls).for_each(|key| { simulation.throw(key); });
+        let max_load: usize = simulation.max_load() as usize;
+        if histogram.len() <= max_load {
+            histogram.resize(max_load + 1, 0);
+        }
+        histogram[max_load] += 1;
+    }
+    return histogram;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn more_choices_flatten_the_maximum_load() {
+        // With n = 2^16 balls and bins, one choice expects n P(Poisson(1) >= 6) ≈ 39 bins
+        // with load 6 or more, so missing them all has probability about e^-39. Two and
+        // three choices stay at 3 or 4.
+        let n: usize = 1 << 16;
+        let one: Vec<usize> = max_load_distribution(n as u64, n, Policy::OneChoice, 3);
+        assert_eq!(one.iter().sum::<usize>(), 3);
+        assert!(one[..6].iter().all(|count| *count == 0), "{:?}", one);
+        let two: Vec<usize> = max_load_distribution(n as u64, n, Policy::TwoChoice, 3);
+        assert!(two.len() <= 5, "{:?}", two);
+        let three: Vec<usize> = max_load_distribution(n as u64, n, Policy::DChoice(3), 3);
+        assert!(three.len() <= 4, "{:?}", three);
+
+        // With 10 balls per bin two choices stay within a few balls of the average
+        let mut heavy: BallsIntoBins = BallsIntoBins::new(n, Policy::TwoChoice);
+        (0..10 * n as u64).for_each(|key| { heavy.throw(key); });
+        assert_eq!(heavy.loads().iter().map(|load| *load as usize).sum::<usize>(), 10 * n);
+        assert!(heavy.max_load() <= 14, "{}", heavy.max_load());
+    }
+}
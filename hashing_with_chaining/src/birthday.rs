@@ -0,0 +1,74 @@
+use rand::prelude::*;
+use crate::hwc::HwC;
+
+// Two keys with the same hash value, and how many keys the search hashed to find them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Collision {
+    pub first: u64,
+    pub second: u64,
+    pub hashed: u64,
+    pub keys: usize,
+}
+
+// Birthday attack on any hash function: hashes uniformly random keys below 2^62 and keeps
+// them in a hash table by hash value until two distinct keys share one. For a hash that
+// behaves randomly on N values that takes about sqrt(pi N / 2) keys, so a search that needs
+// far fewer points to a range that is smaller or more skewed than claimed. Gives up after
+// max_keys keys.
+pub fn find_collision(hash: impl Fn(u64) -> u64, max_keys: usize, rng: &mut impl Rng) -> Option<Collision> {
+    // Maps every hash value to the position of its key in tried plus one, since a count of 0
+    // means absent. The chains absorb searches that outgrow the buckets.
+    let mut seen: HwC = HwC::new(max_keys.clamp(1, 1 << 16));
+    let mut tried: Vec<u64> = Vec::new();
+    for keys in 1..=max_keys {
+        let key: u64 = rng.gen_range(0..1 << 62);
+        let hashed: u64 = hash(key);
+        let stored: i64 = seen.get(hashed);
+        if stored == 0 {
+            tried.push(key);
+            seen.insert(hashed, tried.len() as i64);
+        } else if tried[stored as usize - 1] != key {
+            return Some(Collision { first: tried[stored as usize - 1], second: key, hashed, keys })
+        }
+    }
+    return None;
+}
+
+// Expected number of uniform draws from range values until one repeats,
+// sqrt(pi N / 2) + 2/3 up to O(1 / sqrt(N)) (Ramanujan's Q function plus one)
+pub fn expected_keys_to_collision(range: f64) -> f64 {
+    return (std::f64::consts::PI * range / 2.0).sqrt() + 2.0 / 3.0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::{IndependentHash, SeededHash};
+
+    // Mean keys to the first collision over trials searches, each with a new hash function.
+    // Searches of 2^20 values that find nothing in 2^14 keys have probability e^-128.
+    fn mean_keys<H: Fn(u64) -> u64>(new_hash: impl Fn() -> H, trials: usize) -> f64 {
+        let mut rng = thread_rng();
+        let total: usize = (0..trials).map(|_| {
+            let hash: H = new_hash();
+            let collision: Collision = find_collision(&hash, 1 << 14, &mut rng).unwrap();
+            assert!(collision.first != collision.second && hash(collision.first) == hash(collision.second));
+            return collision.keys;
+        }).sum();
+        return total as f64 / trials as f64;
+    }
+
+    #[test]
+    fn collisions_come_after_about_the_square_root_of_the_range() {
+        // The number of keys has standard deviation about sqrt((2 - pi / 2) N) ≈ 670 for
+        // N = 2^20, so the mean of 400 searches is within 5 standard errors, 170, of 1284
+        let expected: f64 = expected_keys_to_collision((1 << 20) as f64);
+        let multiply_shift: f64 = mean_keys(|| { let h: SeededHash = SeededHash::new(20); move |x: u64| h.hash(x) as u64 }, 400);
+        assert!((multiply_shift - expected).abs() < 170.0, "{} {}", multiply_shift, expected);
+        let polynomial: f64 = mean_keys(|| { let h: IndependentHash = IndependentHash::new(2); move |x: u64| h.hash(x) >> 41 }, 400);
+        assert!((polynomial - expected).abs() < 170.0, "{} {}", polynomial, expected);
+        // A hash that only reaches 1000 of its 2^20 values is caught after about 40 keys
+        let narrow: f64 = mean_keys(|| |x: u64| x % 1000, 400);
+        assert!(narrow < 100.0, "{}", narrow);
+    }
+}
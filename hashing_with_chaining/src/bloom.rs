@@ -0,0 +1,734 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f64::consts::LN_2;
+use core::mem::size_of;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::float::{ceil, exp, ln, powf, powi, round, sqrt};
+use crate::hash::IndependentHash;
+use crate::memory::{vec_allocations, MemoryFootprint};
+use crate::mergeable::{MergeError, MergeableSketch};
+use crate::sketch_format::{ByteReader, ByteWriter, DecodeError, SketchKind};
+
+// Largest log2 of the number of bits or cells, as probes are cut from 61-bit hash values
+pub const MAX_BITS_LEN: u32 = 61;
+
+// Bit array size and number of hash functions minimizing the memory for n keys at a
+// false positive rate of fpr: m = -n ln(fpr) / ln(2)^2 bits and k = m / n ln(2). The
+// bit count is returned as its log2, rounded up to a power of two.
+pub fn optimal_dimensions(n: usize, fpr: f64) -> (u32, usize) {
+    assert!(fpr > 0.0 && fpr < 1.0, "False positive rate must be in (0, 1)");
+    return checked_optimal_dimensions(n, fpr).expect("Too many keys for one filter");
+}
+
+// optimal_dimensions, or None where it would panic: for fpr outside (0, 1), or for more
+// bits than a filter has room for or a usize can count
+pub fn checked_optimal_dimensions(n: usize, fpr: f64) -> Option<(u32, usize)> {
+    if !(fpr > 0.0 && fpr < 1.0) {
+        return None;
+    }
+    let bits: f64 = -(n.max(1) as f64) * ln(fpr) / (LN_2 * LN_2);
+    if bits > (1u64 << MAX_BITS_LEN.min(usize::BITS - 1)) as f64 {
+        return None;
+    }
+    let bits_len: u32 = (ceil(bits) as usize).max(64).next_power_of_two().ilog2();
+    let k: usize = round((1usize << bits_len) as f64 / n.max(1) as f64 * LN_2).max(1.0) as usize;
+    return Some((bits_len, k));
+}
+
+// The k probes of a key into 2^bits_len cells are h1 + i * h2 for two hash functions
+// (Kirsch and Mitzenmacher), which keeps the false positive rate of k independent hash
+// functions. h2 is made odd so the probes never repeat. Both come from 4-wise independent
+// polynomials: with multiply-shift, runs of consecutive keys pile up on the same cells
+// often enough to saturate counting filter counters.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Probes {
+    hash_functions: [IndependentHash; 2],
+    k: usize,
+    mask: usize,
+}
+
+impl Probes {
+    fn with_rng(bits_len: u32, k: usize, rng: &mut impl Rng) -> Probes {
+        assert!(bits_len <= MAX_BITS_LEN, "At most 2^61 cells");
+        return Probes {
+            hash_functions: [IndependentHash::with_rng(4, rng), IndependentHash::with_rng(4, rng)],
+            k,
+            mask: (1 << bits_len) - 1,
+        }
+    }
+    fn of(&self, key: u64) -> impl Iterator<Item = usize> + '_ {
+        let bits_len: u32 = self.mask.count_ones();
+        let h1: usize = (self.hash_functions[0].hash(key) >> (61 - bits_len)) as usize;
+        let h2: usize = (self.hash_functions[1].hash(key) >> (61 - bits_len)) as usize | 1;
+        return (0..self.k).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) & self.mask);
+    }
+    // (1 - e^(-kn/m))^k, the false positive rate expected after n distinct inserts
+    fn expected_fpr(&self, n: usize) -> f64 {
+        let m: f64 = (self.mask + 1) as f64;
+        return powi(1.0 - exp(-(self.k as f64) * n as f64 / m), self.k as i32);
+    }
+    // bits_len (u32) | k (u32) | the two hash functions
+    fn write(&self, writer: &mut ByteWriter) {
+        writer.u32(self.mask.count_ones());
+        writer.u32(self.k as u32);
+        self.hash_functions.iter().for_each(|hash_function| writer.independent_hash(hash_function));
+    }
+    // Probes over at least 2^min_bits_len cells
+    fn read(reader: &mut ByteReader, min_bits_len: u32) -> Result<Probes, DecodeError> {
+        let bits_len: u32 = reader.u32()?;
+        if !(min_bits_len..=MAX_BITS_LEN.min(usize::BITS - 1)).contains(&bits_len) {
+            return Err(DecodeError::Invalid("number of cells out of range"));
+        }
+        let k: usize = reader.u32()? as usize;
+        if k == 0 {
+            return Err(DecodeError::Invalid("no hash functions"));
+        }
+        let hash_functions: [IndependentHash; 2] = [reader.independent_hash()?, reader.independent_hash()?];
+        return Ok(Probes { hash_functions, k, mask: (1 << bits_len) - 1 });
+    }
+}
+
+// Reads the u64 words of a filter, which must be exactly words_len of them
+fn read_words(reader: &mut ByteReader, words_len: usize) -> Result<Vec<u64>, DecodeError> {
+    let len: usize = reader.len(size_of::<u64>())?;
+    if len != words_len {
+        return Err(DecodeError::Invalid("words do not match the number of cells"));
+    }
+    return (0..len).map(|_| reader.u64()).collect();
+}
+
+// Bloom filter over 2^bits_len bits
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    probes: Probes,
+    // Number of bits set
+    ones: usize,
+}
+
+impl BloomFilter {
+    #[cfg(feature = "std")]
+    pub fn new(bits_len: u32, k: usize) -> BloomFilter {
+        return BloomFilter::with_rng(bits_len, k, &mut rand::thread_rng());
+    }
+    // With the hash functions drawn from rng, for builds without std
+    pub fn with_rng(bits_len: u32, k: usize, rng: &mut impl Rng) -> BloomFilter {
+        assert!(bits_len >= 6, "Needs at least 64 bits");
+        return BloomFilter {
+            bits: vec![0; 1 << (bits_len - 6)],
+            probes: Probes::with_rng(bits_len, k, rng),
+            ones: 0,
+        }
+    }
+    // Sized for n keys at a false positive rate of at most fpr
+    #[cfg(feature = "std")]
+    pub fn optimal(n: usize, fpr: f64) -> BloomFilter {
+        let (bits_len, k) = optimal_dimensions(n, fpr);
+        return BloomFilter::new(bits_len, k);
+    }
+    pub fn insert(&mut self, key: u64) {
+        for bit in self.probes.of(key) {
+            let word: &mut u64 = &mut self.bits[bit / 64];
+            if *word & (1 << (bit % 64)) == 0 {
+                *word |= 1 << (bit % 64);
+                self.ones += 1;
+            }
+        }
+    }
+    // Never false for an inserted key
+    pub fn contains(&self, key: u64) -> bool {
+        return self.probes.of(key).all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0);
+    }
+    pub fn expected_fpr(&self, n: usize) -> f64 {
+        return self.probes.expected_fpr(n);
+    }
+    // Fraction of bits set. A query for a key that was never inserted hits k random
+    // bits, so the false positive rate is about fill_ratio^k.
+    pub fn fill_ratio(&self) -> f64 {
+        return self.ones as f64 / self.bit_count() as f64;
+    }
+    pub fn bit_count(&self) -> usize {
+        return self.probes.mask + 1;
+    }
+    pub fn hash_count(&self) -> usize {
+        return self.probes.k;
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.bits.len() * size_of::<u64>();
+    }
+    // Probes, followed by the number of words (u64) and the words of bits (u64)
+    fn write(&self, writer: &mut ByteWriter) {
+        self.probes.write(writer);
+        writer.u64(self.bits.len() as u64);
+        self.bits.iter().for_each(|word| writer.u64(*word));
+    }
+    fn read(reader: &mut ByteReader) -> Result<BloomFilter, DecodeError> {
+        let probes: Probes = Probes::read(reader, 6)?;
+        let bits: Vec<u64> = read_words(reader, (probes.mask + 1) / 64)?;
+        let ones: usize = bits.iter().map(|word| word.count_ones() as usize).sum();
+        return Ok(BloomFilter { bits, probes, ones });
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new(SketchKind::BloomFilter);
+        self.write(&mut writer);
+        return writer.finish();
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<BloomFilter, DecodeError> {
+        let mut reader: ByteReader = ByteReader::new(bytes, SketchKind::BloomFilter)?;
+        let filter: BloomFilter = BloomFilter::read(&mut reader)?;
+        reader.finish()?;
+        return Ok(filter);
+    }
+    fn check_compatible(&self, other: &BloomFilter) -> Result<(), MergeError> {
+        if self.bits.len() != other.bits.len() || self.probes.k != other.probes.k {
+            return Err(MergeError::DimensionMismatch);
+        }
+        if self.probes != other.probes {
+            return Err(MergeError::SeedMismatch);
+        }
+        return Ok(());
+    }
+    fn combine(&self, other: &BloomFilter, op: fn(u64, u64) -> u64) -> Result<BloomFilter, MergeError> {
+        self.check_compatible(other)?;
+        let bits: Vec<u64> = self.bits.iter().zip(&other.bits).map(|(a, b)| op(*a, *b)).collect();
+        let ones: usize = bits.iter().map(|word| word.count_ones() as usize).sum();
+        return Ok(BloomFilter { bits, probes: self.probes.clone(), ones });
+    }
+    // The filter of the keys of both filters, the same as inserting them all into one
+    pub fn union(&self, other: &BloomFilter) -> Result<BloomFilter, MergeError> {
+        return self.combine(other, |a, b| a | b);
+    }
+    // Contains every key inserted into both filters. It has at least the false positive
+    // rate of a filter built from the common keys alone, since bits set by different keys
+    // of the two sets survive as well.
+    pub fn intersect(&self, other: &BloomFilter) -> Result<BloomFilter, MergeError> {
+        return self.combine(other, |a, b| a & b);
+    }
+    // Number of distinct keys inserted, estimated from the fill ratio as -(m / k) ln(1 - X / m)
+    // with X of the m bits set (Swamidass and Baldi). Infinite for a filter full of ones.
+    pub fn estimate_cardinality(&self) -> f64 {
+        let m: f64 = self.bit_count() as f64;
+        return -(m / self.probes.k as f64) * ln(1.0 - self.ones as f64 / m);
+    }
+    // Number of keys inserted into both filters, by inclusion-exclusion over the estimated
+    // cardinalities of the two filters and their union. The AND of the bits would count
+    // the bits set by different keys of the two sets too.
+    pub fn estimate_intersection(&self, other: &BloomFilter) -> Result<f64, MergeError> {
+        let union: BloomFilter = self.union(other)?;
+        let estimate: f64 = self.estimate_cardinality() + other.estimate_cardinality() - union.estimate_cardinality();
+        return Ok(estimate.max(0.0));
+    }
+}
+
+impl MemoryFootprint for BloomFilter {
+    fn heap_bytes(&self) -> usize {
+        return self.space_usage();
+    }
+    fn allocations(&self) -> usize {
+        return vec_allocations(&self.bits);
+    }
+}
+
+// The filters must share their size and hash functions, which is easiest by cloning one
+// empty filter. Merging is the union.
+impl MergeableSketch for BloomFilter {
+    fn merge(&mut self, other: &BloomFilter) -> Result<(), MergeError> {
+        *self = self.union(other)?;
+        return Ok(());
+    }
+}
+
+// Fill ratio at which a ScalableBloomFilter starts a new sub-filter
+#[cfg(feature = "std")]
+const MAX_FILL_RATIO: f64 = 0.5;
+// Every sub-filter of a ScalableBloomFilter holds GROWTH times the keys of the one
+// before, at TIGHTENING times its false positive rate
+#[cfg(feature = "std")]
+const GROWTH: usize = 2;
+#[cfg(feature = "std")]
+const TIGHTENING: f64 = 0.5;
+
+// Bloom filter for an unknown number of keys (Almeida et al.). Keys go into the newest
+// of a chain of sub-filters, and once its fill ratio passes MAX_FILL_RATIO a larger
+// and tighter one is started. Sub-filter i gets a false positive rate of
+// fpr * (1 - TIGHTENING) * TIGHTENING^i, so the rates of the whole chain sum to at most fpr.
+// It draws the hash functions of every new sub-filter itself, so it needs std.
+#[cfg(feature = "std")]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScalableBloomFilter {
+    filters: Vec<BloomFilter>,
+    initial_capacity: usize,
+    fpr: f64,
+}
+
+#[cfg(feature = "std")]
+impl ScalableBloomFilter {
+    pub fn new(initial_capacity: usize, fpr: f64) -> ScalableBloomFilter {
+        assert!(fpr > 0.0 && fpr < 1.0, "False positive rate must be in (0, 1)");
+        let mut scalable: ScalableBloomFilter = ScalableBloomFilter {
+            filters: Vec::new(),
+            initial_capacity: initial_capacity.max(1),
+            fpr,
+        };
+        scalable.add_filter();
+        return scalable;
+    }
+    // At fill ratio 1/2 a filter with k hash functions has a false positive rate of
+    // 2^-k, so k = log2(1 / fpr) rounded up. Inserting n keys sets about a
+    // fraction 1 - e^(-kn/m) of the bits, which reaches 1/2 at m = kn / ln(2).
+    fn add_filter(&mut self) {
+        let i: usize = self.filters.len();
+        let capacity: usize = self.initial_capacity * GROWTH.pow(i as u32);
+        let fpr: f64 = self.fpr * (1.0 - TIGHTENING) * TIGHTENING.powi(i as i32);
+        let k: usize = (1.0 / fpr).log2().ceil() as usize;
+        let bits: f64 = (k * capacity) as f64 / LN_2;
+        let bits_len: u32 = (bits.ceil() as usize).max(64).next_power_of_two().ilog2();
+        self.filters.push(BloomFilter::new(bits_len, k));
+    }
+    pub fn insert(&mut self, key: u64) {
+        if self.contains(key) {
+            return;
+        }
+        let last: &mut BloomFilter = self.filters.last_mut().unwrap();
+        last.insert(key);
+        if last.fill_ratio() > MAX_FILL_RATIO {
+            self.add_filter();
+        }
+    }
+    pub fn contains(&self, key: u64) -> bool {
+        return self.filters.iter().any(|filter| filter.contains(key));
+    }
+    // Upper bound on the false positive rate of the chain as it is now
+    pub fn fpr_bound(&self) -> f64 {
+        return self.filters.iter().map(|filter| filter.fill_ratio().powi(filter.hash_count() as i32)).sum();
+    }
+    pub fn filter_count(&self) -> usize {
+        return self.filters.len();
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.filters.iter().map(|filter| filter.space_usage()).sum();
+    }
+    // initial_capacity (u64) | fpr (f64) | number of sub-filters (u64), followed by every
+    // sub-filter as BloomFilter writes it
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new(SketchKind::ScalableBloomFilter);
+        writer.u64(self.initial_capacity as u64);
+        writer.f64(self.fpr);
+        writer.u64(self.filters.len() as u64);
+        self.filters.iter().for_each(|filter| filter.write(&mut writer));
+        return writer.finish();
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<ScalableBloomFilter, DecodeError> {
+        let mut reader: ByteReader = ByteReader::new(bytes, SketchKind::ScalableBloomFilter)?;
+        let initial_capacity: usize = reader.u64()? as usize;
+        let fpr: f64 = reader.f64()?;
+        if initial_capacity == 0 || !(fpr > 0.0 && fpr < 1.0) {
+            return Err(DecodeError::Invalid("capacity or false positive rate out of range"));
+        }
+        // Every sub-filter takes more than one u64, which bounds their number by the bytes left
+        let len: usize = reader.len(size_of::<u64>())?;
+        if len == 0 {
+            return Err(DecodeError::Invalid("no sub-filters"));
+        }
+        let filters: Vec<BloomFilter> = (0..len).map(|_| BloomFilter::read(&mut reader)).collect::<Result<_, _>>()?;
+        reader.finish()?;
+        return Ok(ScalableBloomFilter { filters, initial_capacity, fpr });
+    }
+}
+
+#[cfg(feature = "std")]
+impl MemoryFootprint for ScalableBloomFilter {
+    fn heap_bytes(&self) -> usize {
+        return self.space_usage();
+    }
+    fn allocations(&self) -> usize {
+        return vec_allocations(&self.filters) + self.filters.iter().map(|filter| filter.allocations()).sum::<usize>();
+    }
+}
+
+// Bits in one block of a BlockedBloomFilter, one cache line
+const BLOCK_BITS: usize = 512;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[repr(align(64))]
+struct Block([u64; BLOCK_BITS / 64]);
+
+// Bloom filter split into cache line sized blocks, where one hash picks the block of
+// a key and all k probes land inside it, so a lookup touches a single cache line.
+// Blocks receive an uneven share of the keys, which costs some false positive rate
+// compared to BloomFilter with the same memory.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BlockedBloomFilter {
+    blocks: Vec<Block>,
+    // The top bits of the hash value pick the block and the lowest 18 bits the first probe
+    // and the odd stride within the block, 9 bits each
+    hash_function: IndependentHash,
+    blocks_len: u32,
+    k: usize,
+}
+
+impl BlockedBloomFilter {
+    #[cfg(feature = "std")]
+    pub fn new(bits_len: u32, k: usize) -> BlockedBloomFilter {
+        return BlockedBloomFilter::with_rng(bits_len, k, &mut rand::thread_rng());
+    }
+    pub fn with_rng(bits_len: u32, k: usize, rng: &mut impl Rng) -> BlockedBloomFilter {
+        assert!((9..=52).contains(&bits_len), "Needs between one block of {} bits and 2^52 bits", BLOCK_BITS);
+        return BlockedBloomFilter {
+            blocks: vec![Block([0; BLOCK_BITS / 64]); 1 << (bits_len - 9)],
+            hash_function: IndependentHash::with_rng(4, rng),
+            blocks_len: bits_len - 9,
+            k,
+        }
+    }
+    // Uses the dimensions of the standard filter, so the false positive rate ends up
+    // slightly above fpr
+    #[cfg(feature = "std")]
+    pub fn optimal(n: usize, fpr: f64) -> BlockedBloomFilter {
+        let (bits_len, k) = optimal_dimensions(n, fpr);
+        return BlockedBloomFilter::new(bits_len, k);
+    }
+    fn probes(&self, key: u64) -> (usize, impl Iterator<Item = usize>) {
+        let hashed: u64 = self.hash_function.hash(key);
+        let block: usize = (hashed >> (61 - self.blocks_len)) as usize;
+        let first: usize = (hashed >> 9) as usize & (BLOCK_BITS - 1);
+        let stride: usize = (hashed as usize & (BLOCK_BITS - 1)) | 1;
+        return (block, (0..self.k).map(move |i| (first + i * stride) & (BLOCK_BITS - 1)));
+    }
+    pub fn insert(&mut self, key: u64) {
+        let (block, bits) = self.probes(key);
+        let block: &mut Block = &mut self.blocks[block];
+        for bit in bits {
+            block.0[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+    pub fn contains(&self, key: u64) -> bool {
+        let (block, mut bits) = self.probes(key);
+        let block: &Block = &self.blocks[block];
+        return bits.all(|bit| block.0[bit / 64] & (1 << (bit % 64)) != 0);
+    }
+    // The number of keys in a block is about Poisson distributed with mean n / blocks,
+    // and a block holding i keys has the false positive rate of a 512 bit filter
+    pub fn expected_fpr(&self, n: usize) -> f64 {
+        let mean: f64 = n as f64 / self.blocks.len() as f64;
+        let k: f64 = self.k as f64;
+        let mut fpr: f64 = 0.0;
+        // Poisson probability of i keys, updated term by term
+        let mut probability: f64 = exp(-mean);
+        for i in 0..(mean + 10.0 * sqrt(mean) + 20.0) as usize {
+            fpr += probability * powf(1.0 - exp(-k * i as f64 / BLOCK_BITS as f64), k);
+            probability *= mean / (i + 1) as f64;
+        }
+        return fpr;
+    }
+    pub fn bit_count(&self) -> usize {
+        return self.blocks.len() * BLOCK_BITS;
+    }
+    pub fn hash_count(&self) -> usize {
+        return self.k;
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.blocks.len() * size_of::<Block>();
+    }
+    // blocks_len (u32) | k (u32) | hash function | number of blocks (u64), followed by the
+    // eight words (u64) of every block
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new(SketchKind::BlockedBloomFilter);
+        writer.u32(self.blocks_len);
+        writer.u32(self.k as u32);
+        writer.independent_hash(&self.hash_function);
+        writer.u64(self.blocks.len() as u64);
+        self.blocks.iter().flat_map(|block| block.0).for_each(|word| writer.u64(word));
+        return writer.finish();
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<BlockedBloomFilter, DecodeError> {
+        let mut reader: ByteReader = ByteReader::new(bytes, SketchKind::BlockedBloomFilter)?;
+        let blocks_len: u32 = reader.u32()?;
+        if blocks_len > 43.min(usize::BITS - 1) {
+            return Err(DecodeError::Invalid("number of blocks out of range"));
+        }
+        let k: usize = reader.u32()? as usize;
+        if k == 0 {
+            return Err(DecodeError::Invalid("no hash functions"));
+        }
+        let hash_function: IndependentHash = reader.independent_hash()?;
+        let len: usize = reader.len(size_of::<Block>())?;
+        if len != 1 << blocks_len {
+            return Err(DecodeError::Invalid("blocks do not match their number"));
+        }
+        let mut blocks: Vec<Block> = Vec::with_capacity(len);
+        for _ in 0..len {
+            let mut block: Block = Block([0; BLOCK_BITS / 64]);
+            for word in &mut block.0 {
+                *word = reader.u64()?;
+            }
+            blocks.push(block);
+        }
+        reader.finish()?;
+        return Ok(BlockedBloomFilter { blocks, hash_function, blocks_len, k });
+    }
+}
+
+impl MemoryFootprint for BlockedBloomFilter {
+    fn heap_bytes(&self) -> usize {
+        return self.space_usage();
+    }
+    fn allocations(&self) -> usize {
+        return vec_allocations(&self.blocks);
+    }
+}
+
+// Largest value of a 4-bit counter. A counter that reaches it stays there, since the
+// true count behind it is unknown from then on.
+const COUNTER_MAX: u8 = 15;
+
+// Bloom filter with a 4-bit counter in place of every bit, packed 16 to a u64, so
+// keys can be removed again. Counters that overflow stick at COUNTER_MAX and are
+// never decremented, which keeps every inserted key a member at the cost of
+// possibly keeping removed ones.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CountingBloomFilter {
+    counters: Vec<u64>,
+    probes: Probes,
+    overflows: usize,
+}
+
+impl CountingBloomFilter {
+    #[cfg(feature = "std")]
+    pub fn new(counters_len: u32, k: usize) -> CountingBloomFilter {
+        return CountingBloomFilter::with_rng(counters_len, k, &mut rand::thread_rng());
+    }
+    pub fn with_rng(counters_len: u32, k: usize, rng: &mut impl Rng) -> CountingBloomFilter {
+        assert!(counters_len >= 4, "Needs at least 16 counters");
+        return CountingBloomFilter {
+            counters: vec![0; 1 << (counters_len - 4)],
+            probes: Probes::with_rng(counters_len, k, rng),
+            overflows: 0,
+        }
+    }
+    #[cfg(feature = "std")]
+    pub fn optimal(n: usize, fpr: f64) -> CountingBloomFilter {
+        let (counters_len, k) = optimal_dimensions(n, fpr);
+        return CountingBloomFilter::new(counters_len, k);
+    }
+    fn counter(&self, cell: usize) -> u8 {
+        return ((self.counters[cell / 16] >> (4 * (cell % 16))) & 0xF) as u8;
+    }
+    fn set_counter(&mut self, cell: usize, value: u8) {
+        let shift: usize = 4 * (cell % 16);
+        self.counters[cell / 16] = (self.counters[cell / 16] & !(0xF << shift)) | ((value as u64) << shift);
+    }
+    // Returns false if a counter overflowed on this insert
+    pub fn insert(&mut self, key: u64) -> bool {
+        let cells: Vec<usize> = self.probes.of(key).collect();
+        let mut overflowed: bool = false;
+        for cell in cells {
+            let value: u8 = self.counter(cell);
+            if value == COUNTER_MAX - 1 {
+                overflowed = true;
+            }
+            if value < COUNTER_MAX {
+                self.set_counter(cell, value + 1);
+            }
+        }
+        if overflowed {
+            self.overflows += 1;
+        }
+        return !overflowed;
+    }
+    // Removes one occurrence of key. Returns false, changing nothing, if key is
+    // certainly not in the filter. Removing a key that was never inserted but is
+    // a false positive corrupts the filter, like in every counting Bloom filter.
+    pub fn remove(&mut self, key: u64) -> bool {
+        let cells: Vec<usize> = self.probes.of(key).collect();
+        if cells.iter().any(|cell| self.counter(*cell) == 0) {
+            return false;
+        }
+        for cell in cells {
+            let value: u8 = self.counter(cell);
+            if value < COUNTER_MAX {
+                self.set_counter(cell, value - 1);
+            }
+        }
+        return true;
+    }
+    pub fn contains(&self, key: u64) -> bool {
+        return self.probes.of(key).all(|cell| self.counter(cell) != 0);
+    }
+    // Number of inserts that drove a counter to COUNTER_MAX
+    pub fn overflows(&self) -> usize {
+        return self.overflows;
+    }
+    pub fn expected_fpr(&self, n: usize) -> f64 {
+        return self.probes.expected_fpr(n);
+    }
+    pub fn counter_count(&self) -> usize {
+        return self.probes.mask + 1;
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.counters.len() * size_of::<u64>();
+    }
+    // Probes | overflows (u64), followed by the number of words (u64) and the words of
+    // counters (u64)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new(SketchKind::CountingBloomFilter);
+        self.probes.write(&mut writer);
+        writer.u64(self.overflows as u64);
+        writer.u64(self.counters.len() as u64);
+        self.counters.iter().for_each(|word| writer.u64(*word));
+        return writer.finish();
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<CountingBloomFilter, DecodeError> {
+        let mut reader: ByteReader = ByteReader::new(bytes, SketchKind::CountingBloomFilter)?;
+        let probes: Probes = Probes::read(&mut reader, 4)?;
+        let overflows: usize = reader.u64()? as usize;
+        let counters: Vec<u64> = read_words(&mut reader, (probes.mask + 1) / 16)?;
+        reader.finish()?;
+        return Ok(CountingBloomFilter { counters, probes, overflows });
+    }
+}
+
+impl MemoryFootprint for CountingBloomFilter {
+    fn heap_bytes(&self) -> usize {
+        return self.space_usage();
+    }
+    fn allocations(&self) -> usize {
+        return vec_allocations(&self.counters);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn false_positive_rate_stays_near_the_target() {
+        let mut bloom: BloomFilter = BloomFilter::optimal(10_000, 0.01);
+        for key in 0..10_000u64 {
+            bloom.insert(key * 2);
+        }
+        assert!((0..10_000u64).all(|key| bloom.contains(key * 2)));
+        let false_positives: usize = (0..100_000u64).filter(|key| bloom.contains(key * 2 + 1)).count();
+        // Rounding the bits up to a power of two only lowers the expected rate
+        assert!(bloom.expected_fpr(10_000) <= 0.01);
+        assert!(false_positives < 2_000);
+    }
+
+    #[test]
+    fn scalable_filter_grows_within_its_bound() {
+        let mut scalable: ScalableBloomFilter = ScalableBloomFilter::new(1_000, 0.01);
+        for key in 0..100_000u64 {
+            scalable.insert(key * 2);
+        }
+        assert!(scalable.filter_count() > 3);
+        assert!((0..100_000u64).all(|key| scalable.contains(key * 2)));
+        assert!(scalable.fpr_bound() <= 0.01);
+        let false_positives: usize = (0..100_000u64).filter(|key| scalable.contains(key * 2 + 1)).count();
+        assert!(false_positives < 1_500);
+    }
+
+    #[test]
+    fn blocked_filter_trades_a_little_accuracy() {
+        let mut blocked: BlockedBloomFilter = BlockedBloomFilter::optimal(10_000, 0.01);
+        let mut bloom: BloomFilter = BloomFilter::optimal(10_000, 0.01);
+        for key in 0..10_000u64 {
+            blocked.insert(key * 2);
+            bloom.insert(key * 2);
+        }
+        assert!((0..10_000u64).all(|key| blocked.contains(key * 2)));
+        assert_eq!(blocked.space_usage(), bloom.space_usage());
+        assert!(blocked.expected_fpr(10_000) > bloom.expected_fpr(10_000));
+        assert!(blocked.expected_fpr(10_000) < 0.01);
+        let false_positives: usize = (0..100_000u64).filter(|key| blocked.contains(key * 2 + 1)).count();
+        assert!(false_positives < 2_000);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut bloom: BloomFilter = BloomFilter::optimal(1000, 0.01);
+        let mut blocked: BlockedBloomFilter = BlockedBloomFilter::optimal(1000, 0.01);
+        let mut counting: CountingBloomFilter = CountingBloomFilter::optimal(1000, 0.01);
+        for key in 0..1000u64 {
+            bloom.insert(key);
+            blocked.insert(key);
+            counting.insert(key);
+        }
+        let restored: BloomFilter = BloomFilter::from_bytes(&bloom.to_bytes()).unwrap();
+        assert_eq!(restored.fill_ratio(), bloom.fill_ratio());
+        assert!((0..10_000u64).all(|key| restored.contains(key) == bloom.contains(key)));
+        let restored: BlockedBloomFilter = BlockedBloomFilter::from_bytes(&blocked.to_bytes()).unwrap();
+        assert!((0..10_000u64).all(|key| restored.contains(key) == blocked.contains(key)));
+        let mut restored: CountingBloomFilter = CountingBloomFilter::from_bytes(&counting.to_bytes()).unwrap();
+        (0..500u64).for_each(|key| assert!(restored.remove(key)));
+        assert!((500..1000u64).all(|key| restored.contains(key)));
+
+        assert_eq!(BloomFilter::from_bytes(&counting.to_bytes()).err(), Some(DecodeError::WrongKind { expected: SketchKind::BloomFilter, found: SketchKind::CountingBloomFilter as u8 }));
+        let bytes: Vec<u8> = bloom.to_bytes();
+        assert_eq!(BloomFilter::from_bytes(&bytes[..bytes.len() - 1]).err(), Some(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn scalable_bytes_round_trip() {
+        let mut scalable: ScalableBloomFilter = ScalableBloomFilter::new(100, 0.01);
+        (0..1000u64).for_each(|key| scalable.insert(key));
+        let restored: ScalableBloomFilter = ScalableBloomFilter::from_bytes(&scalable.to_bytes()).unwrap();
+        assert_eq!(restored.filter_count(), scalable.filter_count());
+        assert!((0..10_000u64).all(|key| restored.contains(key) == scalable.contains(key)));
+    }
+
+    #[test]
+    fn counting_filter_forgets_removed_keys() {
+        let mut counting: CountingBloomFilter = CountingBloomFilter::optimal(10_000, 0.01);
+        for key in 0..10_000u64 {
+            assert!(counting.insert(key));
+        }
+        for key in 0..5_000u64 {
+            assert!(counting.remove(key));
+        }
+        assert!((5_000..10_000u64).all(|key| counting.contains(key)));
+        assert!((0..5_000u64).filter(|key| counting.contains(*key)).count() < 200);
+        assert_eq!(counting.overflows(), 0);
+        assert!(!counting.remove(20_000) || counting.contains(20_000));
+    }
+
+    #[test]
+    fn overflowing_counters_stick() {
+        let mut counting: CountingBloomFilter = CountingBloomFilter::new(4, 1);
+        for _ in 0..14 {
+            assert!(counting.insert(7));
+        }
+        assert!(!counting.insert(7));
+        counting.insert(7);
+        assert_eq!(counting.overflows(), 1);
+        for _ in 0..20 {
+            counting.remove(7);
+        }
+        assert!(counting.contains(7));
+    }
+
+    #[test]
+    fn set_algebra_estimates_the_overlap() {
+        // A = [0, 30000) and B = [20000, 50000) in filters sized for the union
+        let empty: BloomFilter = BloomFilter::optimal(50_000, 0.01);
+        let (mut a, mut b) = (empty.clone(), empty.clone());
+        (0..30_000u64).for_each(|key| a.insert(key));
+        (20_000..50_000u64).for_each(|key| b.insert(key));
+        assert!((a.estimate_cardinality() - 30_000.0).abs() < 600.0, "{}", a.estimate_cardinality());
+        let intersection: f64 = a.estimate_intersection(&b).unwrap();
+        assert!((intersection - 10_000.0).abs() < 600.0, "{}", intersection);
+
+        let union: BloomFilter = a.union(&b).unwrap();
+        assert!((0..50_000u64).all(|key| union.contains(key)));
+        assert!((union.estimate_cardinality() - 50_000.0).abs() < 1000.0, "{}", union.estimate_cardinality());
+        let mut merged: BloomFilter = a.clone();
+        merged.merge(&b).unwrap();
+        assert_eq!(merged.fill_ratio(), union.fill_ratio());
+        let common: BloomFilter = a.intersect(&b).unwrap();
+        assert!((20_000..30_000u64).all(|key| common.contains(key)));
+        assert!((40_000..50_000u64).filter(|key| common.contains(*key)).count() < 1000);
+
+        assert_eq!(a.union(&BloomFilter::optimal(50_000, 0.01)).err(), Some(MergeError::SeedMismatch));
+        assert_eq!(a.intersect(&BloomFilter::optimal(1000, 0.01)).err(), Some(MergeError::DimensionMismatch));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::hash::SeededHash;
+use crate::las_vegas::las_vegas;
+
+// Cells per key needed for peeling to succeed with high probability on three hash functions
+const SPACE_FACTOR: f64 = 1.23;
+
+// Bloomier filter: a static function from a key set to r-bit values in about 1.23r bits per key.
+// Every key hashes to one cell in each of three segments, and its value is the xor of those cells.
+// The key set itself is not stored, so keys outside of it map to arbitrary values.
+pub struct BloomierFilter {
+    cells: Vec<u64>,
+    value_bits: u32,
+    segment_len: usize,
+    hash_functions: [SeededHash; 3],
+    len: usize,
+}
+
+impl BloomierFilter {
+    // Builds the filter mapping keys[i] to values[i], of which only the low value_bits bits are kept.
+    // Keys must be distinct.
+    pub fn build(keys: &[u64], values: &[u64], value_bits: u32) -> BloomierFilter {
+        assert_eq!(keys.len(), values.len(), "Every key needs exactly one value");
+        assert!((1..=64).contains(&value_bits), "Values must be between 1 and 64 bits");
+        let mut sorted: Vec<u64> = keys.to_vec();
+        sorted.sort_unstable();
+        assert!(sorted.windows(2).all(|w| w[0] != w[1]), "Keys must be distinct");
+
+        let segment_len: usize = ((keys.len() as f64 * SPACE_FACTOR).ceil() as usize + 32).div_ceil(3);
+        // Retries with new hash functions until peeling succeeds
+        let (mut filter, order) = las_vegas(usize::MAX, || {
+            let filter: BloomierFilter = BloomierFilter {
+                cells: vec![0; (3 * segment_len * value_bits as usize).div_ceil(64)],
+                value_bits,
+                segment_len,
+                hash_functions: [SeededHash::new(64), SeededHash::new(64), SeededHash::new(64)],
+                len: keys.len(),
+            };
+            return filter.peel(keys).map(|order| (filter, order));
+        }).value.unwrap();
+        filter.assign(keys, &order, values);
+        return filter;
+    }
+    fn positions(&self, key: u64) -> [usize; 3] {
+        let mut positions: [usize; 3] = [0; 3];
+        for (i, hash_function) in self.hash_functions.iter().enumerate() {
+            let offset: usize = ((hash_function.hash(key) as u128 * self.segment_len as u128) >> 64) as usize;
+            positions[i] = i * self.segment_len + offset;
+        }
+        return positions;
+    }
+    // Repeatedly removes keys that are alone in one of their cells. Returns the removed keys
+    // with their lonely cell, or None if the hypergraph has a 2-core and new hash functions are needed.
+    fn peel(&self, keys: &[u64]) -> Option<Vec<(usize, usize)>> {
+        let cell_count: usize = 3 * self.segment_len;
+        let mut count: Vec<u32> = vec![0; cell_count];
+        let mut key_xor: Vec<usize> = vec![0; cell_count];
+        for (i, key) in keys.iter().enumerate() {
+            for pos in self.positions(*key) {
+                count[pos] += 1;
+                key_xor[pos] ^= i;
+            }
+        }
+
+        let mut queue: Vec<usize> = (0..cell_count).filter(|pos| count[*pos] == 1).collect();
+        let mut order: Vec<(usize, usize)> = Vec::with_capacity(keys.len());
+        while let Some(cell) = queue.pop() {
+            if count[cell] != 1 {
+                continue;
+            }
+            let i: usize = key_xor[cell];
+            order.push((i, cell));
+            for pos in self.positions(keys[i]) {
+                count[pos] -= 1;
+                key_xor[pos] ^= i;
+                if count[pos] == 1 {
+                    queue.push(pos);
+                }
+            }
+        }
+        if order.len() < keys.len() {
+            return None;
+        }
+        return Some(order);
+    }
+    // Sets the cells in reverse peeling order, so each key's lonely cell is written last
+    fn assign(&mut self, keys: &[u64], order: &[(usize, usize)], values: &[u64]) {
+        let mask: u64 = u64::MAX >> (64 - self.value_bits);
+        for (i, cell) in order.iter().rev() {
+            let mut value: u64 = values[*i] & mask;
+            for pos in self.positions(keys[*i]) {
+                if pos != *cell {
+                    value ^= self.cell(pos);
+                }
+            }
+            self.set_cell(*cell, value);
+        }
+    }
+    fn cell(&self, pos: usize) -> u64 {
+        let bit: usize = pos * self.value_bits as usize;
+        let (word, offset) = (bit / 64, bit % 64);
+        let mut value: u64 = self.cells[word] >> offset;
+        if offset + self.value_bits as usize > 64 {
+            value |= self.cells[word + 1] << (64 - offset);
+        }
+        return value & (u64::MAX >> (64 - self.value_bits));
+    }
+    fn set_cell(&mut self, pos: usize, value: u64) {
+        let mask: u64 = u64::MAX >> (64 - self.value_bits);
+        let bit: usize = pos * self.value_bits as usize;
+        let (word, offset) = (bit / 64, bit % 64);
+        self.cells[word] &= !(mask << offset);
+        self.cells[word] |= value << offset;
+        if offset + self.value_bits as usize > 64 {
+            let high: usize = 64 - offset;
+            self.cells[word + 1] &= !(mask >> high);
+            self.cells[word + 1] |= value >> high;
+        }
+    }
+    pub fn get(&self, key: u64) -> u64 {
+        let [a, b, c] = self.positions(key);
+        return self.cell(a) ^ self.cell(b) ^ self.cell(c);
+    }
+    pub fn bits_per_key(&self) -> f64 {
+        return (self.cells.len() * 64) as f64 / self.len.max(1) as f64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::random_generator;
+
+    #[test]
+    fn retrieves_every_value() {
+        for value_bits in [1, 7, 13, 64] {
+            let keys: Vec<u64> = (1..=10_000u64).map(|x| x * 2654435761).collect();
+            let values: Vec<u64> = keys.iter().map(|_| random_generator(0, u32::MAX) as u64 * 31).collect();
+            let filter: BloomierFilter = BloomierFilter::build(&keys, &values, value_bits);
+            let mask: u64 = u64::MAX >> (64 - value_bits);
+            for (key, value) in keys.iter().zip(&values) {
+                assert_eq!(filter.get(*key), value & mask);
+            }
+            assert!(filter.bits_per_key() < 1.3 * value_bits as f64);
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::size_of;
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::{AtomicU64, Ordering};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::counter::Counter;
+use crate::hash::SeededHash;
+use crate::memory::{vec_allocations, MemoryFootprint};
+use crate::mergeable::{MergeError, MergeableSketch};
+use crate::sketch_format::{ByteReader, ByteWriter, DecodeError, SketchKind};
+
+// How an update spreads over the rows of a Count-Min sketch
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum UpdatePolicy {
+    // Every row adds the full count
+    Standard,
+    // Rows only grow as far as needed to lift the minimum by the count, which keeps
+    // estimates closer to the truth. Only valid when counts are never decreased.
+    Conservative,
+}
+
+// Count-Min sketch for streams of non-negative counts. Each of the depth rows has its
+// own hash function into 2^width_len counters, and a key is estimated by the smallest
+// of its counters, which never underestimates the true count. Counters are u64 unless
+// another Counter type is picked, such as u32 for narrow counters or f64 for weights.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CountMin<C: Counter = u64> {
+    counters: Vec<C>,
+    hash_functions: Vec<SeededHash>,
+    width: usize,
+    policy: UpdatePolicy,
+}
+
+impl<C: Counter> CountMin<C> {
+    #[cfg(feature = "std")]
+    pub fn new(depth: usize, width_len: u32, policy: UpdatePolicy) -> CountMin<C> {
+        return CountMin::with_rng(depth, width_len, policy, &mut rand::thread_rng());
+    }
+    // With the row hashes drawn from rng, for builds without std
+    pub fn with_rng(depth: usize, width_len: u32, policy: UpdatePolicy, rng: &mut impl Rng) -> CountMin<C> {
+        let width: usize = 1 << width_len;
+        return CountMin {
+            counters: vec![C::default(); depth * width],
+            hash_functions: (0..depth).map(|_| SeededHash::with_rng(width_len, rng)).collect(),
+            width,
+            policy,
+        }
+    }
+    // Index into counters of the cell key hashes to in every row
+    fn cells(&self, key: u64) -> impl Iterator<Item = usize> + '_ {
+        return self.hash_functions.iter().enumerate().map(move |(row, hash_function)| row * self.width + hash_function.hash(key));
+    }
+    // Replaces the counter of key in every row by update of it. Borrows the hash functions
+    // and the counters apart, so updates need no buffer of cells.
+    fn update_cells(&mut self, key: u64, update: impl Fn(C) -> C) {
+        for (row, hash_function) in self.hash_functions.iter().enumerate() {
+            let counter: &mut C = &mut self.counters[row * self.width + hash_function.hash(key)];
+            *counter = update(*counter);
+        }
+    }
+    pub fn insert(&mut self, key: u64, count: C) {
+        match self.policy {
+            UpdatePolicy::Standard => self.update_cells(key, |counter| counter.add(count)),
+            UpdatePolicy::Conservative => {
+                let target: C = self.estimate(key).add(count);
+                self.update_cells(key, |counter| if counter < target { target } else { counter });
+            }
+        }
+    }
+    // Takes back count earlier inserts of key. Estimates still never fall below the true
+    // count as long as no key's count goes negative.
+    pub fn remove(&mut self, key: u64, count: C) {
+        assert_eq!(self.policy, UpdatePolicy::Standard, "Conservative updates cannot be undone");
+        self.update_cells(key, |counter| counter.sub(count));
+    }
+    // Multiplies every counter by factor, rounding at random so counters keep their expected
+    // value. Estimates then only overestimate the scaled counts in expectation.
+    #[cfg(feature = "std")]
+    pub fn scale(&mut self, factor: f64) {
+        for counter in &mut self.counters {
+            *counter = counter.scale(factor);
+        }
+    }
+    pub fn estimate(&self, key: u64) -> C {
+        return self.cells(key)
+            .map(|cell| self.counters[cell])
+            .reduce(|smallest, counter| if counter < smallest { counter } else { smallest })
+            .unwrap_or_default();
+    }
+    // Sum of all counts, read off the first row. Exact for standard updates, while
+    // conservative rows add up to less.
+    pub fn total(&self) -> C {
+        return self.counters[..self.width].iter().fold(C::default(), |sum, counter| sum.add(*counter));
+    }
+    pub fn policy(&self) -> UpdatePolicy {
+        return self.policy;
+    }
+    pub fn depth(&self) -> usize {
+        return self.hash_functions.len();
+    }
+    pub fn width(&self) -> usize {
+        return self.width;
+    }
+    // Heap footprint of the counters in bytes
+    pub fn space_usage(&self) -> usize {
+        return self.counters.len() * size_of::<C>();
+    }
+}
+
+impl<C: Counter> MemoryFootprint for CountMin<C> {
+    fn heap_bytes(&self) -> usize {
+        return self.space_usage();
+    }
+    fn allocations(&self) -> usize {
+        return vec_allocations(&self.counters);
+    }
+}
+
+impl CountMin {
+    // policy (u8, 0 standard, 1 conservative) | depth (u32) | one hash per row |
+    // number of counters (u64) | counters (u64), row after row
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new(SketchKind::CountMin);
+        writer.u8(match self.policy { UpdatePolicy::Standard => 0, UpdatePolicy::Conservative => 1 });
+        writer.u32(self.hash_functions.len() as u32);
+        self.hash_functions.iter().for_each(|hash_function| writer.seeded_hash(hash_function));
+        writer.u64(self.counters.len() as u64);
+        self.counters.iter().for_each(|counter| writer.u64(*counter));
+        return writer.finish();
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<CountMin, DecodeError> {
+        let mut reader: ByteReader = ByteReader::new(bytes, SketchKind::CountMin)?;
+        let policy: UpdatePolicy = match reader.u8()? {
+            0 => UpdatePolicy::Standard,
+            1 => UpdatePolicy::Conservative,
+            _ => return Err(DecodeError::Invalid("unknown update policy")),
+        };
+        let depth: usize = reader.u32()? as usize;
+        if depth == 0 {
+            return Err(DecodeError::Invalid("no rows"));
+        }
+        let hash_functions: Vec<SeededHash> = (0..depth).map(|_| reader.seeded_hash()).collect::<Result<_, _>>()?;
+        let len: usize = reader.len(size_of::<u64>())?;
+        let counters: Vec<u64> = (0..len).map(|_| reader.u64()).collect::<Result<_, _>>()?;
+        reader.finish()?;
+        let width_len: u32 = hash_functions.first().map_or(0, |hash_function| hash_function.hash_len());
+        if width_len >= usize::BITS
+            || hash_functions.iter().any(|hash_function| hash_function.hash_len() != width_len)
+            || counters.len() != depth << width_len {
+            return Err(DecodeError::Invalid("counters do not match the row hashes"));
+        }
+        return Ok(CountMin { counters, hash_functions, width: 1 << width_len, policy });
+    }
+}
+
+// Adding up the counters gives the standard sketch of both streams. For conservative
+// sketches the sum still never underestimates, it is just less tight than updating
+// one sketch with both streams.
+impl<C: Counter> MergeableSketch for CountMin<C> {
+    fn merge(&mut self, other: &CountMin<C>) -> Result<(), MergeError> {
+        if self.counters.len() != other.counters.len() || self.width != other.width {
+            return Err(MergeError::DimensionMismatch);
+        }
+        if self.hash_functions != other.hash_functions {
+            return Err(MergeError::SeedMismatch);
+        }
+        for (counter, other_counter) in self.counters.iter_mut().zip(&other.counters) {
+            *counter = counter.add(*other_counter);
+        }
+        return Ok(());
+    }
+}
+
+// Count-Min sketch that any number of threads can update through a shared reference. Each
+// update is a relaxed fetch_add per row, since counters are only ever summed and no other
+// memory depends on them, so concurrent inserts add up to the same counters as a
+// sequential run. Only the standard policy is supported: conservative updates read the
+// minimum before writing, and two threads racing on one key would lose an increment.
+// Targets without 64-bit atomics, like many microcontrollers, go without it.
+#[cfg(target_has_atomic = "64")]
+pub struct AtomicCountMin {
+    counters: Vec<AtomicU64>,
+    hash_functions: Vec<SeededHash>,
+    width: usize,
+}
+
+#[cfg(target_has_atomic = "64")]
+impl AtomicCountMin {
+    #[cfg(feature = "std")]
+    pub fn new(depth: usize, width_len: u32) -> AtomicCountMin {
+        return AtomicCountMin::from_sketch(CountMin::new(depth, width_len, UpdatePolicy::Standard));
+    }
+    // Continues from the counters and seeds of a sequential sketch
+    pub fn from_sketch(sketch: CountMin) -> AtomicCountMin {
+        assert_eq!(sketch.policy, UpdatePolicy::Standard, "Conservative updates cannot be made atomic");
+        return AtomicCountMin {
+            counters: sketch.counters.into_iter().map(AtomicU64::new).collect(),
+            hash_functions: sketch.hash_functions,
+            width: sketch.width,
+        }
+    }
+    // Copy of the counters as a sequential sketch, for merging or serializing. Inserts that
+    // run concurrently with the copy may be only partly included.
+    pub fn to_sketch(&self) -> CountMin {
+        return CountMin {
+            counters: self.counters.iter().map(|counter| counter.load(Ordering::Relaxed)).collect(),
+            hash_functions: self.hash_functions.clone(),
+            width: self.width,
+            policy: UpdatePolicy::Standard,
+        }
+    }
+    pub fn insert(&self, key: u64, count: u64) {
+        for (row, hash_function) in self.hash_functions.iter().enumerate() {
+            self.counters[row * self.width + hash_function.hash(key)].fetch_add(count, Ordering::Relaxed);
+        }
+    }
+    pub fn estimate(&self, key: u64) -> u64 {
+        return self.hash_functions.iter().enumerate()
+            .map(|(row, hash_function)| self.counters[row * self.width + hash_function.hash(key)].load(Ordering::Relaxed))
+            .min()
+            .unwrap_or(0);
+    }
+    pub fn depth(&self) -> usize {
+        return self.hash_functions.len();
+    }
+    pub fn width(&self) -> usize {
+        return self.width;
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.counters.len() * size_of::<AtomicU64>();
+    }
+}
+
+#[cfg(target_has_atomic = "64")]
+impl MemoryFootprint for AtomicCountMin {
+    fn heap_bytes(&self) -> usize {
+        return self.space_usage();
+    }
+    fn allocations(&self) -> usize {
+        return vec_allocations(&self.counters);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn conservative_updates_never_overestimate_more_than_standard() {
+        let mut standard: CountMin = CountMin::new(4, 6, UpdatePolicy::Standard);
+        let mut conservative: CountMin = CountMin { policy: UpdatePolicy::Conservative, ..standard.clone() };
+        // Key k appears k times
+        for key in 1..=500u64 {
+            standard.insert(key, key);
+            conservative.insert(key, key);
+        }
+        for key in 1..=500u64 {
+            assert!(conservative.estimate(key) >= key);
+            assert!(conservative.estimate(key) <= standard.estimate(key));
+        }
+        let standard_error: u64 = (1..=500u64).map(|key| standard.estimate(key) - key).sum();
+        let conservative_error: u64 = (1..=500u64).map(|key| conservative.estimate(key) - key).sum();
+        assert!(conservative_error < standard_error);
+    }
+
+    #[test]
+    fn merged_shards_match_one_sketch() {
+        let mut whole: CountMin = CountMin::new(3, 5, UpdatePolicy::Standard);
+        let mut left: CountMin = whole.clone();
+        let mut right: CountMin = whole.clone();
+        for key in 0..1000u64 {
+            whole.insert(key, key % 7);
+            if key < 500 {
+                left.insert(key, key % 7);
+            } else {
+                right.insert(key, key % 7);
+            }
+        }
+        left.merge(&right).unwrap();
+        assert!((0..1000).all(|key| left.estimate(key) == whole.estimate(key)));
+        assert_eq!(left.merge(&CountMin::new(3, 5, UpdatePolicy::Standard)).err(), Some(MergeError::SeedMismatch));
+        assert_eq!(left.merge(&CountMin::new(3, 4, UpdatePolicy::Standard)).err(), Some(MergeError::DimensionMismatch));
+        // Collectors drawing their hash functions from the same seed merge without first
+        // sharing an empty sketch
+        let seeded = || -> CountMin { CountMin::with_rng(3, 5, UpdatePolicy::Standard, &mut StdRng::seed_from_u64(7)) };
+        let mut collector: CountMin = seeded();
+        collector.insert(1, 2);
+        assert!(collector.merge(&seeded()).is_ok() && collector.estimate(1) == 2);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut sketch: CountMin = CountMin::new(4, 5, UpdatePolicy::Conservative);
+        for key in 0..1000u64 {
+            sketch.insert(key, key % 13);
+        }
+        let bytes: Vec<u8> = sketch.to_bytes();
+        let decoded: CountMin = CountMin::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.policy(), UpdatePolicy::Conservative);
+        assert!((0..1000).all(|key| decoded.estimate(key) == sketch.estimate(key)));
+        assert_eq!(CountMin::from_bytes(&bytes[..20]).err(), Some(DecodeError::Truncated));
+        let empty: Vec<u8> = CountMin::<u64>::new(0, 5, UpdatePolicy::Standard).to_bytes();
+        assert_eq!(CountMin::from_bytes(&empty).err(), Some(DecodeError::Invalid("no rows")));
+    }
+
+    #[test]
+    fn concurrent_inserts_match_a_sequential_run() {
+        let mut sequential: CountMin = CountMin::new(4, 8, UpdatePolicy::Standard);
+        let concurrent: AtomicCountMin = AtomicCountMin::from_sketch(sequential.clone());
+        let keys: Vec<u64> = (0..100_000u64).map(|x| x * x % 5003).collect();
+        std::thread::scope(|s| {
+            for chunk in keys.chunks(12_500) {
+                let concurrent: &AtomicCountMin = &concurrent;
+                s.spawn(move || chunk.iter().for_each(|key| concurrent.insert(*key, key % 3 + 1)));
+            }
+        });
+        keys.iter().for_each(|key| sequential.insert(*key, key % 3 + 1));
+        assert_eq!(concurrent.to_sketch().counters, sequential.counters);
+        assert!((0..5003).all(|key| concurrent.estimate(key) == sequential.estimate(key)));
+    }
+}
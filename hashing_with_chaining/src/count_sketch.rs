@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use crate::mergeable::{MergeError, MergeableSketch};
+use crate::memory::{vec_allocations, MemoryFootprint};
+use crate::norm_sketch::NormSketch;
+use crate::sketch_format::{ByteReader, ByteWriter, DecodeError, SketchKind};
+
+// Count Sketch with depth independent NormSketch rows. Taking the median over the rows
+// turns the unbiased but noisy estimates of a single row into estimates that are
+// accurate with high probability, for point queries as well as for the norm.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CountSketch {
+    rows: Vec<NormSketch>,
+}
+
+// Median of the values, averaging the two middle ones for an even count. Works in
+// 128 bits so that both signed estimates and u64 norms fit.
+fn median(mut values: Vec<i128>) -> i128 {
+    values.sort_unstable();
+    let mid: usize = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        return (values[mid - 1] + values[mid]) / 2;
+    }
+    return values[mid];
+}
+
+impl CountSketch {
+    pub fn new(depth: usize, width_len: u32) -> CountSketch {
+        assert!(depth > 0, "A Count Sketch needs at least one row");
+        return CountSketch {
+            rows: (0..depth).map(|_| NormSketch::new(width_len)).collect(),
+        }
+    }
+    pub fn insert(&mut self, key: u64, delta: i64) {
+        for row in &mut self.rows {
+            row.insert(key, delta);
+        }
+    }
+    // Median over the rows of the signed counter key hashes to
+    pub fn estimate(&self, key: u64) -> i64 {
+        return median(self.rows.iter().map(|row| row.estimate(key) as i128).collect()) as i64;
+    }
+    // Median over the rows of their F2 estimates
+    pub fn norm(&self) -> u64 {
+        return median(self.rows.iter().map(|row| row.saturating_norm() as i128).collect()) as u64;
+    }
+    pub fn depth(&self) -> usize {
+        return self.rows.len();
+    }
+    pub fn width(&self) -> usize {
+        return self.rows[0].width();
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.rows.iter().map(|row| row.space_usage()).sum();
+    }
+    // depth (u32) followed by every row as a NormSketch without header
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new(SketchKind::CountSketch);
+        writer.u32(self.rows.len() as u32);
+        self.rows.iter().for_each(|row| row.write_body(&mut writer));
+        return writer.finish();
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<CountSketch, DecodeError> {
+        let mut reader: ByteReader = ByteReader::new(bytes, SketchKind::CountSketch)?;
+        let depth: usize = reader.u32()? as usize;
+        let rows: Vec<NormSketch> = (0..depth).map(|_| NormSketch::read_body(&mut reader)).collect::<Result<_, _>>()?;
+        reader.finish()?;
+        if rows.is_empty() || rows.iter().any(|row| row.width() != rows[0].width()) {
+            return Err(DecodeError::Invalid("rows of a Count Sketch must share one width"));
+        }
+        return Ok(CountSketch { rows });
+    }
+}
+
+impl MemoryFootprint for CountSketch {
+    fn heap_bytes(&self) -> usize {
+        return self.space_usage();
+    }
+    fn allocations(&self) -> usize {
+        return vec_allocations(&self.rows) + self.rows.iter().map(|row| row.allocations()).sum::<usize>();
+    }
+}
+
+impl MergeableSketch for CountSketch {
+    fn merge(&mut self, other: &CountSketch) -> Result<(), MergeError> {
+        if self.rows.len() != other.rows.len() {
+            return Err(MergeError::DimensionMismatch);
+        }
+        // Check every row before touching any, so a failed merge leaves self unchanged
+        for (row, other_row) in self.rows.iter().zip(&other.rows) {
+            row.check_mergeable(other_row)?;
+        }
+        for (row, other_row) in self.rows.iter_mut().zip(&other.rows) {
+            row.merge(other_row)?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heavy_keys_are_estimated_closely() {
+        let mut sketch: CountSketch = CountSketch::new(5, 10);
+        let mut norm: u64 = 0;
+        for key in 0..10_000u64 {
+            sketch.insert(key, 1);
+            norm += 1;
+        }
+        sketch.insert(7, 4999);
+        sketch.insert(8, -3000);
+        norm += 5000 * 5000 - 1 + 2999 * 2999 - 1;
+        // The light keys add noise of about sqrt(10_000 / 1024) per row
+        assert!((sketch.estimate(7) - 5000).abs() < 100);
+        assert!((sketch.estimate(8) + 2999).abs() < 100);
+        let relative_error: f64 = (sketch.norm() as f64 - norm as f64).abs() / norm as f64;
+        assert!(relative_error < 0.1);
+    }
+
+    #[test]
+    fn merged_shards_match_one_sketch() {
+        let mut whole: CountSketch = CountSketch::new(3, 6);
+        let mut left: CountSketch = whole.clone();
+        let mut right: CountSketch = whole.clone();
+        for key in 0..1000u64 {
+            whole.insert(key, key as i64 % 11 - 5);
+            if key % 2 == 0 {
+                left.insert(key, key as i64 % 11 - 5);
+            } else {
+                right.insert(key, key as i64 % 11 - 5);
+            }
+        }
+        left.merge(&right).unwrap();
+        assert!((0..1000).all(|key| left.estimate(key) == whole.estimate(key)));
+        assert_eq!(left.norm(), whole.norm());
+
+        assert_eq!(left.merge(&CountSketch::new(3, 6)).err(), Some(MergeError::SeedMismatch));
+        assert_eq!(left.merge(&CountSketch::new(3, 5)).err(), Some(MergeError::DimensionMismatch));
+        assert_eq!(left.merge(&CountSketch::new(2, 6)).err(), Some(MergeError::DimensionMismatch));
+        assert_eq!(left.norm(), whole.norm());
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut sketch: CountSketch = CountSketch::new(3, 6);
+        for key in 0..1000u64 {
+            sketch.insert(key, 3);
+        }
+        let mut decoded: CountSketch = CountSketch::from_bytes(&sketch.to_bytes()).unwrap();
+        assert!((0..1000).all(|key| decoded.estimate(key) == sketch.estimate(key)));
+        decoded.merge(&sketch).unwrap();
+        assert_eq!(decoded.estimate(7), 2 * sketch.estimate(7));
+        assert!(matches!(CountSketch::from_bytes(&sketch.rows[0].to_bytes()), Err(DecodeError::WrongKind { .. })));
+    }
+}
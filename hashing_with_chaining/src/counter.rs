@@ -0,0 +1,190 @@
+use core::fmt::Debug;
+use core::num::Saturating;
+use core::ops::{Add, Sub};
+#[cfg(feature = "std")]
+use rand::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// Counter type of NormSketch, CountMin and HwC. The plain integer types wrap on overflow:
+// sums modulo 2^bits are still exact, so a counter of a linear sketch comes out right
+// whenever its final value fits, even if partial sums overflowed on the way. Saturating
+// integers clamp at their bounds instead, which keeps a huge counter huge but is no longer
+// exact once it clamped. f64 counters take fractional weights.
+pub trait Counter: Copy + Default + PartialEq + PartialOrd + Debug + Send + Sync {
+    // Type the squares of counters are summed in for norms, exact for the integer counters
+    // as long as the sum fits
+    type Square: Copy + Default + PartialEq + Debug + Add<Output = Self::Square> + Sub<Output = Self::Square>
+        + Send + Sync + Serialize + DeserializeOwned;
+    fn add(self, other: Self) -> Self;
+    fn sub(self, other: Self) -> Self;
+    fn square(self) -> Self::Square;
+    // The counter multiplied by factor. Integers round up or down at random, with the chance
+    // of rounding up being the fractional part, so the expected result is the exact product
+    // and small counters still reach zero, which deterministic rounding would keep at one.
+    // The random rounding draws from thread_rng, so it needs std.
+    #[cfg(feature = "std")]
+    fn scale(self, factor: f64) -> Self;
+    fn to_f64(self) -> f64;
+    fn is_zero(self) -> bool {
+        return self == Self::default();
+    }
+}
+
+#[cfg(feature = "std")]
+fn round_randomly(scaled: f64) -> f64 {
+    let floor: f64 = scaled.floor();
+    return floor + (thread_rng().gen::<f64>() < scaled - floor) as u8 as f64;
+}
+
+macro_rules! wrapping_counter {
+    ($counter:ty, $square:ty) => {
+        impl Counter for $counter {
+            type Square = $square;
+            fn add(self, other: $counter) -> $counter {
+                return self.wrapping_add(other);
+            }
+            fn sub(self, other: $counter) -> $counter {
+                return self.wrapping_sub(other);
+            }
+            fn square(self) -> $square {
+                return (self.unsigned_abs() as $square).wrapping_pow(2);
+            }
+            #[cfg(feature = "std")]
+            fn scale(self, factor: f64) -> $counter {
+                return round_randomly(self as f64 * factor) as $counter;
+            }
+            fn to_f64(self) -> f64 {
+                return self as f64;
+            }
+        }
+    };
+}
+
+macro_rules! wrapping_unsigned_counter {
+    ($counter:ty, $square:ty) => {
+        impl Counter for $counter {
+            type Square = $square;
+            fn add(self, other: $counter) -> $counter {
+                return self.wrapping_add(other);
+            }
+            fn sub(self, other: $counter) -> $counter {
+                return self.wrapping_sub(other);
+            }
+            fn square(self) -> $square {
+                return (self as $square).wrapping_pow(2);
+            }
+            #[cfg(feature = "std")]
+            fn scale(self, factor: f64) -> $counter {
+                return round_randomly(self as f64 * factor) as $counter;
+            }
+            fn to_f64(self) -> f64 {
+                return self as f64;
+            }
+        }
+    };
+}
+
+macro_rules! saturating_counter {
+    ($counter:ty, $square:ty) => {
+        impl Counter for Saturating<$counter> {
+            type Square = Saturating<$square>;
+            fn add(self, other: Saturating<$counter>) -> Saturating<$counter> {
+                return self + other;
+            }
+            fn sub(self, other: Saturating<$counter>) -> Saturating<$counter> {
+                return self - other;
+            }
+            fn square(self) -> Saturating<$square> {
+                let magnitude: Saturating<$square> = Saturating(self.0.unsigned_abs() as $square);
+                return magnitude * magnitude;
+            }
+            // Float to integer casts saturate, so scaling cannot wrap either
+            #[cfg(feature = "std")]
+            fn scale(self, factor: f64) -> Saturating<$counter> {
+                return Saturating(round_randomly(self.0 as f64 * factor) as $counter);
+            }
+            fn to_f64(self) -> f64 {
+                return self.0 as f64;
+            }
+        }
+    };
+}
+
+wrapping_counter!(i32, u64);
+wrapping_counter!(i64, u64);
+wrapping_counter!(i128, u128);
+wrapping_unsigned_counter!(u32, u64);
+wrapping_unsigned_counter!(u64, u64);
+saturating_counter!(i32, u64);
+saturating_counter!(i64, u64);
+
+impl Counter for f64 {
+    type Square = f64;
+    fn add(self, other: f64) -> f64 {
+        return self + other;
+    }
+    fn sub(self, other: f64) -> f64 {
+        return self - other;
+    }
+    fn square(self) -> f64 {
+        return self * self;
+    }
+    #[cfg(feature = "std")]
+    fn scale(self, factor: f64) -> f64 {
+        return self * factor;
+    }
+    fn to_f64(self) -> f64 {
+        return self;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::count_min::{CountMin, UpdatePolicy};
+    use crate::hwc::{ArenaChains, HwC, InlineChains};
+    use crate::norm_sketch::NormSketch;
+
+    #[test]
+    fn narrow_counters_match_wide_ones_when_the_result_fits() {
+        let mut wide: HwC = HwC::new(64);
+        let mut narrow: HwC<InlineChains<i32>> = HwC::new(64);
+        let mut sketch: NormSketch<i32> = NormSketch::new(6);
+        // Counts reach 3 * 2^30 and overflow an i32 on the way, then come back down. The
+        // squares of the wrapped counts are below 2^62, so three keys keep the norm in a u64.
+        for delta in [1 << 30, 1 << 30, 1 << 30, -(1 << 30), -(1 << 30), -(1 << 30)] {
+            (0..3u64).for_each(|key| narrow.insert(key, delta));
+            (0..1000u64).for_each(|key| sketch.insert(key, delta));
+        }
+        assert_eq!((narrow.stats().entries, sketch.norm_f64()), (0, 0.0));
+        for key in 0..1000u64 {
+            wide.insert(key, key as i64 % 7 - 3);
+            narrow.insert(key, key as i32 % 7 - 3);
+        }
+        assert!((0..1000u64).all(|key| wide.get(key) == narrow.get(key) as i64));
+        assert_eq!(wide.norm(), narrow.norm());
+        assert_eq!(sketch.space_usage() * 2, NormSketch::<i64>::new(6).space_usage());
+
+        let mut clamped: NormSketch<Saturating<i32>> = NormSketch::new(0);
+        (0..4).for_each(|_| clamped.insert(1, Saturating(1 << 30)));
+        assert_eq!(clamped.estimate(1).0, i32::MAX);
+    }
+
+    #[test]
+    fn float_counters_take_weighted_updates() {
+        let mut sketch: CountMin<f64> = CountMin::new(4, 10, UpdatePolicy::Conservative);
+        let mut inline: HwC<InlineChains<f64>> = HwC::new(256);
+        let mut arena: HwC<ArenaChains<f64>> = HwC::new(256);
+        for key in 0..500u64 {
+            sketch.insert(key, key as f64 * 0.25);
+            inline.insert(key, key as f64 * 0.25);
+            arena.insert(key, 0.5);
+            arena.insert(key, -0.5);
+        }
+        assert!((0..500u64).all(|key| sketch.estimate(key) >= inline.get(key)));
+        assert_eq!(inline.get(10), 2.5);
+        assert_eq!(inline.norm(), inline.get_norm());
+        assert_eq!((arena.stats().entries, arena.norm()), (0, 0.0));
+    }
+}
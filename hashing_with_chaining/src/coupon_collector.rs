@@ -0,0 +1,103 @@
+use rand::prelude::*;
+use crate::monte_carlo::Estimator;
+
+pub fn harmonic(n: usize) -> f64 {
+    return (1..=n).map(|i| 1.0 / i as f64).sum();
+}
+
+// Uniform draws from n coupons until every coupon has come up copies times
+pub fn draws_to_collect(coupons: usize, copies: usize, rng: &mut impl Rng) -> usize {
+    let mut counts: Vec<usize> = vec![0; coupons];
+    let mut incomplete: usize = if copies == 0 { 0 } else { coupons };
+    let mut draws: usize = 0;
+    while incomplete > 0 {
+        let coupon: usize = rng.gen_range(0..coupons);
+        counts[coupon] += 1;
+        if counts[coupon] == copies {
+            incomplete -= 1;
+        }
+        draws += 1;
+    }
+    return draws;
+}
+
+// Expected draws to collect every coupon once: with i coupons still missing a draw finds a
+// new one with probability i / n, so the wait for it is geometric with mean n / i and the
+// total is n H_n ≈ n ln n + γ n
+pub fn expected_draws(coupons: usize) -> f64 {
+    return coupons as f64 * harmonic(coupons);
+}
+
+// Expected draws to collect every coupon copies times. If the draws come as a Poisson
+// process of rate n, every coupon arrives at rate 1 independently of the others, so the
+// collection is done by time t with probability (1 - e^-t S_k(t))^n, where S_k(t) =
+// sum_{i < k} t^i / i! and e^-t S_k(t) is the chance of fewer than k arrivals. The expected
+// time is the integral of the complement, and n times it is the expected number of draws.
+// Simpson's rule evaluates it. For k >= 2 this grows like n (ln n + (k - 1) ln ln n + γ -
+// ln (k - 1)!) (Newman and Shepp, Erdős and Rényi), but with an o(n) term that is still
+// several percent at n = 1000.
+pub fn expected_draws_with_copies(coupons: usize, copies: usize) -> f64 {
+    if copies == 0 {
+        return 0.0;
+    }
+    let n: f64 = coupons as f64;
+    let unfinished = |t: f64| -> f64 {
+        let mut term: f64 = 1.0;
+        let mut below_k: f64 = 1.0;
+        for i in 1..copies {
+            term *= t / i as f64;
+            below_k += term;
+        }
+        return 1.0 - (1.0 - (-t).exp() * below_k).powf(n);
+    };
+    // Well past the point where all coupons are collected with probability 1 - e^-40
+    let end: f64 = n.ln() + copies as f64 * (n.ln() + copies as f64).ln() + 40.0;
+    let steps: usize = 100_000;
+    let h: f64 = end / steps as f64;
+    let inner: f64 = (1..steps).map(|i| if i % 2 == 1 { 4.0 } else { 2.0 } * unfinished(i as f64 * h)).sum();
+    return n * h / 3.0 * (unfinished(0.0) + inner + unfinished(end));
+}
+
+// Limit of the chance that collecting all n coupons takes more than n ln n + c n draws,
+// 1 - e^(-e^-c), as the number of missing coupons after that many draws tends to a
+// Poisson variable with mean e^-c
+pub fn tail_probability(c: f64) -> f64 {
+    return 1.0 - (-(-c).exp()).exp();
+}
+
+// The draws of one collection as a Monte Carlo estimator, so the means come with standard
+// errors and confidence intervals
+pub struct CouponCollector {
+    pub coupons: usize,
+    pub copies: usize,
+}
+
+impl Estimator for CouponCollector {
+    fn sample(&mut self, rng: &mut ThreadRng) -> f64 {
+        return draws_to_collect(self.coupons, self.copies, rng) as f64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monte_carlo::Estimate;
+
+    #[test]
+    fn draws_match_the_harmonic_prediction() {
+        let estimate: Estimate = CouponCollector { coupons: 100, copies: 1 }.estimate(20_000);
+        assert!((estimate.mean - expected_draws(100)).abs() < 5.0 * estimate.standard_error, "{:?}", estimate);
+
+        // 1 - e^(-e^-1) ≈ 0.31 of the collections of 1000 coupons take over n ln n + n draws,
+        // up to a finite n correction of about 0.01
+        let mut rng = thread_rng();
+        let threshold: f64 = 1000.0 * (1000f64.ln() + 1.0);
+        let over: usize = (0..5000).filter(|_| draws_to_collect(1000, 1, &mut rng) as f64 > threshold).count();
+        assert!((over as f64 / 5000.0 - tail_probability(1.0)).abs() < 0.04, "{}", over);
+
+        // The integral agrees with n H_n for one copy
+        assert!((expected_draws_with_copies(1000, 1) - expected_draws(1000)).abs() < 1e-3);
+        let doubled: Estimate = CouponCollector { coupons: 1000, copies: 2 }.estimate(2000);
+        assert!((doubled.mean - expected_draws_with_copies(1000, 2)).abs() < 5.0 * doubled.standard_error, "{:?}", doubled);
+    }
+}
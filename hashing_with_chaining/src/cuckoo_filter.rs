@@ -0,0 +1,285 @@
+use std::mem::size_of;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::hash::{random_generator, IndependentHash, SeededHash};
+use crate::memory::{vec_allocations, MemoryFootprint};
+use crate::sketch_format::{ByteReader, ByteWriter, DecodeError, SketchKind};
+
+// Slots per bucket
+const BUCKET_LEN: usize = 4;
+// Evictions an insert may do before the filter counts as full
+const MAX_KICKS: usize = 500;
+// Load that optimal sizes for, safely below the 95% at which inserts into buckets of
+// four slots start to fail
+const TARGET_LOAD: f64 = 0.85;
+// Fewest buckets optimal gives, as a handful of buckets can fill up well below that load
+const MIN_BUCKETS: usize = 64;
+// Marks an empty slot, fingerprints are never 0
+const EMPTY: u16 = 0;
+
+// Log2 of the number of buckets and the fingerprint length for n keys at a false positive
+// rate of fpr: room at a load of at most 85%, with fingerprints long enough that the 2 * 4
+// fingerprints a lookup compares against collide with probability at most fpr
+pub fn optimal_dimensions(n: usize, fpr: f64) -> (u32, u32) {
+    assert!(fpr > 0.0 && fpr < 1.0, "False positive rate must be in (0, 1)");
+    let buckets: usize = ((n as f64 / (BUCKET_LEN as f64 * TARGET_LOAD)).ceil() as usize).max(MIN_BUCKETS).next_power_of_two();
+    let fingerprint_bits: u32 = ((2.0 * BUCKET_LEN as f64 / fpr).log2().ceil() as u32).clamp(1, 16);
+    return (buckets.ilog2(), fingerprint_bits);
+}
+
+// Cuckoo filter (Fan et al.) storing a fingerprint of up to 16 bits per key in
+// buckets of four slots. The two candidate buckets of a key are i1 = h(key) and
+// i2 = i1 xor h(fingerprint), so either bucket can be computed from the other and the
+// fingerprint alone, which is what lets fingerprints be moved and deleted.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CuckooFilter {
+    slots: Vec<u16>,
+    bucket_hash: IndependentHash,
+    fingerprint_hash: IndependentHash,
+    // Hashes a fingerprint to the offset between the two buckets of a key
+    alternate_hash: SeededHash,
+    fingerprint_bits: u32,
+    mask: usize,
+    len: usize,
+}
+
+impl CuckooFilter {
+    pub fn new(buckets_len: u32, fingerprint_bits: u32) -> CuckooFilter {
+        return CuckooFilter::with_rng(buckets_len, fingerprint_bits, &mut thread_rng());
+    }
+    // Hash functions drawn from rng. The evictions of a full bucket still pick their
+    // victims with the thread's generator.
+    pub fn with_rng(buckets_len: u32, fingerprint_bits: u32, rng: &mut impl Rng) -> CuckooFilter {
+        assert!((1..=16).contains(&fingerprint_bits), "Fingerprints must have 1 to 16 bits");
+        return CuckooFilter {
+            slots: vec![EMPTY; BUCKET_LEN << buckets_len],
+            // Multiply-shift hashing of keys in arithmetic progressions is too regular
+            // here, with some seeds crowding keys into few bucket and fingerprint pairs
+            bucket_hash: IndependentHash::with_rng(4, rng),
+            fingerprint_hash: IndependentHash::with_rng(4, rng),
+            alternate_hash: SeededHash::with_rng(buckets_len, rng),
+            fingerprint_bits,
+            mask: (1 << buckets_len) - 1,
+            len: 0,
+        }
+    }
+    // Sized for n keys at a false positive rate of at most fpr
+    pub fn optimal(n: usize, fpr: f64) -> CuckooFilter {
+        let (buckets_len, fingerprint_bits) = optimal_dimensions(n, fpr);
+        return CuckooFilter::new(buckets_len, fingerprint_bits);
+    }
+    fn fingerprint(&self, key: u64) -> u16 {
+        let fingerprint: u16 = (self.fingerprint_hash.hash(key) >> (61 - self.fingerprint_bits)) as u16;
+        // Moves 0 to another value, which makes that value slightly more common
+        return if fingerprint == EMPTY { 1 } else { fingerprint };
+    }
+    fn first_bucket(&self, key: u64) -> usize {
+        return ((self.bucket_hash.hash(key) as u128 * (self.mask + 1) as u128) >> 61) as usize;
+    }
+    fn alternate(&self, bucket: usize, fingerprint: u16) -> usize {
+        return (bucket ^ self.alternate_hash.hash(fingerprint as u64)) & self.mask;
+    }
+    fn bucket(&self, bucket: usize) -> &[u16] {
+        return &self.slots[bucket * BUCKET_LEN..(bucket + 1) * BUCKET_LEN];
+    }
+    fn try_put(&mut self, bucket: usize, fingerprint: u16) -> bool {
+        for slot in &mut self.slots[bucket * BUCKET_LEN..(bucket + 1) * BUCKET_LEN] {
+            if *slot == EMPTY {
+                *slot = fingerprint;
+                return true;
+            }
+        }
+        return false;
+    }
+    // Returns false if the filter is too full, in which case the evictions are undone and
+    // the filter is left as it was, so no key that was inserted before is ever lost
+    pub fn insert(&mut self, key: u64) -> bool {
+        let mut fingerprint: u16 = self.fingerprint(key);
+        let i1: usize = self.first_bucket(key);
+        let i2: usize = self.alternate(i1, fingerprint);
+        if self.try_put(i1, fingerprint) || self.try_put(i2, fingerprint) {
+            self.len += 1;
+            return true;
+        }
+
+        let mut bucket: usize = if random_generator(0, 2) == 0 { i1 } else { i2 };
+        let mut kicked: Vec<usize> = Vec::with_capacity(MAX_KICKS);
+        for _ in 0..MAX_KICKS {
+            let slot: usize = bucket * BUCKET_LEN + random_generator(0, BUCKET_LEN as u32) as usize;
+            std::mem::swap(&mut fingerprint, &mut self.slots[slot]);
+            kicked.push(slot);
+            bucket = self.alternate(bucket, fingerprint);
+            if self.try_put(bucket, fingerprint) {
+                self.len += 1;
+                return true;
+            }
+        }
+        // Swapping back in reverse order moves every evicted fingerprint home again
+        for slot in kicked.into_iter().rev() {
+            std::mem::swap(&mut fingerprint, &mut self.slots[slot]);
+        }
+        return false;
+    }
+    pub fn contains(&self, key: u64) -> bool {
+        let fingerprint: u16 = self.fingerprint(key);
+        let i1: usize = self.first_bucket(key);
+        let i2: usize = self.alternate(i1, fingerprint);
+        return self.bucket(i1).contains(&fingerprint) || self.bucket(i2).contains(&fingerprint);
+    }
+    // Removes one copy of the fingerprint of key. Only keys that were inserted should
+    // be removed, or the fingerprint of another key may go instead.
+    pub fn remove(&mut self, key: u64) -> bool {
+        let fingerprint: u16 = self.fingerprint(key);
+        let i1: usize = self.first_bucket(key);
+        let i2: usize = self.alternate(i1, fingerprint);
+        for bucket in [i1, i2] {
+            if let Some(pos) = self.bucket(bucket).iter().position(|slot| *slot == fingerprint) {
+                self.slots[bucket * BUCKET_LEN + pos] = EMPTY;
+                self.len -= 1;
+                return true;
+            }
+        }
+        return false;
+    }
+    pub fn len(&self) -> usize {
+        return self.len;
+    }
+    pub fn is_empty(&self) -> bool {
+        return self.len == 0;
+    }
+    pub fn load_factor(&self) -> f64 {
+        return self.len as f64 / self.slots.len() as f64;
+    }
+    pub fn fingerprint_bits(&self) -> u32 {
+        return self.fingerprint_bits;
+    }
+    // Fingerprints are stored in 16 bits whatever their length
+    pub fn space_usage(&self) -> usize {
+        return self.slots.len() * size_of::<u16>();
+    }
+    // fingerprint_bits (u32) | the three hash functions | number of slots (u64), followed
+    // by the slots (u16)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new(SketchKind::CuckooFilter);
+        writer.u32(self.fingerprint_bits);
+        writer.independent_hash(&self.bucket_hash);
+        writer.independent_hash(&self.fingerprint_hash);
+        writer.seeded_hash(&self.alternate_hash);
+        writer.u64(self.slots.len() as u64);
+        self.slots.iter().for_each(|slot| writer.u16(*slot));
+        return writer.finish();
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<CuckooFilter, DecodeError> {
+        let mut reader: ByteReader = ByteReader::new(bytes, SketchKind::CuckooFilter)?;
+        let fingerprint_bits: u32 = reader.u32()?;
+        if !(1..=16).contains(&fingerprint_bits) {
+            return Err(DecodeError::Invalid("fingerprint length out of range"));
+        }
+        let bucket_hash: IndependentHash = reader.independent_hash()?;
+        let fingerprint_hash: IndependentHash = reader.independent_hash()?;
+        let alternate_hash: SeededHash = reader.seeded_hash()?;
+        let buckets_len: u32 = alternate_hash.hash_len();
+        let len: usize = reader.len(size_of::<u16>())?;
+        if buckets_len >= usize::BITS - 2 || len != BUCKET_LEN << buckets_len {
+            return Err(DecodeError::Invalid("slots do not match the alternate bucket hash"));
+        }
+        let slots: Vec<u16> = (0..len).map(|_| reader.u16()).collect::<Result<_, _>>()?;
+        reader.finish()?;
+        if slots.iter().any(|slot| (*slot as u32) >> fingerprint_bits != 0) {
+            return Err(DecodeError::Invalid("fingerprint longer than its length"));
+        }
+        return Ok(CuckooFilter {
+            len: slots.iter().filter(|slot| **slot != EMPTY).count(),
+            slots,
+            bucket_hash,
+            fingerprint_hash,
+            alternate_hash,
+            fingerprint_bits,
+            mask: (1 << buckets_len) - 1,
+        });
+    }
+}
+
+impl MemoryFootprint for CuckooFilter {
+    fn heap_bytes(&self) -> usize {
+        return self.space_usage();
+    }
+    fn allocations(&self) -> usize {
+        return vec_allocations(&self.slots);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn inserts_lookups_and_deletions() {
+        let mut filter: CuckooFilter = CuckooFilter::optimal(10_000, 0.01);
+        for key in 0..10_000u64 {
+            assert!(filter.insert(key * 2));
+        }
+        assert_eq!(filter.len(), 10_000);
+        assert!((0..10_000u64).all(|key| filter.contains(key * 2)));
+        let false_positives: usize = (0..100_000u64).filter(|key| filter.contains(key * 2 + 1)).count();
+        assert!(false_positives < 1_000);
+
+        for key in 0..5_000u64 {
+            assert!(filter.remove(key * 2));
+        }
+        assert!((5_000..10_000u64).all(|key| filter.contains(key * 2)));
+        assert!((0..5_000u64).filter(|key| filter.contains(key * 2)).count() < 100);
+        assert!(filter.load_factor() < 0.5);
+    }
+
+    // Just below a power of two buckets, where optimal is as full as it gets. About one
+    // filter of 64 buckets in 2000 has hash functions that cannot hold that many keys, so
+    // the filters have fixed seeds. The evictions stay random, but fit the keys every time.
+    #[test]
+    fn optimal_holds_n_keys() {
+        for buckets_len in MIN_BUCKETS.ilog2()..=12 {
+            let most: usize = ((BUCKET_LEN << buckets_len) as f64 * TARGET_LOAD) as usize;
+            for n in [most - 1, most, most + 1] {
+                let (optimal_len, fingerprint_bits) = optimal_dimensions(n, 0.01);
+                assert_eq!(optimal_len, if n > most { buckets_len + 1 } else { buckets_len });
+                let mut filter: CuckooFilter = CuckooFilter::with_rng(optimal_len, fingerprint_bits, &mut StdRng::seed_from_u64(n as u64));
+                assert!((0..n as u64).all(|key| filter.insert(key)), "{} keys", n);
+                assert!((0..n as u64).all(|key| filter.contains(key)));
+            }
+        }
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut filter: CuckooFilter = CuckooFilter::with_rng(8, 12, &mut StdRng::seed_from_u64(3));
+        (0..500u64).for_each(|key| assert!(filter.insert(key * 3)));
+        let restored: CuckooFilter = CuckooFilter::from_bytes(&filter.to_bytes()).unwrap();
+        assert_eq!(restored.slots, filter.slots);
+        assert_eq!(restored.len(), 500);
+        assert!((0..500u64).all(|key| restored.contains(key * 3)));
+
+        let mut bytes: Vec<u8> = filter.to_bytes();
+        let last: usize = bytes.len() - 1;
+        bytes[last] = 0x10;
+        assert_eq!(CuckooFilter::from_bytes(&bytes).err(), Some(DecodeError::Invalid("fingerprint longer than its length")));
+        assert_eq!(CuckooFilter::from_bytes(&filter.to_bytes()[..100]).err(), Some(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn failed_insert_leaves_filter_unchanged() {
+        let mut filter: CuckooFilter = CuckooFilter::new(2, 16);
+        let mut inserted: Vec<u64> = Vec::new();
+        for key in 0..100u64 {
+            let before: Vec<u16> = filter.slots.clone();
+            if filter.insert(key) {
+                inserted.push(key);
+            } else {
+                assert_eq!(filter.slots, before);
+            }
+        }
+        assert!(inserted.len() < 100);
+        assert_eq!(filter.len(), inserted.len());
+        assert!(inserted.iter().all(|key| filter.contains(*key)));
+    }
+}
@@ -0,0 +1,209 @@
+use std::mem::size_of;
+use crate::hash::{log2u, SeededHash};
+use crate::las_vegas::{las_vegas, Attempts};
+use crate::memory::{vec_allocations, MemoryFootprint};
+use crate::table_stats::TableStats;
+
+// Fresh hash functions tried at one size before the tables are doubled
+const ATTEMPTS_PER_SIZE: usize = 16;
+
+// Cuckoo hashing as from lecture notes, with 0 marking an empty slot.
+// Elements that find no nest may be parked in a small stash instead of forcing a rehash.
+pub struct CuckooTable {
+    vec1: Vec<u64>,
+    vec2: Vec<u64>,
+    stash: Vec<u64>,
+    stash_size: usize,
+    hash_function1: SeededHash,
+    hash_function2: SeededHash,
+    hash_len: u32,
+    max_loop: usize,
+    rehashes: usize,
+    stash_saves: usize,
+    max_stash: usize,
+}
+
+impl CuckooTable {
+    pub fn new(size: usize) -> CuckooTable {
+        CuckooTable::with_stash(size, 0)
+    }
+    pub fn with_stash(size: usize, stash_size: usize) -> CuckooTable {
+        // Each of the two tables gets twice as many slots as the expected number of elements
+        CuckooTable::with_hash_len(log2u(size) + 1, stash_size)
+    }
+    pub fn with_hash_len(hash_len: u32, stash_size: usize) -> CuckooTable {
+        let table_len: usize = 1 << hash_len;
+        return CuckooTable {
+            vec1: vec![0; table_len],
+            vec2: vec![0; table_len],
+            stash: Vec::with_capacity(stash_size),
+            stash_size,
+            hash_function1: SeededHash::new(hash_len),
+            hash_function2: SeededHash::new(hash_len),
+            hash_len,
+            max_loop: 3 * hash_len as usize,
+            rehashes: 0,
+            stash_saves: 0,
+            max_stash: 0,
+        }
+    }
+    // Moves elem into its nest, kicking out the occupants in turn. Returns the element
+    // left without a nest if no free slot was found within max_loop displacements.
+    fn place(&mut self, elem: u64) -> Option<u64> {
+        let mut x: u64 = elem;
+        for _ in 0..self.max_loop {
+            let h1: usize = self.hash_function1.hash(x);
+            std::mem::swap(&mut x, &mut self.vec1[h1]);
+            if x == 0 {
+                return None;
+            }
+            let h2: usize = self.hash_function2.hash(x);
+            std::mem::swap(&mut x, &mut self.vec2[h2]);
+            if x == 0 {
+                return None;
+            }
+        }
+        return Some(x);
+    }
+    // Parks a nestless element in the stash if there is room
+    fn try_stash(&mut self, elem: u64) -> bool {
+        if self.stash.len() >= self.stash_size {
+            return false;
+        }
+        self.stash.push(elem);
+        if self.stash.len() > self.max_stash {
+            self.max_stash = self.stash.len();
+        }
+        return true;
+    }
+    // Draws new hash functions until every element, including the pending one, fits.
+    // The tables are doubled whenever ATTEMPTS_PER_SIZE hash functions in a row fail.
+    fn rehash(&mut self, pending: u64) {
+        let mut elems: Vec<u64> = self.vec1.iter()
+            .chain(self.vec2.iter())
+            .chain(self.stash.iter())
+            .filter(|x| **x != 0)
+            .copied()
+            .collect();
+        elems.push(pending);
+        let mut failures: usize = 0;
+        let outcome: Attempts<()> = las_vegas(usize::MAX, || {
+            if failures == ATTEMPTS_PER_SIZE {
+                failures = 0;
+                self.hash_len += 1;
+                self.max_loop = 3 * self.hash_len as usize;
+                self.vec1 = vec![0; 1 << self.hash_len];
+                self.vec2 = vec![0; 1 << self.hash_len];
+            }
+            self.hash_function1 = SeededHash::new(self.hash_len);
+            self.hash_function2 = SeededHash::new(self.hash_len);
+            self.vec1.fill(0);
+            self.vec2.fill(0);
+            self.stash.clear();
+            if elems.iter().all(|x| self.place(*x).is_none_or(|nestless| self.try_stash(nestless))) {
+                return Some(());
+            }
+            failures += 1;
+            return None;
+        });
+        self.rehashes += outcome.attempts;
+    }
+    pub fn insert(&mut self, elem: u64) {
+        assert_ne!(elem, 0, "0 marks an empty slot");
+        if self.query(elem) {
+            return;
+        }
+        let Some(nestless) = self.place(elem) else { return; };
+        if self.try_stash(nestless) {
+            self.stash_saves += 1;
+            return;
+        }
+        self.rehash(nestless);
+    }
+    // Never true for 0, which marks an empty slot
+    pub fn query(&self, elem: u64) -> bool {
+        return elem != 0 && (self.vec1[self.hash_function1.hash(elem)] == elem
+            || self.vec2[self.hash_function2.hash(elem)] == elem
+            || self.stash.contains(&elem));
+    }
+    // Every slot counts as a bucket holding at most one element, the slots of the stash included
+    pub fn stats(&self) -> TableStats {
+        let mut stats: TableStats = TableStats::new();
+        self.vec1.iter().for_each(|x| stats.record_chain((*x != 0) as usize));
+        self.vec2.iter().for_each(|x| stats.record_chain((*x != 0) as usize));
+        (0..self.stash_size).for_each(|slot| stats.record_chain((slot < self.stash.len()) as usize));
+        return stats;
+    }
+    pub fn space_usage(&self) -> usize {
+        return (self.vec1.len() + self.vec2.len() + self.stash_size) * size_of::<u64>();
+    }
+    // Attempts at new hash functions, over all rehashes
+    pub fn rehashes(&self) -> usize {
+        return self.rehashes;
+    }
+    // Inserts that the stash saved from a rehash
+    pub fn stash_saves(&self) -> usize {
+        return self.stash_saves;
+    }
+    // Most elements the stash has held at once
+    pub fn max_stash(&self) -> usize {
+        return self.max_stash;
+    }
+}
+
+impl MemoryFootprint for CuckooTable {
+    fn heap_bytes(&self) -> usize {
+        return self.space_usage();
+    }
+    fn allocations(&self) -> usize {
+        return [&self.vec1, &self.vec2, &self.stash].iter().map(|vec| vec_allocations(vec)).sum();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inserts_and_queries_with_rehashes() {
+        let mut cuckoo: CuckooTable = CuckooTable::new(10_000);
+        for key in 1..=10_000u64 {
+            cuckoo.insert(key * 3);
+        }
+        cuckoo.insert(3);
+        assert!((1..=10_000u64).all(|key| cuckoo.query(key * 3)));
+        assert!(!(1..=10_000u64).any(|key| cuckoo.query(key * 3 + 1)));
+        let stats: TableStats = cuckoo.stats();
+        assert_eq!((stats.entries, stats.buckets, stats.max_chain), (10_000, 2 << 14, 1));
+        assert_eq!(cuckoo.stash_saves(), 0);
+    }
+
+    #[test]
+    fn stats_count_the_stash() {
+        // Two slots per table for six keys, so at least two of them wait in the stash
+        let mut cuckoo: CuckooTable = CuckooTable::with_hash_len(1, 4);
+        (1..=6u64).for_each(|key| cuckoo.insert(key));
+        assert!((1..=6u64).all(|key| cuckoo.query(key)));
+        let stats: TableStats = cuckoo.stats();
+        assert_eq!((stats.entries, stats.buckets), (6, 8));
+        assert!(cuckoo.max_stash() >= 2);
+    }
+
+    #[test]
+    fn grows_when_the_tables_are_full() {
+        let mut cuckoo: CuckooTable = CuckooTable::with_hash_len(1, 0);
+        (1..=5u64).for_each(|key| cuckoo.insert(key));
+        assert!((1..=5u64).all(|key| cuckoo.query(key)) && !cuckoo.query(6));
+        let stats: TableStats = cuckoo.stats();
+        assert_eq!(stats.entries, 5);
+        assert!(stats.buckets >= 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "0 marks an empty slot")]
+    fn rejects_the_empty_marker() {
+        let mut cuckoo: CuckooTable = CuckooTable::new(16);
+        assert!(!cuckoo.query(0));
+        cuckoo.insert(0);
+    }
+}
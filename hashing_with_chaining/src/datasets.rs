@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::BufRead;
+use std::io::ErrorKind::InvalidData;
+use std::net::{IpAddr, Ipv4Addr};
+use crate::hash::hash_bytes;
+
+// Loaders that turn real data into key streams for the streaming experiments. Every
+// occurrence becomes one key, an update of 1, and the keys are below 2^61 - 1 like those of
+// the generators in inputs.
+
+// A text as the stream of its words, each word keyed by hash_bytes of its lowercase form.
+// The words are kept by key so heavy hitters can be reported as words.
+pub struct WordStream {
+    pub keys: Vec<u64>,
+    words: HashMap<u64, String>,
+}
+
+impl WordStream {
+    pub fn word(&self, key: u64) -> Option<&str> {
+        return self.words.get(&key).map(|word| word.as_str());
+    }
+    pub fn distinct_words(&self) -> usize {
+        return self.words.len();
+    }
+}
+
+// Words are the runs of letters and digits, apostrophes included so "don't" stays one word
+pub fn read_words(reader: impl BufRead) -> io::Result<WordStream> {
+    let mut stream: WordStream = WordStream { keys: Vec::new(), words: HashMap::new() };
+    for line in reader.lines() {
+        let line: String = line?;
+        for word in line.split(|c: char| !c.is_alphanumeric() && c != '\'') {
+            let word: &str = word.trim_matches('\'');
+            if word.is_empty() {
+                continue;
+            }
+            let word: String = word.to_lowercase();
+            let key: u64 = hash_bytes(word.as_bytes());
+            stream.keys.push(key);
+            stream.words.entry(key).or_insert(word);
+        }
+    }
+    return Ok(stream);
+}
+
+// Layouts of a packet or request trace
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TraceFormat {
+    // Comma separated fields with the key in the given column, counting from 0, and
+    // optionally a header line
+    Csv { column: usize, header: bool },
+    // Text output of tcpdump -n, keyed by the source address of every IP packet. Lines of
+    // other protocols are skipped.
+    Tcpdump,
+}
+
+// An IPv4 address as its 32-bit value, an IPv6 address by hash_bytes of its octets, a
+// number below 2^61 - 1 as itself and anything else by hash_bytes of its text
+pub fn parse_key(field: &str) -> u64 {
+    let field: &str = field.trim();
+    return match field.parse::<IpAddr>() {
+        Ok(IpAddr::V4(address)) => u32::from(address) as u64,
+        Ok(IpAddr::V6(address)) => hash_bytes(&address.octets()),
+        Err(_) => match field.parse::<u64>() {
+            Ok(number) if number < (1 << 61) - 1 => number,
+            _ => hash_bytes(field.as_bytes()),
+        },
+    }
+}
+
+// Source address of a tcpdump -n line such as
+// 12:00:00.000001 IP 10.0.0.1.443 > 10.0.0.2.51000: Flags [S], ...
+// where the address is followed by the port
+fn tcpdump_source(line: &str) -> Option<u64> {
+    let mut tokens = line.split_whitespace();
+    let protocol: &str = tokens.find(|token| *token == "IP" || *token == "IP6")?;
+    let source: &str = tokens.next()?;
+    let address: &str = source.rsplit_once('.').map_or(source, |(address, _port)| address);
+    return match protocol {
+        "IP" => address.parse::<Ipv4Addr>().ok().map(|address| u32::from(address) as u64),
+        _ => Some(parse_key(address)),
+    }
+}
+
+// Keys of a trace, one per record. Empty lines and lines starting with # are skipped, and
+// a CSV line without the key column is an error.
+pub fn read_trace(reader: impl BufRead, format: TraceFormat) -> io::Result<Vec<u64>> {
+    let mut keys: Vec<u64> = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line: String = line?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match format {
+            TraceFormat::Csv { header: true, .. } if number == 0 => continue,
+            TraceFormat::Csv { column, .. } => {
+                let field: &str = line.split(',').nth(column).ok_or_else(|| {
+                    io::Error::new(InvalidData, format!("Line {} has no column {}", number + 1, column))
+                })?;
+                keys.push(parse_key(field.trim_matches('"')));
+            }
+            TraceFormat::Tcpdump => keys.extend(tcpdump_source(&line)),
+        }
+    }
+    return Ok(keys);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_words_and_traces() {
+        let text: &str = "The cat saw the dog.\n\nTHE dog didn't see 'the' cat";
+        let words: WordStream = read_words(text.as_bytes()).unwrap();
+        assert_eq!(words.keys.len(), 11);
+        assert_eq!(words.distinct_words(), 6);
+        let the: u64 = hash_bytes(b"the");
+        assert_eq!(words.keys.iter().filter(|key| **key == the).count(), 4);
+        assert_eq!(words.word(the), Some("the"));
+        assert!(words.word(hash_bytes(b"didn't")).is_some());
+
+        let csv: &str = "time,source,bytes\n1,10.0.0.1,60\n2,\"10.0.0.2\",1500\n# comment\n3,10.0.0.1,60\n4,host-a,40\n";
+        let keys: Vec<u64> = read_trace(csv.as_bytes(), TraceFormat::Csv { column: 1, header: true }).unwrap();
+        assert_eq!(keys, vec![0x0a000001, 0x0a000002, 0x0a000001, hash_bytes(b"host-a")]);
+        let error: io::Error = read_trace("1,2\n3\n".as_bytes(), TraceFormat::Csv { column: 1, header: false }).unwrap_err();
+        assert_eq!(error.kind(), InvalidData);
+
+        let dump: &str = "12:00:00.000001 IP 192.168.1.7.443 > 10.0.0.2.51000: Flags [S], length 0\n\
+                          12:00:00.000002 ARP, Request who-has 10.0.0.1 tell 10.0.0.2, length 28\n\
+                          12:00:00.000003 IP6 fe80::1.546 > ff02::1:2.547: dhcp6 solicit\n";
+        let keys: Vec<u64> = read_trace(dump.as_bytes(), TraceFormat::Tcpdump).unwrap();
+        assert_eq!(keys, vec![0xc0a80107, parse_key("fe80::1")]);
+        assert!(keys.iter().all(|key| *key < (1 << 61) - 1));
+    }
+}
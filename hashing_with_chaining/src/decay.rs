@@ -0,0 +1,102 @@
+use crate::count_min::CountMin;
+use crate::counter::Counter;
+use crate::norm_sketch::NormSketch;
+
+// Multiplies every counter by factor after each interval of inserts, so an insert that is
+// n intervals old only counts factor^n times
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DecaySchedule {
+    pub factor: f64,
+    pub interval: u64,
+}
+
+// A linear sketch whose counters can be scaled down all at once
+pub trait DecayableSketch {
+    type Delta;
+    fn insert(&mut self, key: u64, delta: Self::Delta);
+    fn scale(&mut self, factor: f64);
+}
+
+impl<C: Counter> DecayableSketch for CountMin<C> {
+    type Delta = C;
+    fn insert(&mut self, key: u64, delta: C) {
+        CountMin::insert(self, key, delta);
+    }
+    fn scale(&mut self, factor: f64) {
+        CountMin::scale(self, factor);
+    }
+}
+
+impl<C: Counter> DecayableSketch for NormSketch<C> {
+    type Delta = C;
+    fn insert(&mut self, key: u64, delta: C) {
+        NormSketch::insert(self, key, delta);
+    }
+    fn scale(&mut self, factor: f64) {
+        NormSketch::scale(self, factor);
+    }
+}
+
+// Exponentially time-decayed sketch: the wrapped sketch is scaled by the schedule's factor
+// after every interval of inserts, so estimates weight recent items more and a key that
+// stops appearing fades towards zero. Time-based schedules can call decay directly.
+pub struct Decayed<S: DecayableSketch> {
+    sketch: S,
+    schedule: DecaySchedule,
+    since_decay: u64,
+}
+
+impl<S: DecayableSketch> Decayed<S> {
+    pub fn new(sketch: S, schedule: DecaySchedule) -> Decayed<S> {
+        assert!(schedule.factor > 0.0 && schedule.factor <= 1.0, "The decay factor must be in (0, 1]");
+        assert!(schedule.interval > 0, "The decay interval must be at least one insert");
+        return Decayed { sketch, schedule, since_decay: 0 }
+    }
+    pub fn insert(&mut self, key: u64, delta: S::Delta) {
+        self.sketch.insert(key, delta);
+        self.since_decay += 1;
+        if self.since_decay == self.schedule.interval {
+            self.decay();
+        }
+    }
+    // Applies the decay factor once, right away, and restarts the interval
+    pub fn decay(&mut self) {
+        self.sketch.scale(self.schedule.factor);
+        self.since_decay = 0;
+    }
+    // The decayed sketch, for queries
+    pub fn sketch(&self) -> &S {
+        return &self.sketch;
+    }
+    pub fn schedule(&self) -> DecaySchedule {
+        return self.schedule;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::count_min::UpdatePolicy;
+
+    #[test]
+    fn stopped_keys_decay_towards_zero() {
+        let schedule: DecaySchedule = DecaySchedule { factor: 0.8, interval: 2000 };
+        let mut count_min: Decayed<CountMin> = Decayed::new(CountMin::new(4, 14, UpdatePolicy::Standard), schedule);
+        let mut norm_sketch: Decayed<NormSketch> = Decayed::new(NormSketch::new(14), schedule);
+        // Key 1 takes a tenth of the first 10000 updates and then stops, key 2 goes on
+        for i in 0..100_000u64 {
+            let key: u64 = if i % 10 == 0 && i < 10_000 { 1 } else if i % 10 == 0 { 2 } else { 1000 + i % 5000 };
+            count_min.insert(key, 1);
+            norm_sketch.insert(key, 1);
+            if i == 9_999 {
+                assert!(count_min.sketch().estimate(1) >= 300, "{}", count_min.sketch().estimate(1));
+            }
+        }
+        // 45 intervals later key 1 is down by 0.8^45, far below one count
+        assert!(count_min.sketch().estimate(1) <= 5, "{}", count_min.sketch().estimate(1));
+        assert!(norm_sketch.sketch().estimate(1).abs() <= 5, "{}", norm_sketch.sketch().estimate(1));
+        // Right after a decay key 2 sits near x = 0.8 (x + 200), so x = 800
+        let steady: u64 = count_min.sketch().estimate(2);
+        assert!((700..900).contains(&steady), "{}", steady);
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use crate::metrics::Elapsed;
+use crate::plot::{render_svg, series, Chart, Series};
+use crate::results::ResultRow;
+
+// A benchmark campaign as one shareable document: a section per results file, with a table
+// per algorithm and a chart of the phase times against n. Markdown links the charts as SVG
+// files written next to it, HTML embeds them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DocumentFormat {
+    Markdown,
+    Html,
+}
+
+// The results of one file or report under its name
+pub struct Section {
+    pub title: String,
+    pub rows: Vec<ResultRow>,
+}
+
+// The text of the document and the charts it links to, as file names and SVG
+pub struct Document {
+    pub text: String,
+    pub images: Vec<(String, String)>,
+}
+
+// Averages of the runs of one algorithm at one n and phase
+struct TableRow {
+    n: u64,
+    phase: String,
+    runs: usize,
+    duration_ns: Option<f64>,
+    memory: Option<f64>,
+    metrics: BTreeMap<String, f64>,
+}
+
+fn average(values: &[f64]) -> Option<f64> {
+    return (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64);
+}
+
+// Repeated runs averaged into one row per n and phase. The summary rows of the runner are
+// left out, as the runs they summarize are already there.
+fn table_rows(rows: &[&ResultRow]) -> Vec<TableRow> {
+    let mut grouped: BTreeMap<(u64, &str), Vec<&ResultRow>> = BTreeMap::new();
+    for row in rows.iter().filter(|row| !row.phase.ends_with("_summary")) {
+        grouped.entry((row.n, row.phase.as_str())).or_default().push(row);
+    }
+    return grouped.into_iter().map(|((n, phase), runs)| {
+        let column = |name: &str| -> Vec<f64> { runs.iter().filter_map(|row| row.value(name)).collect() };
+        let names: BTreeSet<&str> = runs.iter().flat_map(|row| row.extra.iter().map(|(name, _)| name.as_str())).collect();
+        let metrics: BTreeMap<String, f64> = names.into_iter()
+            .filter_map(|name| average(&column(name)).map(|value| (name.to_owned(), value)))
+            .collect();
+        return TableRow {
+            n,
+            phase: phase.to_owned(),
+            runs: runs.len(),
+            duration_ns: average(&column("duration_ns")),
+            memory: average(&column("memory")),
+            metrics,
+        }
+    }).collect();
+}
+
+// Whole numbers as they are, others with four significant digits
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+    if value != 0.0 && (value.abs() < 1e-3 || value.abs() >= 1e6) {
+        return format!("{:.3e}", value);
+    }
+    return format!("{:.4}", value);
+}
+
+fn escape_html(text: &str) -> String {
+    return text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+}
+
+fn table(header: &[String], cells: &[Vec<String>], format: DocumentFormat) -> String {
+    let mut text: String = String::new();
+    match format {
+        DocumentFormat::Markdown => {
+            let line = |cells: &[String]| format!("| {} |\n", cells.iter().map(|cell| cell.replace('|', "\\|")).collect::<Vec<_>>().join(" | "));
+            text.push_str(&line(header));
+            text.push_str(&format!("|{}\n", "---|".repeat(header.len())));
+            cells.iter().for_each(|row| text.push_str(&line(row)));
+        }
+        DocumentFormat::Html => {
+            let line = |tag: &str, cells: &[String]| {
+                format!("<tr>{}</tr>\n", cells.iter().map(|cell| format!("<{}>{}</{}>", tag, escape_html(cell), tag)).collect::<String>())
+            };
+            text.push_str("<table>\n");
+            text.push_str(&line("th", header));
+            cells.iter().for_each(|row| text.push_str(&line("td", row)));
+            text.push_str("</table>\n");
+        }
+    }
+    return text;
+}
+
+// The table of one algorithm: n, phase and runs, then the mean duration and memory and
+// every numeric metric, each only if some row has it
+fn algorithm_table(rows: &[&ResultRow], format: DocumentFormat) -> String {
+    let averaged: Vec<TableRow> = table_rows(rows);
+    let has_duration: bool = averaged.iter().any(|row| row.duration_ns.is_some());
+    let has_memory: bool = averaged.iter().any(|row| row.memory.is_some());
+    let metric_names: BTreeSet<&str> = averaged.iter().flat_map(|row| row.metrics.keys().map(|name| name.as_str())).collect();
+
+    let mut header: Vec<String> = vec!["n".to_owned(), "phase".to_owned(), "runs".to_owned()];
+    header.extend(has_duration.then(|| "duration".to_owned()));
+    header.extend(has_memory.then(|| "memory".to_owned()));
+    header.extend(metric_names.iter().map(|name| name.to_string()));
+    let cells: Vec<Vec<String>> = averaged.iter().map(|row| {
+        let mut cells: Vec<String> = vec![row.n.to_string(), row.phase.clone(), row.runs.to_string()];
+        if has_duration {
+            cells.push(row.duration_ns.map_or(String::new(), |duration| Elapsed::from_nanoseconds(duration.round() as u64).to_string()));
+        }
+        if has_memory {
+            cells.push(row.memory.map_or(String::new(), format_number));
+        }
+        cells.extend(metric_names.iter().map(|name| row.metrics.get(*name).map_or(String::new(), |value| format_number(*value))));
+        return cells;
+    }).collect();
+    return table(&header, &cells, format);
+}
+
+// File name of the chart of a section, from its title
+fn chart_file(title: &str, index: usize) -> String {
+    let stem: String = title.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' }).collect();
+    return format!("{}-{}.svg", index, stem);
+}
+
+pub fn render_document(title: &str, sections: &[Section], format: DocumentFormat) -> io::Result<Document> {
+    let mut document: Document = Document { text: String::new(), images: Vec::new() };
+    let heading = |level: usize, text: &str| -> String {
+        return match format {
+            DocumentFormat::Markdown => format!("{} {}\n\n", "#".repeat(level), text),
+            DocumentFormat::Html => format!("<h{}>{}</h{}>\n", level, escape_html(text), level),
+        }
+    };
+    if format == DocumentFormat::Html {
+        document.text.push_str(&format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n", escape_html(title)));
+        document.text.push_str("<style>table { border-collapse: collapse; } th, td { border: 1px solid #ccc; padding: 2px 8px; text-align: right; }</style>\n</head>\n<body>\n");
+    }
+    document.text.push_str(&heading(1, title));
+
+    for (index, section) in sections.iter().enumerate() {
+        document.text.push_str(&heading(2, &section.title));
+        // Times of the runs against n, when there is more than one n to draw them over
+        let runs: Vec<ResultRow> = section.rows.iter().filter(|row| !row.phase.ends_with("_summary")).cloned().collect();
+        let lines: Vec<Series> = series(&runs, "n", "duration_ns");
+        if lines.iter().any(|line| line.points.len() > 1) {
+            let chart: Chart = Chart {
+                title: section.title.clone(),
+                x_label: "n".to_owned(),
+                y_label: "duration_ns".to_owned(),
+                log_x: true,
+                log_y: true,
+            };
+            let svg: String = render_svg(&lines, &chart)?;
+            match format {
+                DocumentFormat::Markdown => {
+                    let file: String = chart_file(&section.title, index);
+                    document.text.push_str(&format!("![{}]({})\n\n", section.title, file));
+                    document.images.push((file, svg));
+                }
+                DocumentFormat::Html => document.text.push_str(&format!("<figure>\n{}\n</figure>\n", svg)),
+            }
+        }
+        let algorithms: BTreeSet<&str> = section.rows.iter().map(|row| row.algorithm.as_str()).collect();
+        for algorithm in algorithms {
+            let rows: Vec<&ResultRow> = section.rows.iter().filter(|row| row.algorithm == algorithm).collect();
+            document.text.push_str(&heading(3, algorithm));
+            document.text.push_str(&algorithm_table(&rows, format));
+            if format == DocumentFormat::Markdown {
+                document.text.push('\n');
+            }
+        }
+    }
+    if format == DocumentFormat::Html {
+        document.text.push_str("</body>\n</html>\n");
+    }
+    return Ok(document);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documents_average_runs_into_tables() {
+        let row = |algorithm: &str, n: u64, duration: i128| ResultRow::new(algorithm, n, "query").duration_ns(duration).metric("found", n);
+        let rows: Vec<ResultRow> = vec![
+            row("hwc", 16, 1000), row("hwc", 16, 3000), row("hwc", 1024, 50_000),
+            ResultRow::new("hwc", 16, "query_summary").duration_ns(2000),
+            row("lp", 16, 800), row("lp", 1024, 40_000).memory(4096),
+        ];
+        let sections: Vec<Section> = vec![Section { title: "tables".to_owned(), rows }];
+
+        let markdown: Document = render_document("Campaign", &sections, DocumentFormat::Markdown).unwrap();
+        assert!(markdown.text.starts_with("# Campaign\n\n## tables\n\n![tables](0-tables.svg)\n"));
+        assert!(markdown.text.contains("### hwc\n\n| n | phase | runs | duration | found |\n|---|---|---|---|---|\n| 16 | query | 2 | 2.000µs | 16 |\n| 1024 | query | 1 | 50.000µs | 1024 |\n"));
+        assert!(markdown.text.contains("| 1024 | query | 1 | 40.000µs | 4096 | 1024 |"));
+        assert_eq!(markdown.images.len(), 1);
+        assert!(markdown.images[0].1.starts_with("<svg"));
+
+        let html: Document = render_document("A <campaign>", &sections, DocumentFormat::Html).unwrap();
+        assert!(html.text.contains("<title>A &lt;campaign&gt;</title>") && html.text.contains("<figure>\n<svg"));
+        assert!(html.text.contains("<tr><td>16</td><td>query</td><td>2</td><td>2.000µs</td><td>16</td></tr>"));
+        assert!(html.images.is_empty());
+        assert_eq!(format_number(0.000012), "1.200e-5");
+        assert_eq!(format_number(0.25), "0.2500");
+    }
+}
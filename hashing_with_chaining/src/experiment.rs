@@ -0,0 +1,345 @@
+use std::hint::black_box;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use rayon::ThreadPoolBuilder;
+use signal_hook::consts::SIGINT;
+use crate::memory::MemoryFootprint;
+use crate::metrics::{now, Elapsed, Latencies};
+use crate::progress::Progress;
+use crate::results::ResultRow;
+use crate::stats::Summary;
+
+// A structure under benchmark: how to allocate it, fill it with the input and query it.
+// The runner does the rest, so a new structure needs only these few methods.
+pub trait Experiment {
+    type Structure;
+
+    // Name in the algorithm column
+    fn name(&self) -> String;
+    // An empty structure for n keys, timed as the allocation phase
+    fn setup(&self, n: usize) -> Self::Structure;
+    // Inserts the input, timed as the construction phase
+    fn run_updates(&self, structure: &mut Self::Structure, input: &[u64]);
+    // Looks up every input key, timed as the query phase. Returns how many were found, which
+    // also keeps the lookups from being optimized away.
+    fn run_query(&self, structure: &Self::Structure, input: &[u64]) -> usize;
+    // Whether inserting the input in parts builds the same structure as inserting it at
+    // once, so the runner can report progress in between. Static structures, which are
+    // built from their whole input, are not.
+    fn incremental(&self) -> bool {
+        return true;
+    }
+    // Memory footprint of the structure, when it can tell
+    fn footprint<'a>(&self, _structure: &'a Self::Structure) -> Option<&'a dyn MemoryFootprint> {
+        return None;
+    }
+    // Metrics of the filled structure, added to the query row
+    fn metrics(&self, _structure: &Self::Structure, row: ResultRow) -> ResultRow {
+        return row;
+    }
+}
+
+const PHASES: [&str; 3] = ["allocation", "construction", "query"];
+// Keys inserted or looked up between two progress updates
+const PROGRESS_CHUNK: usize = 1 << 20;
+
+// Set by the first Ctrl-C once interrupts are watched
+static INTERRUPTED: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+// Turns the first Ctrl-C into a request to stop: runners finish the runs at hand, record
+// them and start no others, so the results so far reach their files. A second Ctrl-C ends
+// the process at once.
+pub fn watch_interrupts() -> io::Result<()> {
+    let flag: &Arc<AtomicBool> = INTERRUPTED.get_or_init(|| Arc::new(AtomicBool::new(false)));
+    signal_hook::flag::register_conditional_shutdown(SIGINT, 130, Arc::clone(flag))?;
+    signal_hook::flag::register(SIGINT, Arc::clone(flag))?;
+    return Ok(());
+}
+
+pub fn interrupted() -> bool {
+    return INTERRUPTED.get().is_some_and(|flag| flag.load(Ordering::Relaxed));
+}
+
+// Phase durations and the rows of one run
+struct Run {
+    size: i32,
+    warmup: bool,
+    durations: [Elapsed; 3],
+    rows: [ResultRow; 3],
+}
+
+// Runs an experiment over inputs of 2^size distinct keys 1 to n, for every size and as many
+// times as repetitions, recording an allocation, a construction and a query row per run.
+// The query row carries the memory footprint of the filled structure.
+// With more than one run, a <phase>_summary row per phase follows the runs of a size, with
+// the mean time as its duration and the median, standard deviation and 95% confidence
+// interval of the mean as metrics. Phases whose coefficient of variation exceeds
+// noise_threshold are flagged noisy, on the row and on stderr. With progress on, the
+// updates and queries of incremental experiments are fed in chunks, with progress lines
+// on stderr in between.
+// With more than one job, the runs are spread over a thread pool of that many threads, each
+// taking the next (size, repetition) pair when it is done with one and keeping its runs
+// until the pool is done. A run is still timed on one thread, but runs at once share the
+// caches and memory bandwidth, so use no more jobs than the machine has cores to spare.
+// The rows come out in the same order either way.
+// With a budget, a phase that takes longer ends the sweep: the other runs of that size still
+// go ahead, but the larger sizes are skipped.
+// With latency sampling, every insert and lookup is also timed on its own and up to
+// latency_samples of these latencies per phase are kept, from which the construction and
+// query rows get their percentiles. Static experiments only get them for queries, as they
+// are built from their whole input. Timing single operations adds its own overhead to the
+// phase durations, so compare those only between runs with the same setting.
+// Before the runs of a size come warmup runs that are done the same way but not recorded,
+// so the measured runs find the caches, the branch predictors and the allocator warm. The
+// structure and its inputs pass through black_box and the found keys are summed, so the
+// compiler cannot drop or hoist the work it times.
+pub struct Runner {
+    pub sizes: Vec<i32>,
+    pub repetitions: usize,
+    pub noise_threshold: f64,
+    pub progress: bool,
+    pub jobs: usize,
+    pub budget: Option<Duration>,
+    pub latency_samples: Option<usize>,
+    pub warmup: usize,
+}
+
+impl Runner {
+    pub fn new(sizes: &[i32], repetitions: usize) -> Runner {
+        return Runner { sizes: sizes.to_vec(), repetitions, noise_threshold: 0.1, progress: false, jobs: 1, budget: None, latency_samples: None, warmup: 0 }
+    }
+    fn run_once<E: Experiment>(&self, experiment: &E, size: i32, repetition: usize, progress: &Mutex<Option<Progress>>) -> Run {
+        let name: String = experiment.name();
+        let n: usize = 1 << size;
+        let input: Vec<u64> = Vec::from_iter(1..(n + 1) as u64);
+        let chunk_len: usize = if self.progress && experiment.incremental() { PROGRESS_CHUNK } else { n };
+        let advance = |operations: usize| {
+            if let Some(progress) = &mut *progress.lock().unwrap() {
+                progress.advance(operations as u64);
+            }
+        };
+        if let Some(progress) = &mut *progress.lock().unwrap() {
+            let run: String = match repetition.checked_sub(self.warmup) {
+                Some(measured) => format!("run {}/{}", measured + 1, self.repetitions),
+                None => format!("warm-up {}/{}", repetition + 1, self.warmup),
+            };
+            progress.set_label(&format!("{} n = 2^{} {}", name, size, run));
+        }
+        let a_start = now();
+        let mut structure: E::Structure = black_box(experiment.setup(n));
+        let a_stop = now();
+
+        let mut update_latencies: Option<Latencies> = self.latency_samples.filter(|_| experiment.incremental()).map(Latencies::new);
+        let c_start = now();
+        for chunk in input.chunks(chunk_len) {
+            match &mut update_latencies {
+                Some(latencies) => chunk.chunks(1).for_each(|key| latencies.time(|| experiment.run_updates(black_box(&mut structure), black_box(key)))),
+                None => experiment.run_updates(black_box(&mut structure), black_box(chunk)),
+            }
+            advance(chunk.len());
+        }
+        let c_stop = now();
+
+        let mut query_latencies: Option<Latencies> = self.latency_samples.map(Latencies::new);
+        let q_start = now();
+        let mut found: usize = 0;
+        for chunk in input.chunks(chunk_len) {
+            found += match &mut query_latencies {
+                Some(latencies) => chunk.chunks(1).map(|key| latencies.time(|| black_box(experiment.run_query(black_box(&structure), black_box(key))))).sum(),
+                None => black_box(experiment.run_query(black_box(&structure), black_box(chunk))),
+            };
+            advance(chunk.len());
+        }
+        let q_stop = now();
+
+        let durations: [Elapsed; 3] = [a_stop - a_start, c_stop - c_start, q_stop - q_start];
+        let mut query: ResultRow = ResultRow::new(&name, n as u64, PHASES[2])
+            .throughput(durations[2], n as u64)
+            .metric("found", found);
+        if let Some(footprint) = experiment.footprint(&structure) {
+            query = query.footprint(footprint, n);
+        }
+        if let Some(latencies) = &query_latencies {
+            query = query.latencies(latencies);
+        }
+        let mut construction: ResultRow = ResultRow::new(&name, n as u64, PHASES[1]).throughput(durations[1], n as u64);
+        if let Some(latencies) = &update_latencies {
+            construction = construction.latencies(latencies);
+        }
+        return Run {
+            size,
+            warmup: repetition < self.warmup,
+            durations,
+            rows: [
+                ResultRow::new(&name, n as u64, PHASES[0]).duration_ns(durations[0].whole_nanoseconds()),
+                construction,
+                experiment.metrics(&structure, query),
+            ],
+        }
+    }
+    // The runs of every size, in order of size and then repetition, but for those the
+    // budget or an interrupt left out
+    fn run_all<E: Experiment + Sync>(&self, experiment: &E, progress: &Mutex<Option<Progress>>) -> Vec<Run> {
+        let configurations: Vec<(i32, usize)> = self.sizes.iter()
+            .flat_map(|size| (0..self.warmup + self.repetitions).map(move |repetition| (*size, repetition)))
+            .collect();
+        let next: AtomicUsize = AtomicUsize::new(0);
+        // Largest size still to run
+        let size_limit: AtomicI32 = AtomicI32::new(i32::MAX);
+        let work = || -> Vec<(usize, Run)> {
+            let mut done: Vec<(usize, Run)> = Vec::new();
+            loop {
+                let index: usize = next.fetch_add(1, Ordering::Relaxed);
+                let Some(&(size, repetition)) = configurations.get(index) else { return done; };
+                if interrupted() {
+                    return done;
+                }
+                if size > size_limit.load(Ordering::Relaxed) {
+                    continue;
+                }
+                let run: Run = self.run_once(experiment, size, repetition, progress);
+                let over_budget: bool = self.budget.is_some_and(|budget| run.durations.iter().any(|duration| *duration > Elapsed::from(budget)));
+                if over_budget && size_limit.fetch_min(size, Ordering::Relaxed) > size && size < *self.sizes.last().unwrap() {
+                    eprintln!("{} at n = {} went over the time budget, skipping the larger sizes", experiment.name(), 1usize << size);
+                }
+                done.push((index, run));
+            }
+        };
+        let mut runs: Vec<(usize, Run)> = if self.jobs < 2 {
+            work()
+        } else {
+            let pool = ThreadPoolBuilder::new().num_threads(self.jobs.min(configurations.len())).build().expect("Cannot start the thread pool");
+            pool.broadcast(|_| work()).into_iter().flatten().collect()
+        };
+        runs.sort_unstable_by_key(|(index, _)| *index);
+        return runs.into_iter().map(|(_, run)| run).filter(|run| !run.warmup).collect();
+    }
+    pub fn run<E: Experiment + Sync>(&self, experiment: &E, mut record: impl FnMut(ResultRow)) {
+        let name: String = experiment.name();
+        // Every run inserts and looks up all n keys
+        let total: u64 = self.sizes.iter().map(|size| 2 * (self.warmup + self.repetitions) as u64 * (1 << size)).sum();
+        let progress: Mutex<Option<Progress>> = Mutex::new(self.progress.then(|| Progress::new(&name, total)));
+        let mut runs = self.run_all(experiment, &progress).into_iter().peekable();
+        for &size in &self.sizes {
+            let n: usize = 1 << size;
+            let mut durations: [Vec<f64>; 3] = [Vec::new(), Vec::new(), Vec::new()];
+            while let Some(run) = runs.next_if(|run| run.size == size) {
+                for (phase, duration) in run.durations.iter().enumerate() {
+                    durations[phase].push(duration.whole_nanoseconds() as f64);
+                }
+                run.rows.into_iter().for_each(&mut record);
+            }
+            if durations[0].len() < 2 {
+                continue;
+            }
+            for (phase, samples) in PHASES.iter().zip(&durations) {
+                let summary: Summary = Summary::of(samples);
+                let noisy: bool = summary.coefficient_of_variation() > self.noise_threshold;
+                if noisy {
+                    eprintln!("{} {} at n = {} is noisy: standard deviation {:.0}% of the mean", name, phase, n, 100.0 * summary.coefficient_of_variation());
+                }
+                record(ResultRow::new(&name, n as u64, &format!("{}_summary", phase))
+                    .duration_ns(summary.mean.round() as i128)
+                    .metric("runs", summary.runs)
+                    .metric("median_ns", summary.median)
+                    .metric("std_dev_ns", summary.std_dev)
+                    .metric("ci_low_ns", summary.interval.0)
+                    .metric("ci_high_ns", summary.interval.1)
+                    .metric("noisy", noisy));
+            }
+        }
+        if let Some(mut progress) = progress.into_inner().unwrap() {
+            progress.set_label(&name);
+            progress.finish();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    // The standard library set, which holds only the even keys
+    struct EvenKeys;
+
+    impl Experiment for EvenKeys {
+        type Structure = HashSet<u64>;
+
+        fn name(&self) -> String {
+            return "even_keys".to_owned();
+        }
+        fn setup(&self, n: usize) -> HashSet<u64> {
+            return HashSet::with_capacity(n);
+        }
+        fn run_updates(&self, set: &mut HashSet<u64>, input: &[u64]) {
+            set.extend(input.iter().filter(|x| *x % 2 == 0));
+        }
+        fn run_query(&self, set: &HashSet<u64>, input: &[u64]) -> usize {
+            return input.iter().filter(|x| set.contains(x)).count();
+        }
+        fn metrics(&self, set: &HashSet<u64>, row: ResultRow) -> ResultRow {
+            return row.metric("len", set.len());
+        }
+    }
+
+    #[test]
+    fn runner_records_every_phase_of_every_run() {
+        let mut rows: Vec<ResultRow> = Vec::new();
+        Runner::new(&[4, 6], 2).run(&EvenKeys, |row| rows.push(row));
+        let phases: Vec<(u64, &str)> = rows.iter().map(|row| (row.n, row.phase.as_str())).collect();
+        let run = |n: u64| [(n, "allocation"), (n, "construction"), (n, "query")];
+        let summary = |n: u64| [(n, "allocation_summary"), (n, "construction_summary"), (n, "query_summary")];
+        assert_eq!(phases, [run(16), run(16), summary(16), run(64), run(64), summary(64)].concat());
+        assert!(rows.iter().all(|row| row.algorithm == "even_keys" && row.duration_ns.is_some() && row.memory.is_none()));
+        let query: &ResultRow = &rows[14];
+        assert_eq!((query.value("found"), query.value("len")), (Some(32.0), Some(32.0)));
+        assert!(query.value("ops_per_sec").unwrap() > 0.0 && rows[0].value("ops_per_sec").is_none());
+
+        // The summary of the two query runs above
+        let times: Vec<f64> = [&rows[11], query].iter().map(|row| row.value("duration_ns").unwrap()).collect();
+        let query_summary: &ResultRow = &rows[17];
+        assert_eq!(query_summary.value("runs"), Some(2.0));
+        assert_eq!(query_summary.value("median_ns"), Some((times[0] + times[1]) / 2.0));
+        assert!(query_summary.value("ci_low_ns").unwrap() <= times[0].min(times[1]));
+
+        // A single run has no spread to summarize
+        let mut single: Vec<ResultRow> = Vec::new();
+        Runner::new(&[4], 1).run(&EvenKeys, |row| single.push(row));
+        assert_eq!(single.len(), 3);
+
+        // Progress reporting leaves the results alone
+        let mut reported: Vec<ResultRow> = Vec::new();
+        Runner { progress: true, ..Runner::new(&[4], 1) }.run(&EvenKeys, |row| reported.push(row));
+        assert_eq!(reported[2].value("found"), Some(8.0));
+
+        // Runs spread over threads come out in the same order, with the same results
+        let mut parallel: Vec<ResultRow> = Vec::new();
+        Runner { jobs: 3, ..Runner::new(&[4, 6], 2) }.run(&EvenKeys, |row| parallel.push(row));
+        let parallel_phases: Vec<(u64, &str)> = parallel.iter().map(|row| (row.n, row.phase.as_str())).collect();
+        assert_eq!(parallel_phases, phases);
+        assert_eq!(parallel[14].value("found"), Some(32.0));
+
+        // Every phase goes over a budget of nothing, so only the runs of the first size happen
+        let mut budgeted: Vec<ResultRow> = Vec::new();
+        Runner { budget: Some(Duration::ZERO), ..Runner::new(&[4, 6, 8], 2) }.run(&EvenKeys, |row| budgeted.push(row));
+        assert_eq!(budgeted.len(), 9);
+        assert!(budgeted.iter().all(|row| row.n == 16));
+
+        // Sampled latencies add percentiles to the construction and query rows
+        let mut sampled: Vec<ResultRow> = Vec::new();
+        Runner { latency_samples: Some(10), ..Runner::new(&[6], 1) }.run(&EvenKeys, |row| sampled.push(row));
+        assert_eq!(sampled[0].value("latency_p50_ns"), None);
+        assert_eq!((sampled[1].value("latency_samples"), sampled[2].value("latency_samples")), (Some(10.0), Some(10.0)));
+        assert!(sampled[2].value("latency_p50_ns").unwrap() <= sampled[2].value("latency_p999_ns").unwrap());
+        assert_eq!(sampled[2].value("found"), Some(32.0));
+
+        // Warm-up runs are left out of the rows and the summaries
+        let mut warmed: Vec<ResultRow> = Vec::new();
+        Runner { warmup: 2, ..Runner::new(&[4, 6], 2) }.run(&EvenKeys, |row| warmed.push(row));
+        assert_eq!(warmed.iter().map(|row| (row.n, row.phase.as_str())).collect::<Vec<_>>(), phases);
+        assert_eq!(warmed[17].value("runs"), Some(2.0));
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::VecDeque;
+use std::mem::size_of;
+
+// Sum of the values inserted between two timestamps, both included
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    oldest: u64,
+    newest: u64,
+    sum: u64,
+}
+
+// Exponential histogram over a sliding window: the sum of the values inserted in the last
+// `window` time units, within a factor 1 +- epsilon. Inserts become buckets, and two adjacent
+// buckets are merged once their combined sum is at most 2 epsilon times the sum of all newer
+// buckets. Only the oldest bucket can straddle the window boundary, and counting half of it
+// is off by at most epsilon times the newer buckets, which all lie inside the window. Bucket
+// sums grow geometrically with age, so O(log(N) / epsilon) buckets cover a sum of N.
+// Counting is the special case of inserting ones, and windows over the last W items use the
+// item index as time.
+#[derive(Clone)]
+pub struct ExponentialHistogram {
+    // Oldest bucket at the front
+    buckets: VecDeque<Bucket>,
+    window: u64,
+    epsilon: f64,
+    now: u64,
+}
+
+impl ExponentialHistogram {
+    pub fn new(window: u64, epsilon: f64) -> ExponentialHistogram {
+        assert!(window > 0, "The window must span at least one time unit");
+        assert!(epsilon > 0.0 && epsilon < 1.0, "Needs 0 < epsilon < 1");
+        return ExponentialHistogram {
+            buckets: VecDeque::new(),
+            window,
+            epsilon,
+            now: 0,
+        }
+    }
+    // Moves the clock to time, which may not go backwards, and drops the buckets that left the window
+    pub fn advance(&mut self, time: u64) {
+        assert!(time >= self.now, "Time cannot go backwards");
+        self.now = time;
+        while let Some(bucket) = self.buckets.front() {
+            if bucket.newest + self.window > self.now {
+                break;
+            }
+            self.buckets.pop_front();
+        }
+    }
+    pub fn insert(&mut self, time: u64, value: u64) {
+        self.advance(time);
+        if value == 0 {
+            return;
+        }
+        self.buckets.push_back(Bucket { oldest: time, newest: time, sum: value });
+        self.compact();
+    }
+    // Inserts the value as the next item of a stream, for windows over the last W items
+    pub fn push(&mut self, value: u64) {
+        self.insert(self.now + 1, value);
+    }
+    // Merges adjacent buckets, from the newest to the oldest, wherever their sum allows it
+    fn compact(&mut self) {
+        let mut newer_sum: u64 = 0;
+        let mut i: usize = self.buckets.len() - 1;
+        while i > 0 {
+            let merged: u64 = self.buckets[i].sum + self.buckets[i - 1].sum;
+            if merged as f64 <= 2.0 * self.epsilon * newer_sum as f64 {
+                let older: Bucket = self.buckets.remove(i - 1).unwrap();
+                i -= 1;
+                self.buckets[i].oldest = older.oldest;
+                self.buckets[i].sum = merged;
+            } else {
+                newer_sum += self.buckets[i].sum;
+                i -= 1;
+            }
+        }
+    }
+    // Whether the oldest bucket has values from before the window
+    fn straddles(&self, bucket: &Bucket) -> bool {
+        return bucket.oldest + self.window <= self.now;
+    }
+    // Estimate of the sum over the window (now - window, now], counting half of a bucket
+    // that straddles its start
+    pub fn estimate(&self) -> f64 {
+        let total: u64 = self.buckets.iter().map(|bucket| bucket.sum).sum();
+        return total as f64 - self.error_bound();
+    }
+    // Largest possible distance of the estimate from the true sum
+    pub fn error_bound(&self) -> f64 {
+        return match self.buckets.front() {
+            Some(bucket) if self.straddles(bucket) => bucket.sum as f64 / 2.0,
+            _ => 0.0,
+        };
+    }
+    pub fn now(&self) -> u64 {
+        return self.now;
+    }
+    pub fn window(&self) -> u64 {
+        return self.window;
+    }
+    pub fn bucket_count(&self) -> usize {
+        return self.buckets.len();
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.buckets.len() * size_of::<Bucket>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::random_generator;
+
+    // Exact sums over the same window for a stream of values, one per time unit
+    fn check_window(values: &[u64], window: usize, epsilon: f64) -> usize {
+        let mut histogram: ExponentialHistogram = ExponentialHistogram::new(window as u64, epsilon);
+        let (mut max_buckets, mut exact) = (0, 0);
+        for (i, value) in values.iter().enumerate() {
+            histogram.push(*value);
+            exact += value;
+            if i >= window {
+                exact -= values[i - window];
+            }
+            let error: f64 = (histogram.estimate() - exact as f64).abs();
+            assert!(error <= epsilon * exact as f64 + 1e-9, "{} vs {} at {}", histogram.estimate(), exact, i);
+            assert!(error <= histogram.error_bound());
+            max_buckets = max_buckets.max(histogram.bucket_count());
+        }
+        return max_buckets;
+    }
+
+    #[test]
+    fn counts_and_sums_stay_within_epsilon() {
+        let bits: Vec<u64> = (0..100_000).map(|_| random_generator(0, 2) as u64).collect();
+        let buckets: usize = check_window(&bits, 10_000, 0.05);
+        assert!(buckets < 200, "{}", buckets);
+        let values: Vec<u64> = (0..100_000).map(|_| random_generator(0, 1000) as u64).collect();
+        check_window(&values, 10_000, 0.01);
+    }
+
+    #[test]
+    fn time_windows_expire_old_buckets() {
+        let mut histogram: ExponentialHistogram = ExponentialHistogram::new(100, 0.1);
+        for time in 0..1000 {
+            histogram.insert(time * 10, 1);
+        }
+        // Times 9901 to 10000 hold the inserts at 9910, ..., 9990
+        histogram.advance(10_000);
+        assert!((histogram.estimate() - 9.0).abs() <= 0.9);
+        histogram.advance(20_000);
+        assert_eq!(histogram.bucket_count(), 0);
+        assert_eq!(histogram.estimate(), 0.0);
+    }
+}
@@ -0,0 +1,398 @@
+// The contract of every function is the same, and is stated once in the header
+#![allow(clippy::missing_safety_doc)]
+
+use core::ptr;
+use core::slice;
+use crate::bloom::{checked_optimal_dimensions, BloomFilter};
+use crate::count_min::{CountMin, UpdatePolicy};
+use crate::hwc::HwC;
+use crate::hyperloglog::{HyperLogLog, MAX_PRECISION, MIN_PRECISION};
+use crate::mergeable::{MergeError, MergeableSketch};
+use crate::norm_sketch::NormSketch;
+
+// The sketches and the table for C and C++, through opaque handles that the caller creates,
+// passes back to every call and frees once. A handle is never shared between threads
+// without a lock. Functions take null handles without crashing: they do nothing, return
+// zero or a null handle, or report HWC_STATUS_NULL_POINTER. Arguments that would make the
+// library panic give a null handle instead, since a panic cannot unwind into C. The
+// header ffi/hashing_with_chaining.h is generated from this module by cbindgen.
+
+// Largest log2 of a row or counter array, 2^32 counters
+const MAX_WIDTH_LEN: u32 = 32;
+
+/// Outcome of the functions that can fail
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HwcStatus {
+    Ok = 0,
+    NullPointer = 1,
+    /// The sketches have different dimensions
+    DimensionMismatch = 2,
+    /// The sketches hash keys differently, so only clones of one sketch merge
+    SeedMismatch = 3,
+}
+
+/// Count-Min sketch with u64 counters and standard updates
+pub struct HwcCountMin(CountMin);
+/// HyperLogLog distinct counter
+pub struct HwcHyperLogLog(HyperLogLog);
+/// NormSketch of a turnstile stream with i64 counters
+pub struct HwcNormSketch(NormSketch);
+/// Hashing with chaining table of exact i64 counts
+pub struct HwcTable(HwC);
+/// Bloom filter
+pub struct HwcBloomFilter(BloomFilter);
+
+fn into_handle<T>(value: T) -> *mut T {
+    return Box::into_raw(Box::new(value));
+}
+
+unsafe fn free_handle<T>(handle: *mut T) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+fn merge_handles<S: MergeableSketch>(into: Option<&mut S>, other: Option<&S>) -> HwcStatus {
+    return match (into, other) {
+        (Some(into), Some(other)) => match into.merge(other) {
+            Ok(()) => HwcStatus::Ok,
+            Err(MergeError::DimensionMismatch) => HwcStatus::DimensionMismatch,
+            Err(MergeError::SeedMismatch) => HwcStatus::SeedMismatch,
+        },
+        _ => HwcStatus::NullPointer,
+    }
+}
+
+// Hands bytes over to the caller, who gives them back to hwc_bytes_free
+unsafe fn bytes_out(bytes: Vec<u8>, len: *mut usize) -> *mut u8 {
+    if len.is_null() {
+        return ptr::null_mut();
+    }
+    *len = bytes.len();
+    return Box::into_raw(bytes.into_boxed_slice()) as *mut u8;
+}
+
+unsafe fn bytes_in<'a>(bytes: *const u8, len: usize) -> Option<&'a [u8]> {
+    return (!bytes.is_null()).then(|| slice::from_raw_parts(bytes, len));
+}
+
+/// Frees the bytes of a *_to_bytes function, given with the length it returned
+#[no_mangle]
+pub unsafe extern "C" fn hwc_bytes_free(bytes: *mut u8, len: usize) {
+    if !bytes.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(bytes, len)));
+    }
+}
+
+/// depth rows of 2^width_len counters, or null unless depth >= 1 and width_len is in 1..=32
+#[no_mangle]
+pub extern "C" fn hwc_count_min_new(depth: usize, width_len: u32) -> *mut HwcCountMin {
+    if depth == 0 || !(1..=MAX_WIDTH_LEN).contains(&width_len) {
+        return ptr::null_mut();
+    }
+    return into_handle(HwcCountMin(CountMin::new(depth, width_len, UpdatePolicy::Standard)));
+}
+
+/// A copy with the same hash functions, so the two can be merged later
+#[no_mangle]
+pub unsafe extern "C" fn hwc_count_min_clone(sketch: *const HwcCountMin) -> *mut HwcCountMin {
+    return sketch.as_ref().map_or(ptr::null_mut(), |sketch| into_handle(HwcCountMin(sketch.0.clone())));
+}
+
+/// Adds count to key
+#[no_mangle]
+pub unsafe extern "C" fn hwc_count_min_insert(sketch: *mut HwcCountMin, key: u64, count: u64) {
+    if let Some(sketch) = sketch.as_mut() {
+        sketch.0.insert(key, count);
+    }
+}
+
+/// Takes count of key back out again
+#[no_mangle]
+pub unsafe extern "C" fn hwc_count_min_remove(sketch: *mut HwcCountMin, key: u64, count: u64) {
+    if let Some(sketch) = sketch.as_mut() {
+        sketch.0.remove(key, count);
+    }
+}
+
+/// Never below the count of key
+#[no_mangle]
+pub unsafe extern "C" fn hwc_count_min_estimate(sketch: *const HwcCountMin, key: u64) -> u64 {
+    return sketch.as_ref().map_or(0, |sketch| sketch.0.estimate(key));
+}
+
+/// Adds the counts of other into sketch
+#[no_mangle]
+pub unsafe extern "C" fn hwc_count_min_merge(sketch: *mut HwcCountMin, other: *const HwcCountMin) -> HwcStatus {
+    return merge_handles(sketch.as_mut().map(|sketch| &mut sketch.0), other.as_ref().map(|other| &other.0));
+}
+
+/// The sketch in the format of the sketch files, with its length stored in len
+#[no_mangle]
+pub unsafe extern "C" fn hwc_count_min_to_bytes(sketch: *const HwcCountMin, len: *mut usize) -> *mut u8 {
+    return sketch.as_ref().map_or(ptr::null_mut(), |sketch| bytes_out(sketch.0.to_bytes(), len));
+}
+
+/// The sketch of hwc_count_min_to_bytes, or null for bytes of anything else, including
+/// sketches with conservative updates, which cannot take counts back out
+#[no_mangle]
+pub unsafe extern "C" fn hwc_count_min_from_bytes(bytes: *const u8, len: usize) -> *mut HwcCountMin {
+    return match bytes_in(bytes, len).map(CountMin::from_bytes) {
+        Some(Ok(sketch)) if sketch.policy() == UpdatePolicy::Standard => into_handle(HwcCountMin(sketch)),
+        _ => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hwc_count_min_free(sketch: *mut HwcCountMin) {
+    free_handle(sketch);
+}
+
+/// 2^precision registers, or null unless precision is in 4..=18
+#[no_mangle]
+pub extern "C" fn hwc_hyperloglog_new(precision: u32) -> *mut HwcHyperLogLog {
+    if !(MIN_PRECISION..=MAX_PRECISION).contains(&precision) {
+        return ptr::null_mut();
+    }
+    return into_handle(HwcHyperLogLog(HyperLogLog::new(precision)));
+}
+
+/// A copy with the same hash function, so the two can be merged later
+#[no_mangle]
+pub unsafe extern "C" fn hwc_hyperloglog_clone(sketch: *const HwcHyperLogLog) -> *mut HwcHyperLogLog {
+    return sketch.as_ref().map_or(ptr::null_mut(), |sketch| into_handle(HwcHyperLogLog(sketch.0.clone())));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hwc_hyperloglog_insert(sketch: *mut HwcHyperLogLog, key: u64) {
+    if let Some(sketch) = sketch.as_mut() {
+        sketch.0.insert(key);
+    }
+}
+
+/// Estimated number of distinct keys inserted
+#[no_mangle]
+pub unsafe extern "C" fn hwc_hyperloglog_estimate(sketch: *const HwcHyperLogLog) -> f64 {
+    return sketch.as_ref().map_or(0.0, |sketch| sketch.0.estimate());
+}
+
+/// Counts the keys of other in sketch as well
+#[no_mangle]
+pub unsafe extern "C" fn hwc_hyperloglog_merge(sketch: *mut HwcHyperLogLog, other: *const HwcHyperLogLog) -> HwcStatus {
+    return merge_handles(sketch.as_mut().map(|sketch| &mut sketch.0), other.as_ref().map(|other| &other.0));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hwc_hyperloglog_to_bytes(sketch: *const HwcHyperLogLog, len: *mut usize) -> *mut u8 {
+    return sketch.as_ref().map_or(ptr::null_mut(), |sketch| bytes_out(sketch.0.to_bytes(), len));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hwc_hyperloglog_from_bytes(bytes: *const u8, len: usize) -> *mut HwcHyperLogLog {
+    return match bytes_in(bytes, len).map(HyperLogLog::from_bytes) {
+        Some(Ok(sketch)) => into_handle(HwcHyperLogLog(sketch)),
+        _ => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hwc_hyperloglog_free(sketch: *mut HwcHyperLogLog) {
+    free_handle(sketch);
+}
+
+/// 2^width_len counters, or null unless width_len is in 1..=32
+#[no_mangle]
+pub extern "C" fn hwc_norm_sketch_new(width_len: u32) -> *mut HwcNormSketch {
+    if !(1..=MAX_WIDTH_LEN).contains(&width_len) {
+        return ptr::null_mut();
+    }
+    return into_handle(HwcNormSketch(NormSketch::new(width_len)));
+}
+
+/// A copy with the same hash functions, so the two can be merged later
+#[no_mangle]
+pub unsafe extern "C" fn hwc_norm_sketch_clone(sketch: *const HwcNormSketch) -> *mut HwcNormSketch {
+    return sketch.as_ref().map_or(ptr::null_mut(), |sketch| into_handle(HwcNormSketch(sketch.0.clone())));
+}
+
+/// Adds delta, which may be negative, to the count of key
+#[no_mangle]
+pub unsafe extern "C" fn hwc_norm_sketch_update(sketch: *mut HwcNormSketch, key: u64, delta: i64) {
+    if let Some(sketch) = sketch.as_mut() {
+        sketch.0.insert(key, delta);
+    }
+}
+
+/// Unbiased estimate of the count of key
+#[no_mangle]
+pub unsafe extern "C" fn hwc_norm_sketch_estimate(sketch: *const HwcNormSketch, key: u64) -> i64 {
+    return sketch.as_ref().map_or(0, |sketch| sketch.0.estimate(key));
+}
+
+/// Estimated sum of the squared counts
+#[no_mangle]
+pub unsafe extern "C" fn hwc_norm_sketch_norm(sketch: *const HwcNormSketch) -> f64 {
+    return sketch.as_ref().map_or(0.0, |sketch| sketch.0.norm_f64());
+}
+
+/// Adds the counts of other into sketch
+#[no_mangle]
+pub unsafe extern "C" fn hwc_norm_sketch_merge(sketch: *mut HwcNormSketch, other: *const HwcNormSketch) -> HwcStatus {
+    return merge_handles(sketch.as_mut().map(|sketch| &mut sketch.0), other.as_ref().map(|other| &other.0));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hwc_norm_sketch_to_bytes(sketch: *const HwcNormSketch, len: *mut usize) -> *mut u8 {
+    return sketch.as_ref().map_or(ptr::null_mut(), |sketch| bytes_out(sketch.0.to_bytes(), len));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hwc_norm_sketch_from_bytes(bytes: *const u8, len: usize) -> *mut HwcNormSketch {
+    return match bytes_in(bytes, len).map(NormSketch::from_bytes) {
+        Some(Ok(sketch)) => into_handle(HwcNormSketch(sketch)),
+        _ => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hwc_norm_sketch_free(sketch: *mut HwcNormSketch) {
+    free_handle(sketch);
+}
+
+/// Table with the given number of buckets, or null for none
+#[no_mangle]
+pub extern "C" fn hwc_table_new(buckets: usize) -> *mut HwcTable {
+    if buckets == 0 {
+        return ptr::null_mut();
+    }
+    return into_handle(HwcTable(HwC::new(buckets)));
+}
+
+/// Adds delta, which may be negative, to the count of key. Keys whose count returns to zero
+/// are removed.
+#[no_mangle]
+pub unsafe extern "C" fn hwc_table_update(table: *mut HwcTable, key: u64, delta: i64) {
+    if let Some(table) = table.as_mut() {
+        table.0.insert(key, delta);
+    }
+}
+
+/// The exact count of key, zero for keys not in the table
+#[no_mangle]
+pub unsafe extern "C" fn hwc_table_get(table: *const HwcTable, key: u64) -> i64 {
+    return table.as_ref().map_or(0, |table| table.0.get(key));
+}
+
+/// Exact sum of the squared counts
+#[no_mangle]
+pub unsafe extern "C" fn hwc_table_norm(table: *const HwcTable) -> u64 {
+    return table.as_ref().map_or(0, |table| table.0.norm());
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hwc_table_to_bytes(table: *const HwcTable, len: *mut usize) -> *mut u8 {
+    return table.as_ref().map_or(ptr::null_mut(), |table| bytes_out(table.0.to_bytes(), len));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hwc_table_from_bytes(bytes: *const u8, len: usize) -> *mut HwcTable {
+    return match bytes_in(bytes, len).map(HwC::from_bytes) {
+        Some(Ok(table)) => into_handle(HwcTable(table)),
+        _ => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hwc_table_free(table: *mut HwcTable) {
+    free_handle(table);
+}
+
+/// Sized for n keys at a false positive rate of at most fpr, or null unless fpr is in (0, 1)
+/// and the filter needs at most 2^61 bits
+#[no_mangle]
+pub extern "C" fn hwc_bloom_filter_new(n: usize, fpr: f64) -> *mut HwcBloomFilter {
+    return match checked_optimal_dimensions(n, fpr) {
+        Some((bits_len, k)) => into_handle(HwcBloomFilter(BloomFilter::new(bits_len, k))),
+        None => ptr::null_mut(),
+    }
+}
+
+/// A copy with the same hash functions, so the two can be merged later
+#[no_mangle]
+pub unsafe extern "C" fn hwc_bloom_filter_clone(filter: *const HwcBloomFilter) -> *mut HwcBloomFilter {
+    return filter.as_ref().map_or(ptr::null_mut(), |filter| into_handle(HwcBloomFilter(filter.0.clone())));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hwc_bloom_filter_insert(filter: *mut HwcBloomFilter, key: u64) {
+    if let Some(filter) = filter.as_mut() {
+        filter.0.insert(key);
+    }
+}
+
+/// Always true for an inserted key, and true for others at about the false positive rate
+#[no_mangle]
+pub unsafe extern "C" fn hwc_bloom_filter_contains(filter: *const HwcBloomFilter, key: u64) -> bool {
+    return filter.as_ref().is_some_and(|filter| filter.0.contains(key));
+}
+
+/// Adds the keys of other to filter
+#[no_mangle]
+pub unsafe extern "C" fn hwc_bloom_filter_merge(filter: *mut HwcBloomFilter, other: *const HwcBloomFilter) -> HwcStatus {
+    return merge_handles(filter.as_mut().map(|filter| &mut filter.0), other.as_ref().map(|other| &other.0));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hwc_bloom_filter_free(filter: *mut HwcBloomFilter) {
+    free_handle(filter);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The functions called as C would call them, through handles and byte buffers
+    #[test]
+    fn handles_update_merge_and_round_trip() {
+        unsafe {
+            let sketch: *mut HwcCountMin = hwc_count_min_new(4, 8);
+            let shard: *mut HwcCountMin = hwc_count_min_clone(sketch);
+            hwc_count_min_insert(sketch, 7, 5);
+            hwc_count_min_insert(shard, 7, 3);
+            hwc_count_min_remove(shard, 7, 1);
+            assert_eq!(hwc_count_min_merge(sketch, shard), HwcStatus::Ok);
+            assert!(hwc_count_min_estimate(sketch, 7) >= 7);
+            let stranger: *mut HwcCountMin = hwc_count_min_new(4, 8);
+            assert_eq!(hwc_count_min_merge(sketch, stranger), HwcStatus::SeedMismatch);
+            assert_eq!(hwc_count_min_merge(sketch, ptr::null()), HwcStatus::NullPointer);
+
+            let mut len: usize = 0;
+            let bytes: *mut u8 = hwc_count_min_to_bytes(sketch, &mut len);
+            let decoded: *mut HwcCountMin = hwc_count_min_from_bytes(bytes, len);
+            assert_eq!(hwc_count_min_estimate(decoded, 7), hwc_count_min_estimate(sketch, 7));
+            assert!(hwc_count_min_from_bytes(bytes, len - 1).is_null());
+            hwc_bytes_free(bytes, len);
+            [sketch, shard, stranger, decoded].into_iter().for_each(|handle| hwc_count_min_free(handle));
+
+            let table: *mut HwcTable = hwc_table_new(64);
+            hwc_table_update(table, 3, 4);
+            hwc_table_update(table, 3, -1);
+            assert_eq!((hwc_table_get(table, 3), hwc_table_norm(table)), (3, 9));
+            hwc_table_free(table);
+
+            let distinct: *mut HwcHyperLogLog = hwc_hyperloglog_new(12);
+            (0..1000).for_each(|key| hwc_hyperloglog_insert(distinct, key % 200));
+            assert!((hwc_hyperloglog_estimate(distinct) - 200.0).abs() < 10.0);
+            hwc_hyperloglog_free(distinct);
+
+            // Arguments the library would panic on give null handles
+            assert!(hwc_hyperloglog_new(40).is_null() && hwc_count_min_new(0, 8).is_null() && hwc_bloom_filter_new(10, 1.5).is_null());
+            assert!(hwc_bloom_filter_new(usize::MAX, 0.01).is_null());
+            let conservative: Vec<u8> = CountMin::<u64>::new(4, 8, UpdatePolicy::Conservative).to_bytes();
+            assert!(hwc_count_min_from_bytes(conservative.as_ptr(), conservative.len()).is_null());
+            assert_eq!(hwc_norm_sketch_estimate(ptr::null(), 1), 0);
+            hwc_norm_sketch_free(ptr::null_mut());
+        }
+    }
+}
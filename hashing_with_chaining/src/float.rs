@@ -0,0 +1,33 @@
+// The float functions of std that core lacks, from libm when built without std. With std
+// they are the usual methods, so results do not change with the feature.
+
+macro_rules! float_function {
+    ($name:ident, $libm:ident) => {
+        pub(crate) fn $name(x: f64) -> f64 {
+            #[cfg(feature = "std")]
+            return x.$name();
+            #[cfg(not(feature = "std"))]
+            return libm::$libm(x);
+        }
+    };
+}
+
+float_function!(ceil, ceil);
+float_function!(exp, exp);
+float_function!(ln, log);
+float_function!(round, round);
+float_function!(sqrt, sqrt);
+
+pub(crate) fn powi(x: f64, n: i32) -> f64 {
+    #[cfg(feature = "std")]
+    return x.powi(n);
+    #[cfg(not(feature = "std"))]
+    return libm::pow(x, n as f64);
+}
+
+pub(crate) fn powf(x: f64, y: f64) -> f64 {
+    #[cfg(feature = "std")]
+    return x.powf(y);
+    #[cfg(not(feature = "std"))]
+    return libm::pow(x, y);
+}
@@ -0,0 +1,79 @@
+use rand::prelude::*;
+
+// Matrices are vectors of rows. Arithmetic wraps, so products are exact modulo 2^64, which
+// is plain equality whenever the true entries fit in an i64.
+
+fn multiply_vector(matrix: &[Vec<i64>], vector: &[i64]) -> Vec<i64> {
+    return matrix.iter()
+        .map(|row| row.iter().zip(vector).fold(0i64, |sum, (a, x)| sum.wrapping_add(a.wrapping_mul(*x))))
+        .collect();
+}
+
+// The product A B the usual way, in O(n^3) time
+pub fn multiply(a: &[Vec<i64>], b: &[Vec<i64>]) -> Vec<Vec<i64>> {
+    let columns: usize = b.first().map_or(0, |row| row.len());
+    return a.iter().map(|row| {
+        let mut product: Vec<i64> = vec![0; columns];
+        for (x, b_row) in row.iter().zip(b) {
+            for (entry, y) in product.iter_mut().zip(b_row) {
+                *entry = entry.wrapping_add(x.wrapping_mul(*y));
+            }
+        }
+        return product;
+    }).collect();
+}
+
+// Freivalds' check of A B = C: compares A (B r) with C r for random r in {-1, 1}^n, which
+// takes three matrix-vector products, O(n^2) time, instead of computing A B. When A B != C,
+// some row d of A B - C is nonzero, and for a coordinate j with d_j != 0 the two choices of
+// r_j give values of d . r that differ by 2 d_j, so at most one of them is zero. A round
+// thus catches a wrong C with probability at least 1/2, and false is certain to be right.
+// A single wrong entry is always caught, since d . r = +-d_j then.
+pub fn freivalds_verify(a: &[Vec<i64>], b: &[Vec<i64>], c: &[Vec<i64>], rounds: usize) -> bool {
+    assert!(a.len() == c.len() && a.first().map_or(0, |row| row.len()) == b.len(), "Matrix dimensions do not match");
+    let mut rng = thread_rng();
+    let columns: usize = c.first().map_or(0, |row| row.len());
+    for _ in 0..rounds {
+        let r: Vec<i64> = (0..columns).map(|_| if rng.gen::<bool>() { 1 } else { -1 }).collect();
+        if multiply_vector(a, &multiply_vector(b, &r)) != multiply_vector(c, &r) {
+            return false;
+        }
+    }
+    return true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_matrix(n: usize) -> Vec<Vec<i64>> {
+        let mut rng = thread_rng();
+        return (0..n).map(|_| (0..n).map(|_| rng.gen_range(-1000..1000)).collect()).collect();
+    }
+
+    #[test]
+    fn detects_injected_errors() {
+        let (a, b) = (random_matrix(40), random_matrix(40));
+        let mut c: Vec<Vec<i64>> = multiply(&a, &b);
+        assert!(freivalds_verify(&a, &b, &c, 20));
+
+        // A single wrong entry is caught by every round
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let (i, j) = (rng.gen_range(0..40), rng.gen_range(0..40));
+            c[i][j] += 1;
+            assert!(!freivalds_verify(&a, &b, &c, 1));
+            c[i][j] -= 1;
+        }
+
+        // +1 and -1 in one row cancel for half the vectors, so a round misses them half of
+        // the time and k rounds miss them 2^-k of the time
+        c[3][5] += 1;
+        c[3][7] -= 1;
+        for rounds in [1, 2, 3] {
+            let missed: usize = (0..4000).filter(|_| freivalds_verify(&a, &b, &c, rounds)).count();
+            let expected: f64 = 4000.0 / (1 << rounds) as f64;
+            assert!((missed as f64 - expected).abs() < 0.15 * expected, "{} {}", rounds, missed);
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::HashSet;
+use rand::prelude::*;
+use crate::las_vegas::las_vegas;
+use crate::subset::floyd_sample;
+
+// Disjoint sets of vertices, merged as edges get contracted
+struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+    sets: usize,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> UnionFind {
+        return UnionFind {
+            parent: (0..len).collect(),
+            rank: vec![0; len],
+            sets: len,
+        }
+    }
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        return x;
+    }
+    // Merges the sets of a and b, false if they already were one set
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        let (low, high) = if self.rank[a] < self.rank[b] { (a, b) } else { (b, a) };
+        self.parent[low] = high;
+        if self.rank[low] == self.rank[high] {
+            self.rank[high] += 1;
+        }
+        self.sets -= 1;
+        return true;
+    }
+}
+
+// A cut of a graph: side[v] tells which part vertex v is in and size counts the edges
+// between the parts
+#[derive(Clone, Debug)]
+pub struct Cut {
+    pub size: usize,
+    pub side: Vec<bool>,
+}
+
+// Undirected multigraph on vertices 0 to n - 1. Parallel edges count separately towards cuts.
+#[derive(Clone, Debug)]
+pub struct Graph {
+    vertices: usize,
+    edges: Vec<(usize, usize)>,
+}
+
+impl Graph {
+    pub fn new(vertices: usize) -> Graph {
+        return Graph { vertices, edges: Vec::new() }
+    }
+    pub fn add_edge(&mut self, u: usize, v: usize) {
+        assert!(u < self.vertices && v < self.vertices, "Vertex out of range");
+        if u != v {
+            self.edges.push((u, v));
+        }
+    }
+    pub fn vertices(&self) -> usize {
+        return self.vertices;
+    }
+    pub fn edges(&self) -> &[(usize, usize)] {
+        return &self.edges;
+    }
+    // Neighbors of every vertex, a neighbor appearing once per parallel edge
+    pub fn adjacency(&self) -> Vec<Vec<usize>> {
+        let mut adjacency: Vec<Vec<usize>> = vec![Vec::new(); self.vertices];
+        for (u, v) in &self.edges {
+            adjacency[*u].push(*v);
+            adjacency[*v].push(*u);
+        }
+        return adjacency;
+    }
+    pub fn degrees(&self) -> Vec<usize> {
+        let mut degrees: Vec<usize> = vec![0; self.vertices];
+        self.edges.iter().for_each(|(u, v)| { degrees[*u] += 1; degrees[*v] += 1; });
+        return degrees;
+    }
+
+    // The random graph generators take the generator to draw from, so an experiment that
+    // passes a seeded StdRng gets the same graph on every run.
+
+    // Erdős–Rényi G(n, p): every pair of vertices is an edge independently with probability
+    // p. Instead of a coin per pair, the gaps between edges in the order of the pairs are
+    // drawn from their geometric distribution (Batagelj and Brandes), O(n + m) time.
+    pub fn erdos_renyi(n: usize, p: f64, rng: &mut impl Rng) -> Graph {
+        assert!((0.0..=1.0).contains(&p), "Needs a probability");
+        let mut graph: Graph = Graph::new(n);
+        if p == 0.0 {
+            return graph;
+        }
+        let log_q: f64 = (1.0 - p).ln();
+        // Pair (v, w) with w < v, walked through row by row
+        let (mut v, mut w): (usize, i64) = (1, -1);
+        while v < n {
+            let skip: f64 = if p == 1.0 { 0.0 } else { ((1.0 - rng.gen::<f64>()).ln() / log_q).floor() };
+            w += 1 + skip.min(n as f64 * n as f64) as i64;
+            while v < n && w >= v as i64 {
+                w -= v as i64;
+                v += 1;
+            }
+            if v < n {
+                graph.add_edge(v, w as usize);
+            }
+        }
+        return graph;
+    }
+    // Erdős–Rényi G(n, m): a uniformly random simple graph with exactly m edges, picked as a
+    // random m-subset of the n (n - 1) / 2 pairs numbered row by row, pair (v, w) with w < v
+    // having number v (v - 1) / 2 + w
+    pub fn gnm(n: usize, m: usize, rng: &mut impl Rng) -> Graph {
+        let pairs: u64 = n as u64 * (n as u64).saturating_sub(1) / 2;
+        assert!(m as u64 <= pairs, "Needs m <= n (n - 1) / 2");
+        let mut graph: Graph = Graph::new(n);
+        for pair in floyd_sample(pairs, m, rng) {
+            let mut v: u64 = ((1.0 + (1.0 + 8.0 * pair as f64).sqrt()) / 2.0) as u64;
+            // The square root can be off by one for large pair numbers
+            while v * (v - 1) / 2 > pair {
+                v -= 1;
+            }
+            while (v + 1) * v / 2 <= pair {
+                v += 1;
+            }
+            graph.add_edge(v as usize, (pair - v * (v - 1) / 2) as usize);
+        }
+        return graph;
+    }
+    // Uniform-ish random d-regular simple graph: stubs are paired at random, redrawing pairs
+    // that would form a loop or a parallel edge, and starting over when the remaining stubs
+    // cannot be paired (Steger and Wormald), which is close to uniform for small d
+    pub fn random_regular(n: usize, d: usize, rng: &mut impl Rng) -> Graph {
+        assert!(d < n && (n * d).is_multiple_of(2), "Needs d < n and n d even");
+        return las_vegas(usize::MAX, || {
+            let mut graph: Graph = Graph::new(n);
+            let mut stubs: Vec<usize> = (0..n).flat_map(|v| std::iter::repeat_n(v, d)).collect();
+            let mut edges: HashSet<(usize, usize)> = HashSet::new();
+            while !stubs.is_empty() {
+                let mut paired: bool = false;
+                for _ in 0..100 {
+                    let (i, j) = (rng.gen_range(0..stubs.len()), rng.gen_range(0..stubs.len()));
+                    let (u, v) = (stubs[i], stubs[j]);
+                    if u != v && edges.insert((u.min(v), u.max(v))) {
+                        stubs.swap_remove(i.max(j));
+                        stubs.swap_remove(i.min(j));
+                        graph.add_edge(u, v);
+                        paired = true;
+                        break;
+                    }
+                }
+                if !paired {
+                    return None;
+                }
+            }
+            return Some(graph);
+        }).value.unwrap();
+    }
+    // Barabási–Albert preferential attachment: a clique on m + 1 vertices, then every new
+    // vertex links to m distinct earlier vertices picked with probability proportional to
+    // their degree, which gives a power law degree distribution with exponent 3. Picking a
+    // uniform endpoint of the edges so far is picking proportionally to degree.
+    pub fn preferential_attachment(n: usize, m: usize, rng: &mut impl Rng) -> Graph {
+        assert!(m >= 1 && n > m, "Needs 1 <= m < n");
+        let mut graph: Graph = Graph::new(n);
+        let mut endpoints: Vec<usize> = Vec::new();
+        for u in 0..=m {
+            for v in 0..u {
+                graph.add_edge(u, v);
+                endpoints.extend([u, v]);
+            }
+        }
+        for v in m + 1..n {
+            let mut targets: Vec<usize> = Vec::with_capacity(m);
+            while targets.len() < m {
+                let target: usize = endpoints[rng.gen_range(0..endpoints.len())];
+                if !targets.contains(&target) {
+                    targets.push(target);
+                }
+            }
+            for target in targets {
+                graph.add_edge(v, target);
+                endpoints.extend([v, target]);
+            }
+        }
+        return graph;
+    }
+    // Number of edges between the two parts of side
+    pub fn cut_size(&self, side: &[bool]) -> usize {
+        return self.edges.iter().filter(|(u, v)| side[*u] != side[*v]).count();
+    }
+    // Contracts uniformly random edges until target super vertices are left, returning the
+    // super vertex of every vertex. Going through the edges in random order and skipping the
+    // ones inside a super vertex picks every contraction uniformly among the remaining edges.
+    // A disconnected graph may stop above target once the edges run out.
+    fn contract_to(&self, target: usize, rng: &mut impl Rng) -> UnionFind {
+        let mut sets: UnionFind = UnionFind::new(self.vertices);
+        let mut order: Vec<usize> = (0..self.edges.len()).collect();
+        order.shuffle(rng);
+        for edge in order {
+            if sets.sets <= target {
+                break;
+            }
+            let (u, v) = self.edges[edge];
+            sets.union(u, v);
+        }
+        return sets;
+    }
+    // The graph of the super vertices after contracting down to target of them, and the
+    // super vertex of every vertex. Edges inside a super vertex disappear.
+    fn contract(&self, target: usize, rng: &mut impl Rng) -> (Graph, Vec<usize>) {
+        let mut sets: UnionFind = self.contract_to(target, rng);
+        let mut labels: Vec<usize> = vec![usize::MAX; self.vertices];
+        let mut super_vertices: usize = 0;
+        for v in 0..self.vertices {
+            let root: usize = sets.find(v);
+            if labels[root] == usize::MAX {
+                labels[root] = super_vertices;
+                super_vertices += 1;
+            }
+            labels[v] = labels[root];
+        }
+        let mut contracted: Graph = Graph::new(super_vertices);
+        self.edges.iter().for_each(|(u, v)| contracted.add_edge(labels[*u], labels[*v]));
+        return (contracted, labels);
+    }
+    // Minimum cut by trying every side containing vertex 0, for small graphs
+    fn brute_force_min_cut(&self) -> Cut {
+        let mut best: Cut = Cut { size: usize::MAX, side: Vec::new() };
+        for mask in 0..(1usize << (self.vertices - 1)) - 1 {
+            let side: Vec<bool> = (0..self.vertices).map(|v| v == 0 || (mask >> (v - 1)) & 1 == 1).collect();
+            let size: usize = self.cut_size(&side);
+            if size < best.size {
+                best = Cut { size, side };
+            }
+        }
+        return best;
+    }
+    // One run of Karger's contraction down to two super vertices. The result is a minimum
+    // cut with probability at least 2 / (n (n - 1)), since a fixed minimum cut of size k
+    // survives the contraction from i vertices with probability at least 1 - 2 / i.
+    pub fn karger_trial(&self, rng: &mut impl Rng) -> Cut {
+        assert!(self.vertices >= 2, "Needs at least two vertices");
+        let mut sets: UnionFind = self.contract_to(2, rng);
+        let first: usize = sets.find(0);
+        let side: Vec<bool> = (0..self.vertices).map(|v| sets.find(v) == first).collect();
+        return Cut { size: self.cut_size(&side), side }
+    }
+    // Best cut over the given number of Karger trials
+    pub fn karger(&self, trials: usize) -> Cut {
+        let mut rng = thread_rng();
+        return (0..trials.max(1))
+            .map(|_| self.karger_trial(&mut rng))
+            .min_by_key(|cut| cut.size)
+            .unwrap();
+    }
+    // One run of Karger-Stein. A minimum cut survives the contraction from n down to
+    // 1 + n / sqrt(2) vertices with probability at least 1/2, so the algorithm contracts that
+    // far twice independently and recurses on both. A run finds the minimum cut with
+    // probability Omega(1 / log n) in O(n^2 log n) time, against O(n^2) for a single Karger
+    // trial that only succeeds with probability 2 / (n (n - 1)).
+    pub fn karger_stein_trial(&self, rng: &mut impl Rng) -> Cut {
+        assert!(self.vertices >= 2, "Needs at least two vertices");
+        if self.edges.is_empty() {
+            let side: Vec<bool> = (0..self.vertices).map(|v| v == 0).collect();
+            return Cut { size: 0, side }
+        }
+        if self.vertices <= 6 {
+            return self.brute_force_min_cut();
+        }
+        let target: usize = (1.0 + self.vertices as f64 / 2f64.sqrt()).ceil() as usize;
+        let mut best: Cut = Cut { size: usize::MAX, side: Vec::new() };
+        for _ in 0..2 {
+            let (contracted, labels) = self.contract(target, rng);
+            let cut: Cut = contracted.karger_stein_trial(rng);
+            if cut.size < best.size {
+                best = Cut { size: cut.size, side: labels.iter().map(|label| cut.side[*label]).collect() };
+            }
+        }
+        return best;
+    }
+    // Minimum cut by log2(n)^2 Karger-Stein runs, which all fail with probability O(1 / n)
+    pub fn karger_stein_min_cut(&self) -> Cut {
+        let runs: usize = (self.vertices as f64).log2().ceil().powi(2).max(1.0) as usize;
+        let mut rng = thread_rng();
+        return (0..runs)
+            .map(|_| self.karger_stein_trial(&mut rng))
+            .min_by_key(|cut| cut.size)
+            .unwrap();
+    }
+    // Minimum cut by n (n - 1) / 2 * ln n Karger trials, which all miss the minimum cut with
+    // probability at most (1 - 2 / (n (n - 1)))^(n (n - 1) / 2 * ln n) <= 1 / n
+    pub fn karger_min_cut(&self) -> Cut {
+        let n: f64 = self.vertices as f64;
+        return self.karger((n * (n - 1.0) / 2.0 * n.ln()).ceil() as usize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+
+    // Adds all edges among vertices offset to offset + k - 1
+    fn add_clique(graph: &mut Graph, offset: usize, k: usize) {
+        for u in 0..k {
+            for v in u + 1..k {
+                graph.add_edge(offset + u, offset + v);
+            }
+        }
+    }
+
+    #[test]
+    fn finds_known_minimum_cuts() {
+        // Two cliques on 8 vertices joined by two edges, which form the only cut of size 2
+        let mut barbell: Graph = Graph::new(16);
+        add_clique(&mut barbell, 0, 8);
+        add_clique(&mut barbell, 8, 8);
+        barbell.add_edge(0, 8);
+        barbell.add_edge(1, 9);
+        let cut: Cut = barbell.karger_min_cut();
+        assert_eq!(cut.size, 2);
+        assert!((0..8).all(|v| cut.side[v] == cut.side[0]) && (8..16).all(|v| cut.side[v] != cut.side[0]));
+
+        let mut cycle: Graph = Graph::new(20);
+        (0..20).for_each(|v| cycle.add_edge(v, (v + 1) % 20));
+        assert_eq!(cycle.karger_min_cut().size, 2);
+
+        // Every cut of the complete graph on 10 vertices has at least 9 edges, with equality
+        // exactly when one side is a single vertex
+        let mut complete: Graph = Graph::new(10);
+        add_clique(&mut complete, 0, 10);
+        let cut: Cut = complete.karger_min_cut();
+        assert_eq!((cut.size, complete.cut_size(&cut.side)), (9, 9));
+        assert!(cut.side.iter().filter(|side| **side).count() % 8 == 1);
+    }
+
+    #[test]
+    fn karger_stein_matches_brute_force() {
+        let mut barbell: Graph = Graph::new(40);
+        add_clique(&mut barbell, 0, 20);
+        add_clique(&mut barbell, 20, 20);
+        (0..3).for_each(|i| barbell.add_edge(i, 20 + i));
+        let cut: Cut = barbell.karger_stein_min_cut();
+        assert_eq!((cut.size, barbell.cut_size(&cut.side)), (3, 3));
+        assert!((0..20).all(|v| cut.side[v] == cut.side[0]) && (20..40).all(|v| cut.side[v] != cut.side[0]));
+
+        // Random multigraphs on 12 vertices, small enough to try all 2^11 cuts
+        let mut rng = thread_rng();
+        for _ in 0..20 {
+            let mut graph: Graph = Graph::new(12);
+            (0..40).for_each(|_| graph.add_edge(rng.gen_range(0..12), rng.gen_range(0..12)));
+            assert_eq!(graph.karger_stein_min_cut().size, graph.brute_force_min_cut().size);
+        }
+    }
+
+    #[test]
+    fn generators_have_the_expected_shape() {
+        let mut rng: StdRng = StdRng::seed_from_u64(7);
+        // n (n - 1) / 2 * p = 4995 edges expected, with standard deviation about 70
+        let random: Graph = Graph::erdos_renyi(1000, 0.01, &mut rng);
+        assert!((random.edges().len() as f64 - 4995.0).abs() < 400.0, "{}", random.edges().len());
+        assert_eq!(Graph::erdos_renyi(20, 1.0, &mut rng).edges().len(), 190);
+        assert!(Graph::erdos_renyi(20, 0.0, &mut rng).edges().is_empty());
+        let edges: HashSet<(usize, usize)> = random.edges().iter().map(|(u, v)| (*u.min(v), *u.max(v))).collect();
+        assert_eq!(edges.len(), random.edges().len());
+
+        let regular: Graph = Graph::random_regular(500, 5, &mut rng);
+        assert!(regular.degrees().iter().all(|degree| *degree == 5));
+        let edges: HashSet<(usize, usize)> = regular.edges().iter().map(|(u, v)| (*u.min(v), *u.max(v))).collect();
+        assert_eq!(edges.len(), 1250);
+
+        let dense: Graph = Graph::gnm(50, 1000, &mut rng);
+        let edges: HashSet<(usize, usize)> = dense.edges().iter().map(|(u, v)| (*u.min(v), *u.max(v))).collect();
+        assert_eq!(edges.len(), 1000);
+        assert_eq!(Graph::gnm(20, 190, &mut rng).degrees(), vec![19; 20]);
+
+        let attachment: Graph = Graph::preferential_attachment(10_000, 3, &mut rng);
+        let degrees: Vec<usize> = attachment.degrees();
+        assert_eq!(attachment.edges().len(), 6 + 3 * (10_000 - 4));
+        assert!(degrees.iter().all(|degree| *degree >= 3) && *degrees.iter().max().unwrap() > 100);
+
+        // The same seed gives the same graph
+        let again: Graph = Graph::erdos_renyi(1000, 0.01, &mut StdRng::seed_from_u64(7));
+        assert_eq!(again.edges(), random.edges());
+    }
+}
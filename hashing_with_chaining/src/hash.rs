@@ -0,0 +1,137 @@
+use alloc::vec::Vec;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use crate::distributions::standard_normal;
+
+#[cfg(feature = "std")]
+pub fn random_generator(from: u32, to: u32) -> u32 {
+    let mut rng = thread_rng();
+    return rng.gen_range(from..to);
+}
+
+// Standard normal sample from the thread's generator, by the ziggurat
+#[cfg(feature = "std")]
+pub fn gaussian() -> f64 {
+    return standard_normal(&mut thread_rng());
+}
+
+// The hash function is c-universal
+pub const C: usize = 2;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SeededHash {
+    l: u32,
+    a: u128,
+    b: u128,
+}
+
+impl SeededHash {
+    #[cfg(feature = "std")]
+    pub fn new(hash_len: u32) -> SeededHash {
+        return SeededHash::with_rng(hash_len, &mut thread_rng());
+    }
+    // Seeds drawn from rng, which builds without std have to pass in
+    pub fn with_rng(hash_len: u32, rng: &mut impl Rng) -> SeededHash {
+        return SeededHash {
+            a: rng.gen(),
+            b: rng.gen(),
+            l: hash_len
+        }
+    }
+    // Rebuilds a hash function from the seeds of another one
+    pub fn with_seeds(hash_len: u32, a: u128, b: u128) -> SeededHash {
+        return SeededHash {
+            a,
+            b,
+            l: hash_len
+        }
+    }
+    pub fn seeds(&self) -> (u128, u128) {
+        return (self.a, self.b);
+    }
+    pub fn hash_len(&self) -> u32 {
+        return self.l;
+    }
+    // Multiply-add-shift hashing as from lecture notes (https://arxiv.org/pdf/1504.06804.pdf) at 3.3,
+    // done in 128 bits so that 64-bit keys hash into l bits
+    pub fn hash(&self, x: u64) -> usize {
+        let multiply_add: u128 = self.a.wrapping_mul(x as u128).wrapping_add(self.b);
+        return multiply_add.checked_shr(128 - self.l).unwrap_or(0) as usize;
+    }
+}
+
+// Mersenne prime 2^61 - 1, the field IndependentHash works in
+const MERSENNE_61: u64 = (1 << 61) - 1;
+
+// A random polynomial of degree k-1 over the integers modulo 2^61 - 1, which makes the
+// hash values of any k distinct keys below 2^61 - 1 independent and uniform. Sketches use
+// it where the analysis needs more than 2-independence, like 4-wise independent signs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IndependentHash {
+    coefficients: Vec<u64>,
+}
+
+impl IndependentHash {
+    #[cfg(feature = "std")]
+    pub fn new(k: usize) -> IndependentHash {
+        return IndependentHash::with_rng(k, &mut thread_rng());
+    }
+    pub fn with_rng(k: usize, rng: &mut impl Rng) -> IndependentHash {
+        return IndependentHash {
+            coefficients: (0..k).map(|_| rng.gen_range(0..MERSENNE_61)).collect()
+        }
+    }
+    // Rebuilds a hash function from the coefficients of another one
+    pub fn with_coefficients(coefficients: Vec<u64>) -> IndependentHash {
+        assert!(coefficients.iter().all(|c| *c < MERSENNE_61), "Coefficients must be below 2^61 - 1");
+        return IndependentHash { coefficients }
+    }
+    pub fn coefficients(&self) -> &[u64] {
+        return &self.coefficients;
+    }
+    fn mod_mersenne(x: u128) -> u64 {
+        let folded: u128 = (x & MERSENNE_61 as u128) + (x >> 61);
+        let folded: u64 = ((folded & MERSENNE_61 as u128) + (folded >> 61)) as u64;
+        return if folded >= MERSENNE_61 { folded - MERSENNE_61 } else { folded };
+    }
+    // Horner evaluation of the polynomial, a value in [0, 2^61 - 1)
+    pub fn hash(&self, x: u64) -> u64 {
+        let x: u64 = x % MERSENNE_61;
+        let mut value: u64 = 0;
+        for coefficient in &self.coefficients {
+            value = IndependentHash::mod_mersenne(value as u128 * x as u128 + *coefficient as u128);
+        }
+        return value;
+    }
+    // +1 or -1 from the lowest bit of the hash value
+    pub fn sign(&self, x: u64) -> i64 {
+        return if self.hash(x) & 1 == 0 { 1 } else { -1 };
+    }
+    pub fn independence(&self) -> usize {
+        return self.coefficients.len();
+    }
+}
+
+// Base of the polynomial hash_bytes evaluates, a fixed value below 2^61 - 1
+const BYTES_BASE: u64 = 0x1f3d_5b79_c2e4_a687;
+
+// A key below 2^61 - 1 for a byte string, such as a word or an address, by evaluating the
+// 8-byte words of the string as a polynomial at a fixed point modulo 2^61 - 1, starting from
+// the length so strings that differ only in trailing zero bytes differ. Two distinct strings
+// of up to l words collide with probability about l / 2^61 over the choice of the point, and
+// the point is fixed so the same string gets the same key in every run.
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut value: u64 = bytes.len() as u64 % MERSENNE_61;
+    for chunk in bytes.chunks(8) {
+        let mut word: [u8; 8] = [0; 8];
+        word[..chunk.len()].copy_from_slice(chunk);
+        let word: u64 = u64::from_le_bytes(word) % MERSENNE_61;
+        value = IndependentHash::mod_mersenne(value as u128 * BYTES_BASE as u128 + word as u128);
+    }
+    return value;
+}
+
+pub fn log2u(x: usize) -> u32 {
+    x.ilog2()
+}
@@ -0,0 +1,131 @@
+use std::f64::consts::E;
+use crate::count_min::CountMin;
+use crate::count_sketch::CountSketch;
+use crate::counter::Counter;
+
+// A sketch answering point queries within an additive error that follows from its width
+// and the stream it has seen
+pub trait PointQuerySketch {
+    type Delta: Copy;
+    type Estimate;
+    fn insert(&mut self, key: u64, delta: Self::Delta);
+    fn estimate(&self, key: u64) -> Self::Estimate;
+    // Error the estimates stay within with high probability, for the stream so far
+    fn error_bound(&self) -> f64;
+    fn space_usage(&self) -> usize;
+}
+
+// Count-Min overestimates by at most e / width times the total count, with probability
+// 1 - e^-depth. The total is read off the first row, so the bound only holds for
+// standard updates.
+impl<C: Counter> PointQuerySketch for CountMin<C> {
+    type Delta = C;
+    type Estimate = C;
+    fn insert(&mut self, key: u64, delta: C) {
+        CountMin::insert(self, key, delta);
+    }
+    fn estimate(&self, key: u64) -> C {
+        return CountMin::estimate(self, key);
+    }
+    fn error_bound(&self) -> f64 {
+        return E / self.width() as f64 * self.total().to_f64();
+    }
+    fn space_usage(&self) -> usize {
+        return CountMin::space_usage(self);
+    }
+}
+
+// Count Sketch is off by at most sqrt(3 F2 / width), with F2 estimated by the sketch itself
+impl PointQuerySketch for CountSketch {
+    type Delta = i64;
+    type Estimate = i64;
+    fn insert(&mut self, key: u64, delta: i64) {
+        CountSketch::insert(self, key, delta);
+    }
+    fn estimate(&self, key: u64) -> i64 {
+        return CountSketch::estimate(self, key);
+    }
+    fn error_bound(&self) -> f64 {
+        return (3.0 * self.norm() as f64 / self.width() as f64).sqrt();
+    }
+    fn space_usage(&self) -> usize {
+        return CountSketch::space_usage(self);
+    }
+}
+
+// One stream kept in several sketches of growing size and accuracy. Every update goes to
+// all levels, and a query is answered by the smallest level whose error bound meets the
+// requested one, so a single pass serves coarse overviews from a level that fits in cache
+// as well as precise follow-ups from the largest level.
+pub struct SketchHierarchy<S: PointQuerySketch> {
+    // Smallest level first
+    levels: Vec<S>,
+}
+
+impl<S: PointQuerySketch> SketchHierarchy<S> {
+    pub fn new(mut levels: Vec<S>) -> SketchHierarchy<S> {
+        assert!(!levels.is_empty(), "The hierarchy needs at least one level");
+        levels.sort_by_key(|level| level.space_usage());
+        return SketchHierarchy { levels }
+    }
+    pub fn insert(&mut self, key: u64, delta: S::Delta) {
+        for level in &mut self.levels {
+            level.insert(key, delta);
+        }
+    }
+    // Index of the smallest level whose error bound is at most max_error, if any
+    pub fn level_for(&self, max_error: f64) -> Option<usize> {
+        return self.levels.iter().position(|level| level.error_bound() <= max_error);
+    }
+    // Estimate of key from the smallest level meeting max_error, with that level's bound.
+    // None if even the largest level is not accurate enough.
+    pub fn estimate(&self, key: u64, max_error: f64) -> Option<(S::Estimate, f64)> {
+        let level: &S = &self.levels[self.level_for(max_error)?];
+        return Some((level.estimate(key), level.error_bound()));
+    }
+    // Estimate of key from the largest level, the most accurate one
+    pub fn estimate_best(&self, key: u64) -> (S::Estimate, f64) {
+        let level: &S = self.levels.last().unwrap();
+        return (level.estimate(key), level.error_bound());
+    }
+    pub fn levels(&self) -> &[S] {
+        return &self.levels;
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.levels.iter().map(|level| level.space_usage()).sum();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::count_min::UpdatePolicy;
+
+    #[test]
+    fn queries_use_the_cheapest_accurate_level() {
+        // Estimates only stay within the bounds with high probability, so the sketches are
+        // deeper than usual to keep the checks below from failing now and then
+        let mut hierarchy: SketchHierarchy<CountMin> = SketchHierarchy::new(
+            [14, 6, 10].iter().map(|width_len| CountMin::new(10, *width_len, UpdatePolicy::Standard)).collect(),
+        );
+        // Key x < 1000 appears x times, 499500 in total
+        for key in 0..1000u64 {
+            hierarchy.insert(key, key);
+        }
+        let bounds: Vec<f64> = hierarchy.levels().iter().map(|level| level.error_bound()).collect();
+        assert!(bounds[0] > bounds[1] && bounds[1] > bounds[2]);
+        assert_eq!(hierarchy.level_for(bounds[0]), Some(0));
+        assert_eq!(hierarchy.level_for(bounds[1] + 1.0), Some(1));
+        assert_eq!(hierarchy.level_for(bounds[2] / 2.0), None);
+        for max_error in [bounds[0], bounds[1], bounds[2]] {
+            let (estimate, bound) = hierarchy.estimate(777, max_error).unwrap();
+            assert!(bound <= max_error && estimate >= 777 && (estimate - 777) as f64 <= bound);
+        }
+        assert_eq!(hierarchy.estimate_best(777), hierarchy.estimate(777, bounds[2]).unwrap());
+
+        let mut signed: SketchHierarchy<CountSketch> = SketchHierarchy::new(vec![CountSketch::new(9, 12), CountSketch::new(9, 6)]);
+        (0..1000u64).for_each(|key| signed.insert(key, key as i64 - 500));
+        let (estimate, bound) = signed.estimate_best(900);
+        assert!((estimate - 400).abs() as f64 <= bound, "{} {}", estimate, bound);
+    }
+}
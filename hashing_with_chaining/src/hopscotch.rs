@@ -0,0 +1,173 @@
+use std::mem::size_of;
+use crate::hash::{log2u, SeededHash};
+use crate::las_vegas::{las_vegas, Attempts};
+use crate::table_stats::TableStats;
+
+// Hopscotch hashing: every element lives within `neighborhood` slots of its home bucket,
+// and the home bucket's hop_info bitmap records which of those slots it owns.
+// 0 marks an empty slot.
+pub struct Hopscotch {
+    vec: Vec<u64>,
+    hop_info: Vec<u64>,
+    neighborhood: usize,
+    hash_function: SeededHash,
+    hash_len: u32,
+    rehashes: usize,
+}
+
+impl Hopscotch {
+    pub fn new(size: usize, neighborhood: usize) -> Hopscotch {
+        Hopscotch::with_hash_len(log2u(size) + 1, neighborhood)
+    }
+    pub fn with_hash_len(hash_len: u32, neighborhood: usize) -> Hopscotch {
+        assert!((1..=64).contains(&neighborhood), "Neighborhood size must be between 1 and 64");
+        let table_len: usize = 1 << hash_len;
+        return Hopscotch {
+            vec: vec![0; table_len],
+            hop_info: vec![0; table_len],
+            neighborhood,
+            hash_function: SeededHash::new(hash_len),
+            hash_len,
+            rehashes: 0,
+        }
+    }
+    // Places elem without rehashing. Returns false if no free slot could be
+    // hopped into the neighborhood of its home bucket.
+    fn place(&mut self, elem: u64) -> bool {
+        let mask: usize = self.vec.len() - 1;
+        let home: usize = self.hash_function.hash(elem);
+
+        // Find the closest free slot by linear probing
+        let mut dist: usize = 0;
+        while self.vec[(home + dist) & mask] != 0 {
+            dist += 1;
+            if dist == self.vec.len() {
+                return false;
+            }
+        }
+
+        // Move the free slot backwards until it lies in the neighborhood of home
+        while dist >= self.neighborhood {
+            let free: usize = (home + dist) & mask;
+            let mut moved: bool = false;
+            for back in (1..self.neighborhood).rev() {
+                let bucket: usize = (free + self.vec.len() - back) & mask;
+                // Only elements stored before the free slot can be moved into it
+                let movable: u64 = self.hop_info[bucket] & ((1u64 << back) - 1);
+                if movable != 0 {
+                    let offset: usize = movable.trailing_zeros() as usize;
+                    let from: usize = (bucket + offset) & mask;
+                    self.vec[free] = self.vec[from];
+                    self.vec[from] = 0;
+                    self.hop_info[bucket] &= !(1u64 << offset);
+                    self.hop_info[bucket] |= 1u64 << back;
+                    dist -= back - offset;
+                    moved = true;
+                    break;
+                }
+            }
+            if !moved {
+                return false;
+            }
+        }
+
+        self.vec[(home + dist) & mask] = elem;
+        self.hop_info[home] |= 1u64 << dist;
+        return true;
+    }
+    // Draws new hash functions until every element, including the pending one, fits.
+    // The table is doubled whenever a fresh hash function is not enough.
+    fn rehash(&mut self, pending: u64) {
+        let mut elems: Vec<u64> = self.vec.iter().filter(|x| **x != 0).copied().collect();
+        elems.push(pending);
+        let mut hash_len: u32 = self.hash_len;
+        let outcome: Attempts<()> = las_vegas(usize::MAX, || {
+            self.hash_len = hash_len;
+            self.vec = vec![0; 1 << hash_len];
+            self.hop_info = vec![0; 1 << hash_len];
+            self.hash_function = SeededHash::new(hash_len);
+            if elems.iter().all(|x| self.place(*x)) {
+                return Some(());
+            }
+            hash_len += 1;
+            return None;
+        });
+        self.rehashes += outcome.attempts;
+    }
+    pub fn insert(&mut self, elem: u64) {
+        assert_ne!(elem, 0, "0 marks an empty slot");
+        if self.query(elem) {
+            return;
+        }
+        if !self.place(elem) {
+            self.rehash(elem);
+        }
+    }
+    // Never true for 0, which marks an empty slot
+    pub fn query(&self, elem: u64) -> bool {
+        if elem == 0 {
+            return false;
+        }
+        let mask: usize = self.vec.len() - 1;
+        let home: usize = self.hash_function.hash(elem);
+        let mut bits: u64 = self.hop_info[home];
+        while bits != 0 {
+            let offset: usize = bits.trailing_zeros() as usize;
+            if self.vec[(home + offset) & mask] == elem {
+                return true;
+            }
+            bits &= bits - 1;
+        }
+        return false;
+    }
+    // Every slot counts as a bucket holding at most one element
+    pub fn stats(&self) -> TableStats {
+        let mut stats: TableStats = TableStats::new();
+        self.vec.iter().for_each(|x| stats.record_chain((*x != 0) as usize));
+        return stats;
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.vec.len() * 2 * size_of::<u64>();
+    }
+    // Attempts at new hash functions, over all rehashes
+    pub fn rehashes(&self) -> usize {
+        return self.rehashes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inserts_and_queries_within_the_neighborhood() {
+        let mut hopscotch: Hopscotch = Hopscotch::new(10_000, 32);
+        for key in 1..=10_000u64 {
+            hopscotch.insert(key * 3);
+        }
+        hopscotch.insert(3);
+        assert!((1..=10_000u64).all(|key| hopscotch.query(key * 3)));
+        assert!(!(1..=10_000u64).any(|key| hopscotch.query(key * 3 + 1)));
+        let stats: TableStats = hopscotch.stats();
+        assert_eq!((stats.entries, stats.buckets, stats.max_chain), (10_000, 1 << 14, 1));
+    }
+
+    // More keys than slots, with a neighborhood of one slot that leaves no room to hop,
+    // so the table has to rehash into a larger one
+    #[test]
+    fn rehashes_into_a_larger_table() {
+        let mut hopscotch: Hopscotch = Hopscotch::with_hash_len(4, 1);
+        (1..=20u64).for_each(|key| hopscotch.insert(key));
+        assert!((1..=20u64).all(|key| hopscotch.query(key)));
+        assert_eq!(hopscotch.stats().entries, 20);
+        assert!(hopscotch.rehashes() > 0 && hopscotch.stats().buckets > 16);
+    }
+
+    #[test]
+    #[should_panic(expected = "0 marks an empty slot")]
+    fn rejects_the_empty_marker() {
+        let mut hopscotch: Hopscotch = Hopscotch::new(16, 8);
+        assert!(!hopscotch.query(0));
+        hopscotch.insert(0);
+    }
+}
@@ -0,0 +1,604 @@
+use std::mem::size_of;
+use serde::{Deserialize, Serialize};
+use crate::counter::Counter;
+use crate::hash::{log2u, SeededHash};
+use crate::memory::{vec_allocations, MemoryFootprint};
+use crate::sketch_format::{ByteReader, ByteWriter, DecodeError, SketchKind};
+use crate::table_stats::TableStats;
+
+// Number of entries a chain stores in place before spilling to the heap
+const INLINE_LEN: usize = 4;
+// Number of buckets InlineChains allocates at once, the first time one of them is touched
+const PAGE_LEN: usize = 1024;
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry<C = i64> {
+    pub key: u64,
+    pub count: C,
+}
+
+// A chain of entries kept inside the bucket array while short, so most lookups
+// never leave the bucket they hash to
+#[derive(Clone, Serialize, Deserialize)]
+enum Chain<C> {
+    Inline { len: u8, entries: [Entry<C>; INLINE_LEN] },
+    Spilled(Vec<Entry<C>>),
+}
+
+impl<C: Counter> Chain<C> {
+    fn new() -> Chain<C> {
+        Chain::Inline { len: 0, entries: [Entry::default(); INLINE_LEN] }
+    }
+    fn push(&mut self, entry: Entry<C>) {
+        match self {
+            Chain::Inline { len, entries } if (*len as usize) < INLINE_LEN => {
+                entries[*len as usize] = entry;
+                *len += 1;
+            }
+            Chain::Inline { entries, .. } => {
+                let mut spilled: Vec<Entry<C>> = Vec::with_capacity(2 * INLINE_LEN);
+                spilled.extend_from_slice(entries);
+                spilled.push(entry);
+                *self = Chain::Spilled(spilled);
+            }
+            Chain::Spilled(vec) => vec.push(entry),
+        }
+    }
+    fn as_slice(&self) -> &[Entry<C>] {
+        return match self {
+            Chain::Inline { len, entries } => &entries[..*len as usize],
+            Chain::Spilled(vec) => vec,
+        }
+    }
+    fn as_mut_slice(&mut self) -> &mut [Entry<C>] {
+        return match self {
+            Chain::Inline { len, entries } => &mut entries[..*len as usize],
+            Chain::Spilled(vec) => vec,
+        }
+    }
+    fn len(&self) -> usize {
+        return self.as_slice().len();
+    }
+    // Removes the entry at pos by moving the last entry into its place
+    fn swap_remove(&mut self, pos: usize) {
+        match self {
+            Chain::Inline { len, entries } => {
+                entries[pos] = entries[*len as usize - 1];
+                *len -= 1;
+            }
+            Chain::Spilled(vec) => {
+                vec.swap_remove(pos);
+            }
+        }
+    }
+    fn heap_bytes(&self) -> usize {
+        return match self {
+            Chain::Inline { .. } => 0,
+            Chain::Spilled(vec) => vec.capacity() * size_of::<Entry<C>>(),
+        }
+    }
+}
+
+// Bucket storage behind HwC, so different memory layouts can be compared under one API
+pub trait ChainStorage {
+    type Count: Counter;
+    // Creating the storage must not touch every bucket, so that a huge, sparsely used
+    // table is cheap to build. capacity_hint is the number of entries expected.
+    fn with_buckets(buckets: usize, capacity_hint: usize) -> Self;
+    // Adds delta to the count of key in the given bucket, inserting the key if it is
+    // missing and removing it once its count is zero. Returns the old count.
+    fn update(&mut self, bucket: usize, key: u64, delta: Self::Count) -> Self::Count;
+    fn get(&self, bucket: usize, key: u64) -> Option<Self::Count>;
+    fn for_each_entry<F: FnMut(&Entry<Self::Count>)>(&self, f: F);
+    fn for_each_chain_len<F: FnMut(usize)>(&self, f: F);
+    // Heap footprint in bytes
+    fn space_usage(&self) -> usize;
+    // Number of heap allocations behind the footprint
+    fn allocations(&self) -> usize;
+}
+
+// One inline chain per bucket. The buckets are split into pages which are allocated
+// on the first update that lands in them, so untouched buckets cost no memory.
+#[derive(Serialize, Deserialize)]
+pub struct InlineChains<C = i64> {
+    pages: Vec<Option<Box<[Chain<C>]>>>,
+    buckets: usize,
+}
+
+impl<C: Counter> InlineChains<C> {
+    fn chain(&self, bucket: usize) -> Option<&Chain<C>> {
+        return self.pages[bucket / PAGE_LEN].as_ref().map(|page| &page[bucket % PAGE_LEN]);
+    }
+    fn chain_mut(&mut self, bucket: usize) -> &mut Chain<C> {
+        let page = self.pages[bucket / PAGE_LEN].get_or_insert_with(|| vec![Chain::new(); PAGE_LEN].into_boxed_slice());
+        return &mut page[bucket % PAGE_LEN];
+    }
+}
+
+impl<C: Counter> ChainStorage for InlineChains<C> {
+    type Count = C;
+    // Pages are allocated as they are touched, so the hint is not needed
+    fn with_buckets(buckets: usize, _capacity_hint: usize) -> InlineChains<C> {
+        let mut pages: Vec<Option<Box<[Chain<C>]>>> = Vec::new();
+        pages.resize_with(buckets.div_ceil(PAGE_LEN), || None);
+        return InlineChains { pages, buckets }
+    }
+    fn update(&mut self, bucket: usize, key: u64, delta: C) -> C {
+        if delta.is_zero() && self.chain(bucket).is_none() {
+            return C::default();
+        }
+        let chain: &mut Chain<C> = self.chain_mut(bucket);
+        return match chain.as_slice().iter().position(|entry| entry.key == key) {
+            Some(pos) => {
+                let old: C = chain.as_slice()[pos].count;
+                chain.as_mut_slice()[pos].count = old.add(delta);
+                if old.add(delta).is_zero() {
+                    chain.swap_remove(pos);
+                }
+                old
+            }
+            None => {
+                if !delta.is_zero() {
+                    chain.push(Entry { key, count: delta });
+                }
+                C::default()
+            }
+        }
+    }
+    fn get(&self, bucket: usize, key: u64) -> Option<C> {
+        return self.chain(bucket)?.as_slice().iter().find(|entry| entry.key == key).map(|entry| entry.count);
+    }
+    fn for_each_entry<F: FnMut(&Entry<C>)>(&self, mut f: F) {
+        for page in self.pages.iter().flatten() {
+            for chain in page.iter() {
+                chain.as_slice().iter().for_each(&mut f);
+            }
+        }
+    }
+    fn for_each_chain_len<F: FnMut(usize)>(&self, mut f: F) {
+        for bucket in 0..self.buckets {
+            f(self.chain(bucket).map_or(0, |chain| chain.len()));
+        }
+    }
+    // The allocated pages with their inline entries plus the spilled chains
+    fn space_usage(&self) -> usize {
+        let mut usage: usize = self.pages.len() * size_of::<Option<Box<[Chain<C>]>>>();
+        for page in self.pages.iter().flatten() {
+            usage += PAGE_LEN * size_of::<Chain<C>>();
+            usage += page.iter().map(|chain| chain.heap_bytes()).sum::<usize>();
+        }
+        return usage;
+    }
+    // The page vector, the allocated pages and the spilled chains
+    fn allocations(&self) -> usize {
+        let mut allocations: usize = vec_allocations(&self.pages);
+        for page in self.pages.iter().flatten() {
+            allocations += 1 + page.iter().filter(|chain| chain.heap_bytes() > 0).count();
+        }
+        return allocations;
+    }
+}
+
+// Marks the end of a chain in ArenaChains. Slot 0 of the arena is never used for an
+// entry, so the bucket heads can start out as zeroed memory.
+const NIL: u32 = 0;
+
+#[derive(Serialize, Deserialize)]
+struct Node<C> {
+    entry: Entry<C>,
+    next: u32,
+}
+
+// All entries live in one contiguous arena, and every bucket only stores the arena
+// index of the head of its chain. Nodes link to the next node by index, and removed
+// nodes are linked into a free list for reuse. The heads are allocated zeroed, which
+// the allocator hands out without writing to it.
+#[derive(Serialize, Deserialize)]
+pub struct ArenaChains<C = i64> {
+    heads: Vec<u32>,
+    arena: Vec<Node<C>>,
+    free: u32,
+}
+
+impl<C: Counter> ArenaChains<C> {
+    fn find(&self, bucket: usize, key: u64) -> Option<usize> {
+        let mut node: u32 = self.heads[bucket];
+        while node != NIL {
+            if self.arena[node as usize].entry.key == key {
+                return Some(node as usize);
+            }
+            node = self.arena[node as usize].next;
+        }
+        return None;
+    }
+    fn allocate(&mut self, node: Node<C>) -> u32 {
+        if self.free != NIL {
+            let index: u32 = self.free;
+            self.free = self.arena[index as usize].next;
+            self.arena[index as usize] = node;
+            return index;
+        }
+        assert!(self.arena.len() < u32::MAX as usize, "Arena is full");
+        self.arena.push(node);
+        return (self.arena.len() - 1) as u32;
+    }
+    fn unlink(&mut self, bucket: usize, index: u32) {
+        let next: u32 = self.arena[index as usize].next;
+        if self.heads[bucket] == index {
+            self.heads[bucket] = next;
+        } else {
+            let mut node: u32 = self.heads[bucket];
+            while self.arena[node as usize].next != index {
+                node = self.arena[node as usize].next;
+            }
+            self.arena[node as usize].next = next;
+        }
+        // Freed nodes keep a zero count, which live entries never have
+        self.arena[index as usize] = Node { entry: Entry::default(), next: self.free };
+        self.free = index;
+    }
+}
+
+impl<C: Counter> ChainStorage for ArenaChains<C> {
+    type Count = C;
+    fn with_buckets(buckets: usize, capacity_hint: usize) -> ArenaChains<C> {
+        let mut arena: Vec<Node<C>> = Vec::with_capacity(capacity_hint + 1);
+        arena.push(Node { entry: Entry::default(), next: NIL });
+        return ArenaChains {
+            heads: vec![NIL; buckets],
+            arena,
+            free: NIL,
+        }
+    }
+    fn update(&mut self, bucket: usize, key: u64, delta: C) -> C {
+        return match self.find(bucket, key) {
+            Some(node) => {
+                let old: C = self.arena[node].entry.count;
+                self.arena[node].entry.count = old.add(delta);
+                if old.add(delta).is_zero() {
+                    self.unlink(bucket, node as u32);
+                }
+                old
+            }
+            None => {
+                if !delta.is_zero() {
+                    let head: u32 = self.allocate(Node { entry: Entry { key, count: delta }, next: self.heads[bucket] });
+                    self.heads[bucket] = head;
+                }
+                C::default()
+            }
+        }
+    }
+    fn get(&self, bucket: usize, key: u64) -> Option<C> {
+        return self.find(bucket, key).map(|node| self.arena[node].entry.count);
+    }
+    fn for_each_entry<F: FnMut(&Entry<C>)>(&self, f: F) {
+        self.arena.iter().map(|node| &node.entry).filter(|entry| !entry.count.is_zero()).for_each(f);
+    }
+    fn for_each_chain_len<F: FnMut(usize)>(&self, mut f: F) {
+        for head in &self.heads {
+            let mut len: usize = 0;
+            let mut node: u32 = *head;
+            while node != NIL {
+                len += 1;
+                node = self.arena[node as usize].next;
+            }
+            f(len);
+        }
+    }
+    fn space_usage(&self) -> usize {
+        return self.heads.len() * size_of::<u32>() + self.arena.capacity() * size_of::<Node<C>>();
+    }
+    fn allocations(&self) -> usize {
+        return vec_allocations(&self.heads) + vec_allocations(&self.arena);
+    }
+}
+
+// Hashing with chaining mapping every key to a signed count, so it can serve as the
+// exact baseline for turnstile streams. Keys whose count returns to zero are removed.
+// Serializing it keeps the seeds of the hash function, so a reloaded table hashes every
+// key the same way. Counts are i64 unless the storage is built over another Counter
+// type, like InlineChains<f64>.
+#[derive(Serialize, Deserialize)]
+pub struct HwC<S: ChainStorage = InlineChains> {
+    storage: S,
+    hash_function: SeededHash,
+    // Running sum of squared counts, kept up to date by every update
+    norm: <S::Count as Counter>::Square,
+}
+
+impl<S: ChainStorage> HwC<S> {
+    pub fn new(size: usize) -> HwC<S> {
+        return HwC::with_capacity_hint(size, 0);
+    }
+    // A table with size buckets, prepared to hold about expected_entries keys
+    pub fn with_capacity_hint(size: usize, expected_entries: usize) -> HwC<S> {
+        let input_len: usize = size;
+        let hash_len: u32 = log2u(input_len);
+        let hash_fn: SeededHash = SeededHash::new(hash_len);
+        return HwC {
+            storage: S::with_buckets(input_len, expected_entries),
+            hash_function: hash_fn,
+            norm: Default::default(),
+        }
+    }
+    // Changing a count from c to c+v changes the norm by (c+v)^2 - c^2
+    fn apply(&mut self, hash_val: usize, key: u64, delta: S::Count) {
+        let old: S::Count = self.storage.update(hash_val, key, delta);
+        self.norm = self.norm - old.square() + old.add(delta).square();
+    }
+    // Adds delta to the count of key
+    pub fn insert(&mut self, key: u64, delta: S::Count) {
+        let hash_val: usize = self.hash_function.hash(key);
+        self.apply(hash_val, key, delta);
+    }
+    // Hashes every key in a first pass before touching any bucket in a second pass
+    pub fn insert_batch(&mut self, updates: &[(u64, S::Count)]) {
+        let hashes: Vec<usize> = updates.iter().map(|(key, _)| self.hash_function.hash(*key)).collect();
+        for (hash_val, (key, delta)) in hashes.iter().zip(updates) {
+            self.apply(*hash_val, *key, *delta);
+        }
+    }
+    // Like insert_batch, but applies the updates in bucket order so consecutive
+    // updates touch neighbouring buckets
+    pub fn insert_batch_sorted(&mut self, updates: &[(u64, S::Count)]) {
+        let mut hashed: Vec<(usize, u64, S::Count)> = updates.iter()
+            .map(|(key, delta)| (self.hash_function.hash(*key), *key, *delta))
+            .collect();
+        hashed.sort_unstable_by_key(|(hash_val, _, _)| *hash_val);
+        for (hash_val, key, delta) in hashed {
+            self.apply(hash_val, key, delta);
+        }
+    }
+    pub fn query(&self, key: u64) -> bool {
+        let hash_val: usize = self.hash_function.hash(key);
+        self.storage.get(hash_val, key).is_some()
+    }
+    pub fn get(&self, key: u64) -> S::Count {
+        let hash_val: usize = self.hash_function.hash(key);
+        return self.storage.get(hash_val, key).unwrap_or_default();
+    }
+    // Sum of squared counts, the exact second frequency moment, in constant time
+    pub fn norm(&self) -> <S::Count as Counter>::Square {
+        return self.norm;
+    }
+    // Recomputes the norm by scanning every entry, to validate norm()
+    pub fn get_norm(&self) -> <S::Count as Counter>::Square {
+        let mut norm: <S::Count as Counter>::Square = Default::default();
+        self.storage.for_each_entry(|entry| norm = norm + entry.count.square());
+        return norm;
+    }
+    pub fn stats(&self) -> TableStats {
+        let mut stats: TableStats = TableStats::new();
+        self.storage.for_each_chain_len(|len| stats.record_chain(len));
+        return stats;
+    }
+    pub fn longest_chain(&self) -> usize {
+        return self.stats().max_chain;
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.storage.space_usage();
+    }
+}
+
+impl<S: ChainStorage> MemoryFootprint for HwC<S> {
+    fn heap_bytes(&self) -> usize {
+        return self.space_usage();
+    }
+    fn allocations(&self) -> usize {
+        return self.storage.allocations();
+    }
+}
+
+impl<S: ChainStorage<Count = i64>> HwC<S> {
+    // hash function | buckets (u64) | norm (u64) | number of entries (u64) | entries, each a
+    // key (u64) and a count (i64). The entries do not depend on the chain storage, so a table
+    // can be reloaded into either layout.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new(SketchKind::HwC);
+        writer.seeded_hash(&self.hash_function);
+        writer.u64(self.stats().buckets as u64);
+        writer.u64(self.norm);
+        let mut entries: Vec<Entry> = Vec::new();
+        self.storage.for_each_entry(|entry| entries.push(*entry));
+        writer.u64(entries.len() as u64);
+        for entry in entries {
+            writer.u64(entry.key);
+            writer.i64(entry.count);
+        }
+        return writer.finish();
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<HwC<S>, DecodeError> {
+        let mut reader: ByteReader = ByteReader::new(bytes, SketchKind::HwC)?;
+        let hash_function: SeededHash = reader.seeded_hash()?;
+        let buckets: usize = reader.u64()? as usize;
+        if buckets == 0 || log2u(buckets) != hash_function.hash_len() {
+            return Err(DecodeError::Invalid("buckets do not match the hash function"));
+        }
+        let norm: u64 = reader.u64()?;
+        let len: usize = reader.len(size_of::<Entry>())?;
+        let mut hwc: HwC<S> = HwC {
+            storage: S::with_buckets(buckets, len),
+            hash_function,
+            norm: 0,
+        };
+        for _ in 0..len {
+            let key: u64 = reader.u64()?;
+            let count: i64 = reader.i64()?;
+            if count == 0 || hwc.query(key) {
+                return Err(DecodeError::Invalid("entries must be distinct keys with nonzero counts"));
+            }
+            hwc.insert(key, count);
+        }
+        reader.finish()?;
+        if hwc.norm != norm {
+            return Err(DecodeError::Invalid("norm does not match the entries"));
+        }
+        return Ok(hwc);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_chains_spill_to_the_heap() {
+        let mut hwc: HwC = HwC::new(4);
+        for x in 1..=1000 {
+            hwc.insert(x, x as i64);
+        }
+        assert!((1..=1000).all(|x| hwc.get(x) == x as i64));
+        assert!(!hwc.query(1001));
+        assert!(hwc.longest_chain() > INLINE_LEN);
+        let stats: TableStats = hwc.stats();
+        assert_eq!((stats.buckets, stats.entries), (4, 1000));
+        assert_eq!(stats.chain_histogram.iter().sum::<usize>(), 4);
+        assert_eq!(stats.load_factor(), 250.0);
+        assert!(hwc.space_usage() >= 1000 * size_of::<Entry>());
+    }
+
+    #[test]
+    fn counts_accumulate_into_the_norm() {
+        let mut hwc: HwC = HwC::new(16);
+        hwc.insert(1, 3);
+        hwc.insert(2, 1);
+        hwc.insert(1, 1);
+        assert_eq!(hwc.get(1), 4);
+        assert_eq!(hwc.get(3), 0);
+        assert_eq!(hwc.get_norm(), 17);
+    }
+
+    #[test]
+    fn batch_inserts_match_single_inserts() {
+        let updates: Vec<(u64, i64)> = (0..10_000u64).map(|x| (x * x % 997, x as i64 % 5 - 2)).collect();
+        let mut single: HwC = HwC::new(512);
+        let mut batch: HwC = HwC::new(512);
+        let mut sorted: HwC<ArenaChains> = HwC::new(512);
+        for (key, count) in &updates {
+            single.insert(*key, *count);
+        }
+        batch.insert_batch(&updates);
+        sorted.insert_batch_sorted(&updates);
+        assert!((0..997).all(|x| single.get(x) == batch.get(x) && single.get(x) == sorted.get(x)));
+        assert_eq!(single.norm(), single.get_norm());
+        assert_eq!(batch.norm(), single.norm());
+        assert_eq!(sorted.norm(), single.norm());
+    }
+
+    #[test]
+    fn matched_deletions_bring_the_norm_back_to_zero() {
+        let mut inline: HwC = HwC::new(64);
+        let mut arena: HwC<ArenaChains> = HwC::new(64);
+        for x in 0..2000u64 {
+            inline.insert(x % 300, x as i64);
+            arena.insert(x % 300, x as i64);
+        }
+        assert!(inline.get_norm() > 0);
+        for x in (0..2000u64).rev() {
+            inline.insert(x % 300, -(x as i64));
+            arena.insert(x % 300, -(x as i64));
+        }
+        assert_eq!(inline.get_norm(), 0);
+        assert_eq!(arena.get_norm(), 0);
+        assert_eq!(inline.norm(), 0);
+        assert!((0..300).all(|x| !inline.query(x) && !arena.query(x)));
+        assert_eq!(inline.stats().entries, 0);
+        assert_eq!(arena.stats().entries, 0);
+
+        // Freed arena nodes are reused
+        arena.insert(5, -3);
+        assert_eq!(arena.get(5), -3);
+        assert_eq!(arena.get_norm(), 9);
+    }
+
+    #[test]
+    fn arena_backend_matches_inline_backend() {
+        let mut inline: HwC = HwC::new(64);
+        let mut arena: HwC<ArenaChains> = HwC::new(64);
+        for x in (0..5000).map(|x| x * 37 % 3001) {
+            inline.insert(x, 2);
+            arena.insert(x, 2);
+        }
+        for x in 0..5000 {
+            assert_eq!(inline.get(x), arena.get(x));
+        }
+        assert_eq!(inline.get_norm(), arena.get_norm());
+    }
+
+    #[test]
+    fn sparse_tables_only_allocate_touched_buckets() {
+        let mut inline: HwC = HwC::new(1 << 24);
+        let mut arena: HwC<ArenaChains> = HwC::with_capacity_hint(1 << 24, 100);
+        let empty: usize = inline.space_usage();
+        for x in 0..100 {
+            inline.insert(x, 1);
+            arena.insert(x, 1);
+        }
+        assert!(inline.space_usage() - empty <= 100 * PAGE_LEN * size_of::<Chain<i64>>());
+        assert!((0..100).all(|x| inline.get(x) == 1 && arena.get(x) == 1));
+        assert_eq!(inline.stats().buckets, 1 << 24);
+        assert_eq!(arena.stats().entries, 100);
+        assert!(!inline.query(100) && !arena.query(100));
+    }
+
+    #[cfg(feature = "harness")]
+    #[test]
+    fn reloaded_table_hashes_keys_the_same_way() {
+        let mut hwc: HwC<ArenaChains> = HwC::new(256);
+        for x in 0..1000 {
+            hwc.insert(x * 7, x as i64);
+        }
+        let json: String = serde_json::to_string(&hwc).unwrap();
+        let mut reloaded: HwC<ArenaChains> = serde_json::from_str(&json).unwrap();
+        assert!((0..1000).all(|x| reloaded.get(x * 7) == x as i64));
+        reloaded.insert(7, 1);
+        assert_eq!(reloaded.get(7), 2);
+        assert_eq!(reloaded.longest_chain(), hwc.longest_chain());
+    }
+
+    #[test]
+    fn rejects_a_norm_that_does_not_match() {
+        let mut hwc: HwC = HwC::new(1000);
+        for x in 0..5000u64 {
+            hwc.insert(x % 700, x as i64 % 9 - 4);
+        }
+        let mut bytes: Vec<u8> = hwc.to_bytes();
+        // Header (8 bytes), hash function (36 bytes) and buckets (8 bytes) come before the norm
+        bytes[52] ^= 1;
+        assert_eq!(HwC::<InlineChains>::from_bytes(&bytes).err(), Some(DecodeError::Invalid("norm does not match the entries")));
+    }
+
+    // Removes the checkpoint file when the test ends, also when it fails
+    #[cfg(feature = "harness")]
+    struct TempFile(std::path::PathBuf);
+
+    #[cfg(feature = "harness")]
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[cfg(feature = "harness")]
+    #[test]
+    fn checkpoints_resume_in_either_layout() {
+        use crate::count_min::{CountMin, UpdatePolicy};
+        use crate::sketch_format::Checkpoint;
+        let mut hwc: HwC = HwC::new(1000);
+        for x in 0..5000u64 {
+            hwc.insert(x % 700, x as i64 % 9 - 4);
+        }
+        let file: TempFile = TempFile(std::env::temp_dir().join(format!("hwc_checkpoint_{}", std::process::id())));
+        hwc.save(&file.0).unwrap();
+        let mut resumed: HwC<ArenaChains> = HwC::load(&file.0).unwrap();
+        assert!((0..700).all(|x| resumed.get(x) == hwc.get(x)));
+        assert_eq!((resumed.norm(), resumed.stats().buckets), (hwc.norm(), 1000));
+        resumed.insert(3, 1);
+        hwc.insert(3, 1);
+        assert_eq!(resumed.norm(), hwc.norm());
+
+        CountMin::new(2, 4, UpdatePolicy::Standard).save(&file.0).unwrap();
+        assert_eq!(HwC::<InlineChains>::load(&file.0).err().map(|err| err.kind()), Some(std::io::ErrorKind::InvalidData));
+    }
+}
@@ -0,0 +1,442 @@
+use std::collections::VecDeque;
+use std::mem::size_of;
+use serde::{Deserialize, Serialize};
+use crate::hash::IndependentHash;
+use crate::memory::{vec_allocations, MemoryFootprint};
+use crate::mergeable::{MergeError, MergeableSketch};
+use crate::sketch_format::{ByteReader, ByteWriter, DecodeError, SketchKind};
+
+pub const MIN_PRECISION: u32 = 4;
+pub const MAX_PRECISION: u32 = 18;
+
+// Bits of the hash values of IndependentHash
+const HASH_BITS: u32 = 61;
+// Precision of the sparse representation, as in HLL++
+const SPARSE_PRECISION: u32 = 25;
+// Bytes of one sparse entry: the register index at SPARSE_PRECISION above a 6 bit rank
+const SPARSE_ENTRY_LEN: usize = 4;
+// Largest rank of a sparse entry, for a hash value whose bits after the index are all zero
+const MAX_SPARSE_RANK: u32 = HASH_BITS - SPARSE_PRECISION + 1;
+
+// While few keys have been seen, only the nonzero registers of a sketch with the much
+// higher SPARSE_PRECISION are kept, sorted by index. Once they would take more space
+// than the dense registers, they are folded into those.
+#[derive(Clone, Serialize, Deserialize)]
+enum Registers {
+    Sparse(Vec<u32>),
+    Dense(Vec<u8>),
+}
+
+// Register index and rank of a hash value, for 2^precision registers
+fn index_and_rank(hash_val: u64, precision: u32) -> (usize, u8) {
+    let hash_val: u64 = hash_val << (64 - HASH_BITS);
+    let index: usize = (hash_val >> (64 - precision)) as usize;
+    // Position of the first one bit after the index bits, capped for an all zero rest
+    let rest: u64 = hash_val << precision;
+    let rank: u8 = (rest.leading_zeros().min(HASH_BITS - precision) + 1) as u8;
+    return (index, rank);
+}
+
+// Raw HyperLogLog estimate of dense registers with the bias correction constant alpha,
+// falling back to linear counting over the empty registers for small cardinalities
+fn dense_estimate(registers: &[u8]) -> f64 {
+    let m: f64 = registers.len() as f64;
+    let alpha: f64 = match registers.len() {
+        16 => 0.673,
+        32 => 0.697,
+        64 => 0.709,
+        _ => 0.7213 / (1.0 + 1.079 / m),
+    };
+    let sum: f64 = registers.iter().map(|register| (-(*register as f64)).exp2()).sum();
+    let raw: f64 = alpha * m * m / sum;
+    let empty: usize = registers.iter().filter(|register| **register == 0).count();
+    if raw <= 2.5 * m && empty > 0 {
+        return m * (m / empty as f64).ln();
+    }
+    return raw;
+}
+
+// HyperLogLog distinct counting with 2^precision registers. The top precision bits of
+// a 61-bit hash pick a register, which remembers the longest run of leading zeros
+// seen in the remaining bits. The harmonic mean of 2^register then estimates the
+// number of distinct keys with a standard error of about 1.04 / sqrt(2^precision).
+// Like HLL++, it starts out sparse, so small distinct counts cost little memory and
+// are counted at a much higher precision.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HyperLogLog {
+    registers: Registers,
+    hash_function: IndependentHash,
+    precision: u32,
+}
+
+impl HyperLogLog {
+    pub fn new(precision: u32) -> HyperLogLog {
+        assert!((MIN_PRECISION..=MAX_PRECISION).contains(&precision), "Precision must be in {}..={}", MIN_PRECISION, MAX_PRECISION);
+        return HyperLogLog {
+            registers: Registers::Sparse(Vec::new()),
+            // Multiply-shift hashing of consecutive keys is too regular in its low bits
+            // for the leading zero counts, so a 4-wise independent polynomial is used
+            hash_function: IndependentHash::new(4),
+            precision,
+        }
+    }
+    pub fn insert(&mut self, key: u64) {
+        let hash_val: u64 = self.hash_function.hash(key);
+        match &mut self.registers {
+            Registers::Sparse(entries) => {
+                let (index, rank) = index_and_rank(hash_val, SPARSE_PRECISION);
+                HyperLogLog::insert_sparse(entries, index as u32, rank);
+                if entries.len() * SPARSE_ENTRY_LEN > 1 << self.precision {
+                    self.make_dense();
+                }
+            }
+            Registers::Dense(registers) => {
+                let (index, rank) = index_and_rank(hash_val, self.precision);
+                registers[index] = registers[index].max(rank);
+            }
+        }
+    }
+    fn insert_sparse(entries: &mut Vec<u32>, index: u32, rank: u8) {
+        match entries.binary_search_by_key(&index, |entry| entry >> 6) {
+            Ok(pos) => entries[pos] = entries[pos].max(index << 6 | rank as u32),
+            Err(pos) => entries.insert(pos, index << 6 | rank as u32),
+        }
+    }
+    // The dense register and rank a sparse entry stands for. The index bits below
+    // the dense precision are the start of the dense rest, so if they are all zero
+    // the sparse rank continues the run of zeros.
+    fn fold_sparse_entry(&self, entry: u32) -> (usize, u8) {
+        let extra_bits: u32 = SPARSE_PRECISION - self.precision;
+        let index: u32 = entry >> 6;
+        let low: u32 = index & ((1 << extra_bits) - 1);
+        let rank: u8 = if low != 0 {
+            (low.leading_zeros() - (32 - extra_bits) + 1) as u8
+        } else {
+            extra_bits as u8 + (entry & 63) as u8
+        };
+        return ((index >> extra_bits) as usize, rank);
+    }
+    fn dense_registers(&self) -> Vec<u8> {
+        return match &self.registers {
+            Registers::Sparse(entries) => {
+                let mut registers: Vec<u8> = vec![0; 1 << self.precision];
+                for entry in entries {
+                    let (index, rank) = self.fold_sparse_entry(*entry);
+                    registers[index] = registers[index].max(rank);
+                }
+                registers
+            }
+            Registers::Dense(registers) => registers.clone(),
+        }
+    }
+    fn make_dense(&mut self) {
+        if self.is_sparse() {
+            self.registers = Registers::Dense(self.dense_registers());
+        }
+    }
+    pub fn is_sparse(&self) -> bool {
+        return matches!(self.registers, Registers::Sparse(_));
+    }
+    // Sparse sketches count the nonzero registers out of 2^SPARSE_PRECISION, which is
+    // linear counting at high precision. Dense ones use the HyperLogLog estimate.
+    pub fn estimate(&self) -> f64 {
+        return match &self.registers {
+            Registers::Sparse(entries) => {
+                let m: f64 = (1u64 << SPARSE_PRECISION) as f64;
+                m * (m / (m - entries.len() as f64)).ln()
+            }
+            Registers::Dense(registers) => dense_estimate(registers),
+        };
+    }
+    pub fn precision(&self) -> u32 {
+        return self.precision;
+    }
+    pub fn space_usage(&self) -> usize {
+        return match &self.registers {
+            Registers::Sparse(entries) => entries.capacity() * SPARSE_ENTRY_LEN,
+            Registers::Dense(registers) => registers.len(),
+        }
+    }
+    // precision (u32) | hash function | mode (u8), followed for mode 0 by the dense
+    // registers (u8) and for mode 1 by the number of sparse entries (u64) and the entries (u32)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new(SketchKind::HyperLogLog);
+        writer.u32(self.precision);
+        writer.independent_hash(&self.hash_function);
+        match &self.registers {
+            Registers::Dense(registers) => {
+                writer.u8(0);
+                registers.iter().for_each(|register| writer.u8(*register));
+            }
+            Registers::Sparse(entries) => {
+                writer.u8(1);
+                writer.u64(entries.len() as u64);
+                entries.iter().for_each(|entry| writer.u32(*entry));
+            }
+        }
+        return writer.finish();
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<HyperLogLog, DecodeError> {
+        let mut reader: ByteReader = ByteReader::new(bytes, SketchKind::HyperLogLog)?;
+        let precision: u32 = reader.u32()?;
+        if !(MIN_PRECISION..=MAX_PRECISION).contains(&precision) {
+            return Err(DecodeError::Invalid("precision out of range"));
+        }
+        let hash_function: IndependentHash = reader.independent_hash()?;
+        let registers: Registers = match reader.u8()? {
+            0 => Registers::Dense((0..1 << precision).map(|_| reader.u8()).collect::<Result<_, _>>()?),
+            1 => {
+                let len: usize = reader.len(SPARSE_ENTRY_LEN)?;
+                let entries: Vec<u32> = (0..len).map(|_| reader.u32()).collect::<Result<_, _>>()?;
+                if entries.windows(2).any(|pair| pair[0] >> 6 >= pair[1] >> 6) {
+                    return Err(DecodeError::Invalid("sparse entries out of order"));
+                }
+                // Folding them into dense registers indexes by the top bits of the entry
+                if entries.iter().any(|entry| entry >> 6 >= 1 << SPARSE_PRECISION || !(1..=MAX_SPARSE_RANK).contains(&(entry & 63))) {
+                    return Err(DecodeError::Invalid("sparse entry out of range"));
+                }
+                Registers::Sparse(entries)
+            }
+            _ => return Err(DecodeError::Invalid("unknown register mode")),
+        };
+        reader.finish()?;
+        return Ok(HyperLogLog { registers, hash_function, precision });
+    }
+}
+
+impl MemoryFootprint for HyperLogLog {
+    fn heap_bytes(&self) -> usize {
+        return self.space_usage();
+    }
+    fn allocations(&self) -> usize {
+        return match &self.registers {
+            Registers::Sparse(entries) => vec_allocations(entries),
+            Registers::Dense(registers) => vec_allocations(registers),
+        };
+    }
+}
+
+// The union of two streams keeps the larger register on both sides. Two sparse
+// sketches stay sparse as long as their union is small enough.
+impl MergeableSketch for HyperLogLog {
+    fn merge(&mut self, other: &HyperLogLog) -> Result<(), MergeError> {
+        if self.precision != other.precision {
+            return Err(MergeError::DimensionMismatch);
+        }
+        if self.hash_function != other.hash_function {
+            return Err(MergeError::SeedMismatch);
+        }
+        if let (Registers::Sparse(entries), Registers::Sparse(other_entries)) = (&mut self.registers, &other.registers) {
+            for entry in other_entries {
+                HyperLogLog::insert_sparse(entries, entry >> 6, (entry & 63) as u8);
+            }
+            if entries.len() * SPARSE_ENTRY_LEN > 1 << self.precision {
+                self.make_dense();
+            }
+            return Ok(());
+        }
+        self.make_dense();
+        let other_registers: Vec<u8> = other.dense_registers();
+        if let Registers::Dense(registers) = &mut self.registers {
+            for (register, other_register) in registers.iter_mut().zip(&other_registers) {
+                *register = (*register).max(*other_register);
+            }
+        }
+        return Ok(());
+    }
+}
+
+// Sliding HyperLogLog (Chabchoub and Hébrail): distinct keys among the updates of the last
+// `window` time units. Every register keeps the ranks that can still become its maximum,
+// with the time they were seen: a rank is dropped once a newer one at least as large comes
+// in or it leaves the window. The ranks left decrease with time, so the register of any
+// window up to `window` is the oldest rank inside it. A register holds O(log n) entries in
+// expectation. Windows over the last W updates use the update index as time.
+#[derive(Clone)]
+pub struct SlidingHyperLogLog {
+    // (time, rank) entries of every register, oldest and largest rank at the front
+    registers: Vec<VecDeque<(u64, u8)>>,
+    hash_function: IndependentHash,
+    precision: u32,
+    window: u64,
+    now: u64,
+}
+
+impl SlidingHyperLogLog {
+    pub fn new(precision: u32, window: u64) -> SlidingHyperLogLog {
+        assert!((MIN_PRECISION..=MAX_PRECISION).contains(&precision), "Precision must be in {}..={}", MIN_PRECISION, MAX_PRECISION);
+        assert!(window > 0, "The window must span at least one time unit");
+        return SlidingHyperLogLog {
+            registers: vec![VecDeque::new(); 1 << precision],
+            hash_function: IndependentHash::new(4),
+            precision,
+            window,
+            now: 0,
+        }
+    }
+    // Moves the clock to time, which may not go backwards, dropping ranks that left the window
+    pub fn advance(&mut self, time: u64) {
+        assert!(time >= self.now, "Time cannot go backwards");
+        self.now = time;
+        for register in &mut self.registers {
+            while register.front().is_some_and(|(seen, _)| seen + self.window <= time) {
+                register.pop_front();
+            }
+        }
+    }
+    pub fn insert(&mut self, time: u64, key: u64) {
+        assert!(time >= self.now, "Time cannot go backwards");
+        self.now = time;
+        let (index, rank) = index_and_rank(self.hash_function.hash(key), self.precision);
+        let register: &mut VecDeque<(u64, u8)> = &mut self.registers[index];
+        while register.back().is_some_and(|(_, kept)| *kept <= rank) {
+            register.pop_back();
+        }
+        register.push_back((time, rank));
+        while register.front().is_some_and(|(seen, _)| seen + self.window <= time) {
+            register.pop_front();
+        }
+    }
+    // Inserts the key as the next update, for windows over the last W updates
+    pub fn push(&mut self, key: u64) {
+        self.insert(self.now + 1, key);
+    }
+    // Distinct keys seen in the last `within` time units, up to the window
+    pub fn estimate_within(&self, within: u64) -> f64 {
+        assert!(within <= self.window, "Only windows up to {} are tracked", self.window);
+        let registers: Vec<u8> = self.registers.iter()
+            .map(|register| register.iter().find(|(seen, _)| seen + within > self.now).map_or(0, |(_, rank)| *rank))
+            .collect();
+        return dense_estimate(&registers);
+    }
+    // Distinct keys seen in the whole window
+    pub fn estimate(&self) -> f64 {
+        return self.estimate_within(self.window);
+    }
+    pub fn precision(&self) -> u32 {
+        return self.precision;
+    }
+    pub fn window(&self) -> u64 {
+        return self.window;
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.registers.iter().map(|register| register.len() * size_of::<(u64, u8)>()).sum();
+    }
+}
+
+impl MemoryFootprint for SlidingHyperLogLog {
+    fn heap_bytes(&self) -> usize {
+        return self.space_usage();
+    }
+    fn allocations(&self) -> usize {
+        return vec_allocations(&self.registers) + self.registers.iter().filter(|register| register.capacity() > 0).count();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use crate::hash::random_generator;
+
+    #[test]
+    fn estimates_distinct_counts_of_merged_streams() {
+        let mut left: HyperLogLog = HyperLogLog::new(12);
+        let mut right: HyperLogLog = left.clone();
+        // 100_000 distinct keys, each inserted twice, with 50_000 of them on both sides
+        for key in 0..100_000u64 {
+            left.insert(key);
+            left.insert(key);
+            if key >= 50_000 {
+                right.insert(key);
+            }
+        }
+        for key in 100_000..150_000u64 {
+            right.insert(key);
+        }
+        // The standard error is 1.04 / 64, so 10% is more than six of them
+        assert!((left.estimate() - 100_000.0).abs() < 10_000.0);
+        left.merge(&right).unwrap();
+        assert!((left.estimate() - 150_000.0).abs() < 15_000.0);
+        assert_eq!(left.merge(&HyperLogLog::new(12)).err(), Some(MergeError::SeedMismatch));
+
+        let decoded: HyperLogLog = HyperLogLog::from_bytes(&left.to_bytes()).unwrap();
+        assert_eq!(decoded.estimate(), left.estimate());
+    }
+
+    #[test]
+    fn small_cardinalities_use_linear_counting() {
+        let mut hll: HyperLogLog = HyperLogLog::new(10);
+        assert_eq!(hll.estimate(), 0.0);
+        for key in 0..50u64 {
+            hll.insert(key * 1_000_003);
+        }
+        assert!((hll.estimate() - 50.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn sparse_sketches_upgrade_to_dense_on_demand() {
+        let mut hll: HyperLogLog = HyperLogLog::new(14);
+        let mut dense: HyperLogLog = hll.clone();
+        dense.make_dense();
+        for key in 0..1000u64 {
+            hll.insert(key);
+            dense.insert(key);
+        }
+        assert!(hll.is_sparse());
+        assert!(hll.space_usage() < dense.space_usage());
+        assert!((hll.estimate() - 1000.0).abs() < 5.0);
+        // Folding the sparse entries gives exactly the registers of a dense sketch
+        assert_eq!(hll.dense_registers(), dense.dense_registers());
+        let decoded: HyperLogLog = HyperLogLog::from_bytes(&hll.to_bytes()).unwrap();
+        assert!(decoded.is_sparse());
+        assert_eq!(decoded.estimate(), hll.estimate());
+
+        for key in 1000..100_000u64 {
+            hll.insert(key);
+            dense.insert(key);
+        }
+        assert!(!hll.is_sparse());
+        assert_eq!(hll.dense_registers(), dense.dense_registers());
+
+        let mut small: HyperLogLog = dense.clone();
+        small.registers = Registers::Sparse(Vec::new());
+        small.insert(100_000);
+        small.merge(&hll).unwrap();
+        assert!(!small.is_sparse());
+        dense.insert(100_000);
+        assert_eq!(small.dense_registers(), dense.dense_registers());
+    }
+
+    #[test]
+    fn rejects_sparse_entries_out_of_range() {
+        let mut hll: HyperLogLog = HyperLogLog::new(12);
+        for entry in [(1 << SPARSE_PRECISION) << 6 | 1, 5 << 6, 5 << 6 | (MAX_SPARSE_RANK + 1)] {
+            hll.registers = Registers::Sparse(vec![entry]);
+            assert_eq!(HyperLogLog::from_bytes(&hll.to_bytes()).err(), Some(DecodeError::Invalid("sparse entry out of range")));
+        }
+        hll.registers = Registers::Sparse(vec![5 << 6 | MAX_SPARSE_RANK]);
+        assert!(HyperLogLog::from_bytes(&hll.to_bytes()).is_ok());
+    }
+
+    #[test]
+    fn sliding_estimates_follow_the_window() {
+        const WINDOW: usize = 20_000;
+        let mut sliding: SlidingHyperLogLog = SlidingHyperLogLog::new(12, WINDOW as u64);
+        // Keys drawn from a range that shifts over time, so old keys leave the window for good
+        let stream: Vec<u64> = (0..100_000u64).map(|i| i / 4 + random_generator(0, 30_000) as u64).collect();
+        for (i, key) in stream.iter().enumerate() {
+            sliding.push(*key);
+            if (i + 1) % 10_000 != 0 {
+                continue;
+            }
+            for within in [WINDOW, WINDOW / 4] {
+                let recent: HashSet<u64> = stream[(i + 1).saturating_sub(within)..=i].iter().copied().collect();
+                let estimate: f64 = sliding.estimate_within(within as u64);
+                assert!((estimate - recent.len() as f64).abs() < 0.08 * recent.len() as f64, "{} vs {}", estimate, recent.len());
+            }
+        }
+        sliding.advance(sliding.now + WINDOW as u64);
+        assert_eq!(sliding.estimate(), 0.0);
+    }
+}
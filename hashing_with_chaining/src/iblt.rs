@@ -0,0 +1,160 @@
+use std::mem::size_of;
+use serde::{Deserialize, Serialize};
+use crate::hash::{IndependentHash, SeededHash};
+use crate::mergeable::MergeError;
+
+// Number of hash functions, and so of segments
+const K: usize = 4;
+// Cells per difference, with some slack over the peeling threshold of 1.3 for four
+// hash functions. Small tables also get SLACK extra cells per segment, as their main
+// risk is two keys sharing all of their cells.
+const SPACE_FACTOR: f64 = 1.5;
+const SLACK: usize = 16;
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+struct Cell {
+    count: i64,
+    key_sum: u64,
+    hash_sum: u64,
+}
+
+impl Cell {
+    fn update(&mut self, key: u64, check: u64, delta: i64) {
+        self.count += delta;
+        self.key_sum ^= key;
+        self.hash_sum ^= check;
+    }
+}
+
+// Invertible Bloom lookup table (Goodrich and Mitzenmacher). Every key is added to one
+// cell in each of K segments, and a cell keeps the number of keys in it plus the
+// xor of the keys and of a checksum of the keys. Deletions cancel insertions, so the
+// table of set A minus the table of set B only holds the symmetric difference, which
+// list_entries recovers by peeling cells holding a single key.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Iblt {
+    cells: Vec<Cell>,
+    segment_len: usize,
+    hash_functions: [IndependentHash; K],
+    checksum: SeededHash,
+}
+
+impl Iblt {
+    // Room to list about `differences` keys
+    pub fn new(differences: usize) -> Iblt {
+        let segment_len: usize = (differences as f64 * SPACE_FACTOR).ceil() as usize / K + SLACK;
+        return Iblt {
+            cells: vec![Cell::default(); K * segment_len],
+            segment_len,
+            // Multiply-shift positions of consecutive keys line up into 2-cores far too
+            // often for peeling, so the positions come from 4-wise independent polynomials
+            hash_functions: std::array::from_fn(|_| IndependentHash::new(4)),
+            checksum: SeededHash::new(64),
+        }
+    }
+    fn positions(&self, key: u64) -> [usize; K] {
+        let mut positions: [usize; K] = [0; K];
+        for (i, hash_function) in self.hash_functions.iter().enumerate() {
+            let offset: usize = ((hash_function.hash(key) as u128 * self.segment_len as u128) >> 61) as usize;
+            positions[i] = i * self.segment_len + offset;
+        }
+        return positions;
+    }
+    fn update(&mut self, key: u64, delta: i64) {
+        let check: u64 = self.checksum.hash(key) as u64;
+        for pos in self.positions(key) {
+            self.cells[pos].update(key, check, delta);
+        }
+    }
+    pub fn insert(&mut self, key: u64) {
+        self.update(key, 1);
+    }
+    pub fn delete(&mut self, key: u64) {
+        self.update(key, -1);
+    }
+    // The table of the keys in self but not in other, and, with a count of -1, the
+    // keys in other but not in self. Both must share their hash functions, e.g. by
+    // cloning one empty table.
+    pub fn subtract(&self, other: &Iblt) -> Result<Iblt, MergeError> {
+        if self.cells.len() != other.cells.len() {
+            return Err(MergeError::DimensionMismatch);
+        }
+        if self.hash_functions != other.hash_functions || self.checksum != other.checksum {
+            return Err(MergeError::SeedMismatch);
+        }
+        let mut difference: Iblt = self.clone();
+        for (cell, other_cell) in difference.cells.iter_mut().zip(&other.cells) {
+            cell.update(other_cell.key_sum, other_cell.hash_sum, -other_cell.count);
+        }
+        return Ok(difference);
+    }
+    // A cell with count +-1 whose checksum matches its key holds exactly that key
+    fn is_pure(&self, cell: &Cell) -> bool {
+        return (cell.count == 1 || cell.count == -1) && cell.hash_sum == self.checksum.hash(cell.key_sum) as u64;
+    }
+    // The inserted and the deleted keys, or None if peeling gets stuck because the
+    // table holds more keys than it has room for
+    pub fn list_entries(&self) -> Option<(Vec<u64>, Vec<u64>)> {
+        let mut table: Iblt = self.clone();
+        let mut inserted: Vec<u64> = Vec::new();
+        let mut deleted: Vec<u64> = Vec::new();
+        let mut queue: Vec<usize> = (0..table.cells.len()).filter(|pos| table.is_pure(&table.cells[*pos])).collect();
+        while let Some(pos) = queue.pop() {
+            let cell: Cell = table.cells[pos];
+            if !table.is_pure(&cell) {
+                continue;
+            }
+            if cell.count == 1 {
+                inserted.push(cell.key_sum);
+            } else {
+                deleted.push(cell.key_sum);
+            }
+            table.update(cell.key_sum, -cell.count);
+            for next in table.positions(cell.key_sum) {
+                if table.is_pure(&table.cells[next]) {
+                    queue.push(next);
+                }
+            }
+        }
+        if table.cells.iter().any(|cell| cell.count != 0 || cell.key_sum != 0 || cell.hash_sum != 0) {
+            return None;
+        }
+        return Some((inserted, deleted));
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.cells.len() * size_of::<Cell>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reconciles_sets_through_their_difference() {
+        let mut alice: Iblt = Iblt::new(200);
+        let mut bob: Iblt = alice.clone();
+        for key in 0..100_000u64 {
+            alice.insert(key);
+            bob.insert(key);
+        }
+        for key in 100_000..100_040u64 {
+            alice.insert(key);
+        }
+        for key in 200_000..200_050u64 {
+            bob.insert(key);
+        }
+        let (mut only_alice, mut only_bob) = alice.subtract(&bob).unwrap().list_entries().unwrap();
+        only_alice.sort_unstable();
+        only_bob.sort_unstable();
+        assert_eq!(only_alice, (100_000..100_040).collect::<Vec<u64>>());
+        assert_eq!(only_bob, (200_000..200_050).collect::<Vec<u64>>());
+
+        // Far more differences than cells cannot be listed
+        for key in 300_000..302_000u64 {
+            alice.insert(key);
+        }
+        assert!(alice.subtract(&bob).unwrap().list_entries().is_none());
+        assert_eq!(alice.subtract(&Iblt::new(200)).err(), Some(MergeError::SeedMismatch));
+    }
+}
@@ -0,0 +1,103 @@
+use rand::prelude::*;
+use crate::alias::AliasTable;
+
+// Skewed key streams for the sketch and table experiments, as opposed to the uniform keys
+// most of them use. The generators only draw from the given generator, so a seeded StdRng
+// gives the same stream on every run. Keys stay below 2^61 - 1, where every hash function
+// of the crate treats distinct keys as distinct.
+
+// Zipf distribution over the keys 1..=keys, key k drawn with probability proportional to
+// 1/k^exponent. Exponents around 1 resemble word frequencies and web traffic, larger ones
+// put most of the stream on a few keys.
+#[derive(Clone, Debug)]
+pub struct Zipf {
+    table: AliasTable,
+}
+
+impl Zipf {
+    pub fn new(keys: usize, exponent: f64) -> Zipf {
+        let weights: Vec<f64> = (1..=keys).map(|k| 1.0 / (k as f64).powf(exponent)).collect();
+        return Zipf { table: AliasTable::new(&weights) }
+    }
+    pub fn sample(&self, rng: &mut impl Rng) -> u64 {
+        return (self.table.sample(rng) + 1) as u64;
+    }
+}
+
+pub fn zipf(keys: usize, exponent: f64, len: usize, rng: &mut impl Rng) -> Vec<u64> {
+    let distribution: Zipf = Zipf::new(keys, exponent);
+    return (0..len).map(|_| distribution.sample(rng)).collect();
+}
+
+// Key k >= 1 with probability p (1 - p)^(k - 1), by inverting the distribution function
+fn geometric_sample(p: f64, rng: &mut impl Rng) -> u64 {
+    let u: f64 = 1.0 - rng.gen::<f64>();
+    return 1 + (u.ln() / (1.0 - p).ln()).floor() as u64;
+}
+
+// Geometrically distributed keys, with mean 1 / p: every key is p times as likely as the
+// one before it, a much steeper skew than Zipf's
+pub fn geometric(p: f64, len: usize, rng: &mut impl Rng) -> Vec<u64> {
+    assert!(p > 0.0 && p <= 1.0, "Needs p in (0, 1]");
+    return (0..len).map(|_| geometric_sample(p, rng)).collect();
+}
+
+// Keys from a few dense ranges: each key is one of clusters random starts plus a uniform
+// offset below spread. Runs of nearby keys like these are where hash functions with little
+// independence, and tables that hash by the low bits, show their worst chains.
+pub fn clustered(clusters: usize, spread: u64, len: usize, rng: &mut impl Rng) -> Vec<u64> {
+    assert!(clusters > 0 && spread > 0, "Needs at least one cluster of one key");
+    let starts: Vec<u64> = (0..clusters).map(|_| rng.gen_range(1..(1 << 61) - spread)).collect();
+    return (0..len).map(|_| starts[rng.gen_range(0..clusters)] + rng.gen_range(0..spread)).collect();
+}
+
+// Uniform keys from 1..=keys, each repeated in a burst of geometric length with the given
+// mean, like a flow of packets or a user's session. Counts are as uniform as without the
+// bursts, but consecutive updates keep hitting the same counters.
+pub fn bursts(keys: u64, mean_burst: f64, len: usize, rng: &mut impl Rng) -> Vec<u64> {
+    assert!(mean_burst >= 1.0, "Bursts have at least one update");
+    let mut stream: Vec<u64> = Vec::with_capacity(len);
+    while stream.len() < len {
+        let key: u64 = rng.gen_range(1..=keys);
+        let burst: usize = geometric_sample(1.0 / mean_burst, rng) as usize;
+        stream.extend(std::iter::repeat_n(key, burst.min(len - stream.len())));
+    }
+    return stream;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn generators_follow_their_distributions() {
+        let mut rng: StdRng = StdRng::seed_from_u64(3);
+        let len: usize = 100_000;
+
+        // Zipf(1) over 1000 keys puts 1 / H_1000 ≈ 0.134 of the stream on key 1
+        let stream: Vec<u64> = zipf(1000, 1.0, len, &mut rng);
+        let ones: f64 = stream.iter().filter(|x| **x == 1).count() as f64 / len as f64;
+        assert!((ones - 0.134).abs() < 0.01, "{}", ones);
+        assert!(stream.iter().all(|x| (1..=1000).contains(x)));
+
+        let stream: Vec<u64> = geometric(0.25, len, &mut rng);
+        let mean: f64 = stream.iter().sum::<u64>() as f64 / len as f64;
+        assert!((mean - 4.0).abs() < 0.1, "{}", mean);
+
+        // Sorted, the keys of a cluster follow each other with gaps below spread
+        let mut stream: Vec<u64> = clustered(10, 1000, len, &mut rng);
+        stream.sort_unstable();
+        assert!(stream.windows(2).filter(|pair| pair[1] - pair[0] >= 1000).count() < 10);
+        assert!(stream[len - 1] < (1 << 61) - 1);
+
+        let stream: Vec<u64> = bursts(1 << 20, 8.0, len, &mut rng);
+        let runs: usize = 1 + stream.windows(2).filter(|pair| pair[0] != pair[1]).count();
+        assert_eq!(stream.len(), len);
+        assert!((len as f64 / runs as f64 - 8.0).abs() < 0.5, "{}", runs);
+
+        // The same seed gives the same stream
+        let again = |seed: u64| zipf(100, 1.2, 1000, &mut StdRng::seed_from_u64(seed));
+        assert_eq!(again(7), again(7));
+    }
+}
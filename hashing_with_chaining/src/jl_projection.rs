@@ -0,0 +1,137 @@
+use rand::prelude::*;
+use crate::hash::{gaussian, IndependentHash};
+
+// Distribution of the entries of a projection matrix
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProjectionEntries {
+    // Standard normal entries
+    Gaussian,
+    // Uniform +-1 entries, which are cheaper to draw and store the same guarantee
+    Sign,
+}
+
+// Johnson–Lindenstrauss transform: a dense k x d random matrix scaled by 1 / sqrt(k) maps
+// d-dimensional points to k dimensions such that every squared distance is preserved in
+// expectation, and all squared distances among n points are within a factor 1 +- epsilon
+// with high probability once k is about 8 ln(n) / epsilon^2.
+pub struct JlProjection {
+    matrix: Vec<Vec<f64>>,
+    entries: ProjectionEntries,
+}
+
+impl JlProjection {
+    pub fn new(dimension: usize, k: usize, entries: ProjectionEntries) -> JlProjection {
+        assert!(k > 0, "Needs at least one target dimension");
+        let scale: f64 = 1.0 / (k as f64).sqrt();
+        let mut rng = thread_rng();
+        let mut entry = || -> f64 {
+            match entries {
+                ProjectionEntries::Gaussian => gaussian() * scale,
+                ProjectionEntries::Sign => if rng.gen::<bool>() { scale } else { -scale },
+            }
+        };
+        return JlProjection {
+            matrix: (0..k).map(|_| (0..dimension).map(|_| entry()).collect()).collect(),
+            entries,
+        }
+    }
+    // Target dimension that keeps the squared distances among n points within 1 +- epsilon
+    // with probability at least 1 - 1/n, from the bound 4 ln(n) / (epsilon^2 / 2 - epsilon^3 / 3)
+    pub fn target_dimension(points: usize, epsilon: f64) -> usize {
+        assert!(epsilon > 0.0 && epsilon < 1.0, "Needs 0 < epsilon < 1");
+        let bound: f64 = 4.0 * (points.max(2) as f64).ln() / (epsilon * epsilon / 2.0 - epsilon.powi(3) / 3.0);
+        return bound.ceil() as usize;
+    }
+    pub fn project(&self, point: &[f64]) -> Vec<f64> {
+        assert_eq!(point.len(), self.dimension(), "Point has the wrong dimension");
+        return self.matrix.iter().map(|row| row.iter().zip(point).map(|(a, x)| a * x).sum()).collect();
+    }
+    pub fn dimension(&self) -> usize {
+        return self.matrix.first().map_or(0, |row| row.len());
+    }
+    pub fn target(&self) -> usize {
+        return self.matrix.len();
+    }
+    pub fn entries(&self) -> ProjectionEntries {
+        return self.entries;
+    }
+}
+
+// Sparse JL transform by the hashing trick: coordinate i of a feature vector is added to
+// bucket h(i) of k with the random sign g(i). Inner products and squared norms are preserved
+// in expectation, with the 4-wise independent signs bounding the variance, and a vector costs
+// time in its nonzero coordinates only, so no projection matrix is ever materialized.
+pub struct FeatureHashing {
+    bucket_hash: IndependentHash,
+    sign_hash: IndependentHash,
+    k: usize,
+}
+
+impl FeatureHashing {
+    pub fn new(k: usize) -> FeatureHashing {
+        assert!(k > 0, "Needs at least one bucket");
+        return FeatureHashing {
+            bucket_hash: IndependentHash::new(2),
+            sign_hash: IndependentHash::new(4),
+            k,
+        }
+    }
+    fn bucket(&self, feature: u64) -> usize {
+        return ((self.bucket_hash.hash(feature) as u128 * self.k as u128) >> 61) as usize;
+    }
+    // Compresses a vector given by its nonzero (feature, value) coordinates into k dimensions
+    pub fn transform(&self, features: &[(u64, f64)]) -> Vec<f64> {
+        let mut compressed: Vec<f64> = vec![0.0; self.k];
+        for (feature, value) in features {
+            compressed[self.bucket(*feature)] += self.sign_hash.sign(*feature) as f64 * value;
+        }
+        return compressed;
+    }
+    pub fn target(&self) -> usize {
+        return self.k;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(a: &[f64], b: &[f64]) -> f64 {
+        return a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum::<f64>().sqrt();
+    }
+
+    #[test]
+    fn pairwise_distances_are_preserved() {
+        const POINTS: usize = 50;
+        assert_eq!(JlProjection::target_dimension(POINTS, 0.5), 188);
+        // Twice the epsilon the dimension is chosen for, so the 1/n failure chance does not show
+        let k: usize = JlProjection::target_dimension(POINTS, 0.25);
+        let points: Vec<Vec<f64>> = (0..POINTS).map(|_| (0..1000).map(|_| gaussian()).collect()).collect();
+        for entries in [ProjectionEntries::Gaussian, ProjectionEntries::Sign] {
+            let projection: JlProjection = JlProjection::new(1000, k, entries);
+            let projected: Vec<Vec<f64>> = points.iter().map(|point| projection.project(point)).collect();
+            assert_eq!(projected[0].len(), k);
+            for i in 0..POINTS {
+                for j in 0..i {
+                    let ratio: f64 = distance(&projected[i], &projected[j]) / distance(&points[i], &points[j]);
+                    assert!((0.5..1.5).contains(&(ratio * ratio)), "{:?}: {}", entries, ratio);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn feature_hashing_preserves_sparse_inner_products() {
+        let hashing: FeatureHashing = FeatureHashing::new(1 << 12);
+        // Two vectors over features up to 2^40 sharing half of their 200 nonzero coordinates
+        let first: Vec<(u64, f64)> = (0..200u64).map(|i| (i * 5_497_558_139, 1.0 + (i % 3) as f64)).collect();
+        let second: Vec<(u64, f64)> = (100..300u64).map(|i| (i * 5_497_558_139, 2.0)).collect();
+        let dot = |a: &[f64], b: &[f64]| -> f64 { a.iter().zip(b).map(|(x, y)| x * y).sum() };
+        let (a, b) = (hashing.transform(&first), hashing.transform(&second));
+        assert_eq!(a.len(), 1 << 12);
+        let exact: f64 = (100..200u64).map(|i| 2.0 * (1.0 + (i % 3) as f64)).sum();
+        assert!((dot(&a, &b) - exact).abs() < 0.25 * exact, "{} vs {}", dot(&a, &b), exact);
+        let norm: f64 = first.iter().map(|(_, value)| value * value).sum();
+        assert!((dot(&a, &a) - norm).abs() < 0.1 * norm, "{} vs {}", dot(&a, &a), norm);
+    }
+}
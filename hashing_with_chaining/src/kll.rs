@@ -0,0 +1,237 @@
+use rand::prelude::*;
+use crate::mergeable::{MergeError, MergeableSketch};
+use crate::sketch_format::{ByteReader, ByteWriter, DecodeError, SketchKind};
+
+// Ratio between the capacities of consecutive compactors
+const CAPACITY_DECAY: f64 = 2.0 / 3.0;
+// Smallest compactor capacity, which must leave a pair to compact
+const MIN_CAPACITY: usize = 2;
+
+// KLL quantile sketch. Values enter the compactor at level 0, and an item at level h stands
+// for 2^h values. A full compactor sorts its items and promotes either the odd or the even
+// positioned ones to the next level, chosen at random so ranks stay unbiased. Capacities
+// shrink geometrically below the top level of k items, so the sketch keeps O(k) items and
+// estimates ranks within about 2.5 / k of the stream length.
+#[derive(Clone, Debug)]
+pub struct KllSketch {
+    compactors: Vec<Vec<f64>>,
+    k: usize,
+    len: u64,
+}
+
+impl KllSketch {
+    // Ranks within epsilon of the stream length with high probability
+    pub fn new(epsilon: f64) -> KllSketch {
+        assert!(epsilon > 0.0 && epsilon < 1.0, "Epsilon must be in (0, 1)");
+        return KllSketch::with_k((2.5 / epsilon).ceil() as usize);
+    }
+    pub fn with_k(k: usize) -> KllSketch {
+        assert!(k >= MIN_CAPACITY, "k must be at least {}", MIN_CAPACITY);
+        return KllSketch {
+            compactors: vec![Vec::new()],
+            k,
+            len: 0,
+        }
+    }
+    fn capacity(&self, level: usize) -> usize {
+        let depth: i32 = (self.compactors.len() - level - 1) as i32;
+        return ((self.k as f64 * CAPACITY_DECAY.powi(depth)).ceil() as usize).max(MIN_CAPACITY);
+    }
+    fn size(&self) -> usize {
+        return self.compactors.iter().map(|compactor| compactor.len()).sum();
+    }
+    fn max_size(&self) -> usize {
+        return (0..self.compactors.len()).map(|level| self.capacity(level)).sum();
+    }
+    pub fn update(&mut self, value: f64) {
+        assert!(!value.is_nan(), "NaN has no rank");
+        self.compactors[0].push(value);
+        self.len += 1;
+        self.compress();
+    }
+    // Compacts the lowest full compactor until the sketch is within its total capacity
+    fn compress(&mut self) {
+        while self.size() > self.max_size() {
+            let level: usize = (0..self.compactors.len())
+                .find(|level| self.compactors[*level].len() >= self.capacity(*level))
+                .expect("Some compactor is over capacity");
+            if level + 1 == self.compactors.len() {
+                self.compactors.push(Vec::new());
+            }
+            let mut items: Vec<f64> = std::mem::take(&mut self.compactors[level]);
+            items.sort_unstable_by(f64::total_cmp);
+            if items.len() % 2 == 1 {
+                self.compactors[level].push(items.pop().unwrap());
+            }
+            let offset: usize = thread_rng().gen_range(0..2);
+            let promoted: Vec<f64> = items.into_iter().skip(offset).step_by(2).collect();
+            self.compactors[level + 1].extend(promoted);
+        }
+    }
+    // Retained items with their weights, sorted by value
+    fn weighted_items(&self) -> Vec<(f64, u64)> {
+        let mut items: Vec<(f64, u64)> = self.compactors.iter().enumerate()
+            .flat_map(|(level, compactor)| compactor.iter().map(move |value| (*value, 1u64 << level)))
+            .collect();
+        items.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+        return items;
+    }
+    // Estimated fraction of the values that are at most value
+    pub fn rank(&self, value: f64) -> f64 {
+        if self.len == 0 {
+            return 0.0;
+        }
+        let below: u64 = self.compactors.iter().enumerate()
+            .map(|(level, compactor)| (compactor.iter().filter(|x| **x <= value).count() as u64) << level)
+            .sum();
+        return below as f64 / self.len as f64;
+    }
+    // Smallest retained value whose estimated rank is at least q, or None for an empty sketch
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        assert!((0.0..=1.0).contains(&q), "Quantiles must be in [0, 1]");
+        let items: Vec<(f64, u64)> = self.weighted_items();
+        let target: f64 = q * self.len as f64;
+        let mut cumulative: u64 = 0;
+        for (value, weight) in &items {
+            cumulative += weight;
+            if cumulative as f64 >= target {
+                return Some(*value);
+            }
+        }
+        return items.last().map(|(value, _)| *value);
+    }
+    pub fn len(&self) -> u64 {
+        return self.len;
+    }
+    pub fn is_empty(&self) -> bool {
+        return self.len == 0;
+    }
+    pub fn k(&self) -> usize {
+        return self.k;
+    }
+    // Number of values the sketch holds
+    pub fn retained(&self) -> usize {
+        return self.size();
+    }
+    // k (u64) | len (u64) | number of compactors (u64), followed by every compactor as its
+    // number of items (u64) and the items (f64)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new(SketchKind::KllSketch);
+        writer.u64(self.k as u64);
+        writer.u64(self.len);
+        writer.u64(self.compactors.len() as u64);
+        for compactor in &self.compactors {
+            writer.u64(compactor.len() as u64);
+            compactor.iter().for_each(|value| writer.f64(*value));
+        }
+        return writer.finish();
+    }
+    // The items of level h stand for 2^h values each, which must add up to len
+    pub fn from_bytes(bytes: &[u8]) -> Result<KllSketch, DecodeError> {
+        let mut reader: ByteReader = ByteReader::new(bytes, SketchKind::KllSketch)?;
+        let k: usize = reader.u64()? as usize;
+        if k < MIN_CAPACITY {
+            return Err(DecodeError::Invalid("k below the smallest capacity"));
+        }
+        let len: u64 = reader.u64()?;
+        let levels: usize = reader.len(size_of::<u64>())?;
+        if !(1..=64).contains(&levels) {
+            return Err(DecodeError::Invalid("number of compactors out of range"));
+        }
+        let mut compactors: Vec<Vec<f64>> = Vec::with_capacity(levels);
+        let mut weight: u64 = 0;
+        for level in 0..levels {
+            let items: usize = reader.len(size_of::<f64>())?;
+            let compactor: Vec<f64> = (0..items).map(|_| reader.f64()).collect::<Result<_, _>>()?;
+            if compactor.iter().any(|value| value.is_nan()) {
+                return Err(DecodeError::Invalid("NaN item"));
+            }
+            weight = (items as u64).checked_mul(1 << level).and_then(|w| weight.checked_add(w))
+                .ok_or(DecodeError::Invalid("item weights do not add up to the stream length"))?;
+            compactors.push(compactor);
+        }
+        reader.finish()?;
+        if weight != len {
+            return Err(DecodeError::Invalid("item weights do not add up to the stream length"));
+        }
+        return Ok(KllSketch { compactors, k, len });
+    }
+}
+
+// Both sketches' compactors are concatenated level by level and compressed back to capacity
+impl MergeableSketch for KllSketch {
+    fn merge(&mut self, other: &KllSketch) -> Result<(), MergeError> {
+        if self.k != other.k {
+            return Err(MergeError::DimensionMismatch);
+        }
+        while self.compactors.len() < other.compactors.len() {
+            self.compactors.push(Vec::new());
+        }
+        for (compactor, other_compactor) in self.compactors.iter_mut().zip(&other.compactors) {
+            compactor.extend_from_slice(other_compactor);
+        }
+        self.len += other.len;
+        self.compress();
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn max_rank_error(sketch: &KllSketch, sorted: &[f64]) -> f64 {
+        return (1..100).map(|i| {
+            let value: f64 = sorted[i * sorted.len() / 100];
+            let true_rank: f64 = sorted.partition_point(|x| *x <= value) as f64 / sorted.len() as f64;
+            (sketch.rank(value) - true_rank).abs()
+        }).fold(0.0, f64::max);
+    }
+
+    #[test]
+    fn ranks_and_quantiles_are_within_epsilon() {
+        let mut values: Vec<f64> = (0..200_000).map(|x| x as f64).collect();
+        values.shuffle(&mut thread_rng());
+        let mut sketch: KllSketch = KllSketch::new(0.01);
+        values.iter().for_each(|x| sketch.update(*x));
+        assert!(sketch.retained() < 4 * sketch.k());
+        values.sort_unstable_by(f64::total_cmp);
+        assert!(max_rank_error(&sketch, &values) < 0.01);
+        let median: f64 = sketch.quantile(0.5).unwrap();
+        assert!((median - 100_000.0).abs() < 2000.0);
+        assert_eq!(KllSketch::new(0.01).quantile(0.5), None);
+    }
+
+    #[test]
+    fn merged_sketches_keep_the_error_bound() {
+        let empty: KllSketch = KllSketch::new(0.01);
+        let (mut left, mut right) = (empty.clone(), empty.clone());
+        let mut values: Vec<f64> = Vec::new();
+        for i in 0..100_000 {
+            let value: f64 = thread_rng().gen::<f64>().powi(3);
+            values.push(value);
+            if i % 4 == 0 { left.update(value) } else { right.update(value) }
+        }
+        left.merge(&right).unwrap();
+        assert_eq!(left.len(), 100_000);
+        values.sort_unstable_by(f64::total_cmp);
+        assert!(max_rank_error(&left, &values) < 0.01);
+        assert_eq!(left.merge(&KllSketch::with_k(10)), Err(MergeError::DimensionMismatch));
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut sketch: KllSketch = KllSketch::new(0.05);
+        (0..10_000).for_each(|x| sketch.update((x * 7919 % 10_000) as f64));
+        let bytes: Vec<u8> = sketch.to_bytes();
+        let restored: KllSketch = KllSketch::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.compactors, sketch.compactors);
+        assert_eq!(restored.len(), 10_000);
+        assert!((0..=10).all(|i| restored.quantile(i as f64 / 10.0) == sketch.quantile(i as f64 / 10.0)));
+
+        // One more value than the items stand for
+        let mut bytes: Vec<u8> = bytes;
+        bytes[16..24].copy_from_slice(&10_001u64.to_le_bytes());
+        assert_eq!(KllSketch::from_bytes(&bytes).err(), Some(DecodeError::Invalid("item weights do not add up to the stream length")));
+    }
+}
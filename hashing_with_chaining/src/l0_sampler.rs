@@ -0,0 +1,84 @@
+use crate::hash::IndependentHash;
+use crate::sparse_recovery::SparseRecovery;
+
+// Levels of an L0 sampler, one for every possible number of leading zeros of a 61-bit hash
+const LEVELS: u32 = 62;
+
+// L0 sampler: returns a near uniform key among those with a nonzero count in a turnstile
+// stream. A key belongs to level j when its 61-bit hash is below 2^(61 - j), so level j holds
+// about a 2^-j fraction of the support, and each level keeps a sparse recovery structure.
+// Every nonempty level contains the key of smallest hash, so the sampler recovers the
+// sparsest nonempty level it can and returns that key.
+pub struct L0Sampler {
+    levels: Vec<SparseRecovery>,
+    level_hash: IndependentHash,
+}
+
+impl L0Sampler {
+    // Sparsity around 8 already makes failures rare, since some level holds between s / 4
+    // and s / 2 keys unless the support is tiny
+    pub fn new(sparsity: usize) -> L0Sampler {
+        return L0Sampler {
+            levels: (0..LEVELS).map(|_| SparseRecovery::new(sparsity)).collect(),
+            level_hash: IndependentHash::new(4),
+        }
+    }
+    // Number of levels containing the key, which are the levels 0 to its leading zero count
+    fn depth(&self, key: u64) -> usize {
+        let hashed: u64 = self.level_hash.hash(key);
+        return if hashed == 0 { LEVELS as usize } else { (hashed.leading_zeros() - 3) as usize + 1 };
+    }
+    pub fn update(&mut self, key: u64, delta: i64) {
+        let depth: usize = self.depth(key);
+        for level in &mut self.levels[..depth] {
+            level.update(key, delta);
+        }
+    }
+    // A key of the support with its count, or None if the support is empty or recovery failed
+    pub fn sample(&self) -> Option<(u64, i64)> {
+        for level in self.levels.iter().rev() {
+            if let Some(recovered) = level.recover() {
+                if let Some(sampled) = recovered.into_iter().min_by_key(|(key, _)| self.level_hash.hash(*key)) {
+                    return Some(sampled);
+                }
+            }
+        }
+        return None;
+    }
+    // Heap footprint of all levels in bytes
+    pub fn space_usage(&self) -> usize {
+        return self.levels.iter().map(|level| level.space_usage()).sum();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::hash::random_generator;
+
+    #[test]
+    fn samples_are_close_to_uniform_over_the_support() {
+        const TRIALS: usize = 2000;
+        let survivors: Vec<u64> = (0..50u64).map(|x| x * 1_000_003 + 17).collect();
+        let mut counts: HashMap<u64, usize> = HashMap::new();
+        for _ in 0..TRIALS {
+            let mut sampler: L0Sampler = L0Sampler::new(8);
+            // Keys that are inserted and later deleted must never be sampled
+            for _ in 0..200 {
+                let key: u64 = random_generator(0, u32::MAX) as u64 * 2;
+                sampler.update(key, 5);
+                sampler.update(key, -5);
+            }
+            for key in &survivors {
+                sampler.update(*key, random_generator(1, 10) as i64);
+            }
+            let (key, count) = sampler.sample().unwrap();
+            assert!(survivors.contains(&key) && count > 0);
+            *counts.entry(key).or_default() += 1;
+        }
+        // 40 expected per key, with a standard deviation of about 6
+        assert_eq!(counts.len(), survivors.len());
+        assert!(counts.values().all(|count| (10..=75).contains(count)), "{:?}", counts);
+    }
+}
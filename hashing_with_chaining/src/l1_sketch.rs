@@ -0,0 +1,119 @@
+use std::f64::consts::PI;
+use std::mem::size_of;
+use serde::{Deserialize, Serialize};
+use crate::hash::IndependentHash;
+use crate::mergeable::{MergeError, MergeableSketch};
+
+// L1 norm of a turnstile vector through 1-stable random projections. Every row adds each
+// update times a standard Cauchy variable derived from the key, so by stability the row
+// is distributed as ||f||_1 times a standard Cauchy. The median of |Cauchy| is 1, so the
+// median of the absolute rows estimates ||f||_1. Counters are floating point, so an
+// update that is later undone only cancels up to rounding.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct L1Sketch {
+    counters: Vec<f64>,
+    hashes: Vec<IndependentHash>,
+}
+
+impl L1Sketch {
+    pub fn new(rows: usize) -> L1Sketch {
+        assert!(rows > 0, "Needs at least one row");
+        return L1Sketch {
+            counters: vec![0.0; rows],
+            hashes: (0..rows).map(|_| IndependentHash::new(4)).collect(),
+        }
+    }
+    // The fraction of rows below (1 - epsilon) ||f||_1 or above (1 + epsilon) ||f||_1 is off
+    // from 1/2 by about epsilon / pi, so by Hoeffding pi^2 / (2 epsilon^2) ln(2 / delta) rows
+    // keep the median within epsilon with probability 1 - delta
+    pub fn with_guarantees(epsilon: f64, delta: f64) -> L1Sketch {
+        let rows: f64 = PI * PI / (2.0 * epsilon * epsilon) * (2.0 / delta).ln();
+        return L1Sketch::new(rows.ceil().max(1.0) as usize);
+    }
+    // Standard Cauchy variable tan(pi (u - 1/2)) for the uniform u in (0, 1) given by the hash
+    fn cauchy(hash_function: &IndependentHash, key: u64) -> f64 {
+        let u: f64 = (hash_function.hash(key) as f64 + 0.5) / (1u64 << 61) as f64;
+        return (PI * (u - 0.5)).tan();
+    }
+    pub fn insert(&mut self, key: u64, delta: i64) {
+        for (counter, hash_function) in self.counters.iter_mut().zip(&self.hashes) {
+            *counter += delta as f64 * L1Sketch::cauchy(hash_function, key);
+        }
+    }
+    fn median_abs(values: impl Iterator<Item = f64>) -> f64 {
+        let mut values: Vec<f64> = values.map(f64::abs).collect();
+        values.sort_unstable_by(|a, b| a.total_cmp(b));
+        let middle: usize = values.len() / 2;
+        if values.len().is_multiple_of(2) {
+            return (values[middle - 1] + values[middle]) / 2.0;
+        }
+        return values[middle];
+    }
+    // Estimate of the sum of absolute counts
+    pub fn norm(&self) -> f64 {
+        return L1Sketch::median_abs(self.counters.iter().copied());
+    }
+    // Estimate of the L1 distance between the streams of two sketches, which is the norm of
+    // their difference since the rows are linear
+    pub fn distance(&self, other: &L1Sketch) -> Result<f64, MergeError> {
+        self.check_mergeable(other)?;
+        return Ok(L1Sketch::median_abs(self.counters.iter().zip(&other.counters).map(|(a, b)| a - b)));
+    }
+    fn check_mergeable(&self, other: &L1Sketch) -> Result<(), MergeError> {
+        if self.counters.len() != other.counters.len() {
+            return Err(MergeError::DimensionMismatch);
+        }
+        if self.hashes != other.hashes {
+            return Err(MergeError::SeedMismatch);
+        }
+        return Ok(());
+    }
+    pub fn rows(&self) -> usize {
+        return self.counters.len();
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.counters.len() * size_of::<f64>();
+    }
+}
+
+// Rows are linear in the stream, so merging adds them up
+impl MergeableSketch for L1Sketch {
+    fn merge(&mut self, other: &L1Sketch) -> Result<(), MergeError> {
+        self.check_mergeable(other)?;
+        for (counter, other_counter) in self.counters.iter_mut().zip(&other.counters) {
+            *counter += *other_counter;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn norm_and_distance_stay_within_epsilon() {
+        let empty: L1Sketch = L1Sketch::with_guarantees(0.1, 0.01);
+        assert_eq!(empty.rows(), 2615);
+        let (mut first, mut second) = (empty.clone(), empty.clone());
+        for key in 0..1000u64 {
+            // Keys added and removed again must not count
+            first.insert(key + 5000, 7);
+            first.insert(key, (key % 11) as i64 - 5);
+            first.insert(key + 5000, -7);
+            second.insert(key, (key % 7) as i64 - 3);
+        }
+        let norm: f64 = (0..1000).map(|key| ((key % 11) as f64 - 5.0).abs()).sum();
+        assert!((first.norm() - norm).abs() < 0.1 * norm, "{} vs {}", first.norm(), norm);
+        let distance: f64 = (0..1000).map(|key| ((key % 11) as f64 - 5.0 - ((key % 7) as f64 - 3.0)).abs()).sum();
+        let estimate: f64 = first.distance(&second).unwrap();
+        assert!((estimate - distance).abs() < 0.1 * distance, "{} vs {}", estimate, distance);
+
+        let mut merged: L1Sketch = first.clone();
+        merged.merge(&second).unwrap();
+        let sum: f64 = (0..1000).map(|key| ((key % 11) as f64 - 5.0 + (key % 7) as f64 - 3.0).abs()).sum();
+        assert!((merged.norm() - sum).abs() < 0.1 * sum, "{} vs {}", merged.norm(), sum);
+        assert_eq!(first.distance(&L1Sketch::new(10)).err(), Some(MergeError::DimensionMismatch));
+        assert_eq!(first.merge(&L1Sketch::with_guarantees(0.1, 0.01)).err(), Some(MergeError::SeedMismatch));
+    }
+}
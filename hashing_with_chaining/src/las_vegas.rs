@@ -0,0 +1,51 @@
+// Result of a Las Vegas retry loop: the value of the first attempt that succeeded, if any
+// did within the limit, and how many attempts were made
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Attempts<T> {
+    pub value: Option<T>,
+    pub attempts: usize,
+}
+
+impl<T> Attempts<T> {
+    // Attempts that failed and were thrown away
+    pub fn retries(&self) -> usize {
+        return self.attempts - self.value.is_some() as usize;
+    }
+}
+
+// Runs attempt, which draws fresh randomness every time and returns None on failure, until it
+// succeeds or max_attempts attempts have failed. The answer is always right and only the
+// running time is random: if every attempt succeeds with probability at least p, the
+// attempts are geometric with mean at most 1 / p and all k fail with probability at most
+// (1 - p)^k. A limit of usize::MAX retries for as long as it takes.
+pub fn las_vegas<T>(max_attempts: usize, mut attempt: impl FnMut() -> Option<T>) -> Attempts<T> {
+    for attempts in 1..=max_attempts {
+        if let Some(value) = attempt() {
+            return Attempts { value: Some(value), attempts }
+        }
+    }
+    return Attempts { value: None, attempts: max_attempts }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn counts_attempts_until_success() {
+        let mut calls: usize = 0;
+        let found: Attempts<usize> = las_vegas(10, || { calls += 1; if calls == 4 { Some(calls) } else { None } });
+        assert_eq!(found, Attempts { value: Some(4), attempts: 4 });
+        assert_eq!(found.retries(), 3);
+        let failed: Attempts<()> = las_vegas(5, || None);
+        assert_eq!((failed.value, failed.attempts, failed.retries()), (None, 5, 5));
+        assert_eq!(las_vegas(0, || Some(1)).value, None);
+
+        // Success probability 1/4 takes 4 attempts on average, with standard deviation
+        // sqrt(12) / sqrt(10000) ≈ 0.035 for the mean
+        let mut rng = thread_rng();
+        let total: usize = (0..10_000).map(|_| las_vegas(usize::MAX, || (rng.gen_range(0..4) == 0).then_some(())).attempts).sum();
+        assert!((total as f64 / 10_000.0 - 4.0).abs() < 0.2, "{}", total);
+    }
+}
@@ -0,0 +1,178 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+// The hash functions, NormSketch, Count-Min and the Bloom filters build without std, on
+// core and alloc alone, so embedded collectors can sketch a stream and send the serialized
+// sketches upstream. Without std there is no thread_rng, so their hash functions are drawn
+// through the with_rng constructors.
+pub mod bloom;
+pub mod count_min;
+pub mod counter;
+pub mod hash;
+pub mod memory;
+pub mod mergeable;
+pub mod norm_sketch;
+pub mod sketch_format;
+pub(crate) mod float;
+
+// Everything else needs std. The modules that read the clock, write files or listen on a
+// socket need the harness feature on top, which the WebAssembly build leaves out.
+#[cfg(feature = "std")]
+pub mod accuracy;
+#[cfg(feature = "std")]
+pub mod alias;
+#[cfg(feature = "std")]
+pub mod ams;
+#[cfg(feature = "std")]
+pub mod annealing;
+#[cfg(feature = "std")]
+pub mod atomic_table;
+#[cfg(feature = "std")]
+pub mod balls_into_bins;
+#[cfg(feature = "std")]
+pub mod birthday;
+#[cfg(feature = "std")]
+pub mod bloomier;
+#[cfg(feature = "std")]
+pub mod coupon_collector;
+#[cfg(feature = "std")]
+pub mod count_sketch;
+#[cfg(feature = "std")]
+pub mod cuckoo_filter;
+#[cfg(feature = "std")]
+pub mod cuckoo_table;
+#[cfg(feature = "std")]
+pub mod datasets;
+#[cfg(feature = "std")]
+pub mod decay;
+#[cfg(feature = "std")]
+pub mod distributions;
+#[cfg(feature = "harness")]
+pub mod document;
+#[cfg(feature = "harness")]
+pub mod experiment;
+#[cfg(feature = "std")]
+pub mod double_hashing;
+#[cfg(feature = "std")]
+pub mod exponential_histogram;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod freivalds;
+#[cfg(feature = "std")]
+pub mod graph;
+#[cfg(feature = "std")]
+pub mod hierarchy;
+#[cfg(feature = "std")]
+pub mod hopscotch;
+#[cfg(feature = "std")]
+pub mod hwc;
+#[cfg(feature = "std")]
+pub mod hyperloglog;
+#[cfg(feature = "std")]
+pub mod iblt;
+#[cfg(feature = "std")]
+pub mod inputs;
+#[cfg(feature = "std")]
+pub mod jl_projection;
+#[cfg(feature = "std")]
+pub mod kll;
+#[cfg(feature = "std")]
+pub mod las_vegas;
+#[cfg(feature = "std")]
+pub mod l0_sampler;
+#[cfg(feature = "std")]
+pub mod l1_sketch;
+#[cfg(feature = "harness")]
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod minhash;
+#[cfg(feature = "std")]
+pub mod misra_gries;
+#[cfg(feature = "std")]
+pub mod monte_carlo;
+#[cfg(feature = "std")]
+pub mod linear_probing;
+#[cfg(feature = "std")]
+pub mod low_rank;
+#[cfg(feature = "std")]
+pub mod lp_sampler;
+#[cfg(feature = "std")]
+pub mod lsh;
+#[cfg(feature = "std")]
+pub mod matrix_sampling;
+#[cfg(feature = "std")]
+pub mod max_cut;
+#[cfg(feature = "std")]
+pub mod mphf;
+#[cfg(feature = "std")]
+pub mod odd_sketch;
+#[cfg(feature = "std")]
+pub mod perfect_hashing;
+#[cfg(feature = "harness")]
+pub mod plot;
+#[cfg(feature = "std")]
+pub mod prime;
+#[cfg(feature = "harness")]
+pub mod progress;
+#[cfg(test)]
+mod properties;
+#[cfg(feature = "std")]
+pub mod quicksort;
+#[cfg(feature = "std")]
+pub mod random_walk;
+#[cfg(feature = "std")]
+pub mod range_count_min;
+#[cfg(feature = "std")]
+pub mod reservoir;
+#[cfg(feature = "harness")]
+pub mod results;
+#[cfg(feature = "std")]
+pub mod rounding;
+#[cfg(feature = "std")]
+pub mod sat;
+#[cfg(feature = "std")]
+pub mod selection;
+#[cfg(feature = "std")]
+pub mod sharded_hwc;
+#[cfg(feature = "std")]
+pub mod shuffle;
+#[cfg(feature = "std")]
+pub mod simhash;
+#[cfg(feature = "std")]
+pub mod sketch_builder;
+#[cfg(feature = "harness")]
+pub mod sketch_server;
+#[cfg(feature = "std")]
+pub mod skip_list;
+#[cfg(feature = "std")]
+pub mod space_saving;
+#[cfg(feature = "std")]
+pub mod sparse_recovery;
+#[cfg(feature = "harness")]
+pub mod static_table;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod streaming;
+#[cfg(feature = "std")]
+pub mod subset;
+#[cfg(feature = "std")]
+pub mod subset_sum;
+#[cfg(feature = "std")]
+pub mod table_stats;
+#[cfg(feature = "std")]
+pub mod tdigest;
+#[cfg(feature = "std")]
+pub mod theta_sketch;
+#[cfg(feature = "std")]
+pub mod top_k;
+#[cfg(feature = "std")]
+pub mod treap;
+#[cfg(feature = "std")]
+pub mod two_choice_hwc;
+#[cfg(feature = "std")]
+pub mod update_format;
+#[cfg(feature = "wasm")]
+pub mod wasm;
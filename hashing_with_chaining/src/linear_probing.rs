@@ -0,0 +1,166 @@
+use std::mem::size_of;
+use crate::hash::{log2u, SeededHash};
+use crate::memory::{vec_allocations, MemoryFootprint};
+use crate::table_stats::TableStats;
+
+// Linear probing with multiply-shift hashing, 0 marks an empty slot.
+// Deletion shifts later elements of the cluster back instead of leaving tombstones,
+// so long running insert/delete workloads keep their probe lengths
+pub struct LinearProbing {
+    vec: Vec<u64>,
+    hash_function: SeededHash,
+    // probe_lengths[i] counts the insertions that inspected i+1 slots
+    probe_lengths: Vec<usize>,
+}
+
+impl LinearProbing {
+    pub fn new(size: usize) -> LinearProbing {
+        // Twice as many slots as the expected number of elements keeps the load factor at 1/2
+        LinearProbing::with_hash_len(log2u(size) + 1)
+    }
+    pub fn with_hash_len(hash_len: u32) -> LinearProbing {
+        return LinearProbing {
+            vec: vec![0; 1 << hash_len],
+            hash_function: SeededHash::new(hash_len),
+            probe_lengths: Vec::new(),
+        }
+    }
+    pub fn insert(&mut self, elem: u64) {
+        assert_ne!(elem, 0, "0 marks an empty slot");
+        let mask: usize = self.vec.len() - 1;
+        let mut pos: usize = self.hash_function.hash(elem);
+        for probes in 0..self.vec.len() {
+            if self.vec[pos] == elem {
+                return;
+            }
+            if self.vec[pos] == 0 {
+                self.vec[pos] = elem;
+                if self.probe_lengths.len() <= probes {
+                    self.probe_lengths.resize(probes + 1, 0);
+                }
+                self.probe_lengths[probes] += 1;
+                return;
+            }
+            pos = (pos + 1) & mask;
+        }
+        panic!("Linear probing table is full");
+    }
+    pub fn query(&self, elem: u64) -> bool {
+        let mask: usize = self.vec.len() - 1;
+        let mut pos: usize = self.hash_function.hash(elem);
+        for _ in 0..self.vec.len() {
+            if self.vec[pos] == elem {
+                return true;
+            }
+            if self.vec[pos] == 0 {
+                return false;
+            }
+            pos = (pos + 1) & mask;
+        }
+        return false;
+    }
+    pub fn delete(&mut self, elem: u64) -> bool {
+        let mask: usize = self.vec.len() - 1;
+        let mut pos: usize = self.hash_function.hash(elem);
+        let mut found: Option<usize> = None;
+        for _ in 0..self.vec.len() {
+            if self.vec[pos] == 0 {
+                return false;
+            }
+            if self.vec[pos] == elem {
+                found = Some(pos);
+                break;
+            }
+            pos = (pos + 1) & mask;
+        }
+        let Some(mut hole) = found else {
+            return false;
+        };
+
+        // Move every later element of the cluster that may live in the hole into it,
+        // going at most once around a full table
+        let mut next: usize = (hole + 1) & mask;
+        for _ in 1..self.vec.len() {
+            if self.vec[next] == 0 {
+                break;
+            }
+            let home: usize = self.hash_function.hash(self.vec[next]);
+            if (next.wrapping_sub(home) & mask) >= (next.wrapping_sub(hole) & mask) {
+                self.vec[hole] = self.vec[next];
+                hole = next;
+            }
+            next = (next + 1) & mask;
+        }
+        self.vec[hole] = 0;
+        return true;
+    }
+    // probe_lengths()[i] counts the insertions that inspected i+1 slots
+    pub fn probe_lengths(&self) -> &[usize] {
+        return &self.probe_lengths;
+    }
+    pub fn average_probe_length(&self) -> f64 {
+        let mut inserts: usize = 0;
+        let mut probes: usize = 0;
+        for (i, count) in self.probe_lengths.iter().enumerate() {
+            inserts += count;
+            probes += (i + 1) * count;
+        }
+        return probes as f64 / inserts as f64;
+    }
+    // Every slot counts as a bucket holding at most one element
+    pub fn stats(&self) -> TableStats {
+        let mut stats: TableStats = TableStats::new();
+        self.vec.iter().for_each(|x| stats.record_chain((*x != 0) as usize));
+        return stats;
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.vec.len() * size_of::<u64>();
+    }
+}
+
+impl MemoryFootprint for LinearProbing {
+    fn heap_bytes(&self) -> usize {
+        return self.space_usage();
+    }
+    fn allocations(&self) -> usize {
+        return vec_allocations(&self.vec);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use crate::hash::random_generator;
+
+    #[test]
+    fn interleaved_inserts_and_deletes_match_a_set() {
+        let mut lp: LinearProbing = LinearProbing::with_hash_len(14);
+        let mut model: HashSet<u64> = HashSet::new();
+        for _ in 0..2_000_000 {
+            // Keys from a small range keep the table around half full
+            let key: u64 = random_generator(1, 16_384) as u64;
+            if random_generator(0, 2) == 0 {
+                lp.insert(key);
+                model.insert(key);
+            } else {
+                assert_eq!(lp.delete(key), model.remove(&key));
+            }
+        }
+        assert!((1..16_384).all(|key| lp.query(key) == model.contains(&key)));
+        assert_eq!(lp.stats().entries, model.len());
+    }
+
+    #[test]
+    fn deletes_from_a_full_table() {
+        let mut lp: LinearProbing = LinearProbing::with_hash_len(3);
+        (1..=8).for_each(|key| lp.insert(key));
+        assert!(!lp.delete(100));
+        for key in 1..=8 {
+            assert!(lp.delete(key));
+            assert!(!lp.query(key));
+            assert!((key + 1..=8).all(|later| lp.query(later)));
+        }
+        assert_eq!(lp.stats().entries, 0);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::count_sketch::CountSketch;
+use crate::hash::IndependentHash;
+
+// Fixed point factor of the scaled updates, so small scales still survive rounding
+const FIXED_POINT: f64 = 16.0;
+// Largest scale of a coordinate, which keeps scaled updates far from overflowing the counters
+const MAX_SCALE: f64 = (1u64 << 36) as f64;
+
+// Approximate Lp sampler for p = 1 or 2 over the coordinates [0, universe) of a turnstile
+// vector f. Coordinate i is scaled by E_i^(-1/p) for an exponential E_i derived from its
+// hash, and the largest scaled coordinate is i with probability |f_i|^p / ||f||_p^p, since
+// the minimum of E_i / |f_i|^p is an exponential race. A Count Sketch of the scaled vector
+// finds that coordinate, which is found exactly when it dominates the sketch error.
+pub struct LpSampler {
+    sketch: CountSketch,
+    scale_hash: IndependentHash,
+    p: u32,
+    universe: u64,
+}
+
+impl LpSampler {
+    pub fn new(p: u32, universe: u64, depth: usize, width_len: u32) -> LpSampler {
+        assert!(p == 1 || p == 2, "Only p = 1 and p = 2 are supported");
+        return LpSampler {
+            sketch: CountSketch::new(depth, width_len),
+            scale_hash: IndependentHash::new(4),
+            p,
+            universe,
+        }
+    }
+    // E_i^(-1/p) for the exponential E_i = -ln(u_i) of a uniform u_i in (0, 1]
+    fn scale(&self, key: u64) -> f64 {
+        let u: f64 = (self.scale_hash.hash(key) + 1) as f64 / (1u64 << 61) as f64;
+        let exponential: f64 = -u.ln();
+        return exponential.powf(-1.0 / self.p as f64).min(MAX_SCALE);
+    }
+    pub fn update(&mut self, key: u64, delta: i64) {
+        assert!(key < self.universe, "Key {} is outside of the universe", key);
+        let scaled: f64 = (delta as f64 * self.scale(key) * FIXED_POINT).round();
+        self.sketch.insert(key, scaled as i64);
+    }
+    // A coordinate with its estimated value, chosen with probability about |f_i|^p / ||f||_p^p.
+    // Scans the whole universe, so it is meant for moderate universes. None if f looks zero.
+    pub fn sample(&self) -> Option<(u64, i64)> {
+        let (key, scaled) = (0..self.universe)
+            .map(|key| (key, self.sketch.estimate(key)))
+            .max_by_key(|(_, scaled)| scaled.unsigned_abs())?;
+        if scaled == 0 {
+            return None;
+        }
+        let value: f64 = scaled as f64 / (self.scale(key) * FIXED_POINT);
+        return Some((key, value.round() as i64));
+    }
+    pub fn p(&self) -> u32 {
+        return self.p;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sampling frequencies over the vector f_i = (-1)^i (i + 1) for i < 8 must be close to
+    // |f_i|^p / ||f||_p^p. Sketch collisions rarely return a wrong key or value.
+    fn check_distribution(p: u32) {
+        const TRIALS: usize = 4000;
+        let values: Vec<i64> = (0..8).map(|i| if i % 2 == 0 { i + 1 } else { -(i + 1) }).collect();
+        let mut counts: Vec<usize> = vec![0; values.len()];
+        let mut errors: usize = 0;
+        for _ in 0..TRIALS {
+            let mut sampler: LpSampler = LpSampler::new(p, 64, 5, 6);
+            for (key, value) in values.iter().enumerate() {
+                // Two updates per coordinate, so cancellation across updates is exercised
+                sampler.update(key as u64, 2 * value);
+                sampler.update(key as u64, -value);
+            }
+            match sampler.sample() {
+                Some((key, value)) if key < 8 && value == values[key as usize] => counts[key as usize] += 1,
+                _ => errors += 1,
+            }
+        }
+        assert!(errors < TRIALS / 100);
+        let norm: f64 = values.iter().map(|value| (value.abs() as f64).powi(p as i32)).sum();
+        for (count, value) in counts.iter().zip(&values) {
+            let expected: f64 = (value.abs() as f64).powi(p as i32) / norm;
+            assert!((*count as f64 / TRIALS as f64 - expected).abs() < 0.03, "{} vs {}", count, expected);
+        }
+    }
+
+    #[test]
+    fn l1_sampling_follows_absolute_values() {
+        check_distribution(1);
+    }
+
+    #[test]
+    fn l2_sampling_follows_squares() {
+        check_distribution(2);
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use crate::hash::{gaussian, SeededHash};
+use crate::minhash::MinHash;
+
+// Bucket of a tuple of hash values, chaining the hash function over the values
+fn bucket(hash_function: &SeededHash, values: impl Iterator<Item = u64>) -> u64 {
+    return values.fold(0u64, |bucket, value| hash_function.hash(bucket ^ value) as u64);
+}
+
+// Locality sensitive hashing index over MinHash signatures of bands * rows coordinates.
+// Every band of rows consecutive coordinates is hashed to a bucket of its own table, and
+// two sets become candidates when they share a bucket in any band. Sets with Jaccard
+// similarity J collide in a band with probability J^rows, and so become candidates
+// with probability 1 - (1 - J^rows)^bands, an S-curve around (1 / bands)^(1 / rows).
+pub struct LshIndex {
+    tables: Vec<HashMap<u64, Vec<usize>>>,
+    band_hashes: Vec<SeededHash>,
+    rows: usize,
+}
+
+impl LshIndex {
+    pub fn new(bands: usize, rows: usize) -> LshIndex {
+        assert!(bands > 0 && rows > 0, "Needs at least one band of one row");
+        return LshIndex {
+            tables: vec![HashMap::new(); bands],
+            band_hashes: (0..bands).map(|_| SeededHash::new(64)).collect(),
+            rows,
+        }
+    }
+    // Similarity at which a pair is a candidate with probability about 1/2
+    pub fn threshold(&self) -> f64 {
+        return (1.0 / self.tables.len() as f64).powf(1.0 / self.rows as f64);
+    }
+    // The bucket of every band, chaining the band hash over the coordinates of the band
+    fn buckets<'a>(&'a self, signature: &'a MinHash) -> impl Iterator<Item = u64> + 'a {
+        assert_eq!(signature.len(), self.tables.len() * self.rows, "Signatures must have bands * rows coordinates");
+        return signature.signature().chunks(self.rows).zip(&self.band_hashes)
+            .map(|(band, hash_function)| bucket(hash_function, band.iter().copied()));
+    }
+    pub fn insert(&mut self, id: usize, signature: &MinHash) {
+        let buckets: Vec<u64> = self.buckets(signature).collect();
+        for (table, bucket) in self.tables.iter_mut().zip(buckets) {
+            table.entry(bucket).or_default().push(id);
+        }
+    }
+    // Ids sharing a bucket with the query in at least one band, sorted and without duplicates
+    pub fn candidates(&self, query: &MinHash) -> Vec<usize> {
+        let mut candidates: Vec<usize> = Vec::new();
+        for (table, bucket) in self.tables.iter().zip(self.buckets(query)) {
+            if let Some(ids) = table.get(&bucket) {
+                candidates.extend_from_slice(ids);
+            }
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+        return candidates;
+    }
+}
+
+// E2LSH hash function for Euclidean distance, concatenating k functions
+// h(x) = floor((a·x + b) / w) with a Gaussian and b uniform in [0, w). Gaussians are
+// 2-stable, so a·x - a·y is distributed as |x - y| times a standard normal, and close
+// points are more likely to fall in the same cell of width w.
+pub struct PStableHash {
+    projections: Vec<Vec<f64>>,
+    offsets: Vec<f64>,
+    width: f64,
+}
+
+impl PStableHash {
+    pub fn new(dimension: usize, k: usize, width: f64) -> PStableHash {
+        assert!(width > 0.0, "Cells must have positive width");
+        return PStableHash {
+            projections: (0..k).map(|_| (0..dimension).map(|_| gaussian()).collect()).collect(),
+            offsets: (0..k).map(|_| rand::random::<f64>() * width).collect(),
+            width,
+        }
+    }
+    pub fn hash(&self, point: &[f64]) -> Vec<i64> {
+        assert_eq!(point.len(), self.dimension(), "Point has the wrong dimension");
+        return self.projections.iter().zip(&self.offsets).map(|(projection, offset)| {
+            let dot: f64 = projection.iter().zip(point).map(|(a, x)| a * x).sum();
+            ((dot + offset) / self.width).floor() as i64
+        }).collect();
+    }
+    pub fn dimension(&self) -> usize {
+        return self.projections.first().map_or(0, |projection| projection.len());
+    }
+}
+
+fn distance(a: &[f64], b: &[f64]) -> f64 {
+    return a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum::<f64>().sqrt();
+}
+
+// Approximate Euclidean nearest neighbor index over `tables` independent E2LSH hash functions.
+// Points are candidates when they share a cell with the query in any table, and only the
+// candidates are compared exactly. More tables raise recall at the cost of query time.
+pub struct EuclideanLshIndex {
+    tables: Vec<HashMap<u64, Vec<usize>>>,
+    hash_functions: Vec<PStableHash>,
+    bucket_hash: SeededHash,
+    points: Vec<Vec<f64>>,
+}
+
+impl EuclideanLshIndex {
+    pub fn new(dimension: usize, tables: usize, k: usize, width: f64) -> EuclideanLshIndex {
+        assert!(tables > 0 && k > 0, "Needs at least one table of one hash function");
+        return EuclideanLshIndex {
+            tables: vec![HashMap::new(); tables],
+            hash_functions: (0..tables).map(|_| PStableHash::new(dimension, k, width)).collect(),
+            bucket_hash: SeededHash::new(64),
+            points: Vec::new(),
+        }
+    }
+    fn buckets<'a>(&'a self, point: &'a [f64]) -> impl Iterator<Item = u64> + 'a {
+        return self.hash_functions.iter()
+            .map(|hash_function| bucket(&self.bucket_hash, hash_function.hash(point).into_iter().map(|cell| cell as u64)));
+    }
+    // Stores a copy of the point and returns its id
+    pub fn insert(&mut self, point: &[f64]) -> usize {
+        let id: usize = self.points.len();
+        let buckets: Vec<u64> = self.buckets(point).collect();
+        for (table, bucket) in self.tables.iter_mut().zip(buckets) {
+            table.entry(bucket).or_default().push(id);
+        }
+        self.points.push(point.to_vec());
+        return id;
+    }
+    // Ids sharing a cell with the query in at least one table, sorted and without duplicates
+    pub fn candidates(&self, query: &[f64]) -> Vec<usize> {
+        let mut candidates: Vec<usize> = Vec::new();
+        for (table, bucket) in self.tables.iter().zip(self.buckets(query)) {
+            if let Some(ids) = table.get(&bucket) {
+                candidates.extend_from_slice(ids);
+            }
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+        return candidates;
+    }
+    // Closest candidate to the query, or None if no point shares a cell with it
+    pub fn nearest(&self, query: &[f64]) -> Option<usize> {
+        return self.candidates(query).into_iter()
+            .min_by(|a, b| distance(&self.points[*a], query).total_cmp(&distance(&self.points[*b], query)));
+    }
+    pub fn point(&self, id: usize) -> &[f64] {
+        return &self.points[id];
+    }
+    pub fn len(&self) -> usize {
+        return self.points.len();
+    }
+    pub fn is_empty(&self) -> bool {
+        return self.points.is_empty();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn near_duplicates_become_candidates() {
+        let empty: MinHash = MinHash::new(20 * 5);
+        let mut index: LshIndex = LshIndex::new(20, 5);
+        assert!(index.threshold() > 0.5 && index.threshold() < 0.6);
+        let mut signatures: Vec<MinHash> = Vec::new();
+        // Set i holds 1000 keys starting at 10_000 * i, so distinct sets are disjoint
+        for i in 0..50u64 {
+            let mut signature: MinHash = empty.clone();
+            (10_000 * i..10_000 * i + 1000).for_each(|key| signature.insert(key));
+            index.insert(i as usize, &signature);
+            signatures.push(signature);
+        }
+        // Jaccard 0.9 with set 7
+        let mut query: MinHash = empty.clone();
+        (70_000 + 50..71_000 + 50).for_each(|key| query.insert(key));
+        assert_eq!(index.candidates(&query), vec![7]);
+        assert_eq!(index.candidates(&signatures[12]), vec![12]);
+    }
+
+    #[test]
+    fn finds_the_nearest_point_under_small_perturbations() {
+        let mut index: EuclideanLshIndex = EuclideanLshIndex::new(16, 10, 4, 4.0);
+        let points: Vec<Vec<f64>> = (0..200).map(|_| (0..16).map(|_| 10.0 * gaussian()).collect()).collect();
+        for point in &points {
+            index.insert(point);
+        }
+        assert_eq!(index.len(), 200);
+        for (id, point) in points.iter().enumerate() {
+            let query: Vec<f64> = point.iter().map(|x| x + 0.01 * gaussian()).collect();
+            assert_eq!(index.nearest(&query), Some(id));
+        }
+        // Far away from every point, nothing shares a cell with the query
+        assert_eq!(index.nearest(&[1000.0; 16]), None);
+    }
+}
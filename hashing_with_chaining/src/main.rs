@@ -1,291 +1,3112 @@
-use std::collections::LinkedList;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use hashing_with_chaining::accuracy::{relative_error, ErrorSummary};
+use hashing_with_chaining::alias::AliasTable;
+use hashing_with_chaining::ams::{AmsF2Estimator, AmsFkEstimator};
+use hashing_with_chaining::annealing::{anneal, Annealed, Schedule};
+use hashing_with_chaining::balls_into_bins::{max_load_distribution, Policy};
+use hashing_with_chaining::birthday::{expected_keys_to_collision, find_collision, Collision};
+use hashing_with_chaining::bloom::{BlockedBloomFilter, BloomFilter, CountingBloomFilter, ScalableBloomFilter};
+use hashing_with_chaining::count_min::{CountMin, UpdatePolicy};
+use hashing_with_chaining::coupon_collector::{draws_to_collect, expected_draws_with_copies, tail_probability, CouponCollector};
+use hashing_with_chaining::count_sketch::CountSketch;
+use hashing_with_chaining::cuckoo_filter::CuckooFilter;
+use hashing_with_chaining::cuckoo_table::CuckooTable;
+use hashing_with_chaining::datasets::{read_trace, read_words, TraceFormat, WordStream};
+use hashing_with_chaining::distributions::{box_muller, standard_exponential, standard_normal};
+use hashing_with_chaining::double_hashing::DoubleHashingTable;
+use hashing_with_chaining::document::{render_document, Document, DocumentFormat, Section};
+use hashing_with_chaining::experiment::{interrupted, watch_interrupts, Experiment, Runner};
+use hashing_with_chaining::freivalds::{freivalds_verify, multiply};
+use hashing_with_chaining::graph::{Cut, Graph};
+use hashing_with_chaining::hopscotch::Hopscotch;
+use hashing_with_chaining::hash::{gaussian, random_generator, IndependentHash, SeededHash};
+use hashing_with_chaining::hyperloglog::HyperLogLog;
+use hashing_with_chaining::hwc::{ArenaChains, ChainStorage, HwC, InlineChains};
+use hashing_with_chaining::iblt::Iblt;
+use hashing_with_chaining::inputs::{clustered, zipf};
+use hashing_with_chaining::jl_projection::{FeatureHashing, JlProjection, ProjectionEntries};
+use hashing_with_chaining::kll::KllSketch;
+use hashing_with_chaining::linear_probing::LinearProbing;
+use hashing_with_chaining::low_rank::{exact_svd, optimal_error, randomized_svd, with_singular_values};
+use hashing_with_chaining::lsh::{EuclideanLshIndex, LshIndex};
+use hashing_with_chaining::matrix_sampling::{approximate_product, error_bound, exact_product, frobenius_error, frobenius_norm};
+use hashing_with_chaining::memory::MemoryFootprint;
+use hashing_with_chaining::max_cut::{expected_hyperplane_cut, goemans_williamson, local_embedding, random_cut, relaxation_value, CutState};
+use hashing_with_chaining::mergeable::{par_update, MergeableSketch};
+use hashing_with_chaining::metrics::{now, Elapsed};
+use hashing_with_chaining::minhash::{MinHash, OnePermutationHash};
+use hashing_with_chaining::misra_gries::MisraGries;
+use hashing_with_chaining::monte_carlo::{Estimate, Estimator};
+use hashing_with_chaining::mphf::Mphf;
+use hashing_with_chaining::norm_sketch::NormSketch;
+use hashing_with_chaining::odd_sketch::OddSketch;
+use hashing_with_chaining::perfect_hashing::{ConstructionStats, PerfectHashing};
+use hashing_with_chaining::plot::{render_svg, series, Chart, Series};
+use hashing_with_chaining::prime::{pollard_rho, random_prime};
+use hashing_with_chaining::quicksort::{quicksort, PivotStrategy};
+use hashing_with_chaining::random_walk::{pagerank_monte_carlo, pagerank_power_iteration};
+use hashing_with_chaining::results::{read_csv, read_report, CsvWriter, Report, ResultRow};
+use hashing_with_chaining::rounding::{exact_routing, exact_set_cover, fractional_routing, fractional_set_cover, round_routing, round_set_cover, Routing, SetCover};
+use hashing_with_chaining::sat::{schoening, Formula, Search};
+use hashing_with_chaining::selection::{introselect, randomized_select};
+use hashing_with_chaining::sharded_hwc::ShardedHwC;
+use hashing_with_chaining::sketch_builder::{SketchBuilder, SketchDimensions};
+use hashing_with_chaining::sketch_format::Checkpoint;
+use hashing_with_chaining::sketch_server::{Response as ServerResponse, SketchSet};
+use hashing_with_chaining::skip_list::SkipList;
+use hashing_with_chaining::space_saving::{Counter, SpaceSaving, WindowedSpaceSaving};
+use hashing_with_chaining::stats::{bootstrap_interval, mean, permutation_test};
+use hashing_with_chaining::subset_sum::{PrioritySampler, VarOptSampler};
+use hashing_with_chaining::tdigest::TDigest;
+use hashing_with_chaining::top_k::TopK;
+use hashing_with_chaining::treap::Treap;
+use hashing_with_chaining::two_choice_hwc::TwoChoiceHwC;
+use hashing_with_chaining::update_format::{UpdateFormat, UpdateReader, UpdateWriter};
+use ahash::RandomState as AHashState;
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
 use rand::prelude::*;
+use rand::rngs::StdRng;
 use rbtree::RBTree;
-use time::OffsetDateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState as SipHashState;
 use std::fs::File;
 use std::fs::OpenOptions;
 use std::io::prelude::*;
+use std::hash::BuildHasher;
+use std::hint::black_box;
+use std::io::{BufReader, BufWriter};
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::thread;
+use std::time::Duration;
 
-pub fn random_generator(from: u32, to: u32) -> u32 {
-    let mut rng = thread_rng();
-    return rng.gen_range(from..to);
+// Size of the neighborhood every hopscotch element must stay within
+const NEIGHBORHOOD: usize = 32;
+
+// Shards per thread in the sharded table, to keep lock contention low
+const SHARDS_PER_THREAD: usize = 4;
+
+// Number of elements the cuckoo stash can hold before a rehash is forced
+const STASH_SIZE: usize = 4;
+
+// The operations the ordered structure comparison needs, so one generic function can time
+// every structure on the same workload
+trait OrderedMap {
+    fn insert(&mut self, key: u64);
+    fn contains(&self, key: u64) -> bool;
+    fn remove(&mut self, key: u64);
+}
+
+impl OrderedMap for RBTree<u64, u64> {
+    fn insert(&mut self, key: u64) {
+        RBTree::insert(self, key, key);
+    }
+    fn contains(&self, key: u64) -> bool {
+        return self.contains_key(&key);
+    }
+    fn remove(&mut self, key: u64) {
+        RBTree::remove(self, &key);
+    }
+}
+
+impl OrderedMap for BTreeMap<u64, u64> {
+    fn insert(&mut self, key: u64) {
+        BTreeMap::insert(self, key, key);
+    }
+    fn contains(&self, key: u64) -> bool {
+        return self.contains_key(&key);
+    }
+    fn remove(&mut self, key: u64) {
+        BTreeMap::remove(self, &key);
+    }
 }
 
-// The hash function is c-universal
-const C: usize = 2;
+impl OrderedMap for SkipList<u64, u64> {
+    fn insert(&mut self, key: u64) {
+        SkipList::insert(self, key, key);
+    }
+    fn contains(&self, key: u64) -> bool {
+        return self.contains_key(&key);
+    }
+    fn remove(&mut self, key: u64) {
+        SkipList::remove(self, &key);
+    }
+}
 
-struct SeededHash {
-    l: u32,
-    a: u32,
-    b: u32,
+impl OrderedMap for Treap<u64, u64> {
+    fn insert(&mut self, key: u64) {
+        Treap::insert(self, key, key);
+    }
+    fn contains(&self, key: u64) -> bool {
+        return self.contains_key(&key);
+    }
+    fn remove(&mut self, key: u64) {
+        Treap::remove(self, &key);
+    }
 }
 
-impl SeededHash {
-    fn new(hash_len: u32) -> SeededHash {
-        let base: u32 = 2;
-        let randomness_size: u32 = base.pow(31);
-        let rand_a: u32 = random_generator(1, randomness_size);
-        let rand_b: u32 = random_generator(1, randomness_size);
-        return SeededHash {
-            a: rand_a,
-            b: rand_b,
-            l: hash_len
+// Rows of the comparison table: the time to insert the input, to look up every input key
+// and as many absent keys, and to run the mixed workload of lookups, inserts and removals
+fn ordered_structure<M: OrderedMap>(mut map: M, name: &str, input: &[u64], mixed: &[(u8, u64)], csv: &mut CsvWriter<File>) {
+    let c_start = now();
+    input.iter().for_each(|x| map.insert(*x));
+    let c_stop = now();
+
+    let mut sum: usize = 0;
+    let q_start = now();
+    for x in input {
+        sum += map.contains(*x) as usize + map.contains(*x + input.len() as u64) as usize;
+    }
+    let q_stop = now();
+
+    let m_start = now();
+    for (operation, key) in mixed {
+        match operation {
+            0 => map.insert(*key),
+            1 => map.remove(*key),
+            _ => sum += map.contains(*key) as usize,
         }
     }
-    // Multiply shift hashing as from lecture notes (https://arxiv.org/pdf/1504.06804.pdf) at 3.3
-    fn hash(&self, x: u32) -> usize {
-        let multiply_add: u32 = self.a.wrapping_mul(x).wrapping_add(self.b);
-        return multiply_add.wrapping_shr(32 - self.l) as usize;
+    let m_stop = now();
+
+    let n: u64 = input.len() as u64;
+    write_row(csv, ResultRow::new(name, n, "construction").throughput(c_stop - c_start, n));
+    write_row(csv, ResultRow::new(name, n, "query").throughput(q_stop - q_start, 2 * n));
+    write_row(csv, ResultRow::new(name, n, "mixed").throughput(m_stop - m_start, mixed.len() as u64));
+    black_box(sum);
+}
+
+// Hashing with chaining over either chain storage, sized for the input up front
+struct HwCExperiment<S> {
+    name: &'static str,
+    storage: PhantomData<S>,
+}
+
+impl<S> HwCExperiment<S> {
+    fn new(name: &'static str) -> HwCExperiment<S> {
+        return HwCExperiment { name, storage: PhantomData }
     }
 }
 
-struct HwC {
-    vec: Vec<LinkedList<u32>>,
-    hash_function: SeededHash
+impl<S: ChainStorage<Count = i64>> Experiment for HwCExperiment<S> {
+    type Structure = HwC<S>;
+
+    fn name(&self) -> String {
+        return self.name.to_owned();
+    }
+    fn setup(&self, n: usize) -> HwC<S> {
+        return HwC::with_capacity_hint(n, n);
+    }
+    fn run_updates(&self, hwc: &mut HwC<S>, input: &[u64]) {
+        input.iter().for_each(|x| hwc.insert(*x, 1));
+    }
+    fn run_query(&self, hwc: &HwC<S>, input: &[u64]) -> usize {
+        return input.iter().filter(|x| hwc.query(**x)).count();
+    }
+    fn footprint<'a>(&self, hwc: &'a HwC<S>) -> Option<&'a dyn MemoryFootprint> {
+        return Some(hwc);
+    }
+    fn metrics(&self, hwc: &HwC<S>, row: ResultRow) -> ResultRow {
+        return row.table_stats(&hwc.stats());
+    }
 }
 
-impl HwC {
-    fn new(size: usize) -> HwC {
-        let input_len: usize = size;
-        let hash_len: u32 = log2u(input_len);
-        let vec = vec![LinkedList::<u32>::new(); input_len];
-        let hash_fn: SeededHash = SeededHash::new(hash_len);
-        return HwC {
-            vec,
-            hash_function: hash_fn
+// Inserts the input from `threads` threads at once, each taking an equal slice of it
+fn sharded_hashing_with_chaining(input: &[u64], threads: usize, csv: &mut CsvWriter<File>) {
+    let n: u64 = input.len() as u64;
+    let sharded: ShardedHwC = ShardedHwC::new(input.len(), SHARDS_PER_THREAD * threads);
+    let chunk_len: usize = input.len().div_ceil(threads);
+
+    let c_start = now();
+    thread::scope(|s| {
+        for chunk in input.chunks(chunk_len) {
+            let sharded: &ShardedHwC = &sharded;
+            s.spawn(move || {
+                for x in chunk {
+                    sharded.insert(*x, 1);
+                }
+            });
         }
+    });
+    let c_stop = now();
+    write_row(csv, ResultRow::new("sharded_hwc", n, "update")
+        .throughput(c_stop - c_start, n)
+        .metric("threads", threads));
+
+    let q_start = now();
+    let sum: i64 = thread::scope(|s| {
+        let handles: Vec<_> = input.chunks(chunk_len)
+            .map(|chunk| {
+                let sharded: &ShardedHwC = &sharded;
+                s.spawn(move || chunk.iter().map(|x| sharded.get(*x)).sum::<i64>())
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    });
+    let q_stop = now();
+    black_box(sum);
+    write_row(csv, ResultRow::new("sharded_hwc", n, "query")
+        .throughput(q_stop - q_start, n)
+        .metric("threads", threads)
+        .table_stats(&sharded.stats()));
+}
+
+struct CuckooExperiment {
+    name: &'static str,
+    stash_size: usize,
+}
+
+impl Experiment for CuckooExperiment {
+    type Structure = CuckooTable;
+
+    fn name(&self) -> String {
+        return self.name.to_owned();
+    }
+    fn setup(&self, n: usize) -> CuckooTable {
+        return CuckooTable::with_stash(n, self.stash_size);
+    }
+    fn run_updates(&self, cuckoo: &mut CuckooTable, input: &[u64]) {
+        input.iter().for_each(|x| cuckoo.insert(*x));
+    }
+    fn run_query(&self, cuckoo: &CuckooTable, input: &[u64]) -> usize {
+        return input.iter().filter(|x| cuckoo.query(**x)).count();
     }
-    fn insert(&mut self, elem: u32) {
-        let hash_val: usize = self.hash_function.hash(elem);
-        self.vec[hash_val].push_back(elem)
+    fn footprint<'a>(&self, cuckoo: &'a CuckooTable) -> Option<&'a dyn MemoryFootprint> {
+        return Some(cuckoo);
     }
-    fn query(&self, elem: u32) -> bool {
-        let hash_val: usize = self.hash_function.hash(elem);
-        self.vec[hash_val].contains(&elem)
+    fn metrics(&self, cuckoo: &CuckooTable, row: ResultRow) -> ResultRow {
+        return row.metric("rehashes", cuckoo.rehashes())
+            .metric("stash_saves", cuckoo.stash_saves())
+            .metric("peak_stash", cuckoo.max_stash())
+            .metric("stash_size", self.stash_size)
+            .table_stats(&cuckoo.stats());
     }
 }
 
-struct Bucket {
-    vec: Vec<u32>,
-    hash_function: SeededHash,
+// Probe length histograms as counts separated by slashes, starting at length 1
+fn probe_histogram(probe_lengths: &[usize]) -> String {
+    let counts: Vec<String> = probe_lengths.iter().map(|count| count.to_string()).collect();
+    return counts.join("/");
 }
 
-impl Bucket {
-    fn new(input_array: &Vec<u32>) -> Bucket {
-        let array_len: usize = 2*C*input_array.len().pow(2);
-        if array_len == 0 {
-            return Bucket {
-                vec: vec![0; 0],
-                hash_function: SeededHash::new(0)
-            }
+struct LinearProbingExperiment;
+
+impl Experiment for LinearProbingExperiment {
+    type Structure = LinearProbing;
+
+    fn name(&self) -> String {
+        return "lp".to_owned();
+    }
+    fn setup(&self, n: usize) -> LinearProbing {
+        return LinearProbing::new(n);
+    }
+    fn run_updates(&self, lp: &mut LinearProbing, input: &[u64]) {
+        input.iter().for_each(|x| lp.insert(*x));
+    }
+    fn run_query(&self, lp: &LinearProbing, input: &[u64]) -> usize {
+        return input.iter().filter(|x| lp.query(**x)).count();
+    }
+    fn footprint<'a>(&self, lp: &'a LinearProbing) -> Option<&'a dyn MemoryFootprint> {
+        return Some(lp);
+    }
+    fn metrics(&self, lp: &LinearProbing, row: ResultRow) -> ResultRow {
+        return row.metric("average_probe_length", lp.average_probe_length())
+            .table_stats(&lp.stats())
+            .metric("probe_histogram", probe_histogram(lp.probe_lengths()));
+    }
+}
+
+struct DoubleHashingExperiment;
+
+impl Experiment for DoubleHashingExperiment {
+    type Structure = DoubleHashingTable;
+
+    fn name(&self) -> String {
+        return "dh".to_owned();
+    }
+    fn setup(&self, n: usize) -> DoubleHashingTable {
+        return DoubleHashingTable::new(n);
+    }
+    fn run_updates(&self, dh: &mut DoubleHashingTable, input: &[u64]) {
+        input.iter().for_each(|x| dh.insert(*x));
+    }
+    fn run_query(&self, dh: &DoubleHashingTable, input: &[u64]) -> usize {
+        return input.iter().filter(|x| dh.query(**x)).count();
+    }
+    fn footprint<'a>(&self, dh: &'a DoubleHashingTable) -> Option<&'a dyn MemoryFootprint> {
+        return Some(dh);
+    }
+    fn metrics(&self, dh: &DoubleHashingTable, row: ResultRow) -> ResultRow {
+        return row.metric("average_probe_length", dh.average_probe_length())
+            .table_stats(&dh.stats())
+            .metric("probe_histogram", probe_histogram(dh.probe_lengths()));
+    }
+}
+
+// The static structures are built from the whole input at once, so there is nothing to
+// allocate before it arrives
+struct PerfectHashingExperiment;
+
+impl Experiment for PerfectHashingExperiment {
+    type Structure = Option<PerfectHashing>;
+
+    fn name(&self) -> String {
+        return "ph".to_owned();
+    }
+    fn setup(&self, _n: usize) -> Option<PerfectHashing> {
+        return None;
+    }
+    fn run_updates(&self, ph: &mut Option<PerfectHashing>, input: &[u64]) {
+        *ph = Some(PerfectHashing::new(input));
+    }
+    fn run_query(&self, ph: &Option<PerfectHashing>, input: &[u64]) -> usize {
+        return ph.as_ref().map_or(0, |ph| input.iter().filter(|x| ph.query(**x)).count());
+    }
+    fn incremental(&self) -> bool {
+        return false;
+    }
+    fn footprint<'a>(&self, ph: &'a Option<PerfectHashing>) -> Option<&'a dyn MemoryFootprint> {
+        return ph.as_ref().map(|ph| ph as &dyn MemoryFootprint);
+    }
+    fn metrics(&self, ph: &Option<PerfectHashing>, row: ResultRow) -> ResultRow {
+        let Some(ph) = ph else { return row; };
+        let stats: ConstructionStats = ph.construction_stats();
+        return row.metric("first_level_retries", stats.first_level_retries)
+            .metric("second_level_retries", stats.second_level_retries);
+    }
+}
+
+struct MphfExperiment;
+
+impl Experiment for MphfExperiment {
+    type Structure = Option<Mphf>;
+
+    fn name(&self) -> String {
+        return "mphf".to_owned();
+    }
+    fn setup(&self, _n: usize) -> Option<Mphf> {
+        return None;
+    }
+    fn run_updates(&self, mphf: &mut Option<Mphf>, input: &[u64]) {
+        *mphf = Some(Mphf::build(input));
+    }
+    // Every key maps to an index below the number of keys, so all count as found
+    fn run_query(&self, mphf: &Option<Mphf>, input: &[u64]) -> usize {
+        return mphf.as_ref().map_or(0, |mphf| input.iter().filter(|x| mphf.index(**x) < mphf.len()).count());
+    }
+    fn incremental(&self) -> bool {
+        return false;
+    }
+    fn footprint<'a>(&self, mphf: &'a Option<Mphf>) -> Option<&'a dyn MemoryFootprint> {
+        return mphf.as_ref().map(|mphf| mphf as &dyn MemoryFootprint);
+    }
+}
+
+// Maps of the standard library, and HashMap with ahash, as baselines for the tables of the
+// crate. They count keys the way HwC does, so both run the very same workload.
+trait BaselineMap {
+    fn with_capacity(n: usize) -> Self;
+    fn add(&mut self, key: u64);
+    fn contains(&self, key: u64) -> bool;
+}
+
+impl<S: BuildHasher + Default> BaselineMap for HashMap<u64, i64, S> {
+    fn with_capacity(n: usize) -> Self {
+        return HashMap::with_capacity_and_hasher(n, S::default());
+    }
+    fn add(&mut self, key: u64) {
+        *self.entry(key).or_insert(0) += 1;
+    }
+    fn contains(&self, key: u64) -> bool {
+        return self.contains_key(&key);
+    }
+}
+
+impl BaselineMap for BTreeMap<u64, i64> {
+    fn with_capacity(_n: usize) -> Self {
+        return BTreeMap::new();
+    }
+    fn add(&mut self, key: u64) {
+        *self.entry(key).or_insert(0) += 1;
+    }
+    fn contains(&self, key: u64) -> bool {
+        return self.contains_key(&key);
+    }
+}
+
+struct BaselineExperiment<M> {
+    name: &'static str,
+    map: PhantomData<M>,
+}
+
+impl<M> BaselineExperiment<M> {
+    fn new(name: &'static str) -> BaselineExperiment<M> {
+        return BaselineExperiment { name, map: PhantomData }
+    }
+}
+
+impl<M: BaselineMap> Experiment for BaselineExperiment<M> {
+    type Structure = M;
+
+    fn name(&self) -> String {
+        return self.name.to_owned();
+    }
+    fn setup(&self, n: usize) -> M {
+        return M::with_capacity(n);
+    }
+    fn run_updates(&self, map: &mut M, input: &[u64]) {
+        input.iter().for_each(|x| map.add(*x));
+    }
+    fn run_query(&self, map: &M, input: &[u64]) -> usize {
+        return input.iter().filter(|x| map.contains(**x)).count();
+    }
+}
+
+// Compares the open addressing tables on one table of 2^hash_len slots filled to increasing load factors
+fn benchmark_load_factors(hash_len: u32, load_factors: [f64; 9]) {
+    let mut file: File = make_writable_file("load_factors");
+    let table_len: usize = 1 << hash_len;
+
+    for load_factor in load_factors {
+        writeln!(file, "Load factor: {}", load_factor).expect("Cannot write to file");
+        let input_size: usize = (load_factor * table_len as f64) as usize;
+        let input: Vec<u64> = Vec::from_iter(1..(input_size +1) as u64);
+
+        let c_start = now();
+        let mut lp: LinearProbing = LinearProbing::with_hash_len(hash_len);
+        for x in &input {
+            lp.insert(*x);
         }
-        let hash_len: u32 = log2u(array_len);
-        let arr = vec![0; array_len];
-        let mut bucket: Bucket = Bucket {
-            vec: arr,
-            hash_function: SeededHash::new(hash_len),
-        };
-        for x in input_array {
-            let success: bool = bucket.insert(*x);
-            if !success {
-                return Bucket::new(input_array)
+        let c_stop = now();
+        let q_start = now();
+        let lp_sum: usize = input.iter().filter(|x| lp.query(**x)).count();
+        let q_stop = now();
+        black_box(lp_sum);
+        writeln!(file, "Linear probing construction time: {}", c_stop - c_start).expect("Cannot write to file");
+        writeln!(file, "Linear probing query time: {}", q_stop - q_start).expect("Cannot write to file");
+        writeln!(file, "Linear probing average probe length: {}", lp.average_probe_length()).expect("Cannot write to file");
+        writeln!(file, "Linear probing longest probe: {}", lp.probe_lengths().len()).expect("Cannot write to file");
+
+        let c_start = now();
+        let mut dh: DoubleHashingTable = DoubleHashingTable::with_hash_len(hash_len);
+        for x in &input {
+            dh.insert(*x);
+        }
+        let c_stop = now();
+        let q_start = now();
+        let dh_sum: usize = input.iter().filter(|x| dh.query(**x)).count();
+        let q_stop = now();
+        black_box(dh_sum);
+        writeln!(file, "Double hashing construction time: {}", c_stop - c_start).expect("Cannot write to file");
+        writeln!(file, "Double hashing query time: {}", q_stop - q_start).expect("Cannot write to file");
+        writeln!(file, "Double hashing average probe length: {}", dh.average_probe_length()).expect("Cannot write to file");
+        writeln!(file, "Double hashing longest probe: {}", dh.probe_lengths().len()).expect("Cannot write to file");
+
+        let c_start = now();
+        let mut hopscotch: Hopscotch = Hopscotch::with_hash_len(hash_len, NEIGHBORHOOD);
+        for x in &input {
+            hopscotch.insert(*x);
+        }
+        let c_stop = now();
+        let q_start = now();
+        let hopscotch_sum: usize = input.iter().filter(|x| hopscotch.query(**x)).count();
+        let q_stop = now();
+        black_box(hopscotch_sum);
+        writeln!(file, "Hopscotch construction time: {}", c_stop - c_start).expect("Cannot write to file");
+        writeln!(file, "Hopscotch query time: {}", q_stop - q_start).expect("Cannot write to file");
+        writeln!(file, "Hopscotch rehashes: {}", hopscotch.rehashes()).expect("Cannot write to file");
+        writeln!(file, "Hopscotch space usage: {} bytes", hopscotch.space_usage()).expect("Cannot write to file");
+        hopscotch.stats().write_to(&mut file).expect("Cannot write to file");
+
+        // Two-table cuckoo hashing only works below a total load factor of 1/2
+        if load_factor < 0.5 {
+            let c_start = now();
+            let mut cuckoo: CuckooTable = CuckooTable::with_hash_len(hash_len - 1, 0);
+            for x in &input {
+                cuckoo.insert(*x);
             }
+            let c_stop = now();
+            let q_start = now();
+            let cuckoo_sum: usize = input.iter().filter(|x| cuckoo.query(**x)).count();
+            let q_stop = now();
+            black_box(cuckoo_sum);
+            writeln!(file, "Cuckoo construction time: {}", c_stop - c_start).expect("Cannot write to file");
+            writeln!(file, "Cuckoo query time: {}", q_stop - q_start).expect("Cannot write to file");
+            writeln!(file, "Cuckoo rehashes: {}", cuckoo.rehashes()).expect("Cannot write to file");
         }
-        return bucket;
     }
-    fn insert(&mut self, elem: u32) -> bool {
-        return if self.vec[self.hash_function.hash(elem)] != 0 {
-            false
-        } else {
-            self.vec[self.hash_function.hash(elem)] = elem;
-            true
+}
+
+// Average overestimation of Count-Min with standard and conservative updates on Zipfian streams,
+// measured over every distinct key against the exact counts from HwC
+fn benchmark_count_min(stream_len: usize, keys: usize, exponents: [f64; 4]) {
+    let builder: SketchBuilder = SketchBuilder::new(0.005, 0.05);
+    let dimensions: SketchDimensions = builder.count_min_dimensions();
+    let mut file: File = make_writable_file("count_min");
+    let mut input_rng: StdRng = StdRng::seed_from_u64(input_seed());
+    writeln!(file, "Depth: {}, width: {}", dimensions.depth, dimensions.width()).expect("Cannot write to file");
+
+    for exponent in exponents {
+        writeln!(file, "Zipf exponent: {}", exponent).expect("Cannot write to file");
+        let stream: Vec<u64> = zipf(keys, exponent, stream_len, &mut input_rng);
+        record_input(&format!("count_min_zipf_{}", exponent), &stream);
+        let mut exact: HwC = HwC::new(keys);
+        let mut standard: CountMin = builder.count_min(UpdatePolicy::Standard);
+        let mut conservative: CountMin = builder.count_min(UpdatePolicy::Conservative);
+        for x in &stream {
+            exact.insert(*x, 1);
+            standard.insert(*x, 1);
+            conservative.insert(*x, 1);
+        }
+
+        let distinct: Vec<u64> = (1..=keys as u64).filter(|x| exact.query(*x)).collect();
+        for sketch in [&standard, &conservative] {
+            let error: u64 = distinct.iter().map(|x| sketch.estimate(*x) - exact.get(*x) as u64).sum();
+            writeln!(file, "{:?} average overestimation: {}", sketch.policy(), error as f64 / distinct.len() as f64).expect("Cannot write to file");
         }
     }
-    fn query(&self, elem: u32) -> bool {
-        return self.vec[self.hash_function.hash(elem)] == elem
+}
+
+// Heavy hitters above phi * stream_len from a Misra–Gries summary with 2 / phi counters
+// and a Count-Min sketch with error phi / 2. Both report every true heavy hitter, so the
+// comparison is on precision and space.
+fn benchmark_heavy_hitters(stream_len: usize, keys: usize, exponents: [f64; 4], phi: f64) {
+    let builder: SketchBuilder = SketchBuilder::new(phi / 2.0, 0.05);
+    let mut file: File = make_writable_file("heavy_hitters");
+    let mut input_rng: StdRng = StdRng::seed_from_u64(input_seed());
+    let threshold: u64 = (phi * stream_len as f64).ceil() as u64;
+
+    for exponent in exponents {
+        writeln!(file, "Zipf exponent: {}", exponent).expect("Cannot write to file");
+        let stream: Vec<u64> = zipf(keys, exponent, stream_len, &mut input_rng);
+        record_input(&format!("heavy_hitters_zipf_{}", exponent), &stream);
+        let mut exact: HwC = HwC::new(keys);
+        let mut summary: MisraGries = MisraGries::new((2.0 / phi).ceil() as usize);
+        let mut sketch: CountMin = builder.count_min(UpdatePolicy::Conservative);
+        for x in &stream {
+            exact.insert(*x, 1);
+            summary.update(*x, 1);
+            sketch.insert(*x, 1);
+        }
+
+        let heavy: Vec<u64> = (1..=keys as u64).filter(|x| exact.query(*x) && exact.get(*x) as u64 >= threshold).collect();
+        let mg_reported: Vec<u64> = summary.heavy_hitters(threshold).iter().map(|(key, _)| *key).collect();
+        let cm_reported: Vec<u64> = (1..=keys as u64).filter(|x| sketch.estimate(*x) >= threshold).collect();
+        writeln!(file, "Heavy hitters: {}", heavy.len()).expect("Cannot write to file");
+        for (name, reported, space) in [("Misra-Gries", &mg_reported, summary.space_usage()), ("Count-Min", &cm_reported, sketch.space_usage())] {
+            let found: usize = reported.iter().filter(|x| heavy.contains(x)).count();
+            writeln!(file, "{} recall: {}, precision: {}, space: {}", name,
+                     found as f64 / heavy.len() as f64, found as f64 / reported.len() as f64, space).expect("Cannot write to file");
+        }
+    }
+}
+
+// Heavy hitters above phi of the stream and the number of distinct keys of real data: the
+// words of a text and the keys of a packet trace. Misra–Gries and Count-Min are checked
+// against the exact counts as in heavy_hitters, with the distinct keys of the stream as the
+// candidates Count-Min is queried for, and HyperLogLog against the exact distinct count.
+fn benchmark_real_data(words: Option<&Path>, trace: Option<&Path>, trace_column: Option<usize>, phi: f64) {
+    if words.is_none() && trace.is_none() {
+        eprintln!("real-data needs --words or --trace, skipping it");
+        return;
+    }
+    let builder: SketchBuilder = SketchBuilder::new(phi / 2.0, 0.05);
+    let mut csv: CsvWriter<File> = make_csv_writer("real_data");
+    let open = |path: &Path| BufReader::new(File::open(path).unwrap_or_else(|err| panic!("Cannot open {}: {}", path.display(), err)));
+    let text: Option<WordStream> = words.map(|path| read_words(open(path)).expect("Cannot read the words"));
+    let packets: Option<Vec<u64>> = trace.map(|path| {
+        let is_csv: bool = trace_column.is_some() || path.extension().is_some_and(|extension| extension == "csv");
+        let format: TraceFormat = if is_csv { TraceFormat::Csv { column: trace_column.unwrap_or(0), header: true } } else { TraceFormat::Tcpdump };
+        read_trace(open(path), format).expect("Cannot read the trace")
+    });
+
+    for (dataset, stream) in [("words", text.as_ref().map(|text| text.keys.as_slice())), ("trace", packets.as_deref())] {
+        let Some(stream) = stream else { continue; };
+        let n: u64 = stream.len() as u64;
+        let threshold: u64 = (phi * n as f64).ceil() as u64;
+        let mut exact: HashMap<u64, u64> = HashMap::new();
+        let mut summary: MisraGries = MisraGries::new((2.0 / phi).ceil() as usize);
+        let mut sketch: CountMin = builder.count_min(UpdatePolicy::Conservative);
+        let mut hll: HyperLogLog = HyperLogLog::new(14);
+        for x in stream {
+            *exact.entry(*x).or_default() += 1;
+            summary.update(*x, 1);
+            sketch.insert(*x, 1);
+            hll.insert(*x);
+        }
+
+        let mut heavy: Vec<(u64, u64)> = exact.iter().filter(|(_, count)| **count >= threshold).map(|(x, count)| (*count, *x)).collect();
+        heavy.sort_unstable_by(|a, b| b.cmp(a));
+        let heavy_keys: Vec<u64> = heavy.iter().map(|(_, x)| *x).collect();
+        let mg_reported: Vec<u64> = summary.heavy_hitters(threshold).iter().map(|(key, _)| *key).collect();
+        let cm_reported: Vec<u64> = exact.keys().copied().filter(|x| sketch.estimate(*x) >= threshold).collect();
+        for (name, reported, space) in [("misra_gries", &mg_reported, summary.space_usage()), ("count_min", &cm_reported, sketch.space_usage())] {
+            let found: usize = reported.iter().filter(|x| heavy_keys.contains(x)).count();
+            let mut row: ResultRow = ResultRow::new(name, n, dataset)
+                .memory(space)
+                .metric("heavy_hitters", heavy_keys.len())
+                .metric("recall", found as f64 / heavy_keys.len() as f64)
+                .metric("precision", found as f64 / reported.len() as f64);
+            if let (Some(text), "words") = (&text, dataset) {
+                let top: Vec<&str> = heavy_keys.iter().take(5).filter_map(|x| text.word(*x)).collect();
+                row = row.metric("top", top.join("/"));
+            }
+            write_row(&mut csv, row);
+        }
+        let distinct: usize = exact.len();
+        write_row(&mut csv, ResultRow::new("hyperloglog", n, dataset)
+            .memory(hll.space_usage())
+            .metric("estimate", hll.estimate())
+            .metric("exact", distinct)
+            .metric("relative_error", (hll.estimate() - distinct as f64).abs() / distinct as f64));
+    }
+}
+
+// Top-k keys of Zipfian streams from SpaceSaving summaries with 4k counters, built once
+// sequentially and once as one summary per thread merged afterwards. Precision is the
+// fraction of reported keys that are in the exact top k.
+fn benchmark_space_saving(stream_len: usize, keys: usize, exponents: [f64; 4], k: usize, threads: usize) {
+    let mut file: File = make_writable_file("space_saving");
+    let mut input_rng: StdRng = StdRng::seed_from_u64(input_seed());
+
+    for exponent in exponents {
+        writeln!(file, "Zipf exponent: {}", exponent).expect("Cannot write to file");
+        let stream: Vec<u64> = zipf(keys, exponent, stream_len, &mut input_rng);
+        record_input(&format!("space_saving_zipf_{}", exponent), &stream);
+        let mut exact: HwC = HwC::new(keys);
+        stream.iter().for_each(|x| exact.insert(*x, 1));
+        let mut ranked: Vec<(i64, u64)> = (1..=keys as u64).filter(|x| exact.query(*x)).map(|x| (exact.get(x), x)).collect();
+        ranked.sort_unstable_by(|a, b| b.cmp(a));
+        let top: Vec<u64> = ranked.iter().take(k).map(|(_, x)| *x).collect();
+
+        let empty: SpaceSaving = SpaceSaving::new(4 * k);
+        let c_start = now();
+        let mut sequential: SpaceSaving = empty.clone();
+        stream.iter().for_each(|x| sequential.update(*x, 1));
+        let c_stop = now();
+        writeln!(file, "Sequential update time: {}", c_stop - c_start).expect("Cannot write to file");
+
+        let c_start = now();
+        let chunk_len: usize = stream.len().div_ceil(threads);
+        let parallel: SpaceSaving = thread::scope(|s| {
+            let handles: Vec<_> = stream.chunks(chunk_len)
+                .map(|chunk| {
+                    let mut summary: SpaceSaving = empty.clone();
+                    s.spawn(move || {
+                        chunk.iter().for_each(|x| summary.update(*x, 1));
+                        summary
+                    })
+                })
+                .collect();
+            let mut merged: SpaceSaving = empty.clone();
+            for handle in handles {
+                merged.merge(&handle.join().unwrap()).expect("Summaries have the same capacity");
+            }
+            merged
+        });
+        let c_stop = now();
+        writeln!(file, "Parallel update and merge time: {}", c_stop - c_start).expect("Cannot write to file");
+
+        for (name, summary) in [("Sequential", &sequential), ("Merged", &parallel)] {
+            let reported: Vec<Counter> = summary.top_k(k);
+            let found: usize = reported.iter().filter(|counter| top.contains(&counter.key)).count();
+            let overcount: u64 = reported.iter().map(|counter| counter.count - exact.get(counter.key) as u64).max().unwrap_or(0);
+            writeln!(file, "{} precision: {}, max overcount: {}, exact: {}", name,
+                     found as f64 / k as f64, overcount, summary.is_top_k_exact(k)).expect("Cannot write to file");
+        }
     }
 }
 
-struct PerfectHashing {
-    vec: Vec<Bucket>,
-    hash_function: SeededHash,
+// Heavy hitters of the last `window` updates of a drifting Zipf stream: every phase draws from
+// a fresh set of keys, so the top k of one phase should disappear from the windowed summary
+// within a window of the next. Checked every quarter window against the exact window counts.
+fn benchmark_windowed_heavy_hitters(phase_len: usize, keys: usize, exponent: f64, phases: usize, window: usize, k: usize) {
+    const BLOCKS: usize = 8;
+    let mut file: File = make_writable_file("windowed_heavy_hitters");
+    let mut input_rng: StdRng = StdRng::seed_from_u64(input_seed());
+
+    let mut windowed: WindowedSpaceSaving = WindowedSpaceSaving::new(4 * k, window as u64, BLOCKS);
+    let mut recent: VecDeque<u64> = VecDeque::with_capacity(window);
+    let mut exact: HashMap<u64, u64> = HashMap::new();
+    let exact_top = |exact: &HashMap<u64, u64>| -> Vec<u64> {
+        let mut ranked: Vec<(u64, u64)> = exact.iter().map(|(x, count)| (*count, *x)).collect();
+        ranked.sort_unstable_by(|a, b| b.cmp(a));
+        ranked.iter().take(k).map(|(_, x)| *x).collect()
+    };
+    let mut previous_top: Vec<u64> = Vec::new();
+    for phase in 0..phases {
+        writeln!(file, "Phase: {}", phase).expect("Cannot write to file");
+        let offset: u64 = (phase * keys) as u64;
+        let stream: Vec<u64> = zipf(keys, exponent, phase_len, &mut input_rng).iter().map(|x| x + offset).collect();
+        record_input(&format!("windowed_heavy_hitters_phase_{}", phase), &stream);
+        for (i, x) in stream.iter().enumerate() {
+            windowed.update(*x, 1);
+            recent.push_back(*x);
+            *exact.entry(*x).or_default() += 1;
+            if recent.len() > window {
+                let old: u64 = recent.pop_front().unwrap();
+                *exact.get_mut(&old).unwrap() -= 1;
+            }
+            if (i + 1) % (window / 4) != 0 {
+                continue;
+            }
+            let top: Vec<u64> = exact_top(&exact);
+            let reported: Vec<Counter> = windowed.top_k(k);
+            let found: usize = reported.iter().filter(|counter| top.contains(&counter.key)).count();
+            let stale: usize = reported.iter().filter(|counter| previous_top.contains(&counter.key)).count();
+            writeln!(file, "Updates into phase: {}, precision: {}, previous phase keys reported: {}",
+                i + 1, found as f64 / k as f64, stale).expect("Cannot write to file");
+        }
+        exact.retain(|_, count| *count > 0);
+        previous_top = exact_top(&exact);
+    }
 }
 
-impl PerfectHashing {
-    fn new(input_array: &Vec<u32>) -> PerfectHashing {
-        let array_len: usize = 4*C*input_array.len();
+// Top-k keys of turnstile streams, where after every insert an earlier insert is deleted
+// with probability deletion_rate. TopK sees the whole stream, while SpaceSaving with as
+// much space cannot process deletions and only sees the inserts. Both are compared with
+// the exact top k of the final counts.
+fn benchmark_top_k(stream_len: usize, keys: usize, exponents: [f64; 4], k: usize, deletion_rate: f64) {
+    let builder: SketchBuilder = SketchBuilder::new(0.001, 0.05);
+    let mut file: File = make_writable_file("top_k");
+    let mut input_rng: StdRng = StdRng::seed_from_u64(input_seed());
+    let mut rng = thread_rng();
 
-        let hash_len: u32 = log2u(array_len);
-        let hash_fn: SeededHash = SeededHash::new(hash_len);
+    for exponent in exponents {
+        writeln!(file, "Zipf exponent: {}", exponent).expect("Cannot write to file");
+        let inserts: Vec<u64> = zipf(keys, exponent, stream_len, &mut input_rng);
+        record_input(&format!("top_k_zipf_{}", exponent), &inserts);
+        let mut exact: HwC = HwC::new(keys);
+        let mut top_k: TopK = TopK::new(k, builder.count_min(UpdatePolicy::Standard));
+        let sketch_space: usize = top_k.sketch().space_usage();
+        let mut summary: SpaceSaving = SpaceSaving::new(sketch_space / (4 * size_of::<u64>()));
+        for (i, x) in inserts.iter().enumerate() {
+            exact.insert(*x, 1);
+            top_k.update(*x, 1);
+            summary.update(*x, 1);
+            if rng.gen::<f64>() < deletion_rate {
+                let deleted: u64 = inserts[rng.gen_range(0..=i)];
+                if exact.get(deleted) > 0 {
+                    exact.insert(deleted, -1);
+                    top_k.update(deleted, -1);
+                }
+            }
+        }
+        let mut ranked: Vec<(i64, u64)> = (1..=keys as u64).filter(|x| exact.query(*x)).map(|x| (exact.get(x), x)).collect();
+        ranked.sort_unstable_by(|a, b| b.cmp(a));
+        let top: Vec<u64> = ranked.iter().take(k).map(|(_, x)| *x).collect();
 
-        let mut buckets: Vec<Vec<u32>> = Vec::new();
-        for _ in 0..array_len {
-            buckets.push(Vec::new());
+        let from_sketch: Vec<(u64, u64)> = top_k.top_k();
+        let from_summary: Vec<(u64, u64)> = summary.top_k(k).iter().map(|counter| (counter.key, counter.count)).collect();
+        for (name, reported) in [("Count-Min top-k", &from_sketch), ("SpaceSaving", &from_summary)] {
+            let found: usize = reported.iter().filter(|(key, _)| top.contains(key)).count();
+            let error: f64 = reported.iter()
+                .map(|(key, count)| (*count as f64 - exact.get(*key) as f64).abs() / exact.get(*key).max(1) as f64)
+                .sum::<f64>() / reported.len() as f64;
+            writeln!(file, "{} precision: {}, average relative error: {}", name, found as f64 / k as f64, error).expect("Cannot write to file");
         }
+    }
+}
 
-        for x in input_array {
-            let hash = hash_fn.hash(*x);
-            buckets[hash].push(*x);
+// Samples of the distributions used by the quantile experiments. Pareto with shape 1.5
+// stands in for latencies, with most values near the minimum and a heavy tail.
+fn sample_distribution(name: &str, len: usize) -> Vec<f64> {
+    let mut rng = thread_rng();
+    return (0..len).map(|_| {
+        let u: f64 = 1.0 - rng.gen::<f64>();
+        match name {
+            "uniform" => u,
+            "normal" => gaussian(),
+            "exponential" => -u.ln(),
+            "pareto" => u.powf(-1.0 / 1.5),
+            _ => panic!("Unknown distribution {}", name),
         }
+    }).collect();
+}
 
-        let mut sum_of_squares = 0;
-        for x in &mut buckets {
-            sum_of_squares += x.len().pow(2);
+// Median and tail quantiles estimated by a KLL sketch with error epsilon and a t-digest,
+// against the exact values taken from the sorted input
+fn benchmark_quantiles(len: usize, epsilon: f64, compression: f64) {
+    const DISTRIBUTIONS: [&str; 4] = ["uniform", "normal", "exponential", "pareto"];
+    const QUANTILES: [f64; 3] = [0.5, 0.99, 0.999];
+    let mut file: File = make_writable_file("quantiles");
+
+    for distribution in DISTRIBUTIONS {
+        writeln!(file, "Distribution: {}", distribution).expect("Cannot write to file");
+        let mut values: Vec<f64> = sample_distribution(distribution, len);
+        let mut kll: KllSketch = KllSketch::new(epsilon);
+        let mut digest: TDigest = TDigest::new(compression);
+        for x in &values {
+            kll.update(*x);
+            digest.update(*x);
         }
-        if sum_of_squares > array_len {
-            println!("Fail");
-            return PerfectHashing::new(input_array);
+        values.sort_unstable_by(f64::total_cmp);
+        writeln!(file, "KLL retained values: {}, t-digest centroids: {}", kll.retained(), digest.centroid_count()).expect("Cannot write to file");
+
+        for q in QUANTILES {
+            let exact: f64 = values[((q * len as f64).ceil() as usize).clamp(1, len) - 1];
+            writeln!(file, "Quantile {}: exact {}", q, exact).expect("Cannot write to file");
+            for (name, estimate) in [("KLL", kll.quantile(q).unwrap()), ("t-digest", digest.quantile(q).unwrap())] {
+                let rank: f64 = values.partition_point(|x| *x <= estimate) as f64 / len as f64;
+                writeln!(file, "{}: {} (rank error {})", name, estimate, (rank - q).abs()).expect("Cannot write to file");
+            }
         }
+    }
+}
+
+// Totals of the key ranges [i 2^range_len, (i + 1) 2^range_len) estimated from priority and
+// VarOpt samples of k out of stream_len keys with heavy tailed weights, against exact sums
+fn benchmark_subset_sums(stream_len: usize, k: usize, key_len: u32, range_len: u32) {
+    let mut file: File = make_writable_file("subset_sums");
+    let mut rng = thread_rng();
+    let ranges: usize = 1 << (key_len - range_len);
+
+    let mut keys: Vec<u64> = (0..1u64 << key_len).collect();
+    keys.shuffle(&mut rng);
+    let stream: Vec<(u64, f64)> = keys.into_iter().take(stream_len)
+        .map(|key| (key, (1.0 - rng.gen::<f64>()).powf(-1.0 / 1.5)))
+        .collect();
+    let mut exact: Vec<f64> = vec![0.0; ranges];
+    let mut priority: PrioritySampler = PrioritySampler::new(k);
+    let mut varopt: VarOptSampler = VarOptSampler::new(k);
+    for (key, weight) in &stream {
+        exact[(*key >> range_len) as usize] += weight;
+        priority.insert(*key, *weight);
+        varopt.insert(*key, *weight);
+    }
+
+    let (mut priority_error, mut varopt_error) = (0.0, 0.0);
+    for (range, exact) in exact.iter().enumerate() {
+        let in_range = |key: u64| (key >> range_len) as usize == range;
+        let (priority_estimate, varopt_estimate) = (priority.estimate(in_range), varopt.estimate(in_range));
+        writeln!(file, "Range {}: exact {}, priority {}, VarOpt {}", range, exact, priority_estimate, varopt_estimate).expect("Cannot write to file");
+        priority_error += (priority_estimate - exact).abs() / exact;
+        varopt_error += (varopt_estimate - exact).abs() / exact;
+    }
+    writeln!(file, "Priority average relative error: {}", priority_error / ranges as f64).expect("Cannot write to file");
+    writeln!(file, "VarOpt average relative error: {}", varopt_error / ranges as f64).expect("Cannot write to file");
+}
 
-        let mut vec = Vec::<Bucket>::with_capacity(array_len);
-        for vec_bucket in &mut buckets {
-            if vec_bucket.len() == 0 {
-                vec.push(Bucket::new(&Vec::new()));
-                continue
+// AMS estimates of F2, from NormSketch groups, and of F3, from sampled stream positions, against
+// the exact moments of the same stream, over several independent trials per epsilon
+fn benchmark_ams(stream_len: usize, keys: usize, epsilons: [f64; 3], delta: f64) {
+    const TRIALS: usize = 5;
+    let mut file: File = make_writable_file("ams");
+    let mut input_rng: StdRng = StdRng::seed_from_u64(input_seed());
+
+    for epsilon in epsilons {
+        writeln!(file, "Epsilon: {}, delta: {}", epsilon, delta).expect("Cannot write to file");
+        let mut errors: Vec<f64> = Vec::with_capacity(TRIALS);
+        let mut f3_errors: Vec<f64> = Vec::with_capacity(TRIALS);
+        for _ in 0..TRIALS {
+            let stream: Vec<u64> = zipf(keys, 1.0, stream_len, &mut input_rng);
+            let mut exact: HwC = HwC::new(keys);
+            let mut ams: AmsF2Estimator = SketchBuilder::new(epsilon, delta).ams();
+            let mut f3: AmsFkEstimator = AmsFkEstimator::with_guarantees(3, epsilon, delta, keys as u64);
+            for x in &stream {
+                exact.insert(*x, 1);
+                ams.insert(*x, 1);
+                f3.insert(*x);
             }
-            let bucket = Bucket::new(vec_bucket);
-            vec.push(bucket);
+            errors.push((ams.norm() as f64 - exact.norm() as f64).abs() / exact.norm() as f64);
+            let exact_f3: f64 = (1..=keys as u64).map(|key| (exact.get(key) as f64).powi(3)).sum();
+            f3_errors.push((f3.moment() - exact_f3).abs() / exact_f3);
+        }
+        let (s1, s2) = SketchBuilder::new(epsilon, delta).ams().dimensions();
+        writeln!(file, "Groups: {} of {} sketches", s2, s1).expect("Cannot write to file");
+        writeln!(file, "Mean relative error: {}", errors.iter().sum::<f64>() / TRIALS as f64).expect("Cannot write to file");
+        writeln!(file, "Max relative error: {}", errors.iter().cloned().fold(0.0, f64::max)).expect("Cannot write to file");
+        let (s1, s2) = AmsFkEstimator::with_guarantees(3, epsilon, delta, keys as u64).dimensions();
+        writeln!(file, "F3 groups: {} of {} estimators", s2, s1).expect("Cannot write to file");
+        writeln!(file, "F3 mean relative error: {}", f3_errors.iter().sum::<f64>() / TRIALS as f64).expect("Cannot write to file");
+        writeln!(file, "F3 max relative error: {}", f3_errors.iter().cloned().fold(0.0, f64::max)).expect("Cannot write to file");
+    }
+}
+
+// HyperLogLog estimates against the exact distinct count from HwC on the same stream
+fn benchmark_hyperloglog(test_sizes: &[i32], precisions: [u32; 3]) {
+    let mut file: File = make_writable_file("hyperloglog");
+
+    for &test_size in test_sizes {
+        writeln!(file, "Test size: {}", test_size).expect("Cannot write to file");
+        let input_size: usize = 2_i32.pow(test_size as u32) as usize;
+        let input: Vec<u64> = (0..input_size).map(|_| random_generator(1, input_size as u32) as u64).collect();
+        let mut exact: HwC = HwC::new(input_size);
+        let mut sketches: Vec<HyperLogLog> = precisions.iter().map(|precision| HyperLogLog::new(*precision)).collect();
+        for x in &input {
+            exact.insert(*x, 1);
+            sketches.iter_mut().for_each(|hll| hll.insert(*x));
         }
-        return PerfectHashing {
-            vec,
-            hash_function: hash_fn
+        let distinct: usize = exact.stats().entries;
+        writeln!(file, "Distinct keys: {}", distinct).expect("Cannot write to file");
+        for hll in &sketches {
+            let relative_error: f64 = (hll.estimate() - distinct as f64).abs() / distinct as f64;
+            writeln!(file, "Precision {}: estimate {}, relative error {}, {} bytes{}", hll.precision(), hll.estimate(), relative_error,
+                hll.space_usage(), if hll.is_sparse() { " (sparse)" } else { "" }).expect("Cannot write to file");
         }
     }
-    // fn insert(&mut self, elem: u32) {
-    //     let hash: usize = self.hash_function.hash(elem);
-    //     self.vec[hash].insert(elem);
-    // }
-    fn query(&self, elem: u32) -> bool {
-        let hash: usize = self.hash_function.hash(elem);
-        return self.vec[hash].query(elem);
+}
+
+// Empirical false positive rate of Bloom filters sized for n keys, against the
+// rate the filter expects from its dimensions. The counting filter is measured
+// again after removing half of the keys, and the cuckoo filter is sized for the same rate.
+fn benchmark_bloom(test_sizes: &[i32], fprs: [f64; 3]) {
+    const QUERIES: u64 = 1 << 20;
+    let mut file: File = make_writable_file("bloom");
+
+    for &test_size in test_sizes {
+        writeln!(file, "Test size: {}", test_size).expect("Cannot write to file");
+        let input_size: usize = 2_i32.pow(test_size as u32) as usize;
+        for fpr in fprs {
+            let mut bloom: BloomFilter = BloomFilter::optimal(input_size, fpr);
+            let mut counting: CountingBloomFilter = CountingBloomFilter::optimal(input_size, fpr);
+            let mut cuckoo: CuckooFilter = CuckooFilter::optimal(input_size, fpr);
+            for x in 1..=input_size as u64 {
+                bloom.insert(x);
+                counting.insert(x);
+                cuckoo.insert(x);
+            }
+            // Keys above the input were never inserted
+            let never_inserted = || (0..QUERIES).map(|x| input_size as u64 + 1 + x);
+            let false_positives: usize = never_inserted().filter(|x| bloom.contains(*x)).count();
+            writeln!(file, "Target {}: {} bits, {} hashes, expected {}, measured {}, {} bytes per key", fpr, bloom.bit_count(), bloom.hash_count(),
+                bloom.expected_fpr(input_size), false_positives as f64 / QUERIES as f64,
+                bloom.space_usage() as f64 / input_size as f64).expect("Cannot write to file");
+
+            let false_positives: usize = never_inserted().filter(|x| cuckoo.contains(*x)).count();
+            writeln!(file, "Cuckoo: {} bit fingerprints, measured {}, {} bytes per key", cuckoo.fingerprint_bits(),
+                false_positives as f64 / QUERIES as f64, cuckoo.space_usage() as f64 / input_size as f64).expect("Cannot write to file");
+
+            let false_positives: usize = never_inserted().filter(|x| counting.contains(*x)).count();
+            writeln!(file, "Counting: measured {}, {} overflows, {} bytes per key", false_positives as f64 / QUERIES as f64,
+                counting.overflows(), counting.space_usage() as f64 / input_size as f64).expect("Cannot write to file");
+            for x in 1..=(input_size / 2) as u64 {
+                counting.remove(x);
+            }
+            let false_positives: usize = never_inserted().filter(|x| counting.contains(*x)).count();
+            writeln!(file, "Counting after removing half: expected {}, measured {}", counting.expected_fpr(input_size - input_size / 2),
+                false_positives as f64 / QUERIES as f64).expect("Cannot write to file");
+        }
     }
 }
 
-fn log2u(x: usize) -> u32 {
-    x.ilog2()
+// A scalable Bloom filter that starts out sized for 1000 keys, against the overall
+// false positive rate it promises
+fn benchmark_scalable_bloom(test_sizes: &[i32], fpr: f64) {
+    const QUERIES: u64 = 1 << 20;
+    let mut file: File = make_writable_file("scalable_bloom");
+
+    for &test_size in test_sizes {
+        writeln!(file, "Test size: {}", test_size).expect("Cannot write to file");
+        let input_size: usize = 2_i32.pow(test_size as u32) as usize;
+        let mut scalable: ScalableBloomFilter = ScalableBloomFilter::new(1000, fpr);
+        for x in 1..=input_size as u64 {
+            scalable.insert(x);
+        }
+        let false_positives: usize = (0..QUERIES).filter(|x| scalable.contains(input_size as u64 + 1 + x)).count();
+        writeln!(file, "Target {}: {} sub-filters, bound {}, measured {}, {} bytes per key", fpr, scalable.filter_count(),
+            scalable.fpr_bound(), false_positives as f64 / QUERIES as f64,
+            scalable.space_usage() as f64 / input_size as f64).expect("Cannot write to file");
+    }
 }
 
-fn make_rb_tree(input: &Vec<u32>) -> RBTree<u32, u32> {
-    let mut tree = RBTree::new();
-    for x in input {
-        tree.insert(*x, *x);
+// Lookup throughput of the standard and the cache-blocked Bloom filter and of the
+// cuckoo filter for 2^test_size keys, with `queries` lookups of mostly never-inserted keys
+fn benchmark_blocked_bloom(test_size: u32, fpr: f64, queries: u64) {
+    let mut file: File = make_writable_file("blocked_bloom");
+    let input_size: usize = 1 << test_size;
+    writeln!(file, "Test size: {}, target {}, {} queries", test_size, fpr, queries).expect("Cannot write to file");
+
+    let mut bloom: BloomFilter = BloomFilter::optimal(input_size, fpr);
+    let mut blocked: BlockedBloomFilter = BlockedBloomFilter::optimal(input_size, fpr);
+    let mut cuckoo: CuckooFilter = CuckooFilter::optimal(input_size, fpr);
+    for x in 1..=input_size as u64 {
+        bloom.insert(x);
+        blocked.insert(x);
+        cuckoo.insert(x);
     }
-    return tree;
+
+    let q_start = now();
+    let hits: u64 = (1..=queries).filter(|x| bloom.contains(*x)).count() as u64;
+    let q_stop = now();
+    writeln!(file, "Standard query time: {}", q_stop - q_start).expect("Cannot write to file");
+    writeln!(file, "Standard expected {}, measured {}", bloom.expected_fpr(input_size),
+        (hits - input_size as u64) as f64 / (queries - input_size as u64) as f64).expect("Cannot write to file");
+
+    let q_start = now();
+    let hits: u64 = (1..=queries).filter(|x| blocked.contains(*x)).count() as u64;
+    let q_stop = now();
+    writeln!(file, "Blocked query time: {}", q_stop - q_start).expect("Cannot write to file");
+    writeln!(file, "Blocked expected {}, measured {}", blocked.expected_fpr(input_size),
+        (hits - input_size as u64) as f64 / (queries - input_size as u64) as f64).expect("Cannot write to file");
+
+    let q_start = now();
+    let hits: u64 = (1..=queries).filter(|x| cuckoo.contains(*x)).count() as u64;
+    let q_stop = now();
+    writeln!(file, "Cuckoo query time: {}", q_stop - q_start).expect("Cannot write to file");
+    writeln!(file, "Cuckoo measured {}", (hits - input_size as u64) as f64 / (queries - input_size as u64) as f64).expect("Cannot write to file");
 }
 
-fn rb_tree(input: &Vec<u32>, file: &mut File) {
-    let c_start = OffsetDateTime::now_utc();
-    let tree = make_rb_tree(input);
-    let c_stop = OffsetDateTime::now_utc();
-    writeln!(file, "Construction time: {}", c_stop - c_start).expect("Cannot write to file");
+// Set reconciliation of two sets of 2^test_size keys that differ in d keys, half on
+// each side, with an IBLT sized for d differences. Reports how often the difference
+// was recovered exactly and the space it took.
+fn benchmark_iblt(test_size: u32, differences: [usize; 4]) {
+    const TRIALS: usize = 10;
+    let mut file: File = make_writable_file("iblt");
+    let input_size: u64 = 1 << test_size;
+    writeln!(file, "Test size: {}", test_size).expect("Cannot write to file");
 
-    let mut sum: usize = 0;
+    for d in differences {
+        let mut recovered: usize = 0;
+        let mut space: usize = 0;
+        for _ in 0..TRIALS {
+            let mut alice: Iblt = Iblt::new(d);
+            let mut bob: Iblt = alice.clone();
+            // Keys below the input size are shared, the rest is unique to one side
+            for x in 0..input_size {
+                alice.insert(x);
+                bob.insert(x);
+            }
+            let only_alice: Vec<u64> = (0..(d / 2) as u64).map(|x| input_size + 2 * x).collect();
+            let only_bob: Vec<u64> = (0..(d - d / 2) as u64).map(|x| input_size + 2 * x + 1).collect();
+            only_alice.iter().for_each(|x| alice.insert(*x));
+            only_bob.iter().for_each(|x| bob.insert(*x));
 
-    let q_start = OffsetDateTime::now_utc();
-    for x in input {
-        if tree.contains_key(x) {
-            sum += 1;
+            let listed = alice.subtract(&bob).expect("Tables share their hash functions").list_entries();
+            if let Some((mut inserted, mut deleted)) = listed {
+                inserted.sort_unstable();
+                deleted.sort_unstable();
+                if inserted == only_alice && deleted == only_bob {
+                    recovered += 1;
+                }
+            }
+            space = alice.space_usage();
         }
+        writeln!(file, "Differences {}: recovered {}/{}, {} bytes", d, recovered, TRIALS, space).expect("Cannot write to file");
     }
-    let q_stop = OffsetDateTime::now_utc();
-    writeln!(file, "Query time: {}", q_stop - q_start).expect("Cannot write to file");
+}
+
+// MinHash estimates for pairs of sets of 2^test_size keys overlapping so that their
+// Jaccard similarity is known, for several signature lengths
+fn benchmark_minhash(test_size: u32, signature_lens: [usize; 3], similarities: [f64; 3]) {
+    const TRIALS: usize = 10;
+    let mut file: File = make_writable_file("minhash");
+    let set_size: u64 = 1 << test_size;
+    writeln!(file, "Test size: {}", test_size).expect("Cannot write to file");
 
-    println!("{}", sum);
+    for similarity in similarities {
+        // Shifting B by s keys gives J = (n - s) / (n + s)
+        let shift: u64 = (set_size as f64 * (1.0 - similarity) / (1.0 + similarity)).round() as u64;
+        let exact: f64 = (set_size - shift) as f64 / (set_size + shift) as f64;
+        for k in signature_lens {
+            let mut errors: Vec<f64> = Vec::with_capacity(TRIALS);
+            for _ in 0..TRIALS {
+                let mut a: MinHash = MinHash::new(k);
+                let mut b: MinHash = a.clone();
+                (0..set_size).for_each(|x| a.insert(x));
+                (shift..set_size + shift).for_each(|x| b.insert(x));
+                errors.push((a.jaccard(&b) - exact).abs());
+            }
+            writeln!(file, "Jaccard {}, k {}: mean absolute error {}", exact, k, errors.iter().sum::<f64>() / TRIALS as f64).expect("Cannot write to file");
+        }
+    }
 }
 
-fn hashing_with_chaining(input: &Vec<u32>, file: &mut File) {
-    let c_start = OffsetDateTime::now_utc();
-    let mut hwc: HwC = HwC::new(input.len());
-    for x in input {
-        hwc.insert(*x);
+// Jaccard estimates of highly similar sets at the memory of a MinHash signature of length k:
+// the signature itself, an odd sketch of a signature eight times longer, and an odd sketch of
+// the sets, whose symmetric difference gives J = (|A| + |B| - d) / (|A| + |B| + d)
+fn benchmark_odd_sketch(test_size: u32, k: usize, similarities: [f64; 3]) {
+    const TRIALS: usize = 10;
+    const SIGNATURE_FACTOR: usize = 8;
+    let mut file: File = make_writable_file("odd_sketch");
+    let set_size: u64 = 1 << test_size;
+    // As many bits as the k 64-bit coordinates of the signature
+    let bits_len: u32 = (64 * k).ilog2();
+    writeln!(file, "Test size: {}, k: {}, odd sketch bits: {}", test_size, k, 1 << bits_len).expect("Cannot write to file");
+
+    for similarity in similarities {
+        let shift: u64 = (set_size as f64 * (1.0 - similarity) / (1.0 + similarity)).round() as u64;
+        let exact: f64 = (set_size - shift) as f64 / (set_size + shift) as f64;
+        let (mut minhash_error, mut signature_error, mut set_error) = (0.0, 0.0, 0.0);
+        for _ in 0..TRIALS {
+            let minhash: MinHash = MinHash::new(k);
+            let long_minhash: MinHash = MinHash::new(SIGNATURE_FACTOR * k);
+            let odd: OddSketch = OddSketch::new(bits_len);
+            let (mut a, mut b) = (minhash.clone(), minhash.clone());
+            let (mut long_a, mut long_b) = (long_minhash.clone(), long_minhash.clone());
+            let (mut odd_a, mut odd_b) = (odd.clone(), odd.clone());
+            for x in 0..set_size {
+                a.insert(x);
+                long_a.insert(x);
+                odd_a.insert(x);
+                b.insert(x + shift);
+                long_b.insert(x + shift);
+                odd_b.insert(x + shift);
+            }
+            minhash_error += (a.jaccard(&b) - exact).abs();
+            let (mut signature_a, mut signature_b) = (odd.clone(), odd.clone());
+            signature_a.insert_signature(long_a.signature());
+            signature_b.insert_signature(long_b.signature());
+            let estimate: f64 = signature_a.signature_jaccard(&signature_b, SIGNATURE_FACTOR * k).expect("Sketches share their hash function");
+            signature_error += (estimate - exact).abs();
+            let difference: f64 = odd_a.symmetric_difference(&odd_b).expect("Sketches share their hash function");
+            set_error += (((2 * set_size) as f64 - difference) / ((2 * set_size) as f64 + difference) - exact).abs();
+        }
+        writeln!(file, "Jaccard {}: mean absolute error MinHash {}, odd sketch of signatures {}, odd sketch of sets {}",
+            exact, minhash_error / TRIALS as f64, signature_error / TRIALS as f64, set_error / TRIALS as f64).expect("Cannot write to file");
     }
-    let c_stop = OffsetDateTime::now_utc();
-    writeln!(file, "Construction time: {}", c_stop - c_start).expect("Cannot write to file");
+}
 
+// Variance of the b-bit MinHash estimator against the one of full 64-bit signatures,
+// at a fixed signature length and similarity
+fn benchmark_b_bit_minhash(k: usize, bits: [u32; 4], similarity: f64) {
+    const TRIALS: usize = 100;
+    const SET_SIZE: u64 = 1 << 12;
+    let mut file: File = make_writable_file("b_bit_minhash");
+    let shift: u64 = (SET_SIZE as f64 * (1.0 - similarity) / (1.0 + similarity)).round() as u64;
+    let exact: f64 = (SET_SIZE - shift) as f64 / (SET_SIZE + shift) as f64;
+    writeln!(file, "Jaccard {}, k {}", exact, k).expect("Cannot write to file");
 
-    let mut sum: usize = 0;
-    let q_start = OffsetDateTime::now_utc();
-    for x in input {
-        if hwc.query(*x) {
-            sum += 1;
+    let variance = |estimates: &Vec<f64>| estimates.iter().map(|e| (e - exact).powi(2)).sum::<f64>() / estimates.len() as f64;
+    let mut full: Vec<f64> = Vec::with_capacity(TRIALS);
+    let mut b_bit: Vec<Vec<f64>> = vec![Vec::with_capacity(TRIALS); bits.len()];
+    for _ in 0..TRIALS {
+        let mut a: MinHash = MinHash::new(k);
+        let mut b: MinHash = a.clone();
+        (0..SET_SIZE).for_each(|x| a.insert(x));
+        (shift..SET_SIZE + shift).for_each(|x| b.insert(x));
+        full.push(a.jaccard(&b));
+        for (estimates, b_len) in b_bit.iter_mut().zip(bits) {
+            estimates.push(a.to_b_bit(b_len).jaccard(&b.to_b_bit(b_len)));
         }
     }
-    println!("{}", sum);
-    let q_stop = OffsetDateTime::now_utc();
-    writeln!(file, "Query time: {}", q_stop - q_start).expect("Cannot write to file");
+    writeln!(file, "64 bits: variance {}, {} bytes", variance(&full), MinHash::new(k).space_usage()).expect("Cannot write to file");
+    for (estimates, b_len) in b_bit.iter().zip(bits) {
+        writeln!(file, "{} bits: variance {}, {} bytes", b_len, variance(estimates),
+            MinHash::new(k).to_b_bit(b_len).space_usage()).expect("Cannot write to file");
+    }
+}
+
+// Time to build a signature of k coordinates for a set of 2^test_size keys, with k
+// independent MinHash functions and with one-permutation hashing
+fn benchmark_one_permutation_hashing(test_sizes: &[i32], k: usize) {
+    let mut file: File = make_writable_file("one_permutation_hashing");
+
+    for &test_size in test_sizes {
+        writeln!(file, "Test size: {}", test_size).expect("Cannot write to file");
+        let input_size: u64 = 1 << test_size;
+
+        let c_start = now();
+        let mut minhash: MinHash = MinHash::new(k);
+        (0..input_size).for_each(|x| minhash.insert(x));
+        let c_stop = now();
+        writeln!(file, "MinHash construction time: {}", c_stop - c_start).expect("Cannot write to file");
+
+        let c_start = now();
+        let mut oph: OnePermutationHash = OnePermutationHash::new(k);
+        (0..input_size).for_each(|x| oph.insert(x));
+        let signature: Vec<u64> = oph.signature();
+        let c_stop = now();
+        writeln!(file, "One permutation construction time: {}", c_stop - c_start).expect("Cannot write to file");
+        black_box(signature.len() + minhash.len());
+    }
+}
 
-    let mut longest_ll: usize = 0;
-    for ll in hwc.vec {
-        if ll.len() > longest_ll {
-            longest_ll = ll.len();
+// Exact Jaccard similarity of two sorted sets
+fn jaccard(a: &[u64], b: &[u64]) -> f64 {
+    let (mut i, mut j, mut shared) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            shared += 1;
+        }
+        if a[i] <= b[j] {
+            i += 1;
+        } else {
+            j += 1;
         }
     }
-    writeln!(file, "Longest linked list: {}", longest_ll).expect("Cannot write to file");
+    return shared as f64 / (a.len() + b.len() - shared) as f64;
 }
 
-fn perfect_hashing(input: &Vec<u32>, file: &mut File) {
-    let c_start = OffsetDateTime::now_utc();
-    let ph_struct: PerfectHashing = PerfectHashing::new(&input);
-    let c_stop = OffsetDateTime::now_utc();
-    writeln!(file, "Construction time: {}", c_stop - c_start).expect("Cannot write to file");
+// Near-duplicate retrieval with an LSH index over MinHash signatures. The sets are
+// groups of noisy copies of random base sets, and the candidates of every set are
+// compared with a brute-force scan for the sets at Jaccard similarity >= threshold.
+fn benchmark_lsh(groups: usize, copies: usize, bands: usize, rows: usize, threshold: f64) {
+    const SET_SIZE: usize = 200;
+    let mut file: File = make_writable_file("lsh");
+    writeln!(file, "Bands {}, rows {}, threshold {}", bands, rows, threshold).expect("Cannot write to file");
+
+    let mut sets: Vec<Vec<u64>> = Vec::with_capacity(groups * copies);
+    for _ in 0..groups {
+        let base: Vec<u64> = (0..SET_SIZE).map(|_| random_generator(0, u32::MAX) as u64).collect();
+        for _ in 0..copies {
+            // Every copy replaces each key of the base set with probability 1/10
+            let mut set: Vec<u64> = base.iter()
+                .map(|x| if random_generator(0, 10) == 0 { random_generator(0, u32::MAX) as u64 } else { *x })
+                .collect();
+            set.sort_unstable();
+            set.dedup();
+            sets.push(set);
+        }
+    }
 
+    let empty: MinHash = MinHash::new(bands * rows);
+    let mut index: LshIndex = LshIndex::new(bands, rows);
+    let signatures: Vec<MinHash> = sets.iter().map(|set| {
+        let mut signature: MinHash = empty.clone();
+        set.iter().for_each(|x| signature.insert(*x));
+        signature
+    }).collect();
+    for (id, signature) in signatures.iter().enumerate() {
+        index.insert(id, signature);
+    }
 
-    let q_start = OffsetDateTime::now_utc();
-    let mut sum: usize = 0;
-    for x in input {
-        let s = ph_struct.query(*x) as usize;
-        sum = sum+s;
+    let (mut true_positives, mut candidates, mut relevant) = (0, 0, 0);
+    for (id, set) in sets.iter().enumerate() {
+        let found: Vec<usize> = index.candidates(&signatures[id]);
+        let near: Vec<usize> = (0..sets.len()).filter(|other| *other != id && jaccard(set, &sets[*other]) >= threshold).collect();
+        candidates += found.iter().filter(|other| **other != id).count();
+        relevant += near.len();
+        true_positives += near.iter().filter(|other| found.binary_search(other).is_ok()).count();
+    }
+    writeln!(file, "Precision: {}", true_positives as f64 / candidates as f64).expect("Cannot write to file");
+    writeln!(file, "Recall: {}", true_positives as f64 / relevant as f64).expect("Cannot write to file");
+}
+
+// Serialized size of every sketch type for the dimensions SketchBuilder derives
+fn benchmark_sketch_sizes(epsilons: [f64; 3], delta: f64) {
+    let mut file: File = make_writable_file("sketch_sizes");
+
+    for epsilon in epsilons {
+        writeln!(file, "Epsilon: {}, delta: {}", epsilon, delta).expect("Cannot write to file");
+        let builder: SketchBuilder = SketchBuilder::new(epsilon, delta);
+        let count_min: CountMin = builder.count_min(UpdatePolicy::Standard);
+        let count_sketch: CountSketch = builder.count_sketch();
+        let norm_sketch: NormSketch = builder.norm_sketch();
+        let ams: AmsF2Estimator = builder.ams();
+        writeln!(file, "Count-Min: {} bytes", count_min.to_bytes().len()).expect("Cannot write to file");
+        writeln!(file, "Count Sketch: {} bytes", count_sketch.to_bytes().len()).expect("Cannot write to file");
+        writeln!(file, "NormSketch: {} bytes", norm_sketch.to_bytes().len()).expect("Cannot write to file");
+        writeln!(file, "AMS: {} bytes", ams.to_bytes().len()).expect("Cannot write to file");
     }
-    let q_stop = OffsetDateTime::now_utc();
-    writeln!(file, "Query time: {}", q_stop - q_start).expect("Cannot write to file");
 }
 
+// Directory the result files go to, the working directory unless --output-dir says otherwise
+static OUTPUT_DIR: OnceLock<PathBuf> = OnceLock::new();
+
 fn make_writable_file(file_name: &str) -> File {
+    let directory: &Path = OUTPUT_DIR.get().map_or(Path::new("."), |path| path.as_path());
     return OpenOptions::new()
-        .write(true)
+        .create(true)
+        .append(true)
+        .open(directory.join(file_name.to_owned() + ".txt"))
+        .unwrap();
+}
+
+// Results file in CSV, appended to across runs like the .txt ones, with the header written
+// only when the file is new
+fn make_csv_writer(file_name: &str) -> CsvWriter<File> {
+    let directory: &Path = OUTPUT_DIR.get().map_or(Path::new("."), |path| path.as_path());
+    let file: File = OpenOptions::new()
+        .create(true)
         .append(true)
-        .open(file_name.to_owned() + ".txt")
+        .open(directory.join(file_name.to_owned() + ".csv"))
         .unwrap();
+    let is_new: bool = file.metadata().map_or(true, |metadata| metadata.len() == 0);
+    return CsvWriter::new(file, is_new).expect("Cannot write to file");
 }
 
-fn benchmark_rb_tree(test_sizes: [i32; 7]) {
-    let mut file: File = make_writable_file("rb_tree");
+// Every row written during the run, kept for the JSON report when --json asks for one
+static MEASUREMENTS: OnceLock<Mutex<Vec<ResultRow>>> = OnceLock::new();
 
-    for test_size in test_sizes {
-        writeln!(file, "Test size: {}", test_size).expect("Cannot write to file");
-        let input_size: usize = 2_i32.pow(test_size as u32) as usize;
-        let input: Vec<u32> = Vec::from_iter(1..(input_size +1) as u32);
-        rb_tree(&input, &mut file);
+fn write_row(csv: &mut CsvWriter<File>, row: ResultRow) {
+    csv.write(&row).expect("Cannot write to file");
+    if let Some(measurements) = MEASUREMENTS.get() {
+        measurements.lock().unwrap().push(row);
     }
 }
 
-fn benchmark_hwc(test_sizes: [i32; 7]) {
-    let mut file: File = make_writable_file("hwc");
-
-    for test_size in test_sizes {
-        writeln!(file, "Test size: {}", test_size).expect("Cannot write to file");
-        let input_size: usize = 2_i32.pow(test_size as u32) as usize;
-        let input: Vec<u32> = Vec::from_iter(1..(input_size +1) as u32);
-        hashing_with_chaining(&input, &mut file);
+// The experiment runner as the options set it up
+fn make_runner(options: &Options) -> Runner {
+    return Runner {
+        progress: options.progress,
+        jobs: options.jobs,
+        budget: options.budget,
+        latency_samples: options.latency_samples,
+        warmup: options.warmup.unwrap_or(WARMUP),
+        ..Runner::new(&options.sizes, options.repetitions.unwrap_or(REPETITIONS))
     }
 }
 
-fn benchmark_ph(test_sizes: [i32; 7]) {
-    let mut file: File = make_writable_file("ph");
+// Runs an experiment over the test sizes, appending its rows to <output-dir>/<file_name>.csv
+fn benchmark_experiment<E: Experiment + Sync>(file_name: &str, experiment: E, options: &Options) {
+    let mut csv: CsvWriter<File> = make_csv_writer(file_name);
+    let runner: Runner = make_runner(options);
+    runner.run(&experiment, |row| write_row(&mut csv, row));
+}
 
-    for test_size in test_sizes {
-        writeln!(file, "Test size: {}", test_size).expect("Cannot write to file");
-        let input_size: usize = 2_i32.pow(test_size as u32) as usize;
-        let input: Vec<u32> = Vec::from_iter(1..(input_size +1) as u32);
-        perfect_hashing(&input, &mut file);
-    }
+// The tables of the crate against the maps of the standard library, HashMap with both its
+// default SipHash and ahash and BTreeMap, all through the same runner and in one table, so
+// the overhead of each table shows next to the baselines at every size
+fn benchmark_baselines(options: &Options) {
+    let mut csv: CsvWriter<File> = make_csv_writer("baselines");
+    let runner: Runner = make_runner(options);
+    let mut record = |row: ResultRow| write_row(&mut csv, row);
+    runner.run(&BaselineExperiment::<HashMap<u64, i64, SipHashState>>::new("hashmap_siphash"), &mut record);
+    runner.run(&BaselineExperiment::<HashMap<u64, i64, AHashState>>::new("hashmap_ahash"), &mut record);
+    runner.run(&BaselineExperiment::<BTreeMap<u64, i64>>::new("btree_map"), &mut record);
+    runner.run(&HwCExperiment::<InlineChains>::new("hwc"), &mut record);
+    runner.run(&LinearProbingExperiment, &mut record);
+    runner.run(&DoubleHashingExperiment, &mut record);
 }
 
+// Construction, query and mixed workloads over every ordered structure, one combined table.
+// Keys are 1 to n in random order, and the mixed workload is n operations on keys up to 2n of
+// which a quarter insert, a quarter remove and half look up.
+fn benchmark_ordered_structures(test_sizes: &[i32]) {
+    let mut csv: CsvWriter<File> = make_csv_writer("ordered_structures");
 
-fn main() -> std::io::Result<()> {
-    const TEST_SIZES: [i32; 7] = [12, 14, 16, 18, 20, 22, 24];
-    benchmark_rb_tree(TEST_SIZES);
-    benchmark_hwc(TEST_SIZES);
-    benchmark_ph(TEST_SIZES);
+    for &test_size in test_sizes {
+        let input_size: usize = 2_i32.pow(test_size as u32) as usize;
+        let mut input: Vec<u64> = Vec::from_iter(1..(input_size +1) as u64);
+        input.shuffle(&mut thread_rng());
+        let mixed: Vec<(u8, u64)> = (0..input_size)
+            .map(|_| (random_generator(0, 4) as u8, random_generator(1, 2 * input_size as u32) as u64))
+            .collect();
+
+        ordered_structure(RBTree::new(), "rb_tree", &input, &mixed, &mut csv);
+        ordered_structure(BTreeMap::new(), "btree_map", &input, &mixed, &mut csv);
+        ordered_structure(Treap::new(), "treap", &input, &mixed, &mut csv);
+        for probability in [0.25, 0.5] {
+            let name: String = format!("skip_list_{}", probability);
+            ordered_structure(SkipList::new(probability), &name, &input, &mixed, &mut csv);
+        }
+    }
+}
+
+// Scaling of the sharded table from one thread up to one per cpu
+fn benchmark_sharded_hwc(test_sizes: &[i32]) {
+    let mut csv: CsvWriter<File> = make_csv_writer("sharded_hwc");
+    let max_threads: usize = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+
+    for &test_size in test_sizes {
+        let input_size: usize = 2_i32.pow(test_size as u32) as usize;
+        let input: Vec<u64> = Vec::from_iter(1..(input_size +1) as u64);
+        for threads in 1..=max_threads {
+            sharded_hashing_with_chaining(&input, threads, &mut csv);
+        }
+    }
+}
+
+// Scaling of sharded sketch updates from one thread up to one per cpu, on a turnstile stream
+// of updates_len updates. Each thread feeds a clone of one empty sketch and the clones are merged.
+fn benchmark_parallel_sketches(updates_len: usize, keys: usize) {
+    let mut file: File = make_writable_file("parallel_sketches");
+    let max_threads: usize = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let updates: Vec<(u64, i64)> = (0..updates_len).map(|_| (random_generator(0, keys as u32) as u64, random_generator(0, 11) as i64 - 5)).collect();
+    let norm_sketch: NormSketch = NormSketch::new(16);
+    let count_sketch: CountSketch = CountSketch::new(5, 16);
+
+    for threads in 1..=max_threads {
+        writeln!(file, "Threads: {}", threads).expect("Cannot write to file");
+        let c_start = now();
+        let sketch: NormSketch = par_update(&norm_sketch, &updates, threads, |sketch, (key, delta)| sketch.insert(*key, *delta));
+        let c_stop = now();
+        writeln!(file, "NormSketch time: {}, norm: {}", c_stop - c_start, sketch.norm()).expect("Cannot write to file");
+        let c_start = now();
+        let sketch: CountSketch = par_update(&count_sketch, &updates, threads, |sketch, (key, delta)| sketch.insert(*key, *delta));
+        let c_stop = now();
+        writeln!(file, "Count Sketch time: {}, estimate of key 0: {}", c_stop - c_start, sketch.estimate(0)).expect("Cannot write to file");
+    }
+}
+
+// Longest chain for n random keys in n buckets with one and with two choices, and for keys
+// drawn from 1024-key ranges, which the hash functions should spread just as well
+fn benchmark_max_chain(test_sizes: &[i32]) {
+    let mut csv: CsvWriter<File> = make_csv_writer("max_chain");
+    let mut input_rng: StdRng = StdRng::seed_from_u64(input_seed());
+
+    for &test_size in test_sizes {
+        let input_size: usize = 2_i32.pow(test_size as u32) as usize;
+        let uniform: Vec<u64> = (0..input_size).map(|_| random_generator(1, u32::MAX) as u64).collect();
+        // Duplicates would count twice in the two-choice table
+        let mut clustered_keys: Vec<u64> = clustered(input_size.div_ceil(256), 1024, input_size, &mut input_rng);
+        clustered_keys.sort_unstable();
+        clustered_keys.dedup();
+        record_input(&format!("max_chain_clustered_2^{}", test_size), &clustered_keys);
+
+        for (suffix, input) in [("", uniform), ("_clustered", clustered_keys)] {
+            let mut one_choice: HwC = HwC::new(input_size);
+            let mut two_choice: TwoChoiceHwC = TwoChoiceHwC::new(input_size);
+            for x in &input {
+                one_choice.insert(*x, 1);
+                two_choice.insert(*x);
+            }
+            write_row(&mut csv, ResultRow::new(&format!("one_choice{}", suffix), input_size as u64, "construction").table_stats(&one_choice.stats()));
+            write_row(&mut csv, ResultRow::new(&format!("two_choice{}", suffix), input_size as u64, "construction").table_stats(&two_choice.stats()));
+        }
+    }
+}
+
+// Nearest neighbor queries on Gaussian clusters, comparing E2LSH indexes with a growing
+// number of tables against a brute-force scan. Recall is the fraction of queries whose
+// exact nearest neighbor is returned.
+fn benchmark_euclidean_lsh(points_len: usize, queries_len: usize, dimension: usize, table_counts: [usize; 4]) {
+    const CLUSTERS: usize = 100;
+    const K: usize = 8;
+    const WIDTH: f64 = 16.0;
+    let mut file: File = make_writable_file("euclidean_lsh");
+
+    let centers: Vec<Vec<f64>> = (0..CLUSTERS).map(|_| (0..dimension).map(|_| 10.0 * gaussian()).collect()).collect();
+    let sample = || -> Vec<f64> {
+        let center: &Vec<f64> = &centers[random_generator(0, CLUSTERS as u32) as usize];
+        center.iter().map(|x| x + gaussian()).collect()
+    };
+    let points: Vec<Vec<f64>> = (0..points_len).map(|_| sample()).collect();
+    let queries: Vec<Vec<f64>> = (0..queries_len).map(|_| sample()).collect();
+    let distance = |a: &[f64], b: &[f64]| -> f64 { a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum::<f64>() };
+
+    let c_start = now();
+    let exact: Vec<usize> = queries.iter().map(|query| {
+        (0..points.len()).min_by(|a, b| distance(&points[*a], query).total_cmp(&distance(&points[*b], query))).unwrap()
+    }).collect();
+    let c_stop = now();
+    writeln!(file, "Brute force query time: {}", c_stop - c_start).expect("Cannot write to file");
+
+    for tables in table_counts {
+        writeln!(file, "Tables: {}", tables).expect("Cannot write to file");
+        let mut index: EuclideanLshIndex = EuclideanLshIndex::new(dimension, tables, K, WIDTH);
+        points.iter().for_each(|point| { index.insert(point); });
+
+        let c_start = now();
+        let found: Vec<Option<usize>> = queries.iter().map(|query| index.nearest(query)).collect();
+        let c_stop = now();
+        writeln!(file, "Query time: {}", c_stop - c_start).expect("Cannot write to file");
+        let hits: usize = found.iter().zip(&exact).filter(|(found, exact)| **found == Some(**exact)).count();
+        writeln!(file, "Recall: {}", hits as f64 / queries_len as f64).expect("Cannot write to file");
+    }
+}
+
+// Distortion of the pairwise distances of random Gaussian points under JL projections to k
+// dimensions, as the largest deviation of the distance ratio from 1 and the mean deviation
+fn benchmark_jl(points_len: usize, dimension: usize, targets: [usize; 4]) {
+    let mut file: File = make_writable_file("jl");
+
+    let points: Vec<Vec<f64>> = (0..points_len).map(|_| (0..dimension).map(|_| gaussian()).collect()).collect();
+    let distance = |a: &[f64], b: &[f64]| -> f64 { a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum::<f64>().sqrt() };
+    for entries in [ProjectionEntries::Gaussian, ProjectionEntries::Sign] {
+        writeln!(file, "Entries: {:?}", entries).expect("Cannot write to file");
+        for k in targets {
+            let projection: JlProjection = JlProjection::new(dimension, k, entries);
+            let c_start = now();
+            let projected: Vec<Vec<f64>> = points.iter().map(|point| projection.project(point)).collect();
+            let c_stop = now();
+            let (mut max_distortion, mut total_distortion, mut pairs) = (0.0, 0.0, 0);
+            for i in 0..points_len {
+                for j in 0..i {
+                    let distortion: f64 = (distance(&projected[i], &projected[j]) / distance(&points[i], &points[j]) - 1.0).abs();
+                    max_distortion = f64::max(max_distortion, distortion);
+                    total_distortion += distortion;
+                    pairs += 1;
+                }
+            }
+            writeln!(file, "k: {}, max distortion: {}, mean distortion: {}, projection time: {}",
+                k, max_distortion, total_distortion / pairs as f64, c_stop - c_start).expect("Cannot write to file");
+        }
+    }
+}
+
+// Distortion of the pairwise distances of sparse vectors with `nonzeros` random features out of
+// 2^40 under feature hashing into k buckets, where a dense projection would not fit in memory
+fn benchmark_feature_hashing(points_len: usize, nonzeros: usize, targets: [usize; 4]) {
+    let mut file: File = make_writable_file("feature_hashing");
+
+    let mut rng = thread_rng();
+    let points: Vec<Vec<(u64, f64)>> = (0..points_len)
+        .map(|_| (0..nonzeros).map(|_| (rng.gen_range(0..1 << 40), gaussian())).collect())
+        .collect();
+    let distance = |a: &[f64], b: &[f64]| -> f64 { a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum::<f64>().sqrt() };
+    // Features are distinct with high probability, so the exact distance adds up both sides
+    let sparse_distance = |a: &[(u64, f64)], b: &[(u64, f64)]| -> f64 {
+        a.iter().chain(b).map(|(_, value)| value * value).sum::<f64>().sqrt()
+    };
+    for k in targets {
+        let hashing: FeatureHashing = FeatureHashing::new(k);
+        let c_start = now();
+        let compressed: Vec<Vec<f64>> = points.iter().map(|point| hashing.transform(point)).collect();
+        let c_stop = now();
+        let (mut max_distortion, mut total_distortion, mut pairs) = (0.0, 0.0, 0);
+        for i in 0..points_len {
+            for j in 0..i {
+                let distortion: f64 = (distance(&compressed[i], &compressed[j]) / sparse_distance(&points[i], &points[j]) - 1.0).abs();
+                max_distortion = f64::max(max_distortion, distortion);
+                total_distortion += distortion;
+                pairs += 1;
+            }
+        }
+        writeln!(file, "k: {}, max distortion: {}, mean distortion: {}, transform time: {}",
+            k, max_distortion, total_distortion / pairs as f64, c_stop - c_start).expect("Cannot write to file");
+    }
+}
+
+// Two cliques on n / 2 vertices joined by two edges, whose only minimum cut separates the cliques
+fn barbell_graph(vertices: usize) -> Graph {
+    let half: usize = vertices / 2;
+    let mut graph: Graph = Graph::new(2 * half);
+    for offset in [0, half] {
+        for u in 0..half {
+            for v in u + 1..half {
+                graph.add_edge(offset + u, offset + v);
+            }
+        }
+    }
+    graph.add_edge(0, half);
+    graph.add_edge(1, half + 1);
+    return graph;
+}
+
+// Fraction of single contraction runs that find the minimum cut next to the 2 / (n (n - 1))
+// lower bound, and the time and result of the full n^2 log n trials
+fn benchmark_karger(vertex_counts: [usize; 4], trials: usize) {
+    let mut file: File = make_writable_file("karger");
+    let mut rng = thread_rng();
+
+    for vertices in vertex_counts {
+        let graph: Graph = barbell_graph(vertices);
+        let successes: usize = (0..trials).filter(|_| graph.karger_trial(&mut rng).size == 2).count();
+        let n: f64 = vertices as f64;
+        writeln!(file, "Vertices: {}, edges: {}", vertices, graph.edges().len()).expect("Cannot write to file");
+        writeln!(file, "Single trial success rate: {}, lower bound: {}", successes as f64 / trials as f64, 2.0 / (n * (n - 1.0)))
+            .expect("Cannot write to file");
+
+        let start = now();
+        let cut: Cut = graph.karger_min_cut();
+        let stop = now();
+        writeln!(file, "Minimum cut: {}, time: {}", cut.size, stop - start).expect("Cannot write to file");
+    }
+}
+
+// Seed of the generated graphs and skewed streams, so every run of an experiment sees the
+// same inputs. --seed replaces the default.
+const DEFAULT_SEED: u64 = 2024;
+static INPUT_SEED: OnceLock<u64> = OnceLock::new();
+
+fn input_seed() -> u64 {
+    return *INPUT_SEED.get().unwrap_or(&DEFAULT_SEED);
+}
+
+// Whether --record asked for the generated streams to be written out
+static RECORD_INPUTS: OnceLock<bool> = OnceLock::new();
+
+// With --record, writes a generated stream to <output-dir>/<name>_seed_<seed>.updates as
+// binary updates of 1, so the stream command can replay it into any structure when a run
+// turns up something odd. The name tells the generator and its parameters apart.
+fn record_input(name: &str, keys: &[u64]) {
+    if !RECORD_INPUTS.get().copied().unwrap_or(false) {
+        return;
+    }
+    let directory: &Path = OUTPUT_DIR.get().map_or(Path::new("."), |path| path.as_path());
+    let file: File = File::create(directory.join(format!("{}_seed_{}.updates", name, input_seed()))).expect("Cannot create file");
+    let mut writer: UpdateWriter<BufWriter<File>> = UpdateWriter::new(BufWriter::new(file), UpdateFormat::Binary);
+    keys.iter().for_each(|key| writer.write(*key, 1).expect("Cannot write to file"));
+    writer.into_inner().flush().expect("Cannot write to file");
+}
+
+// Karger against Karger-Stein on random 4-regular graphs: the time of one run of each and
+// how often it finds the minimum cut, taken as the best cut any run found
+fn benchmark_min_cut(vertex_counts: [usize; 4], runs: usize) {
+    let mut file: File = make_writable_file("min_cut");
+    let mut rng = thread_rng();
+    let mut graph_rng: StdRng = StdRng::seed_from_u64(input_seed());
+
+    for vertices in vertex_counts {
+        let graph: Graph = Graph::random_regular(vertices, 4, &mut graph_rng);
+
+        let k_start = now();
+        let karger: Vec<usize> = (0..runs).map(|_| graph.karger_trial(&mut rng).size).collect();
+        let k_stop = now();
+        let ks_start = now();
+        let karger_stein: Vec<usize> = (0..runs).map(|_| graph.karger_stein_trial(&mut rng).size).collect();
+        let ks_stop = now();
+
+        let best: usize = *karger.iter().chain(&karger_stein).min().unwrap();
+        let success_rate = |sizes: &[usize]| sizes.iter().filter(|size| **size == best).count() as f64 / runs as f64;
+        writeln!(file, "Vertices: {}, edges: {}, minimum cut: {}", vertices, graph.edges().len(), best).expect("Cannot write to file");
+        writeln!(file, "Karger: {} per run, success rate: {}", (k_stop - k_start) / runs as f64, success_rate(&karger))
+            .expect("Cannot write to file");
+        writeln!(file, "Karger-Stein: {} per run, success rate: {}", (ks_stop - ks_start) / runs as f64, success_rate(&karger_stein))
+            .expect("Cannot write to file");
+    }
+}
+
+// Average rho steps to split p q with p of the given bits and q of 62 - bits bits, against
+// the sqrt(pi p / 2) steps a random mapping modulo p takes to repeat
+fn benchmark_pollard_rho(factor_bits: [u32; 4], samples: usize) {
+    let mut file: File = make_writable_file("pollard_rho");
+    let mut rng = thread_rng();
+
+    for bits in factor_bits {
+        let mut steps: u64 = 0;
+        let mut expected: f64 = 0.0;
+        let start = now();
+        for _ in 0..samples {
+            let p: u64 = random_prime(bits, &mut rng);
+            let q: u64 = random_prime(62 - bits, &mut rng);
+            steps += pollard_rho(p * q, &mut rng).1;
+            expected += (std::f64::consts::PI * p.min(q) as f64 / 2.0).sqrt();
+        }
+        let stop = now();
+        writeln!(file, "Factor bits: {}, average steps: {}, sqrt(pi p / 2): {}, time per factorization: {}",
+            bits, steps as f64 / samples as f64, expected / samples as f64, (stop - start) / samples as f64)
+            .expect("Cannot write to file");
+    }
+}
+
+// Freivalds' check against computing the product, and how often 1 to 4 rounds detect a
+// single wrong entry and a +1/-1 pair of wrong entries in one row, which can cancel
+fn benchmark_freivalds(sizes: [usize; 3], trials: usize) {
+    let mut file: File = make_writable_file("freivalds");
+    let mut rng = thread_rng();
+
+    for n in sizes {
+        let a: Vec<Vec<i64>> = (0..n).map(|_| (0..n).map(|_| rng.gen_range(-1000..1000)).collect()).collect();
+        let b: Vec<Vec<i64>> = (0..n).map(|_| (0..n).map(|_| rng.gen_range(-1000..1000)).collect()).collect();
+        let m_start = now();
+        let mut c: Vec<Vec<i64>> = multiply(&a, &b);
+        let m_stop = now();
+        let v_start = now();
+        let verified: bool = freivalds_verify(&a, &b, &c, 1);
+        let v_stop = now();
+        writeln!(file, "Size: {}, multiplication: {}, one round: {}, verified: {}", n, m_stop - m_start, v_stop - v_start, verified)
+            .expect("Cannot write to file");
+
+        for rounds in 1..=4 {
+            let mut single: usize = 0;
+            let mut pair: usize = 0;
+            for _ in 0..trials {
+                let (i, j) = (rng.gen_range(0..n), rng.gen_range(0..n));
+                let k: usize = (j + rng.gen_range(1..n)) % n;
+                c[i][j] += 1;
+                single += !freivalds_verify(&a, &b, &c, rounds) as usize;
+                c[i][k] -= 1;
+                pair += !freivalds_verify(&a, &b, &c, rounds) as usize;
+                c[i][k] += 1;
+                c[i][j] -= 1;
+            }
+            writeln!(file, "Rounds: {}, single entry detected: {}, pair detected: {}, bound: {}",
+                rounds, single as f64 / trials as f64, pair as f64 / trials as f64, 1.0 - 0.5f64.powi(rounds as i32))
+                .expect("Cannot write to file");
+        }
+    }
+}
+
+// Inputs of n keys in the orders that hurt naive pivot choices: random, sorted, reversed,
+// few distinct values and organ pipe (ascending, then descending)
+fn ordered_input(distribution: &str, n: usize) -> Vec<u64> {
+    let mut rng = thread_rng();
+    return match distribution {
+        "random" => (0..n).map(|_| rng.gen()).collect(),
+        "sorted" => (0..n as u64).collect(),
+        "reversed" => (0..n as u64).rev().collect(),
+        "few_distinct" => (0..n).map(|_| rng.gen_range(0..16)).collect(),
+        "organ_pipe" => (0..n as u64).map(|i| i.min(n as u64 - i)).collect(),
+        _ => panic!("Unknown distribution {}", distribution),
+    };
+}
+
+// Median by quickselect, introselect, select_nth_unstable and a full sort
+fn benchmark_selection(test_sizes: &[i32], distributions: [&str; 5]) {
+    let mut file: File = make_writable_file("selection");
+
+    for distribution in distributions {
+        for &test_size in test_sizes {
+            let n: usize = 1 << test_size;
+            let input: Vec<u64> = ordered_input(distribution, n);
+            writeln!(file, "Distribution: {}, test size: {}", distribution, test_size).expect("Cannot write to file");
+
+            let mut v: Vec<u64> = input.clone();
+            let start = now();
+            let median: u64 = *randomized_select(&mut v, n / 2);
+            let stop = now();
+            writeln!(file, "Randomized select: {}", stop - start).expect("Cannot write to file");
+
+            let mut v: Vec<u64> = input.clone();
+            let start = now();
+            assert_eq!(*introselect(&mut v, n / 2), median);
+            let stop = now();
+            writeln!(file, "Introselect: {}", stop - start).expect("Cannot write to file");
+
+            let mut v: Vec<u64> = input.clone();
+            let start = now();
+            assert_eq!(*v.select_nth_unstable(n / 2).1, median);
+            let stop = now();
+            writeln!(file, "select_nth_unstable: {}", stop - start).expect("Cannot write to file");
+
+            let mut v: Vec<u64> = input.clone();
+            let start = now();
+            v.sort_unstable();
+            assert_eq!(v[n / 2], median);
+            let stop = now();
+            writeln!(file, "Sorting: {}", stop - start).expect("Cannot write to file");
+        }
+    }
+}
+
+// Comparisons made through CountedKey since the last reset
+static COMPARISONS: AtomicUsize = AtomicUsize::new(0);
+
+// Key that counts how often it is compared
+#[derive(PartialEq, Eq)]
+struct CountedKey(u64);
+
+impl PartialOrd for CountedKey {
+    fn partial_cmp(&self, other: &CountedKey) -> Option<std::cmp::Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl Ord for CountedKey {
+    fn cmp(&self, other: &CountedKey) -> std::cmp::Ordering {
+        COMPARISONS.fetch_add(1, AtomicOrdering::Relaxed);
+        return self.0.cmp(&other.0);
+    }
+}
+
+// Comparisons and time of quicksort with every pivot strategy on every input order, against
+// the 1.39 n log2 n comparisons random pivots take in expectation on any input
+fn benchmark_quicksort(test_sizes: [u32; 3], distributions: [&str; 5]) {
+    let mut file: File = make_writable_file("quicksort");
+    let strategies: [PivotStrategy; 3] = [PivotStrategy::First, PivotStrategy::Random, PivotStrategy::MedianOfThreeRandom];
+
+    for distribution in distributions {
+        for test_size in test_sizes {
+            let n: usize = 1 << test_size;
+            let input: Vec<u64> = ordered_input(distribution, n);
+            writeln!(file, "Distribution: {}, test size: {}, 1.39 n log2 n: {}", distribution, test_size, 1.39 * (n * test_size as usize) as f64)
+                .expect("Cannot write to file");
+            for strategy in strategies {
+                let mut keys: Vec<CountedKey> = input.iter().map(|x| CountedKey(*x)).collect();
+                COMPARISONS.store(0, AtomicOrdering::Relaxed);
+                let start = now();
+                quicksort(&mut keys, strategy);
+                let stop = now();
+                writeln!(file, "{:?}: {} comparisons, {}", strategy, COMPARISONS.load(AtomicOrdering::Relaxed), stop - start)
+                    .expect("Cannot write to file");
+            }
+        }
+    }
+}
+
+// The nodes with the k highest ranks
+fn top_nodes(rank: &[f64], k: usize) -> Vec<usize> {
+    let mut nodes: Vec<usize> = (0..rank.len()).collect();
+    nodes.sort_by(|a, b| rank[*b].total_cmp(&rank[*a]));
+    nodes.truncate(k);
+    return nodes;
+}
+
+// Monte Carlo PageRank against 100 rounds of power iteration on preferential attachment
+// graphs of the given average degree, with every edge a link both ways: L1 error, how many
+// of the top 10 nodes it gets right, and the running times
+fn benchmark_pagerank(node_counts: [usize; 3], average_degree: usize, walks_per_node: [usize; 4]) {
+    let mut file: File = make_writable_file("pagerank");
+    let mut graph_rng: StdRng = StdRng::seed_from_u64(input_seed());
+
+    for n in node_counts {
+        let links: Vec<Vec<usize>> = Graph::preferential_attachment(n, average_degree / 2, &mut graph_rng).adjacency();
+        let p_start = now();
+        let exact: Vec<f64> = pagerank_power_iteration(&links, 0.85, 100);
+        let p_stop = now();
+        writeln!(file, "Nodes: {}, power iteration: {}", n, p_stop - p_start).expect("Cannot write to file");
+        let top: Vec<usize> = top_nodes(&exact, 10);
+
+        for walks in walks_per_node {
+            let m_start = now();
+            let estimate: Vec<f64> = pagerank_monte_carlo(&links, 0.85, walks);
+            let m_stop = now();
+            let l1: f64 = exact.iter().zip(&estimate).map(|(a, b)| (a - b).abs()).sum();
+            let top_hits: usize = top_nodes(&estimate, 10).iter().filter(|node| top.contains(node)).count();
+            writeln!(file, "Walks per node: {}, L1 error: {}, top 10 overlap: {}, time: {}", walks, l1, top_hits, m_stop - m_start)
+                .expect("Cannot write to file");
+        }
+    }
+}
+
+// Schöning on random 3-SAT formulas around the threshold of 4.26 clauses per variable: how
+// many it solves within max_tries, and the mean tries and flips and the time of those
+fn benchmark_schoening(variable_counts: [usize; 4], ratios: [f64; 5], instances: usize, max_tries: usize) {
+    let mut file: File = make_writable_file("schoening");
+    let mut rng = thread_rng();
+
+    for variables in variable_counts {
+        for ratio in ratios {
+            let clauses: usize = (ratio * variables as f64).round() as usize;
+            let (mut solved, mut tries, mut flips): (usize, usize, usize) = (0, 0, 0);
+            let start = now();
+            for _ in 0..instances {
+                let formula: Formula = Formula::random_k_sat(variables, clauses, 3, &mut rng);
+                let search: Search = schoening(&formula, max_tries, &mut rng);
+                if search.assignment.is_some() {
+                    solved += 1;
+                    tries += search.tries;
+                    flips += search.flips;
+                }
+            }
+            let stop = now();
+            writeln!(file, "Variables: {}, clauses per variable: {}, solved: {}/{}, mean tries: {}, mean flips: {}, time: {}",
+                     variables, ratio, solved, instances, tries as f64 / solved.max(1) as f64, flips as f64 / solved.max(1) as f64, stop - start)
+                .expect("Cannot write to file");
+        }
+    }
+}
+
+// Randomized rounding against the exact optimum on small random instances: the mean and
+// worst ratio over trials roundings of every instance, for set cover from the LP optimum
+// and for routing from a multiplicative weights fractional routing
+fn benchmark_rounding(instances: usize, trials: usize) {
+    let mut file: File = make_writable_file("rounding");
+    let mut rng = thread_rng();
+
+    for universe in [20, 40, 60] {
+        let (mut mean, mut worst, mut integrality_gap): (f64, f64, f64) = (0.0, 0.0, 0.0);
+        for _ in 0..instances {
+            let cover: SetCover = SetCover::random(universe, 16, 0.1, &mut rng);
+            let fractional: Vec<f64> = fractional_set_cover(&cover);
+            let exact: f64 = cover.cost(&exact_set_cover(&cover));
+            integrality_gap += exact / cover.fractional_cost(&fractional) / instances as f64;
+            for _ in 0..trials {
+                let ratio: f64 = cover.cost(&round_set_cover(&cover, &fractional, &mut rng)) / exact;
+                mean += ratio / (instances * trials) as f64;
+                worst = worst.max(ratio);
+            }
+        }
+        writeln!(file, "Set cover, elements: {}, mean ratio: {}, worst ratio: {}, mean integrality gap: {}", universe, mean, worst, integrality_gap)
+            .expect("Cannot write to file");
+    }
+    for commodities in [4, 6, 8] {
+        let (mut mean, mut worst): (f64, f64) = (0.0, 0.0);
+        for _ in 0..instances {
+            let routing: Routing = Routing::random(4 * commodities, commodities, 4, 5, &mut rng);
+            let fractional: Vec<Vec<f64>> = fractional_routing(&routing, 1000, 0.1);
+            let exact: f64 = routing.congestion(&exact_routing(&routing)) as f64;
+            for _ in 0..trials {
+                let ratio: f64 = routing.congestion(&round_routing(&routing, &fractional, &mut rng)) as f64 / exact;
+                mean += ratio / (instances * trials) as f64;
+                worst = worst.max(ratio);
+            }
+        }
+        writeln!(file, "Routing, commodities: {}, mean ratio: {}, worst ratio: {}", commodities, mean, worst).expect("Cannot write to file");
+    }
+}
+
+// Max-Cut on seeded G(n, p) graphs with average degree 10: the relaxation value of the local
+// embedding, the expected and best hyperplane cut over trials, and the best of as many
+// random coin flip cuts
+fn benchmark_max_cut(vertex_counts: [usize; 4], trials: usize) {
+    let mut file: File = make_writable_file("max_cut");
+    let mut rng = thread_rng();
+    let mut graph_rng: StdRng = StdRng::seed_from_u64(input_seed());
+
+    for n in vertex_counts {
+        let graph: Graph = Graph::erdos_renyi(n, 10.0 / n as f64, &mut graph_rng);
+        let dimension: usize = ((2 * n) as f64).sqrt().ceil() as usize;
+        let start = now();
+        let embedding: Vec<Vec<f64>> = local_embedding(&graph, dimension, 100);
+        let best: Cut = goemans_williamson(&graph, &embedding, trials);
+        let stop = now();
+        let coin: usize = (0..trials).map(|_| random_cut(&graph, &mut rng).size).max().unwrap_or(0);
+        writeln!(file, "Vertices: {}, edges: {}, relaxation: {}, expected hyperplane cut: {}, best hyperplane cut: {}, best random cut: {}, time: {}",
+                 n, graph.edges().len(), relaxation_value(&graph, &embedding), expected_hyperplane_cut(&graph, &embedding), best.size, coin, stop - start)
+            .expect("Cannot write to file");
+    }
+}
+
+// Time of samples Zipf(1) draws over 2^size keys with the alias method and with a binary
+// search of the cumulative weights, which falls behind once the weights leave the cache
+fn benchmark_alias(test_sizes: &[i32], samples: usize) {
+    let mut file: File = make_writable_file("alias");
+    let mut rng = thread_rng();
+
+    for &size in test_sizes {
+        let weights: Vec<f64> = (1..=1usize << size).map(|k| 1.0 / k as f64).collect();
+        let b_start = now();
+        let table: AliasTable = AliasTable::new(&weights);
+        let b_stop = now();
+        let mut checksum: usize = 0;
+        let a_start = now();
+        (0..samples).for_each(|_| checksum += table.sample(&mut rng));
+        let a_stop = now();
+
+        let cumulative: Vec<f64> = weights.iter().scan(0.0, |total, w| { *total += w; Some(*total) }).collect();
+        let total: f64 = cumulative[cumulative.len() - 1];
+        let c_start = now();
+        (0..samples).for_each(|_| {
+            let u: f64 = rng.gen::<f64>() * total;
+            checksum += cumulative.partition_point(|c| *c < u);
+        });
+        let c_stop = now();
+        black_box(checksum);
+        writeln!(file, "Keys: 2^{}, alias build: {}, alias sampling: {}, binary search sampling: {}", size, b_stop - b_start, a_stop - a_start, c_stop - c_start)
+            .expect("Cannot write to file");
+    }
+}
+
+// Distribution of the maximum load over trials for n balls in n bins and for 16 n balls,
+// under one, two and three choices
+fn benchmark_balls_into_bins(test_sizes: &[i32], trials: usize) {
+    let mut file: File = make_writable_file("balls_into_bins");
+
+    for &size in test_sizes {
+        let bins: usize = 1 << size;
+        for balls_per_bin in [1, 16] {
+            for policy in [Policy::OneChoice, Policy::TwoChoice, Policy::DChoice(3)] {
+                let start = now();
+                let histogram: Vec<usize> = max_load_distribution((balls_per_bin * bins) as u64, bins, policy, trials);
+                let stop = now();
+                let distribution: String = histogram.iter().enumerate()
+                    .filter(|(_, count)| **count > 0)
+                    .map(|(load, count)| format!("{}: {}", load, count))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                writeln!(file, "Bins: 2^{}, balls per bin: {}, {:?}, max loads: [{}], time: {}", size, balls_per_bin, policy, distribution, stop - start)
+                    .expect("Cannot write to file");
+            }
+        }
+    }
+}
+
+// Draws to collect n coupons once, twice and three times over trials collections: the mean
+// with its standard error against the exact expectation, and for one copy how often it
+// takes more than n ln n + c n draws against the limit 1 - e^(-e^-c)
+fn benchmark_coupon_collector(coupon_counts: [usize; 4], trials: usize) {
+    let mut file: File = make_writable_file("coupon_collector");
+    let mut rng = thread_rng();
+
+    for coupons in coupon_counts {
+        for copies in [1, 2, 3] {
+            let estimate: Estimate = CouponCollector { coupons, copies }.estimate(trials);
+            writeln!(file, "Coupons: {}, copies: {}, mean draws: {} +- {}, expected: {}",
+                     coupons, copies, estimate.mean, estimate.standard_error, expected_draws_with_copies(coupons, copies))
+                .expect("Cannot write to file");
+        }
+        let draws: Vec<usize> = (0..trials).map(|_| draws_to_collect(coupons, 1, &mut rng)).collect();
+        for c in [-1.0, 0.0, 1.0, 2.0, 4.0] {
+            let threshold: f64 = coupons as f64 * ((coupons as f64).ln() + c);
+            let over: usize = draws.iter().filter(|d| **d as f64 > threshold).count();
+            writeln!(file, "Coupons: {}, c: {}, fraction over n ln n + c n: {}, limit: {}", coupons, c, over as f64 / trials as f64, tail_probability(c))
+                .expect("Cannot write to file");
+        }
+    }
+}
+
+// Birthday attacks on the crate's hash functions cut to l bit values: the mean keys to the
+// first collision over trials searches against the sqrt(pi 2^l / 2) expectation, for
+// multiply-shift and for 2- and 4-wise independent polynomials
+fn benchmark_birthday(hash_lens: [u32; 4], trials: usize) {
+    let mut file: File = make_writable_file("birthday");
+    let mut rng = thread_rng();
+
+    for hash_len in hash_lens {
+        let max_keys: usize = 1 << (hash_len / 2 + 6);
+        let mut means: Vec<f64> = Vec::new();
+        for family in ["multiply_shift", "polynomial_2", "polynomial_4"] {
+            let mut keys: usize = 0;
+            for _ in 0..trials {
+                let collision: Option<Collision> = match family {
+                    "multiply_shift" => {
+                        let h: SeededHash = SeededHash::new(hash_len);
+                        find_collision(|x| h.hash(x) as u64, max_keys, &mut rng)
+                    }
+                    _ => {
+                        let h: IndependentHash = IndependentHash::new(if family == "polynomial_2" { 2 } else { 4 });
+                        find_collision(|x| h.hash(x) >> (61 - hash_len), max_keys, &mut rng)
+                    }
+                };
+                keys += collision.map_or(max_keys, |c| c.keys);
+            }
+            means.push(keys as f64 / trials as f64);
+        }
+        writeln!(file, "Hash length: {}, expected: {}, multiply-shift: {}, 2-wise polynomial: {}, 4-wise polynomial: {}",
+                 hash_len, expected_keys_to_collision((1u64 << hash_len) as f64), means[0], means[1], means[2])
+            .expect("Cannot write to file");
+    }
+}
+
+// Nanoseconds per key of runs passes over the same keys for multiply-shift and the 4-wise
+// polynomial hash, with bootstrap intervals for the mean of each and the p-value of a
+// permutation test for the difference, so a gap is only read as real when it is significant
+fn benchmark_hash_timing(test_sizes: &[i32], runs: usize) {
+    let mut file: File = make_writable_file("hash_timing");
+    let mut rng = thread_rng();
+
+    for &size in test_sizes {
+        let keys: Vec<u64> = (0..1u64 << size).map(|_| rng.gen()).collect();
+        let multiply_shift: SeededHash = SeededHash::new(size as u32);
+        let polynomial: IndependentHash = IndependentHash::new(4);
+        // The hashes are summed and the keys and the sum go through black_box, so no pass can be
+        // hoisted out of the loop or dropped as unused
+        let mut checksum: u64 = 0;
+        let mut time_pass = |hash: &dyn Fn(u64) -> u64| -> f64 {
+            let start = now();
+            keys.iter().for_each(|x| checksum = checksum.wrapping_add(hash(black_box(*x))));
+            let stop = now();
+            black_box(checksum);
+            return (stop - start).whole_nanoseconds() as f64 / keys.len() as f64;
+        };
+        // An unrecorded pass of each first, so neither is timed with cold caches
+        time_pass(&|x| multiply_shift.hash(x) as u64);
+        time_pass(&|x| polynomial.hash(x));
+        // Interleaved so drifts in the machine's speed hit both samples alike
+        let mut ms_samples: Vec<f64> = Vec::new();
+        let mut poly_samples: Vec<f64> = Vec::new();
+        for _ in 0..runs {
+            ms_samples.push(time_pass(&|x| multiply_shift.hash(x) as u64));
+            poly_samples.push(time_pass(&|x| polynomial.hash(x)));
+        }
+        let ms_interval: (f64, f64) = bootstrap_interval(&ms_samples, mean, 10_000, 0.95, &mut rng);
+        let poly_interval: (f64, f64) = bootstrap_interval(&poly_samples, mean, 10_000, 0.95, &mut rng);
+        let p_value: f64 = permutation_test(&ms_samples, &poly_samples, 10_000, &mut rng);
+        writeln!(file, "Keys: 2^{}, multiply-shift ns/key: {} {:?}, 4-wise polynomial ns/key: {} {:?}, p-value: {}",
+                 size, mean(&ms_samples), ms_interval, mean(&poly_samples), poly_interval, p_value)
+            .expect("Cannot write to file");
+    }
+}
+
+// Relative Frobenius error and time of sampled products of n x n matrices with Gaussian
+// entries scaled per column, against the bound and the exact product
+fn benchmark_matrix_sampling(sizes: [usize; 3], samples: [usize; 4], trials: usize) {
+    let mut file: File = make_writable_file("matrix_sampling");
+    let mut rng = thread_rng();
+
+    for n in sizes {
+        let a: Vec<Vec<f64>> = (0..n).map(|_| (0..n).map(|k| gaussian() * (1.0 + (k % 16) as f64)).collect()).collect();
+        let b: Vec<Vec<f64>> = (0..n).map(|_| (0..n).map(|_| gaussian()).collect()).collect();
+        let e_start = now();
+        let exact: Vec<Vec<f64>> = exact_product(&a, &b);
+        let e_stop = now();
+        let norm: f64 = frobenius_norm(&exact);
+        writeln!(file, "Size: {}, exact product: {}", n, e_stop - e_start).expect("Cannot write to file");
+        for s in samples {
+            let mut error: f64 = 0.0;
+            let s_start = now();
+            for _ in 0..trials {
+                error += frobenius_error(&approximate_product(&a, &b, s, &mut rng), &exact) / norm;
+            }
+            let s_stop = now();
+            writeln!(file, "Samples: {}, relative error: {}, bound: {}, time per product: {}",
+                     s, error / trials as f64, error_bound(&a, &b, s) / norm, (s_stop - s_start) / trials as f64)
+                .expect("Cannot write to file");
+        }
+    }
+}
+
+// Rank 10 approximations of m x m/2 matrices with a polynomially and an exponentially
+// decaying spectrum: Frobenius error of the randomized SVD relative to the optimal one from
+// the exact SVD, and the time of both, for 0 to 3 power iterations
+fn benchmark_low_rank(sizes: [usize; 3], rank: usize, oversampling: usize) {
+    let mut file: File = make_writable_file("low_rank");
+    let mut rng = thread_rng();
+
+    for m in sizes {
+        let n: usize = m / 2;
+        for (decay, spectrum) in [
+            ("polynomial", (1..=n).map(|i| 1.0 / i as f64).collect::<Vec<f64>>()),
+            ("exponential", (0..n).map(|i| 0.8f64.powi(i as i32)).collect::<Vec<f64>>()),
+        ] {
+            let a: Vec<Vec<f64>> = with_singular_values(m, n, &spectrum);
+            let e_start = now();
+            let exact_values: Vec<f64> = exact_svd(&a).singular_values;
+            let e_stop = now();
+            let optimal: f64 = optimal_error(&exact_values, rank);
+            writeln!(file, "Size: {}x{}, decay: {}, exact SVD: {}, optimal error: {}", m, n, decay, e_stop - e_start, optimal)
+                .expect("Cannot write to file");
+            for power_iterations in 0..=3 {
+                let r_start = now();
+                let approximation: Vec<Vec<f64>> = randomized_svd(&a, rank, oversampling, power_iterations, &mut rng).reconstruct();
+                let r_stop = now();
+                writeln!(file, "Power iterations: {}, error ratio: {}, time: {}",
+                         power_iterations, frobenius_error(&approximation, &a) / optimal, r_stop - r_start)
+                    .expect("Cannot write to file");
+            }
+        }
+    }
+}
+
+// Max-Cut by simulated annealing from a random cut on the same graphs as benchmark_max_cut,
+// next to the best hyperplane cut, under each schedule. The annealing runs are seeded, and
+// the energy of every run is traced at 100 points along the way.
+fn benchmark_annealing(vertex_counts: [usize; 4], steps_per_vertex: usize) {
+    let mut file: File = make_writable_file("annealing");
+    let mut trace_file: File = make_writable_file("annealing_trace");
+    let mut graph_rng: StdRng = StdRng::seed_from_u64(input_seed());
+
+    for n in vertex_counts {
+        let graph: Graph = Graph::erdos_renyi(n, 10.0 / n as f64, &mut graph_rng);
+        let adjacency: Vec<Vec<usize>> = graph.adjacency();
+        let dimension: usize = ((2 * n) as f64).sqrt().ceil() as usize;
+        let hyperplane: Cut = goemans_williamson(&graph, &local_embedding(&graph, dimension, 100), 100);
+        let steps: usize = steps_per_vertex * n;
+        for schedule in [Schedule::Geometric { start: 2.0, end: 0.01 }, Schedule::Linear { start: 2.0 }, Schedule::Logarithmic { c: 2.0 }] {
+            let mut rng: StdRng = StdRng::seed_from_u64(input_seed());
+            let start_cut: Cut = random_cut(&graph, &mut rng);
+            let start = now();
+            let annealed: Annealed<CutState> = anneal(CutState::new(&adjacency, start_cut.side), schedule, steps, &mut rng, |step, temperature, energy| {
+                if step % (steps / 100).max(1) == 0 {
+                    writeln!(trace_file, "Vertices: {}, schedule: {:?}, step: {}, temperature: {}, cut: {}", n, schedule, step, temperature, -energy)
+                        .expect("Cannot write to file");
+                }
+            });
+            let stop = now();
+            writeln!(file, "Vertices: {}, edges: {}, schedule: {:?}, random cut: {}, annealed cut: {}, best hyperplane cut: {}, accepted: {}, time: {}",
+                     n, graph.edges().len(), schedule, start_cut.size, annealed.best.cut().size, hyperplane.size, annealed.accepted, stop - start)
+                .expect("Cannot write to file");
+        }
+    }
+}
+
+// Time of samples normal and exponential draws by the ziggurat against Box-Muller and
+// inversion, with the sample means and variances as a sanity check
+fn benchmark_distributions(samples: usize) {
+    let mut file: File = make_writable_file("distributions");
+    let mut rng = thread_rng();
+
+    let mut sampled: Vec<f64> = vec![0.0; samples];
+    let mut run = |name: &str, draw: &mut dyn FnMut() -> f64| {
+        let start = now();
+        sampled.iter_mut().for_each(|x| *x = draw());
+        let stop = now();
+        let mean: f64 = sampled.iter().sum::<f64>() / samples as f64;
+        let variance: f64 = sampled.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / samples as f64;
+        writeln!(file, "Sampler: {}, samples: {}, time: {}, mean: {}, variance: {}", name, samples, stop - start, mean, variance)
+            .expect("Cannot write to file");
+    };
+    run("ziggurat normal", &mut || standard_normal(&mut rng));
+    run("box-muller normal", &mut || box_muller(&mut rng).0);
+    run("ziggurat exponential", &mut || standard_exponential(&mut rng));
+    run("inversion exponential", &mut || -(1.0 - rng.gen::<f64>()).ln());
+}
+
+// Sketches against the exact counts of HwC on the same Zipfian streams, one stream per
+// seed from the input seed on. Every seed builds fresh sketches, so the errors pooled over
+// the seeds include the luck of their hash functions. Point query errors are relative to
+// the stream length, the others to the exact value.
+fn benchmark_accuracy(stream_len: usize, keys: usize, exponent: f64, seeds: usize) {
+    let mut csv: CsvWriter<File> = make_csv_writer("accuracy");
+    let builder: SketchBuilder = SketchBuilder::new(0.05, 0.05);
+    let names: [&str; 5] = ["count_min", "count_sketch", "norm_sketch", "count_sketch_f2", "hyperloglog"];
+    let mut errors: [Vec<f64>; 5] = Default::default();
+    let mut spaces: [usize; 5] = [0; 5];
+
+    for seed in 0..seeds as u64 {
+        let stream: Vec<u64> = zipf(keys, exponent, stream_len, &mut StdRng::seed_from_u64(input_seed() + seed));
+        record_input(&format!("accuracy_zipf_{}_run_{}", exponent, seed), &stream);
+        let mut exact: HwC = HwC::new(keys);
+        let mut count_min: CountMin = builder.count_min(UpdatePolicy::Standard);
+        let mut count_sketch: CountSketch = builder.count_sketch();
+        let mut norm_sketch: NormSketch = builder.norm_sketch();
+        let mut hll: HyperLogLog = HyperLogLog::new(12);
+        for x in &stream {
+            exact.insert(*x, 1);
+            count_min.insert(*x, 1);
+            count_sketch.insert(*x, 1);
+            norm_sketch.insert(*x, 1);
+            hll.insert(*x);
+        }
+
+        let n: f64 = stream_len as f64;
+        let distinct: Vec<u64> = (1..=keys as u64).filter(|x| exact.query(*x)).collect();
+        for x in &distinct {
+            let count: f64 = exact.get(*x) as f64;
+            errors[0].push(relative_error(count_min.estimate(*x) as f64, count, n));
+            errors[1].push(relative_error(count_sketch.estimate(*x) as f64, count, n));
+        }
+        let norm: f64 = exact.get_norm() as f64;
+        errors[2].push(relative_error(norm_sketch.norm() as f64, norm, norm));
+        errors[3].push(relative_error(count_sketch.norm() as f64, norm, norm));
+        errors[4].push(relative_error(hll.estimate(), distinct.len() as f64, distinct.len() as f64));
+        spaces = [count_min.space_usage(), count_sketch.space_usage(), norm_sketch.space_usage(), count_sketch.space_usage(), hll.space_usage()];
+    }
+
+    let quantities: [&str; 5] = ["point_query", "point_query", "f2", "f2", "distinct"];
+    for (i, name) in names.iter().enumerate() {
+        let summary: ErrorSummary = ErrorSummary::of(&errors[i]);
+        write_row(&mut csv, ResultRow::new(name, stream_len as u64, quantities[i])
+            .memory(spaces[i])
+            .metric("seeds", seeds)
+            .metric("queries", summary.samples)
+            .metric("mean_error", summary.mean)
+            .metric("p95_error", summary.p95)
+            .metric("max_error", summary.max));
+    }
+}
+
+// Updates of the norm-sketch stream generated at once. Every chunk comes from its own seed, so
+// a resumed run generates the rest of the same stream without replaying the start.
+const NORM_SKETCH_CHUNK: usize = 1 << 22;
+// Time between the checkpoints of a run with --checkpoint
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+const NORM_SKETCH_PROGRESS: &str = "norm_sketch.progress";
+
+// How far a norm-sketch run with --checkpoint got: the seed and dimensions it ran with, the
+// updates applied so far and the time their inserts into the sketch took. It is saved as
+// one line of text after the sketch and the exact table it names.
+struct NormSketchProgress {
+    seed: u64,
+    keys: usize,
+    width_len: u32,
+    done: usize,
+    elapsed_ns: u64,
+}
+
+impl NormSketchProgress {
+    fn read(dir: &Path) -> Option<NormSketchProgress> {
+        let text: String = std::fs::read_to_string(dir.join(NORM_SKETCH_PROGRESS)).ok()?;
+        let fields: Vec<u64> = text.split_whitespace().map(|field| field.parse::<u64>().expect("Corrupt checkpoint progress")).collect();
+        let [seed, keys, width_len, done, elapsed_ns] = fields[..] else { panic!("Corrupt checkpoint progress") };
+        return Some(NormSketchProgress { seed, keys: keys as usize, width_len: width_len as u32, done: done as usize, elapsed_ns });
+    }
+    // The sketch and the exact table after done updates. Their names hold done, so saving
+    // the next ones never touches the files the current progress line names.
+    fn files(dir: &Path, done: usize) -> (PathBuf, PathBuf) {
+        return (dir.join(format!("norm_sketch_{}.sketch", done)), dir.join(format!("norm_sketch_{}.exact", done)));
+    }
+    // Writes the sketch and the exact table, then the progress line, and only then removes
+    // the files of the previous checkpoint, so a run killed at any point leaves a whole one
+    fn save(&self, dir: &Path, sketch: &NormSketch, exact: &HwC, previous: Option<usize>) {
+        let (sketch_file, exact_file) = NormSketchProgress::files(dir, self.done);
+        sketch.save(sketch_file).expect("Cannot write checkpoint");
+        exact.save(exact_file).expect("Cannot write checkpoint");
+        let line: String = format!("{} {} {} {} {}\n", self.seed, self.keys, self.width_len, self.done, self.elapsed_ns);
+        let partial: PathBuf = dir.join(NORM_SKETCH_PROGRESS.to_owned() + ".partial");
+        std::fs::write(&partial, line).expect("Cannot write checkpoint");
+        std::fs::rename(&partial, dir.join(NORM_SKETCH_PROGRESS)).expect("Cannot write checkpoint");
+        if let Some(previous) = previous.filter(|previous| *previous != self.done) {
+            let (sketch_file, exact_file) = NormSketchProgress::files(dir, previous);
+            let _ = std::fs::remove_file(sketch_file);
+            let _ = std::fs::remove_file(exact_file);
+        }
+    }
+}
+
+// Update time of a single NormSketch with 2^width_len counters on a turnstile stream over
+// keys keys, and its F2 estimate against the exact one from HwC. With a checkpoint
+// directory the run saves itself there every CHECKPOINT_INTERVAL, at the end, and on a
+// first Ctrl-C, which stops it, and a run with the same seed, keys and width picks up from
+// the latest checkpoint. A resumed run may go on to more updates than the saved one.
+fn benchmark_norm_sketch(updates_len: usize, keys: usize, width_len: u32, checkpoint: Option<&Path>) {
+    let mut csv: CsvWriter<File> = make_csv_writer("norm_sketch");
+    let seed: u64 = input_seed();
+    let mut progress: NormSketchProgress = NormSketchProgress { seed, keys, width_len, done: 0, elapsed_ns: 0 };
+    let mut sketch: NormSketch = NormSketch::new(width_len);
+    let mut exact: HwC = HwC::new(keys);
+    let mut saved: Option<usize> = None;
+    if let Some(dir) = checkpoint {
+        std::fs::create_dir_all(dir).expect("Cannot create checkpoint directory");
+        if let Some(resumed) = NormSketchProgress::read(dir) {
+            assert!(resumed.seed == seed && resumed.keys == keys && resumed.width_len == width_len && resumed.done <= updates_len,
+                "The checkpoint in {} is of another run, with another seed or width or more updates", dir.display());
+            let (sketch_file, exact_file) = NormSketchProgress::files(dir, resumed.done);
+            sketch = NormSketch::load(sketch_file).expect("Cannot read checkpoint");
+            exact = HwC::load(exact_file).expect("Cannot read checkpoint");
+            eprintln!("Resuming norm-sketch after {} of {} updates", resumed.done, updates_len);
+            saved = Some(resumed.done);
+            progress = resumed;
+        }
+    }
+    let mut last_save = now();
+    while progress.done < updates_len {
+        let chunk: usize = progress.done / NORM_SKETCH_CHUNK;
+        let mut rng: StdRng = StdRng::seed_from_u64(seed ^ (chunk as u64) << 32);
+        let updates: Vec<(u64, i64)> = (0..NORM_SKETCH_CHUNK).map(|_| (rng.gen_range(0..keys as u64), rng.gen_range(-5..=5))).collect();
+        let updates: &[(u64, i64)] = &updates[progress.done % NORM_SKETCH_CHUNK..(updates_len - chunk * NORM_SKETCH_CHUNK).min(NORM_SKETCH_CHUNK)];
+        let start = now();
+        updates.iter().for_each(|(key, delta)| sketch.insert(*key, *delta));
+        let stop = now();
+        progress.elapsed_ns += (stop - start).whole_nanoseconds() as u64;
+        updates.iter().for_each(|(key, delta)| exact.insert(*key, *delta));
+        progress.done += updates.len();
+
+        let Some(dir) = checkpoint else { continue; };
+        if interrupted() || progress.done == updates_len || now() - last_save > Elapsed::from(CHECKPOINT_INTERVAL) {
+            progress.save(dir, &sketch, &exact, saved);
+            saved = Some(progress.done);
+            last_save = now();
+        }
+        if interrupted() && progress.done < updates_len {
+            eprintln!("Interrupted, saved norm-sketch after {} of {} updates to {}", progress.done, updates_len, dir.display());
+            return;
+        }
+    }
+    let exact_norm: u64 = exact.norm();
+    let relative_error: f64 = (sketch.norm() as f64 - exact_norm as f64).abs() / exact_norm as f64;
+    write_row(&mut csv, ResultRow::new("norm_sketch", updates_len as u64, "update")
+        .throughput(Elapsed::from_nanoseconds(progress.elapsed_ns), updates_len as u64)
+        .footprint(&sketch, keys)
+        .metric("keys", keys)
+        .metric("width", sketch.width())
+        .metric("estimate", sketch.norm())
+        .metric("exact", exact_norm)
+        .metric("relative_error", relative_error));
+}
+
+const TEST_SIZES: [i32; 7] = [12, 14, 16, 18, 20, 22, 24];
+const LOAD_FACTOR_HASH_LEN: u32 = 20;
+const LOAD_FACTORS: [f64; 9] = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9];
+const ZIPF_EXPONENTS: [f64; 4] = [0.8, 1.0, 1.2, 1.5];
+const AMS_EPSILONS: [f64; 3] = [0.5, 0.3, 0.2];
+const HLL_PRECISIONS: [u32; 3] = [8, 12, 16];
+const BLOOM_FPRS: [f64; 3] = [0.1, 0.01, 0.001];
+const BLOOM_QUERIES: u64 = 1_000_000_000;
+// Runs per test size of the experiment runner, enough for a confidence interval
+const REPETITIONS: usize = 5;
+// Unrecorded runs before those of each test size
+const WARMUP: usize = 1;
+const ORDERS: [&str; 5] = ["random", "sorted", "reversed", "few_distinct", "organ_pipe"];
+
+// Settings from the command line the benchmarks read: test sizes as exponents of two, the
+// number of stream updates, the sketch width and the runs and warm-up runs per size, each
+// left at the benchmark's own default when not given, the threads, time budget and latency
+// sampling of the runner, whether it reports progress, the real datasets and where the long
+// streaming runs keep their checkpoints
+#[derive(Serialize)]
+struct Options {
+    sizes: Vec<i32>,
+    updates: Option<usize>,
+    width: Option<usize>,
+    repetitions: Option<usize>,
+    jobs: usize,
+    budget: Option<Duration>,
+    latency_samples: Option<usize>,
+    warmup: Option<usize>,
+    progress: bool,
+    words: Option<PathBuf>,
+    trace: Option<PathBuf>,
+    trace_column: Option<usize>,
+    checkpoint: Option<PathBuf>,
+}
+
+// A benchmark by its command-line name, with the options it reads
+struct Benchmark {
+    name: &'static str,
+    options: &'static [&'static str],
+    run: fn(&Options),
+}
+
+// Every benchmark in the order a full run goes through them
+const BENCHMARKS: &[Benchmark] = &[
+    Benchmark { name: "ordered-structures", options: &["sizes"], run: |o| benchmark_ordered_structures(&o.sizes) },
+    Benchmark { name: "hwc", options: &["sizes", "repetitions", "warmup", "jobs", "budget", "latency-samples"], run: |o| benchmark_experiment("hwc", HwCExperiment::<InlineChains>::new("hwc"), o) },
+    Benchmark { name: "hwc-arena", options: &["sizes", "repetitions", "warmup", "jobs", "budget", "latency-samples"], run: |o| benchmark_experiment("hwc_arena", HwCExperiment::<ArenaChains>::new("hwc_arena"), o) },
+    Benchmark { name: "sharded-hwc", options: &["sizes"], run: |o| benchmark_sharded_hwc(&o.sizes) },
+    Benchmark { name: "parallel-sketches", options: &["updates"], run: |o| benchmark_parallel_sketches(o.updates.unwrap_or(1 << 26), 1 << 20) },
+    Benchmark { name: "cuckoo", options: &["sizes", "repetitions", "warmup", "jobs", "budget", "latency-samples"], run: |o| benchmark_experiment("cuckoo", CuckooExperiment { name: "cuckoo", stash_size: 0 }, o) },
+    Benchmark { name: "cuckoo-stash", options: &["sizes", "repetitions", "warmup", "jobs", "budget", "latency-samples"], run: |o| benchmark_experiment("cuckoo_stash", CuckooExperiment { name: "cuckoo_stash", stash_size: STASH_SIZE }, o) },
+    Benchmark { name: "lp", options: &["sizes", "repetitions", "warmup", "jobs", "budget", "latency-samples"], run: |o| benchmark_experiment("lp", LinearProbingExperiment, o) },
+    Benchmark { name: "dh", options: &["sizes", "repetitions", "warmup", "jobs", "budget", "latency-samples"], run: |o| benchmark_experiment("dh", DoubleHashingExperiment, o) },
+    Benchmark { name: "baselines", options: &["sizes", "repetitions", "warmup", "jobs", "budget", "latency-samples"], run: benchmark_baselines },
+    Benchmark { name: "max-chain", options: &["sizes"], run: |o| benchmark_max_chain(&o.sizes) },
+    Benchmark { name: "load-factors", options: &[], run: |_| benchmark_load_factors(LOAD_FACTOR_HASH_LEN, LOAD_FACTORS) },
+    Benchmark { name: "ph", options: &["sizes", "repetitions", "warmup", "jobs", "budget", "latency-samples"], run: |o| benchmark_experiment("ph", PerfectHashingExperiment, o) },
+    Benchmark { name: "mphf", options: &["sizes", "repetitions", "warmup", "jobs", "budget", "latency-samples"], run: |o| benchmark_experiment("mphf", MphfExperiment, o) },
+    Benchmark { name: "accuracy", options: &["updates", "repetitions"], run: |o| benchmark_accuracy(o.updates.unwrap_or(1 << 20), 1 << 16, 1.1, o.repetitions.unwrap_or(10)) },
+    Benchmark { name: "count-min", options: &["updates"], run: |o| benchmark_count_min(o.updates.unwrap_or(1 << 20), 1 << 16, ZIPF_EXPONENTS) },
+    Benchmark { name: "heavy-hitters", options: &["updates"], run: |o| benchmark_heavy_hitters(o.updates.unwrap_or(1 << 20), 1 << 16, ZIPF_EXPONENTS, 0.001) },
+    Benchmark { name: "space-saving", options: &["updates"], run: |o| benchmark_space_saving(o.updates.unwrap_or(1 << 22), 1 << 16, ZIPF_EXPONENTS, 100, 8) },
+    Benchmark { name: "top-k", options: &["updates"], run: |o| benchmark_top_k(o.updates.unwrap_or(1 << 22), 1 << 16, ZIPF_EXPONENTS, 100, 0.25) },
+    Benchmark { name: "real-data", options: &["words", "trace", "trace-column"], run: |o| benchmark_real_data(o.words.as_deref(), o.trace.as_deref(), o.trace_column, 0.001) },
+    Benchmark { name: "windowed-heavy-hitters", options: &[], run: |_| benchmark_windowed_heavy_hitters(1 << 20, 1 << 16, 1.2, 4, 1 << 18, 20) },
+    Benchmark { name: "quantiles", options: &["updates"], run: |o| benchmark_quantiles(o.updates.unwrap_or(1 << 22), 0.01, 100.0) },
+    Benchmark { name: "subset-sums", options: &["updates"], run: |o| benchmark_subset_sums(o.updates.unwrap_or(1 << 20), 1 << 10, 24, 20) },
+    Benchmark { name: "ams", options: &["updates"], run: |o| benchmark_ams(o.updates.unwrap_or(1 << 14), 1 << 12, AMS_EPSILONS, 0.05) },
+    Benchmark { name: "sketch-sizes", options: &[], run: |_| benchmark_sketch_sizes(AMS_EPSILONS, 0.05) },
+    Benchmark { name: "hyperloglog", options: &["sizes"], run: |o| benchmark_hyperloglog(&o.sizes, HLL_PRECISIONS) },
+    Benchmark { name: "bloom", options: &["sizes"], run: |o| benchmark_bloom(&o.sizes, BLOOM_FPRS) },
+    Benchmark { name: "blocked-bloom", options: &[], run: |_| benchmark_blocked_bloom(24, 0.01, BLOOM_QUERIES) },
+    Benchmark { name: "scalable-bloom", options: &["sizes"], run: |o| benchmark_scalable_bloom(&o.sizes, 0.01) },
+    Benchmark { name: "iblt", options: &[], run: |_| benchmark_iblt(20, [10, 100, 1000, 10_000]) },
+    Benchmark { name: "minhash", options: &[], run: |_| benchmark_minhash(16, [16, 64, 256], [0.1, 0.5, 0.9]) },
+    Benchmark { name: "odd-sketch", options: &[], run: |_| benchmark_odd_sketch(16, 64, [0.95, 0.99, 0.999]) },
+    Benchmark { name: "b-bit-minhash", options: &[], run: |_| benchmark_b_bit_minhash(256, [1, 2, 4, 8], 0.5) },
+    Benchmark { name: "one-permutation-hashing", options: &["sizes"], run: |o| benchmark_one_permutation_hashing(&o.sizes, 256) },
+    Benchmark { name: "lsh", options: &[], run: |_| benchmark_lsh(100, 10, 20, 5, 0.5) },
+    Benchmark { name: "euclidean-lsh", options: &[], run: |_| benchmark_euclidean_lsh(100_000, 1000, 32, [1, 4, 16, 64]) },
+    Benchmark { name: "jl", options: &[], run: |_| benchmark_jl(500, 10_000, [16, 64, 256, 1024]) },
+    Benchmark { name: "feature-hashing", options: &[], run: |_| benchmark_feature_hashing(500, 1000, [16, 64, 256, 1024]) },
+    Benchmark { name: "karger", options: &[], run: |_| benchmark_karger([16, 32, 64, 128], 10_000) },
+    Benchmark { name: "min-cut", options: &[], run: |_| benchmark_min_cut([32, 64, 128, 256], 100) },
+    Benchmark { name: "pollard-rho", options: &[], run: |_| benchmark_pollard_rho([16, 20, 24, 28], 100) },
+    Benchmark { name: "freivalds", options: &[], run: |_| benchmark_freivalds([100, 300, 1000], 1000) },
+    Benchmark { name: "selection", options: &["sizes"], run: |o| benchmark_selection(&o.sizes, ORDERS) },
+    Benchmark { name: "quicksort", options: &[], run: |_| benchmark_quicksort([10, 12, 14], ORDERS) },
+    Benchmark { name: "pagerank", options: &[], run: |_| benchmark_pagerank([1000, 10_000, 100_000], 8, [1, 5, 25, 125]) },
+    Benchmark { name: "schoening", options: &[], run: |_| benchmark_schoening([20, 40, 60, 80], [3.0, 3.5, 4.0, 4.26, 4.5], 100, 10_000) },
+    Benchmark { name: "rounding", options: &[], run: |_| benchmark_rounding(50, 100) },
+    Benchmark { name: "max-cut", options: &[], run: |_| benchmark_max_cut([100, 300, 1000, 3000], 100) },
+    Benchmark { name: "alias", options: &["sizes"], run: |o| benchmark_alias(&o.sizes, 1 << 26) },
+    Benchmark { name: "balls-into-bins", options: &["sizes"], run: |o| benchmark_balls_into_bins(&o.sizes, 10) },
+    Benchmark { name: "coupon-collector", options: &[], run: |_| benchmark_coupon_collector([10, 100, 1000, 10_000], 10_000) },
+    Benchmark { name: "birthday", options: &[], run: |_| benchmark_birthday([16, 20, 24, 28], 100) },
+    Benchmark { name: "hash-timing", options: &["sizes"], run: |o| benchmark_hash_timing(&o.sizes, 30) },
+    Benchmark { name: "matrix-sampling", options: &[], run: |_| benchmark_matrix_sampling([100, 300, 1000], [10, 30, 100, 300], 10) },
+    Benchmark { name: "low-rank", options: &[], run: |_| benchmark_low_rank([100, 200, 400], 10, 10) },
+    Benchmark { name: "annealing", options: &[], run: |_| benchmark_annealing([100, 300, 1000, 3000], 1000) },
+    Benchmark { name: "distributions", options: &["updates"], run: |o| benchmark_distributions(o.updates.unwrap_or(1 << 26)) },
+    Benchmark { name: "norm-sketch", options: &["updates", "width", "checkpoint"], run: |o| benchmark_norm_sketch(o.updates.unwrap_or(1 << 24), 1 << 20, o.width.unwrap_or(1 << 16).next_power_of_two().trailing_zeros(), o.checkpoint.as_deref()) },
+];
+
+// A count such as 1000000, 1_000_000, 1e6 or 2^20
+fn parse_count(text: &str) -> Result<usize, String> {
+    let text: String = text.replace('_', "");
+    if let Some(exponent) = text.strip_prefix("2^") {
+        return exponent.parse::<u32>().ok().and_then(|e| 1usize.checked_shl(e)).ok_or(format!("Invalid power of two {}", text));
+    }
+    if let Ok(count) = text.parse::<usize>() {
+        return Ok(count);
+    }
+    return match text.parse::<f64>() {
+        Ok(count) if count >= 0.0 && count.fract() == 0.0 && count < usize::MAX as f64 => Ok(count as usize),
+        _ => Err(format!("Invalid count {}", text)),
+    };
+}
+
+// A time such as 90s, 30m or 2h, or a number of seconds
+fn parse_budget(text: &str) -> Result<Duration, String> {
+    let (number, unit): (&str, f64) = match text.char_indices().last() {
+        Some((end, 's')) => (&text[..end], 1.0),
+        Some((end, 'm')) => (&text[..end], 60.0),
+        Some((end, 'h')) => (&text[..end], 3600.0),
+        _ => (text, 1.0),
+    };
+    return match number.parse::<f64>() {
+        Ok(value) if value > 0.0 && value.is_finite() => Ok(Duration::from_secs_f64(value * unit)),
+        _ => Err(format!("Invalid time {}", text)),
+    };
+}
+
+// Test sizes, which are powers of two: a comma separated list of sizes, or a range 2^a..2^b
+// of every power of two in between, such as 2^6..2^28 or 2^12,2^16,2^20
+#[derive(Clone, Debug)]
+struct Sizes(Vec<i32>);
+
+fn parse_sizes(text: &str) -> Result<Sizes, String> {
+    let exponent = |size: &str| -> Result<i32, String> {
+        let count: usize = parse_count(size)?;
+        if !count.is_power_of_two() {
+            return Err(format!("Size {} is not a power of two", size));
+        }
+        return Ok(count.trailing_zeros() as i32);
+    };
+    if let Some((low, high)) = text.split_once("..") {
+        let (low, high): (i32, i32) = (exponent(low)?, exponent(high.trim_start_matches('='))?);
+        return if low <= high { Ok(Sizes((low..=high).collect())) } else { Err(format!("Empty range {}", text)) };
+    }
+    return text.split(',').map(exponent).collect::<Result<Vec<i32>, String>>().map(Sizes);
+}
+
+// Runs the experiments, each appending its results to <output-dir>/<name>.csv or .txt, or
+// plots CSV results and JSON reports.
+// cargo run --release -- hwc --sizes 2^6..2^28 --seed 42
+// cargo run --release -- norm-sketch --width 128 --updates 1e9 --checkpoint checkpoints
+// cargo run --release -- plot hwc.csv lp.csv --phase query_summary --log-x --log-y
+#[derive(Parser, Debug)]
+#[command(about = "Runs the randomized algorithms experiments, all of them unless some are named")]
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Benchmarks to run, in the order given
+    #[arg(value_name = "BENCHMARK")]
+    benchmarks: Vec<String>,
+    /// Prints the benchmark names and the options each reads, then exits
+    #[arg(long)]
+    list: bool,
+    /// Test sizes such as 2^12..2^24 or 2^16,2^20, for the benchmarks over test sizes
+    #[arg(long, value_parser = parse_sizes)]
+    sizes: Option<Sizes>,
+    /// Stream length such as 1e9 or 2^26, for the streaming benchmarks
+    #[arg(long, value_parser = parse_count)]
+    updates: Option<usize>,
+    /// Number of counters, rounded up to a power of two, for the sketches that take a width
+    #[arg(long, value_parser = parse_count)]
+    width: Option<usize>,
+    /// Runs per test size, for the benchmarks that go through the experiment runner. With
+    /// two or more, each size also gets summary rows with the mean, median, standard
+    /// deviation and 95% confidence interval of every phase. 5 unless given.
+    #[arg(long)]
+    repetitions: Option<usize>,
+    /// Runs per test size before those that are recorded, for the benchmarks that go through
+    /// the experiment runner, so the measured runs start with warm caches. 1 unless given.
+    #[arg(long)]
+    warmup: Option<usize>,
+    /// Threads that run the repetitions and sizes of the experiment runner benchmarks side by
+    /// side. Each run is still timed on a single thread, but runs at once compete for caches
+    /// and memory bandwidth. 1 unless given.
+    #[arg(long)]
+    jobs: Option<usize>,
+    /// Longest a phase of an experiment runner benchmark may take, such as 90s, 30m or 2h.
+    /// Once a phase goes over it, the runs of that size finish and the larger sizes are
+    /// skipped.
+    #[arg(long, value_parser = parse_budget)]
+    budget: Option<Duration>,
+    /// Also times every insert and lookup of the experiment runner benchmarks on its own,
+    /// keeping a uniform sample of this many latencies per phase, and adds their p50, p90,
+    /// p99 and p999 to the construction and query rows. The timing slows the phases down.
+    #[arg(long, value_name = "SAMPLES", value_parser = parse_count)]
+    latency_samples: Option<usize>,
+    /// Prints a line every ten seconds with the share done, the current operations per
+    /// second and the time left, for the benchmarks that go through the experiment runner
+    #[arg(long)]
+    progress: bool,
+    /// Text file whose words are the stream of real-data
+    #[arg(long, value_name = "FILE")]
+    words: Option<PathBuf>,
+    /// Packet trace for real-data: CSV with a header line, keyed by the --trace-column
+    /// field, or else the text output of tcpdump -n, keyed by the source address
+    #[arg(long, value_name = "FILE")]
+    trace: Option<PathBuf>,
+    /// Column of the key in a CSV trace, counting from 0. Implies CSV; 0 unless given.
+    #[arg(long)]
+    trace_column: Option<usize>,
+    /// Seed of the generated graphs and skewed streams. Hash functions still draw from the
+    /// thread's generator, so timings and estimates vary between runs. 2024 unless given.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Directory for the result files, created if missing. The working directory unless
+    /// given.
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
+    /// Also writes the configuration, the machine and every measurement of the run to
+    /// report-<unix time>.json in the output directory
+    #[arg(long)]
+    json: bool,
+    /// Also writes the generated key streams of the streaming benchmarks and max-chain to
+    /// <name>_seed_<seed>.updates in the output directory, for replaying with stream
+    #[arg(long)]
+    record: bool,
+    /// Directory norm-sketch saves its sketch and exact table to every minute, at the end and
+    /// on Ctrl-C. Rerun with the same directory, seed and width, it resumes from there
+    /// instead of starting over, and may go on to more updates.
+    #[arg(long, value_name = "DIR")]
+    checkpoint: Option<PathBuf>,
+    /// TOML file describing a whole suite: the benchmarks and any of the options above,
+    /// with the same names. Options given on the command line take precedence.
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Draws results as an SVG line chart with one line per algorithm, such as time against
+    /// n or error against memory
+    Plot(PlotArgs),
+    /// Gathers results files and JSON reports into one Markdown or HTML document, with a
+    /// section per file holding a chart of the phase times and a table per algorithm
+    Report(ReportArgs),
+    /// Keeps a Count-Min sketch, a HyperLogLog and a NormSketch updated from a stream of
+    /// updates and answers GET /estimate?key=, /distinct, /norm and /sketch?kind= and
+    /// POST /merge over HTTP
+    Serve(ServeArgs),
+    /// Feeds updates from a file or stdin into one structure as they are read, so streams
+    /// larger than memory work, and prints what the structure estimates
+    Stream(StreamArgs),
+}
+
+#[derive(Args, Debug)]
+struct ReportArgs {
+    /// CSV results files or JSON reports, a section each
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Document title
+    #[arg(long, default_value = "Benchmark results")]
+    title: String,
+    /// Document to write, HTML when it ends in .html and Markdown otherwise. The charts of
+    /// a Markdown document go next to it as SVG files.
+    #[arg(long, short, default_value = "report.md")]
+    output: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct PlotArgs {
+    /// CSV results files or JSON reports
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Column or extra metric on the x axis
+    #[arg(long, default_value = "n")]
+    x: String,
+    /// Column or extra metric on the y axis
+    #[arg(long, default_value = "duration_ns")]
+    y: String,
+    /// Only rows of this phase, such as query or update
+    #[arg(long)]
+    phase: Option<String>,
+    /// Only these algorithms, separated by commas
+    #[arg(long, value_delimiter = ',')]
+    algorithms: Vec<String>,
+    /// Logarithmic x axis
+    #[arg(long)]
+    log_x: bool,
+    /// Logarithmic y axis
+    #[arg(long)]
+    log_y: bool,
+    /// Chart title, the y column against the x column unless given
+    #[arg(long)]
+    title: Option<String>,
+    /// SVG file to write
+    #[arg(long, short, default_value = "chart.svg")]
+    output: PathBuf,
+}
+
+// Structures the stream subcommand can feed
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum StreamStructure {
+    Hwc,
+    CountMin,
+    CountSketch,
+    NormSketch,
+    Ams,
+    Hyperloglog,
+    MisraGries,
+    SpaceSaving,
+}
+
+#[derive(Args, Debug)]
+struct StreamArgs {
+    /// Update file, stdin when left out or -
+    input: Option<PathBuf>,
+    /// Structure the updates go into
+    #[arg(long, value_enum, default_value = "norm-sketch")]
+    structure: StreamStructure,
+    /// Reads 16-byte records of a u64 key and an i64 delta, little endian, instead of text
+    /// lines of a key and an optional delta. Implied by a .updates file, as --record writes.
+    #[arg(long)]
+    binary: bool,
+    /// Number of counters, rounded up to a power of two for the sketches, and the number of
+    /// buckets of hwc
+    #[arg(long, value_parser = parse_count, default_value = "65536")]
+    width: usize,
+    /// Keys whose counts the frequency structures print, separated by commas
+    #[arg(long, value_delimiter = ',')]
+    query: Vec<u64>,
+    /// Number of the largest counts the heavy hitter summaries print
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+}
+
+// Runs the stream into the chosen structure, printing the updates read, the time and the
+// memory taken and the structure's estimates. Misra–Gries, SpaceSaving and HyperLogLog only
+// take insertions, so they skip updates with a negative delta and say how many.
+fn stream(args: StreamArgs) -> std::io::Result<()> {
+    let input: Box<dyn BufRead> = match &args.input {
+        Some(path) if path.as_os_str() != "-" => Box::new(BufReader::new(File::open(path)?)),
+        _ => Box::new(std::io::stdin().lock()),
+    };
+    let recorded: bool = args.input.as_ref().is_some_and(|path| path.extension().is_some_and(|extension| extension == "updates"));
+    let format: UpdateFormat = if args.binary || recorded { UpdateFormat::Binary } else { UpdateFormat::Text };
+    let mut reader: UpdateReader<Box<dyn BufRead>> = UpdateReader::new(input, format);
+    let width_len: u32 = args.width.next_power_of_two().trailing_zeros();
+    let mut deletions: u64 = 0;
+    let mut insertions = |(key, delta): (u64, i64)| -> Option<(u64, u64)> {
+        if delta < 0 {
+            deletions += 1;
+        }
+        return u64::try_from(delta).ok().map(|delta| (key, delta));
+    };
+    let mut results: Vec<String> = Vec::new();
+
+    let start = now();
+    let memory: usize = match args.structure {
+        StreamStructure::Hwc => {
+            let mut hwc: HwC = HwC::new(args.width);
+            hwc.consume(reader.updates());
+            results.push(format!("Distinct keys: {}, F2: {}", hwc.stats().entries, hwc.norm()));
+            results.extend(args.query.iter().map(|key| format!("Count of {}: {}", key, hwc.get(*key))));
+            hwc.space_usage()
+        }
+        StreamStructure::CountMin => {
+            let mut sketch: CountMin<i64> = CountMin::new(5, width_len, UpdatePolicy::Standard);
+            sketch.consume(reader.updates());
+            results.extend(args.query.iter().map(|key| format!("Estimate of {}: {}", key, sketch.estimate(*key))));
+            sketch.space_usage()
+        }
+        StreamStructure::CountSketch => {
+            let mut sketch: CountSketch = CountSketch::new(5, width_len);
+            sketch.consume(reader.updates());
+            results.push(format!("F2 estimate: {}", sketch.norm()));
+            results.extend(args.query.iter().map(|key| format!("Estimate of {}: {}", key, sketch.estimate(*key))));
+            sketch.space_usage()
+        }
+        StreamStructure::NormSketch => {
+            let mut sketch: NormSketch = NormSketch::new(width_len);
+            sketch.consume(reader.updates());
+            results.push(format!("F2 estimate: {}", sketch.norm_wide()));
+            sketch.space_usage()
+        }
+        StreamStructure::Ams => {
+            let mut estimator: AmsF2Estimator = AmsF2Estimator::with_guarantees(0.1, 0.05);
+            estimator.consume(reader.updates());
+            results.push(format!("F2 estimate: {}", estimator.norm()));
+            estimator.space_usage()
+        }
+        StreamStructure::Hyperloglog => {
+            let mut hll: HyperLogLog = HyperLogLog::new(14);
+            hll.consume(reader.updates().filter_map(&mut insertions).filter(|(_, delta)| *delta > 0).map(|(key, _)| key));
+            results.push(format!("Distinct keys estimate: {:.0}", hll.estimate()));
+            hll.space_usage()
+        }
+        StreamStructure::MisraGries => {
+            let mut summary: MisraGries = MisraGries::new(args.width);
+            summary.consume(reader.updates().filter_map(&mut insertions));
+            let mut heavy: Vec<(u64, u64)> = summary.heavy_hitters(0);
+            heavy.sort_unstable_by_key(|(_, count)| Reverse(*count));
+            results.extend(heavy.iter().take(args.top).map(|(key, count)| format!("{}: at least {}", key, count)));
+            summary.space_usage()
+        }
+        StreamStructure::SpaceSaving => {
+            let mut summary: SpaceSaving = SpaceSaving::new(args.width);
+            summary.consume(reader.updates().filter_map(&mut insertions));
+            results.extend(summary.top_k(args.top).iter().map(|counter| format!("{}: {} (error at most {})", counter.key, counter.count, counter.error)));
+            summary.space_usage()
+        }
+    };
+    let stop = now();
+    let updates: u64 = reader.finish()?;
+    println!("{} updates in {}, {:.2} million per second, {} bytes", updates, stop - start, (stop - start).ops_per_second(updates) / 1e6, memory);
+    if deletions > 0 {
+        println!("Skipped {} updates with a negative delta", deletions);
+    }
+    results.iter().for_each(|line| println!("{}", line));
+    return Ok(());
+}
+
+#[derive(Args, Debug)]
+struct ServeArgs {
+    /// Update file, stdin when left out or -
+    input: Option<PathBuf>,
+    /// Address and port to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: String,
+    /// Reads 16-byte records of a u64 key and an i64 delta, little endian, instead of text
+    /// lines of a key and an optional delta. Implied by a .updates file, as --record writes.
+    #[arg(long)]
+    binary: bool,
+    /// Counters per row of the Count-Min sketch and the NormSketch, rounded up to a power
+    /// of two
+    #[arg(long, value_parser = parse_count, default_value = "65536")]
+    width: usize,
+    /// Directory the sketches start from when saved there before, and are saved to when
+    /// the server stops. Servers started from the same files hash alike, so they can merge
+    /// each other's sketches.
+    #[arg(long, value_name = "DIR")]
+    checkpoint: Option<PathBuf>,
+}
+
+// Updates applied to the sketches under one lock, so queries get in between
+const SERVE_BATCH: usize = 4096;
+
+// Serves the sketches while a thread feeds them the stream, until Ctrl-C. A stream that
+// ends leaves the server answering with what it has.
+fn serve(args: ServeArgs) -> std::io::Result<()> {
+    let width_len: u32 = args.width.next_power_of_two().trailing_zeros();
+    let sketches: SketchSet = match &args.checkpoint {
+        Some(dir) if SketchSet::is_saved_in(dir) => SketchSet::load(dir)?,
+        _ => SketchSet::new(width_len, 14),
+    };
+    let sketches: Arc<Mutex<SketchSet>> = Arc::new(Mutex::new(sketches));
+    // Opened here, so a bad path stops the server before it starts rather than leaving it empty
+    let input: Option<PathBuf> = args.input.filter(|path| path.as_os_str() != "-");
+    let recorded: bool = input.as_ref().is_some_and(|path| path.extension().is_some_and(|extension| extension == "updates"));
+    let format: UpdateFormat = if args.binary || recorded { UpdateFormat::Binary } else { UpdateFormat::Text };
+    let file: Option<File> = input.as_ref().map(File::open).transpose()?;
+    let server: tiny_http::Server = tiny_http::Server::http(&args.listen).map_err(std::io::Error::other)?;
+    watch_interrupts()?;
+    eprintln!("Serving the sketches on http://{}", args.listen);
+
+    let feeder: Arc<Mutex<SketchSet>> = Arc::clone(&sketches);
+    thread::spawn(move || {
+        let input: Box<dyn BufRead> = match file {
+            Some(file) => Box::new(BufReader::new(file)),
+            None => Box::new(std::io::stdin().lock()),
+        };
+        let mut reader: UpdateReader<Box<dyn BufRead>> = UpdateReader::new(input, format);
+        let mut batch: Vec<(u64, i64)> = Vec::with_capacity(SERVE_BATCH);
+        for update in reader.updates() {
+            batch.push(update);
+            if batch.len() == SERVE_BATCH {
+                let mut sketches = feeder.lock().unwrap();
+                batch.drain(..).for_each(|(key, delta)| sketches.update(key, delta));
+            }
+        }
+        let mut sketches = feeder.lock().unwrap();
+        batch.drain(..).for_each(|(key, delta)| sketches.update(key, delta));
+        match reader.finish() {
+            Ok(updates) => eprintln!("The stream ended after {} updates, still serving", updates),
+            Err(err) => eprintln!("The stream stopped: {}, still serving", err),
+        }
+    });
+
+    // One bad request is answered with an error and never stops the server
+    while !interrupted() {
+        let Some(mut request) = server.recv_timeout(Duration::from_millis(100))? else { continue; };
+        let mut body: Vec<u8> = Vec::new();
+        let response: ServerResponse = match request.as_reader().read_to_end(&mut body) {
+            Ok(_) => {
+                let mut sketches = sketches.lock().unwrap_or_else(PoisonError::into_inner);
+                panic::catch_unwind(AssertUnwindSafe(|| sketches.handle(request.method().as_str(), request.url(), &body)))
+                    .unwrap_or_else(|_| ServerResponse::error(500, "the request could not be handled"))
+            }
+            Err(err) => {
+                eprintln!("Cannot read a request: {}", err);
+                ServerResponse::error(400, "cannot read the request body")
+            }
+        };
+        let content_type: tiny_http::Header = tiny_http::Header::from_bytes("Content-Type", response.content_type).unwrap();
+        let answer = tiny_http::Response::from_data(response.body).with_status_code(response.status).with_header(content_type);
+        if let Err(err) = request.respond(answer) {
+            eprintln!("Cannot answer a request: {}", err);
+        }
+    }
+    let sketches = sketches.lock().unwrap();
+    if let Some(dir) = &args.checkpoint {
+        sketches.save(dir)?;
+        eprintln!("Saved the sketches of {} updates to {}", sketches.updates(), dir.display());
+    }
+    return Ok(());
+}
+
+// Reads the inputs, JSON reports by their extension and CSV otherwise, and draws the rows
+// that pass the filters
+fn report(args: ReportArgs) -> std::io::Result<()> {
+    let mut sections: Vec<Section> = Vec::new();
+    for input in &args.inputs {
+        let text: String = std::fs::read_to_string(input)?;
+        let is_json: bool = input.extension().is_some_and(|extension| extension == "json");
+        let title: String = input.file_stem().map_or(input.display().to_string(), |stem| stem.to_string_lossy().into_owned());
+        sections.push(Section { title, rows: if is_json { read_report(&text)? } else { read_csv(&text)? } });
+    }
+    let is_html: bool = args.output.extension().is_some_and(|extension| extension == "html" || extension == "htm");
+    let format: DocumentFormat = if is_html { DocumentFormat::Html } else { DocumentFormat::Markdown };
+    let document: Document = render_document(&args.title, &sections, format)?;
+    let directory: &Path = args.output.parent().unwrap_or(Path::new("."));
+    for (file_name, svg) in &document.images {
+        std::fs::write(directory.join(file_name), svg)?;
+    }
+    std::fs::write(&args.output, &document.text)?;
+    println!("Wrote {} sections and {} charts to {}", sections.len(), document.images.len(), args.output.display());
+    return Ok(());
+}
+
+fn plot(args: PlotArgs) -> std::io::Result<()> {
+    let mut rows: Vec<ResultRow> = Vec::new();
+    for input in &args.inputs {
+        let text: String = std::fs::read_to_string(input)?;
+        let is_json: bool = input.extension().is_some_and(|extension| extension == "json");
+        rows.extend(if is_json { read_report(&text)? } else { read_csv(&text)? });
+    }
+    rows.retain(|row| {
+        return args.phase.as_ref().is_none_or(|phase| &row.phase == phase)
+            && (args.algorithms.is_empty() || args.algorithms.contains(&row.algorithm));
+    });
+    let lines: Vec<Series> = series(&rows, &args.x, &args.y);
+    if lines.is_empty() {
+        eprintln!("No rows have both {} and {}", args.x, args.y);
+    }
+    let chart: Chart = Chart {
+        title: args.title.unwrap_or(format!("{} against {}", args.y, args.x)),
+        x_label: args.x,
+        y_label: args.y,
+        log_x: args.log_x,
+        log_y: args.log_y,
+    };
+    std::fs::write(&args.output, render_svg(&lines, &chart)?)?;
+    println!("Wrote {} series to {}", lines.len(), args.output.display());
+    return Ok(());
+}
+
+// A suite of experiments in a TOML file, so published results can ship with the file that
+// produced them. Every key is optional and named as the command-line option:
+// benchmarks = ["hwc", "lp", "baselines"]
+// sizes = "2^12..2^24"
+// updates = "2^26"
+// repetitions = 10
+// seed = 42
+// output-dir = "results/tables"
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct Suite {
+    benchmarks: Vec<String>,
+    sizes: Option<String>,
+    updates: Option<CountSetting>,
+    width: Option<CountSetting>,
+    repetitions: Option<usize>,
+    warmup: Option<usize>,
+    jobs: Option<usize>,
+    budget: Option<String>,
+    latency_samples: Option<CountSetting>,
+    progress: bool,
+    words: Option<PathBuf>,
+    trace: Option<PathBuf>,
+    trace_column: Option<usize>,
+    seed: Option<u64>,
+    output_dir: Option<PathBuf>,
+    json: bool,
+    record: bool,
+    checkpoint: Option<PathBuf>,
+}
+
+// A count as a TOML integer or as a string in any form the command line takes, like "1e9"
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum CountSetting {
+    Number(usize),
+    Text(String),
+}
+
+impl CountSetting {
+    fn parse(&self) -> Result<usize, String> {
+        return match self {
+            CountSetting::Number(count) => Ok(*count),
+            CountSetting::Text(text) => parse_count(text),
+        }
+    }
+}
+
+fn read_suite(path: &Path) -> Result<Suite, String> {
+    let text: String = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    return toml::from_str(&text).map_err(|err| err.to_string());
+}
+
+impl Cli {
+    // The options of the suite wherever the command line leaves them out. Paths in the
+    // suite are relative to the working directory, like those on the command line.
+    fn with_suite(mut self, suite: Suite) -> Result<Cli, String> {
+        if self.benchmarks.is_empty() {
+            self.benchmarks = suite.benchmarks;
+        }
+        if self.sizes.is_none() {
+            self.sizes = suite.sizes.as_deref().map(parse_sizes).transpose()?;
+        }
+        if self.updates.is_none() {
+            self.updates = suite.updates.as_ref().map(CountSetting::parse).transpose()?;
+        }
+        if self.width.is_none() {
+            self.width = suite.width.as_ref().map(CountSetting::parse).transpose()?;
+        }
+        self.repetitions = self.repetitions.or(suite.repetitions);
+        self.warmup = self.warmup.or(suite.warmup);
+        self.jobs = self.jobs.or(suite.jobs);
+        if self.budget.is_none() {
+            self.budget = suite.budget.as_deref().map(parse_budget).transpose()?;
+        }
+        if self.latency_samples.is_none() {
+            self.latency_samples = suite.latency_samples.as_ref().map(CountSetting::parse).transpose()?;
+        }
+        self.progress |= suite.progress;
+        self.words = self.words.or(suite.words);
+        self.trace = self.trace.or(suite.trace);
+        self.trace_column = self.trace_column.or(suite.trace_column);
+        self.seed = self.seed.or(suite.seed);
+        self.output_dir = self.output_dir.or(suite.output_dir);
+        self.json |= suite.json;
+        self.record |= suite.record;
+        self.checkpoint = self.checkpoint.or(suite.checkpoint);
+        return Ok(self);
+    }
+}
+
+// The configuration of a run as it goes into the JSON report
+#[derive(Serialize)]
+struct Configuration<'a> {
+    benchmarks: Vec<&'static str>,
+    seed: u64,
+    record: bool,
+    #[serde(flatten)]
+    options: &'a Options,
+}
+
+fn main() -> std::io::Result<()> {
+    let mut cli: Cli = Cli::parse();
+    if let Some(path) = cli.config.clone() {
+        cli = read_suite(&path).and_then(|suite| cli.with_suite(suite)).unwrap_or_else(|err| {
+            Cli::command().error(ErrorKind::InvalidValue, format!("Invalid suite {}: {}", path.display(), err)).exit()
+        });
+    }
+    match cli.command {
+        Some(Command::Plot(args)) => return plot(args),
+        Some(Command::Report(args)) => return report(args),
+        Some(Command::Serve(args)) => return serve(args),
+        Some(Command::Stream(args)) => return stream(args),
+        None => {}
+    }
+    if cli.list {
+        for benchmark in BENCHMARKS {
+            println!("{}\t{}", benchmark.name, benchmark.options.join(", "));
+        }
+        return Ok(());
+    }
+    let selected: Vec<&Benchmark> = if cli.benchmarks.is_empty() {
+        BENCHMARKS.iter().collect()
+    } else {
+        cli.benchmarks.iter().map(|name| {
+            return BENCHMARKS.iter().find(|benchmark| benchmark.name == name).unwrap_or_else(|| {
+                Cli::command().error(ErrorKind::InvalidValue, format!("Unknown benchmark {}, see --list", name)).exit()
+            });
+        }).collect()
+    };
+    for (option, given) in [("sizes", cli.sizes.is_some()), ("updates", cli.updates.is_some()), ("width", cli.width.is_some()), ("repetitions", cli.repetitions.is_some()), ("jobs", cli.jobs.is_some()),
+                            ("warmup", cli.warmup.is_some()), ("budget", cli.budget.is_some()), ("latency-samples", cli.latency_samples.is_some()),
+                            ("words", cli.words.is_some()), ("trace", cli.trace.is_some()), ("trace-column", cli.trace_column.is_some()),
+                            ("checkpoint", cli.checkpoint.is_some())] {
+        for benchmark in selected.iter().filter(|benchmark| given && !benchmark.options.contains(&option)) {
+            eprintln!("{} does not take --{}, ignoring it there", benchmark.name, option);
+        }
+    }
+
+    let output_dir: PathBuf = cli.output_dir.unwrap_or_else(|| PathBuf::from("."));
+    let seed: u64 = cli.seed.unwrap_or(DEFAULT_SEED);
+    std::fs::create_dir_all(&output_dir)?;
+    OUTPUT_DIR.set(output_dir).expect("Output directory set twice");
+    INPUT_SEED.set(seed).expect("Seed set twice");
+    RECORD_INPUTS.set(cli.record).expect("Recording set twice");
+    let options: Options = Options {
+        sizes: cli.sizes.map_or(TEST_SIZES.to_vec(), |sizes| sizes.0),
+        updates: cli.updates,
+        width: cli.width,
+        repetitions: cli.repetitions,
+        jobs: cli.jobs.unwrap_or(1).max(1),
+        budget: cli.budget,
+        latency_samples: cli.latency_samples.filter(|samples| *samples > 0),
+        warmup: cli.warmup,
+        progress: cli.progress,
+        words: cli.words,
+        trace: cli.trace,
+        trace_column: cli.trace_column,
+        checkpoint: cli.checkpoint,
+    };
+    let names: Vec<&'static str> = selected.iter().map(|benchmark| benchmark.name).collect();
+    let report: Option<Report<Configuration>> = cli.json.then(|| Report::new(Configuration { benchmarks: names, seed, record: cli.record, options: &options }));
+    if report.is_some() {
+        MEASUREMENTS.set(Mutex::new(Vec::new())).expect("Measurements set twice");
+    }
+    // A first Ctrl-C stops the benchmarks once the runs at hand are written, and the report
+    // still gets the measurements so far
+    watch_interrupts()?;
+    for benchmark in selected {
+        if interrupted() {
+            eprintln!("Interrupted, skipping {} and the benchmarks after it", benchmark.name);
+            break;
+        }
+        (benchmark.run)(&options);
+    }
+    if let Some(mut report) = report {
+        report.measurements = std::mem::take(&mut *MEASUREMENTS.get().unwrap().lock().unwrap());
+        let file_name: String = format!("report-{}.json", report.started_at);
+        report.write_to(File::create(OUTPUT_DIR.get().unwrap().join(file_name))?)?;
+    }
     Ok(())
 }
@@ -0,0 +1,113 @@
+use rand::prelude::*;
+use crate::alias::AliasTable;
+
+// Matrices are vectors of rows, as in freivalds, but with real entries.
+
+fn inner_dimension(a: &[Vec<f64>], b: &[Vec<f64>]) -> usize {
+    let inner: usize = a.first().map_or(0, |row| row.len());
+    assert!(inner == b.len(), "Matrix dimensions do not match");
+    return inner;
+}
+
+// The product A B the usual way, in O(m n p) time
+pub fn exact_product(a: &[Vec<f64>], b: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    inner_dimension(a, b);
+    let columns: usize = b.first().map_or(0, |row| row.len());
+    return a.iter().map(|row| {
+        let mut product: Vec<f64> = vec![0.0; columns];
+        for (x, b_row) in row.iter().zip(b) {
+            product.iter_mut().zip(b_row).for_each(|(entry, y)| *entry += x * y);
+        }
+        return product;
+    }).collect();
+}
+
+pub fn frobenius_norm(matrix: &[Vec<f64>]) -> f64 {
+    return matrix.iter().flatten().map(|x| x * x).sum::<f64>().sqrt();
+}
+
+// Frobenius norm of the difference of two matrices of the same shape
+pub fn frobenius_error(approximate: &[Vec<f64>], exact: &[Vec<f64>]) -> f64 {
+    assert!(approximate.len() == exact.len(), "Matrix dimensions do not match");
+    return approximate.iter().zip(exact)
+        .flat_map(|(x_row, y_row)| x_row.iter().zip(y_row).map(|(x, y)| (x - y) * (x - y)))
+        .sum::<f64>()
+        .sqrt();
+}
+
+// Probability of outer product k in the sampling, |A^(k)| |B_(k)| over the sum of these,
+// for column k of A and row k of B. It minimizes the expected squared error of the
+// estimate among all sampling distributions.
+pub fn sampling_probabilities(a: &[Vec<f64>], b: &[Vec<f64>]) -> Vec<f64> {
+    let inner: usize = inner_dimension(a, b);
+    let weights: Vec<f64> = (0..inner).map(|k| {
+        let column: f64 = a.iter().map(|row| row[k] * row[k]).sum::<f64>().sqrt();
+        let row: f64 = b[k].iter().map(|x| x * x).sum::<f64>().sqrt();
+        return column * row;
+    }).collect();
+    let total: f64 = weights.iter().sum();
+    if total == 0.0 {
+        return vec![1.0 / inner as f64; inner];
+    }
+    return weights.iter().map(|w| w / total).collect();
+}
+
+// Approximate A B from samples of the n outer products A^(k) B_(k) it is the sum of, drawn
+// with replacement with the probabilities above and each scaled by 1 / (samples p_k), so
+// the estimate is unbiased (Drineas, Kannan and Mahoney). Its expected squared Frobenius
+// error is at most |A|_F^2 |B|_F^2 / samples, and each sample costs O(m p) instead of the
+// O(m n p) of the exact product.
+pub fn approximate_product(a: &[Vec<f64>], b: &[Vec<f64>], samples: usize, rng: &mut impl Rng) -> Vec<Vec<f64>> {
+    assert!(samples > 0, "Needs at least one sample");
+    let probabilities: Vec<f64> = sampling_probabilities(a, b);
+    let columns: usize = b.first().map_or(0, |row| row.len());
+    let mut product: Vec<Vec<f64>> = vec![vec![0.0; columns]; a.len()];
+    if probabilities.is_empty() {
+        return product;
+    }
+    let table: AliasTable = AliasTable::new(&probabilities);
+    for _ in 0..samples {
+        let k: usize = table.sample(rng);
+        let scale: f64 = 1.0 / (samples as f64 * probabilities[k]);
+        for (product_row, a_row) in product.iter_mut().zip(a) {
+            let x: f64 = a_row[k] * scale;
+            product_row.iter_mut().zip(&b[k]).for_each(|(entry, y)| *entry += x * y);
+        }
+    }
+    return product;
+}
+
+// The bound |A|_F |B|_F / sqrt(samples) on the root mean squared Frobenius error
+pub fn error_bound(a: &[Vec<f64>], b: &[Vec<f64>], samples: usize) -> f64 {
+    return frobenius_norm(a) * frobenius_norm(b) / (samples as f64).sqrt();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::gaussian;
+
+    #[test]
+    fn sampled_products_stay_within_the_error_bound() {
+        let mut rng = thread_rng();
+        // Columns of A of very different norms, so norm sampling matters
+        let a: Vec<Vec<f64>> = (0..30).map(|_| (0..200).map(|k| gaussian() * (1.0 + (k % 10) as f64)).collect()).collect();
+        let b: Vec<Vec<f64>> = (0..200).map(|_| (0..20).map(|_| gaussian()).collect()).collect();
+        let exact: Vec<Vec<f64>> = exact_product(&a, &b);
+        assert!((sampling_probabilities(&a, &b).iter().sum::<f64>() - 1.0).abs() < 1e-9);
+
+        // The mean squared error over 50 estimates is below the bound on its expectation
+        for samples in [10, 100, 1000] {
+            let squared: f64 = (0..50).map(|_| frobenius_error(&approximate_product(&a, &b, samples, &mut rng), &exact).powi(2)).sum::<f64>() / 50.0;
+            assert!(squared.sqrt() < error_bound(&a, &b, samples), "{} {} {}", samples, squared.sqrt(), error_bound(&a, &b, samples));
+        }
+
+        // Unbiased: the average of many estimates closes in on the product
+        let mut average: Vec<Vec<f64>> = vec![vec![0.0; 20]; 30];
+        for _ in 0..200 {
+            let estimate: Vec<Vec<f64>> = approximate_product(&a, &b, 50, &mut rng);
+            average.iter_mut().flatten().zip(estimate.iter().flatten()).for_each(|(x, y)| *x += y / 200.0);
+        }
+        assert!(frobenius_error(&average, &exact) < 2.0 * error_bound(&a, &b, 50 * 200), "{}", frobenius_error(&average, &exact));
+    }
+}
@@ -0,0 +1,187 @@
+use rand::prelude::*;
+use crate::annealing::Annealable;
+use crate::graph::{Cut, Graph};
+use crate::hash::gaussian;
+
+// Max-Cut asks for the cut with the most edges. Goemans and Williamson relax it to unit
+// vectors v_u, one per vertex, maximizing sum over edges uv of (1 - v_u . v_v) / 2: a cut
+// is the special case of vectors +-x, so the best vectors are worth at least the maximum
+// cut. Rounding cuts the vectors by a random hyperplane through the origin.
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    return a.iter().zip(b).map(|(x, y)| x * y).sum();
+}
+
+fn normalize(v: &mut [f64]) {
+    let norm: f64 = dot(v, v).sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+// Value of the relaxation for an embedding of unit vectors
+pub fn relaxation_value(graph: &Graph, embedding: &[Vec<f64>]) -> f64 {
+    return graph.edges().iter().map(|(u, v)| (1.0 - dot(&embedding[*u], &embedding[*v])) / 2.0).sum();
+}
+
+// Heuristic embedding in place of the semidefinite program: random unit vectors in the
+// given dimension, then rounds of moving every vertex to the unit vector opposite the sum
+// of its neighbors, which maximizes the relaxation over that vertex alone. This climbs to a
+// local optimum of the rank restricted relaxation (Burer and Monteiro), which for
+// dimension about sqrt(2n) is the optimum of the full program.
+pub fn local_embedding(graph: &Graph, dimension: usize, rounds: usize) -> Vec<Vec<f64>> {
+    let adjacency: Vec<Vec<usize>> = graph.adjacency();
+    let mut embedding: Vec<Vec<f64>> = (0..graph.vertices())
+        .map(|_| {
+            let mut v: Vec<f64> = (0..dimension).map(|_| gaussian()).collect();
+            normalize(&mut v);
+            return v;
+        })
+        .collect();
+    for _ in 0..rounds {
+        for u in 0..graph.vertices() {
+            if adjacency[u].is_empty() {
+                continue;
+            }
+            let mut opposite: Vec<f64> = vec![0.0; dimension];
+            adjacency[u].iter().for_each(|v| opposite.iter_mut().zip(&embedding[*v]).for_each(|(a, x)| *a -= x));
+            normalize(&mut opposite);
+            embedding[u] = opposite;
+        }
+    }
+    return embedding;
+}
+
+// Cut by the hyperplane orthogonal to a random Gaussian vector r, putting u on the side of
+// the sign of v_u . r. The hyperplane separates v_u and v_v with probability theta / pi for
+// the angle theta between them, and theta / pi >= 0.878 (1 - cos theta) / 2, so the
+// expected cut is at least 0.878 times the relaxation value.
+pub fn hyperplane_cut(graph: &Graph, embedding: &[Vec<f64>]) -> Cut {
+    let dimension: usize = embedding.first().map_or(0, |v| v.len());
+    let r: Vec<f64> = (0..dimension).map(|_| gaussian()).collect();
+    let side: Vec<bool> = embedding.iter().map(|v| dot(v, &r) >= 0.0).collect();
+    return Cut { size: graph.cut_size(&side), side }
+}
+
+// Expected size of hyperplane_cut, the sum over edges of angle / pi
+pub fn expected_hyperplane_cut(graph: &Graph, embedding: &[Vec<f64>]) -> f64 {
+    return graph.edges().iter()
+        .map(|(u, v)| dot(&embedding[*u], &embedding[*v]).clamp(-1.0, 1.0).acos() / std::f64::consts::PI)
+        .sum();
+}
+
+// The baseline: every vertex picks a side by a fair coin, which cuts every edge with
+// probability 1/2 and so half of the edges in expectation
+pub fn random_cut(graph: &Graph, rng: &mut impl Rng) -> Cut {
+    let side: Vec<bool> = (0..graph.vertices()).map(|_| rng.gen()).collect();
+    return Cut { size: graph.cut_size(&side), side }
+}
+
+// Best of the given number of hyperplane cuts of one embedding
+pub fn goemans_williamson(graph: &Graph, embedding: &[Vec<f64>], trials: usize) -> Cut {
+    return (0..trials.max(1))
+        .map(|_| hyperplane_cut(graph, embedding))
+        .max_by_key(|cut| cut.size)
+        .unwrap();
+}
+
+// A cut as a simulated annealing state: a move flips one vertex to the other side, and the
+// energy is minus the cut size. A flip changes the cut by the neighbors on the vertex's own
+// side minus those on the other side, so a move is scored in O(degree).
+#[derive(Clone, Debug)]
+pub struct CutState<'a> {
+    adjacency: &'a [Vec<usize>],
+    side: Vec<bool>,
+    size: usize,
+}
+
+impl<'a> CutState<'a> {
+    // Starts from the cut given by side, on a graph given by its adjacency lists
+    pub fn new(adjacency: &'a [Vec<usize>], side: Vec<bool>) -> CutState<'a> {
+        assert!(adjacency.len() == side.len(), "Needs a side for every vertex");
+        let size: usize = adjacency.iter().enumerate()
+            .map(|(u, neighbors)| neighbors.iter().filter(|v| side[**v] != side[u]).count())
+            .sum::<usize>() / 2;
+        return CutState { adjacency, side, size }
+    }
+    pub fn cut(&self) -> Cut {
+        return Cut { size: self.size, side: self.side.clone() }
+    }
+}
+
+impl Annealable for CutState<'_> {
+    type Move = usize;
+
+    fn energy(&self) -> f64 {
+        return -(self.size as f64);
+    }
+    fn propose(&self, rng: &mut impl Rng) -> usize {
+        return rng.gen_range(0..self.side.len());
+    }
+    fn delta(&self, v: &usize) -> f64 {
+        let across: usize = self.adjacency[*v].iter().filter(|w| self.side[**w] != self.side[*v]).count();
+        return across as f64 - (self.adjacency[*v].len() - across) as f64;
+    }
+    fn apply(&mut self, v: usize) {
+        let across: usize = self.adjacency[v].iter().filter(|w| self.side[**w] != self.side[v]).count();
+        self.size = self.size + self.adjacency[v].len() - 2 * across;
+        self.side[v] = !self.side[v];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annealing::{anneal, Annealed, Schedule};
+
+    #[test]
+    fn hyperplanes_cut_bipartite_and_odd_cycles() {
+        // Vertices of an even cycle alternate between opposite vectors, so every hyperplane
+        // cuts all of its edges
+        let mut cycle: Graph = Graph::new(20);
+        (0..20).for_each(|v| cycle.add_edge(v, (v + 1) % 20));
+        let embedding: Vec<Vec<f64>> = local_embedding(&cycle, 3, 200);
+        assert!((relaxation_value(&cycle, &embedding) - 20.0).abs() < 1e-6);
+        assert_eq!(hyperplane_cut(&cycle, &embedding).size, 20);
+
+        // The optimum for the 5-cycle puts the vectors on a pentagram with angles 4 pi / 5,
+        // worth 5 (1 - cos(4 pi / 5)) / 2 ≈ 4.52 against a maximum cut of 4
+        let mut pentagon: Graph = Graph::new(5);
+        (0..5).for_each(|v| pentagon.add_edge(v, (v + 1) % 5));
+        let embedding: Vec<Vec<f64>> = local_embedding(&pentagon, 2, 1000);
+        assert!((relaxation_value(&pentagon, &embedding) - 4.5225).abs() < 1e-3, "{}", relaxation_value(&pentagon, &embedding));
+        assert_eq!(goemans_williamson(&pentagon, &embedding, 20).size, 4);
+    }
+
+    #[test]
+    fn hyperplane_cuts_beat_the_guarantee() {
+        let mut rng = thread_rng();
+        let graph: Graph = Graph::erdos_renyi(100, 0.1, &mut rng);
+        let embedding: Vec<Vec<f64>> = local_embedding(&graph, 15, 100);
+        let expected: f64 = expected_hyperplane_cut(&graph, &embedding);
+        assert!(expected >= 0.878 * relaxation_value(&graph, &embedding));
+        // A cut has at most the about 500 edges, so the mean of 2000 cuts is well within 15
+        // of its expectation
+        let mean: f64 = (0..2000).map(|_| hyperplane_cut(&graph, &embedding).size as f64).sum::<f64>() / 2000.0;
+        assert!((mean - expected).abs() < 15.0, "{} {}", mean, expected);
+        let coin: f64 = (0..2000).map(|_| random_cut(&graph, &mut rng).size as f64).sum::<f64>() / 2000.0;
+        assert!(mean > coin + 0.1 * graph.edges().len() as f64, "{} {}", mean, coin);
+    }
+
+    #[test]
+    fn annealing_cuts_every_edge_of_a_bipartite_graph() {
+        // Edges only between the even and the odd vertices, so the maximum cut has them all
+        let mut rng = thread_rng();
+        let mut bipartite: Graph = Graph::new(100);
+        for u in (0..100).step_by(2) {
+            (1..100).step_by(2).filter(|_| rng.gen_bool(0.1)).for_each(|v| bipartite.add_edge(u, v));
+        }
+        let adjacency: Vec<Vec<usize>> = bipartite.adjacency();
+        let start: CutState = CutState::new(&adjacency, random_cut(&bipartite, &mut rng).side);
+        assert_eq!(start.cut().size, bipartite.cut_size(&start.cut().side));
+        let annealed: Annealed<CutState> = anneal(start, Schedule::Geometric { start: 2.0, end: 0.01 }, 200_000, &mut rng, |_, _, _| {});
+        let cut: Cut = annealed.best.cut();
+        assert_eq!((cut.size, bipartite.cut_size(&cut.side)), (bipartite.edges().len(), bipartite.edges().len()));
+        assert_eq!(annealed.best_energy, -(cut.size as f64));
+    }
+}
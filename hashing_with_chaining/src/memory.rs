@@ -0,0 +1,52 @@
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+// Memory a structure holds, so structures of different kinds can be compared by how much
+// space they spend for their speed or accuracy. The bytes are the ones space_usage reports,
+// the heap behind the structure without the few words of the struct itself.
+pub trait MemoryFootprint {
+    fn heap_bytes(&self) -> usize;
+    // Separate heap allocations behind those bytes, which cost allocator overhead and
+    // pointer chasing that the byte count does not show
+    fn allocations(&self) -> usize;
+    // Bits spent per key for a structure built over the given number of keys
+    fn bits_per_key(&self, keys: usize) -> f64 {
+        return (8 * self.heap_bytes()) as f64 / keys.max(1) as f64;
+    }
+}
+
+// Allocations of a vector: one once it has reserved any memory
+pub fn vec_allocations<T>(vec: &Vec<T>) -> usize {
+    return (vec.capacity() > 0 && size_of::<T>() > 0) as usize;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bloom::{BloomFilter, ScalableBloomFilter};
+    use crate::count_sketch::CountSketch;
+    use crate::hwc::{ArenaChains, HwC};
+
+    #[test]
+    fn footprints_follow_the_layout() {
+        // A table of 2^12 buckets starts out as the vector of its four pages, which are
+        // allocated as keys land in them
+        let mut hwc: HwC = HwC::new(1 << 12);
+        assert_eq!(hwc.allocations(), 1);
+        (1..=100).for_each(|key| hwc.insert(key, 1));
+        assert!(hwc.allocations() >= 2);
+        assert_eq!(hwc.heap_bytes(), hwc.space_usage());
+        assert_eq!(hwc.bits_per_key(100), (8 * hwc.space_usage()) as f64 / 100.0);
+        let arena: HwC<ArenaChains> = HwC::new(1 << 12);
+        assert_eq!(arena.allocations(), 2);
+
+        let sketch: CountSketch = CountSketch::new(5, 10);
+        assert_eq!((sketch.heap_bytes(), sketch.allocations()), (5 * 1024 * 8, 6));
+        let bloom: BloomFilter = BloomFilter::new(16, 4);
+        assert_eq!(bloom.bits_per_key(1 << 12), 16.0);
+        let mut scalable: ScalableBloomFilter = ScalableBloomFilter::new(100, 0.01);
+        (0..1000).for_each(|key| scalable.insert(key));
+        assert_eq!(scalable.heap_bytes(), scalable.space_usage());
+        assert!(scalable.allocations() > 2);
+    }
+}
@@ -0,0 +1,81 @@
+use core::fmt;
+#[cfg(feature = "harness")]
+use rayon::prelude::*;
+#[cfg(feature = "harness")]
+use rayon::ThreadPoolBuilder;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeError {
+    // The sketches have a different number of rows or counters
+    DimensionMismatch,
+    // The sketches hash keys differently, so their counters do not line up
+    SeedMismatch,
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MergeError::DimensionMismatch => write!(f, "sketches have different dimensions"),
+            MergeError::SeedMismatch => write!(f, "sketches use different hash functions"),
+        }
+    }
+}
+
+impl core::error::Error for MergeError {}
+
+// Sketches of two streams that combine into the sketch of the concatenated stream.
+// Both sides must be built with the same dimensions and hash functions, which is
+// easiest by cloning one empty sketch for every shard.
+pub trait MergeableSketch {
+    fn merge(&mut self, other: &Self) -> Result<(), MergeError>;
+}
+
+// Applies the updates from a pool of `threads` threads. Every thread feeds an equal slice of
+// the updates to its own clone of empty, which must be a sketch without any updates yet so
+// no counts are taken twice, and the clones are merged at the end. The result is the sketch
+// of all updates, the same as one thread would build for sketches whose merge is exact.
+#[cfg(feature = "harness")]
+pub fn par_update<S, T, F>(empty: &S, updates: &[T], threads: usize, update: F) -> S
+where
+    S: MergeableSketch + Clone + Send + Sync,
+    T: Sync,
+    F: Fn(&mut S, &T) + Sync,
+{
+    assert!(threads > 0, "Needs at least one thread");
+    let chunk_len: usize = updates.len().div_ceil(threads).max(1);
+    let pool = ThreadPoolBuilder::new().num_threads(threads).build().expect("Cannot start the thread pool");
+    return pool.install(|| {
+        updates.par_chunks(chunk_len)
+            .map(|chunk| {
+                let mut shard: S = empty.clone();
+                chunk.iter().for_each(|x| update(&mut shard, x));
+                shard
+            })
+            .reduce(|| empty.clone(), |mut merged, shard| {
+                merged.merge(&shard).expect("Clones of one sketch always merge");
+                merged
+            })
+    });
+}
+
+#[cfg(all(test, feature = "harness"))]
+mod tests {
+    use super::*;
+    use crate::count_min::{CountMin, UpdatePolicy};
+    use crate::count_sketch::CountSketch;
+
+    #[test]
+    fn parallel_updates_match_one_thread() {
+        let updates: Vec<(u64, i64)> = (0..200_000u64).map(|x| (x * x % 9973, x as i64 % 7 - 3)).collect();
+        let empty: CountSketch = CountSketch::new(5, 10);
+        let mut sequential: CountSketch = empty.clone();
+        updates.iter().for_each(|(key, delta)| sequential.insert(*key, *delta));
+        for threads in [1, 3, 8] {
+            let parallel: CountSketch = par_update(&empty, &updates, threads, |sketch, (key, delta)| sketch.insert(*key, *delta));
+            assert!((0..9973).all(|key| parallel.estimate(key) == sequential.estimate(key)));
+        }
+
+        let counts: CountMin = par_update(&CountMin::new(3, 6, UpdatePolicy::Standard), &[] as &[u64], 4, |sketch, key| sketch.insert(*key, 1));
+        assert_eq!(counts.estimate(1), 0);
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt;
+use std::ops::{Div, Sub};
+use std::time::{Duration, Instant};
+use crate::reservoir::{Reservoir, SamplingAlgorithm};
+
+// Timing of the experiments on the monotonic clock. The wall clock can jump when the
+// system adjusts it and is coarse on some platforms, which the short phases of the small
+// test sizes cannot afford. Two readings of now subtract to the Elapsed time between them,
+// which prints with a fitting unit and turns a number of operations into a throughput.
+
+#[derive(Clone, Copy, Debug)]
+pub struct Timestamp(Instant);
+
+pub fn now() -> Timestamp {
+    return Timestamp(Instant::now());
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Elapsed(Duration);
+
+impl Elapsed {
+    pub fn from_nanoseconds(nanoseconds: u64) -> Elapsed {
+        return Elapsed(Duration::from_nanos(nanoseconds));
+    }
+    pub fn whole_nanoseconds(&self) -> i128 {
+        return self.0.as_nanos() as i128;
+    }
+    pub fn as_seconds_f64(&self) -> f64 {
+        return self.0.as_secs_f64();
+    }
+    // Operations per second, infinite for a phase too short to measure
+    pub fn ops_per_second(&self, operations: u64) -> f64 {
+        return operations as f64 / self.as_seconds_f64();
+    }
+    pub fn ns_per_op(&self, operations: u64) -> f64 {
+        return self.whole_nanoseconds() as f64 / operations.max(1) as f64;
+    }
+}
+
+impl From<Duration> for Elapsed {
+    fn from(duration: Duration) -> Elapsed {
+        return Elapsed(duration);
+    }
+}
+
+impl Sub for Timestamp {
+    type Output = Elapsed;
+
+    fn sub(self, earlier: Timestamp) -> Elapsed {
+        return Elapsed(self.0.saturating_duration_since(earlier.0));
+    }
+}
+
+// The time per operation of a phase that did operations of the same kind
+impl Div<f64> for Elapsed {
+    type Output = Elapsed;
+
+    fn div(self, operations: f64) -> Elapsed {
+        return Elapsed(self.0.div_f64(operations));
+    }
+}
+
+// Percentiles of the latencies of single operations, by name
+pub const LATENCY_PERCENTILES: [(&str, f64); 4] = [("p50", 0.5), ("p90", 0.9), ("p99", 0.99), ("p999", 0.999)];
+
+// Latencies of single operations, reservoir sampled so a phase of any length keeps at most
+// capacity of them, with the percentiles of the sample standing in for those of every
+// operation. The mean of a phase hides the rare slow operations, like the rehash of a
+// cuckoo table, that show up in the high percentiles.
+pub struct Latencies {
+    sample: Reservoir<Elapsed>,
+}
+
+impl Latencies {
+    pub fn new(capacity: usize) -> Latencies {
+        return Latencies { sample: Reservoir::new(capacity, SamplingAlgorithm::L) }
+    }
+    pub fn record(&mut self, latency: Elapsed) {
+        self.sample.insert(latency);
+    }
+    // Does the operation, recording how long it took
+    pub fn time<T>(&mut self, operation: impl FnOnce() -> T) -> T {
+        let start: Timestamp = now();
+        let result: T = operation();
+        self.record(now() - start);
+        return result;
+    }
+    // Number of operations recorded, sampled or not
+    pub fn operations(&self) -> u64 {
+        return self.sample.seen();
+    }
+    // Number of latencies kept
+    pub fn samples(&self) -> usize {
+        return self.sample.sample().len();
+    }
+    // The latencies at LATENCY_PERCENTILES by the nearest rank, and the longest one sampled,
+    // or nothing before the first operation
+    pub fn percentiles(&self) -> Option<([Elapsed; 4], Elapsed)> {
+        let mut sorted: Vec<Elapsed> = self.sample.sample().to_vec();
+        sorted.sort_unstable();
+        let rank = |q: f64| ((q * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len()) - 1;
+        let max: Elapsed = *sorted.last()?;
+        return Some((LATENCY_PERCENTILES.map(|(_, q)| sorted[rank(q)]), max));
+    }
+}
+
+// Seconds, milliseconds, microseconds or nanoseconds, whichever keeps the value between 1
+// and 1000
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let seconds: f64 = self.as_seconds_f64();
+        return match seconds {
+            s if s >= 1.0 => write!(f, "{:.3}s", s),
+            s if s >= 1e-3 => write!(f, "{:.3}ms", s * 1e3),
+            s if s >= 1e-6 => write!(f, "{:.3}µs", s * 1e6),
+            _ => write!(f, "{}ns", self.whole_nanoseconds()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elapsed_times_print_and_give_throughput() {
+        let start: Timestamp = now();
+        let stop: Timestamp = now();
+        assert!(stop - start >= Elapsed::default());
+        assert_eq!(start - stop, Elapsed::default());
+
+        let phase: Elapsed = Elapsed::from_nanoseconds(2_500_000_000);
+        assert_eq!(phase.to_string(), "2.500s");
+        assert_eq!((phase / 1000.0).to_string(), "2.500ms");
+        assert_eq!(Elapsed::from_nanoseconds(1234).to_string(), "1.234µs");
+        assert_eq!(Elapsed::from_nanoseconds(42).to_string(), "42ns");
+        assert_eq!(phase.ops_per_second(5_000_000), 2e6);
+        assert_eq!(phase.ns_per_op(5_000_000), 500.0);
+
+        // 1 to 1000ns, sampled down to 100 latencies
+        let mut latencies: Latencies = Latencies::new(100);
+        assert!(latencies.percentiles().is_none());
+        (1..=1000).for_each(|ns| latencies.record(Elapsed::from_nanoseconds(ns)));
+        assert_eq!(latencies.operations(), 1000);
+        let (percentiles, max): ([Elapsed; 4], Elapsed) = latencies.percentiles().unwrap();
+        assert!(percentiles.windows(2).all(|pair| pair[0] <= pair[1]) && percentiles[3] <= max);
+        assert!((200..800).contains(&percentiles[0].whole_nanoseconds()));
+        let mut exact: Latencies = Latencies::new(1000);
+        (1..=1000).rev().for_each(|ns| exact.record(Elapsed::from_nanoseconds(ns)));
+        let nanoseconds: Vec<i128> = exact.percentiles().unwrap().0.iter().map(Elapsed::whole_nanoseconds).collect();
+        assert_eq!(nanoseconds, vec![500, 900, 990, 999]);
+        assert_eq!(exact.time(|| 7), 7);
+    }
+}
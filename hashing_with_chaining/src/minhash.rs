@@ -0,0 +1,288 @@
+use std::mem::size_of;
+use serde::{Deserialize, Serialize};
+use crate::hash::IndependentHash;
+use crate::mergeable::{MergeError, MergeableSketch};
+use crate::sketch_format::{ByteReader, ByteWriter, DecodeError, SketchKind};
+
+// MinHash signature of a set: for each of k hash functions, the smallest hash value of
+// any key in the set. Two sets agree on a coordinate with probability about equal to
+// their Jaccard similarity |A n B| / |A u B|, as the key with the smallest hash
+// of the union is equally likely to be any key of the union.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MinHash {
+    hash_functions: Vec<IndependentHash>,
+    mins: Vec<u64>,
+}
+
+impl MinHash {
+    // Signatures are only comparable when they share their hash functions, so
+    // signatures of different sets should be cloned from one empty MinHash
+    pub fn new(k: usize) -> MinHash {
+        return MinHash {
+            // Min-wise hashing needs more than pairwise independence to be unbiased
+            hash_functions: (0..k).map(|_| IndependentHash::new(4)).collect(),
+            mins: vec![u64::MAX; k],
+        }
+    }
+    pub fn insert(&mut self, key: u64) {
+        for (min, hash_function) in self.mins.iter_mut().zip(&self.hash_functions) {
+            *min = (*min).min(hash_function.hash(key));
+        }
+    }
+    // Fraction of coordinates on which the signatures agree. Panics if the signatures
+    // do not share their hash functions.
+    pub fn jaccard(&self, other: &MinHash) -> f64 {
+        assert!(self.hash_functions == other.hash_functions, "Signatures use different hash functions");
+        let equal: usize = self.mins.iter().zip(&other.mins).filter(|(a, b)| a == b).count();
+        return equal as f64 / self.mins.len() as f64;
+    }
+    // Keeps only the lowest b bits of every coordinate
+    pub fn to_b_bit(&self, b: u32) -> BBitSignature {
+        assert!((1..=16).contains(&b), "b must be between 1 and 16");
+        let mut words: Vec<u64> = vec![0; (self.mins.len() * b as usize).div_ceil(64)];
+        for (i, min) in self.mins.iter().enumerate() {
+            for bit in 0..b as usize {
+                if min >> bit & 1 == 1 {
+                    let pos: usize = i * b as usize + bit;
+                    words[pos / 64] |= 1 << (pos % 64);
+                }
+            }
+        }
+        return BBitSignature { words, b, k: self.mins.len() }
+    }
+    pub fn signature(&self) -> &[u64] {
+        return &self.mins;
+    }
+    pub fn len(&self) -> usize {
+        return self.mins.len();
+    }
+    pub fn is_empty(&self) -> bool {
+        return self.mins.is_empty();
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.mins.len() * size_of::<u64>();
+    }
+    // Number of coordinates (u64), followed by every coordinate as its hash function and
+    // minimum (u64)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new(SketchKind::MinHash);
+        writer.u64(self.mins.len() as u64);
+        for (hash_function, min) in self.hash_functions.iter().zip(&self.mins) {
+            writer.independent_hash(hash_function);
+            writer.u64(*min);
+        }
+        return writer.finish();
+    }
+    // A minimum is either a hash value, below 2^61 - 1, or u64::MAX for an empty set
+    pub fn from_bytes(bytes: &[u8]) -> Result<MinHash, DecodeError> {
+        let mut reader: ByteReader = ByteReader::new(bytes, SketchKind::MinHash)?;
+        let k: usize = reader.len(size_of::<u32>() + size_of::<u64>())?;
+        let mut signature: MinHash = MinHash { hash_functions: Vec::with_capacity(k), mins: Vec::with_capacity(k) };
+        for _ in 0..k {
+            signature.hash_functions.push(reader.independent_hash()?);
+            let min: u64 = reader.u64()?;
+            if min >= (1 << 61) - 1 && min != u64::MAX {
+                return Err(DecodeError::Invalid("minimum out of range"));
+            }
+            signature.mins.push(min);
+        }
+        reader.finish()?;
+        return Ok(signature);
+    }
+}
+
+// b-bit MinHash (Li and Konig): a MinHash signature with only the lowest b bits of
+// every coordinate, packed into words. Coordinates of different sets now also agree by
+// chance, with probability 2^-b since the hash values come from a universe far larger
+// than the sets, and jaccard corrects for that.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BBitSignature {
+    words: Vec<u64>,
+    b: u32,
+    k: usize,
+}
+
+impl BBitSignature {
+    fn coordinate(&self, i: usize) -> u64 {
+        let mut value: u64 = 0;
+        for bit in 0..self.b as usize {
+            let pos: usize = i * self.b as usize + bit;
+            value |= (self.words[pos / 64] >> (pos % 64) & 1) << bit;
+        }
+        return value;
+    }
+    // Solves P(match) = 2^-b + (1 - 2^-b) J for J. Both signatures must come from
+    // MinHash signatures with the same hash functions.
+    pub fn jaccard(&self, other: &BBitSignature) -> f64 {
+        assert!(self.b == other.b && self.k == other.k, "Signatures have different dimensions");
+        let equal: usize = (0..self.k).filter(|i| self.coordinate(*i) == other.coordinate(*i)).count();
+        let chance: f64 = (-(self.b as f64)).exp2();
+        return ((equal as f64 / self.k as f64 - chance) / (1.0 - chance)).max(0.0);
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.words.len() * size_of::<u64>();
+    }
+}
+
+// One-permutation hashing (Li, Owen and Zhang): a single hash per key, whose value
+// picks one of k bins and competes for the minimum of that bin only, so building a
+// signature costs one hash per key instead of k. Bins no key fell into are filled by
+// optimal densification (Shrivastava): an empty bin keeps probing bins with its own
+// sequence of random bins until it finds one that is not empty, and copies its value.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OnePermutationHash {
+    hash_function: IndependentHash,
+    densify_hash: IndependentHash,
+    bins: Vec<u64>,
+}
+
+// Marks a bin no key has fallen into
+const EMPTY_BIN: u64 = u64::MAX;
+
+impl OnePermutationHash {
+    pub fn new(k: usize) -> OnePermutationHash {
+        assert!(k > 0, "Needs at least one bin");
+        return OnePermutationHash {
+            hash_function: IndependentHash::new(4),
+            densify_hash: IndependentHash::new(2),
+            bins: vec![EMPTY_BIN; k],
+        }
+    }
+    // Maps a hash value in [0, 2^61 - 1) to one of the bins
+    fn bin_of(&self, hash_val: u64) -> usize {
+        return ((hash_val as u128 * self.bins.len() as u128) >> 61) as usize;
+    }
+    pub fn insert(&mut self, key: u64) {
+        let hash_val: u64 = self.hash_function.hash(key);
+        let bin: usize = self.bin_of(hash_val);
+        self.bins[bin] = self.bins[bin].min(hash_val);
+    }
+    // The densified signature, empty only if no key was inserted. Sets with the same
+    // hash functions probe the same bins for the same empty bin, which keeps
+    // the agreement rate an unbiased estimate of the Jaccard similarity.
+    pub fn signature(&self) -> Vec<u64> {
+        if self.bins.iter().all(|bin| *bin == EMPTY_BIN) {
+            return Vec::new();
+        }
+        return (0..self.bins.len()).map(|i| {
+            let mut attempt: u64 = 0;
+            let mut bin: usize = i;
+            while self.bins[bin] == EMPTY_BIN {
+                attempt += 1;
+                bin = self.bin_of(self.densify_hash.hash((i as u64) << 32 | attempt));
+            }
+            self.bins[bin]
+        }).collect();
+    }
+    // Fraction of agreeing coordinates of the densified signatures
+    pub fn jaccard(&self, other: &OnePermutationHash) -> f64 {
+        assert!(self.hash_function == other.hash_function && self.densify_hash == other.densify_hash,
+            "Signatures use different hash functions");
+        let (a, b) = (self.signature(), other.signature());
+        if a.is_empty() || b.is_empty() {
+            return 0.0;
+        }
+        return a.iter().zip(&b).filter(|(x, y)| x == y).count() as f64 / a.len() as f64;
+    }
+}
+
+// The signature of a union is the coordinate-wise minimum
+impl MergeableSketch for MinHash {
+    fn merge(&mut self, other: &MinHash) -> Result<(), MergeError> {
+        if self.mins.len() != other.mins.len() {
+            return Err(MergeError::DimensionMismatch);
+        }
+        if self.hash_functions != other.hash_functions {
+            return Err(MergeError::SeedMismatch);
+        }
+        for (min, other_min) in self.mins.iter_mut().zip(&other.mins) {
+            *min = (*min).min(*other_min);
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_jaccard_and_merges_unions() {
+        let mut a: MinHash = MinHash::new(512);
+        let mut b: MinHash = a.clone();
+        // |A n B| = 5000 and |A u B| = 15000
+        for key in 0..10_000u64 {
+            a.insert(key);
+        }
+        for key in 5_000..15_000u64 {
+            b.insert(key);
+        }
+        // The standard error is sqrt(J (1 - J) / k), about 0.02
+        assert!((a.jaccard(&b) - 1.0 / 3.0).abs() < 0.1);
+
+        let mut union: MinHash = a.clone();
+        union.merge(&b).unwrap();
+        let mut direct: MinHash = MinHash { mins: vec![u64::MAX; 512], ..a.clone() };
+        (0..15_000u64).for_each(|key| direct.insert(key));
+        assert_eq!(union.signature(), direct.signature());
+        assert_eq!(union.jaccard(&direct), 1.0);
+        assert_eq!(a.merge(&MinHash::new(512)).err(), Some(MergeError::SeedMismatch));
+    }
+
+    #[test]
+    fn one_permutation_hashing_densifies_sparse_sets() {
+        let mut a: OnePermutationHash = OnePermutationHash::new(256);
+        let mut b: OnePermutationHash = a.clone();
+        for key in 0..10_000u64 {
+            a.insert(key);
+        }
+        for key in 5_000..15_000u64 {
+            b.insert(key);
+        }
+        assert!((a.jaccard(&b) - 1.0 / 3.0).abs() < 0.1);
+
+        // 20 keys leave most of the 256 bins empty
+        let mut small: OnePermutationHash = OnePermutationHash { bins: vec![EMPTY_BIN; 256], ..a.clone() };
+        let mut other: OnePermutationHash = small.clone();
+        (0..20u64).for_each(|key| small.insert(key));
+        (10..30u64).for_each(|key| other.insert(key));
+        assert!(small.signature().iter().all(|value| *value != EMPTY_BIN));
+        assert!((small.jaccard(&other) - 1.0 / 3.0).abs() < 0.2);
+        assert_eq!(small.jaccard(&small), 1.0);
+    }
+
+    #[test]
+    fn b_bit_signatures_correct_for_chance_matches() {
+        let mut a: MinHash = MinHash::new(1024);
+        let mut b: MinHash = a.clone();
+        for key in 0..10_000u64 {
+            a.insert(key);
+        }
+        for key in 5_000..15_000u64 {
+            b.insert(key);
+        }
+        let (a_bits, b_bits) = (a.to_b_bit(2), b.to_b_bit(2));
+        assert_eq!(a_bits.space_usage(), 1024 * 2 / 8);
+        assert!((0..1024).all(|i| a_bits.coordinate(i) == a.signature()[i] & 3));
+        // Without the correction this would be about 1/3 + 2/3 * 1/4 = 1/2
+        assert!((a_bits.jaccard(&b_bits) - 1.0 / 3.0).abs() < 0.1);
+        assert_eq!(a_bits.jaccard(&a_bits), 1.0);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut a: MinHash = MinHash::new(64);
+        let empty: MinHash = MinHash::from_bytes(&a.to_bytes()).unwrap();
+        (0..1000u64).for_each(|key| a.insert(key));
+        let mut restored: MinHash = MinHash::from_bytes(&a.to_bytes()).unwrap();
+        assert_eq!(restored.signature(), a.signature());
+        assert_eq!(restored.jaccard(&a), 1.0);
+        restored.merge(&empty).unwrap();
+        assert_eq!(restored.signature(), a.signature());
+
+        let mut bytes: Vec<u8> = a.to_bytes();
+        let last: usize = bytes.len();
+        bytes[last - 8..].copy_from_slice(&(1u64 << 61).to_le_bytes());
+        assert_eq!(MinHash::from_bytes(&bytes).err(), Some(DecodeError::Invalid("minimum out of range")));
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::mem::size_of;
+use crate::sketch_format::{ByteReader, ByteWriter, DecodeError, SketchKind};
+
+// Misra–Gries summary keeping at most k counters. When a new key arrives with every
+// counter taken, all counters and the new key's weight are decreased by the same amount
+// until one of them reaches zero. Every such decrement removes k + 1 times its size from
+// the stream weight, so no estimate is more than total / (k + 1) below the true count.
+pub struct MisraGries {
+    counters: HashMap<u64, u64>,
+    k: usize,
+    total: u64,
+    decremented: u64,
+}
+
+impl MisraGries {
+    pub fn new(k: usize) -> MisraGries {
+        assert!(k > 0, "Needs at least one counter");
+        return MisraGries {
+            counters: HashMap::with_capacity(k + 1),
+            k,
+            total: 0,
+            decremented: 0,
+        }
+    }
+    pub fn update(&mut self, key: u64, weight: u64) {
+        self.total += weight;
+        if let Some(count) = self.counters.get_mut(&key) {
+            *count += weight;
+            return;
+        }
+        if self.counters.len() < self.k {
+            self.counters.insert(key, weight);
+            return;
+        }
+        let decrement: u64 = self.counters.values().copied().min().unwrap_or(0).min(weight);
+        self.decremented += decrement;
+        self.counters.retain(|_, count| {
+            *count -= decrement;
+            *count > 0
+        });
+        if weight > decrement {
+            self.counters.insert(key, weight - decrement);
+        }
+    }
+    // Lower bound on the weight of key, at most error_bound() below it
+    pub fn estimate(&self, key: u64) -> u64 {
+        return self.counters.get(&key).copied().unwrap_or(0);
+    }
+    // Total amount subtracted from any single key, which is at most total() / (k + 1)
+    pub fn error_bound(&self) -> u64 {
+        return self.decremented;
+    }
+    pub fn total(&self) -> u64 {
+        return self.total;
+    }
+    // Every key whose weight may be at least threshold, which includes all keys that are.
+    // Sorted by decreasing estimate.
+    pub fn heavy_hitters(&self, threshold: u64) -> Vec<(u64, u64)> {
+        let mut heavy: Vec<(u64, u64)> = self.counters.iter()
+            .filter(|(_, count)| **count + self.decremented >= threshold)
+            .map(|(key, count)| (*key, *count))
+            .collect();
+        heavy.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        return heavy;
+    }
+    pub fn counters(&self) -> usize {
+        return self.k;
+    }
+    // Heap footprint of the counters in bytes
+    pub fn space_usage(&self) -> usize {
+        return self.counters.capacity() * (size_of::<u64>() + size_of::<u64>());
+    }
+    // k (u64) | total (u64) | decremented (u64) | number of counters (u64), followed by the
+    // key (u64) and count (u64) of every counter in order of their keys
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new(SketchKind::MisraGries);
+        writer.u64(self.k as u64);
+        writer.u64(self.total);
+        writer.u64(self.decremented);
+        let mut counters: Vec<(&u64, &u64)> = self.counters.iter().collect();
+        counters.sort_unstable();
+        writer.u64(counters.len() as u64);
+        for (key, count) in counters {
+            writer.u64(*key);
+            writer.u64(*count);
+        }
+        return writer.finish();
+    }
+    // Every decrement takes k + 1 times its size off the stream, so the counts and the
+    // decrements must add up to the total
+    pub fn from_bytes(bytes: &[u8]) -> Result<MisraGries, DecodeError> {
+        let mut reader: ByteReader = ByteReader::new(bytes, SketchKind::MisraGries)?;
+        let k: usize = reader.u64()? as usize;
+        let total: u64 = reader.u64()?;
+        let decremented: u64 = reader.u64()?;
+        let len: usize = reader.len(2 * size_of::<u64>())?;
+        if k == 0 || len > k {
+            return Err(DecodeError::Invalid("more counters than k"));
+        }
+        let mut counters: HashMap<u64, u64> = HashMap::with_capacity(len);
+        let mut previous: Option<u64> = None;
+        let mut weight: Option<u64> = (k as u64).checked_add(1).and_then(|k| k.checked_mul(decremented));
+        for _ in 0..len {
+            let (key, count) = (reader.u64()?, reader.u64()?);
+            if count == 0 || previous.is_some_and(|previous| previous >= key) {
+                return Err(DecodeError::Invalid("counters out of order or empty"));
+            }
+            counters.insert(key, count);
+            weight = weight.and_then(|weight| weight.checked_add(count));
+            previous = Some(key);
+        }
+        reader.finish()?;
+        if weight != Some(total) {
+            return Err(DecodeError::Invalid("counts and decrements do not add up to the total"));
+        }
+        return Ok(MisraGries { counters, k, total, decremented });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::random_generator;
+
+    #[test]
+    fn estimates_stay_within_the_deterministic_bound() {
+        let mut summary: MisraGries = MisraGries::new(50);
+        let mut exact: HashMap<u64, u64> = HashMap::new();
+        for i in 0..100_000u64 {
+            // Key 0 takes a fifth of the stream, the rest is spread over 1000 keys
+            let key: u64 = if i % 5 == 0 { 0 } else { random_generator(1, 1001) as u64 };
+            let weight: u64 = random_generator(1, 4) as u64;
+            summary.update(key, weight);
+            *exact.entry(key).or_default() += weight;
+        }
+        assert!(summary.error_bound() <= summary.total() / 51);
+        for (key, count) in &exact {
+            assert!(summary.estimate(*key) <= *count);
+            assert!(summary.estimate(*key) + summary.error_bound() >= *count);
+        }
+        let heavy: Vec<(u64, u64)> = summary.heavy_hitters(summary.total() / 10);
+        assert_eq!(heavy[0].0, 0);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut summary: MisraGries = MisraGries::new(8);
+        (0..10_000u64).for_each(|i| summary.update(i * i % 101, i % 3 + 1));
+        let bytes: Vec<u8> = summary.to_bytes();
+        let restored: MisraGries = MisraGries::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.heavy_hitters(0), summary.heavy_hitters(0));
+        assert_eq!(restored.error_bound(), summary.error_bound());
+        assert_eq!(restored.total(), summary.total());
+
+        let mut bytes: Vec<u8> = bytes;
+        bytes[16..24].copy_from_slice(&(summary.total() + 1).to_le_bytes());
+        assert_eq!(MisraGries::from_bytes(&bytes).err(), Some(DecodeError::Invalid("counts and decrements do not add up to the total")));
+    }
+}
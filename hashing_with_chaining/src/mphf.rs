@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::mem::size_of;
+use crate::hash::SeededHash;
+use crate::memory::{vec_allocations, MemoryFootprint};
+
+// Bits per remaining key in every level, trading space for fewer levels
+const GAMMA: f64 = 2.0;
+
+// Keys still colliding after this many levels are stored in a plain map
+const MAX_LEVELS: usize = 32;
+
+// Maps a full 64-bit hash value into 0..len without a division
+fn reduce(hash: usize, len: usize) -> usize {
+    return ((hash as u128 * len as u128) >> 64) as usize;
+}
+
+struct Level {
+    hash_function: SeededHash,
+    bits: Vec<u64>,
+    // Number of set bits in all previous levels and words, so index() is one popcount away
+    ranks: Vec<usize>,
+}
+
+impl Level {
+    fn len(&self) -> usize {
+        return self.bits.len() * 64;
+    }
+    fn position(&self, key: u64) -> usize {
+        return reduce(self.hash_function.hash(key), self.len());
+    }
+}
+
+// Minimal perfect hash function in the style of BBHash: every level hashes the keys that
+// are still unplaced into a bit array and keeps the keys that landed alone. The index of
+// a key is the rank of its bit over all levels.
+pub struct Mphf {
+    levels: Vec<Level>,
+    fallback: HashMap<u64, usize>,
+    len: usize,
+}
+
+impl Mphf {
+    pub fn build(keys: &[u64]) -> Mphf {
+        let mut remaining: Vec<u64> = keys.to_vec();
+        remaining.sort_unstable();
+        remaining.dedup();
+        let len: usize = remaining.len();
+
+        let mut levels: Vec<Level> = Vec::new();
+        let mut rank: usize = 0;
+        while !remaining.is_empty() && levels.len() < MAX_LEVELS {
+            let words: usize = ((remaining.len() as f64 * GAMMA).ceil() as usize).div_ceil(64);
+            let mut level: Level = Level {
+                hash_function: SeededHash::new(64),
+                bits: vec![0; words],
+                ranks: Vec::with_capacity(words),
+            };
+
+            let mut collided: Vec<u64> = vec![0; words];
+            for key in &remaining {
+                let pos: usize = level.position(*key);
+                let mask: u64 = 1 << (pos % 64);
+                if level.bits[pos / 64] & mask != 0 {
+                    collided[pos / 64] |= mask;
+                }
+                level.bits[pos / 64] |= mask;
+            }
+            for (word, collisions) in level.bits.iter_mut().zip(&collided) {
+                *word &= !collisions;
+                level.ranks.push(rank);
+                rank += word.count_ones() as usize;
+            }
+
+            remaining.retain(|key| {
+                let pos: usize = level.position(*key);
+                collided[pos / 64] & (1 << (pos % 64)) != 0
+            });
+            levels.push(level);
+        }
+
+        let fallback: HashMap<u64, usize> = remaining.iter()
+            .enumerate()
+            .map(|(i, key)| (*key, rank + i))
+            .collect();
+        return Mphf {
+            levels,
+            fallback,
+            len,
+        }
+    }
+    // Index in 0..len() of a key from the build set. Other keys map to an arbitrary value.
+    pub fn index(&self, key: u64) -> usize {
+        for level in &self.levels {
+            let pos: usize = level.position(key);
+            let word: u64 = level.bits[pos / 64];
+            let mask: u64 = 1 << (pos % 64);
+            if word & mask != 0 {
+                return level.ranks[pos / 64] + (word & (mask - 1)).count_ones() as usize;
+            }
+        }
+        return self.fallback.get(&key).copied().unwrap_or(0);
+    }
+    pub fn len(&self) -> usize {
+        return self.len;
+    }
+    pub fn is_empty(&self) -> bool {
+        return self.len == 0;
+    }
+    pub fn bits_per_key(&self) -> f64 {
+        return MemoryFootprint::bits_per_key(self, self.len);
+    }
+}
+
+// The bit arrays and rank tables of the levels plus the keys left for the fallback map
+impl MemoryFootprint for Mphf {
+    fn heap_bytes(&self) -> usize {
+        let mut bytes: usize = 0;
+        for level in &self.levels {
+            bytes += level.len() / 8 + level.ranks.len() * size_of::<usize>();
+        }
+        return bytes + self.fallback.len() * size_of::<(u64, usize)>();
+    }
+    fn allocations(&self) -> usize {
+        let levels: usize = self.levels.iter().map(|level| vec_allocations(&level.bits) + vec_allocations(&level.ranks)).sum();
+        return vec_allocations(&self.levels) + levels + (self.fallback.capacity() > 0) as usize;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::random_generator;
+
+    #[test]
+    fn indices_are_a_permutation() {
+        let keys: Vec<u64> = (0..20_000).map(|_| random_generator(1, u32::MAX) as u64 * 7919).collect();
+        let mphf: Mphf = Mphf::build(&keys);
+        let mut seen: Vec<bool> = vec![false; mphf.len()];
+        for key in &keys {
+            let i: usize = mphf.index(*key);
+            assert!(i < mphf.len());
+            seen[i] = true;
+        }
+        assert!(seen.iter().all(|x| *x));
+        assert!(mphf.bits_per_key() < 16.0);
+    }
+}
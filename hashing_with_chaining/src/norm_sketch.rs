@@ -0,0 +1,304 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::size_of;
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::{AtomicI64, Ordering};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::counter::Counter;
+use crate::hash::{IndependentHash, SeededHash};
+use crate::memory::{vec_allocations, MemoryFootprint};
+use crate::mergeable::{MergeError, MergeableSketch};
+use crate::sketch_format::{ByteReader, ByteWriter, DecodeError, SketchKind};
+
+// Counters summed together by norm_wide. A block of counters below SMALL_COUNTER = 2^29
+// has squares below 2^58, so all 64 of them add up without overflowing a u64.
+const NORM_BLOCK: usize = 64;
+const SMALL_COUNTER: u64 = 1 << 29;
+
+// One row of a Count Sketch: every key is hashed to one of 2^width_len counters and
+// added there with a random sign. The sum of squared counters is an unbiased estimate
+// of the second frequency moment F2, because the 4-wise independent signs make the
+// cross terms of colliding keys cancel in expectation. Counters are i64 unless another
+// Counter type is picked, such as i32 to halve the memory or f64 for weighted streams.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NormSketch<C: Counter = i64> {
+    counters: Vec<C>,
+    bucket_hash: SeededHash,
+    sign_hash: IndependentHash,
+}
+
+impl<C: Counter> NormSketch<C> {
+    #[cfg(feature = "std")]
+    pub fn new(width_len: u32) -> NormSketch<C> {
+        return NormSketch::with_rng(width_len, &mut rand::thread_rng());
+    }
+    // With the bucket and sign hashes drawn from rng, for builds without std
+    pub fn with_rng(width_len: u32, rng: &mut impl Rng) -> NormSketch<C> {
+        return NormSketch {
+            counters: vec![C::default(); 1 << width_len],
+            bucket_hash: SeededHash::with_rng(width_len, rng),
+            sign_hash: IndependentHash::with_rng(4, rng),
+        }
+    }
+    pub fn insert(&mut self, key: u64, delta: C) {
+        let counter: &mut C = &mut self.counters[self.bucket_hash.hash(key)];
+        *counter = if self.sign_hash.sign(key) > 0 { counter.add(delta) } else { counter.sub(delta) };
+    }
+    // Multiplies every counter by factor, rounding at random so estimates stay unbiased
+    #[cfg(feature = "std")]
+    pub fn scale(&mut self, factor: f64) {
+        for counter in &mut self.counters {
+            *counter = counter.scale(factor);
+        }
+    }
+    // Unbiased estimate of the count of key, skewed by whatever collides with it
+    pub fn estimate(&self, key: u64) -> C {
+        let counter: C = self.counters[self.bucket_hash.hash(key)];
+        return if self.sign_hash.sign(key) > 0 { counter } else { C::default().sub(counter) };
+    }
+    // Estimate of the sum of squared counts in floating point, for any counter type
+    pub fn norm_f64(&self) -> f64 {
+        return self.counters.iter().map(|counter| counter.to_f64() * counter.to_f64()).sum();
+    }
+    pub(crate) fn check_mergeable(&self, other: &NormSketch<C>) -> Result<(), MergeError> {
+        if self.counters.len() != other.counters.len() {
+            return Err(MergeError::DimensionMismatch);
+        }
+        if self.bucket_hash != other.bucket_hash || self.sign_hash != other.sign_hash {
+            return Err(MergeError::SeedMismatch);
+        }
+        return Ok(());
+    }
+    pub fn width(&self) -> usize {
+        return self.counters.len();
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.counters.len() * size_of::<C>();
+    }
+}
+
+impl<C: Counter> MemoryFootprint for NormSketch<C> {
+    fn heap_bytes(&self) -> usize {
+        return self.space_usage();
+    }
+    fn allocations(&self) -> usize {
+        return vec_allocations(&self.counters);
+    }
+}
+
+impl NormSketch {
+    // Estimate of the sum of squared counts, saturating at u64::MAX. A single counter
+    // of 2^32 already squares past u64::MAX, so long streams with large counts should
+    // use checked_norm or norm_wide instead.
+    pub fn norm(&self) -> u64 {
+        return self.saturating_norm();
+    }
+    pub fn saturating_norm(&self) -> u64 {
+        return u64::try_from(self.norm_wide()).unwrap_or(u64::MAX);
+    }
+    // None if the norm does not fit in a u64
+    pub fn checked_norm(&self) -> Option<u64> {
+        return u64::try_from(self.norm_wide()).ok();
+    }
+    // The norm accumulated in 128 bits, where even squared i64::MIN counters fit. Only
+    // saturates with more than three counters of magnitude close to 2^63. Counters are
+    // summed in blocks, and a block whose counters are all below SMALL_COUNTER squares and
+    // sums them in 64 bits, which the compiler turns into vector instructions.
+    pub fn norm_wide(&self) -> u128 {
+        return self.counters.chunks(NORM_BLOCK)
+            .map(|block| {
+                if block.iter().fold(0, |bits, counter| bits | counter.unsigned_abs()) < SMALL_COUNTER {
+                    block.iter().map(|counter| (counter.unsigned_abs() as u32 as u64).pow(2)).sum::<u64>() as u128
+                } else {
+                    block.iter().map(|counter| (counter.unsigned_abs() as u128).pow(2)).fold(0, u128::saturating_add)
+                }
+            })
+            .fold(0, u128::saturating_add);
+    }
+    // bucket hash | sign hash | number of counters (u64) | counters (i64)
+    pub(crate) fn write_body(&self, writer: &mut ByteWriter) {
+        writer.seeded_hash(&self.bucket_hash);
+        writer.independent_hash(&self.sign_hash);
+        writer.u64(self.counters.len() as u64);
+        self.counters.iter().for_each(|counter| writer.i64(*counter));
+    }
+    pub(crate) fn read_body(reader: &mut ByteReader) -> Result<NormSketch, DecodeError> {
+        let bucket_hash: SeededHash = reader.seeded_hash()?;
+        let sign_hash: IndependentHash = reader.independent_hash()?;
+        let len: usize = reader.len(size_of::<i64>())?;
+        if bucket_hash.hash_len() >= usize::BITS || len != 1 << bucket_hash.hash_len() {
+            return Err(DecodeError::Invalid("counters do not match the bucket hash"));
+        }
+        let counters: Vec<i64> = (0..len).map(|_| reader.i64()).collect::<Result<_, _>>()?;
+        return Ok(NormSketch { counters, bucket_hash, sign_hash });
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new(SketchKind::NormSketch);
+        self.write_body(&mut writer);
+        return writer.finish();
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<NormSketch, DecodeError> {
+        let mut reader: ByteReader = ByteReader::new(bytes, SketchKind::NormSketch)?;
+        let sketch: NormSketch = NormSketch::read_body(&mut reader)?;
+        reader.finish()?;
+        return Ok(sketch);
+    }
+}
+
+// Counters are linear in the stream, so merging adds them up
+impl<C: Counter> MergeableSketch for NormSketch<C> {
+    fn merge(&mut self, other: &NormSketch<C>) -> Result<(), MergeError> {
+        self.check_mergeable(other)?;
+        for (counter, other_counter) in self.counters.iter_mut().zip(&other.counters) {
+            *counter = counter.add(*other_counter);
+        }
+        return Ok(());
+    }
+}
+
+// NormSketch that any number of threads can update through a shared reference, with one
+// relaxed fetch_add per update. Signed additions commute, so concurrent inserts and
+// deletions end in the same counters as any sequential order of them. Targets without
+// 64-bit atomics go without it.
+#[cfg(target_has_atomic = "64")]
+pub struct AtomicNormSketch {
+    counters: Vec<AtomicI64>,
+    bucket_hash: SeededHash,
+    sign_hash: IndependentHash,
+}
+
+#[cfg(target_has_atomic = "64")]
+impl AtomicNormSketch {
+    #[cfg(feature = "std")]
+    pub fn new(width_len: u32) -> AtomicNormSketch {
+        return AtomicNormSketch::from_sketch(NormSketch::new(width_len));
+    }
+    // Continues from the counters and seeds of a sequential sketch
+    pub fn from_sketch(sketch: NormSketch) -> AtomicNormSketch {
+        return AtomicNormSketch {
+            counters: sketch.counters.into_iter().map(AtomicI64::new).collect(),
+            bucket_hash: sketch.bucket_hash,
+            sign_hash: sketch.sign_hash,
+        }
+    }
+    // Copy of the counters as a sequential sketch, for norms, merging or serializing.
+    // Inserts that run concurrently with the copy may be only partly included.
+    pub fn to_sketch(&self) -> NormSketch {
+        return NormSketch {
+            counters: self.counters.iter().map(|counter| counter.load(Ordering::Relaxed)).collect(),
+            bucket_hash: self.bucket_hash.clone(),
+            sign_hash: self.sign_hash.clone(),
+        }
+    }
+    pub fn insert(&self, key: u64, delta: i64) {
+        self.counters[self.bucket_hash.hash(key)].fetch_add(self.sign_hash.sign(key).wrapping_mul(delta), Ordering::Relaxed);
+    }
+    pub fn estimate(&self, key: u64) -> i64 {
+        return self.sign_hash.sign(key).wrapping_mul(self.counters[self.bucket_hash.hash(key)].load(Ordering::Relaxed));
+    }
+    pub fn width(&self) -> usize {
+        return self.counters.len();
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.counters.len() * size_of::<AtomicI64>();
+    }
+}
+
+#[cfg(target_has_atomic = "64")]
+impl MemoryFootprint for AtomicNormSketch {
+    fn heap_bytes(&self) -> usize {
+        return self.space_usage();
+    }
+    fn allocations(&self) -> usize {
+        return vec_allocations(&self.counters);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn norms_near_the_u64_boundary() {
+        // With a single counter every key lands in the same place
+        let mut sketch: NormSketch = NormSketch::new(0);
+        sketch.insert(1, (1 << 32) - 1);
+        assert_eq!(sketch.checked_norm(), Some(((1u64 << 32) - 1).pow(2)));
+        assert_eq!(sketch.norm(), ((1u64 << 32) - 1).pow(2));
+
+        sketch.insert(1, sketch.estimate(1).signum());
+        assert_eq!(sketch.estimate(1), 1 << 32);
+        assert_eq!(sketch.checked_norm(), None);
+        assert_eq!(sketch.saturating_norm(), u64::MAX);
+        assert_eq!(sketch.norm_wide(), 1 << 64);
+
+        let mut sketch: NormSketch = NormSketch::new(0);
+        sketch.insert(1, i64::MAX);
+        assert_eq!(sketch.norm_wide(), (i64::MAX as u128).pow(2));
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut sketch: NormSketch = NormSketch::new(8);
+        for key in 0..1000u64 {
+            sketch.insert(key, key as i64 - 500);
+        }
+        let bytes: Vec<u8> = sketch.to_bytes();
+        assert_eq!(bytes.len(), 8 + 36 + 36 + 8 + 256 * 8);
+        let mut decoded: NormSketch = NormSketch::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.norm_wide(), sketch.norm_wide());
+        assert!((0..1000).all(|key| decoded.estimate(key) == sketch.estimate(key)));
+        decoded.merge(&sketch).unwrap();
+
+        assert_eq!(NormSketch::from_bytes(&bytes[..bytes.len() - 1]).err(), Some(DecodeError::Truncated));
+        assert_eq!(NormSketch::from_bytes(b"not a sketch").err(), Some(DecodeError::BadMagic));
+        let mut newer: Vec<u8> = bytes.clone();
+        newer[4] = 2;
+        assert_eq!(NormSketch::from_bytes(&newer).err(), Some(DecodeError::UnsupportedVersion(2)));
+    }
+
+    #[test]
+    fn concurrent_signed_updates_match_a_sequential_run() {
+        let mut sequential: NormSketch = NormSketch::new(10);
+        let concurrent: AtomicNormSketch = AtomicNormSketch::from_sketch(sequential.clone());
+        // Every insert of 7 is followed by a deletion, netting key x a change of x % 5 - 2
+        let updates: Vec<(u64, i64)> = (0..100_000u64)
+            .flat_map(|x| [(x % 4001, 7), (x % 4001, (x % 4001) as i64 % 5 - 9)])
+            .collect();
+        std::thread::scope(|s| {
+            for chunk in updates.chunks(25_000) {
+                let concurrent: &AtomicNormSketch = &concurrent;
+                s.spawn(move || chunk.iter().for_each(|(key, delta)| concurrent.insert(*key, *delta)));
+            }
+        });
+        updates.iter().for_each(|(key, delta)| sequential.insert(*key, *delta));
+        let snapshot: NormSketch = concurrent.to_sketch();
+        assert_eq!(snapshot.counters, sequential.counters);
+        assert_eq!(snapshot.norm_wide(), sequential.norm_wide());
+        assert!((0..4001).all(|key| concurrent.estimate(key) == sequential.estimate(key)));
+    }
+
+    #[test]
+    fn atomic_counters_wrap_like_sequential_ones() {
+        let mut sequential: NormSketch = NormSketch::new(4);
+        let concurrent: AtomicNormSketch = AtomicNormSketch::from_sketch(sequential.clone());
+        for key in 0..64u64 {
+            sequential.insert(key, i64::MIN);
+            concurrent.insert(key, i64::MIN);
+        }
+        assert_eq!(concurrent.to_sketch().counters, sequential.counters);
+        assert!((0..64).all(|key| concurrent.estimate(key) == sequential.estimate(key)));
+    }
+
+    #[test]
+    fn blocked_norm_matches_the_scalar_sum() {
+        let mut sketch: NormSketch = NormSketch::new(10);
+        // Mostly small counters, with a few past 2^29 so some blocks take the wide path
+        for key in 0..5000u64 {
+            sketch.insert(key, if key % 997 == 0 { 1 << 40 } else { (1 << 28) - key as i64 });
+        }
+        let scalar: u128 = sketch.counters.iter().map(|counter| (counter.unsigned_abs() as u128).pow(2)).sum();
+        assert_eq!(sketch.norm_wide(), scalar);
+    }
+}
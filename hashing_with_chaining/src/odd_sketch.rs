@@ -0,0 +1,120 @@
+use std::mem::size_of;
+use serde::{Deserialize, Serialize};
+use crate::hash::IndependentHash;
+use crate::mergeable::{MergeError, MergeableSketch};
+
+// Spreads the coordinate index of a signature value over the key space
+const COORDINATE_MIX: u64 = 0x9E37_79B9_7F4A_7C15;
+
+// Odd sketch (Mitzenmacher, Pagh and Pham): a bitmap of 2^bits_len bits where every key
+// flips the bit it hashes to, so a bit holds the parity of the keys hashing there. The
+// XOR of the sketches of two sets is the sketch of their symmetric difference, whose size
+// follows from the number of ones: a bit is one with probability (1 - (1 - 2/n)^d) / 2 for
+// d keys over n bits. Inserting a key twice cancels it, so sets must be inserted without
+// repeats. Sketches are only comparable when cloned from one empty sketch.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OddSketch {
+    bits: Vec<u64>,
+    hash_function: IndependentHash,
+    bits_len: u32,
+}
+
+impl OddSketch {
+    pub fn new(bits_len: u32) -> OddSketch {
+        assert!((6..=61).contains(&bits_len), "Needs between 2^6 and 2^61 bits");
+        return OddSketch {
+            bits: vec![0; 1 << (bits_len - 6)],
+            hash_function: IndependentHash::new(4),
+            bits_len,
+        }
+    }
+    pub fn insert(&mut self, key: u64) {
+        let bit: usize = (self.hash_function.hash(key) >> (61 - self.bits_len)) as usize;
+        self.bits[bit / 64] ^= 1 << (bit % 64);
+    }
+    // Inserts the (coordinate, value) pairs of a MinHash signature. Highly similar sets
+    // differ in few coordinates, which the odd sketch counts in far less space than the
+    // signature itself.
+    pub fn insert_signature(&mut self, signature: &[u64]) {
+        for (i, value) in signature.iter().enumerate() {
+            self.insert(value.wrapping_add((i as u64).wrapping_mul(COORDINATE_MIX)));
+        }
+    }
+    fn check_compatible(&self, other: &OddSketch) -> Result<(), MergeError> {
+        if self.bits_len != other.bits_len {
+            return Err(MergeError::DimensionMismatch);
+        }
+        if self.hash_function != other.hash_function {
+            return Err(MergeError::SeedMismatch);
+        }
+        return Ok(());
+    }
+    // Size of the symmetric difference estimated from the ones of the XOR, inverting
+    // E[ones] = n (1 - (1 - 2/n)^d) / 2. Infinite once half of the bits are one, where the
+    // sketch is too small for the difference.
+    pub fn symmetric_difference(&self, other: &OddSketch) -> Result<f64, MergeError> {
+        self.check_compatible(other)?;
+        let ones: u32 = self.bits.iter().zip(&other.bits).map(|(a, b)| (a ^ b).count_ones()).sum();
+        let n: f64 = self.bit_count() as f64;
+        let odd_fraction: f64 = 1.0 - 2.0 * ones as f64 / n;
+        if odd_fraction <= 0.0 {
+            return Ok(f64::INFINITY);
+        }
+        return Ok(odd_fraction.ln() / (1.0 - 2.0 / n).ln());
+    }
+    // Jaccard similarity of two sets from the odd sketches of their MinHash signatures of
+    // length k. Every coordinate the signatures disagree on adds two pairs to the symmetric
+    // difference, and they disagree on a 1 - J fraction of the coordinates.
+    pub fn signature_jaccard(&self, other: &OddSketch, k: usize) -> Result<f64, MergeError> {
+        let difference: f64 = self.symmetric_difference(other)?;
+        return Ok((1.0 - difference / (2 * k) as f64).max(0.0));
+    }
+    pub fn bit_count(&self) -> usize {
+        return 1 << self.bits_len;
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.bits.len() * size_of::<u64>();
+    }
+}
+
+// Merging takes the XOR, which is the sketch of the symmetric difference of the two sets
+impl MergeableSketch for OddSketch {
+    fn merge(&mut self, other: &OddSketch) -> Result<(), MergeError> {
+        self.check_compatible(other)?;
+        for (word, other_word) in self.bits.iter_mut().zip(&other.bits) {
+            *word ^= *other_word;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::minhash::MinHash;
+
+    #[test]
+    fn estimates_small_symmetric_differences() {
+        let empty: OddSketch = OddSketch::new(12);
+        let (mut a, mut b) = (empty.clone(), empty.clone());
+        // A = [0, 100000) and B = [500, 100500) differ in 1000 keys
+        (0..100_000u64).for_each(|key| a.insert(key));
+        (500..100_500u64).for_each(|key| b.insert(key));
+        let difference: f64 = a.symmetric_difference(&b).unwrap();
+        assert!((difference - 1000.0).abs() < 200.0, "{}", difference);
+        a.merge(&b).unwrap();
+        assert!((a.symmetric_difference(&empty).unwrap() - difference).abs() < 1e-9);
+        assert_eq!(a.merge(&OddSketch::new(12)).err(), Some(MergeError::SeedMismatch));
+
+        // J = 9950 / 10050, so the signatures of length 1024 disagree on about 10 coordinates
+        let minhash: MinHash = MinHash::new(1024);
+        let (mut first, mut second) = (minhash.clone(), minhash.clone());
+        (0..10_000u64).for_each(|key| first.insert(key));
+        (50..10_050u64).for_each(|key| second.insert(key));
+        let (mut c, mut d) = (empty.clone(), empty.clone());
+        c.insert_signature(first.signature());
+        d.insert_signature(second.signature());
+        let jaccard: f64 = c.signature_jaccard(&d, 1024).unwrap();
+        assert!((jaccard - 9950.0 / 10_050.0).abs() < 0.015, "{}", jaccard);
+    }
+}
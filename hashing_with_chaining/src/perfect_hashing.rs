@@ -0,0 +1,205 @@
+use std::fmt;
+use std::mem::size_of;
+use crate::hash::{log2u, SeededHash, C};
+use crate::las_vegas::{las_vegas, Attempts};
+use crate::memory::{vec_allocations, MemoryFootprint};
+
+// Every attempt succeeds with probability at least 1/2, so running out is practically
+// impossible unless the random source is broken
+pub const DEFAULT_MAX_ATTEMPTS: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConstructionError {
+    // No first level hash function spread the keys so that the second level takes linear space
+    FirstLevel { attempts: usize },
+    // No second level hash function was collision free on the keys of one bucket
+    SecondLevel { keys: usize, attempts: usize },
+}
+
+impl fmt::Display for ConstructionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConstructionError::FirstLevel { attempts } =>
+                write!(f, "first level hash function failed {} times", attempts),
+            ConstructionError::SecondLevel { keys, attempts } =>
+                write!(f, "second level hash function for {} keys failed {} times", keys, attempts),
+        }
+    }
+}
+
+impl std::error::Error for ConstructionError {}
+
+// Number of hash functions that had to be thrown away during construction
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConstructionStats {
+    pub first_level_retries: usize,
+    pub second_level_retries: usize,
+}
+
+// Second level of the FKS scheme: a collision free table with 2*C*n^2 slots
+// for the n keys that landed in the same first level bucket
+struct Bucket {
+    vec: Vec<Option<u64>>,
+    hash_function: SeededHash,
+}
+
+impl Bucket {
+    fn new(input_array: &[u64], max_attempts: usize, stats: &mut ConstructionStats) -> Result<Bucket, ConstructionError> {
+        let array_len: usize = 2*C*input_array.len().pow(2);
+        if array_len == 0 {
+            return Ok(Bucket {
+                vec: Vec::new(),
+                hash_function: SeededHash::new(0)
+            })
+        }
+        let hash_len: u32 = log2u(array_len);
+        let outcome: Attempts<Bucket> = las_vegas(max_attempts, || {
+            let mut bucket: Bucket = Bucket {
+                vec: vec![None; array_len],
+                hash_function: SeededHash::new(hash_len),
+            };
+            return input_array.iter().all(|x| bucket.insert(*x)).then_some(bucket);
+        });
+        stats.second_level_retries += outcome.retries();
+        return outcome.value.ok_or(ConstructionError::SecondLevel { keys: input_array.len(), attempts: max_attempts });
+    }
+    // Fails if the slot is taken by another key
+    fn insert(&mut self, elem: u64) -> bool {
+        let hash: usize = self.hash_function.hash(elem);
+        return match self.vec[hash] {
+            Some(x) => x == elem,
+            None => {
+                self.vec[hash] = Some(elem);
+                true
+            }
+        }
+    }
+    fn query(&self, elem: u64) -> bool {
+        if self.vec.is_empty() {
+            return false;
+        }
+        return self.vec[self.hash_function.hash(elem)] == Some(elem)
+    }
+}
+
+// FKS perfect hashing of a static key set with worst case constant query time
+pub struct PerfectHashing {
+    vec: Vec<Bucket>,
+    hash_function: SeededHash,
+    stats: ConstructionStats,
+}
+
+impl PerfectHashing {
+    pub fn new(input_array: &[u64]) -> PerfectHashing {
+        return PerfectHashing::with_max_attempts(input_array, DEFAULT_MAX_ATTEMPTS)
+            .expect("Perfect hashing construction failed");
+    }
+    // Gives up once a single level has drawn max_attempts hash functions without success
+    pub fn with_max_attempts(input_array: &[u64], max_attempts: usize) -> Result<PerfectHashing, ConstructionError> {
+        let array_len: usize = (4*C*input_array.len()).max(1);
+        let hash_len: u32 = log2u(array_len);
+        let mut stats: ConstructionStats = ConstructionStats::default();
+
+        // A second level failure ends the construction, so it counts as a finished attempt
+        let outcome: Attempts<Result<(SeededHash, Vec<Bucket>), ConstructionError>> = las_vegas(max_attempts, || {
+            let hash_fn: SeededHash = SeededHash::new(hash_len);
+            let mut buckets: Vec<Vec<u64>> = vec![Vec::new(); array_len];
+            for x in input_array {
+                let hash = hash_fn.hash(*x);
+                buckets[hash].push(*x);
+            }
+
+            // Retry with a new hash function until the second level takes linear space
+            let sum_of_squares: usize = buckets.iter().map(|b| b.len().pow(2)).sum();
+            if sum_of_squares > array_len {
+                return None;
+            }
+            let vec: Result<Vec<Bucket>, ConstructionError> = buckets.iter()
+                .map(|bucket| Bucket::new(bucket, max_attempts, &mut stats))
+                .collect();
+            return Some(vec.map(|vec| (hash_fn, vec)));
+        });
+        stats.first_level_retries += outcome.retries();
+        let (hash_function, vec) = outcome.value.ok_or(ConstructionError::FirstLevel { attempts: max_attempts })??;
+        return Ok(PerfectHashing { vec, hash_function, stats })
+    }
+    pub fn query(&self, elem: u64) -> bool {
+        let hash: usize = self.hash_function.hash(elem);
+        return self.vec[hash].query(elem);
+    }
+    pub fn construction_stats(&self) -> ConstructionStats {
+        return self.stats;
+    }
+    // Heap footprint of both levels in bytes
+    pub fn space_usage(&self) -> usize {
+        let slots: usize = self.vec.iter().map(|b| b.vec.len()).sum();
+        return self.vec.len() * size_of::<Bucket>() + slots * size_of::<Option<u64>>();
+    }
+}
+
+impl MemoryFootprint for PerfectHashing {
+    fn heap_bytes(&self) -> usize {
+        return self.space_usage();
+    }
+    fn allocations(&self) -> usize {
+        return vec_allocations(&self.vec) + self.vec.iter().map(|bucket| vec_allocations(&bucket.vec)).sum::<usize>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::random_generator;
+
+    fn random_keys(n: usize) -> Vec<u64> {
+        (0..n).map(|_| random_generator(1, u32::MAX) as u64 * random_generator(1, u32::MAX) as u64).collect()
+    }
+
+    #[test]
+    fn finds_every_key_and_nothing_else() {
+        let keys: Vec<u64> = random_keys(5_000);
+        let ph: PerfectHashing = PerfectHashing::new(&keys);
+        assert!(keys.iter().all(|x| ph.query(*x)));
+        let absent: usize = (0..5_000u64).filter(|x| !keys.contains(x) && ph.query(*x)).count();
+        assert_eq!(absent, 0);
+    }
+
+    #[test]
+    fn repeated_constructions_all_succeed() {
+        // Small sets make failed first and second level attempts common
+        let mut retries: usize = 0;
+        for _ in 0..200 {
+            let keys: Vec<u64> = random_keys(32);
+            let ph: PerfectHashing = PerfectHashing::new(&keys);
+            assert!(keys.iter().all(|x| ph.query(*x)));
+            let stats: ConstructionStats = ph.construction_stats();
+            retries += stats.first_level_retries + stats.second_level_retries;
+        }
+        assert!(retries > 0);
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let result = PerfectHashing::with_max_attempts(&random_keys(100), 0);
+        assert_eq!(result.err(), Some(ConstructionError::FirstLevel { attempts: 0 }));
+    }
+
+    #[test]
+    fn handles_empty_input_and_duplicates() {
+        let empty: PerfectHashing = PerfectHashing::new(&[]);
+        assert!(!empty.query(0));
+        let ph: PerfectHashing = PerfectHashing::new(&[7, 7, 0, 7]);
+        assert!(ph.query(7));
+        assert!(ph.query(0));
+        assert!(!ph.query(1));
+    }
+
+    #[test]
+    fn space_usage_is_linear() {
+        let n: usize = 1 << 12;
+        let ph: PerfectHashing = PerfectHashing::new(&random_keys(n));
+        // At most 4Cn buckets whose squared sizes sum to at most 4Cn, each given 2C slots per square
+        let bound: usize = 4*C*n * size_of::<Bucket>() + 2*C * 4*C*n * size_of::<Option<u64>>();
+        assert!(ph.space_usage() <= bound);
+    }
+}
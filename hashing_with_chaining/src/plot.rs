@@ -0,0 +1,157 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use plotters::coord::ranged1d::{AsRangedCoord, ValueFormatter};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use crate::results::ResultRow;
+
+// One line of a chart, points in increasing x
+#[derive(Clone, Debug, PartialEq)]
+pub struct Series {
+    pub name: String,
+    pub points: Vec<(f64, f64)>,
+}
+
+// One series per algorithm and phase from the rows that have a value for both x and y,
+// averaging y over repeated runs at the same x. Series are named by the algorithm alone
+// when all rows are of one phase.
+pub fn series(rows: &[ResultRow], x: &str, y: &str) -> Vec<Series> {
+    let phases: BTreeSet<&str> = rows.iter().map(|row| row.phase.as_str()).collect();
+    let mut grouped: BTreeMap<String, Vec<(f64, f64)>> = BTreeMap::new();
+    for row in rows {
+        if let (Some(x), Some(y)) = (row.value(x), row.value(y)) {
+            let name: String = if phases.len() > 1 { format!("{} {}", row.algorithm, row.phase) } else { row.algorithm.clone() };
+            grouped.entry(name).or_default().push((x, y));
+        }
+    }
+    return grouped.into_iter().map(|(name, mut points)| {
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut averaged: Vec<(f64, f64)> = Vec::new();
+        for group in points.chunk_by(|a, b| a.0 == b.0) {
+            averaged.push((group[0].0, group.iter().map(|point| point.1).sum::<f64>() / group.len() as f64));
+        }
+        return Series { name, points: averaged }
+    }).collect();
+}
+
+// Titles and axis scales of a chart
+#[derive(Clone, Debug, Default)]
+pub struct Chart {
+    pub title: String,
+    pub x_label: String,
+    pub y_label: String,
+    pub log_x: bool,
+    pub log_y: bool,
+}
+
+const WIDTH: u32 = 1024;
+const HEIGHT: u32 = 768;
+
+// Plotters errors carry the backend's error type, which is all the same to the caller
+fn draw_error(err: impl std::fmt::Display) -> io::Error {
+    return io::Error::other(err.to_string());
+}
+
+// Smallest and largest value, widened to a range of nonzero length
+fn bounds(values: impl Iterator<Item = f64>, log: bool) -> (f64, f64) {
+    let (low, high) = values.filter(|v| !log || *v > 0.0)
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), v| (low.min(v), high.max(v)));
+    if low > high {
+        return (1.0, 10.0);
+    }
+    if low == high {
+        return if log { (low / 2.0, high * 2.0) } else { (low - 1.0, high + 1.0) };
+    }
+    return (low, high);
+}
+
+fn draw_lines<X, Y>(area: &DrawingArea<SVGBackend, Shift>, series: &[Series], chart: &Chart, x_range: X, y_range: Y) -> io::Result<()>
+where
+    X: AsRangedCoord<Value = f64>,
+    Y: AsRangedCoord<Value = f64>,
+    X::CoordDescType: ValueFormatter<f64>,
+    Y::CoordDescType: ValueFormatter<f64>,
+{
+    let mut context = ChartBuilder::on(area)
+        .caption(&chart.title, ("sans-serif", 28))
+        .margin(20)
+        .x_label_area_size(50)
+        .y_label_area_size(90)
+        .build_cartesian_2d(x_range, y_range)
+        .map_err(draw_error)?;
+    context.configure_mesh()
+        .x_desc(&chart.x_label)
+        .y_desc(&chart.y_label)
+        .label_style(("sans-serif", 16))
+        .axis_desc_style(("sans-serif", 20))
+        .x_label_formatter(&|x| format!("{}", x))
+        .y_label_formatter(&|y| format!("{:.3e}", y))
+        .draw()
+        .map_err(draw_error)?;
+    for (i, line) in series.iter().enumerate() {
+        let color: RGBAColor = Palette99::pick(i).to_rgba();
+        let points: Vec<(f64, f64)> = line.points.iter()
+            .filter(|(x, y)| (!chart.log_x || *x > 0.0) && (!chart.log_y || *y > 0.0))
+            .copied()
+            .collect();
+        context.draw_series(LineSeries::new(points.clone(), color.stroke_width(2)))
+            .map_err(draw_error)?
+            .label(&line.name)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+        context.draw_series(points.iter().map(|point| Circle::new(*point, 3, color.filled())))
+            .map_err(draw_error)?;
+    }
+    context.configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .label_font(("sans-serif", 16))
+        .draw()
+        .map_err(draw_error)?;
+    return Ok(());
+}
+
+// The series as an SVG line chart, one color per series with a legend. Logarithmic axes
+// leave out points that are not positive.
+pub fn render_svg(series: &[Series], chart: &Chart) -> io::Result<String> {
+    let (x_low, x_high) = bounds(series.iter().flat_map(|line| line.points.iter().map(|point| point.0)), chart.log_x);
+    let (y_low, y_high) = bounds(series.iter().flat_map(|line| line.points.iter().map(|point| point.1)), chart.log_y);
+    let mut svg: String = String::new();
+    {
+        let area: DrawingArea<SVGBackend, Shift> = SVGBackend::with_string(&mut svg, (WIDTH, HEIGHT)).into_drawing_area();
+        area.fill(&WHITE).map_err(draw_error)?;
+        match (chart.log_x, chart.log_y) {
+            (false, false) => draw_lines(&area, series, chart, x_low..x_high, y_low..y_high)?,
+            (true, false) => draw_lines(&area, series, chart, (x_low..x_high).log_scale(), y_low..y_high)?,
+            (false, true) => draw_lines(&area, series, chart, x_low..x_high, (y_low..y_high).log_scale())?,
+            (true, true) => draw_lines(&area, series, chart, (x_low..x_high).log_scale(), (y_low..y_high).log_scale())?,
+        }
+        area.present().map_err(draw_error)?;
+    }
+    return Ok(svg);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn series_average_repeated_runs() {
+        let row = |algorithm: &str, n: u64, phase: &str, duration: i128| ResultRow::new(algorithm, n, phase).duration_ns(duration);
+        let rows: Vec<ResultRow> = vec![
+            row("hwc", 1024, "query", 300), row("hwc", 16, "query", 10), row("hwc", 1024, "query", 500),
+            row("lp", 16, "query", 8), ResultRow::new("lp", 1024, "query"),
+        ];
+        assert_eq!(series(&rows, "n", "duration_ns"), vec![
+            Series { name: "hwc".to_owned(), points: vec![(16.0, 10.0), (1024.0, 400.0)] },
+            Series { name: "lp".to_owned(), points: vec![(16.0, 8.0)] },
+        ]);
+        let mixed: Vec<ResultRow> = vec![row("hwc", 16, "query", 10), row("hwc", 16, "construction", 20)];
+        let names: Vec<String> = series(&mixed, "n", "duration_ns").into_iter().map(|line| line.name).collect();
+        assert_eq!(names, vec!["hwc construction", "hwc query"]);
+
+        let chart: Chart = Chart { title: "Query time".to_owned(), log_x: true, log_y: true, ..Chart::default() };
+        let svg: String = render_svg(&series(&rows, "n", "duration_ns"), &chart).unwrap();
+        assert!(svg.starts_with("<svg") && svg.contains("Query time") && svg.contains("\nlp\n"));
+    }
+}
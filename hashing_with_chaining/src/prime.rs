@@ -0,0 +1,219 @@
+use rand::prelude::*;
+
+// Bases that make Miller-Rabin exact for every 64-bit number
+const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+fn mul_mod(a: u64, b: u64, modulus: u64) -> u64 {
+    return (a as u128 * b as u128 % modulus as u128) as u64;
+}
+
+fn pow_mod(mut base: u64, mut exponent: u64, modulus: u64) -> u64 {
+    let mut result: u64 = 1 % modulus;
+    base %= modulus;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, modulus);
+        }
+        base = mul_mod(base, base, modulus);
+        exponent >>= 1;
+    }
+    return result;
+}
+
+// Miller-Rabin with the first twelve primes as witnesses, which no composite below
+// 3.3 * 10^24 fools, so the answer is exact for u64
+pub fn is_prime(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for p in WITNESSES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+    // n - 1 = d * 2^s with d odd
+    let s: u32 = (n - 1).trailing_zeros();
+    let d: u64 = (n - 1) >> s;
+    'witness: for a in WITNESSES {
+        let mut x: u64 = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    return true;
+}
+
+// Odd number with exactly bits bits, the top one set
+fn random_candidate(bits: u32, rng: &mut impl Rng) -> u64 {
+    let top: u64 = 1 << (bits - 1);
+    return (rng.gen::<u64>() & (top - 1)) | top | 1;
+}
+
+// Uniformly random prime with exactly bits bits, for hash families over a prime field that
+// should not share a fixed modulus. By the prime number theorem about one in ln(2^bits) / 2
+// odd candidates is prime, so a 61-bit prime takes some 20 tries.
+pub fn random_prime(bits: u32, rng: &mut impl Rng) -> u64 {
+    assert!((2..=64).contains(&bits), "Needs between 2 and 64 bits");
+    if bits == 2 {
+        return if rng.gen::<bool>() { 2 } else { 3 };
+    }
+    loop {
+        let candidate: u64 = random_candidate(bits, rng);
+        if is_prime(candidate) {
+            return candidate;
+        }
+    }
+}
+
+// Random safe prime p = 2q + 1 with q prime and exactly bits bits. The multiplicative group
+// modulo a safe prime has no small subgroups besides {1, -1}, which some hashing and
+// fingerprinting schemes rely on. Safe primes are rarer by another factor of ln(2^bits).
+pub fn random_safe_prime(bits: u32, rng: &mut impl Rng) -> u64 {
+    assert!((3..=64).contains(&bits), "Needs between 3 and 64 bits");
+    loop {
+        let q: u64 = random_candidate(bits - 1, rng);
+        let p: u64 = 2 * q + 1;
+        if is_prime(q) && is_prime(p) {
+            return p;
+        }
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    return a;
+}
+
+// One run of Pollard's rho with Brent's cycle detection on the sequence y -> y^2 + c mod n,
+// starting from y. The differences are multiplied up in batches so that one gcd covers
+// many steps. Returns the factor found, None if the run only found n itself, and the number
+// of steps of the sequence it took.
+fn brent(n: u64, c: u64, mut y: u64) -> (Option<u64>, u64) {
+    const BATCH: u64 = 128;
+    let step = |y: u64| -> u64 { return ((y as u128 * y as u128 + c as u128) % n as u128) as u64 };
+    let (mut x, mut saved): (u64, u64) = (y, y);
+    let (mut factor, mut product, mut steps): (u64, u64, u64) = (1, 1, 0);
+    let mut power: u64 = 1;
+    while factor == 1 {
+        x = y;
+        for _ in 0..power {
+            y = step(y);
+        }
+        steps += power;
+        let mut k: u64 = 0;
+        while k < power && factor == 1 {
+            saved = y;
+            for _ in 0..BATCH.min(power - k) {
+                y = step(y);
+                product = mul_mod(product, x.abs_diff(y), n);
+            }
+            steps += BATCH.min(power - k);
+            factor = gcd(product, n);
+            k += BATCH;
+        }
+        power *= 2;
+    }
+    // The batch overshot and multiplied in a zero, so redo it one step at a time
+    if factor == n {
+        loop {
+            saved = step(saved);
+            steps += 1;
+            factor = gcd(x.abs_diff(saved), n);
+            if factor > 1 {
+                break;
+            }
+        }
+    }
+    return (if factor == n { None } else { Some(factor) }, steps);
+}
+
+// A nontrivial factor of the composite n and the total steps of the rho sequences it took.
+// A sequence modulo the smallest prime factor p of n behaves like a random mapping and
+// repeats after about sqrt(pi p / 2) steps, which is when the gcd reveals p, so the expected
+// work is O(n^(1/4)) multiplications. Runs that find n itself are retried with a new random
+// constant and start.
+pub fn pollard_rho(n: u64, rng: &mut impl Rng) -> (u64, u64) {
+    assert!(n > 3 && !is_prime(n), "Needs a composite number");
+    if n.is_multiple_of(2) {
+        return (2, 0);
+    }
+    let mut total: u64 = 0;
+    loop {
+        let (factor, steps) = brent(n, rng.gen_range(1..n - 1), rng.gen_range(0..n));
+        total += steps;
+        if let Some(factor) = factor {
+            return (factor, total);
+        }
+    }
+}
+
+// Prime factors of n with multiplicity, in increasing order
+pub fn factorize(n: u64) -> Vec<u64> {
+    let mut rng = thread_rng();
+    let mut factors: Vec<u64> = Vec::new();
+    let mut pending: Vec<u64> = vec![n];
+    while let Some(m) = pending.pop() {
+        if m == 1 {
+            continue;
+        }
+        if is_prime(m) {
+            factors.push(m);
+            continue;
+        }
+        let (factor, _) = pollard_rho(m, &mut rng);
+        pending.push(factor);
+        pending.push(m / factor);
+    }
+    factors.sort();
+    return factors;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primes_match_a_sieve_and_have_the_requested_length() {
+        let mut composite: Vec<bool> = vec![false; 100_000];
+        for i in 2..composite.len() {
+            if !composite[i] {
+                (2 * i..composite.len()).step_by(i).for_each(|j| composite[j] = true);
+            }
+        }
+        assert!((0..100_000u64).all(|n| is_prime(n) == (n >= 2 && !composite[n as usize])));
+        // Strong pseudoprimes to several small bases, and the largest 64-bit prime
+        assert!(!is_prime(3_215_031_751) && !is_prime(3_825_123_056_546_413_051));
+        assert!(is_prime(u64::MAX - 58) && is_prime((1 << 61) - 1));
+
+        let mut rng = thread_rng();
+        for bits in [2, 8, 31, 61, 64] {
+            let p: u64 = random_prime(bits, &mut rng);
+            assert!(is_prime(p) && 64 - p.leading_zeros() == bits, "{}", p);
+        }
+        let p: u64 = random_safe_prime(40, &mut rng);
+        assert!(is_prime(p) && is_prime(p / 2) && 64 - p.leading_zeros() == 40, "{}", p);
+    }
+
+    #[test]
+    fn factors_64_bit_semiprimes() {
+        let mut rng = thread_rng();
+        for _ in 0..10 {
+            let (p, q) = (random_prime(32, &mut rng), random_prime(32, &mut rng));
+            assert_eq!(factorize(p * q), if p < q { vec![p, q] } else { vec![q, p] });
+        }
+        let p: u64 = random_prime(32, &mut rng);
+        assert_eq!(factorize(p * p), vec![p, p]);
+        assert_eq!(factorize(u64::MAX), vec![3, 5, 17, 257, 641, 65_537, 6_700_417]);
+        assert_eq!(factorize(1 << 40), vec![2; 40]);
+        assert!(factorize(1).is_empty());
+    }
+}
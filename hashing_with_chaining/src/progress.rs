@@ -0,0 +1,103 @@
+use std::time::{Duration, Instant};
+
+// Progress lines on stderr for long runs: how much of the work is done, the rate since
+// the previous line and the time left at that rate. A line is printed at most once per
+// interval, so advancing in between costs only a clock read.
+pub struct Progress {
+    label: String,
+    total: u64,
+    done: u64,
+    interval: Duration,
+    start: Instant,
+    last_line: Instant,
+    done_at_last_line: u64,
+}
+
+// Hours, minutes and seconds, leaving out the leading units that are zero
+fn format_duration(seconds: f64) -> String {
+    let seconds: u64 = seconds.round() as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        return format!("{}h {:02}m {:02}s", hours, minutes, seconds);
+    }
+    if minutes > 0 {
+        return format!("{}m {:02}s", minutes, seconds);
+    }
+    return format!("{}s", seconds);
+}
+
+// Operations per second with an SI prefix
+fn format_rate(rate: f64) -> String {
+    return match rate {
+        r if r >= 1e9 => format!("{:.2} Gops/s", r / 1e9),
+        r if r >= 1e6 => format!("{:.2} Mops/s", r / 1e6),
+        r if r >= 1e3 => format!("{:.2} kops/s", r / 1e3),
+        r => format!("{:.0} ops/s", r),
+    }
+}
+
+impl Progress {
+    // Progress over total operations, with a line every ten seconds
+    pub fn new(label: &str, total: u64) -> Progress {
+        let now: Instant = Instant::now();
+        return Progress {
+            label: label.to_owned(),
+            total,
+            done: 0,
+            interval: Duration::from_secs(10),
+            start: now,
+            last_line: now,
+            done_at_last_line: 0,
+        }
+    }
+    pub fn with_interval(mut self, interval: Duration) -> Progress {
+        self.interval = interval;
+        return self;
+    }
+    // What the work is at the moment, such as the current test size
+    pub fn set_label(&mut self, label: &str) {
+        label.clone_into(&mut self.label);
+    }
+    pub fn advance(&mut self, operations: u64) {
+        self.done += operations;
+        let now: Instant = Instant::now();
+        if now - self.last_line >= self.interval {
+            eprintln!("{}", self.line(now));
+            self.last_line = now;
+            self.done_at_last_line = self.done;
+        }
+    }
+    fn line(&self, now: Instant) -> String {
+        let elapsed: f64 = (now - self.last_line).as_secs_f64();
+        let rate: f64 = (self.done - self.done_at_last_line) as f64 / elapsed.max(1e-9);
+        let percent: f64 = 100.0 * self.done as f64 / self.total.max(1) as f64;
+        let left: String = if rate > 0.0 { format_duration(self.total.saturating_sub(self.done) as f64 / rate) } else { "unknown".to_owned() };
+        return format!("{}: {:.1}% ({} of {}), {}, {} left", self.label, percent, self.done, self.total, format_rate(rate), left);
+    }
+    // Total time and average rate of the whole run
+    pub fn finish(&self) {
+        let elapsed: f64 = self.start.elapsed().as_secs_f64();
+        eprintln!("{}: {} operations in {}, {}", self.label, self.done, format_duration(elapsed), format_rate(self.done as f64 / elapsed.max(1e-9)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_show_the_rate_and_time_left() {
+        assert_eq!(format_duration(5.4), "5s");
+        assert_eq!(format_duration(125.0), "2m 05s");
+        assert_eq!(format_duration(3725.0), "1h 02m 05s");
+        assert_eq!(format_rate(2.5e7), "25.00 Mops/s");
+
+        // 30 million operations in the 2 seconds since the last line, 50 million to go
+        let mut progress: Progress = Progress::new("hwc n = 2^24", 100_000_000).with_interval(Duration::from_secs(3600));
+        progress.advance(20_000_000);
+        progress.done_at_last_line = 20_000_000;
+        progress.advance(30_000_000);
+        let now: Instant = progress.last_line + Duration::from_secs(2);
+        assert_eq!(progress.line(now), "hwc n = 2^24: 50.0% (50000000 of 100000000), 15.00 Mops/s, 3s left");
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::{HashMap, HashSet};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use crate::atomic_table::AtomicCounterTable;
+use crate::bloom::{BlockedBloomFilter, BloomFilter, CountingBloomFilter};
+use crate::count_min::{CountMin, UpdatePolicy};
+use crate::cuckoo_filter::CuckooFilter;
+use crate::cuckoo_table::CuckooTable;
+use crate::double_hashing::DoubleHashingTable;
+use crate::hopscotch::Hopscotch;
+use crate::hwc::{ArenaChains, ChainStorage, HwC, InlineChains};
+use crate::hyperloglog::HyperLogLog;
+use crate::linear_probing::LinearProbing;
+use crate::mergeable::MergeableSketch;
+use crate::norm_sketch::NormSketch;
+use crate::two_choice_hwc::TwoChoiceHwC;
+
+// Property tests over arbitrary sequences of operations. The tables are run next to a
+// HashMap or HashSet as the model and must give the same answer to every query, the filters
+// must never forget a key the model holds, and the sketches must keep their guarantees and
+// merge into the sketch of the whole stream. proptest shrinks a failing sequence to a
+// minimal one before reporting it.
+
+#[derive(Clone, Debug)]
+enum Op {
+    // Adds a nonzero delta to the count of a key, or inserts it into a set
+    Update(u64, i64),
+    // Takes a key out entirely
+    Delete(u64),
+    Query(u64),
+}
+
+// Mostly keys from a small range, so operations keep hitting the same keys and buckets,
+// and now and then any key but 0, which the open addressing tables reserve
+fn key() -> impl Strategy<Value = u64> {
+    return prop_oneof![9 => 1..64u64, 1 => 1..u64::MAX];
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let delta = prop_oneof![-5..0i64, 1..=5i64];
+    return prop_oneof![
+        4 => (key(), delta).prop_map(|(key, delta)| Op::Update(key, delta)),
+        1 => key().prop_map(Op::Delete),
+        2 => key().prop_map(Op::Query),
+    ];
+}
+
+fn ops() -> impl Strategy<Value = Vec<Op>> {
+    return vec(op(), 0..300);
+}
+
+// A stream of (key, count) for the sketches of non-negative counts
+fn stream() -> impl Strategy<Value = Vec<(u64, u64)>> {
+    return vec((key(), 1..100u64), 0..300);
+}
+
+fn check_hwc<S: ChainStorage<Count = i64>>(ops: &[Op], buckets: usize) -> Result<(), TestCaseError> {
+    let mut table: HwC<S> = HwC::new(buckets);
+    let mut model: HashMap<u64, i64> = HashMap::new();
+    for op in ops {
+        match *op {
+            Op::Update(key, delta) => {
+                table.insert(key, delta);
+                let count: &mut i64 = model.entry(key).or_default();
+                *count += delta;
+                if *count == 0 {
+                    model.remove(&key);
+                }
+            }
+            Op::Delete(key) => {
+                if let Some(count) = model.remove(&key) {
+                    table.insert(key, -count);
+                }
+            }
+            Op::Query(key) => {
+                prop_assert_eq!(table.get(key), model.get(&key).copied().unwrap_or(0));
+                prop_assert_eq!(table.query(key), model.contains_key(&key));
+            }
+        }
+    }
+    let norm: u64 = model.values().map(|count| count.unsigned_abs().pow(2)).sum();
+    prop_assert_eq!((table.norm(), table.get_norm()), (norm, norm));
+    prop_assert_eq!(table.stats().entries, model.len());
+    prop_assert!(model.iter().all(|(key, count)| table.get(*key) == *count));
+    // The entries survive serialization, into either chain layout
+    let reloaded: HwC<ArenaChains> = HwC::from_bytes(&table.to_bytes()).unwrap();
+    prop_assert!(model.iter().all(|(key, count)| reloaded.get(*key) == *count));
+    return Ok(());
+}
+
+proptest! {
+    #[test]
+    fn hwc_matches_a_hashmap(ops in ops(), buckets in 1..64usize) {
+        check_hwc::<InlineChains>(&ops, buckets)?;
+        check_hwc::<ArenaChains>(&ops, buckets)?;
+    }
+
+    #[test]
+    fn linear_probing_matches_a_hashset(ops in ops()) {
+        // 512 slots for at most 300 keys, so the table never fills up
+        let mut table: LinearProbing = LinearProbing::new(256);
+        let mut model: HashSet<u64> = HashSet::new();
+        for op in ops {
+            match op {
+                Op::Update(key, _) => {
+                    table.insert(key);
+                    model.insert(key);
+                }
+                Op::Delete(key) => prop_assert_eq!(table.delete(key), model.remove(&key)),
+                Op::Query(key) => prop_assert_eq!(table.query(key), model.contains(&key)),
+            }
+        }
+        prop_assert!(model.iter().all(|key| table.query(*key)));
+        prop_assert_eq!(table.stats().entries, model.len());
+    }
+
+    #[test]
+    fn atomic_counter_table_matches_a_hashmap(stream in stream()) {
+        let table: AtomicCounterTable = AtomicCounterTable::new(300);
+        let mut model: HashMap<u64, u64> = HashMap::new();
+        for (key, count) in stream {
+            table.insert(key, count);
+            *model.entry(key).or_default() += count;
+        }
+        prop_assert!((1..64).all(|key| table.get(key) == model.get(&key).copied().unwrap_or(0)));
+        prop_assert!(model.iter().all(|(key, count)| table.get(*key) == *count));
+    }
+
+    // The tables without deletion only see the updates and queries
+    #[test]
+    fn insert_only_tables_match_a_hashset(ops in ops(), stash_size in 0..4usize) {
+        let mut cuckoo: CuckooTable = CuckooTable::with_stash(300, stash_size);
+        let mut double_hashing: DoubleHashingTable = DoubleHashingTable::new(300);
+        let mut hopscotch: Hopscotch = Hopscotch::new(300, 8);
+        // Few buckets, so chains grow. Repeated inserts are stored again.
+        let mut two_choice: TwoChoiceHwC = TwoChoiceHwC::new(16);
+        let mut inserts: usize = 0;
+        let mut model: HashSet<u64> = HashSet::new();
+        for op in ops {
+            match op {
+                Op::Update(key, _) => {
+                    cuckoo.insert(key);
+                    double_hashing.insert(key);
+                    hopscotch.insert(key);
+                    two_choice.insert(key);
+                    inserts += 1;
+                    model.insert(key);
+                }
+                Op::Delete(_) => {}
+                Op::Query(key) => {
+                    let expected: bool = model.contains(&key);
+                    prop_assert_eq!(cuckoo.query(key), expected);
+                    prop_assert_eq!(double_hashing.query(key), expected);
+                    prop_assert_eq!(hopscotch.query(key), expected);
+                    prop_assert_eq!(two_choice.query(key), expected);
+                }
+            }
+        }
+        prop_assert!(model.iter().all(|key| cuckoo.query(*key) && double_hashing.query(*key) && hopscotch.query(*key) && two_choice.query(*key)));
+        prop_assert_eq!(cuckoo.stats().entries, model.len());
+        prop_assert_eq!(double_hashing.stats().entries, model.len());
+        prop_assert_eq!(hopscotch.stats().entries, model.len());
+        prop_assert_eq!(two_choice.stats().entries, inserts);
+    }
+
+    #[test]
+    fn filters_never_forget_a_key(ops in ops()) {
+        let mut bloom: BloomFilter = BloomFilter::optimal(300, 0.01);
+        let mut blocked: BlockedBloomFilter = BlockedBloomFilter::optimal(300, 0.01);
+        let mut counting: CountingBloomFilter = CountingBloomFilter::optimal(300, 0.01);
+        // 32 slots for up to 64 frequent keys, so inserts keep running out of room and
+        // evicting the fingerprints of other keys
+        let mut cuckoo: CuckooFilter = CuckooFilter::new(3, 16);
+        // Insertions per key, as the counting and cuckoo filters keep one copy each
+        let mut model: HashMap<u64, usize> = HashMap::new();
+        // The copies the cuckoo filter accepted, as it turns some away once full
+        let mut cuckoo_model: HashMap<u64, usize> = HashMap::new();
+        for op in ops {
+            match op {
+                Op::Update(key, _) => {
+                    bloom.insert(key);
+                    blocked.insert(key);
+                    counting.insert(key);
+                    *model.entry(key).or_default() += 1;
+                    if cuckoo.insert(key) {
+                        *cuckoo_model.entry(key).or_default() += 1;
+                    }
+                }
+                // Only inserted keys may be removed, once per insertion
+                Op::Delete(key) => {
+                    if let Some(copies) = model.get_mut(&key) {
+                        prop_assert!(counting.remove(key));
+                        *copies -= 1;
+                        if *copies == 0 {
+                            model.remove(&key);
+                        }
+                    }
+                    if let Some(copies) = cuckoo_model.get_mut(&key) {
+                        prop_assert!(cuckoo.remove(key));
+                        *copies -= 1;
+                        if *copies == 0 {
+                            cuckoo_model.remove(&key);
+                        }
+                    }
+                }
+                Op::Query(key) => {
+                    prop_assert!(!model.contains_key(&key) || counting.contains(key));
+                    prop_assert!(!cuckoo_model.contains_key(&key) || cuckoo.contains(key));
+                }
+            }
+            // Whether or not the last insert was accepted, no key held before is lost
+            prop_assert!(cuckoo_model.keys().all(|key| cuckoo.contains(*key)));
+        }
+        prop_assert!(model.keys().all(|key| bloom.contains(*key) && blocked.contains(*key) && counting.contains(*key)));
+        prop_assert_eq!(cuckoo.len(), cuckoo_model.values().sum::<usize>());
+    }
+
+    #[test]
+    fn count_min_never_underestimates(stream in stream(), removals in vec(any::<prop::sample::Index>(), 0..50)) {
+        let mut standard: CountMin = CountMin::new(3, 4, UpdatePolicy::Standard);
+        let mut conservative: CountMin = CountMin::new(3, 4, UpdatePolicy::Conservative);
+        let mut model: HashMap<u64, u64> = HashMap::new();
+        for (key, count) in &stream {
+            standard.insert(*key, *count);
+            conservative.insert(*key, *count);
+            *model.entry(*key).or_default() += count;
+        }
+        // Standard sketches can take back earlier updates, a strict turnstile stream
+        if !stream.is_empty() {
+            for removal in removals {
+                let (key, count) = stream[removal.index(stream.len())];
+                let left: &mut u64 = model.get_mut(&key).unwrap();
+                if *left >= count {
+                    standard.remove(key, count);
+                    *left -= count;
+                }
+            }
+        }
+        for (key, count) in &model {
+            prop_assert!(standard.estimate(*key) >= *count);
+            prop_assert!(conservative.estimate(*key) >= *count);
+        }
+    }
+
+    #[test]
+    fn merged_sketches_equal_one_pass(stream in stream(), split in any::<prop::sample::Index>()) {
+        let split: usize = split.index(stream.len() + 1);
+        let mut count_min: CountMin = CountMin::new(3, 6, UpdatePolicy::Standard);
+        let mut norm_sketch: NormSketch = NormSketch::new(6);
+        let mut hyperloglog: HyperLogLog = HyperLogLog::new(8);
+        let mut bloom: BloomFilter = BloomFilter::optimal(300, 0.01);
+        let mut halves: [(CountMin, NormSketch, HyperLogLog, BloomFilter); 2] = [0, 1]
+            .map(|_| (count_min.clone(), norm_sketch.clone(), hyperloglog.clone(), bloom.clone()));
+        for (i, (key, count)) in stream.iter().enumerate() {
+            count_min.insert(*key, *count);
+            norm_sketch.insert(*key, *count as i64 - 50);
+            hyperloglog.insert(*key);
+            bloom.insert(*key);
+            let half: &mut (CountMin, NormSketch, HyperLogLog, BloomFilter) = &mut halves[(i >= split) as usize];
+            half.0.insert(*key, *count);
+            half.1.insert(*key, *count as i64 - 50);
+            half.2.insert(*key);
+            half.3.insert(*key);
+        }
+        let [mut merged, other] = halves;
+        merged.0.merge(&other.0).unwrap();
+        merged.1.merge(&other.1).unwrap();
+        merged.2.merge(&other.2).unwrap();
+        merged.3.merge(&other.3).unwrap();
+        prop_assert_eq!(merged.0.to_bytes(), count_min.to_bytes());
+        prop_assert_eq!(merged.1.to_bytes(), norm_sketch.to_bytes());
+        prop_assert_eq!(merged.2.estimate(), hyperloglog.estimate());
+        prop_assert_eq!(merged.3.estimate_cardinality(), bloom.estimate_cardinality());
+        prop_assert!((0..64).all(|key| merged.3.contains(key) == bloom.contains(key)));
+    }
+}
@@ -0,0 +1,122 @@
+use rand::prelude::*;
+use crate::selection::partition;
+
+// How quicksort picks the pivot of every partition
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PivotStrategy {
+    // The first element, deterministic and quadratic on sorted input
+    First,
+    // A uniformly random element
+    Random,
+    // The median of three uniformly random elements, which splits closer to the middle
+    MedianOfThreeRandom,
+}
+
+fn median_of_three<T: Ord>(v: &[T], a: usize, b: usize, c: usize) -> usize {
+    if (v[a] <= v[b]) == (v[b] <= v[c]) {
+        return b;
+    }
+    if (v[b] <= v[a]) == (v[a] <= v[c]) {
+        return a;
+    }
+    return c;
+}
+
+fn choose_pivot<T: Ord>(v: &[T], strategy: PivotStrategy, rng: &mut impl Rng) -> usize {
+    return match strategy {
+        PivotStrategy::First => 0,
+        PivotStrategy::Random => rng.gen_range(0..v.len()),
+        PivotStrategy::MedianOfThreeRandom => {
+            let [a, b, c] = [rng.gen_range(0..v.len()), rng.gen_range(0..v.len()), rng.gen_range(0..v.len())];
+            median_of_three(v, a, b, c)
+        }
+    };
+}
+
+// Quicksort with three-way partitions. A fixed pivot rule has inputs that make every
+// partition peel off a single element, Θ(n^2) comparisons, and sorted input is one of them
+// for the first element. A random pivot makes the running time depend only on the coin
+// flips: two elements are compared iff one of them is the first pivot drawn among the
+// elements between them, which happens with probability 2 / (j - i + 1), so every input
+// takes 2 n ln n ≈ 1.39 n log2 n comparisons in expectation. The median of three random
+// elements brings this down to about 1.19 n log2 n. Recursing into the smaller side and
+// looping on the larger keeps the stack at O(log n) even when the splits are bad.
+pub fn quicksort<T: Ord>(v: &mut [T], strategy: PivotStrategy) {
+    let mut rng = thread_rng();
+    sort(v, strategy, &mut rng);
+}
+
+fn sort<T: Ord>(mut v: &mut [T], strategy: PivotStrategy, rng: &mut impl Rng) {
+    while v.len() > 1 {
+        let pivot: usize = choose_pivot(v, strategy, rng);
+        let (equal_start, equal_end) = partition(v, pivot);
+        let (less, rest) = std::mem::take(&mut v).split_at_mut(equal_start);
+        let greater: &mut [T] = &mut rest[equal_end - equal_start..];
+        if less.len() < greater.len() {
+            sort(less, strategy, rng);
+            v = greater;
+        } else {
+            sort(greater, strategy, rng);
+            v = less;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::cmp::Ordering;
+    use crate::hash::random_generator;
+
+    thread_local! {
+        static COMPARISONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    // Key that counts its comparisons
+    #[derive(PartialEq, Eq)]
+    struct Counted(u32);
+
+    impl PartialOrd for Counted {
+        fn partial_cmp(&self, other: &Counted) -> Option<Ordering> {
+            return Some(self.cmp(other));
+        }
+    }
+
+    impl Ord for Counted {
+        fn cmp(&self, other: &Counted) -> Ordering {
+            COMPARISONS.with(|count| count.set(count.get() + 1));
+            return self.0.cmp(&other.0);
+        }
+    }
+
+    fn comparisons(input: &[u32], strategy: PivotStrategy) -> usize {
+        let mut v: Vec<Counted> = input.iter().map(|x| Counted(*x)).collect();
+        COMPARISONS.with(|count| count.set(0));
+        quicksort(&mut v, strategy);
+        assert!(v.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        return COMPARISONS.with(|count| count.get());
+    }
+
+    #[test]
+    fn random_pivots_defeat_sorted_input() {
+        let strategies: [PivotStrategy; 3] = [PivotStrategy::First, PivotStrategy::Random, PivotStrategy::MedianOfThreeRandom];
+        let random: Vec<u32> = (0..5000).map(|_| random_generator(0, 1000)).collect();
+        for strategy in strategies {
+            let mut v: Vec<u32> = random.clone();
+            quicksort(&mut v, strategy);
+            let mut expected: Vec<u32> = random.clone();
+            expected.sort();
+            assert_eq!(v, expected);
+        }
+
+        // About 20.2 n comparisons are expected with random pivots and 19.1 n with the median
+        // of three for n = 100000, with standard deviations below n
+        let sorted: Vec<u32> = (0..100_000).collect();
+        let random_pivot: usize = comparisons(&sorted, PivotStrategy::Random);
+        let median_of_three: usize = comparisons(&sorted, PivotStrategy::MedianOfThreeRandom);
+        assert!(random_pivot < 25 * 100_000 && median_of_three < 23 * 100_000, "{} {}", random_pivot, median_of_three);
+        // The first element of a sorted range is its minimum, so n - 1 partitions of n^2 / 2 comparisons
+        assert!(comparisons(&sorted[..5000], PivotStrategy::First) >= 5000 * 4999 / 2);
+    }
+}
@@ -0,0 +1,225 @@
+use rand::prelude::*;
+use crate::graph::Graph;
+use crate::monte_carlo::{Accumulator, Estimate};
+
+// Finite Markov chain on states 0 to n - 1. Every state keeps the states it can move to with
+// the cumulative probabilities of the moves, so a step is one uniform draw and a binary search.
+#[derive(Clone, Debug)]
+pub struct MarkovChain {
+    next: Vec<Vec<usize>>,
+    cumulative: Vec<Vec<f64>>,
+}
+
+impl MarkovChain {
+    // Chain with transition matrix P, where P[i][j] is the probability of moving from i to j
+    pub fn from_matrix(matrix: &[Vec<f64>]) -> MarkovChain {
+        let mut next: Vec<Vec<usize>> = Vec::new();
+        let mut cumulative: Vec<Vec<f64>> = Vec::new();
+        for row in matrix {
+            assert!(row.len() == matrix.len() && row.iter().all(|p| *p >= 0.0), "Needs a square matrix of probabilities");
+            assert!((row.iter().sum::<f64>() - 1.0).abs() < 1e-9, "Rows must sum to one");
+            let mut sum: f64 = 0.0;
+            let (states, sums): (Vec<usize>, Vec<f64>) = row.iter().enumerate()
+                .filter(|(_, p)| **p > 0.0)
+                .map(|(j, p)| { sum += p; (j, sum) })
+                .unzip();
+            next.push(states);
+            cumulative.push(sums);
+        }
+        return MarkovChain { next, cumulative }
+    }
+    // Simple random walk on a graph, moving to a uniformly random neighbor. Parallel edges
+    // make a neighbor proportionally more likely. A connected graph has the stationary
+    // distribution deg(v) / 2m.
+    pub fn from_graph(graph: &Graph) -> MarkovChain {
+        let next: Vec<Vec<usize>> = graph.adjacency();
+        assert!(next.iter().all(|neighbors| !neighbors.is_empty()), "Every vertex needs an edge");
+        let cumulative: Vec<Vec<f64>> = next.iter()
+            .map(|neighbors| (1..=neighbors.len()).map(|i| i as f64 / neighbors.len() as f64).collect())
+            .collect();
+        return MarkovChain { next, cumulative }
+    }
+    pub fn states(&self) -> usize {
+        return self.next.len();
+    }
+    pub fn step(&self, state: usize, rng: &mut impl Rng) -> usize {
+        let cumulative: &[f64] = &self.cumulative[state];
+        let u: f64 = rng.gen::<f64>() * cumulative[cumulative.len() - 1];
+        let move_index: usize = cumulative.partition_point(|sum| *sum <= u).min(cumulative.len() - 1);
+        return self.next[state][move_index];
+    }
+    pub fn walk(&self, start: usize, steps: usize, rng: &mut impl Rng) -> Vec<usize> {
+        let mut walk: Vec<usize> = Vec::with_capacity(steps + 1);
+        walk.push(start);
+        for _ in 0..steps {
+            walk.push(self.step(walk[walk.len() - 1], rng));
+        }
+        return walk;
+    }
+    // Steps until a walk from start first reaches target, None if it has not after max_steps
+    pub fn hitting_time(&self, start: usize, target: usize, max_steps: usize, rng: &mut impl Rng) -> Option<usize> {
+        let mut state: usize = start;
+        for steps in 0..=max_steps {
+            if state == target {
+                return Some(steps);
+            }
+            state = self.step(state, rng);
+        }
+        return None;
+    }
+    // Steps until a walk from start has visited every state, None if it has not after max_steps
+    pub fn cover_time(&self, start: usize, max_steps: usize, rng: &mut impl Rng) -> Option<usize> {
+        let mut visited: Vec<bool> = vec![false; self.states()];
+        visited[start] = true;
+        let mut unvisited: usize = self.states() - 1;
+        let mut state: usize = start;
+        for steps in 0..=max_steps {
+            if unvisited == 0 {
+                return Some(steps);
+            }
+            state = self.step(state, rng);
+            if !visited[state] {
+                visited[state] = true;
+                unvisited -= 1;
+            }
+        }
+        return None;
+    }
+    // Mean hitting time over independent walks. Walks that hit max_steps count as max_steps,
+    // so the mean is too low when that happens.
+    pub fn expected_hitting_time(&self, start: usize, target: usize, walks: usize, max_steps: usize) -> Estimate {
+        let mut rng = thread_rng();
+        let mut accumulator: Accumulator = Accumulator::new();
+        for _ in 0..walks {
+            accumulator.push(self.hitting_time(start, target, max_steps, &mut rng).unwrap_or(max_steps) as f64);
+        }
+        return accumulator.estimate();
+    }
+    // Mean cover time over independent walks, counting walks cut off at max_steps as max_steps
+    pub fn expected_cover_time(&self, start: usize, walks: usize, max_steps: usize) -> Estimate {
+        let mut rng = thread_rng();
+        let mut accumulator: Accumulator = Accumulator::new();
+        for _ in 0..walks {
+            accumulator.push(self.cover_time(start, max_steps, &mut rng).unwrap_or(max_steps) as f64);
+        }
+        return accumulator.estimate();
+    }
+    // Fraction of time a walk of steps steps spends in every state after burn_in steps.
+    // For an irreducible chain this converges to the stationary distribution, periodic or not.
+    pub fn empirical_stationary(&self, start: usize, burn_in: usize, steps: usize) -> Vec<f64> {
+        let mut rng = thread_rng();
+        let mut state: usize = start;
+        for _ in 0..burn_in {
+            state = self.step(state, &mut rng);
+        }
+        let mut visits: Vec<usize> = vec![0; self.states()];
+        for _ in 0..steps {
+            state = self.step(state, &mut rng);
+            visits[state] += 1;
+        }
+        return visits.iter().map(|count| *count as f64 / steps.max(1) as f64).collect();
+    }
+}
+
+// PageRank of a directed graph given by the out-links of every node: the stationary
+// distribution of the walk that follows a random out-link with probability damping and jumps
+// to a uniformly random node otherwise, and always jumps from a node without out-links.
+// Power iteration applies one step of that walk to a distribution iterations times, which
+// brings the error down by a factor damping every time.
+pub fn pagerank_power_iteration(links: &[Vec<usize>], damping: f64, iterations: usize) -> Vec<f64> {
+    let n: usize = links.len();
+    let mut rank: Vec<f64> = vec![1.0 / n as f64; n];
+    for _ in 0..iterations {
+        let dangling: f64 = links.iter().zip(&rank).filter(|(out, _)| out.is_empty()).map(|(_, r)| r).sum();
+        let mut next: Vec<f64> = vec![((1.0 - damping) + damping * dangling) / n as f64; n];
+        for (out, r) in links.iter().zip(&rank) {
+            out.iter().for_each(|v| next[*v] += damping * r / out.len() as f64);
+        }
+        rank = next;
+    }
+    return rank;
+}
+
+// Monte Carlo PageRank (Avrachenkov et al.): walks_per_node walks start at every node and
+// stop at every step with probability 1 - damping. A walk takes 1 / (1 - damping) steps on
+// average and the PageRank of v is the expected visits to v per walk over that length, so
+// the visit counts give the estimate. The highest ranked nodes collect the most visits and
+// so have the smallest relative error, which settles the top of the ranking after a few
+// walks per node.
+pub fn pagerank_monte_carlo(links: &[Vec<usize>], damping: f64, walks_per_node: usize) -> Vec<f64> {
+    let n: usize = links.len();
+    let mut rng = thread_rng();
+    let mut visits: Vec<usize> = vec![0; n];
+    for start in 0..n {
+        for _ in 0..walks_per_node {
+            let mut node: usize = start;
+            visits[node] += 1;
+            while rng.gen::<f64>() < damping {
+                node = match links[node].choose(&mut rng) {
+                    Some(next) => *next,
+                    None => rng.gen_range(0..n),
+                };
+                visits[node] += 1;
+            }
+        }
+    }
+    let total: f64 = (n * walks_per_node) as f64 / (1.0 - damping);
+    return visits.iter().map(|count| *count as f64 / total).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_known_stationary_distributions() {
+        // Two states switching with probabilities a and b have stationary (b, a) / (a + b)
+        let (a, b): (f64, f64) = (0.1, 0.3);
+        let chain: MarkovChain = MarkovChain::from_matrix(&[vec![1.0 - a, a], vec![b, 1.0 - b]]);
+        let stationary: Vec<f64> = chain.empirical_stationary(0, 1000, 1_000_000);
+        assert!((stationary[0] - 0.75).abs() < 0.01 && (stationary[1] - 0.25).abs() < 0.01, "{:?}", stationary);
+        // The time to leave state 0 is geometric with mean 1 / a
+        let leaving: Estimate = chain.expected_hitting_time(0, 1, 20_000, 10_000);
+        assert!((leaving.mean - 1.0 / a).abs() < 5.0 * leaving.standard_error, "{:?}", leaving);
+
+        // The star with four leaves spends half of its steps in the center
+        let mut star: Graph = Graph::new(5);
+        (1..5).for_each(|leaf| star.add_edge(0, leaf));
+        let stationary: Vec<f64> = MarkovChain::from_graph(&star).empirical_stationary(0, 0, 100_000);
+        assert!((stationary[0] - 0.5).abs() < 0.01 && stationary[1..].iter().all(|p| (p - 0.125).abs() < 0.01), "{:?}", stationary);
+    }
+
+    #[test]
+    fn cycle_walks_match_analytic_times() {
+        // On a cycle of n vertices the cover time is n (n - 1) / 2, and reaching the opposite
+        // vertex is gambler's ruin from n / 2 with expected time (n / 2)^2
+        let n: usize = 20;
+        let mut cycle: Graph = Graph::new(n);
+        (0..n).for_each(|v| cycle.add_edge(v, (v + 1) % n));
+        let chain: MarkovChain = MarkovChain::from_graph(&cycle);
+        let cover: Estimate = chain.expected_cover_time(0, 5000, 1_000_000);
+        assert!((cover.mean - 190.0).abs() < 5.0 * cover.standard_error, "{:?}", cover);
+        let opposite: Estimate = chain.expected_hitting_time(0, n / 2, 5000, 1_000_000);
+        assert!((opposite.mean - 100.0).abs() < 5.0 * opposite.standard_error, "{:?}", opposite);
+        assert_eq!(chain.hitting_time(3, 3, 0, &mut thread_rng()), Some(0));
+    }
+
+    #[test]
+    fn monte_carlo_pagerank_matches_power_iteration() {
+        // Node v links to 2v and 2v + 1 modulo n and to three random nodes, node 0 links nowhere
+        let mut rng = thread_rng();
+        let n: usize = 200;
+        let links: Vec<Vec<usize>> = (0..n)
+            .map(|v| if v == 0 { Vec::new() } else { vec![2 * v % n, (2 * v + 1) % n, rng.gen_range(0..n), rng.gen_range(0..n), rng.gen_range(0..n)] })
+            .collect();
+        let exact: Vec<f64> = pagerank_power_iteration(&links, 0.85, 100);
+        assert!((exact.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        let estimate: Vec<f64> = pagerank_monte_carlo(&links, 0.85, 500);
+        let l1: f64 = exact.iter().zip(&estimate).map(|(a, b)| (a - b).abs()).sum();
+        assert!(l1 < 0.05, "{}", l1);
+
+        // Every node of a directed cycle has the same rank
+        let cycle: Vec<Vec<usize>> = (0..10).map(|v| vec![(v + 1) % 10]).collect();
+        assert!(pagerank_power_iteration(&cycle, 0.85, 50).iter().all(|r| (r - 0.1).abs() < 1e-9));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::count_min::{CountMin, UpdatePolicy};
+
+// Count-Min sketches over the dyadic intervals of the universe [0, 2^bits). The sketch at
+// level l counts the intervals [i 2^l, (i + 1) 2^l) under key i, so every range splits into
+// at most two intervals per level. Range sums overestimate by the errors of those at most
+// 2 bits intervals, and counts may be decreased as long as no value's count goes negative.
+pub struct RangeCountMin {
+    levels: Vec<CountMin>,
+    bits: u32,
+    total: i64,
+}
+
+impl RangeCountMin {
+    pub fn new(bits: u32, depth: usize, width_len: u32) -> RangeCountMin {
+        assert!((1..64).contains(&bits), "The universe must have between 1 and 63 bits");
+        return RangeCountMin {
+            levels: (0..bits).map(|_| CountMin::new(depth, width_len, UpdatePolicy::Standard)).collect(),
+            bits,
+            total: 0,
+        }
+    }
+    pub fn update(&mut self, value: u64, delta: i64) {
+        assert!(value >> self.bits == 0, "Value {} is outside of the universe", value);
+        for (level, sketch) in self.levels.iter_mut().enumerate() {
+            if delta >= 0 {
+                sketch.insert(value >> level, delta as u64);
+            } else {
+                sketch.remove(value >> level, delta.unsigned_abs());
+            }
+        }
+        self.total += delta;
+    }
+    // Count of the interval at the given level and index, where the top level is the universe
+    fn interval(&self, level: u32, index: u64) -> u64 {
+        if level == self.bits {
+            return self.total as u64;
+        }
+        return self.levels[level as usize].estimate(index);
+    }
+    // Estimated sum of the counts of the values in [lo, hi]
+    pub fn range_sum(&self, lo: u64, hi: u64) -> u64 {
+        if lo > hi {
+            return 0;
+        }
+        assert!(hi >> self.bits == 0, "Value {} is outside of the universe", hi);
+        // Peel off the interval at either end wherever the remaining range is not aligned
+        let (mut lo, mut hi) = (lo, hi + 1);
+        let mut sum: u64 = 0;
+        let mut level: u32 = 0;
+        while lo < hi {
+            if level == self.bits {
+                return sum + self.interval(level, 0);
+            }
+            if lo & 1 == 1 {
+                sum += self.interval(level, lo);
+                lo += 1;
+            }
+            if hi & 1 == 1 {
+                hi -= 1;
+                sum += self.interval(level, hi);
+            }
+            lo >>= 1;
+            hi >>= 1;
+            level += 1;
+        }
+        return sum;
+    }
+    // Smallest value whose estimated prefix sum reaches q times the total count, found by
+    // descending from the universe into the left or right half at every level
+    pub fn quantile(&self, q: f64) -> u64 {
+        assert!((0.0..=1.0).contains(&q), "Quantiles must be in [0, 1]");
+        let mut remaining: u64 = ((q * self.total as f64).ceil() as u64).max(1);
+        let mut index: u64 = 0;
+        for level in (0..self.bits).rev() {
+            let left: u64 = self.interval(level, 2 * index);
+            if remaining <= left {
+                index *= 2;
+            } else {
+                remaining -= left;
+                index = 2 * index + 1;
+            }
+        }
+        return index;
+    }
+    pub fn total(&self) -> i64 {
+        return self.total;
+    }
+    pub fn bits(&self) -> u32 {
+        return self.bits;
+    }
+    // Heap footprint of the counters of all levels in bytes
+    pub fn space_usage(&self) -> usize {
+        return self.levels.iter().map(|sketch| sketch.space_usage()).sum();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::random_generator;
+    use crate::hwc::HwC;
+
+    #[test]
+    fn range_sums_bound_the_exact_counts() {
+        let mut sketch: RangeCountMin = RangeCountMin::new(16, 5, 10);
+        let mut exact: HwC = HwC::new(1 << 16);
+        let mut inserted: Vec<u64> = Vec::new();
+        for _ in 0..50_000 {
+            let value: u64 = random_generator(0, 1 << 16) as u64;
+            sketch.update(value, 2);
+            exact.insert(value, 2);
+            inserted.push(value);
+        }
+        // Turnstile deletions of half of the inserts
+        for value in inserted.iter().step_by(2) {
+            sketch.update(*value, -2);
+            exact.insert(*value, -2);
+        }
+        assert_eq!(sketch.total(), 50_000);
+        for _ in 0..200 {
+            let (a, b) = (random_generator(0, 1 << 16) as u64, random_generator(0, 1 << 16) as u64);
+            let (lo, hi) = (a.min(b), a.max(b));
+            let truth: u64 = (lo..=hi).map(|x| exact.get(x) as u64).sum();
+            let estimate: u64 = sketch.range_sum(lo, hi);
+            assert!(estimate >= truth);
+            // Each of the at most 32 intervals overestimates by e / 2^10 of the total w.h.p.
+            assert!(estimate - truth <= 32 * 50_000 * 3 / 1024, "{} vs {}", estimate, truth);
+        }
+        assert_eq!(sketch.range_sum(0, (1 << 16) - 1), 50_000);
+        assert_eq!(sketch.range_sum(5, 4), 0);
+    }
+
+    #[test]
+    fn quantiles_of_a_uniform_stream() {
+        let mut sketch: RangeCountMin = RangeCountMin::new(16, 5, 12);
+        for value in 0..1u64 << 16 {
+            sketch.update(value, 3);
+        }
+        for q in [0.1, 0.5, 0.9] {
+            let estimate: f64 = sketch.quantile(q) as f64 / (1u64 << 16) as f64;
+            assert!((estimate - q).abs() < 0.01);
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use rand::prelude::*;
+
+// How a reservoir decides which stream items enter the sample
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SamplingAlgorithm {
+    // Item n replaces a random slot with probability k / n, costing one random number per item
+    R,
+    // Draws the gap to the next accepted item directly, so skipped items need no random numbers
+    L,
+}
+
+// Uniform sample of k items from a stream of unknown length: after n items every one of
+// them is in the sample with probability k / n.
+pub struct Reservoir<T> {
+    sample: Vec<T>,
+    k: usize,
+    seen: u64,
+    algorithm: SamplingAlgorithm,
+    // Algorithm L keeps the largest of k uniform priorities, and the index of the next item
+    // whose priority will be smaller
+    w: f64,
+    next: u64,
+}
+
+impl<T> Reservoir<T> {
+    pub fn new(k: usize, algorithm: SamplingAlgorithm) -> Reservoir<T> {
+        assert!(k > 0, "The sample needs at least one slot");
+        return Reservoir {
+            sample: Vec::with_capacity(k),
+            k,
+            seen: 0,
+            algorithm,
+            w: 1.0,
+            next: 0,
+        }
+    }
+    // Uniform in (0, 1], so its logarithm is finite
+    fn uniform() -> f64 {
+        return 1.0 - thread_rng().gen::<f64>();
+    }
+    // Moves the next accepted index of Algorithm L past a geometric number of items
+    fn skip(&mut self) {
+        self.w *= (Reservoir::<T>::uniform().ln() / self.k as f64).exp();
+        let gap: f64 = (Reservoir::<T>::uniform().ln() / (1.0 - self.w).ln()).floor();
+        self.next = self.next.saturating_add(gap as u64).saturating_add(1);
+    }
+    pub fn insert(&mut self, item: T) {
+        self.seen += 1;
+        if self.sample.len() < self.k {
+            self.sample.push(item);
+            if self.sample.len() == self.k && self.algorithm == SamplingAlgorithm::L {
+                self.next = self.seen;
+                self.skip();
+            }
+            return;
+        }
+        match self.algorithm {
+            SamplingAlgorithm::R => {
+                let slot: u64 = thread_rng().gen_range(0..self.seen);
+                if slot < self.k as u64 {
+                    self.sample[slot as usize] = item;
+                }
+            }
+            SamplingAlgorithm::L => {
+                if self.seen == self.next {
+                    self.sample[thread_rng().gen_range(0..self.k)] = item;
+                    self.skip();
+                }
+            }
+        }
+    }
+    // Inserts every item. Under Algorithm L the skipped items are only counted, without
+    // drawing random numbers for them.
+    pub fn extend<I: IntoIterator<Item = T>>(&mut self, items: I) {
+        let mut items = items.into_iter();
+        while self.sample.len() < self.k || self.algorithm == SamplingAlgorithm::R {
+            match items.next() {
+                Some(item) => self.insert(item),
+                None => return,
+            }
+        }
+        loop {
+            let skipped: u64 = self.next - self.seen - 1;
+            let consumed: u64 = items.by_ref().take(skipped as usize).count() as u64;
+            self.seen += consumed;
+            if consumed < skipped {
+                return;
+            }
+            match items.next() {
+                Some(item) => self.insert(item),
+                None => return,
+            }
+        }
+    }
+    pub fn sample(&self) -> &[T] {
+        return &self.sample;
+    }
+    pub fn into_sample(self) -> Vec<T> {
+        return self.sample;
+    }
+    // Number of stream items inserted so far
+    pub fn seen(&self) -> u64 {
+        return self.seen;
+    }
+    pub fn algorithm(&self) -> SamplingAlgorithm {
+        return self.algorithm;
+    }
+}
+
+// How a weighted reservoir decides which stream items enter the sample
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WeightedAlgorithm {
+    // Efraimidis–Spirakis: every item draws the key u^(1 / weight) and the k largest keys win
+    AEs,
+    // Exponential jumps: draws how much weight passes before the smallest key is beaten,
+    // so only the accepted items need random numbers
+    AExpJ,
+}
+
+// Sampled item with the logarithm of its key, ordered so the heap top is the smallest key
+struct Keyed<T> {
+    log_key: f64,
+    item: T,
+}
+
+impl<T> PartialEq for Keyed<T> {
+    fn eq(&self, other: &Keyed<T>) -> bool {
+        return self.log_key == other.log_key;
+    }
+}
+
+impl<T> Eq for Keyed<T> {}
+
+impl<T> PartialOrd for Keyed<T> {
+    fn partial_cmp(&self, other: &Keyed<T>) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl<T> Ord for Keyed<T> {
+    fn cmp(&self, other: &Keyed<T>) -> Ordering {
+        return other.log_key.total_cmp(&self.log_key);
+    }
+}
+
+// Weighted sample of k items without replacement, where items are drawn one after the other
+// with probability proportional to their weight among those not drawn yet. Keys are kept as
+// logarithms, ln(u) / weight, which do not underflow for large weights.
+pub struct WeightedReservoir<T> {
+    heap: BinaryHeap<Keyed<T>>,
+    k: usize,
+    algorithm: WeightedAlgorithm,
+    // Weight that still has to pass before A-ExpJ accepts an item
+    jump: f64,
+}
+
+impl<T> WeightedReservoir<T> {
+    pub fn new(k: usize, algorithm: WeightedAlgorithm) -> WeightedReservoir<T> {
+        assert!(k > 0, "The sample needs at least one slot");
+        return WeightedReservoir {
+            heap: BinaryHeap::with_capacity(k + 1),
+            k,
+            algorithm,
+            jump: 0.0,
+        }
+    }
+    fn smallest_log_key(&self) -> f64 {
+        return self.heap.peek().map_or(f64::NEG_INFINITY, |keyed| keyed.log_key);
+    }
+    // Weight to skip until the next accepted item, X = ln(r) / ln(T) for the smallest key T
+    fn draw_jump(&mut self) {
+        self.jump = Reservoir::<T>::uniform().ln() / self.smallest_log_key();
+    }
+    pub fn insert(&mut self, item: T, weight: f64) {
+        assert!(weight > 0.0 && weight.is_finite(), "Weights must be positive");
+        if self.heap.len() < self.k {
+            self.heap.push(Keyed { log_key: Reservoir::<T>::uniform().ln() / weight, item });
+            if self.heap.len() == self.k && self.algorithm == WeightedAlgorithm::AExpJ {
+                self.draw_jump();
+            }
+            return;
+        }
+        match self.algorithm {
+            WeightedAlgorithm::AEs => {
+                let log_key: f64 = Reservoir::<T>::uniform().ln() / weight;
+                if log_key > self.smallest_log_key() {
+                    self.heap.pop();
+                    self.heap.push(Keyed { log_key, item });
+                }
+            }
+            WeightedAlgorithm::AExpJ => {
+                self.jump -= weight;
+                if self.jump <= 0.0 {
+                    // The key is conditioned on beating the smallest one, so it is drawn from (T^weight, 1)
+                    let threshold: f64 = (weight * self.smallest_log_key()).exp();
+                    let u: f64 = threshold + (1.0 - threshold) * Reservoir::<T>::uniform();
+                    self.heap.pop();
+                    self.heap.push(Keyed { log_key: u.ln() / weight, item });
+                    self.draw_jump();
+                }
+            }
+        }
+    }
+    pub fn sample(&self) -> Vec<&T> {
+        return self.heap.iter().map(|keyed| &keyed.item).collect();
+    }
+    pub fn into_sample(self) -> Vec<T> {
+        return self.heap.into_iter().map(|keyed| keyed.item).collect();
+    }
+    pub fn len(&self) -> usize {
+        return self.heap.len();
+    }
+    pub fn is_empty(&self) -> bool {
+        return self.heap.is_empty();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subset::{binomial, rank_combination};
+
+    // Every item of a stream of n should be sampled k / n of the time, checked to five
+    // standard deviations of the binomial count over the trials
+    fn check_inclusion(algorithm: SamplingAlgorithm, use_extend: bool) {
+        const N: usize = 20;
+        const K: usize = 5;
+        const TRIALS: usize = 20_000;
+        let mut counts: [usize; N] = [0; N];
+        for _ in 0..TRIALS {
+            let mut reservoir: Reservoir<usize> = Reservoir::new(K, algorithm);
+            if use_extend {
+                reservoir.extend(0..N);
+            } else {
+                (0..N).for_each(|x| reservoir.insert(x));
+            }
+            assert_eq!(reservoir.sample().len(), K);
+            reservoir.sample().iter().for_each(|x| counts[*x] += 1);
+        }
+        let p: f64 = K as f64 / N as f64;
+        let expected: f64 = TRIALS as f64 * p;
+        let deviation: f64 = (TRIALS as f64 * p * (1.0 - p)).sqrt();
+        for count in counts {
+            assert!((count as f64 - expected).abs() < 5.0 * deviation, "{} vs {}", count, expected);
+        }
+    }
+
+    #[test]
+    fn algorithm_r_samples_uniformly() {
+        check_inclusion(SamplingAlgorithm::R, false);
+        check_inclusion(SamplingAlgorithm::R, true);
+    }
+
+    #[test]
+    fn algorithm_l_samples_uniformly() {
+        check_inclusion(SamplingAlgorithm::L, false);
+        check_inclusion(SamplingAlgorithm::L, true);
+    }
+
+    // Beyond every item having the right inclusion probability, all C(6, 3) = 20 samples of
+    // 3 out of 6 items should be equally likely. The chi-square statistic with 19 degrees
+    // of freedom exceeds 60 with probability about 3e-6.
+    #[test]
+    fn samples_are_uniform_subsets() {
+        for algorithm in [SamplingAlgorithm::R, SamplingAlgorithm::L] {
+            let mut counts: Vec<usize> = vec![0; binomial(6, 3) as usize];
+            for _ in 0..20_000 {
+                let mut reservoir: Reservoir<u64> = Reservoir::new(3, algorithm);
+                reservoir.extend(0..6);
+                counts[rank_combination(reservoir.sample()) as usize] += 1;
+            }
+            let statistic: f64 = counts.iter().map(|count| (*count as f64 - 1000.0).powi(2) / 1000.0).sum();
+            assert!(statistic < 60.0, "{:?} {}", algorithm, statistic);
+        }
+    }
+
+    #[test]
+    fn short_streams_are_kept_whole() {
+        let mut reservoir: Reservoir<u64> = Reservoir::new(10, SamplingAlgorithm::L);
+        reservoir.extend(0..4);
+        assert_eq!(reservoir.sample(), &[0, 1, 2, 3]);
+        reservoir.extend(4..1_000_000);
+        assert_eq!(reservoir.seen(), 1_000_000);
+        assert_eq!(reservoir.into_sample().len(), 10);
+    }
+
+    // A single slot holds each item with probability proportional to its weight
+    fn check_weighted_inclusion(algorithm: WeightedAlgorithm) {
+        const TRIALS: usize = 20_000;
+        // Item 4 stands for 100 light items of total weight 4, which A-ExpJ jumps over in groups
+        let weights: [f64; 5] = [1.0, 2.0, 3.0, 4.0, 4.0];
+        let mut counts: [usize; 5] = [0; 5];
+        for _ in 0..TRIALS {
+            let mut reservoir: WeightedReservoir<usize> = WeightedReservoir::new(1, algorithm);
+            for (i, weight) in weights.iter().enumerate().take(4) {
+                reservoir.insert(i, *weight);
+                (0..25).for_each(|_| reservoir.insert(4, 0.04));
+            }
+            counts[*reservoir.sample()[0]] += 1;
+        }
+        for (count, weight) in counts.iter().zip(weights) {
+            let p: f64 = weight / 14.0;
+            let deviation: f64 = (TRIALS as f64 * p * (1.0 - p)).sqrt();
+            assert!((*count as f64 - TRIALS as f64 * p).abs() < 5.0 * deviation, "{} vs {}", count, TRIALS as f64 * p);
+        }
+    }
+
+    #[test]
+    fn weighted_samples_follow_the_weights() {
+        check_weighted_inclusion(WeightedAlgorithm::AEs);
+        check_weighted_inclusion(WeightedAlgorithm::AExpJ);
+        let mut reservoir: WeightedReservoir<u64> = WeightedReservoir::new(3, WeightedAlgorithm::AExpJ);
+        (1..=1000u64).for_each(|x| reservoir.insert(x, x as f64));
+        assert_eq!(reservoir.into_sample().len(), 3);
+    }
+}
@@ -0,0 +1,341 @@
+use std::fmt;
+use std::fmt::Display;
+use std::io;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::de::{MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::memory::MemoryFootprint;
+use crate::metrics::{Elapsed, Latencies, LATENCY_PERCENTILES};
+use crate::table_stats::TableStats;
+
+// Columns of every results file, so files of different experiments can be concatenated and
+// loaded as one table
+pub const HEADER: [&str; 7] = ["algorithm", "n", "seed", "phase", "duration_ns", "memory", "extra"];
+
+// One measurement: how long a phase of an algorithm took on an input of n keys and how much
+// memory it held, in bytes. Metrics particular to an experiment go into extra as
+// name=value pairs, written as one column separated by semicolons. Columns without a value
+// stay empty.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResultRow {
+    pub algorithm: String,
+    pub n: u64,
+    pub seed: Option<u64>,
+    pub phase: String,
+    pub duration_ns: Option<i128>,
+    pub memory: Option<usize>,
+    #[serde(serialize_with = "serialize_extra", deserialize_with = "deserialize_extra")]
+    pub extra: Vec<(String, String)>,
+}
+
+// Extra metrics as one JSON object, in the order they were added
+fn serialize_extra<S: Serializer>(extra: &[(String, String)], serializer: S) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(extra.len()))?;
+    for (name, value) in extra {
+        map.serialize_entry(name, value)?;
+    }
+    return map.end();
+}
+
+struct ExtraVisitor;
+
+impl<'de> Visitor<'de> for ExtraVisitor {
+    type Value = Vec<(String, String)>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        return formatter.write_str("a map of metric names to values");
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut extra: Vec<(String, String)> = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            extra.push(entry);
+        }
+        return Ok(extra);
+    }
+}
+
+// Back from the JSON object, keeping the order of the metrics
+fn deserialize_extra<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(String, String)>, D::Error> {
+    return deserializer.deserialize_map(ExtraVisitor);
+}
+
+impl ResultRow {
+    pub fn new(algorithm: &str, n: u64, phase: &str) -> ResultRow {
+        return ResultRow { algorithm: algorithm.to_owned(), n, phase: phase.to_owned(), ..ResultRow::default() }
+    }
+    pub fn seed(mut self, seed: u64) -> ResultRow {
+        self.seed = Some(seed);
+        return self;
+    }
+    pub fn duration_ns(mut self, duration_ns: i128) -> ResultRow {
+        self.duration_ns = Some(duration_ns);
+        return self;
+    }
+    // The duration of a phase of operations of one kind, with their rate and the time of one
+    pub fn throughput(self, elapsed: Elapsed, operations: u64) -> ResultRow {
+        return self.duration_ns(elapsed.whole_nanoseconds())
+            .metric("ops_per_sec", elapsed.ops_per_second(operations))
+            .metric("ns_per_op", elapsed.ns_per_op(operations));
+    }
+    // The sampled latency percentiles of single operations, as latency_p50_ns and so on up to
+    // latency_max_ns, and how many latencies were sampled
+    pub fn latencies(self, latencies: &Latencies) -> ResultRow {
+        let Some((percentiles, max)) = latencies.percentiles() else { return self; };
+        let row: ResultRow = LATENCY_PERCENTILES.iter().zip(percentiles)
+            .fold(self, |row, ((name, _), latency)| row.metric(&format!("latency_{}_ns", name), latency.whole_nanoseconds()));
+        return row.metric("latency_max_ns", max.whole_nanoseconds())
+            .metric("latency_samples", latencies.samples());
+    }
+    pub fn memory(mut self, bytes: usize) -> ResultRow {
+        self.memory = Some(bytes);
+        return self;
+    }
+    pub fn metric(mut self, name: &str, value: impl Display) -> ResultRow {
+        self.extra.push((name.to_owned(), value.to_string()));
+        return self;
+    }
+    // The heap bytes of a structure as the memory, with its allocations and bits per key
+    // for the given number of keys
+    pub fn footprint(self, structure: &dyn MemoryFootprint, keys: usize) -> ResultRow {
+        return self.memory(structure.heap_bytes())
+            .metric("allocations", structure.allocations())
+            .metric("bits_per_key", structure.bits_per_key(keys));
+    }
+    // The load factor, longest chain and empty fraction of a table, and its chain length
+    // histogram as counts separated by slashes, starting at length 0
+    pub fn table_stats(self, stats: &TableStats) -> ResultRow {
+        let histogram: Vec<String> = stats.chain_histogram.iter().map(|count| count.to_string()).collect();
+        return self.metric("load_factor", stats.load_factor())
+            .metric("max_chain", stats.max_chain)
+            .metric("empty_fraction", stats.empty_fraction())
+            .metric("chain_histogram", histogram.join("/"));
+    }
+    // A column or extra metric as a number, None when the row has no numeric value for it
+    pub fn value(&self, column: &str) -> Option<f64> {
+        return match column {
+            "n" => Some(self.n as f64),
+            "seed" => self.seed.map(|seed| seed as f64),
+            "duration_ns" => self.duration_ns.map(|duration| duration as f64),
+            "memory" => self.memory.map(|bytes| bytes as f64),
+            _ => self.extra.iter().find(|(name, _)| name == column).and_then(|(_, value)| value.parse().ok()),
+        };
+    }
+}
+
+// A field as CSV: quoted, with quotes doubled, when it holds a comma, quote or line break
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        return format!("\"{}\"", field.replace('"', "\"\""));
+    }
+    return field.to_owned();
+}
+
+fn optional(value: Option<impl Display>) -> String {
+    return value.map_or(String::new(), |value| value.to_string());
+}
+
+// Splits CSV text into records of fields, undoing the quoting of escape
+fn csv_records(text: &str) -> Vec<Vec<String>> {
+    let mut records: Vec<Vec<String>> = Vec::new();
+    let mut record: Vec<String> = Vec::new();
+    let mut field: String = String::new();
+    let mut quoted: bool = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    return records;
+}
+
+fn parse_field<T: std::str::FromStr>(field: &str, column: &str) -> io::Result<Option<T>> {
+    if field.is_empty() {
+        return Ok(None);
+    }
+    return field.parse().map(Some).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("Bad {} {}", column, field)));
+}
+
+// Rows of CSV text as written by CsvWriter. Header lines are skipped wherever they occur, so
+// concatenated results files read as one.
+pub fn read_csv(text: &str) -> io::Result<Vec<ResultRow>> {
+    let mut rows: Vec<ResultRow> = Vec::new();
+    for record in csv_records(text) {
+        if record == HEADER {
+            continue;
+        }
+        if record.len() != HEADER.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Expected {} fields, found {}", HEADER.len(), record.len())));
+        }
+        let extra: Vec<(String, String)> = record[6].split(';')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                return (name.to_owned(), value.to_owned());
+            })
+            .collect();
+        rows.push(ResultRow {
+            algorithm: record[0].clone(),
+            n: parse_field(&record[1], "n")?.unwrap_or(0),
+            seed: parse_field(&record[2], "seed")?,
+            phase: record[3].clone(),
+            duration_ns: parse_field(&record[4], "duration_ns")?,
+            memory: parse_field(&record[5], "memory")?,
+            extra,
+        });
+    }
+    return Ok(rows);
+}
+
+// The measurements of a JSON report written by Report::write_to
+pub fn read_report(text: &str) -> io::Result<Vec<ResultRow>> {
+    #[derive(Deserialize)]
+    struct Measurements {
+        measurements: Vec<ResultRow>,
+    }
+    let report: Measurements = serde_json::from_str(text)?;
+    return Ok(report.measurements);
+}
+
+// Writes result rows as CSV under the HEADER columns
+pub struct CsvWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> CsvWriter<W> {
+    // Writes the header first unless the writer appends to rows that already have one
+    pub fn new(mut writer: W, write_header: bool) -> io::Result<CsvWriter<W>> {
+        if write_header {
+            writeln!(writer, "{}", HEADER.join(","))?;
+        }
+        return Ok(CsvWriter { writer })
+    }
+    pub fn write(&mut self, row: &ResultRow) -> io::Result<()> {
+        let extra: Vec<String> = row.extra.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+        let fields: [String; 7] = [
+            row.algorithm.clone(),
+            row.n.to_string(),
+            optional(row.seed),
+            row.phase.clone(),
+            optional(row.duration_ns),
+            optional(row.memory),
+            extra.join(";"),
+        ];
+        let escaped: Vec<String> = fields.iter().map(|field| escape(field)).collect();
+        return writeln!(self.writer, "{}", escaped.join(","));
+    }
+    pub fn into_inner(self) -> W {
+        return self.writer;
+    }
+}
+
+// The machine a run was made on, enough to tell apart results from different ones
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Machine {
+    pub os: String,
+    pub arch: String,
+    pub cpus: usize,
+}
+
+impl Machine {
+    pub fn current() -> Machine {
+        return Machine {
+            os: std::env::consts::OS.to_owned(),
+            arch: std::env::consts::ARCH.to_owned(),
+            cpus: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+// A whole run as one JSON document: the crate version, when and where it ran, its
+// configuration and every measurement, so a report can be read and compared without the
+// command line that produced it
+#[derive(Clone, Debug, Serialize)]
+pub struct Report<C: Serialize> {
+    pub crate_version: String,
+    // Seconds since the Unix epoch
+    pub started_at: u64,
+    pub machine: Machine,
+    pub config: C,
+    pub measurements: Vec<ResultRow>,
+}
+
+impl<C: Serialize> Report<C> {
+    pub fn new(config: C) -> Report<C> {
+        return Report {
+            crate_version: env!("CARGO_PKG_VERSION").to_owned(),
+            started_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs()),
+            machine: Machine::current(),
+            config,
+            measurements: Vec::new(),
+        }
+    }
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        serde_json::to_writer_pretty(writer, self)?;
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_rows_under_the_header() {
+        let mut writer: CsvWriter<Vec<u8>> = CsvWriter::new(Vec::new(), true).unwrap();
+        writer.write(&ResultRow::new("hwc", 4096, "construction").seed(42).duration_ns(1500).memory(65536)).unwrap();
+        let mut stats: TableStats = TableStats::new();
+        [0, 1, 1, 2].iter().for_each(|len| stats.record_chain(*len));
+        writer.write(&ResultRow::new("cuckoo, stash \"4\"", 4, "query").table_stats(&stats)).unwrap();
+        let text: String = String::from_utf8(writer.into_inner()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "algorithm,n,seed,phase,duration_ns,memory,extra");
+        assert_eq!(lines[1], "hwc,4096,42,construction,1500,65536,");
+        assert_eq!(lines[2], "\"cuckoo, stash \"\"4\"\"\",4,,query,,,load_factor=1;max_chain=2;empty_fraction=0.25;chain_histogram=1/2/1");
+
+        // Reading the text back, twice over as if two files were concatenated, gives the rows
+        let rows: Vec<ResultRow> = read_csv(&(text.clone() + &text)).unwrap();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[1].algorithm, "cuckoo, stash \"4\"");
+        assert_eq!((rows[0].value("duration_ns"), rows[0].value("memory"), rows[0].seed), (Some(1500.0), Some(65536.0), Some(42)));
+        assert_eq!((rows[1].value("max_chain"), rows[1].value("chain_histogram"), rows[1].value("duration_ns")), (Some(2.0), None, None));
+        assert!(read_csv("hwc,16,query\n").is_err());
+
+        let appended: CsvWriter<Vec<u8>> = CsvWriter::new(Vec::new(), false).unwrap();
+        assert!(appended.into_inner().is_empty());
+    }
+
+    #[test]
+    fn reports_hold_the_configuration_and_measurements() {
+        let mut report: Report<Vec<&str>> = Report::new(vec!["hwc", "lp"]);
+        report.measurements.push(ResultRow::new("lp", 16, "query").duration_ns(2000).metric("max_chain", 1).metric("empty_fraction", 0.5));
+        let mut json: Vec<u8> = Vec::new();
+        report.write_to(&mut json).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(value["crate_version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(value["config"], serde_json::json!(["hwc", "lp"]));
+        assert!(value["machine"]["cpus"].as_u64().unwrap() >= 1);
+        assert_eq!(value["measurements"][0], serde_json::json!({
+            "algorithm": "lp", "n": 16, "seed": null, "phase": "query", "duration_ns": 2000, "memory": null,
+            "extra": { "max_chain": "1", "empty_fraction": "0.5" },
+        }));
+        assert_eq!(read_report(std::str::from_utf8(&json).unwrap()).unwrap(), report.measurements);
+    }
+}
@@ -0,0 +1,286 @@
+use rand::prelude::*;
+
+// Weighted set cover: pick sets of minimum total cost whose union is the universe 0 to n - 1
+#[derive(Clone, Debug)]
+pub struct SetCover {
+    universe: usize,
+    sets: Vec<Vec<usize>>,
+    costs: Vec<f64>,
+}
+
+impl SetCover {
+    pub fn new(universe: usize, sets: Vec<Vec<usize>>, costs: Vec<f64>) -> SetCover {
+        assert!(sets.len() == costs.len() && costs.iter().all(|c| *c > 0.0), "Needs a positive cost for every set");
+        assert!(sets.iter().flatten().all(|e| *e < universe), "Sets must lie in the universe");
+        let mut covered: Vec<bool> = vec![false; universe];
+        sets.iter().flatten().for_each(|e| covered[*e] = true);
+        assert!(covered.iter().all(|c| *c), "Every element needs a set");
+        return SetCover { universe, sets, costs }
+    }
+    // Every element lies in each set independently with probability density, plus one
+    // random set so that a cover exists. Costs are uniform in [1, 2).
+    pub fn random(universe: usize, sets: usize, density: f64, rng: &mut impl Rng) -> SetCover {
+        let mut members: Vec<Vec<usize>> = (0..sets)
+            .map(|_| (0..universe).filter(|_| rng.gen::<f64>() < density).collect())
+            .collect();
+        for e in 0..universe {
+            let set: &mut Vec<usize> = &mut members[rng.gen_range(0..sets)];
+            if !set.contains(&e) {
+                set.push(e);
+            }
+        }
+        let costs: Vec<f64> = (0..sets).map(|_| rng.gen_range(1.0..2.0)).collect();
+        return SetCover::new(universe, members, costs);
+    }
+    pub fn cost(&self, chosen: &[usize]) -> f64 {
+        return chosen.iter().map(|s| self.costs[*s]).sum();
+    }
+    pub fn fractional_cost(&self, fractional: &[f64]) -> f64 {
+        return fractional.iter().zip(&self.costs).map(|(x, c)| x * c).sum();
+    }
+    pub fn is_cover(&self, chosen: &[usize]) -> bool {
+        let mut covered: Vec<bool> = vec![false; self.universe];
+        chosen.iter().flat_map(|s| &self.sets[*s]).for_each(|e| covered[*e] = true);
+        return covered.iter().all(|c| *c);
+    }
+}
+
+// Optimum of the LP relaxation min sum c_S x_S subject to sum_{S ∋ e} x_S >= 1 and x >= 0,
+// by the simplex method on its dual max sum y_e subject to sum_{e ∈ S} y_e <= c_S and
+// y >= 0. The slacks of the dual give a feasible starting basis, and the optimal x_S are
+// the reduced costs of the slacks in the final tableau. Bland's rule keeps the highly
+// degenerate covering LPs from cycling.
+pub fn fractional_set_cover(cover: &SetCover) -> Vec<f64> {
+    let (n, m): (usize, usize) = (cover.universe, cover.sets.len());
+    // Row i is the constraint of set i over the columns y, then the slacks, then the bound
+    let mut tableau: Vec<Vec<f64>> = (0..m).map(|i| {
+        let mut row: Vec<f64> = vec![0.0; n + m + 1];
+        cover.sets[i].iter().for_each(|e| row[*e] = 1.0);
+        row[n + i] = 1.0;
+        row[n + m] = cover.costs[i];
+        return row;
+    }).collect();
+    let mut objective: Vec<f64> = (0..n + m + 1).map(|j| if j < n { -1.0 } else { 0.0 }).collect();
+    let mut basis: Vec<usize> = (n..n + m).collect();
+    const EPSILON: f64 = 1e-9;
+
+    while let Some(entering) = (0..n + m).find(|j| objective[*j] < -EPSILON) {
+        // Every element lies in a set, so some row bounds the entering column
+        let leaving: usize = (0..m)
+            .filter(|i| tableau[*i][entering] > EPSILON)
+            .min_by(|a, b| {
+                let ratio = |i: usize| tableau[i][n + m] / tableau[i][entering];
+                return ratio(*a).total_cmp(&ratio(*b)).then(basis[*a].cmp(&basis[*b]));
+            })
+            .unwrap();
+        let pivot: f64 = tableau[leaving][entering];
+        tableau[leaving].iter_mut().for_each(|a| *a /= pivot);
+        let pivot_row: Vec<f64> = tableau[leaving].clone();
+        for (i, row) in tableau.iter_mut().enumerate() {
+            let factor: f64 = row[entering];
+            if i != leaving && factor != 0.0 {
+                row.iter_mut().zip(&pivot_row).for_each(|(a, p)| *a -= factor * p);
+            }
+        }
+        let factor: f64 = objective[entering];
+        objective.iter_mut().zip(&pivot_row).for_each(|(a, p)| *a -= factor * p);
+        basis[leaving] = entering;
+    }
+    return objective[n..n + m].iter().map(|x| x.max(0.0)).collect();
+}
+
+// Randomized rounding of a fractional cover: ⌈ln n⌉ + 2 rounds each take every set S with
+// probability min(1, x_S). An element is left uncovered by a round with probability
+// prod (1 - x_S) <= e^-sum x_S <= 1 / e, so by all rounds with probability below 1 / (e^2 n),
+// and the few that remain get their cheapest set. The expected cost is at most
+// (ln n + 3) times the LP optimum plus those repairs, an O(log n) approximation, which is
+// the best possible in polynomial time unless P = NP.
+pub fn round_set_cover(cover: &SetCover, fractional: &[f64], rng: &mut impl Rng) -> Vec<usize> {
+    let rounds: usize = (cover.universe.max(1) as f64).ln().ceil() as usize + 2;
+    let mut chosen: Vec<bool> = vec![false; cover.sets.len()];
+    for _ in 0..rounds {
+        for (s, x) in fractional.iter().enumerate() {
+            if rng.gen::<f64>() < *x {
+                chosen[s] = true;
+            }
+        }
+    }
+    let mut covered: Vec<bool> = vec![false; cover.universe];
+    (0..cover.sets.len()).filter(|s| chosen[*s]).flat_map(|s| &cover.sets[s]).for_each(|e| covered[*e] = true);
+    for e in 0..cover.universe {
+        if !covered[e] {
+            let cheapest: usize = (0..cover.sets.len())
+                .filter(|s| cover.sets[*s].contains(&e))
+                .min_by(|a, b| cover.costs[*a].total_cmp(&cover.costs[*b]))
+                .unwrap();
+            chosen[cheapest] = true;
+            cover.sets[cheapest].iter().for_each(|e| covered[*e] = true);
+        }
+    }
+    return (0..cover.sets.len()).filter(|s| chosen[*s]).collect();
+}
+
+// Cheapest cover by trying all 2^m collections of sets, for up to 64 elements and a few
+// dozen sets
+pub fn exact_set_cover(cover: &SetCover) -> Vec<usize> {
+    assert!(cover.universe <= 64 && cover.sets.len() < 32, "Too large to solve exactly");
+    let masks: Vec<u64> = cover.sets.iter().map(|set| set.iter().fold(0, |mask, e| mask | 1 << e)).collect();
+    let all: u64 = if cover.universe == 64 { u64::MAX } else { (1 << cover.universe) - 1 };
+    let mut best: (f64, u32) = (f64::INFINITY, 0);
+    for collection in 0..1u32 << masks.len() {
+        let sets = (0..masks.len()).filter(|s| collection >> s & 1 == 1);
+        if sets.clone().fold(0, |union, s| union | masks[s]) == all {
+            let cost: f64 = sets.map(|s| cover.costs[s]).sum();
+            if cost < best.0 {
+                best = (cost, collection);
+            }
+        }
+    }
+    return (0..masks.len()).filter(|s| best.1 >> s & 1 == 1).collect();
+}
+
+// Routing with candidate paths: every commodity sends one unit along one of its paths, given
+// as lists of edges 0 to edges - 1, and the aim is the least congestion, the largest number
+// of paths through one edge
+#[derive(Clone, Debug)]
+pub struct Routing {
+    edges: usize,
+    paths: Vec<Vec<Vec<usize>>>,
+}
+
+impl Routing {
+    pub fn new(edges: usize, paths: Vec<Vec<Vec<usize>>>) -> Routing {
+        assert!(paths.iter().all(|candidates| !candidates.is_empty()), "Every commodity needs a path");
+        assert!(paths.iter().flatten().flatten().all(|e| *e < edges), "Paths must use edges 0 to edges - 1");
+        return Routing { edges, paths }
+    }
+    // Every commodity gets paths candidate paths of length random edges
+    pub fn random(edges: usize, commodities: usize, paths: usize, length: usize, rng: &mut impl Rng) -> Routing {
+        let candidates: Vec<Vec<Vec<usize>>> = (0..commodities)
+            .map(|_| (0..paths).map(|_| rand::seq::index::sample(rng, edges, length).into_vec()).collect())
+            .collect();
+        return Routing::new(edges, candidates);
+    }
+    // Congestion when commodity i takes its path choice[i]
+    pub fn congestion(&self, choice: &[usize]) -> usize {
+        let mut load: Vec<usize> = vec![0; self.edges];
+        choice.iter().enumerate().flat_map(|(i, p)| &self.paths[i][*p]).for_each(|e| load[*e] += 1);
+        return load.into_iter().max().unwrap_or(0);
+    }
+    // Congestion when commodity i sends fractional[i][p] along its path p
+    pub fn fractional_congestion(&self, fractional: &[Vec<f64>]) -> f64 {
+        let mut load: Vec<f64> = vec![0.0; self.edges];
+        for (candidates, weights) in self.paths.iter().zip(fractional) {
+            candidates.iter().zip(weights).for_each(|(path, w)| path.iter().for_each(|e| load[*e] += w));
+        }
+        return load.into_iter().fold(0.0, f64::max);
+    }
+}
+
+// Fractional routing of near minimum congestion by multiplicative weights: rounds times
+// every commodity in turn routes a unit along the path that is cheapest when an edge that
+// has carried l units costs e^(epsilon l), and the fractional routing is the average of the
+// rounds. Expensive edges are the congested ones, so the flow keeps moving away from them,
+// and for small epsilon and many rounds the average approaches the optimum of the LP
+// relaxation.
+pub fn fractional_routing(routing: &Routing, rounds: usize, epsilon: f64) -> Vec<Vec<f64>> {
+    let mut load: Vec<f64> = vec![0.0; routing.edges];
+    let mut counts: Vec<Vec<usize>> = routing.paths.iter().map(|candidates| vec![0; candidates.len()]).collect();
+    for _ in 0..rounds {
+        for (candidates, count) in routing.paths.iter().zip(counts.iter_mut()) {
+            // Costs relative to the most loaded edge, which keeps the exponentials finite
+            let top: f64 = load.iter().fold(0.0, |a, b| a.max(*b));
+            let cost = |path: &Vec<usize>| path.iter().map(|e| (epsilon * (load[*e] - top)).exp()).sum::<f64>();
+            let cheapest: usize = (0..candidates.len()).min_by(|a, b| cost(&candidates[*a]).total_cmp(&cost(&candidates[*b]))).unwrap();
+            count[cheapest] += 1;
+            candidates[cheapest].iter().for_each(|e| load[*e] += 1.0);
+        }
+    }
+    return counts.iter().map(|count| count.iter().map(|c| *c as f64 / rounds.max(1) as f64).collect()).collect();
+}
+
+// Raghavan–Thompson rounding: every commodity takes path p with probability fractional[i][p].
+// The load of an edge is then a sum of independent indicators with the fractional load as
+// its mean, so by a Chernoff bound and a union bound over the edges the congestion is
+// O(log m / log log m) times the fractional congestion with high probability.
+pub fn round_routing(routing: &Routing, fractional: &[Vec<f64>], rng: &mut impl Rng) -> Vec<usize> {
+    return routing.paths.iter().zip(fractional).map(|(candidates, weights)| {
+        let mut u: f64 = rng.gen::<f64>() * weights.iter().sum::<f64>();
+        for (p, w) in weights.iter().enumerate() {
+            if u < *w {
+                return p;
+            }
+            u -= w;
+        }
+        return candidates.len() - 1;
+    }).collect();
+}
+
+// Routing of least congestion by trying every combination of paths
+pub fn exact_routing(routing: &Routing) -> Vec<usize> {
+    let mut choice: Vec<usize> = vec![0; routing.paths.len()];
+    let mut best: (usize, Vec<usize>) = (routing.congestion(&choice), choice.clone());
+    // Counts through all choices like an odometer with digit i running over the paths of i
+    loop {
+        let Some(i) = (0..choice.len()).find(|i| choice[*i] + 1 < routing.paths[*i].len()) else {
+            return best.1;
+        };
+        choice[i] += 1;
+        choice[..i].iter_mut().for_each(|c| *c = 0);
+        let congestion: usize = routing.congestion(&choice);
+        if congestion < best.0 {
+            best = (congestion, choice.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounded_covers_are_within_the_log_bound() {
+        // Three elements in pairs: the LP takes half of every pair for 1.5 and a cover needs two
+        let triangle: SetCover = SetCover::new(3, vec![vec![0, 1], vec![1, 2], vec![0, 2]], vec![1.0; 3]);
+        let fractional: Vec<f64> = fractional_set_cover(&triangle);
+        assert!(fractional.iter().all(|x| (x - 0.5).abs() < 1e-9), "{:?}", fractional);
+        assert_eq!(triangle.cost(&exact_set_cover(&triangle)), 2.0);
+
+        let mut rng = thread_rng();
+        for _ in 0..20 {
+            let cover: SetCover = SetCover::random(40, 16, 0.15, &mut rng);
+            let fractional: Vec<f64> = fractional_set_cover(&cover);
+            let lp: f64 = cover.fractional_cost(&fractional);
+            let exact: f64 = cover.cost(&exact_set_cover(&cover));
+            assert!(lp <= exact + 1e-9, "{} {}", lp, exact);
+            // Every element has fractional coverage at least 1
+            assert!((0..40).all(|e| (0..16).filter(|s| cover.sets[*s].contains(&e)).map(|s| fractional[s]).sum::<f64>() >= 1.0 - 1e-6));
+            let rounded: Vec<usize> = round_set_cover(&cover, &fractional, &mut rng);
+            assert!(cover.is_cover(&rounded) && cover.cost(&rounded) >= exact - 1e-9);
+            assert!(cover.cost(&rounded) <= (40f64.ln().ceil() + 2.0) * lp + 2.0 * 40.0, "{} {}", cover.cost(&rounded), lp);
+        }
+    }
+
+    #[test]
+    fn rounded_routings_keep_the_expected_loads() {
+        let mut rng = thread_rng();
+        let routing: Routing = Routing::random(12, 8, 3, 4, &mut rng);
+        let fractional: Vec<Vec<f64>> = fractional_routing(&routing, 1000, 0.1);
+        assert!(fractional.iter().all(|weights| (weights.iter().sum::<f64>() - 1.0).abs() < 1e-9));
+        let exact: usize = routing.congestion(&exact_routing(&routing));
+        assert!(routing.fractional_congestion(&fractional) <= exact as f64 * 1.2, "{} {}", routing.fractional_congestion(&fractional), exact);
+
+        // The mean load of every edge over many roundings is its fractional load
+        let mut load: Vec<f64> = vec![0.0; 12];
+        for _ in 0..20_000 {
+            let choice: Vec<usize> = round_routing(&routing, &fractional, &mut rng);
+            assert!(routing.congestion(&choice) >= exact);
+            choice.iter().enumerate().flat_map(|(i, p)| &routing.paths[i][*p]).for_each(|e| load[*e] += 1.0 / 20_000.0);
+        }
+        let mut expected: Vec<f64> = vec![0.0; 12];
+        for (candidates, weights) in routing.paths.iter().zip(&fractional) {
+            candidates.iter().zip(weights).for_each(|(path, w)| path.iter().for_each(|e| expected[*e] += w));
+        }
+        assert!(load.iter().zip(&expected).all(|(l, e)| (l - e).abs() < 0.05), "{:?} {:?}", load, expected);
+    }
+}
@@ -0,0 +1,237 @@
+use std::fmt;
+use rand::prelude::*;
+
+// CNF formula in the DIMACS convention: variables are 1 to n, literal v is variable v and
+// -v its negation. Assignments are indexed by variable - 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Formula {
+    variables: usize,
+    clauses: Vec<Vec<i32>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    // A clause comes before the "p cnf <variables> <clauses>" line
+    MissingHeader,
+    // A line that is neither a comment, a header nor literals, given by its number
+    InvalidLine(usize),
+    // A literal whose variable is above the header's count, given by its line number
+    VariableOutOfRange(usize),
+    // The last clause is not closed by a 0
+    UnterminatedClause,
+    ClauseCount { expected: usize, found: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::MissingHeader => write!(f, "clause before the problem line"),
+            ParseError::InvalidLine(line) => write!(f, "invalid line {}", line),
+            ParseError::VariableOutOfRange(line) => write!(f, "variable out of range on line {}", line),
+            ParseError::UnterminatedClause => write!(f, "last clause is not terminated by 0"),
+            ParseError::ClauseCount { expected, found } => write!(f, "expected {} clauses, found {}", expected, found),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn is_true(literal: i32, assignment: &[bool]) -> bool {
+    return assignment[literal.unsigned_abs() as usize - 1] == (literal > 0);
+}
+
+impl Formula {
+    pub fn new(variables: usize, clauses: Vec<Vec<i32>>) -> Formula {
+        assert!(clauses.iter().flatten().all(|l| *l != 0 && l.unsigned_abs() as usize <= variables), "Literals must name variables 1 to n");
+        return Formula { variables, clauses }
+    }
+    // Random k-SAT: every clause has k distinct variables with random signs. For k = 3 such
+    // formulas are almost always satisfiable below about 4.26 clauses per variable and almost
+    // never above, and the instances near that threshold are the hardest.
+    pub fn random_k_sat(variables: usize, clauses: usize, k: usize, rng: &mut impl Rng) -> Formula {
+        assert!(k <= variables, "Needs k <= variables");
+        let clauses: Vec<Vec<i32>> = (0..clauses).map(|_| {
+            rand::seq::index::sample(rng, variables, k).iter()
+                .map(|v| if rng.gen::<bool>() { v as i32 + 1 } else { -(v as i32 + 1) })
+                .collect()
+        }).collect();
+        return Formula { variables, clauses }
+    }
+    // Reads the DIMACS CNF format: "c" comment lines, a "p cnf <variables> <clauses>" line,
+    // then the clauses as literals ending in 0, free to span lines. A "%" line ends the
+    // formula, as in the SATLIB benchmark files.
+    pub fn parse_dimacs(text: &str) -> Result<Formula, ParseError> {
+        let mut header: Option<(usize, usize)> = None;
+        let mut clauses: Vec<Vec<i32>> = Vec::new();
+        let mut clause: Vec<i32> = Vec::new();
+        for (number, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line.trim())) {
+            if line.is_empty() || line.starts_with('c') {
+                continue;
+            }
+            if line.starts_with('%') {
+                break;
+            }
+            if line.starts_with('p') {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                match fields[..] {
+                    ["p", "cnf", variables, count] if header.is_none() => {
+                        let variables: usize = variables.parse().map_err(|_| ParseError::InvalidLine(number))?;
+                        let count: usize = count.parse().map_err(|_| ParseError::InvalidLine(number))?;
+                        header = Some((variables, count));
+                    }
+                    _ => return Err(ParseError::InvalidLine(number)),
+                }
+                continue;
+            }
+            let (variables, _) = header.ok_or(ParseError::MissingHeader)?;
+            for token in line.split_whitespace() {
+                let literal: i32 = token.parse().map_err(|_| ParseError::InvalidLine(number))?;
+                if literal == 0 {
+                    clauses.push(std::mem::take(&mut clause));
+                } else if literal.unsigned_abs() as usize > variables {
+                    return Err(ParseError::VariableOutOfRange(number));
+                } else {
+                    clause.push(literal);
+                }
+            }
+        }
+        if !clause.is_empty() {
+            return Err(ParseError::UnterminatedClause);
+        }
+        let (variables, expected) = header.unwrap_or((0, 0));
+        if clauses.len() != expected {
+            return Err(ParseError::ClauseCount { expected, found: clauses.len() });
+        }
+        return Ok(Formula { variables, clauses });
+    }
+    pub fn to_dimacs(&self) -> String {
+        let mut text: String = format!("p cnf {} {}\n", self.variables, self.clauses.len());
+        for clause in &self.clauses {
+            clause.iter().for_each(|l| text.push_str(&format!("{} ", l)));
+            text.push_str("0\n");
+        }
+        return text;
+    }
+    pub fn variables(&self) -> usize {
+        return self.variables;
+    }
+    pub fn clauses(&self) -> &[Vec<i32>] {
+        return &self.clauses;
+    }
+    pub fn is_satisfied(&self, assignment: &[bool]) -> bool {
+        return self.clauses.iter().all(|clause| clause.iter().any(|l| is_true(*l, assignment)));
+    }
+}
+
+// Outcome of a Schöning search, with the restarts and flips it took
+#[derive(Clone, Debug)]
+pub struct Search {
+    pub assignment: Option<Vec<bool>>,
+    pub tries: usize,
+    pub flips: usize,
+}
+
+// Schöning's algorithm: start from a uniformly random assignment and 3n times flip a random
+// variable of a random unsatisfied clause, restarting from a fresh assignment when that
+// fails. Every flip in a k-clause fixes a variable that disagrees with a satisfying
+// assignment with probability at least 1 / k, and for 3-SAT a try succeeds with probability
+// about (3/4)^n, so (4/3)^n tries find a satisfying assignment with constant probability
+// against the 2^n of brute force. It can never show that a formula is unsatisfiable, it
+// just gives up after max_tries.
+pub fn schoening(formula: &Formula, max_tries: usize, rng: &mut impl Rng) -> Search {
+    let n: usize = formula.variables;
+    let clauses: &[Vec<i32>] = &formula.clauses;
+    if clauses.iter().any(|clause| clause.is_empty()) {
+        return Search { assignment: None, tries: 0, flips: 0 }
+    }
+    // Clauses of every literal, 2 (v - 1) for v and 2 (v - 1) + 1 for -v
+    let mut occurrences: Vec<Vec<usize>> = vec![Vec::new(); 2 * n];
+    for (c, clause) in clauses.iter().enumerate() {
+        clause.iter().for_each(|l| occurrences[2 * (l.unsigned_abs() as usize - 1) + (*l < 0) as usize].push(c));
+    }
+
+    let mut flips: usize = 0;
+    for tries in 1..=max_tries {
+        let mut assignment: Vec<bool> = (0..n).map(|_| rng.gen()).collect();
+        // True literals of every clause, and the clauses with none of them in a list that
+        // supports removal by swapping with the last entry
+        let mut true_literals: Vec<usize> = clauses.iter()
+            .map(|clause| clause.iter().filter(|l| is_true(**l, &assignment)).count())
+            .collect();
+        let mut unsatisfied: Vec<usize> = (0..clauses.len()).filter(|c| true_literals[*c] == 0).collect();
+        let mut position: Vec<usize> = vec![usize::MAX; clauses.len()];
+        unsatisfied.iter().enumerate().for_each(|(i, c)| position[*c] = i);
+
+        for _ in 0..3 * n {
+            if unsatisfied.is_empty() {
+                break;
+            }
+            let clause: &[i32] = &clauses[*unsatisfied.choose(rng).unwrap()];
+            let variable: usize = clause.choose(rng).unwrap().unsigned_abs() as usize - 1;
+            assignment[variable] = !assignment[variable];
+            flips += 1;
+            // The literal of the variable that is now true, and its negation
+            let made_true: usize = 2 * variable + !assignment[variable] as usize;
+            for c in &occurrences[made_true] {
+                true_literals[*c] += 1;
+                if true_literals[*c] == 1 {
+                    let last: usize = *unsatisfied.last().unwrap();
+                    position[last] = position[*c];
+                    unsatisfied.swap_remove(position[*c]);
+                    position[*c] = usize::MAX;
+                }
+            }
+            for c in &occurrences[made_true ^ 1] {
+                true_literals[*c] -= 1;
+                if true_literals[*c] == 0 {
+                    position[*c] = unsatisfied.len();
+                    unsatisfied.push(*c);
+                }
+            }
+        }
+        if unsatisfied.is_empty() {
+            return Search { assignment: Some(assignment), tries, flips }
+        }
+    }
+    return Search { assignment: None, tries: max_tries, flips }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_solves_dimacs() {
+        let text: &str = "c (x1 or -x2) and (x2 or x3) and (-x1 or -x3) and (-x3)\n\
+                          p cnf 3 4\n1 -2 0\n2 3\n0 -1 -3 0\n-3 0\n%\n0\n";
+        let formula: Formula = Formula::parse_dimacs(text).unwrap();
+        assert_eq!(formula.clauses(), &[vec![1, -2], vec![2, 3], vec![-1, -3], vec![-3]]);
+        assert_eq!(Formula::parse_dimacs(&formula.to_dimacs()), Ok(formula.clone()));
+        let search: Search = schoening(&formula, 100, &mut thread_rng());
+        assert_eq!(search.assignment, Some(vec![true, true, false]));
+
+        assert_eq!(Formula::parse_dimacs("1 2 0\n"), Err(ParseError::MissingHeader));
+        assert_eq!(Formula::parse_dimacs("p cnf 2 1\n1 3 0\n"), Err(ParseError::VariableOutOfRange(2)));
+        assert_eq!(Formula::parse_dimacs("p cnf 2 1\n1 x 0\n"), Err(ParseError::InvalidLine(2)));
+        assert_eq!(Formula::parse_dimacs("p cnf 2 1\n1 2\n"), Err(ParseError::UnterminatedClause));
+        assert_eq!(Formula::parse_dimacs("p cnf 2 2\n1 2 0\n"), Err(ParseError::ClauseCount { expected: 2, found: 1 }));
+    }
+
+    #[test]
+    fn agrees_with_brute_force() {
+        // At 4.26 clauses per variable about half of the formulas are satisfiable. With 12
+        // variables a try succeeds with probability about (3/4)^12 ≈ 1/32 up to a polynomial
+        // factor, so 2000 tries missing a satisfiable formula is vanishingly unlikely.
+        let mut rng = thread_rng();
+        for _ in 0..50 {
+            let formula: Formula = Formula::random_k_sat(12, 51, 3, &mut rng);
+            let satisfiable: bool = (0..1u32 << 12)
+                .any(|bits| formula.is_satisfied(&(0..12).map(|v| bits >> v & 1 == 1).collect::<Vec<bool>>()));
+            let search: Search = schoening(&formula, 2000, &mut rng);
+            assert_eq!(search.assignment.is_some(), satisfiable);
+            if let Some(assignment) = search.assignment {
+                assert!(formula.is_satisfied(&assignment));
+            }
+        }
+    }
+}
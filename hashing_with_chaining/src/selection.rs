@@ -0,0 +1,126 @@
+use rand::prelude::*;
+
+// Three-way partition of v around the element at pivot: afterwards v is the elements below
+// the pivot, then the ones equal to it, then the ones above it. Returns the range of the
+// equal ones, so runs of duplicates never need another pass. Larger elements only move when
+// a smaller or equal one comes after them, so an already sorted range stays sorted.
+pub(crate) fn partition<T: Ord>(v: &mut [T], pivot: usize) -> (usize, usize) {
+    v.swap(0, pivot);
+    let (pivot, rest) = v.split_first_mut().unwrap();
+    // rest is [less | equal | greater | unseen]
+    let (mut less, mut equal): (usize, usize) = (0, 0);
+    for i in 0..rest.len() {
+        match rest[i].cmp(pivot) {
+            std::cmp::Ordering::Less => {
+                rest.swap(equal, i);
+                rest.swap(less, equal);
+                less += 1;
+                equal += 1;
+            }
+            std::cmp::Ordering::Equal => {
+                rest.swap(equal, i);
+                equal += 1;
+            }
+            std::cmp::Ordering::Greater => {}
+        }
+    }
+    // The pivot goes in front of its equals, taking the place of the last smaller element
+    v.swap(0, less);
+    return (less, equal + 1);
+}
+
+// Index of a pivot that has at least about 3/10 of v on either side: the median of the
+// medians of groups of five, found by a recursive deterministic selection
+fn median_of_medians<T: Ord>(v: &mut [T]) -> usize {
+    if v.len() <= 5 {
+        v.sort();
+        return v.len() / 2;
+    }
+    let groups: usize = v.len().div_ceil(5);
+    for group in 0..groups {
+        let start: usize = 5 * group;
+        let end: usize = (start + 5).min(v.len());
+        v[start..end].sort();
+        // Position group lies in an earlier group, whose median is already collected
+        v.swap(group, start + (end - start) / 2);
+    }
+    select(&mut v[..groups], groups / 2, 0);
+    return groups / 2;
+}
+
+// Moves the k-th smallest element to index k, everything smaller in front of it and
+// everything larger behind it. The first random_partitions partitions use random pivots
+// and later ones the median of medians.
+fn select<T: Ord>(v: &mut [T], k: usize, mut random_partitions: usize) {
+    assert!(k < v.len(), "Needs k below the length");
+    let mut rng = thread_rng();
+    let (mut low, mut high): (usize, usize) = (0, v.len());
+    while high - low > 1 {
+        let range: &mut [T] = &mut v[low..high];
+        let pivot: usize = if random_partitions > 0 {
+            random_partitions -= 1;
+            rng.gen_range(0..range.len())
+        } else {
+            median_of_medians(range)
+        };
+        let (equal_start, equal_end) = partition(range, pivot);
+        if k < low + equal_start {
+            high = low + equal_start;
+        } else if k >= low + equal_end {
+            low += equal_end;
+        } else {
+            return;
+        }
+    }
+}
+
+// Quickselect (Hoare): the k-th smallest element, counting from 0, by partitioning around
+// random pivots and keeping the side that holds k. The expected number of comparisons is at
+// most 4n, whatever the input order, though a run of unlucky pivots can take up to n^2 / 2.
+// Leaves v partitioned around index k like select_nth_unstable.
+pub fn randomized_select<T: Ord>(v: &mut [T], k: usize) -> &T {
+    select(v, k, usize::MAX);
+    return &v[k];
+}
+
+// Quickselect that switches to median of medians pivots after 2 log2 n random partitions,
+// which random pivots only exceed with tiny probability. Every partition after that keeps at
+// most about 7/10 of the range, bounding the worst case by O(n).
+pub fn introselect<T: Ord>(v: &mut [T], k: usize) -> &T {
+    let budget: usize = 2 * (v.len().max(1).ilog2() as usize + 1);
+    select(v, k, budget);
+    return &v[k];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::random_generator;
+
+    #[test]
+    fn selects_the_same_element_as_sorting() {
+        let inputs: Vec<Vec<u32>> = vec![
+            (0..5000).map(|_| random_generator(0, u32::MAX)).collect(),
+            (0..5000).collect(),
+            (0..5000).rev().collect(),
+            (0..5000).map(|_| random_generator(0, 10)).collect(),
+            vec![7; 100],
+            vec![1],
+        ];
+        for input in inputs {
+            let mut sorted: Vec<u32> = input.clone();
+            sorted.sort();
+            for k in [0, input.len() / 3, input.len() / 2, input.len() - 1] {
+                let mut v: Vec<u32> = input.clone();
+                assert_eq!(*randomized_select(&mut v, k), sorted[k]);
+                assert!(v[..k].iter().all(|x| *x <= sorted[k]) && v[k..].iter().all(|x| *x >= sorted[k]));
+                let mut v: Vec<u32> = input.clone();
+                assert_eq!(*introselect(&mut v, k), sorted[k]);
+                // Median of medians pivots only
+                let mut v: Vec<u32> = input.clone();
+                select(&mut v, k, 0);
+                assert_eq!(v[k], sorted[k]);
+            }
+        }
+    }
+}
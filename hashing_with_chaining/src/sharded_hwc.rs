@@ -0,0 +1,73 @@
+use std::sync::Mutex;
+use crate::hash::{log2u, SeededHash};
+use crate::hwc::HwC;
+use crate::table_stats::TableStats;
+
+// HwC split into independently locked shards, so threads updating keys in
+// different shards never wait for each other. The top bits of a separate
+// hash function pick the shard of a key.
+pub struct ShardedHwC {
+    shards: Vec<Mutex<HwC>>,
+    hash_function: SeededHash,
+}
+
+impl ShardedHwC {
+    // The number of shards is rounded up to a power of two
+    pub fn new(size: usize, shards: usize) -> ShardedHwC {
+        let shards: usize = shards.max(1).next_power_of_two();
+        let shard_size: usize = (size / shards).max(1);
+        return ShardedHwC {
+            shards: (0..shards).map(|_| Mutex::new(HwC::new(shard_size))).collect(),
+            hash_function: SeededHash::new(log2u(shards)),
+        }
+    }
+    fn shard(&self, key: u64) -> &Mutex<HwC> {
+        return &self.shards[self.hash_function.hash(key)];
+    }
+    pub fn insert(&self, key: u64, delta: i64) {
+        self.shard(key).lock().unwrap().insert(key, delta);
+    }
+    pub fn get(&self, key: u64) -> i64 {
+        return self.shard(key).lock().unwrap().get(key);
+    }
+    pub fn get_norm(&self) -> u64 {
+        return self.shards.iter().map(|shard| shard.lock().unwrap().norm()).sum();
+    }
+    pub fn stats(&self) -> TableStats {
+        let mut stats: TableStats = TableStats::new();
+        for shard in &self.shards {
+            stats.merge(&shard.lock().unwrap().stats());
+        }
+        return stats;
+    }
+    pub fn shard_count(&self) -> usize {
+        return self.shards.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn concurrent_inserts_match_sequential_counts() {
+        let sharded: ShardedHwC = ShardedHwC::new(1 << 12, 8);
+        let mut sequential: HwC = HwC::new(1 << 12);
+        thread::scope(|s| {
+            for t in 0..4i64 {
+                let sharded: &ShardedHwC = &sharded;
+                s.spawn(move || {
+                    for x in 0..10_000u64 {
+                        sharded.insert(x % 1000, t + 1);
+                    }
+                });
+            }
+        });
+        for x in 0..10_000u64 {
+            sequential.insert(x % 1000, 10);
+        }
+        assert!((0..1000).all(|x| sharded.get(x) == sequential.get(x)));
+        assert_eq!(sharded.get_norm(), sequential.get_norm());
+    }
+}
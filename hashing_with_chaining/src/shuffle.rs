@@ -0,0 +1,135 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use rand::prelude::*;
+
+// Fisher–Yates: position i from the back takes a uniformly random element of the ones not
+// placed yet. That is n (n - 1) ... 1 equally likely choices, one per permutation, so every
+// permutation comes out with probability exactly 1 / n!.
+pub fn fisher_yates<T>(v: &mut [T], rng: &mut impl Rng) {
+    for i in (1..v.len()).rev() {
+        v.swap(i, rng.gen_range(0..=i));
+    }
+}
+
+// The first k steps of Fisher–Yates run from the front, so only k random numbers: the
+// first k elements are then a uniformly random ordered sample of v, which is returned
+pub fn partial_shuffle<'a, T>(v: &'a mut [T], k: usize, rng: &mut impl Rng) -> &'a mut [T] {
+    assert!(k <= v.len(), "Needs k <= n");
+    for i in 0..k.min(v.len().saturating_sub(1)) {
+        v.swap(i, rng.gen_range(i..v.len()));
+    }
+    return &mut v[..k];
+}
+
+// Tells the bucket files of shufflers in the same process apart
+static SHUFFLERS: AtomicUsize = AtomicUsize::new(0);
+
+// Shuffle of more items than fit in memory: every pushed item goes to a uniformly random
+// one of the bucket files, and finish shuffles the buckets one at a time in memory and
+// emits them in order. Given the bucket sizes, every assignment of the items to buckets is
+// equally likely and so is every order within the buckets, which makes every permutation
+// equally likely. Memory holds one bucket, about n / buckets items.
+pub struct ExternalShuffle {
+    paths: Vec<PathBuf>,
+    writers: Vec<BufWriter<File>>,
+    rng: ThreadRng,
+}
+
+impl ExternalShuffle {
+    pub fn new(directory: &Path, buckets: usize) -> io::Result<ExternalShuffle> {
+        assert!(buckets > 0, "Needs a bucket");
+        let id: usize = SHUFFLERS.fetch_add(1, Ordering::Relaxed);
+        let paths: Vec<PathBuf> = (0..buckets)
+            .map(|b| directory.join(format!("shuffle_{}_{}_{}", std::process::id(), id, b)))
+            .collect();
+        let writers: Vec<BufWriter<File>> = paths.iter().map(|path| File::create(path).map(BufWriter::new)).collect::<io::Result<_>>()?;
+        return Ok(ExternalShuffle { paths, writers, rng: thread_rng() })
+    }
+    pub fn push(&mut self, item: u64) -> io::Result<()> {
+        let bucket: usize = self.rng.gen_range(0..self.writers.len());
+        return self.writers[bucket].write_all(&item.to_le_bytes());
+    }
+    // Emits the items in shuffled order and deletes the bucket files
+    pub fn finish(mut self, mut emit: impl FnMut(u64)) -> io::Result<()> {
+        for writer in std::mem::take(&mut self.writers) {
+            writer.into_inner().map_err(|e| e.into_error())?;
+        }
+        for path in &self.paths {
+            let mut bytes: Vec<u8> = Vec::new();
+            BufReader::new(File::open(path)?).read_to_end(&mut bytes)?;
+            let mut items: Vec<u64> = bytes.chunks_exact(8).map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap())).collect();
+            drop(bytes);
+            fisher_yates(&mut items, &mut self.rng);
+            items.into_iter().for_each(&mut emit);
+        }
+        return Ok(());
+    }
+}
+
+impl Drop for ExternalShuffle {
+    fn drop(&mut self) {
+        // Also clears the files of a shuffle that is dropped without finishing
+        self.paths.iter().for_each(|path| { let _ = fs::remove_file(path); });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    // Chi-square statistic of how often every permutation came out against the uniform
+    // distribution over outcomes of them
+    fn chi_square(counts: &HashMap<Vec<u64>, usize>, outcomes: usize, runs: usize) -> f64 {
+        assert_eq!(counts.len(), outcomes);
+        let expected: f64 = runs as f64 / outcomes as f64;
+        return counts.values().map(|count| (*count as f64 - expected).powi(2) / expected).sum();
+    }
+
+    #[test]
+    fn shuffles_are_uniform_over_permutations() {
+        // With 23 degrees of freedom the statistic exceeds 60 with probability about 4e-5,
+        // and with 11 and 5 it exceeds 40 and 30 with about 4e-5 and 1.5e-5
+        let mut rng = thread_rng();
+        let mut counts: HashMap<Vec<u64>, usize> = HashMap::new();
+        for _ in 0..120_000 {
+            let mut v: Vec<u64> = vec![0, 1, 2, 3];
+            fisher_yates(&mut v, &mut rng);
+            *counts.entry(v).or_insert(0) += 1;
+        }
+        assert!(chi_square(&counts, 24, 120_000) < 60.0);
+
+        let mut counts: HashMap<Vec<u64>, usize> = HashMap::new();
+        for _ in 0..60_000 {
+            let mut v: Vec<u64> = vec![0, 1, 2, 3];
+            *counts.entry(partial_shuffle(&mut v, 2, &mut rng).to_vec()).or_insert(0) += 1;
+        }
+        assert!(chi_square(&counts, 12, 60_000) < 40.0);
+
+        let mut counts: HashMap<Vec<u64>, usize> = HashMap::new();
+        for _ in 0..3000 {
+            let mut shuffle: ExternalShuffle = ExternalShuffle::new(&std::env::temp_dir(), 2).unwrap();
+            (0..3).for_each(|item| shuffle.push(item).unwrap());
+            let mut v: Vec<u64> = Vec::new();
+            shuffle.finish(|item| v.push(item)).unwrap();
+            *counts.entry(v).or_insert(0) += 1;
+        }
+        assert!(chi_square(&counts, 6, 3000) < 30.0);
+    }
+
+    #[test]
+    fn external_shuffle_is_a_permutation() {
+        let mut shuffle: ExternalShuffle = ExternalShuffle::new(&std::env::temp_dir(), 8).unwrap();
+        let paths: Vec<PathBuf> = shuffle.paths.clone();
+        (0..100_000).for_each(|item| shuffle.push(item).unwrap());
+        let mut v: Vec<u64> = Vec::new();
+        shuffle.finish(|item| v.push(item)).unwrap();
+        assert!(paths.iter().all(|path| !path.exists()));
+        // Few items stay in place and the order is not the input order
+        assert!(v.iter().enumerate().filter(|(i, item)| *i as u64 == **item).count() < 20);
+        v.sort();
+        assert_eq!(v, (0..100_000).collect::<Vec<u64>>());
+    }
+}
@@ -0,0 +1,88 @@
+use std::f64::consts::PI;
+use serde::{Deserialize, Serialize};
+use crate::hash::IndependentHash;
+
+// SimHash (Charikar): every fingerprint bit is the side of a random hyperplane a
+// weighted feature vector lies on. The hyperplane of bit j has a random +-1
+// coordinate for every feature, given by the sign hash of that bit. Two vectors at
+// angle theta fall on different sides of a hyperplane with probability theta / pi, so
+// the Hamming distance of their fingerprints estimates the angle between them.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SimHash {
+    hyperplanes: Vec<IndependentHash>,
+}
+
+// A fingerprint of f bits, packed into words
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl SimHash {
+    pub fn new(f: usize) -> SimHash {
+        assert!(f > 0, "Fingerprints need at least one bit");
+        return SimHash {
+            hyperplanes: (0..f).map(|_| IndependentHash::new(4)).collect(),
+        }
+    }
+    // Fingerprint of the vector with the given weight for every feature. Features that
+    // appear more than once add up.
+    pub fn fingerprint(&self, features: &[(u64, f64)]) -> Fingerprint {
+        let mut words: Vec<u64> = vec![0; self.hyperplanes.len().div_ceil(64)];
+        for (j, hyperplane) in self.hyperplanes.iter().enumerate() {
+            let projection: f64 = features.iter().map(|(feature, weight)| hyperplane.sign(*feature) as f64 * weight).sum();
+            if projection > 0.0 {
+                words[j / 64] |= 1 << (j % 64);
+            }
+        }
+        return Fingerprint { words, len: self.hyperplanes.len() };
+    }
+    pub fn bits(&self) -> usize {
+        return self.hyperplanes.len();
+    }
+}
+
+impl Fingerprint {
+    pub fn hamming(&self, other: &Fingerprint) -> u32 {
+        assert_eq!(self.len, other.len, "Fingerprints have different lengths");
+        return self.words.iter().zip(&other.words).map(|(a, b)| (a ^ b).count_ones()).sum();
+    }
+    // cos(pi * hamming / f), the cosine similarity of the estimated angle
+    pub fn cosine(&self, other: &Fingerprint) -> f64 {
+        return (PI * self.hamming(other) as f64 / self.len as f64).cos();
+    }
+    pub fn len(&self) -> usize {
+        return self.len;
+    }
+    pub fn is_empty(&self) -> bool {
+        return self.len == 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::random_generator;
+
+    fn random_weight() -> f64 {
+        return random_generator(0, 2001) as f64 / 1000.0 - 1.0;
+    }
+
+    #[test]
+    fn hamming_distance_tracks_cosine_similarity() {
+        let simhash: SimHash = SimHash::new(2048);
+        let a: Vec<(u64, f64)> = (0..200).map(|feature| (feature, random_weight())).collect();
+        for noise in [0.1, 0.5, 1.0, 3.0] {
+            let b: Vec<(u64, f64)> = a.iter().map(|(feature, weight)| (*feature, weight + noise * random_weight())).collect();
+            let dot: f64 = a.iter().zip(&b).map(|((_, x), (_, y))| x * y).sum();
+            let norm = |v: &Vec<(u64, f64)>| v.iter().map(|(_, x)| x * x).sum::<f64>().sqrt();
+            let exact: f64 = dot / (norm(&a) * norm(&b));
+            // The angle estimate has standard deviation up to pi sqrt(1/4 / 2048) ≈ 0.035,
+            // so a tolerance of 0.1 on the cosine failed about once in 300 runs
+            let estimate: f64 = simhash.fingerprint(&a).cosine(&simhash.fingerprint(&b));
+            assert!((estimate - exact).abs() < 0.15, "estimate {} for cosine {}", estimate, exact);
+        }
+        assert_eq!(simhash.fingerprint(&a).hamming(&simhash.fingerprint(&a)), 0);
+    }
+}
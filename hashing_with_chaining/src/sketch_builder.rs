@@ -0,0 +1,94 @@
+use std::f64::consts::E;
+use crate::ams::AmsF2Estimator;
+use crate::count_min::{CountMin, UpdatePolicy};
+use crate::count_sketch::CountSketch;
+use crate::l1_sketch::L1Sketch;
+use crate::norm_sketch::NormSketch;
+
+// Rows and log2 of the row width of a sketch
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SketchDimensions {
+    pub depth: usize,
+    pub width_len: u32,
+}
+
+impl SketchDimensions {
+    pub fn width(&self) -> usize {
+        return 1 << self.width_len;
+    }
+}
+
+// Turns an accuracy target into sketch dimensions: every estimate should be within
+// epsilon of the truth with probability at least 1 - delta. What epsilon is relative
+// to depends on the sketch and is noted at each method. Widths are rounded up to a
+// power of two, since the rows are indexed by multiply-shift hashing.
+pub struct SketchBuilder {
+    epsilon: f64,
+    delta: f64,
+}
+
+fn width_len_for(width: f64) -> u32 {
+    return (width.ceil().max(1.0) as usize).next_power_of_two().ilog2();
+}
+
+// ln(1 / delta) rows, at least one
+fn depth_for(delta: f64) -> usize {
+    return ((1.0 / delta).ln().ceil() as usize).max(1);
+}
+
+impl SketchBuilder {
+    pub fn new(epsilon: f64, delta: f64) -> SketchBuilder {
+        assert!(epsilon > 0.0 && delta > 0.0 && delta < 1.0, "Needs epsilon > 0 and 0 < delta < 1");
+        return SketchBuilder { epsilon, delta }
+    }
+    // Count-Min overestimates by at most epsilon times the total count: e / epsilon
+    // counters per row and ln(1 / delta) rows
+    pub fn count_min_dimensions(&self) -> SketchDimensions {
+        return SketchDimensions { depth: depth_for(self.delta), width_len: width_len_for(E / self.epsilon) };
+    }
+    pub fn count_min(&self, policy: UpdatePolicy) -> CountMin {
+        let dimensions: SketchDimensions = self.count_min_dimensions();
+        return CountMin::new(dimensions.depth, dimensions.width_len, policy);
+    }
+    // Count Sketch point queries are off by at most epsilon times the square root of F2:
+    // 3 / epsilon^2 counters per row and an odd number of about ln(1 / delta) rows
+    pub fn count_sketch_dimensions(&self) -> SketchDimensions {
+        return SketchDimensions { depth: depth_for(self.delta) | 1, width_len: width_len_for(3.0 / (self.epsilon * self.epsilon)) };
+    }
+    pub fn count_sketch(&self) -> CountSketch {
+        let dimensions: SketchDimensions = self.count_sketch_dimensions();
+        return CountSketch::new(dimensions.depth, dimensions.width_len);
+    }
+    // A single row estimates F2 within a factor 1 +- epsilon: its variance is at most
+    // 2 F2^2 / width, so Chebyshev needs 2 / (epsilon^2 delta) counters
+    pub fn norm_sketch_dimensions(&self) -> SketchDimensions {
+        return SketchDimensions { depth: 1, width_len: width_len_for(2.0 / (self.epsilon * self.epsilon * self.delta)) };
+    }
+    pub fn norm_sketch(&self) -> NormSketch {
+        return NormSketch::new(self.norm_sketch_dimensions().width_len);
+    }
+    pub fn ams(&self) -> AmsF2Estimator {
+        return AmsF2Estimator::with_guarantees(self.epsilon, self.delta);
+    }
+    // The L1 norm within a factor 1 +- epsilon
+    pub fn l1_sketch(&self) -> L1Sketch {
+        return L1Sketch::with_guarantees(self.epsilon, self.delta);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dimensions_follow_the_accuracy_target() {
+        let builder: SketchBuilder = SketchBuilder::new(0.01, 0.01);
+        // e / 0.01 = 272 rounds up to 512, ln(100) = 4.6 rounds up to 5
+        assert_eq!(builder.count_min_dimensions(), SketchDimensions { depth: 5, width_len: 9 });
+        assert_eq!(builder.count_sketch_dimensions(), SketchDimensions { depth: 5, width_len: 15 });
+        assert_eq!(builder.norm_sketch_dimensions().width(), 1 << 21);
+        assert_eq!(SketchBuilder::new(0.5, 0.5).count_sketch_dimensions().depth, 1);
+        let count_min: CountMin = builder.count_min(UpdatePolicy::Standard);
+        assert_eq!((count_min.depth(), count_min.width()), (5, 512));
+    }
+}
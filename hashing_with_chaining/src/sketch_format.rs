@@ -0,0 +1,308 @@
+use alloc::vec::Vec;
+use core::fmt;
+use crate::hash::{IndependentHash, SeededHash};
+
+// Every serialized sketch starts with an 8 byte header, all little endian:
+//   magic (4 bytes) | format version (u8) | sketch kind (u8) | padding (2 bytes)
+// followed by the dimensions, the hash seeds and the counters of the sketch
+const MAGIC: &[u8; 4] = b"HWCS";
+pub const FORMAT_VERSION: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SketchKind {
+    NormSketch = 1,
+    CountSketch = 2,
+    CountMin = 3,
+    AmsF2Estimator = 4,
+    HyperLogLog = 5,
+    HwC = 6,
+    BloomFilter = 7,
+    BlockedBloomFilter = 8,
+    CountingBloomFilter = 9,
+    CuckooFilter = 10,
+    ScalableBloomFilter = 11,
+    KllSketch = 12,
+    TDigest = 13,
+    SpaceSaving = 14,
+    MisraGries = 15,
+    ThetaSketch = 16,
+    MinHash = 17,
+    AmsFkEstimator = 18,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    // The bytes do not start with a sketch header
+    BadMagic,
+    UnsupportedVersion(u8),
+    // The bytes hold another kind of sketch, given by its kind byte
+    WrongKind { expected: SketchKind, found: u8 },
+    // The bytes end before the sketch does
+    Truncated,
+    // The header is fine but the contents are inconsistent
+    Invalid(&'static str),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::BadMagic => write!(f, "not a serialized sketch"),
+            DecodeError::UnsupportedVersion(version) => write!(f, "unsupported sketch format version {}", version),
+            DecodeError::WrongKind { expected, found } => write!(f, "expected a {:?}, found sketch kind {}", expected, found),
+            DecodeError::Truncated => write!(f, "sketch bytes end early"),
+            DecodeError::Invalid(msg) => write!(f, "invalid sketch: {}", msg),
+        }
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+const KINDS: [SketchKind; 18] = [
+    SketchKind::NormSketch,
+    SketchKind::CountSketch,
+    SketchKind::CountMin,
+    SketchKind::AmsF2Estimator,
+    SketchKind::HyperLogLog,
+    SketchKind::HwC,
+    SketchKind::BloomFilter,
+    SketchKind::BlockedBloomFilter,
+    SketchKind::CountingBloomFilter,
+    SketchKind::CuckooFilter,
+    SketchKind::ScalableBloomFilter,
+    SketchKind::KllSketch,
+    SketchKind::TDigest,
+    SketchKind::SpaceSaving,
+    SketchKind::MisraGries,
+    SketchKind::ThetaSketch,
+    SketchKind::MinHash,
+    SketchKind::AmsFkEstimator,
+];
+
+// Kind of the sketch serialized in bytes, read off the header, so bytes of an unknown kind
+// can go to the matching from_bytes
+pub fn kind_of(bytes: &[u8]) -> Result<SketchKind, DecodeError> {
+    for kind in KINDS {
+        match ByteReader::new(bytes, kind) {
+            Ok(_) => return Ok(kind),
+            Err(DecodeError::WrongKind { .. }) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    return Err(DecodeError::Invalid("unknown sketch kind"));
+}
+
+pub(crate) struct ByteWriter {
+    bytes: Vec<u8>,
+}
+
+impl ByteWriter {
+    pub(crate) fn new(kind: SketchKind) -> ByteWriter {
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&[FORMAT_VERSION, kind as u8, 0, 0]);
+        return ByteWriter { bytes }
+    }
+    pub(crate) fn u8(&mut self, x: u8) {
+        self.bytes.push(x);
+    }
+    #[cfg(feature = "std")]
+    pub(crate) fn u16(&mut self, x: u16) {
+        self.bytes.extend_from_slice(&x.to_le_bytes());
+    }
+    pub(crate) fn u32(&mut self, x: u32) {
+        self.bytes.extend_from_slice(&x.to_le_bytes());
+    }
+    pub(crate) fn u64(&mut self, x: u64) {
+        self.bytes.extend_from_slice(&x.to_le_bytes());
+    }
+    pub(crate) fn i64(&mut self, x: i64) {
+        self.bytes.extend_from_slice(&x.to_le_bytes());
+    }
+    #[cfg(feature = "std")]
+    pub(crate) fn f64(&mut self, x: f64) {
+        self.u64(x.to_bits());
+    }
+    pub(crate) fn u128(&mut self, x: u128) {
+        self.bytes.extend_from_slice(&x.to_le_bytes());
+    }
+    // hash_len (u32) | seed a (u128) | seed b (u128)
+    pub(crate) fn seeded_hash(&mut self, hash_function: &SeededHash) {
+        let (a, b) = hash_function.seeds();
+        self.u32(hash_function.hash_len());
+        self.u128(a);
+        self.u128(b);
+    }
+    // k (u32) | k coefficients (u64)
+    pub(crate) fn independent_hash(&mut self, hash_function: &IndependentHash) {
+        self.u32(hash_function.coefficients().len() as u32);
+        hash_function.coefficients().iter().for_each(|c| self.u64(*c));
+    }
+    pub(crate) fn finish(self) -> Vec<u8> {
+        return self.bytes;
+    }
+}
+
+pub(crate) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    // Checks the header and positions the reader right after it
+    pub(crate) fn new(bytes: &'a [u8], kind: SketchKind) -> Result<ByteReader<'a>, DecodeError> {
+        if bytes.len() < 8 {
+            return Err(if bytes.starts_with(&MAGIC[..bytes.len().min(4)]) { DecodeError::Truncated } else { DecodeError::BadMagic });
+        }
+        if &bytes[..4] != MAGIC {
+            return Err(DecodeError::BadMagic);
+        }
+        if bytes[4] != FORMAT_VERSION {
+            return Err(DecodeError::UnsupportedVersion(bytes[4]));
+        }
+        if bytes[5] != kind as u8 {
+            return Err(DecodeError::WrongKind { expected: kind, found: bytes[5] });
+        }
+        return Ok(ByteReader { bytes, pos: 8 });
+    }
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let end: usize = self.pos + N;
+        if end > self.bytes.len() {
+            return Err(DecodeError::Truncated);
+        }
+        let taken: [u8; N] = self.bytes[self.pos..end].try_into().unwrap();
+        self.pos = end;
+        return Ok(taken);
+    }
+    pub(crate) fn u8(&mut self) -> Result<u8, DecodeError> {
+        return Ok(self.take::<1>()?[0]);
+    }
+    #[cfg(feature = "std")]
+    pub(crate) fn u16(&mut self) -> Result<u16, DecodeError> {
+        return Ok(u16::from_le_bytes(self.take()?));
+    }
+    pub(crate) fn u32(&mut self) -> Result<u32, DecodeError> {
+        return Ok(u32::from_le_bytes(self.take()?));
+    }
+    pub(crate) fn u64(&mut self) -> Result<u64, DecodeError> {
+        return Ok(u64::from_le_bytes(self.take()?));
+    }
+    pub(crate) fn i64(&mut self) -> Result<i64, DecodeError> {
+        return Ok(i64::from_le_bytes(self.take()?));
+    }
+    #[cfg(feature = "std")]
+    pub(crate) fn f64(&mut self) -> Result<f64, DecodeError> {
+        return Ok(f64::from_bits(self.u64()?));
+    }
+    pub(crate) fn u128(&mut self) -> Result<u128, DecodeError> {
+        return Ok(u128::from_le_bytes(self.take()?));
+    }
+    // Number of items of item_len bytes still to come, checked against the remaining
+    // bytes before anything is allocated for them
+    pub(crate) fn len(&mut self, item_len: usize) -> Result<usize, DecodeError> {
+        let len: usize = self.u64()? as usize;
+        if len.checked_mul(item_len).is_none_or(|bytes| bytes > self.bytes.len() - self.pos) {
+            return Err(DecodeError::Truncated);
+        }
+        return Ok(len);
+    }
+    pub(crate) fn seeded_hash(&mut self) -> Result<SeededHash, DecodeError> {
+        let hash_len: u32 = self.u32()?;
+        if hash_len > 64 {
+            return Err(DecodeError::Invalid("hash length above 64 bits"));
+        }
+        return Ok(SeededHash::with_seeds(hash_len, self.u128()?, self.u128()?));
+    }
+    pub(crate) fn independent_hash(&mut self) -> Result<IndependentHash, DecodeError> {
+        let k: usize = self.u32()? as usize;
+        let coefficients: Vec<u64> = (0..k).map(|_| self.u64()).collect::<Result<_, _>>()?;
+        if coefficients.iter().any(|c| *c >= (1 << 61) - 1) {
+            return Err(DecodeError::Invalid("polynomial coefficient out of range"));
+        }
+        return Ok(IndependentHash::with_coefficients(coefficients));
+    }
+    // Fails unless every byte was read, so trailing garbage is not silently accepted
+    pub(crate) fn finish(self) -> Result<(), DecodeError> {
+        if self.pos != self.bytes.len() {
+            return Err(DecodeError::Invalid("trailing bytes after the sketch"));
+        }
+        return Ok(());
+    }
+}
+
+// Saving to and loading from files, which the harness feature brings
+#[cfg(feature = "harness")]
+mod checkpoint {
+    use std::ffi::OsString;
+    use std::fs;
+    use std::io;
+    use std::path::Path;
+    use crate::ams::{AmsF2Estimator, AmsFkEstimator};
+    use crate::bloom::{BlockedBloomFilter, BloomFilter, CountingBloomFilter, ScalableBloomFilter};
+    use crate::count_min::CountMin;
+    use crate::count_sketch::CountSketch;
+    use crate::cuckoo_filter::CuckooFilter;
+    use crate::hwc::{ChainStorage, HwC};
+    use crate::hyperloglog::HyperLogLog;
+    use crate::kll::KllSketch;
+    use crate::minhash::MinHash;
+    use crate::misra_gries::MisraGries;
+    use crate::norm_sketch::NormSketch;
+    use crate::space_saving::SpaceSaving;
+    use crate::tdigest::TDigest;
+    use crate::theta_sketch::ThetaSketch;
+    use super::DecodeError;
+
+    // A sketch with a serialized form, which long experiments can save mid-run and resume
+    // from. Loading goes through from_bytes, so a file holding another kind of sketch, another
+    // format version, or dimensions that disagree with its hash seeds is rejected with an
+    // InvalidData error instead of being misread.
+    pub trait Checkpoint: Sized {
+        fn to_bytes(&self) -> Vec<u8>;
+        fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError>;
+        // Writes to path.partial and renames it over path, so a run killed mid-save still
+        // leaves the previous checkpoint intact
+        fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+            let mut partial: OsString = path.as_ref().as_os_str().to_owned();
+            partial.push(".partial");
+            fs::write(&partial, self.to_bytes())?;
+            return fs::rename(&partial, path);
+        }
+        fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+            let bytes: Vec<u8> = fs::read(path)?;
+            return Self::from_bytes(&bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err));
+        }
+    }
+
+    // Checkpoint through the to_bytes and from_bytes a sketch already has
+    macro_rules! inherent_checkpoint {
+        ($($sketch:ty),*) => {
+            $(
+                impl Checkpoint for $sketch {
+                    fn to_bytes(&self) -> Vec<u8> {
+                        return <$sketch>::to_bytes(self);
+                    }
+                    fn from_bytes(bytes: &[u8]) -> Result<$sketch, DecodeError> {
+                        return <$sketch>::from_bytes(bytes);
+                    }
+                }
+            )*
+        };
+    }
+
+    inherent_checkpoint!(NormSketch, CountSketch, CountMin, AmsF2Estimator, HyperLogLog);
+    inherent_checkpoint!(BloomFilter, BlockedBloomFilter, CountingBloomFilter, ScalableBloomFilter, CuckooFilter);
+    inherent_checkpoint!(KllSketch, TDigest, SpaceSaving, MisraGries);
+    inherent_checkpoint!(ThetaSketch, MinHash, AmsFkEstimator);
+
+    impl<S: ChainStorage<Count = i64>> Checkpoint for HwC<S> {
+        fn to_bytes(&self) -> Vec<u8> {
+            return HwC::to_bytes(self);
+        }
+        fn from_bytes(bytes: &[u8]) -> Result<HwC<S>, DecodeError> {
+            return HwC::from_bytes(bytes);
+        }
+    }
+}
+
+#[cfg(feature = "harness")]
+pub use checkpoint::Checkpoint;
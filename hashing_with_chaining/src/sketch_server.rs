@@ -0,0 +1,206 @@
+use std::io;
+use std::path::Path;
+use serde_json::{json, Value};
+use crate::count_min::{CountMin, UpdatePolicy};
+use crate::datasets::parse_key;
+use crate::hyperloglog::HyperLogLog;
+use crate::mergeable::{MergeError, MergeableSketch};
+use crate::norm_sketch::NormSketch;
+use crate::sketch_format::{kind_of, Checkpoint, DecodeError, SketchKind};
+
+// The sketches behind the serve mode, which keeps them updated from a stream of telemetry
+// and answers queries over HTTP. The requests are handled here, apart from the server, so
+// they can be tested without sockets:
+//   GET /estimate?key=K     Count-Min estimate of the count of K, a key as the trace
+//                           loaders parse it, so a number, an IP address or a word
+//   GET /distinct           HyperLogLog estimate of the number of distinct keys
+//   GET /norm               NormSketch estimate of the F2 norm
+//   GET /sketch?kind=KIND   The serialized count-min, hyperloglog or norm-sketch
+//   POST /merge             Merges the serialized sketch in the body into the one of its
+//                           kind, which only works for sketches with the same hash functions
+// Answers are JSON, apart from the bytes of /sketch.
+
+const COUNT_MIN_DEPTH: usize = 5;
+// File names of the sketches in a checkpoint directory
+const COUNT_MIN_FILE: &str = "count_min.sketch";
+const HYPERLOGLOG_FILE: &str = "hyperloglog.sketch";
+const NORM_SKETCH_FILE: &str = "norm_sketch.sketch";
+
+pub struct SketchSet {
+    pub count_min: CountMin,
+    pub hyperloglog: HyperLogLog,
+    pub norm_sketch: NormSketch,
+    updates: u64,
+}
+
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, value: Value) -> Response {
+        return Response { status, content_type: "application/json", body: value.to_string().into_bytes() }
+    }
+    pub fn error(status: u16, message: &str) -> Response {
+        return Response::json(status, json!({ "error": message }));
+    }
+}
+
+// The value of name in the query string of a URL, with + and %XX escapes decoded
+fn query_parameter(query: &str, name: &str) -> Option<String> {
+    let value: &str = query.split('&').find_map(|pair| pair.split_once('=').filter(|(key, _)| *key == name).map(|(_, value)| value))?;
+    let bytes: &[u8] = value.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i: usize = 0;
+    while i < bytes.len() {
+        let escaped: Option<u8> = (bytes[i] == b'%').then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], escaped) {
+            (_, Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+            (b'+', None) => decoded.push(b' '),
+            (byte, None) => decoded.push(byte),
+        }
+        i += 1;
+    }
+    return String::from_utf8(decoded).ok();
+}
+
+impl SketchSet {
+    // Count-Min and NormSketch with 2^width_len counters per row, and a HyperLogLog of the
+    // given precision
+    pub fn new(width_len: u32, precision: u32) -> SketchSet {
+        return SketchSet {
+            count_min: CountMin::new(COUNT_MIN_DEPTH, width_len, UpdatePolicy::Standard),
+            hyperloglog: HyperLogLog::new(precision),
+            norm_sketch: NormSketch::new(width_len),
+            updates: 0,
+        }
+    }
+    // The sketches saved in dir, so servers started from the same files hash alike and
+    // their sketches can be merged
+    pub fn load(dir: &Path) -> io::Result<SketchSet> {
+        return Ok(SketchSet {
+            count_min: CountMin::load(dir.join(COUNT_MIN_FILE))?,
+            hyperloglog: HyperLogLog::load(dir.join(HYPERLOGLOG_FILE))?,
+            norm_sketch: NormSketch::load(dir.join(NORM_SKETCH_FILE))?,
+            updates: 0,
+        })
+    }
+    pub fn is_saved_in(dir: &Path) -> bool {
+        return [COUNT_MIN_FILE, HYPERLOGLOG_FILE, NORM_SKETCH_FILE].iter().all(|file| dir.join(file).exists());
+    }
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+        self.count_min.save(dir.join(COUNT_MIN_FILE))?;
+        self.hyperloglog.save(dir.join(HYPERLOGLOG_FILE))?;
+        return self.norm_sketch.save(dir.join(NORM_SKETCH_FILE));
+    }
+    // The stream is taken as a strict turnstile: a negative delta takes back earlier
+    // insertions of the key, which Count-Min can undo but HyperLogLog cannot, so it goes on
+    // counting a key whose count dropped to zero
+    pub fn update(&mut self, key: u64, delta: i64) {
+        if delta < 0 {
+            self.count_min.remove(key, delta.unsigned_abs());
+        } else if delta > 0 {
+            self.count_min.insert(key, delta as u64);
+            self.hyperloglog.insert(key);
+        }
+        self.norm_sketch.insert(key, delta);
+        self.updates += 1;
+    }
+    // Updates applied since the sketches were made or loaded
+    pub fn updates(&self) -> u64 {
+        return self.updates;
+    }
+    // Merges a serialized sketch into the sketch of its kind, returning that kind
+    pub fn merge_bytes(&mut self, bytes: &[u8]) -> Result<SketchKind, String> {
+        let kind: SketchKind = kind_of(bytes).map_err(|err| err.to_string())?;
+        let merged: Result<Result<(), MergeError>, DecodeError> = match kind {
+            SketchKind::CountMin => CountMin::from_bytes(bytes).map(|other| self.count_min.merge(&other)),
+            SketchKind::HyperLogLog => HyperLogLog::from_bytes(bytes).map(|other| self.hyperloglog.merge(&other)),
+            SketchKind::NormSketch => NormSketch::from_bytes(bytes).map(|other| self.norm_sketch.merge(&other)),
+            _ => return Err(format!("cannot merge a {:?}", kind)),
+        };
+        return match merged {
+            Ok(Ok(())) => Ok(kind),
+            Ok(Err(err)) => Err(err.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+    }
+    pub fn handle(&mut self, method: &str, url: &str, body: &[u8]) -> Response {
+        let (path, query): (&str, &str) = url.split_once('?').unwrap_or((url, ""));
+        return match (method, path) {
+            ("GET", "/estimate") => match query_parameter(query, "key") {
+                Some(key) if !key.trim().is_empty() => {
+                    Response::json(200, json!({ "key": key, "estimate": self.count_min.estimate(parse_key(&key)) }))
+                }
+                _ => Response::error(400, "missing key parameter"),
+            },
+            ("GET", "/distinct") => Response::json(200, json!({ "distinct": self.hyperloglog.estimate().round(), "updates": self.updates })),
+            ("GET", "/norm") => Response::json(200, json!({ "norm": self.norm_sketch.norm_f64(), "updates": self.updates })),
+            ("GET", "/sketch") => {
+                let bytes: Vec<u8> = match query_parameter(query, "kind").as_deref() {
+                    Some("count-min") => self.count_min.to_bytes(),
+                    Some("hyperloglog") => self.hyperloglog.to_bytes(),
+                    Some("norm-sketch") => self.norm_sketch.to_bytes(),
+                    _ => return Response::error(400, "kind must be count-min, hyperloglog or norm-sketch"),
+                };
+                Response { status: 200, content_type: "application/octet-stream", body: bytes }
+            }
+            ("POST", "/merge") => match self.merge_bytes(body) {
+                Ok(kind) => Response::json(200, json!({ "merged": format!("{:?}", kind) })),
+                Err(err) => Response::error(409, &err),
+            },
+            (_, "/estimate" | "/distinct" | "/norm" | "/sketch" | "/merge") => Response::error(405, "method not allowed"),
+            _ => Response::error(404, "no such endpoint"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json_of(response: &Response) -> Value {
+        return serde_json::from_slice(&response.body).unwrap();
+    }
+
+    #[test]
+    fn answers_queries_and_merges_sketches() {
+        let mut sketches: SketchSet = SketchSet::new(10, 12);
+        let mut peer: SketchSet = SketchSet { count_min: sketches.count_min.clone(), hyperloglog: sketches.hyperloglog.clone(), norm_sketch: sketches.norm_sketch.clone(), updates: 0 };
+        for key in 0..1000u64 {
+            sketches.update(key % 100, 1);
+        }
+        sketches.update(parse_key("10.0.0.1"), 7);
+        sketches.update(parse_key("10.0.0.1"), -2);
+
+        let estimate: Value = json_of(&sketches.handle("GET", "/estimate?key=10.0.0.1", b""));
+        assert_eq!(estimate["key"], "10.0.0.1");
+        assert!(estimate["estimate"].as_u64().unwrap() >= 5);
+        assert!(json_of(&sketches.handle("GET", "/estimate?key=7", b""))["estimate"].as_u64().unwrap() >= 10);
+        let distinct: f64 = json_of(&sketches.handle("GET", "/distinct", b""))["distinct"].as_f64().unwrap();
+        assert!((95.0..=106.0).contains(&distinct), "{}", distinct);
+        assert_eq!(json_of(&sketches.handle("GET", "/norm", b""))["updates"], 1002);
+
+        // The count of a key seen by both sides adds up once the peer's sketch is merged
+        peer.update(7, 90);
+        let bytes: Vec<u8> = peer.handle("GET", "/sketch?kind=count-min", b"").body;
+        assert_eq!(json_of(&sketches.handle("POST", "/merge", &bytes))["merged"], "CountMin");
+        assert!(json_of(&sketches.handle("GET", "/estimate?key=7", b""))["estimate"].as_u64().unwrap() >= 100);
+
+        assert_eq!(sketches.handle("POST", "/merge", &SketchSet::new(10, 12).count_min.to_bytes()).status, 409);
+        assert_eq!(sketches.handle("POST", "/merge", b"junk").status, 409);
+        assert_eq!(sketches.handle("GET", "/estimate", b"").status, 400);
+        assert_eq!(sketches.handle("POST", "/norm", b"").status, 405);
+        assert_eq!(sketches.handle("GET", "/nothing", b"").status, 404);
+        assert_eq!(query_parameter("a=1&key=two+words%21", "key").as_deref(), Some("two words!"));
+    }
+}
@@ -0,0 +1,195 @@
+use std::mem::size_of;
+use rand::prelude::*;
+
+// Marks the end of a level
+const NIL: usize = usize::MAX;
+// Highest level a node can reach, plenty for 2^32 keys at promotion probability 1/2
+const MAX_LEVEL: usize = 32;
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    // next[l] is the index of the following node on level l
+    next: Vec<usize>,
+}
+
+// Randomized ordered map (Pugh). Every node is on level 0 and is promoted to each further
+// level with the given probability, so level l holds about a p^l fraction of the keys and a
+// search that walks down from the top level makes O(log n / p) steps in expectation. Nodes
+// live in one arena and link to each other by index. A removed node is replaced by the last
+// node of the arena, whose predecessors are relinked, so the arena stays dense.
+pub struct SkipList<K: Ord, V> {
+    nodes: Vec<Node<K, V>>,
+    // First node of every level
+    head: [usize; MAX_LEVEL],
+    // Number of levels holding at least one node
+    levels: usize,
+    probability: f64,
+}
+
+impl<K: Ord, V> SkipList<K, V> {
+    pub fn new(probability: f64) -> SkipList<K, V> {
+        assert!(probability > 0.0 && probability < 1.0, "Needs a promotion probability in (0, 1)");
+        return SkipList {
+            nodes: Vec::new(),
+            head: [NIL; MAX_LEVEL],
+            levels: 0,
+            probability,
+        }
+    }
+    fn random_height(&self) -> usize {
+        let mut rng = thread_rng();
+        let mut height: usize = 1;
+        while height < MAX_LEVEL && rng.gen::<f64>() < self.probability {
+            height += 1;
+        }
+        return height;
+    }
+    fn next(&self, node: usize, level: usize) -> usize {
+        return if node == NIL { self.head[level] } else { self.nodes[node].next[level] };
+    }
+    fn set_next(&mut self, node: usize, level: usize, next: usize) {
+        if node == NIL {
+            self.head[level] = next;
+        } else {
+            self.nodes[node].next[level] = next;
+        }
+    }
+    // The last node before key on every level, NIL standing for the head
+    fn predecessors(&self, key: &K) -> [usize; MAX_LEVEL] {
+        let mut predecessors: [usize; MAX_LEVEL] = [NIL; MAX_LEVEL];
+        let mut node: usize = NIL;
+        for level in (0..self.levels).rev() {
+            let mut next: usize = self.next(node, level);
+            while next != NIL && self.nodes[next].key < *key {
+                node = next;
+                next = self.next(node, level);
+            }
+            predecessors[level] = node;
+        }
+        return predecessors;
+    }
+    fn find(&self, key: &K) -> Option<usize> {
+        if self.levels == 0 {
+            return None;
+        }
+        let next: usize = self.next(self.predecessors(key)[0], 0);
+        return (next != NIL && self.nodes[next].key == *key).then_some(next);
+    }
+    // Inserts the key, or replaces its value and returns the old one
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let predecessors: [usize; MAX_LEVEL] = self.predecessors(&key);
+        let next: usize = if self.levels == 0 { NIL } else { self.next(predecessors[0], 0) };
+        if next != NIL && self.nodes[next].key == key {
+            return Some(std::mem::replace(&mut self.nodes[next].value, value));
+        }
+        let height: usize = self.random_height();
+        let index: usize = self.nodes.len();
+        let next: Vec<usize> = (0..height).map(|level| self.next(predecessors[level], level)).collect();
+        self.nodes.push(Node { key, value, next });
+        for (level, predecessor) in predecessors.iter().enumerate().take(height) {
+            self.set_next(*predecessor, level, index);
+        }
+        self.levels = self.levels.max(height);
+        return None;
+    }
+    pub fn get(&self, key: &K) -> Option<&V> {
+        return self.find(key).map(|node| &self.nodes[node].value);
+    }
+    pub fn contains_key(&self, key: &K) -> bool {
+        return self.find(key).is_some();
+    }
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let node: usize = self.find(key)?;
+        let predecessors: [usize; MAX_LEVEL] = self.predecessors(key);
+        let height: usize = self.nodes[node].next.len();
+        for (level, predecessor) in predecessors.iter().enumerate().take(height) {
+            self.set_next(*predecessor, level, self.nodes[node].next[level]);
+        }
+        while self.levels > 0 && self.head[self.levels - 1] == NIL {
+            self.levels -= 1;
+        }
+        // Move the last node into the freed slot and point its predecessors at the new index
+        let last: usize = self.nodes.len() - 1;
+        if node != last {
+            let moved: [usize; MAX_LEVEL] = self.predecessors(&self.nodes[last].key);
+            let height: usize = self.nodes[last].next.len();
+            for (level, predecessor) in moved.iter().enumerate().take(height) {
+                self.set_next(*predecessor, level, node);
+            }
+        }
+        return Some(self.nodes.swap_remove(node).value);
+    }
+    // Entries in key order
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        let mut node: usize = self.head[0];
+        return std::iter::from_fn(move || {
+            if node == NIL {
+                return None;
+            }
+            let entry: &Node<K, V> = &self.nodes[node];
+            node = entry.next[0];
+            return Some((&entry.key, &entry.value));
+        });
+    }
+    pub fn len(&self) -> usize {
+        return self.nodes.len();
+    }
+    pub fn is_empty(&self) -> bool {
+        return self.nodes.is_empty();
+    }
+    pub fn levels(&self) -> usize {
+        return self.levels;
+    }
+    // height_histogram[h] is the number of nodes on exactly levels 0 to h - 1, which
+    // should fall off by the promotion probability from one height to the next
+    pub fn height_histogram(&self) -> Vec<usize> {
+        let mut histogram: Vec<usize> = vec![0; self.levels + 1];
+        self.nodes.iter().for_each(|node| histogram[node.next.len()] += 1);
+        return histogram;
+    }
+    // Nodes and their forward pointers in bytes
+    pub fn space_usage(&self) -> usize {
+        let pointers: usize = self.nodes.iter().map(|node| node.next.capacity()).sum();
+        return self.nodes.capacity() * size_of::<Node<K, V>>() + pointers * size_of::<usize>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use crate::hash::random_generator;
+
+    #[test]
+    fn random_operations_match_a_btree_map() {
+        let mut list: SkipList<u64, u64> = SkipList::new(0.5);
+        let mut exact: BTreeMap<u64, u64> = BTreeMap::new();
+        for i in 0..50_000u64 {
+            let key: u64 = random_generator(0, 5000) as u64;
+            if random_generator(0, 3) == 0 {
+                assert_eq!(list.remove(&key), exact.remove(&key));
+            } else {
+                assert_eq!(list.insert(key, i), exact.insert(key, i));
+            }
+        }
+        assert_eq!(list.len(), exact.len());
+        assert!(list.iter().eq(exact.iter()));
+        assert!((0..5000u64).all(|key| list.get(&key) == exact.get(&key)));
+        let keys: Vec<u64> = exact.keys().copied().collect();
+        keys.iter().for_each(|key| assert!(list.remove(key).is_some()));
+        assert!(list.is_empty() && list.levels() == 0);
+    }
+
+    #[test]
+    fn heights_are_geometric() {
+        let mut list: SkipList<u64, ()> = SkipList::new(0.25);
+        (0..100_000u64).for_each(|key| { list.insert(key, ()); });
+        let histogram: Vec<usize> = list.height_histogram();
+        // 3/4 of the nodes stop at height 1, 3/16 at height 2 and 3/64 at height 3
+        for (height, expected) in [(1, 75_000.0), (2, 18_750.0), (3, 4687.5)] {
+            assert!((histogram[height] as f64 - expected).abs() < 0.05 * expected, "{:?}", histogram);
+        }
+        assert!(list.levels() < 16);
+    }
+}
@@ -0,0 +1,323 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::mem::size_of;
+use crate::mergeable::{MergeError, MergeableSketch};
+use crate::sketch_format::{ByteReader, ByteWriter, DecodeError, SketchKind};
+
+// A monitored key with the weight counted for it. The key may have taken over the counter
+// of an evicted key, so up to error of count belongs to other keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Counter {
+    pub key: u64,
+    pub count: u64,
+    pub error: u64,
+}
+
+impl Counter {
+    // Weight the key is guaranteed to have had
+    pub fn lower_bound(&self) -> u64 {
+        return self.count - self.error;
+    }
+}
+
+// SpaceSaving summary monitoring at most capacity keys. A new key replaces the key with the
+// smallest count and inherits that count as its error, so counts never underestimate and
+// overestimate by at most total / capacity. The counters are kept ordered by count, which
+// makes finding the minimum logarithmic.
+#[derive(Clone)]
+pub struct SpaceSaving {
+    counters: HashMap<u64, (u64, u64)>,
+    by_count: BTreeSet<(u64, u64)>,
+    capacity: usize,
+    total: u64,
+}
+
+impl SpaceSaving {
+    pub fn new(capacity: usize) -> SpaceSaving {
+        assert!(capacity > 0, "Needs at least one counter");
+        return SpaceSaving {
+            counters: HashMap::with_capacity(capacity),
+            by_count: BTreeSet::new(),
+            capacity,
+            total: 0,
+        }
+    }
+    fn set(&mut self, key: u64, count: u64, error: u64) {
+        if let Some((old_count, _)) = self.counters.insert(key, (count, error)) {
+            self.by_count.remove(&(old_count, key));
+        }
+        self.by_count.insert((count, key));
+    }
+    pub fn update(&mut self, key: u64, weight: u64) {
+        self.total += weight;
+        if let Some((count, error)) = self.counters.get(&key).copied() {
+            self.set(key, count + weight, error);
+        } else if self.counters.len() < self.capacity {
+            self.set(key, weight, 0);
+        } else {
+            let (min_count, min_key) = self.by_count.pop_first().unwrap();
+            self.counters.remove(&min_key);
+            self.set(key, min_count + weight, min_count);
+        }
+    }
+    // Count of an unmonitored key is at most the smallest count once every counter is taken
+    fn min_count(&self) -> u64 {
+        if self.counters.len() < self.capacity {
+            return 0;
+        }
+        return self.by_count.first().map_or(0, |(count, _)| *count);
+    }
+    // Upper bound on the weight of key
+    pub fn estimate(&self, key: u64) -> u64 {
+        return self.counters.get(&key).map_or(self.min_count(), |(count, _)| *count);
+    }
+    // The k monitored keys with the largest counts, largest first
+    pub fn top_k(&self, k: usize) -> Vec<Counter> {
+        return self.by_count.iter().rev().take(k).map(|(count, key)| Counter {
+            key: *key,
+            count: *count,
+            error: self.counters[key].1,
+        }).collect();
+    }
+    // Whether the reported top k is exactly the true top k, which holds when every reported
+    // key is guaranteed more weight than the next counter can have
+    pub fn is_top_k_exact(&self, k: usize) -> bool {
+        let top: Vec<Counter> = self.top_k(k + 1);
+        let next: u64 = if top.len() > k { top[k].count } else { self.min_count() };
+        return top.iter().take(k).all(|counter| counter.lower_bound() >= next);
+    }
+    pub fn total(&self) -> u64 {
+        return self.total;
+    }
+    pub fn capacity(&self) -> usize {
+        return self.capacity;
+    }
+    // Heap footprint of the counters and their ordering in bytes
+    pub fn space_usage(&self) -> usize {
+        return self.counters.capacity() * size_of::<(u64, (u64, u64))>() + self.by_count.len() * size_of::<(u64, u64)>();
+    }
+    // capacity (u64) | total (u64) | number of counters (u64), followed by the key (u64),
+    // count (u64) and error (u64) of every counter in order of their keys
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new(SketchKind::SpaceSaving);
+        writer.u64(self.capacity as u64);
+        writer.u64(self.total);
+        let mut counters: Vec<(&u64, &(u64, u64))> = self.counters.iter().collect();
+        counters.sort_unstable();
+        writer.u64(counters.len() as u64);
+        for (key, (count, error)) in counters {
+            writer.u64(*key);
+            writer.u64(*count);
+            writer.u64(*error);
+        }
+        return writer.finish();
+    }
+    // The ordering by count is rebuilt from the counters
+    pub fn from_bytes(bytes: &[u8]) -> Result<SpaceSaving, DecodeError> {
+        let mut reader: ByteReader = ByteReader::new(bytes, SketchKind::SpaceSaving)?;
+        let capacity: usize = reader.u64()? as usize;
+        let total: u64 = reader.u64()?;
+        let len: usize = reader.len(3 * size_of::<u64>())?;
+        if capacity == 0 || len > capacity {
+            return Err(DecodeError::Invalid("more counters than the capacity"));
+        }
+        let mut summary: SpaceSaving = SpaceSaving {
+            counters: HashMap::with_capacity(len),
+            by_count: BTreeSet::new(),
+            capacity,
+            total,
+        };
+        let mut previous: Option<u64> = None;
+        for _ in 0..len {
+            let (key, count, error) = (reader.u64()?, reader.u64()?, reader.u64()?);
+            if previous.is_some_and(|previous| previous >= key) {
+                return Err(DecodeError::Invalid("counters out of order"));
+            }
+            if error > count || count > total {
+                return Err(DecodeError::Invalid("count out of range"));
+            }
+            summary.set(key, count, error);
+            previous = Some(key);
+        }
+        reader.finish()?;
+        return Ok(summary);
+    }
+}
+
+// Merging takes for every key the sum of its counts on both sides, charging a side that does
+// not monitor the key with its smallest count, and keeps the capacity largest results. The
+// merged summary overestimates by at most the total weight of both streams over capacity.
+impl MergeableSketch for SpaceSaving {
+    fn merge(&mut self, other: &SpaceSaving) -> Result<(), MergeError> {
+        if self.capacity != other.capacity {
+            return Err(MergeError::DimensionMismatch);
+        }
+        let (self_min, other_min) = (self.min_count(), other.min_count());
+        let mut merged: HashMap<u64, (u64, u64)> = HashMap::with_capacity(2 * self.capacity);
+        for (key, (count, error)) in &self.counters {
+            merged.insert(*key, (count + other_min, error + other_min));
+        }
+        for (key, (count, error)) in &other.counters {
+            let entry = merged.entry(*key).or_insert((self_min, self_min));
+            match self.counters.get(key) {
+                Some(_) => *entry = (entry.0 - other_min + count, entry.1 - other_min + error),
+                None => *entry = (entry.0 + count, entry.1 + error),
+            }
+        }
+        let mut ranked: Vec<(u64, u64, u64)> = merged.into_iter().map(|(key, (count, error))| (count, key, error)).collect();
+        ranked.sort_unstable_by(|a, b| b.cmp(a));
+        ranked.truncate(self.capacity);
+
+        self.counters.clear();
+        self.by_count.clear();
+        for (count, key, error) in ranked {
+            self.set(key, count, error);
+        }
+        self.total += other.total;
+        return Ok(());
+    }
+}
+
+// Heavy hitters of the last `window` updates. The window is cut into blocks of
+// window / blocks updates, each with its own SpaceSaving summary, and a block is dropped
+// once it has fully left the window. Queries merge the live blocks, so they cover between
+// window - window / blocks and window of the latest updates, and overestimate by at most
+// the weight of those updates over capacity.
+pub struct WindowedSpaceSaving {
+    // Oldest block at the front, the one being filled at the back
+    blocks: VecDeque<SpaceSaving>,
+    capacity: usize,
+    block_len: u64,
+    block_count: usize,
+    // Updates in the block at the back
+    filled: u64,
+}
+
+impl WindowedSpaceSaving {
+    pub fn new(capacity: usize, window: u64, blocks: usize) -> WindowedSpaceSaving {
+        assert!(blocks > 0 && window >= blocks as u64, "Needs at least one update per block");
+        return WindowedSpaceSaving {
+            blocks: VecDeque::from([SpaceSaving::new(capacity)]),
+            capacity,
+            block_len: window.div_ceil(blocks as u64),
+            block_count: blocks,
+            filled: 0,
+        }
+    }
+    pub fn update(&mut self, key: u64, weight: u64) {
+        if self.filled == self.block_len {
+            self.blocks.push_back(SpaceSaving::new(self.capacity));
+            if self.blocks.len() > self.block_count {
+                self.blocks.pop_front();
+            }
+            self.filled = 0;
+        }
+        self.blocks.back_mut().unwrap().update(key, weight);
+        self.filled += 1;
+    }
+    // Summary of the updates in the window
+    pub fn summary(&self) -> SpaceSaving {
+        let mut merged: SpaceSaving = SpaceSaving::new(self.capacity);
+        for block in &self.blocks {
+            merged.merge(block).expect("Blocks have the same capacity");
+        }
+        return merged;
+    }
+    // Upper bound on the weight of key within the window
+    pub fn estimate(&self, key: u64) -> u64 {
+        return self.summary().estimate(key);
+    }
+    // The k keys with the largest counts in the window, largest first
+    pub fn top_k(&self, k: usize) -> Vec<Counter> {
+        return self.summary().top_k(k);
+    }
+    // Number of the latest updates the queries cover
+    pub fn covered(&self) -> u64 {
+        return (self.blocks.len() as u64 - 1) * self.block_len + self.filled;
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.blocks.iter().map(|block| block.space_usage()).sum();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::random_generator;
+
+    fn check_bounds(summary: &SpaceSaving, exact: &HashMap<u64, u64>) {
+        let slack: u64 = summary.total() / summary.capacity() as u64;
+        for (key, count) in exact {
+            assert!(summary.estimate(*key) >= *count);
+            assert!(summary.estimate(*key) <= count + slack);
+        }
+        for counter in summary.top_k(summary.capacity()) {
+            assert!(counter.lower_bound() <= exact[&counter.key]);
+        }
+    }
+
+    #[test]
+    fn counts_stay_within_bounds_before_and_after_merging() {
+        let mut left: SpaceSaving = SpaceSaving::new(64);
+        let mut right: SpaceSaving = left.clone();
+        let (mut left_exact, mut right_exact) = (HashMap::new(), HashMap::new());
+        for i in 0..200_000 {
+            // Key k has probability about 1 / (k (k + 1))
+            let key: u64 = 10_000 / random_generator(1, 10_001) as u64;
+            if i % 3 == 0 {
+                left.update(key, 1);
+                *left_exact.entry(key).or_default() += 1;
+            } else {
+                right.update(key, 1);
+                *right_exact.entry(key).or_default() += 1;
+            }
+        }
+        check_bounds(&left, &left_exact);
+        check_bounds(&right, &right_exact);
+
+        left.merge(&right).unwrap();
+        for (key, count) in right_exact {
+            *left_exact.entry(key).or_default() += count;
+        }
+        assert_eq!(left.total(), 200_000);
+        check_bounds(&left, &left_exact);
+        assert!(left.is_top_k_exact(1));
+        assert_eq!(left.top_k(1)[0].key, 1);
+        assert_eq!(left.merge(&SpaceSaving::new(32)), Err(MergeError::DimensionMismatch));
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut summary: SpaceSaving = SpaceSaving::new(16);
+        (0..10_000u64).for_each(|i| summary.update(i * i % 101, i % 3 + 1));
+        let bytes: Vec<u8> = summary.to_bytes();
+        let mut restored: SpaceSaving = SpaceSaving::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.top_k(16), summary.top_k(16));
+        assert_eq!(restored.total(), summary.total());
+        restored.update(1000, 1);
+        summary.update(1000, 1);
+        assert_eq!(restored.top_k(16), summary.top_k(16));
+
+        // The first counter with more weight than the whole stream
+        let mut bytes: Vec<u8> = bytes;
+        bytes[40..48].copy_from_slice(&(summary.total() + 1).to_le_bytes());
+        assert_eq!(SpaceSaving::from_bytes(&bytes).err(), Some(DecodeError::Invalid("count out of range")));
+    }
+
+    #[test]
+    fn windowed_heavy_hitters_age_out() {
+        let mut windowed: WindowedSpaceSaving = WindowedSpaceSaving::new(32, 10_000, 10);
+        // Key 1 is heavy first, then key 2 takes over
+        for i in 0..50_000u64 {
+            let heavy: u64 = if i < 25_000 { 1 } else { 2 };
+            windowed.update(if i % 4 == 0 { heavy } else { 100 + i % 1000 }, 1);
+            if i == 24_999 {
+                assert_eq!(windowed.top_k(1)[0].key, 1);
+            }
+        }
+        assert_eq!(windowed.covered(), 10_000);
+        assert_eq!(windowed.top_k(1)[0].key, 2);
+        // Key 1 left the window 25000 updates ago, so only the error of the merged blocks remains
+        assert!(windowed.estimate(1) <= 10_000 / 32);
+        assert!(windowed.estimate(2) >= 2500);
+    }
+}
@@ -0,0 +1,182 @@
+use std::mem::size_of;
+use crate::hash::IndependentHash;
+use crate::mergeable::{MergeError, MergeableSketch};
+
+// Field of the fingerprints, the Mersenne prime 2^61 - 1 that IndependentHash works in
+const PRIME: u128 = (1 << 61) - 1;
+// Rows of buckets, each with its own hash function
+const ROWS: usize = 3;
+
+// Sums over the keys of one bucket, which reveal the key when only one key is left in it
+#[derive(Clone, Copy, Default)]
+struct Cell {
+    count: i64,
+    key_sum: i128,
+    fingerprint: u64,
+}
+
+impl Cell {
+    fn add(&mut self, key: u64, delta: i64, hashed: u64) {
+        self.count += delta;
+        self.key_sum += delta as i128 * key as i128;
+        let delta_mod: u128 = (delta as i128).rem_euclid(PRIME as i128) as u128;
+        self.fingerprint = ((self.fingerprint as u128 + delta_mod * hashed as u128) % PRIME) as u64;
+    }
+    fn is_empty(&self) -> bool {
+        return self.count == 0 && self.key_sum == 0 && self.fingerprint == 0;
+    }
+}
+
+// Exact recovery of vectors with at most s nonzero coordinates under turnstile updates, an
+// Iblt whose cells hold arbitrary counts. Every row hashes keys into 2s buckets of counts,
+// key sums and fingerprints. A bucket with a single nonzero key is pure: the key is
+// key_sum / count, confirmed by the fingerprint. The fingerprint hash must not be linear,
+// or any bucket whose key sum is a multiple of its count would pass for pure. Peeling pure
+// buckets recovers the whole vector with high probability when it is s-sparse. A denser
+// vector almost always gets stuck with cells left over, which is reported as a failure
+// rather than a wrong vector.
+#[derive(Clone)]
+pub struct SparseRecovery {
+    cells: Vec<Cell>,
+    bucket_hashes: Vec<IndependentHash>,
+    fingerprint_hash: IndependentHash,
+    buckets: usize,
+}
+
+impl SparseRecovery {
+    pub fn new(sparsity: usize) -> SparseRecovery {
+        let buckets: usize = 2 * sparsity.max(1);
+        return SparseRecovery {
+            cells: vec![Cell::default(); ROWS * buckets],
+            bucket_hashes: (0..ROWS).map(|_| IndependentHash::new(2)).collect(),
+            fingerprint_hash: IndependentHash::new(4),
+            buckets,
+        }
+    }
+    fn cells_of(&self, key: u64) -> [usize; ROWS] {
+        let mut cells: [usize; ROWS] = [0; ROWS];
+        for (row, hash_function) in self.bucket_hashes.iter().enumerate() {
+            let bucket: usize = ((hash_function.hash(key) as u128 * self.buckets as u128) >> 61) as usize;
+            cells[row] = row * self.buckets + bucket;
+        }
+        return cells;
+    }
+    pub fn update(&mut self, key: u64, delta: i64) {
+        let hashed: u64 = self.fingerprint_hash.hash(key);
+        for cell in self.cells_of(key) {
+            self.cells[cell].add(key, delta, hashed);
+        }
+    }
+    // The key of the cell at index if it is pure
+    fn pure_key(&self, index: usize) -> Option<u64> {
+        let cell: &Cell = &self.cells[index];
+        if cell.count == 0 || cell.key_sum % cell.count as i128 != 0 {
+            return None;
+        }
+        let key: i128 = cell.key_sum / cell.count as i128;
+        if key < 0 || key > u64::MAX as i128 {
+            return None;
+        }
+        let mut expected: Cell = Cell::default();
+        expected.add(key as u64, cell.count, self.fingerprint_hash.hash(key as u64));
+        if expected.fingerprint != cell.fingerprint || !self.cells_of(key as u64).contains(&index) {
+            return None;
+        }
+        return Some(key as u64);
+    }
+    // The nonzero coordinates, or None if peeling got stuck because the vector is not sparse enough
+    pub fn recover(&self) -> Option<Vec<(u64, i64)>> {
+        let mut remaining: SparseRecovery = self.clone();
+        let mut recovered: Vec<(u64, i64)> = Vec::new();
+        let mut queue: Vec<usize> = (0..remaining.cells.len()).filter(|index| remaining.pure_key(*index).is_some()).collect();
+        while let Some(index) = queue.pop() {
+            let Some(key) = remaining.pure_key(index) else {
+                continue;
+            };
+            let value: i64 = remaining.cells[index].count;
+            remaining.update(key, -value);
+            recovered.push((key, value));
+            queue.extend(remaining.cells_of(key).into_iter().filter(|next| remaining.pure_key(*next).is_some()));
+        }
+        if remaining.cells.iter().all(|cell| cell.is_empty()) {
+            return Some(recovered);
+        }
+        return None;
+    }
+    pub fn sparsity(&self) -> usize {
+        return self.buckets / 2;
+    }
+    // Heap footprint of the cells in bytes
+    pub fn space_usage(&self) -> usize {
+        return self.cells.len() * size_of::<Cell>();
+    }
+}
+
+// Cells are linear in the vector, so merging adds them up and recovers the sum of both vectors
+impl MergeableSketch for SparseRecovery {
+    fn merge(&mut self, other: &SparseRecovery) -> Result<(), MergeError> {
+        if self.cells.len() != other.cells.len() {
+            return Err(MergeError::DimensionMismatch);
+        }
+        if self.bucket_hashes != other.bucket_hashes || self.fingerprint_hash != other.fingerprint_hash {
+            return Err(MergeError::SeedMismatch);
+        }
+        for (cell, other_cell) in self.cells.iter_mut().zip(&other.cells) {
+            cell.count += other_cell.count;
+            cell.key_sum += other_cell.key_sum;
+            cell.fingerprint = ((cell.fingerprint as u128 + other_cell.fingerprint as u128) % PRIME) as u64;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn sparse_vectors_are_recovered_exactly() {
+        // Peeling gets stuck when two of the 10 keys share all three buckets, which happens
+        // with probability 45 / (2 s)^3, so the sketches leave room well beyond 10 keys
+        let mut recovery: SparseRecovery = SparseRecovery::new(50);
+        let mut exact: HashMap<u64, i64> = HashMap::new();
+        for key in 0..1000u64 {
+            recovery.update(key * 7919, 3);
+        }
+        for key in 0..1000u64 {
+            if key % 100 != 0 {
+                recovery.update(key * 7919, -3);
+            } else {
+                recovery.update(key * 7919, key as i64);
+                exact.insert(key * 7919, key as i64 + 3);
+            }
+        }
+        let mut recovered: Vec<(u64, i64)> = recovery.recover().unwrap();
+        let mut expected: Vec<(u64, i64)> = exact.into_iter().collect();
+        recovered.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(recovered, expected);
+
+        (0..1000u64).for_each(|key| recovery.update(key, 1));
+        assert_eq!(recovery.recover(), None);
+    }
+
+    #[test]
+    fn merged_vectors_recover_their_sum() {
+        let mut left: SparseRecovery = SparseRecovery::new(50);
+        let mut right: SparseRecovery = left.clone();
+        // 500 keys cancel out across the two sides, 10 keys survive
+        for key in 0..510u64 {
+            left.update(key, 2);
+            right.update(key, if key < 500 { -2 } else { 5 });
+        }
+        assert_eq!(left.recover(), None);
+        left.merge(&right).unwrap();
+        let mut recovered: Vec<(u64, i64)> = left.recover().unwrap();
+        recovered.sort_unstable();
+        assert_eq!(recovered, (500..510u64).map(|key| (key, 7)).collect::<Vec<(u64, i64)>>());
+        assert_eq!(SparseRecovery::new(10).recover(), Some(Vec::new()));
+        assert_eq!(left.merge(&SparseRecovery::new(50)), Err(MergeError::SeedMismatch));
+    }
+}
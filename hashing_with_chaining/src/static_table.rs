@@ -0,0 +1,168 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use memmap2::Mmap;
+use crate::hash::{log2u, SeededHash};
+
+// File layout, all little endian:
+//   magic (8 bytes) | hash_len (u32) | padding (u32) | capacity (u64) | seed a (u128) | seed b (u128)
+// followed by `capacity` slots of (key u64, value u64) forming a linear probing table
+const MAGIC: &[u8; 8] = b"HWCSTAT1";
+const HEADER_LEN: usize = 56;
+const SLOT_LEN: usize = 16;
+
+// Marks an empty slot, so this key cannot be stored
+const EMPTY: u64 = u64::MAX;
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    return u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+}
+
+// Collects key-value pairs and materializes them as a static table file
+pub struct StaticTableBuilder {
+    entries: Vec<(u64, u64)>,
+}
+
+impl StaticTableBuilder {
+    pub fn new() -> StaticTableBuilder {
+        return StaticTableBuilder {
+            entries: Vec::new()
+        }
+    }
+    pub fn insert(&mut self, key: u64, value: u64) {
+        assert_ne!(key, EMPTY, "Key {} is reserved", EMPTY);
+        self.entries.push((key, value));
+    }
+    // Lays out the table at a load factor of at most 1/2. Later inserts of a key win.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let capacity: usize = (2 * self.entries.len()).max(2).next_power_of_two();
+        let hash_fn: SeededHash = SeededHash::new(log2u(capacity));
+        let mut slots: Vec<(u64, u64)> = vec![(EMPTY, 0); capacity];
+        for (key, value) in &self.entries {
+            let mut pos: usize = hash_fn.hash(*key);
+            while slots[pos].0 != EMPTY && slots[pos].0 != *key {
+                pos = (pos + 1) & (capacity - 1);
+            }
+            slots[pos] = (*key, *value);
+        }
+
+        let (a, b) = hash_fn.seeds();
+        let mut bytes: Vec<u8> = Vec::with_capacity(HEADER_LEN + capacity * SLOT_LEN);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&hash_fn.hash_len().to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&(capacity as u64).to_le_bytes());
+        bytes.extend_from_slice(&a.to_le_bytes());
+        bytes.extend_from_slice(&b.to_le_bytes());
+        for (key, value) in slots {
+            bytes.extend_from_slice(&key.to_le_bytes());
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        return bytes;
+    }
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+}
+
+impl Default for StaticTableBuilder {
+    fn default() -> StaticTableBuilder {
+        StaticTableBuilder::new()
+    }
+}
+
+// Read-only table queried straight from a memory mapped file, so it may be larger than RAM
+pub struct StaticTable {
+    map: Mmap,
+    hash_function: SeededHash,
+    capacity: usize,
+}
+
+impl StaticTable {
+    pub fn open(path: &Path) -> io::Result<StaticTable> {
+        let file: File = File::open(path)?;
+        // Safety: the file is only read, and must not be modified while it is mapped
+        let map: Mmap = unsafe { Mmap::map(&file)? };
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_owned());
+        if map.len() < HEADER_LEN || &map[..8] != MAGIC {
+            return Err(invalid("Not a static table file"));
+        }
+        let hash_len: u32 = u32::from_le_bytes(map[8..12].try_into().unwrap());
+        let capacity: usize = read_u64(&map, 16) as usize;
+        let a: u128 = u128::from_le_bytes(map[24..40].try_into().unwrap());
+        let b: u128 = u128::from_le_bytes(map[40..56].try_into().unwrap());
+        // A corrupt header may hold any numbers, so the shift and the size must not overflow
+        let file_len: Option<usize> = capacity.checked_mul(SLOT_LEN).and_then(|slots_len| slots_len.checked_add(HEADER_LEN));
+        if hash_len >= usize::BITS || capacity != 1 << hash_len || file_len != Some(map.len()) {
+            return Err(invalid("Static table header does not match its size"));
+        }
+        return Ok(StaticTable {
+            map,
+            hash_function: SeededHash::with_seeds(hash_len, a, b),
+            capacity,
+        });
+    }
+    pub fn get(&self, key: u64) -> Option<u64> {
+        let mut pos: usize = self.hash_function.hash(key);
+        for _ in 0..self.capacity {
+            let offset: usize = HEADER_LEN + pos * SLOT_LEN;
+            let slot_key: u64 = read_u64(&self.map, offset);
+            // Checked first, as the key EMPTY is never stored
+            if slot_key == EMPTY {
+                return None;
+            }
+            if slot_key == key {
+                return Some(read_u64(&self.map, offset + 8));
+            }
+            pos = (pos + 1) & (self.capacity - 1);
+        }
+        return None;
+    }
+    pub fn capacity(&self) -> usize {
+        return self.capacity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn written_table_reopens_with_the_same_contents() {
+        let mut builder: StaticTableBuilder = StaticTableBuilder::new();
+        for x in 0..10_000u64 {
+            builder.insert(x * 3, x);
+        }
+        let path = std::env::temp_dir().join(format!("static_table_{}.bin", std::process::id()));
+        builder.write(&path).unwrap();
+        let table: StaticTable = StaticTable::open(&path).unwrap();
+        assert!((0..10_000u64).all(|x| table.get(x * 3) == Some(x)));
+        assert_eq!(table.get(1), None);
+        assert_eq!(table.get(EMPTY), None);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_tables() {
+        let path = std::env::temp_dir().join(format!("not_a_table_{}.bin", std::process::id()));
+        std::fs::write(&path, b"definitely not a static table header").unwrap();
+        assert!(StaticTable::open(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_corrupt_headers() {
+        let path = std::env::temp_dir().join(format!("corrupt_table_{}.bin", std::process::id()));
+        let header: Vec<u8> = StaticTableBuilder::new().to_bytes()[..HEADER_LEN].to_vec();
+        // A shift past the width of usize, and a capacity whose file length wraps around to 0
+        for (hash_len, capacity) in [(64u32, 0u64), (60, 1 << 60)] {
+            let mut bytes: Vec<u8> = header.clone();
+            bytes[8..12].copy_from_slice(&hash_len.to_le_bytes());
+            bytes[16..24].copy_from_slice(&capacity.to_le_bytes());
+            std::fs::write(&path, &bytes).unwrap();
+            let err: io::Error = StaticTable::open(&path).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
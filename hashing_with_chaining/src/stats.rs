@@ -0,0 +1,147 @@
+use rand::prelude::*;
+use crate::shuffle::fisher_yates;
+
+pub fn mean(samples: &[f64]) -> f64 {
+    return samples.iter().sum::<f64>() / samples.len().max(1) as f64;
+}
+
+pub fn median(samples: &[f64]) -> f64 {
+    let mut sorted: Vec<f64> = samples.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let middle: usize = sorted.len() / 2;
+    return if sorted.len() % 2 == 1 { sorted[middle] } else { (sorted[middle - 1] + sorted[middle]) / 2.0 };
+}
+
+// Sample standard deviation, with n - 1 in the denominator so its square is unbiased
+pub fn std_dev(samples: &[f64]) -> f64 {
+    if samples.len() < 2 {
+        return 0.0;
+    }
+    let m: f64 = mean(samples);
+    return (samples.iter().map(|x| (x - m) * (x - m)).sum::<f64>() / (samples.len() - 1) as f64).sqrt();
+}
+
+// 97.5% quantiles of Student's t distribution for 1 to 30 degrees of freedom
+const T_975: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228,
+    2.201, 2.179, 2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086,
+    2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+];
+
+// 95% confidence interval of the mean, mean +- t s / sqrt(n) with t from Student's
+// distribution with n - 1 degrees of freedom, and the normal 1.96 beyond 30. It assumes
+// roughly normal samples, which the mean of a handful of timings usually is close enough
+// to; the bootstrap below does without that for larger samples.
+pub fn mean_interval(samples: &[f64]) -> (f64, f64) {
+    assert!(samples.len() >= 2, "Needs at least two samples");
+    let t: f64 = T_975.get(samples.len() - 2).copied().unwrap_or(1.96);
+    let half_width: f64 = t * std_dev(samples) / (samples.len() as f64).sqrt();
+    return (mean(samples) - half_width, mean(samples) + half_width);
+}
+
+// Summary of repeated measurements of one configuration
+#[derive(Clone, Debug, PartialEq)]
+pub struct Summary {
+    pub runs: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub std_dev: f64,
+    pub interval: (f64, f64),
+}
+
+impl Summary {
+    pub fn of(samples: &[f64]) -> Summary {
+        return Summary {
+            runs: samples.len(),
+            mean: mean(samples),
+            median: median(samples),
+            std_dev: std_dev(samples),
+            interval: mean_interval(samples),
+        }
+    }
+    // Standard deviation relative to the mean, comparable across sizes and phases
+    pub fn coefficient_of_variation(&self) -> f64 {
+        return self.std_dev / self.mean.abs();
+    }
+}
+
+// Percentile bootstrap: the statistic of resamples samples drawn with replacement from the
+// samples stands in for its sampling distribution, and the middle confidence part of those
+// values is the interval. It needs no assumption on the distribution of the samples, like
+// the skewed running times of benchmarks, only that they are independent.
+pub fn bootstrap_interval(samples: &[f64], statistic: impl Fn(&[f64]) -> f64, resamples: usize, confidence: f64, rng: &mut impl Rng) -> (f64, f64) {
+    assert!(!samples.is_empty() && resamples > 0, "Needs samples and resamples");
+    assert!(0.0 < confidence && confidence < 1.0, "Confidence must be in (0, 1)");
+    let mut resample: Vec<f64> = vec![0.0; samples.len()];
+    let mut values: Vec<f64> = (0..resamples).map(|_| {
+        resample.iter_mut().for_each(|x| *x = samples[rng.gen_range(0..samples.len())]);
+        return statistic(&resample);
+    }).collect();
+    values.sort_by(f64::total_cmp);
+    let tail: f64 = (1.0 - confidence) / 2.0;
+    let low: usize = (tail * resamples as f64).floor() as usize;
+    let high: usize = (((1.0 - tail) * resamples as f64).ceil() as usize).min(resamples) - 1;
+    return (values[low], values[high.max(low)]);
+}
+
+// Two sided permutation test of whether a and b come from the same distribution, with the
+// difference of their means as the statistic. Under that hypothesis every split of the
+// pooled samples into groups of their sizes is equally likely, so the p-value is the
+// fraction of random splits whose difference is at least as large as the observed one.
+// Counting the observed split too keeps the p-value valid for any number of permutations.
+pub fn permutation_test(a: &[f64], b: &[f64], permutations: usize, rng: &mut impl Rng) -> f64 {
+    assert!(!a.is_empty() && !b.is_empty(), "Needs samples on both sides");
+    let observed: f64 = (mean(a) - mean(b)).abs();
+    let mut pooled: Vec<f64> = a.iter().chain(b).copied().collect();
+    let mut as_extreme: usize = 0;
+    for _ in 0..permutations {
+        fisher_yates(&mut pooled, rng);
+        let (left, right) = pooled.split_at(a.len());
+        // Rounding must not make the observed split count as less extreme than itself
+        if (mean(left) - mean(right)).abs() >= observed * (1.0 - 1e-12) {
+            as_extreme += 1;
+        }
+    }
+    return (as_extreme + 1) as f64 / (permutations + 1) as f64;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use crate::distributions::standard_normal;
+
+    #[test]
+    fn intervals_and_p_values_behave() {
+        // Seeded, as a few of the checks below fail by chance for a small share of samples
+        let mut rng: StdRng = StdRng::seed_from_u64(11);
+        // The bootstrap interval of the mean of 400 standard normal samples is about
+        // +-1.96 / 20 around the sample mean
+        let samples: Vec<f64> = (0..400).map(|_| standard_normal(&mut rng)).collect();
+        let (low, high) = bootstrap_interval(&samples, mean, 4000, 0.95, &mut rng);
+        assert!(low < mean(&samples) && mean(&samples) < high);
+        assert!((high - low - 0.196).abs() < 0.04, "{} {}", low, high);
+        assert_eq!(median(&[3.0, 1.0, 2.0, 10.0]), 2.5);
+
+        // The t interval of the same samples is about as wide, and of two samples 12.706
+        // times the standard deviation over sqrt(2) on either side
+        let (t_low, t_high) = mean_interval(&samples);
+        assert!((t_high - t_low - (high - low)).abs() < 0.04, "{} {}", t_low, t_high);
+        let summary: Summary = Summary::of(&[1.0, 3.0]);
+        assert_eq!((summary.mean, summary.median, summary.std_dev), (2.0, 2.0, 2f64.sqrt()));
+        assert!((summary.interval.1 - 2.0 - 12.706).abs() < 1e-9 && (summary.coefficient_of_variation() - 2f64.sqrt() / 2.0).abs() < 1e-12);
+
+        // Shifting by one standard deviation is obvious with 50 samples per side
+        let a: Vec<f64> = (0..50).map(|_| standard_normal(&mut rng)).collect();
+        let shifted: Vec<f64> = (0..50).map(|_| standard_normal(&mut rng) + 1.0).collect();
+        assert!(permutation_test(&a, &shifted, 2000, &mut rng) < 0.01);
+
+        // Without a shift the p-values are uniform, so about 5% of them fall below 0.05
+        let rejections: usize = (0..400).filter(|_| {
+            let a: Vec<f64> = (0..20).map(|_| standard_normal(&mut rng)).collect();
+            let b: Vec<f64> = (0..20).map(|_| standard_normal(&mut rng)).collect();
+            return permutation_test(&a, &b, 200, &mut rng) < 0.05;
+        }).count();
+        assert!(rejections < 45, "{}", rejections);
+    }
+}
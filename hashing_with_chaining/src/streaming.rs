@@ -0,0 +1,154 @@
+use crate::ams::{AmsF2Estimator, AmsFkEstimator};
+use crate::bloom::{BlockedBloomFilter, BloomFilter, ScalableBloomFilter};
+use crate::count_min::CountMin;
+use crate::count_sketch::CountSketch;
+use crate::counter::Counter;
+use crate::hwc::{ChainStorage, HwC};
+use crate::hyperloglog::HyperLogLog;
+use crate::l0_sampler::L0Sampler;
+use crate::l1_sketch::L1Sketch;
+use crate::lp_sampler::LpSampler;
+use crate::minhash::{MinHash, OnePermutationHash};
+use crate::misra_gries::MisraGries;
+use crate::norm_sketch::NormSketch;
+use crate::odd_sketch::OddSketch;
+use crate::range_count_min::RangeCountMin;
+use crate::space_saving::SpaceSaving;
+use crate::sparse_recovery::SparseRecovery;
+use crate::theta_sketch::ThetaSketch;
+use crate::top_k::TopK;
+
+// Streams of updates straight into a sketch. Every sketch and HwC implements Extend over its
+// update type: (key, delta) pairs for the frequency sketches and plain keys for the distinct
+// counting and similarity sketches. Any iterator works, so updates can come from a
+// generator, a file reader or a channel without collecting them into a Vec first. consume
+// is the same as extend under a name that reads better for a whole stream.
+
+// Extend and consume for a sketch updated by method(key, delta) with deltas of type delta
+macro_rules! pair_stream {
+    ($sketch:ty, $delta:ty, $method:ident) => {
+        impl Extend<(u64, $delta)> for $sketch {
+            fn extend<I: IntoIterator<Item = (u64, $delta)>>(&mut self, updates: I) {
+                for (key, delta) in updates {
+                    self.$method(key, delta);
+                }
+            }
+        }
+
+        impl $sketch {
+            pub fn consume<I: IntoIterator<Item = (u64, $delta)>>(&mut self, updates: I) {
+                self.extend(updates);
+            }
+        }
+    };
+}
+
+// Extend and consume for a sketch of a set of keys, updated by method(key)
+macro_rules! key_stream {
+    ($sketch:ty, $method:ident) => {
+        impl Extend<u64> for $sketch {
+            fn extend<I: IntoIterator<Item = u64>>(&mut self, keys: I) {
+                for key in keys {
+                    self.$method(key);
+                }
+            }
+        }
+
+        impl $sketch {
+            pub fn consume<I: IntoIterator<Item = u64>>(&mut self, keys: I) {
+                self.extend(keys);
+            }
+        }
+    };
+}
+
+pair_stream!(CountSketch, i64, insert);
+pair_stream!(AmsF2Estimator, i64, insert);
+pair_stream!(L1Sketch, i64, insert);
+pair_stream!(L0Sampler, i64, update);
+pair_stream!(LpSampler, i64, update);
+pair_stream!(SparseRecovery, i64, update);
+pair_stream!(TopK, i64, update);
+pair_stream!(RangeCountMin, i64, update);
+pair_stream!(MisraGries, u64, update);
+pair_stream!(SpaceSaving, u64, update);
+
+key_stream!(AmsFkEstimator, insert);
+key_stream!(HyperLogLog, insert);
+key_stream!(ThetaSketch, insert);
+key_stream!(BloomFilter, insert);
+key_stream!(BlockedBloomFilter, insert);
+key_stream!(ScalableBloomFilter, insert);
+key_stream!(MinHash, insert);
+key_stream!(OnePermutationHash, insert);
+key_stream!(OddSketch, insert);
+
+impl<C: Counter> Extend<(u64, C)> for NormSketch<C> {
+    fn extend<I: IntoIterator<Item = (u64, C)>>(&mut self, updates: I) {
+        for (key, delta) in updates {
+            self.insert(key, delta);
+        }
+    }
+}
+
+impl<C: Counter> NormSketch<C> {
+    pub fn consume<I: IntoIterator<Item = (u64, C)>>(&mut self, updates: I) {
+        self.extend(updates);
+    }
+}
+
+impl<C: Counter> Extend<(u64, C)> for CountMin<C> {
+    fn extend<I: IntoIterator<Item = (u64, C)>>(&mut self, updates: I) {
+        for (key, count) in updates {
+            self.insert(key, count);
+        }
+    }
+}
+
+impl<C: Counter> CountMin<C> {
+    pub fn consume<I: IntoIterator<Item = (u64, C)>>(&mut self, updates: I) {
+        self.extend(updates);
+    }
+}
+
+impl<S: ChainStorage> Extend<(u64, S::Count)> for HwC<S> {
+    fn extend<I: IntoIterator<Item = (u64, S::Count)>>(&mut self, updates: I) {
+        for (key, delta) in updates {
+            self.insert(key, delta);
+        }
+    }
+}
+
+impl<S: ChainStorage> HwC<S> {
+    pub fn consume<I: IntoIterator<Item = (u64, S::Count)>>(&mut self, updates: I) {
+        self.extend(updates);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn generated_streams_match_single_updates() {
+        // Key x gets +2 from the first pass and -1 from the second, with no Vec in between
+        let updates = || (0..10_000u64).map(|x| (x % 1000, 2)).chain((0..10_000u64).map(|x| (x % 1000, -1)));
+        let mut hwc: HwC = HwC::new(1024);
+        let mut sketch: CountSketch = CountSketch::new(5, 10);
+        let mut single: CountSketch = sketch.clone();
+        hwc.consume(updates());
+        sketch.extend(updates());
+        updates().for_each(|(key, delta)| single.insert(key, delta));
+        assert!((0..1000u64).all(|key| hwc.get(key) == 10 && sketch.estimate(key) == single.estimate(key)));
+
+        // Keys sent over a channel from another thread
+        let (sender, receiver) = mpsc::channel::<u64>();
+        let producer = thread::spawn(move || (0..50_000u64).for_each(|key| sender.send(key % 20_000).unwrap()));
+        let mut hll: HyperLogLog = HyperLogLog::new(12);
+        hll.consume(receiver);
+        producer.join().unwrap();
+        assert!((hll.estimate() - 20_000.0).abs() < 2000.0, "{}", hll.estimate());
+    }
+}
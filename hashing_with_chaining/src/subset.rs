@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+use rand::prelude::*;
+
+// Number of k-subsets of n elements, 0 for k > n
+pub fn binomial(n: u64, k: u64) -> u64 {
+    if k > n {
+        return 0;
+    }
+    let k: u64 = k.min(n - k);
+    // Every partial product is itself a binomial coefficient, so the division is exact
+    return (0..k).fold(1u64, |c, i| (c as u128 * (n - i) as u128 / (i + 1) as u128) as u64);
+}
+
+// Floyd's algorithm: a uniformly random k-subset of {0, ..., n - 1} from exactly k random
+// numbers and O(k) memory, in no particular order. Step j adds a uniform t from
+// {0, ..., j}, or j itself if t is taken already; by induction over j every (i + 1)-subset
+// of {0, ..., j} is then equally likely, since each arises from exactly i + 1 of the ways
+// the steps can go.
+pub fn floyd_sample(n: u64, k: usize, rng: &mut impl Rng) -> Vec<u64> {
+    assert!(k as u64 <= n, "Needs k <= n");
+    let mut chosen: HashSet<u64> = HashSet::with_capacity(k);
+    let mut sample: Vec<u64> = Vec::with_capacity(k);
+    for j in n - k as u64..n {
+        let t: u64 = rng.gen_range(0..=j);
+        let pick: u64 = if chosen.contains(&t) { j } else { t };
+        chosen.insert(pick);
+        sample.push(pick);
+    }
+    return sample;
+}
+
+// Selection sampling (Knuth's Algorithm S): streams a uniformly random k-subset of
+// {0, ..., n - 1} in increasing order with O(1) memory. Element i is taken with probability
+// (k still needed) / (n - i left), which makes every subset equally likely and always
+// ends with exactly k elements. It takes O(n) time, so it suits k close to n or consumers
+// that want the elements sorted as they come.
+pub struct Combination<R: Rng> {
+    n: u64,
+    needed: u64,
+    next: u64,
+    rng: R,
+}
+
+impl<R: Rng> Combination<R> {
+    pub fn new(n: u64, k: u64, rng: R) -> Combination<R> {
+        assert!(k <= n, "Needs k <= n");
+        return Combination { n, needed: k, next: 0, rng }
+    }
+}
+
+impl<R: Rng> Iterator for Combination<R> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        while self.needed > 0 {
+            let i: u64 = self.next;
+            self.next += 1;
+            if self.rng.gen_range(0..self.n - i) < self.needed {
+                self.needed -= 1;
+                return Some(i);
+            }
+        }
+        return None;
+    }
+}
+
+// Position of a subset among all subsets of its size in the combinatorial number system,
+// sum of C(c_i, i + 1) over its elements sorted increasingly. Every k-subset of
+// {0, ..., n - 1} gets a distinct rank below C(n, k), so counting ranks checks uniformity.
+pub fn rank_combination(subset: &[u64]) -> u64 {
+    let mut sorted: Vec<u64> = subset.to_vec();
+    sorted.sort_unstable();
+    return sorted.iter().enumerate().map(|(i, c)| binomial(*c, i as u64 + 1)).sum();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pearson's statistic of the ranks of trials samples against the uniform distribution
+    // over all C(n, k) subsets
+    fn chi_square(n: u64, k: u64, trials: usize, mut sample: impl FnMut() -> Vec<u64>) -> f64 {
+        let subsets: usize = binomial(n, k) as usize;
+        let mut counts: Vec<usize> = vec![0; subsets];
+        for _ in 0..trials {
+            let subset: Vec<u64> = sample();
+            assert_eq!(subset.len(), k as usize);
+            assert_eq!(subset.iter().collect::<HashSet<_>>().len(), k as usize);
+            counts[rank_combination(&subset) as usize] += 1;
+        }
+        let expected: f64 = trials as f64 / subsets as f64;
+        return counts.iter().map(|count| (*count as f64 - expected).powi(2) / expected).sum();
+    }
+
+    #[test]
+    fn samples_every_subset_equally_often() {
+        assert_eq!((binomial(6, 3), binomial(52, 5), binomial(3, 4)), (20, 2_598_960, 0));
+        let mut ranks: Vec<u64> = (0..6u64).flat_map(|a| (a + 1..6).map(move |b| rank_combination(&[a, b]))).collect();
+        ranks.sort_unstable();
+        assert_eq!(ranks, (0..15).collect::<Vec<u64>>());
+
+        // With 19 degrees of freedom the statistic has mean 19 and exceeds 60 with
+        // probability about 3e-6
+        let mut rng = thread_rng();
+        let floyd: f64 = chi_square(6, 3, 20_000, || floyd_sample(6, 3, &mut rng));
+        assert!(floyd < 60.0, "{}", floyd);
+        let streamed: f64 = chi_square(6, 3, 20_000, || Combination::new(6, 3, thread_rng()).collect());
+        assert!(streamed < 60.0, "{}", streamed);
+        assert!(Combination::new(10, 4, thread_rng()).collect::<Vec<u64>>().windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(floyd_sample(5, 5, &mut rng).iter().collect::<HashSet<_>>().len(), 5);
+    }
+}
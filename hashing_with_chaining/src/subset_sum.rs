@@ -0,0 +1,213 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+use rand::prelude::*;
+
+// Key with a weight or priority, ordered by the latter
+#[derive(Clone, Copy, Debug)]
+struct Weighted {
+    value: f64,
+    key: u64,
+}
+
+impl PartialEq for Weighted {
+    fn eq(&self, other: &Weighted) -> bool {
+        return self.cmp(other) == Ordering::Equal;
+    }
+}
+
+impl Eq for Weighted {}
+
+impl PartialOrd for Weighted {
+    fn partial_cmp(&self, other: &Weighted) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl Ord for Weighted {
+    fn cmp(&self, other: &Weighted) -> Ordering {
+        return self.value.total_cmp(&other.value).then(self.key.cmp(&other.key));
+    }
+}
+
+// Uniform in (0, 1]
+fn uniform() -> f64 {
+    return 1.0 - thread_rng().gen::<f64>();
+}
+
+// Priority sampling of k weighted keys. Every key gets the priority weight / u, and the k
+// keys of highest priority are sampled. With tau the (k + 1)-th highest priority, a sampled
+// key estimates its weight as max(weight, tau), which makes every subset sum unbiased.
+pub struct PrioritySampler {
+    // The k + 1 highest priorities, with their original weights alongside
+    heap: BinaryHeap<Reverse<Weighted>>,
+    weights: HashMap<u64, f64>,
+    k: usize,
+}
+
+impl PrioritySampler {
+    pub fn new(k: usize) -> PrioritySampler {
+        assert!(k > 0, "The sample needs at least one slot");
+        return PrioritySampler {
+            heap: BinaryHeap::with_capacity(k + 2),
+            weights: HashMap::with_capacity(k + 2),
+            k,
+        }
+    }
+    // Keys must be distinct
+    pub fn insert(&mut self, key: u64, weight: f64) {
+        assert!(weight > 0.0 && weight.is_finite(), "Weights must be positive");
+        self.heap.push(Reverse(Weighted { value: weight / uniform(), key }));
+        self.weights.insert(key, weight);
+        if self.heap.len() > self.k + 1 {
+            let Reverse(evicted) = self.heap.pop().unwrap();
+            self.weights.remove(&evicted.key);
+        }
+    }
+    // The (k + 1)-th highest priority, or 0 while every key is still sampled
+    pub fn threshold(&self) -> f64 {
+        if self.heap.len() <= self.k {
+            return 0.0;
+        }
+        return self.heap.peek().unwrap().0.value;
+    }
+    // Sampled keys with their estimated weights
+    pub fn sample(&self) -> Vec<(u64, f64)> {
+        let tau: f64 = self.threshold();
+        return self.heap.iter()
+            .filter(|Reverse(entry)| self.heap.len() <= self.k || entry.value > tau)
+            .map(|Reverse(entry)| (entry.key, self.weights[&entry.key].max(tau)))
+            .collect();
+    }
+    // Unbiased estimate of the total weight of the keys satisfying the predicate
+    pub fn estimate<F: Fn(u64) -> bool>(&self, predicate: F) -> f64 {
+        return self.sample().iter().filter(|(key, _)| predicate(*key)).map(|(_, weight)| weight).sum();
+    }
+}
+
+// VarOpt sample of k weighted keys, which gives unbiased subset sums with the least total
+// variance possible for k samples. With threshold tau, keys heavier than tau are kept with
+// their exact weight and the light ones are kept with the adjusted weight tau. A new key
+// raises tau until the adjusted weights of the k + 1 candidates sum to k tau for the light
+// ones, and exactly one light candidate is dropped, each with probability 1 - weight / tau.
+pub struct VarOptSampler {
+    // Keys heavier than the threshold, lightest on top
+    large: BinaryHeap<Reverse<Weighted>>,
+    // Keys whose adjusted weight is the threshold
+    small: Vec<u64>,
+    tau: f64,
+    k: usize,
+}
+
+impl VarOptSampler {
+    pub fn new(k: usize) -> VarOptSampler {
+        assert!(k > 0, "The sample needs at least one slot");
+        return VarOptSampler {
+            large: BinaryHeap::with_capacity(k + 1),
+            small: Vec::with_capacity(k),
+            tau: 0.0,
+            k,
+        }
+    }
+    // Keys must be distinct
+    pub fn insert(&mut self, key: u64, weight: f64) {
+        assert!(weight > 0.0 && weight.is_finite(), "Weights must be positive");
+        if self.large.len() + self.small.len() < self.k {
+            self.large.push(Reverse(Weighted { value: weight, key }));
+            return;
+        }
+        // Candidates that become light in this step, with their current weights
+        let mut moved: Vec<Weighted> = Vec::new();
+        let mut light_weight: f64 = self.tau * self.small.len() as f64;
+        if weight > self.tau {
+            self.large.push(Reverse(Weighted { value: weight, key }));
+        } else {
+            moved.push(Weighted { value: weight, key });
+            light_weight += weight;
+        }
+        // A large key turns light when it is below the threshold computed with it included
+        while let Some(Reverse(lightest)) = self.large.peek().copied() {
+            let light_count: f64 = (self.small.len() + moved.len()) as f64;
+            if lightest.value * (light_count - 1.0) >= light_weight {
+                break;
+            }
+            self.large.pop();
+            moved.push(lightest);
+            light_weight += lightest.value;
+        }
+        let tau: f64 = light_weight / (self.small.len() + moved.len() - 1) as f64;
+
+        // The moved keys are dropped with probability 1 - weight / tau, the old light ones
+        // with probability 1 - old tau / tau each, and the probabilities sum to one
+        let mut r: f64 = thread_rng().gen::<f64>();
+        let mut dropped: Option<usize> = None;
+        for (i, candidate) in moved.iter().enumerate() {
+            r -= 1.0 - candidate.value / tau;
+            if r < 0.0 {
+                dropped = Some(i);
+                break;
+            }
+        }
+        match dropped {
+            Some(i) => {
+                moved.swap_remove(i);
+            }
+            None if !self.small.is_empty() => {
+                let i: usize = thread_rng().gen_range(0..self.small.len());
+                self.small.swap_remove(i);
+            }
+            // Only reachable when rounding leaves r just above zero
+            None => {
+                moved.pop();
+            }
+        }
+        self.small.extend(moved.iter().map(|candidate| candidate.key));
+        self.tau = tau;
+    }
+    pub fn threshold(&self) -> f64 {
+        return self.tau;
+    }
+    // Sampled keys with their estimated weights
+    pub fn sample(&self) -> Vec<(u64, f64)> {
+        let large = self.large.iter().map(|Reverse(entry)| (entry.key, entry.value));
+        return large.chain(self.small.iter().map(|key| (*key, self.tau))).collect();
+    }
+    // Unbiased estimate of the total weight of the keys satisfying the predicate
+    pub fn estimate<F: Fn(u64) -> bool>(&self, predicate: F) -> f64 {
+        return self.sample().iter().filter(|(key, _)| predicate(*key)).map(|(_, weight)| weight).sum();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRIALS: usize = 20_000;
+
+    // Samples of 10 out of the keys 1, ..., 100 weighted by themselves. The even keys weigh
+    // 2550 in total, which the average estimate must be close to.
+    #[test]
+    fn priority_sampling_is_unbiased() {
+        let mut sum: f64 = 0.0;
+        for _ in 0..TRIALS {
+            let mut sampler: PrioritySampler = PrioritySampler::new(10);
+            (1..=100u64).for_each(|key| sampler.insert(key, key as f64));
+            assert_eq!(sampler.sample().len(), 10);
+            sum += sampler.estimate(|key| key % 2 == 0);
+        }
+        assert!((sum / TRIALS as f64 - 2550.0).abs() < 30.0, "{}", sum / TRIALS as f64);
+    }
+
+    #[test]
+    fn varopt_is_unbiased_and_keeps_the_total() {
+        let mut sum: f64 = 0.0;
+        for _ in 0..TRIALS {
+            let mut sampler: VarOptSampler = VarOptSampler::new(10);
+            (1..=100u64).for_each(|key| sampler.insert(key, key as f64));
+            assert_eq!(sampler.sample().len(), 10);
+            // The adjusted weights of a VarOpt sample always add up to the exact total
+            assert!((sampler.estimate(|_| true) - 5050.0).abs() < 1e-6);
+            sum += sampler.estimate(|key| key % 2 == 0);
+        }
+        assert!((sum / TRIALS as f64 - 2550.0).abs() < 30.0, "{}", sum / TRIALS as f64);
+    }
+}
@@ -0,0 +1,56 @@
+use std::io;
+use std::io::Write;
+
+// Bucket occupancy of a hash table. Chaining tables record one chain per bucket,
+// open addressing tables record every slot as a chain of length 0 or 1.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TableStats {
+    pub buckets: usize,
+    pub entries: usize,
+    pub max_chain: usize,
+    // chain_histogram[i] is the number of buckets holding exactly i entries
+    pub chain_histogram: Vec<usize>,
+}
+
+impl TableStats {
+    pub fn new() -> TableStats {
+        TableStats::default()
+    }
+    pub fn record_chain(&mut self, len: usize) {
+        self.buckets += 1;
+        self.entries += len;
+        self.max_chain = self.max_chain.max(len);
+        if self.chain_histogram.len() <= len {
+            self.chain_histogram.resize(len + 1, 0);
+        }
+        self.chain_histogram[len] += 1;
+    }
+    // Combines the stats of two tables, e.g. the shards of one sharded table
+    pub fn merge(&mut self, other: &TableStats) {
+        self.buckets += other.buckets;
+        self.entries += other.entries;
+        self.max_chain = self.max_chain.max(other.max_chain);
+        if self.chain_histogram.len() < other.chain_histogram.len() {
+            self.chain_histogram.resize(other.chain_histogram.len(), 0);
+        }
+        for (count, other_count) in self.chain_histogram.iter_mut().zip(&other.chain_histogram) {
+            *count += other_count;
+        }
+    }
+    pub fn load_factor(&self) -> f64 {
+        return self.entries as f64 / self.buckets as f64;
+    }
+    pub fn empty_fraction(&self) -> f64 {
+        let empty: usize = self.chain_histogram.first().copied().unwrap_or(0);
+        return empty as f64 / self.buckets as f64;
+    }
+    pub fn write_to<W: Write>(&self, file: &mut W) -> io::Result<()> {
+        writeln!(file, "Load factor: {}", self.load_factor())?;
+        writeln!(file, "Longest chain: {}", self.max_chain)?;
+        writeln!(file, "Empty buckets: {}", self.empty_fraction())?;
+        for (len, count) in self.chain_histogram.iter().enumerate() {
+            writeln!(file, "Chain length {}: {}", len, count)?;
+        }
+        Ok(())
+    }
+}
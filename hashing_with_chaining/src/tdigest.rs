@@ -0,0 +1,284 @@
+use crate::mergeable::{MergeError, MergeableSketch};
+use crate::sketch_format::{ByteReader, ByteWriter, DecodeError, SketchKind};
+
+// Buffered values per unit of compression before they are merged into the centroids
+const BUFFER_FACTOR: f64 = 5.0;
+
+// A cluster of values summarized by their mean and count
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+// Merging t-digest. Values are summarized by centroids whose sizes are limited by the scale
+// function k(q) = compression / Z * ln(q / (1 - q)) with Z = 4 ln(n / compression) + 24:
+// a centroid may only span one unit of k. Centroid sizes grow geometrically from single
+// values at both ends towards the median, so extreme quantiles are much more accurate than
+// the uniform rank error of sketches like KLL.
+#[derive(Clone, Debug)]
+pub struct TDigest {
+    centroids: Vec<Centroid>,
+    buffer: Vec<Centroid>,
+    compression: f64,
+    len: u64,
+    min: f64,
+    max: f64,
+}
+
+impl TDigest {
+    // Keeps at most about compression centroids
+    pub fn new(compression: f64) -> TDigest {
+        assert!(compression >= 10.0, "Compression must be at least 10");
+        return TDigest {
+            centroids: Vec::new(),
+            buffer: Vec::new(),
+            compression,
+            len: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+    fn normalizer(&self) -> f64 {
+        return self.compression / (4.0 * (self.len as f64 / self.compression).max(1.0).ln() + 24.0);
+    }
+    fn scale(&self, q: f64) -> f64 {
+        return self.normalizer() * (q / (1.0 - q)).ln();
+    }
+    fn inverse_scale(&self, k: f64) -> f64 {
+        return 1.0 / (1.0 + (-k / self.normalizer()).exp());
+    }
+    pub fn update(&mut self, value: f64) {
+        assert!(!value.is_nan(), "NaN has no rank");
+        self.buffer.push(Centroid { mean: value, weight: 1.0 });
+        self.len += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        if self.buffer.len() as f64 > BUFFER_FACTOR * self.compression {
+            self.centroids = self.merged();
+            self.buffer.clear();
+        }
+    }
+    // Centroids and buffered values merged greedily in order of their means, where a centroid
+    // keeps absorbing its successor while it spans at most one unit of the scale function
+    fn merged(&self) -> Vec<Centroid> {
+        let mut all: Vec<Centroid> = self.centroids.iter().chain(&self.buffer).copied().collect();
+        if all.is_empty() {
+            return all;
+        }
+        all.sort_unstable_by(|a, b| a.mean.total_cmp(&b.mean));
+        let total: f64 = self.len as f64;
+        let mut merged: Vec<Centroid> = Vec::with_capacity(self.compression as usize);
+        let mut before: f64 = 0.0;
+        let mut limit: f64 = total * self.inverse_scale(self.scale(0.0) + 1.0);
+        let mut current: Centroid = all[0];
+        for next in &all[1..] {
+            if before + current.weight + next.weight <= limit {
+                let weight: f64 = current.weight + next.weight;
+                current.mean += (next.mean - current.mean) * next.weight / weight;
+                current.weight = weight;
+            } else {
+                before += current.weight;
+                merged.push(current);
+                limit = total * self.inverse_scale(self.scale(before / total) + 1.0);
+                current = *next;
+            }
+        }
+        merged.push(current);
+        return merged;
+    }
+    // Each centroid's mean is placed at the middle of its weight, with the minimum and the
+    // maximum at both ends. Quantiles and ranks interpolate linearly between those points.
+    fn points(&self) -> Vec<(f64, f64)> {
+        let mut points: Vec<(f64, f64)> = vec![(0.0, self.min)];
+        let mut before: f64 = 0.0;
+        for centroid in self.merged() {
+            points.push((before + centroid.weight / 2.0, centroid.mean));
+            before += centroid.weight;
+        }
+        points.push((before, self.max));
+        return points;
+    }
+    // Value at rank q, or None for an empty digest
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        assert!((0.0..=1.0).contains(&q), "Quantiles must be in [0, 1]");
+        if self.len == 0 {
+            return None;
+        }
+        let target: f64 = q * self.len as f64;
+        let points: Vec<(f64, f64)> = self.points();
+        let i: usize = points.partition_point(|(position, _)| *position < target).clamp(1, points.len() - 1);
+        let ((left_position, left_value), (right_position, right_value)) = (points[i - 1], points[i]);
+        if right_position <= left_position {
+            return Some(right_value);
+        }
+        return Some(left_value + (right_value - left_value) * (target - left_position) / (right_position - left_position));
+    }
+    // Estimated fraction of the values that are at most value
+    pub fn rank(&self, value: f64) -> f64 {
+        if self.len == 0 || value < self.min {
+            return 0.0;
+        }
+        if value >= self.max {
+            return 1.0;
+        }
+        let points: Vec<(f64, f64)> = self.points();
+        let i: usize = points.partition_point(|(_, mean)| *mean <= value).clamp(1, points.len() - 1);
+        let ((left_position, left_value), (right_position, right_value)) = (points[i - 1], points[i]);
+        let position: f64 = if right_value <= left_value {
+            right_position
+        } else {
+            left_position + (right_position - left_position) * (value - left_value) / (right_value - left_value)
+        };
+        return position / self.len as f64;
+    }
+    pub fn len(&self) -> u64 {
+        return self.len;
+    }
+    pub fn is_empty(&self) -> bool {
+        return self.len == 0;
+    }
+    pub fn compression(&self) -> f64 {
+        return self.compression;
+    }
+    // Number of centroids once the buffer is merged
+    pub fn centroid_count(&self) -> usize {
+        return self.merged().len();
+    }
+    // compression (f64) | len (u64) | min (f64) | max (f64), followed by the centroids and the
+    // buffer, each as their number (u64) and every mean (f64) and weight (f64). The buffer
+    // is kept apart so a resumed digest merges at the same points as one never saved.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new(SketchKind::TDigest);
+        writer.f64(self.compression);
+        writer.u64(self.len);
+        writer.f64(self.min);
+        writer.f64(self.max);
+        for centroids in [&self.centroids, &self.buffer] {
+            writer.u64(centroids.len() as u64);
+            for centroid in centroids {
+                writer.f64(centroid.mean);
+                writer.f64(centroid.weight);
+            }
+        }
+        return writer.finish();
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<TDigest, DecodeError> {
+        let mut reader: ByteReader = ByteReader::new(bytes, SketchKind::TDigest)?;
+        let compression: f64 = reader.f64()?;
+        if !(10.0..=f64::MAX).contains(&compression) {
+            return Err(DecodeError::Invalid("compression out of range"));
+        }
+        let (len, min, max) = (reader.u64()?, reader.f64()?, reader.f64()?);
+        let mut read_centroids = || -> Result<Vec<Centroid>, DecodeError> {
+            let count: usize = reader.len(2 * size_of::<f64>())?;
+            let centroids: Vec<Centroid> = (0..count)
+                .map(|_| Ok(Centroid { mean: reader.f64()?, weight: reader.f64()? }))
+                .collect::<Result<_, _>>()?;
+            if centroids.iter().any(|c| c.mean.is_nan() || !(c.weight >= 1.0 && c.weight.is_finite())) {
+                return Err(DecodeError::Invalid("centroid without a mean or a weight"));
+            }
+            return Ok(centroids);
+        };
+        let centroids: Vec<Centroid> = read_centroids()?;
+        let buffer: Vec<Centroid> = read_centroids()?;
+        reader.finish()?;
+        let weight: f64 = centroids.iter().chain(&buffer).map(|c| c.weight).sum();
+        if weight != len as f64 {
+            return Err(DecodeError::Invalid("centroid weights do not add up to the stream length"));
+        }
+        if len > 0 && (min.is_nan() || max.is_nan() || min > max) {
+            return Err(DecodeError::Invalid("minimum above the maximum"));
+        }
+        return Ok(TDigest { centroids, buffer, compression, len, min, max });
+    }
+}
+
+// The other digest's centroids are merged like buffered values
+impl MergeableSketch for TDigest {
+    fn merge(&mut self, other: &TDigest) -> Result<(), MergeError> {
+        if self.compression != other.compression {
+            return Err(MergeError::DimensionMismatch);
+        }
+        self.buffer.extend_from_slice(&other.centroids);
+        self.buffer.extend_from_slice(&other.buffer);
+        self.len += other.len;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.centroids = self.merged();
+        self.buffer.clear();
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    // Heavy tailed values like latencies, with a Pareto distribution of shape 1.5
+    fn latencies(len: usize) -> Vec<f64> {
+        return (0..len).map(|_| (1.0 - thread_rng().gen::<f64>()).powf(-1.0 / 1.5)).collect();
+    }
+
+    fn rank_error(digest: &TDigest, sorted: &[f64], q: f64) -> f64 {
+        let estimate: f64 = digest.quantile(q).unwrap();
+        return (sorted.partition_point(|x| *x <= estimate) as f64 / sorted.len() as f64 - q).abs();
+    }
+
+    #[test]
+    fn tails_are_more_accurate_than_the_middle() {
+        let mut values: Vec<f64> = latencies(200_000);
+        let mut digest: TDigest = TDigest::new(100.0);
+        values.iter().for_each(|x| digest.update(*x));
+        assert!(digest.centroid_count() <= 100);
+        values.sort_unstable_by(f64::total_cmp);
+        assert!(rank_error(&digest, &values, 0.5) < 0.02);
+        assert!(rank_error(&digest, &values, 0.99) < 0.001);
+        assert!(rank_error(&digest, &values, 0.999) < 0.0002);
+        assert_eq!(digest.quantile(0.0), Some(values[0]));
+        assert_eq!(digest.quantile(1.0), Some(values[values.len() - 1]));
+        assert!((digest.rank(values[100_000]) - 0.5).abs() < 0.02);
+        assert_eq!(TDigest::new(100.0).quantile(0.5), None);
+    }
+
+    #[test]
+    fn merged_digests_stay_accurate() {
+        let empty: TDigest = TDigest::new(100.0);
+        let mut values: Vec<f64> = Vec::new();
+        let mut merged: TDigest = empty.clone();
+        for _ in 0..8 {
+            let part: Vec<f64> = latencies(25_000);
+            let mut digest: TDigest = empty.clone();
+            part.iter().for_each(|x| digest.update(*x));
+            merged.merge(&digest).unwrap();
+            values.extend(part);
+        }
+        assert_eq!(merged.len(), 200_000);
+        values.sort_unstable_by(f64::total_cmp);
+        assert!(rank_error(&merged, &values, 0.5) < 0.03);
+        assert!(rank_error(&merged, &values, 0.999) < 0.0005);
+        assert_eq!(merged.merge(&TDigest::new(50.0)), Err(MergeError::DimensionMismatch));
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut digest: TDigest = TDigest::new(50.0);
+        // Leaves values in the buffer, which must survive too
+        (0..1_100).for_each(|x| digest.update((x * 7919 % 1_100) as f64));
+        assert!(!digest.buffer.is_empty());
+        let bytes: Vec<u8> = digest.to_bytes();
+        let mut restored: TDigest = TDigest::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.centroids, digest.centroids);
+        assert_eq!(restored.buffer, digest.buffer);
+        (0..1_000).for_each(|x| {
+            digest.update(x as f64);
+            restored.update(x as f64);
+        });
+        assert!((0..=10).all(|i| restored.quantile(i as f64 / 10.0) == digest.quantile(i as f64 / 10.0)));
+
+        let mut bytes: Vec<u8> = bytes;
+        bytes[16..24].copy_from_slice(&1_101u64.to_le_bytes());
+        assert_eq!(TDigest::from_bytes(&bytes).err(), Some(DecodeError::Invalid("centroid weights do not add up to the stream length")));
+    }
+}
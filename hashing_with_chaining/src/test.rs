@@ -1,9 +1,107 @@
-use criterion::{criterion_group, criterion_main, Criterion};
-mod main;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hashing_with_chaining::hash::{random_generator, IndependentHash, SeededHash};
+use hashing_with_chaining::hwc::{ArenaChains, HwC};
+use hashing_with_chaining::norm_sketch::NormSketch;
 
 pub fn criterion_random_gen(c: &mut Criterion) {
-    c.bench_function("Random Generator", |b| b.iter(|| main::random_generator(0, 10_000)));
+    c.bench_function("Random Generator", |b| b.iter(|| random_generator(0, 10_000)));
 }
 
-criterion_group!(benches, criterion_random_gen);
-criterion_main!(benches);
\ No newline at end of file
+// Keys hashed per second, for multiply-shift and for k-wise independent polynomials
+pub fn criterion_hash(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Hash");
+    let keys: Vec<u64> = (0..1 << 16).map(|_| random_generator(0, u32::MAX) as u64).collect();
+    group.throughput(Throughput::Elements(keys.len() as u64));
+    for hash_len in [10, 20] {
+        let hash: SeededHash = SeededHash::new(hash_len);
+        group.bench_with_input(BenchmarkId::new("multiply-shift", hash_len), &keys, |b, keys| {
+            b.iter(|| keys.iter().fold(0, |acc, x| acc ^ hash.hash(black_box(*x))))
+        });
+    }
+    for k in [2, 4, 8] {
+        let hash: IndependentHash = IndependentHash::new(k);
+        group.bench_with_input(BenchmarkId::new("k-wise polynomial", k), &keys, |b, keys| {
+            b.iter(|| keys.iter().fold(0, |acc, x| acc ^ hash.hash(black_box(*x))))
+        });
+    }
+    group.finish();
+}
+
+pub fn criterion_hwc(c: &mut Criterion) {
+    let mut group = c.benchmark_group("HwC");
+    for test_size in [12, 16, 20] {
+        let input: Vec<u64> = Vec::from_iter(1..(1 << test_size) + 1);
+        group.bench_with_input(BenchmarkId::new("insert", test_size), &input, |b, input| {
+            b.iter(|| {
+                let mut hwc: HwC = HwC::new(input.len());
+                for x in input {
+                    hwc.insert(*x, 1);
+                }
+                hwc
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("insert arena", test_size), &input, |b, input| {
+            b.iter(|| {
+                let mut hwc: HwC<ArenaChains> = HwC::new(input.len());
+                for x in input {
+                    hwc.insert(*x, 1);
+                }
+                hwc
+            })
+        });
+        let updates: Vec<(u64, i64)> = input.iter().map(|x| (*x, 1)).collect();
+        group.bench_with_input(BenchmarkId::new("insert batch", test_size), &updates, |b, updates| {
+            b.iter(|| {
+                let mut hwc: HwC = HwC::new(updates.len());
+                hwc.insert_batch(updates);
+                hwc
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("insert batch sorted", test_size), &updates, |b, updates| {
+            b.iter(|| {
+                let mut hwc: HwC = HwC::new(updates.len());
+                hwc.insert_batch_sorted(updates);
+                hwc
+            })
+        });
+        let mut hwc: HwC = HwC::new(input.len());
+        for x in &input {
+            hwc.insert(*x, 1);
+        }
+        group.bench_with_input(BenchmarkId::new("query", test_size), &input, |b, input| {
+            b.iter(|| input.iter().filter(|x| hwc.query(**x)).count())
+        });
+        group.bench_with_input(BenchmarkId::new("norm", test_size), &hwc, |b, hwc| {
+            b.iter(|| hwc.get_norm())
+        });
+    }
+    group.finish();
+}
+
+pub fn criterion_norm_sketch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("NormSketch");
+    for width_len in [12, 16, 20] {
+        let mut sketch: NormSketch = NormSketch::new(width_len);
+        for x in 0..4 << width_len {
+            sketch.insert(x, random_generator(0, 1000) as i64);
+        }
+        group.bench_with_input(BenchmarkId::new("norm", width_len), &sketch, |b, sketch| {
+            b.iter(|| sketch.norm_wide())
+        });
+    }
+    let updates: Vec<(u64, i64)> = (0..1 << 16).map(|_| (random_generator(0, 1 << 20) as u64, random_generator(0, 11) as i64 - 5)).collect();
+    group.throughput(Throughput::Elements(updates.len() as u64));
+    for width_len in [12, 16, 20] {
+        let mut sketch: NormSketch = NormSketch::new(width_len);
+        group.bench_with_input(BenchmarkId::new("update", width_len), &updates, |b, updates| {
+            b.iter(|| updates.iter().for_each(|(key, delta)| sketch.insert(*key, *delta)))
+        });
+        group.bench_with_input(BenchmarkId::new("estimate", width_len), &updates, |b, updates| {
+            b.iter(|| updates.iter().map(|(key, _)| sketch.estimate(*key)).sum::<i64>())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_random_gen, criterion_hash, criterion_hwc, criterion_norm_sketch);
+criterion_main!(benches);
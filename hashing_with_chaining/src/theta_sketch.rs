@@ -0,0 +1,219 @@
+use std::collections::BTreeSet;
+use std::mem::size_of;
+use serde::{Deserialize, Serialize};
+use crate::hash::IndependentHash;
+use crate::mergeable::{MergeError, MergeableSketch};
+use crate::sketch_format::{ByteReader, ByteWriter, DecodeError, SketchKind};
+
+// Every hash value of IndependentHash is below 2^61 - 1, so a sketch starts out keeping all
+const MAX_THETA: u64 = (1 << 61) - 1;
+
+// Theta sketch for distinct counting with set operations. It keeps the hash values of the
+// distinct keys below the threshold theta, at most k of them: once a (k + 1)-th value comes
+// in, the largest one becomes theta and is dropped. Every distinct key is kept with
+// probability theta / 2^61, so the retained count divided by that estimates the number of
+// distinct keys with a relative standard error of about 1 / sqrt(k). Sketches of different
+// streams with the same hash function combine into sketches of their union, intersection
+// and difference by applying the set operation to the values below the smaller theta.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ThetaSketch {
+    hashes: BTreeSet<u64>,
+    hash_function: IndependentHash,
+    theta: u64,
+    k: usize,
+}
+
+impl ThetaSketch {
+    pub fn new(k: usize) -> ThetaSketch {
+        assert!(k > 0, "The sketch needs room for at least one hash value");
+        return ThetaSketch {
+            hashes: BTreeSet::new(),
+            hash_function: IndependentHash::new(4),
+            theta: MAX_THETA,
+            k,
+        }
+    }
+    pub fn insert(&mut self, key: u64) {
+        let hash_val: u64 = self.hash_function.hash(key);
+        if hash_val < self.theta && self.hashes.insert(hash_val) {
+            self.trim();
+        }
+    }
+    // Lowers theta to the largest kept value until at most k are left
+    fn trim(&mut self) {
+        while self.hashes.len() > self.k {
+            self.theta = self.hashes.pop_last().unwrap();
+        }
+    }
+    // Estimate of the number of distinct keys, exact while theta has not moved
+    pub fn estimate(&self) -> f64 {
+        return self.hashes.len() as f64 / self.theta_fraction();
+    }
+    // Sampling probability of a distinct key
+    pub fn theta_fraction(&self) -> f64 {
+        return self.theta as f64 / MAX_THETA as f64;
+    }
+    pub fn is_exact(&self) -> bool {
+        return self.theta == MAX_THETA;
+    }
+    fn check_compatible(&self, other: &ThetaSketch) -> Result<(), MergeError> {
+        if self.hash_function != other.hash_function {
+            return Err(MergeError::SeedMismatch);
+        }
+        return Ok(());
+    }
+    // Sketch of the keys in either stream, with room for the smaller k of the two
+    pub fn union(&self, other: &ThetaSketch) -> Result<ThetaSketch, MergeError> {
+        let mut union: ThetaSketch = self.clone();
+        union.merge(other)?;
+        return Ok(union);
+    }
+    // Sketch of the keys in both streams. It can keep fewer than k values, since only the
+    // common ones below the smaller theta survive.
+    pub fn intersection(&self, other: &ThetaSketch) -> Result<ThetaSketch, MergeError> {
+        self.check_compatible(other)?;
+        let theta: u64 = self.theta.min(other.theta);
+        return Ok(ThetaSketch {
+            hashes: self.hashes.range(..theta).filter(|hash_val| other.hashes.contains(hash_val)).copied().collect(),
+            hash_function: self.hash_function.clone(),
+            theta,
+            k: self.k.min(other.k),
+        });
+    }
+    // Sketch of the keys in this stream but not in the other
+    pub fn a_not_b(&self, other: &ThetaSketch) -> Result<ThetaSketch, MergeError> {
+        self.check_compatible(other)?;
+        let theta: u64 = self.theta.min(other.theta);
+        return Ok(ThetaSketch {
+            hashes: self.hashes.range(..theta).filter(|hash_val| !other.hashes.contains(hash_val)).copied().collect(),
+            hash_function: self.hash_function.clone(),
+            theta,
+            k: self.k,
+        });
+    }
+    // Number of hash values kept
+    pub fn len(&self) -> usize {
+        return self.hashes.len();
+    }
+    pub fn is_empty(&self) -> bool {
+        return self.hashes.is_empty();
+    }
+    pub fn k(&self) -> usize {
+        return self.k;
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.hashes.len() * size_of::<u64>();
+    }
+    // k (u64) | theta (u64) | hash function | number of hash values (u64), followed by the
+    // hash values (u64) in increasing order
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new(SketchKind::ThetaSketch);
+        writer.u64(self.k as u64);
+        writer.u64(self.theta);
+        writer.independent_hash(&self.hash_function);
+        writer.u64(self.hashes.len() as u64);
+        self.hashes.iter().for_each(|hash_val| writer.u64(*hash_val));
+        return writer.finish();
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<ThetaSketch, DecodeError> {
+        let mut reader: ByteReader = ByteReader::new(bytes, SketchKind::ThetaSketch)?;
+        let k: usize = reader.u64()? as usize;
+        let theta: u64 = reader.u64()?;
+        if k == 0 || theta > MAX_THETA {
+            return Err(DecodeError::Invalid("k or theta out of range"));
+        }
+        let hash_function: IndependentHash = reader.independent_hash()?;
+        let len: usize = reader.len(size_of::<u64>())?;
+        if len > k {
+            return Err(DecodeError::Invalid("more hash values than k"));
+        }
+        let values: Vec<u64> = (0..len).map(|_| reader.u64()).collect::<Result<_, _>>()?;
+        reader.finish()?;
+        if values.windows(2).any(|pair| pair[0] >= pair[1]) || values.last().is_some_and(|last| *last >= theta) {
+            return Err(DecodeError::Invalid("hash values out of order or not below theta"));
+        }
+        return Ok(ThetaSketch { hashes: values.into_iter().collect(), hash_function, theta, k });
+    }
+}
+
+// Merging is the union: the values of both below the smaller theta, trimmed to the smaller k
+impl MergeableSketch for ThetaSketch {
+    fn merge(&mut self, other: &ThetaSketch) -> Result<(), MergeError> {
+        self.check_compatible(other)?;
+        self.theta = self.theta.min(other.theta);
+        self.k = self.k.min(other.k);
+        let theta: u64 = self.theta;
+        self.hashes.retain(|hash_val| *hash_val < theta);
+        self.hashes.extend(other.hashes.range(..theta));
+        self.trim();
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relative_error(estimate: f64, exact: usize) -> f64 {
+        return (estimate - exact as f64).abs() / exact as f64;
+    }
+
+    #[test]
+    fn set_operations_match_exact_set_algebra() {
+        // A = [0, 60000) and B = [40000, 100000), with each key inserted three times
+        let mut a: ThetaSketch = ThetaSketch::new(4096);
+        let mut b: ThetaSketch = a.clone();
+        for _ in 0..3 {
+            (0..60_000u64).for_each(|key| a.insert(key));
+            (40_000..100_000u64).for_each(|key| b.insert(key));
+        }
+        assert_eq!(a.len(), 4096);
+        // The standard error is about 1.6% for k = 4096, more for the smaller results
+        assert!(relative_error(a.estimate(), 60_000) < 0.08, "{}", a.estimate());
+        assert!(relative_error(b.estimate(), 60_000) < 0.08, "{}", b.estimate());
+        let union: ThetaSketch = a.union(&b).unwrap();
+        assert!(relative_error(union.estimate(), 100_000) < 0.08, "{}", union.estimate());
+        let intersection: ThetaSketch = a.intersection(&b).unwrap();
+        assert!(relative_error(intersection.estimate(), 20_000) < 0.15, "{}", intersection.estimate());
+        let difference: ThetaSketch = a.a_not_b(&b).unwrap();
+        assert!(relative_error(difference.estimate(), 40_000) < 0.1, "{}", difference.estimate());
+
+        let mut merged: ThetaSketch = a.clone();
+        merged.merge(&b).unwrap();
+        assert_eq!(merged.estimate(), union.estimate());
+        assert_eq!(a.union(&ThetaSketch::new(4096)).err(), Some(MergeError::SeedMismatch));
+    }
+
+    #[test]
+    fn small_sets_are_exact() {
+        let mut a: ThetaSketch = ThetaSketch::new(1000);
+        let mut b: ThetaSketch = a.clone();
+        (0..500u64).for_each(|key| a.insert(key));
+        (300..700u64).for_each(|key| b.insert(key));
+        assert!(a.is_exact());
+        assert_eq!(a.estimate(), 500.0);
+        assert_eq!(a.union(&b).unwrap().estimate(), 700.0);
+        assert_eq!(a.intersection(&b).unwrap().estimate(), 200.0);
+        assert_eq!(a.a_not_b(&b).unwrap().estimate(), 300.0);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut sketch: ThetaSketch = ThetaSketch::new(256);
+        (0..10_000u64).for_each(|key| sketch.insert(key));
+        let bytes: Vec<u8> = sketch.to_bytes();
+        let mut restored: ThetaSketch = ThetaSketch::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.estimate(), sketch.estimate());
+        (10_000..20_000u64).for_each(|key| {
+            sketch.insert(key);
+            restored.insert(key);
+        });
+        assert_eq!(restored.estimate(), sketch.estimate());
+        assert_eq!(restored.union(&sketch).unwrap().estimate(), sketch.estimate());
+
+        // theta lowered below the largest kept value
+        let mut bytes: Vec<u8> = bytes;
+        bytes[16..24].copy_from_slice(&1u64.to_le_bytes());
+        assert_eq!(ThetaSketch::from_bytes(&bytes).err(), Some(DecodeError::Invalid("hash values out of order or not below theta")));
+    }
+}
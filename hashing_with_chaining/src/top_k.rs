@@ -0,0 +1,107 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use crate::count_min::CountMin;
+
+// Approximate top-k of a turnstile stream: a Count-Min sketch estimates every key, and the
+// k keys with the largest estimates seen so far are kept as candidates. The candidates sit
+// in a min-heap so a new key only has to beat the smallest of them. Heap entries go stale
+// when a candidate's estimate changes, and are skipped when they reach the top.
+pub struct TopK {
+    sketch: CountMin,
+    candidates: HashMap<u64, u64>,
+    heap: BinaryHeap<Reverse<(u64, u64)>>,
+    k: usize,
+}
+
+impl TopK {
+    // The sketch must use the standard update policy if the stream has deletions
+    pub fn new(k: usize, sketch: CountMin) -> TopK {
+        assert!(k > 0, "Needs to track at least one key");
+        return TopK {
+            sketch,
+            candidates: HashMap::with_capacity(k),
+            heap: BinaryHeap::with_capacity(k),
+            k,
+        }
+    }
+    pub fn update(&mut self, key: u64, delta: i64) {
+        if delta >= 0 {
+            self.sketch.insert(key, delta as u64);
+        } else {
+            self.sketch.remove(key, delta.unsigned_abs());
+        }
+        let estimate: u64 = self.sketch.estimate(key);
+        if let Some(current) = self.candidates.get_mut(&key) {
+            *current = estimate;
+        } else if self.candidates.len() < self.k {
+            self.candidates.insert(key, estimate);
+        } else {
+            match self.smallest() {
+                Some((smallest, smallest_key)) if estimate > smallest => {
+                    self.heap.pop();
+                    self.candidates.remove(&smallest_key);
+                    self.candidates.insert(key, estimate);
+                }
+                _ => return,
+            }
+        }
+        self.heap.push(Reverse((estimate, key)));
+        // Rebuild once stale entries outnumber the live ones
+        if self.heap.len() > 2 * self.k {
+            self.heap = self.candidates.iter().map(|(key, estimate)| Reverse((*estimate, *key))).collect();
+        }
+    }
+    // Candidate with the smallest estimate, dropping stale entries on the way
+    fn smallest(&mut self) -> Option<(u64, u64)> {
+        while let Some(Reverse((estimate, key))) = self.heap.peek().copied() {
+            if self.candidates.get(&key) == Some(&estimate) {
+                return Some((estimate, key));
+            }
+            self.heap.pop();
+        }
+        return None;
+    }
+    // Candidates with their estimates, largest first
+    pub fn top_k(&self) -> Vec<(u64, u64)> {
+        let mut top: Vec<(u64, u64)> = self.candidates.iter().map(|(key, estimate)| (*key, *estimate)).collect();
+        top.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        return top;
+    }
+    pub fn estimate(&self, key: u64) -> u64 {
+        return self.sketch.estimate(key);
+    }
+    pub fn sketch(&self) -> &CountMin {
+        return &self.sketch;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::count_min::UpdatePolicy;
+    use crate::hash::random_generator;
+
+    #[test]
+    fn tracks_the_heaviest_keys_through_deletions() {
+        let mut top: TopK = TopK::new(5, CountMin::new(4, 12, UpdatePolicy::Standard));
+        for _ in 0..20 {
+            for key in 1..=10u64 {
+                top.update(key, 100 * key as i64);
+            }
+            for _ in 0..1000 {
+                top.update(random_generator(100, 100_000) as u64, 1);
+            }
+        }
+        let keys: Vec<u64> = top.top_k().iter().map(|(key, _)| *key).collect();
+        assert_eq!(keys, vec![10, 9, 8, 7, 6]);
+
+        // Once the two heaviest keys are deleted, keys that update again take their place
+        top.update(10, -20 * 1000);
+        top.update(9, -20 * 900);
+        for key in 1..=5u64 {
+            top.update(key, 1);
+        }
+        let keys: Vec<u64> = top.top_k().iter().map(|(key, _)| *key).collect();
+        assert_eq!(keys, vec![8, 7, 6, 5, 4]);
+    }
+}
@@ -0,0 +1,235 @@
+use std::cmp::Ordering;
+use std::mem::size_of;
+use rand::prelude::*;
+
+type Link<K, V> = Option<Box<Node<K, V>>>;
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    priority: u64,
+    // Nodes in the subtree rooted here
+    size: usize,
+    left: Link<K, V>,
+    right: Link<K, V>,
+}
+
+impl<K, V> Node<K, V> {
+    fn update_size(&mut self) {
+        self.size = 1 + size(&self.left) + size(&self.right);
+    }
+}
+
+fn size<K, V>(link: &Link<K, V>) -> usize {
+    return link.as_ref().map_or(0, |node| node.size);
+}
+
+// Splits a subtree into the keys below key and the keys from key on
+fn split<K: Ord, V>(link: Link<K, V>, key: &K) -> (Link<K, V>, Link<K, V>) {
+    match link {
+        None => return (None, None),
+        Some(mut node) => {
+            if node.key < *key {
+                let (less, rest) = split(node.right.take(), key);
+                node.right = less;
+                node.update_size();
+                return (Some(node), rest);
+            }
+            let (less, rest) = split(node.left.take(), key);
+            node.left = rest;
+            node.update_size();
+            return (less, Some(node));
+        }
+    }
+}
+
+// Joins two subtrees where every key of left is below every key of right
+fn merge<K: Ord, V>(left: Link<K, V>, right: Link<K, V>) -> Link<K, V> {
+    match (left, right) {
+        (None, right) => return right,
+        (left, None) => return left,
+        (Some(mut left), Some(mut right)) => {
+            if left.priority > right.priority {
+                left.right = merge(left.right.take(), Some(right));
+                left.update_size();
+                return Some(left);
+            }
+            right.left = merge(Some(left), right.left.take());
+            right.update_size();
+            return Some(right);
+        }
+    }
+}
+
+fn remove<K: Ord, V>(link: &mut Link<K, V>, key: &K) -> Option<V> {
+    let node: &mut Box<Node<K, V>> = link.as_mut()?;
+    let removed: Option<V> = match key.cmp(&node.key) {
+        Ordering::Less => remove(&mut node.left, key),
+        Ordering::Greater => remove(&mut node.right, key),
+        Ordering::Equal => {
+            let mut node: Box<Node<K, V>> = link.take().unwrap();
+            *link = merge(node.left.take(), node.right.take());
+            return Some(node.value);
+        }
+    };
+    if removed.is_some() {
+        node.update_size();
+    }
+    return removed;
+}
+
+fn height<K, V>(link: &Link<K, V>) -> usize {
+    return link.as_ref().map_or(0, |node| 1 + height(&node.left).max(height(&node.right)));
+}
+
+// Randomized balanced search tree (Seidel and Aragon): a binary search tree on the keys that
+// is also a max-heap on random priorities. The shape is that of inserting the keys in random
+// order whatever the real order was, so the expected depth of every node is O(log n).
+// Insert and delete go through split and merge, which are also public: split cuts a treap
+// at a key and merge joins two treaps whose keys do not interleave, both in expected
+// O(log n) time.
+pub struct Treap<K: Ord, V> {
+    root: Link<K, V>,
+}
+
+impl<K: Ord, V> Default for Treap<K, V> {
+    fn default() -> Treap<K, V> {
+        return Treap::new();
+    }
+}
+
+impl<K: Ord, V> Treap<K, V> {
+    pub fn new() -> Treap<K, V> {
+        return Treap { root: None }
+    }
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let mut link: &Link<K, V> = &self.root;
+        while let Some(node) = link {
+            match key.cmp(&node.key) {
+                Ordering::Less => link = &node.left,
+                Ordering::Greater => link = &node.right,
+                Ordering::Equal => return Some(&node.value),
+            }
+        }
+        return None;
+    }
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let mut link: &mut Link<K, V> = &mut self.root;
+        while let Some(node) = link {
+            match key.cmp(&node.key) {
+                Ordering::Less => link = &mut node.left,
+                Ordering::Greater => link = &mut node.right,
+                Ordering::Equal => return Some(&mut node.value),
+            }
+        }
+        return None;
+    }
+    pub fn contains_key(&self, key: &K) -> bool {
+        return self.get(key).is_some();
+    }
+    // Inserts the key, or replaces its value and returns the old one
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(old) = self.get_mut(&key) {
+            return Some(std::mem::replace(old, value));
+        }
+        let (less, rest) = split(self.root.take(), &key);
+        let node: Box<Node<K, V>> = Box::new(Node { key, value, priority: thread_rng().gen(), size: 1, left: None, right: None });
+        self.root = merge(merge(less, Some(node)), rest);
+        return None;
+    }
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        return remove(&mut self.root, key);
+    }
+    // Splits off the keys from key on, leaving the keys below key in self
+    pub fn split_off(&mut self, key: &K) -> Treap<K, V> {
+        let (less, rest) = split(self.root.take(), key);
+        self.root = less;
+        return Treap { root: rest }
+    }
+    // Appends a treap whose keys are all above the keys of self
+    pub fn append(&mut self, other: Treap<K, V>) {
+        if let (Some(last), Some(first)) = (self.last_key(), other.first_key()) {
+            assert!(last < first, "Appended keys must be above the existing keys");
+        }
+        self.root = merge(self.root.take(), other.root);
+    }
+    fn first_key(&self) -> Option<&K> {
+        let mut node: &Node<K, V> = self.root.as_ref()?;
+        while let Some(left) = &node.left {
+            node = left;
+        }
+        return Some(&node.key);
+    }
+    fn last_key(&self) -> Option<&K> {
+        let mut node: &Node<K, V> = self.root.as_ref()?;
+        while let Some(right) = &node.right {
+            node = right;
+        }
+        return Some(&node.key);
+    }
+    // Entries in key order
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        let mut stack: Vec<&Node<K, V>> = Vec::new();
+        let mut link: &Link<K, V> = &self.root;
+        return std::iter::from_fn(move || {
+            while let Some(node) = link {
+                stack.push(node);
+                link = &node.left;
+            }
+            let node: &Node<K, V> = stack.pop()?;
+            link = &node.right;
+            return Some((&node.key, &node.value));
+        });
+    }
+    pub fn len(&self) -> usize {
+        return size(&self.root);
+    }
+    pub fn is_empty(&self) -> bool {
+        return self.root.is_none();
+    }
+    // Longest root to leaf path, about 3 ln n for random priorities
+    pub fn height(&self) -> usize {
+        return height(&self.root);
+    }
+    pub fn space_usage(&self) -> usize {
+        return self.len() * size_of::<Node<K, V>>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use crate::hash::random_generator;
+
+    #[test]
+    fn random_operations_match_a_btree_map() {
+        let mut treap: Treap<u64, u64> = Treap::new();
+        let mut exact: BTreeMap<u64, u64> = BTreeMap::new();
+        for i in 0..50_000u64 {
+            let key: u64 = random_generator(0, 5000) as u64;
+            if random_generator(0, 3) == 0 {
+                assert_eq!(treap.remove(&key), exact.remove(&key));
+            } else {
+                assert_eq!(treap.insert(key, i), exact.insert(key, i));
+            }
+        }
+        assert_eq!(treap.len(), exact.len());
+        assert!(treap.iter().eq(exact.iter()));
+        assert!((0..5000u64).all(|key| treap.get(&key) == exact.get(&key)));
+    }
+
+    #[test]
+    fn split_and_append_keep_sorted_input_balanced() {
+        let mut treap: Treap<u64, ()> = Treap::new();
+        (0..100_000u64).for_each(|key| { treap.insert(key, ()); });
+        // Sorted insertion would make a plain search tree a path of 100000 nodes
+        assert!(treap.height() < 60, "{}", treap.height());
+        let upper: Treap<u64, ()> = treap.split_off(&40_000);
+        assert_eq!((treap.len(), upper.len()), (40_000, 60_000));
+        assert!(treap.iter().map(|(key, _)| *key).eq(0..40_000));
+        assert!(upper.iter().map(|(key, _)| *key).eq(40_000..100_000));
+        treap.append(upper);
+        assert!(treap.len() == 100_000 && treap.contains_key(&99_999) && treap.height() < 60);
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::LinkedList;
+use rand::prelude::*;
+use crate::hash::{log2u, SeededHash};
+use crate::table_stats::TableStats;
+
+// Hashing with chaining using the power of two choices: every element goes into
+// the shorter of its two candidate chains, bringing the longest chain down to O(log log n)
+pub struct TwoChoiceHwC {
+    vec: Vec<LinkedList<u64>>,
+    hash_function1: SeededHash,
+    hash_function2: SeededHash,
+}
+
+impl TwoChoiceHwC {
+    pub fn new(size: usize) -> TwoChoiceHwC {
+        return TwoChoiceHwC::with_rng(size, &mut thread_rng());
+    }
+    // Hash functions drawn from rng, so a seeded generator gives the same chains
+    pub fn with_rng(size: usize, rng: &mut impl Rng) -> TwoChoiceHwC {
+        let hash_len: u32 = log2u(size);
+        return TwoChoiceHwC {
+            vec: vec![LinkedList::<u64>::new(); size],
+            hash_function1: SeededHash::with_rng(hash_len, rng),
+            hash_function2: SeededHash::with_rng(hash_len, rng),
+        }
+    }
+    pub fn insert(&mut self, elem: u64) {
+        let h1: usize = self.hash_function1.hash(elem);
+        let h2: usize = self.hash_function2.hash(elem);
+        if self.vec[h1].len() <= self.vec[h2].len() {
+            self.vec[h1].push_back(elem)
+        } else {
+            self.vec[h2].push_back(elem)
+        }
+    }
+    pub fn query(&self, elem: u64) -> bool {
+        self.vec[self.hash_function1.hash(elem)].contains(&elem)
+            || self.vec[self.hash_function2.hash(elem)].contains(&elem)
+    }
+    pub fn stats(&self) -> TableStats {
+        let mut stats: TableStats = TableStats::new();
+        self.vec.iter().for_each(|ll| stats.record_chain(ll.len()));
+        return stats;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn inserts_into_the_shorter_chain() {
+        // Multiply-shift gives some seeds long chains on these evenly spaced keys, so the
+        // table has fixed seeds
+        let mut two_choice: TwoChoiceHwC = TwoChoiceHwC::with_rng(1 << 14, &mut StdRng::seed_from_u64(7));
+        for key in 1..=(1 << 14) as u64 {
+            two_choice.insert(key * 3);
+        }
+        assert!((1..=(1 << 14) as u64).all(|key| two_choice.query(key * 3)));
+        assert!(!(1..=(1 << 14) as u64).any(|key| two_choice.query(key * 3 + 1)));
+        let stats: TableStats = two_choice.stats();
+        assert_eq!((stats.entries, stats.buckets), (1 << 14, 1 << 14));
+        // One choice gives chains of about log n / log log n, two choices of log log n
+        assert!(stats.max_chain <= 4);
+    }
+}
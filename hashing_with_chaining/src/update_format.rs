@@ -0,0 +1,166 @@
+use std::io;
+use std::io::ErrorKind::InvalidData;
+use std::io::{BufRead, Write};
+
+// Turnstile updates in a file or a pipe, read one at a time so a stream never has to fit in
+// memory. Text has one update per line, the key and the delta separated by whitespace, with
+// the delta 1 when left out; empty lines and lines starting with # are skipped. Binary is a
+// sequence of 16-byte records, the key as a u64 and the delta as an i64, little endian.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpdateFormat {
+    Text,
+    Binary,
+}
+
+const RECORD_LEN: usize = 16;
+
+pub struct UpdateReader<R> {
+    reader: R,
+    format: UpdateFormat,
+    line: String,
+    line_number: usize,
+    read: u64,
+    error: Option<io::Error>,
+}
+
+impl<R: BufRead> UpdateReader<R> {
+    pub fn new(reader: R, format: UpdateFormat) -> UpdateReader<R> {
+        return UpdateReader { reader, format, line: String::new(), line_number: 0, read: 0, error: None }
+    }
+    fn parse_line(&self) -> io::Result<Option<(u64, i64)>> {
+        let invalid = |what: &str| io::Error::new(InvalidData, format!("Line {}: {}", self.line_number, what));
+        let line: &str = self.line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+        let mut fields = line.split_whitespace();
+        let key: u64 = fields.next().unwrap().parse().map_err(|_| invalid("the key is not an unsigned integer"))?;
+        let delta: i64 = match fields.next() {
+            Some(delta) => delta.parse().map_err(|_| invalid("the delta is not an integer"))?,
+            None => 1,
+        };
+        if fields.next().is_some() {
+            return Err(invalid("more than a key and a delta"));
+        }
+        return Ok(Some((key, delta)));
+    }
+    fn next_text(&mut self) -> io::Result<Option<(u64, i64)>> {
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                return Ok(None);
+            }
+            self.line_number += 1;
+            if let Some(update) = self.parse_line()? {
+                return Ok(Some(update));
+            }
+        }
+    }
+    fn next_binary(&mut self) -> io::Result<Option<(u64, i64)>> {
+        let mut record: [u8; RECORD_LEN] = [0; RECORD_LEN];
+        let mut filled: usize = 0;
+        while filled < RECORD_LEN {
+            match self.reader.read(&mut record[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(io::Error::new(InvalidData, format!("The stream ends inside record {}", self.read + 1))),
+                Ok(len) => filled += len,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        let key: u64 = u64::from_le_bytes(record[..8].try_into().unwrap());
+        let delta: i64 = i64::from_le_bytes(record[8..].try_into().unwrap());
+        return Ok(Some((key, delta)));
+    }
+    // The updates until the end of the stream or the first error, for consume or extend of a
+    // sketch. The error, if any, is kept for finish.
+    pub fn updates(&mut self) -> impl Iterator<Item = (u64, i64)> + '_ {
+        return std::iter::from_fn(move || match self.next()? {
+            Ok(update) => Some(update),
+            Err(err) => {
+                self.error = Some(err);
+                None
+            }
+        });
+    }
+    // Number of updates read, or the error that stopped the stream
+    pub fn finish(self) -> io::Result<u64> {
+        return match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.read),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for UpdateReader<R> {
+    type Item = io::Result<(u64, i64)>;
+
+    fn next(&mut self) -> Option<io::Result<(u64, i64)>> {
+        let update: io::Result<Option<(u64, i64)>> = match self.format {
+            UpdateFormat::Text => self.next_text(),
+            UpdateFormat::Binary => self.next_binary(),
+        };
+        if let Ok(Some(_)) = update {
+            self.read += 1;
+        }
+        return update.transpose();
+    }
+}
+
+pub struct UpdateWriter<W: Write> {
+    writer: W,
+    format: UpdateFormat,
+}
+
+impl<W: Write> UpdateWriter<W> {
+    pub fn new(writer: W, format: UpdateFormat) -> UpdateWriter<W> {
+        return UpdateWriter { writer, format }
+    }
+    pub fn write(&mut self, key: u64, delta: i64) -> io::Result<()> {
+        return match self.format {
+            UpdateFormat::Text => writeln!(self.writer, "{} {}", key, delta),
+            UpdateFormat::Binary => {
+                self.writer.write_all(&key.to_le_bytes())?;
+                self.writer.write_all(&delta.to_le_bytes())
+            }
+        }
+    }
+    pub fn into_inner(self) -> W {
+        return self.writer;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::norm_sketch::NormSketch;
+
+    #[test]
+    fn updates_round_trip_in_both_formats() {
+        let updates: Vec<(u64, i64)> = vec![(1, 1), (u64::MAX, -3), (42, -1 << 40), (7, 0)];
+        for format in [UpdateFormat::Text, UpdateFormat::Binary] {
+            let mut writer: UpdateWriter<Vec<u8>> = UpdateWriter::new(Vec::new(), format);
+            updates.iter().for_each(|(key, delta)| writer.write(*key, *delta).unwrap());
+            let bytes: Vec<u8> = writer.into_inner();
+            let read: Vec<(u64, i64)> = UpdateReader::new(bytes.as_slice(), format).collect::<io::Result<_>>().unwrap();
+            assert_eq!(read, updates);
+
+            // A sketch fed from the reader matches one fed directly
+            let mut direct: NormSketch = NormSketch::new(8);
+            let mut streamed: NormSketch = direct.clone();
+            direct.consume(updates.iter().copied());
+            let mut reader: UpdateReader<&[u8]> = UpdateReader::new(bytes.as_slice(), format);
+            streamed.consume(reader.updates());
+            assert_eq!(reader.finish().unwrap(), 4);
+            assert_eq!(streamed.norm_wide(), direct.norm_wide());
+        }
+
+        let text: &str = "# key delta\n5\n\n6 -2\n7 x\n8\n";
+        let mut reader: UpdateReader<&[u8]> = UpdateReader::new(text.as_bytes(), UpdateFormat::Text);
+        assert_eq!(reader.updates().collect::<Vec<_>>(), vec![(5, 1), (6, -2)]);
+        let error: io::Error = reader.finish().unwrap_err();
+        assert_eq!((error.kind(), error.to_string()), (InvalidData, "Line 5: the delta is not an integer".to_owned()));
+        let truncated: io::Error = UpdateReader::new(&[0u8; 20][..], UpdateFormat::Binary).nth(1).unwrap().unwrap_err();
+        assert_eq!(truncated.kind(), InvalidData);
+    }
+}
@@ -0,0 +1,158 @@
+use wasm_bindgen::prelude::*;
+use crate::bloom::{checked_optimal_dimensions, BloomFilter};
+use crate::datasets::parse_key;
+use crate::hwc::HwC;
+use crate::hyperloglog::{HyperLogLog, MAX_PRECISION, MIN_PRECISION};
+
+// The tables and sketches of the browser demo, for JavaScript. Keys are passed as text and
+// parsed like the keys of a trace, so a number, an IP address or a word. Counts cross over
+// as f64, which JavaScript numbers hold exactly up to 2^53, rather than as BigInt. Bad
+// arguments are thrown as errors instead of the panics of the library, which in wasm
+// would end the whole module.
+
+#[wasm_bindgen(js_name = HwC)]
+pub struct JsHwC {
+    table: HwC,
+}
+
+#[wasm_bindgen(js_class = HwC)]
+impl JsHwC {
+    #[wasm_bindgen(constructor)]
+    pub fn new(buckets: usize) -> Result<JsHwC, JsError> {
+        if buckets == 0 {
+            return Err(JsError::new("Needs at least one bucket"));
+        }
+        return Ok(JsHwC { table: HwC::new(buckets) })
+    }
+    // Adds delta to the count of key
+    pub fn insert(&mut self, key: &str, delta: i32) {
+        self.table.insert(parse_key(key), delta as i64);
+    }
+    pub fn count(&self, key: &str) -> f64 {
+        return self.table.get(parse_key(key)) as f64;
+    }
+    pub fn norm(&self) -> f64 {
+        return self.table.norm() as f64;
+    }
+    pub fn entries(&self) -> usize {
+        return self.table.stats().entries;
+    }
+    #[wasm_bindgen(js_name = loadFactor)]
+    pub fn load_factor(&self) -> f64 {
+        return self.table.stats().load_factor();
+    }
+    #[wasm_bindgen(js_name = longestChain)]
+    pub fn longest_chain(&self) -> usize {
+        return self.table.longest_chain();
+    }
+    // Element i is the number of buckets whose chain holds i keys
+    #[wasm_bindgen(js_name = chainHistogram)]
+    pub fn chain_histogram(&self) -> Vec<u32> {
+        return self.table.stats().chain_histogram.iter().map(|count| *count as u32).collect();
+    }
+}
+
+#[wasm_bindgen(js_name = BloomFilter)]
+pub struct JsBloomFilter {
+    filter: BloomFilter,
+    // Keys inserted, repeats included, for the expected false positive rate
+    inserted: usize,
+}
+
+#[wasm_bindgen(js_class = BloomFilter)]
+impl JsBloomFilter {
+    // Sized for n keys at a false positive rate of at most fpr
+    #[wasm_bindgen(constructor)]
+    pub fn new(n: usize, fpr: f64) -> Result<JsBloomFilter, JsError> {
+        if !(fpr > 0.0 && fpr < 1.0) {
+            return Err(JsError::new("False positive rate must be in (0, 1)"));
+        }
+        let Some((bits_len, k)) = checked_optimal_dimensions(n, fpr) else {
+            return Err(JsError::new("Too many keys for one filter"));
+        };
+        return Ok(JsBloomFilter { filter: BloomFilter::new(bits_len, k), inserted: 0 })
+    }
+    pub fn insert(&mut self, key: &str) {
+        self.filter.insert(parse_key(key));
+        self.inserted += 1;
+    }
+    pub fn contains(&self, key: &str) -> bool {
+        return self.filter.contains(parse_key(key));
+    }
+    #[wasm_bindgen(js_name = fillRatio)]
+    pub fn fill_ratio(&self) -> f64 {
+        return self.filter.fill_ratio();
+    }
+    #[wasm_bindgen(js_name = expectedFpr)]
+    pub fn expected_fpr(&self) -> f64 {
+        return self.filter.expected_fpr(self.inserted);
+    }
+    #[wasm_bindgen(js_name = estimatedKeys)]
+    pub fn estimated_keys(&self) -> f64 {
+        return self.filter.estimate_cardinality();
+    }
+    #[wasm_bindgen(js_name = bitCount)]
+    pub fn bit_count(&self) -> usize {
+        return self.filter.bit_count();
+    }
+    #[wasm_bindgen(js_name = hashCount)]
+    pub fn hash_count(&self) -> usize {
+        return self.filter.hash_count();
+    }
+}
+
+#[wasm_bindgen(js_name = HyperLogLog)]
+pub struct JsHyperLogLog {
+    sketch: HyperLogLog,
+}
+
+#[wasm_bindgen(js_class = HyperLogLog)]
+impl JsHyperLogLog {
+    #[wasm_bindgen(constructor)]
+    pub fn new(precision: u32) -> Result<JsHyperLogLog, JsError> {
+        if !(MIN_PRECISION..=MAX_PRECISION).contains(&precision) {
+            return Err(JsError::new(&format!("Precision must be in {}..={}", MIN_PRECISION, MAX_PRECISION)));
+        }
+        return Ok(JsHyperLogLog { sketch: HyperLogLog::new(precision) })
+    }
+    pub fn insert(&mut self, key: &str) {
+        self.sketch.insert(parse_key(key));
+    }
+    pub fn estimate(&self) -> f64 {
+        return self.sketch.estimate();
+    }
+    #[wasm_bindgen(js_name = isSparse)]
+    pub fn is_sparse(&self) -> bool {
+        return self.sketch.is_sparse();
+    }
+    #[wasm_bindgen(js_name = spaceUsage)]
+    pub fn space_usage(&self) -> usize {
+        return self.sketch.space_usage();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The bindings run natively too, as long as nothing is thrown
+    #[test]
+    fn bindings_take_keys_as_text() {
+        let mut table: JsHwC = JsHwC::new(64).ok().unwrap();
+        table.insert("10.0.0.1", 3);
+        table.insert("apple", 2);
+        table.insert("apple", -2);
+        assert_eq!(table.count(" 10.0.0.1 "), 3.0);
+        assert_eq!((table.count("apple"), table.entries(), table.norm()), (0.0, 1, 9.0));
+        assert_eq!(table.chain_histogram().iter().sum::<u32>(), 64);
+
+        let mut filter: JsBloomFilter = JsBloomFilter::new(100, 0.01).ok().unwrap();
+        (0..100).for_each(|key| filter.insert(&key.to_string()));
+        assert!((0..100).all(|key| filter.contains(&key.to_string())));
+        assert!(filter.expected_fpr() <= 0.01 && filter.fill_ratio() > 0.0);
+
+        let mut sketch: JsHyperLogLog = JsHyperLogLog::new(12).ok().unwrap();
+        (0..1000).for_each(|key| sketch.insert(&format!("user{}", key % 300)));
+        assert!((sketch.estimate() - 300.0).abs() < 15.0);
+    }
+}